@@ -60,11 +60,24 @@ impl<TRead: Read + Unpin> MessageReader<TRead> {
 
 pub struct MessageWriter<TWrite: Write + Unpin> {
   writer: TWrite,
+  compression_enabled: bool,
 }
 
 impl<TWrite: Write + Unpin> MessageWriter<TWrite> {
   pub fn new(writer: TWrite) -> Self {
-    Self { writer }
+    Self {
+      writer,
+      compression_enabled: false,
+    }
+  }
+
+  /// Sets if the other side supports receiving compressed payloads.
+  pub fn set_compression_enabled(&mut self, value: bool) {
+    self.compression_enabled = value;
+  }
+
+  pub fn is_compression_enabled(&self) -> bool {
+    self.compression_enabled
   }
 
   pub fn send_u32(&mut self, value: u32) -> Result<()> {
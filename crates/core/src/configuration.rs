@@ -268,6 +268,33 @@ where
   }
 }
 
+/// Takes the names of the experiments enabled in the `experiments` property of the
+/// plugin configuration. The CLI verifies these are advertised by the plugin.
+///
+/// Adds a diagnostic if the value isn't an array of strings.
+pub fn get_experiments(config: &mut ConfigKeyMap, diagnostics: &mut Vec<ConfigurationDiagnostic>) -> Vec<String> {
+  let mut experiments = Vec::new();
+  match config.remove("experiments") {
+    Some(ConfigKeyValue::Array(values)) => {
+      for value in values {
+        match value {
+          ConfigKeyValue::String(value) => experiments.push(value),
+          _ => diagnostics.push(ConfigurationDiagnostic {
+            property_name: "experiments".to_string(),
+            message: "Expected an array of strings.".to_string(),
+          }),
+        }
+      }
+    }
+    Some(ConfigKeyValue::Null) | None => {}
+    Some(_) => diagnostics.push(ConfigurationDiagnostic {
+      property_name: "experiments".to_string(),
+      message: "Expected an array of strings.".to_string(),
+    }),
+  }
+  experiments
+}
+
 /// If it exists, moves over the configuration value over from the old key
 /// to the new key and adds a diagnostic.
 pub fn handle_renamed_config_property(config: &mut ConfigKeyMap, old_key: &str, new_key: &str, diagnostics: &mut Vec<ConfigurationDiagnostic>) {
@@ -427,4 +454,23 @@ mod test {
     assert_eq!(diagnostics[0].message, "The configuration key was renamed to 'newProp'.");
     assert_eq!(diagnostics[0].property_name, "oldProp");
   }
+
+  #[test]
+  fn get_experiments_from_config() {
+    let mut config = ConfigKeyMap::new();
+    let mut diagnostics = Vec::new();
+    config.insert(
+      "experiments".to_string(),
+      ConfigKeyValue::Array(vec![ConfigKeyValue::from_str("tailwind-sorting"), ConfigKeyValue::from_i32(1)]),
+    );
+    assert_eq!(get_experiments(&mut config, &mut diagnostics), vec!["tailwind-sorting".to_string()]);
+    assert_eq!(config.len(), 0);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Expected an array of strings.");
+    assert_eq!(diagnostics[0].property_name, "experiments");
+
+    let mut diagnostics = Vec::new();
+    assert_eq!(get_experiments(&mut config, &mut diagnostics), Vec::<String>::new());
+    assert_eq!(diagnostics.len(), 0);
+  }
 }
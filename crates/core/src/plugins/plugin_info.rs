@@ -28,4 +28,8 @@ pub struct PluginInfo {
   /// Generally in the format: https://plugins.dprint.dev/<org-or-user>/<repo>/latest.json
   /// For example: https://plugins.dprint.dev/dprint/dprint-plugin-typescript/latest.json
   pub update_url: Option<String>,
+  /// The names of the experimental features of the plugin, which may be
+  /// enabled with the `experiments` property in its configuration.
+  #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+  pub experiments: Vec<String>,
 }
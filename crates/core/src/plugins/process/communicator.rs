@@ -19,13 +19,22 @@ use std::time::Duration;
 use tokio::sync::oneshot;
 use tokio_util::sync::CancellationToken;
 
+#[cfg(windows)]
+use super::job_object::resume_process;
+#[cfg(windows)]
+use super::job_object::JobObject;
 use super::messages::FormatMessageBody;
 use super::messages::HostFormatMessageBody;
 use super::messages::MessageBody;
 use super::messages::ProcessPluginMessage;
 use super::messages::RegisterConfigMessageBody;
 use super::messages::ResponseBody;
+use super::sandbox::apply_sandbox;
+use super::sandbox::ProcessPluginSandbox;
+use super::PLUGIN_CAPABILITIES_ENV_VAR_NAME;
 use super::PLUGIN_SCHEMA_VERSION;
+use super::SUPPORTED_CAPABILITIES;
+use super::ZSTD_COMPRESSION_CAPABILITY;
 use crate::communication::ArcFlag;
 use crate::communication::ArcIdStore;
 use crate::communication::IdGenerator;
@@ -63,10 +72,29 @@ struct Context {
   host: Arc<dyn Host>,
 }
 
+/// Options for starting a process plugin.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProcessPluginOptions {
+  pub sandbox: Option<ProcessPluginSandbox>,
+  /// The maximum committed memory in bytes of the plugin and its child processes.
+  ///
+  /// This is only supported on Windows.
+  pub job_memory_limit: Option<u64>,
+  /// The environment variables to start the process with instead of
+  /// inheriting the environment of the current process.
+  ///
+  /// On Windows, `SystemRoot` is still inherited since processes may not start without it.
+  pub env_vars: Option<Vec<(String, String)>>,
+}
+
 /// Communicates with a process plugin.
 pub struct ProcessPluginCommunicator {
   child: Mutex<Option<Child>>,
   context: Context,
+  schema_version: u32,
+  // kills the process and any processes it spawned when dropped
+  #[cfg(windows)]
+  job_object: Option<JobObject>,
 }
 
 impl Drop for ProcessPluginCommunicator {
@@ -77,17 +105,28 @@ impl Drop for ProcessPluginCommunicator {
 
 impl ProcessPluginCommunicator {
   pub async fn new(executable_file_path: &Path, on_std_err: impl Fn(String) + Clone + Send + Sync + 'static, host: Arc<dyn Host>) -> Result<Self> {
-    ProcessPluginCommunicator::new_internal(executable_file_path, false, on_std_err, host).await
+    ProcessPluginCommunicator::new_internal(executable_file_path, false, None, on_std_err, host).await
   }
 
   /// Provides the `--init` CLI flag to tell the process plugin to do any initialization necessary
   pub async fn new_with_init(executable_file_path: &Path, on_std_err: impl Fn(String) + Clone + Send + Sync + 'static, host: Arc<dyn Host>) -> Result<Self> {
-    ProcessPluginCommunicator::new_internal(executable_file_path, true, on_std_err, host).await
+    ProcessPluginCommunicator::new_internal(executable_file_path, true, None, on_std_err, host).await
+  }
+
+  /// Starts the process plugin with the provided restrictions.
+  pub async fn new_with_options(
+    executable_file_path: &Path,
+    options: &ProcessPluginOptions,
+    on_std_err: impl Fn(String) + Clone + Send + Sync + 'static,
+    host: Arc<dyn Host>,
+  ) -> Result<Self> {
+    ProcessPluginCommunicator::new_internal(executable_file_path, false, Some(options), on_std_err, host).await
   }
 
   async fn new_internal(
     executable_file_path: &Path,
     is_init: bool,
+    options: Option<&ProcessPluginOptions>,
     on_std_err: impl Fn(String) + Clone + Send + Sync + 'static,
     host: Arc<dyn Host>,
   ) -> Result<Self> {
@@ -98,13 +137,46 @@ impl ProcessPluginCommunicator {
 
     let poisoner = Poisoner::default();
     let shutdown_flag = ArcFlag::default();
-    let mut child = Command::new(executable_file_path)
+    let mut command = Command::new(executable_file_path);
+    if let Some(env_vars) = options.and_then(|o| o.env_vars.as_ref()) {
+      command.env_clear();
+      #[cfg(windows)]
+      if let Some(system_root) = std::env::var_os("SystemRoot") {
+        command.env("SystemRoot", system_root);
+      }
+      command.envs(env_vars.iter().map(|(name, value)| (name, value)));
+    }
+    command
       .args(&args)
+      .env(PLUGIN_CAPABILITIES_ENV_VAR_NAME, SUPPORTED_CAPABILITIES.to_string())
       .stdin(Stdio::piped())
       .stderr(Stdio::piped())
-      .stdout(Stdio::piped())
+      .stdout(Stdio::piped());
+    let sandbox_guard = match options.and_then(|o| o.sandbox.as_ref()) {
+      Some(sandbox) => Some(apply_sandbox(&mut command, sandbox)?),
+      None => None,
+    };
+    // start suspended so the process can't spawn anything before it's in the job object
+    #[cfg(windows)]
+    {
+      use std::os::windows::process::CommandExt;
+      command.creation_flags(winapi::um::winbase::CREATE_SUSPENDED);
+    }
+    let mut child = command
       .spawn()
       .map_err(|err| anyhow!("Error starting {} with args [{}]. {:#}", executable_file_path.display(), args.join(" "), err))?;
+    drop(sandbox_guard);
+    #[cfg(windows)]
+    let job_object = match assign_job_object(&child, options.and_then(|o| o.job_memory_limit)).and_then(|job_object| {
+      resume_process(&child)?;
+      Ok(job_object)
+    }) {
+      Ok(job_object) => job_object,
+      Err(err) => {
+        let _ignore = child.kill();
+        return Err(err);
+      }
+    };
 
     // read and output stderr prefixed
     let stderr = child.stderr.take().unwrap();
@@ -121,8 +193,9 @@ impl ProcessPluginCommunicator {
     let mut stdout_reader = MessageReader::new(child.stdout.take().unwrap());
     let mut stdin_writer = MessageWriter::new(child.stdin.take().unwrap());
 
-    verify_plugin_schema_version(&mut stdout_reader, &mut stdin_writer)
+    let (schema_version, capabilities) = verify_plugin_schema_version(&mut stdout_reader, &mut stdin_writer)
       .context("Failed plugin schema verification. This may indicate you are using an old version of the dprint CLI or plugin and should upgrade.")?;
+    stdin_writer.set_compression_enabled(capabilities & ZSTD_COMPRESSION_CAPABILITY != 0);
 
     let stdin_writer = SingleThreadMessageWriter::for_stdin(stdin_writer, poisoner.clone());
     let context = Context {
@@ -154,9 +227,17 @@ impl ProcessPluginCommunicator {
     Ok(Self {
       child: Mutex::new(Some(child)),
       context,
+      schema_version,
+      #[cfg(windows)]
+      job_object,
     })
   }
 
+  /// Gets the schema version the plugin reported during the handshake.
+  pub fn schema_version(&self) -> u32 {
+    self.schema_version
+  }
+
   /// Perform a graceful shutdown.
   pub async fn shutdown(&self) {
     self.context.shutdown_flag.raise();
@@ -183,6 +264,27 @@ impl ProcessPluginCommunicator {
     }
   }
 
+  /// Gets the id of the process, which is unknown after it was killed.
+  pub fn process_id(&self) -> Option<u32> {
+    self.child.lock().as_ref().map(|child| child.id())
+  }
+
+  /// Gets the peak memory usage in bytes of the process plugin.
+  ///
+  /// On Linux this is the peak resident set size of the plugin process and on
+  /// Windows it's the peak committed memory of the plugin and its child processes.
+  /// This is not supported on other platforms.
+  pub fn peak_memory_bytes(&self) -> Option<u64> {
+    #[cfg(windows)]
+    {
+      self.job_object.as_ref().and_then(|job_object| job_object.peak_memory_used())
+    }
+    #[cfg(not(windows))]
+    {
+      self.child.lock().as_ref().and_then(|child| get_peak_rss_bytes(child.id()))
+    }
+  }
+
   pub async fn register_config(&self, config_id: u32, global_config: &GlobalConfiguration, plugin_config: &ConfigKeyMap) -> Result<()> {
     let global_config = serde_json::to_vec(global_config)?;
     let plugin_config = serde_json::to_vec(plugin_config)?;
@@ -320,15 +422,46 @@ impl ProcessPluginCommunicator {
   }
 }
 
+/// Places the suspended process in a job object so it and its child processes can't
+/// outlive the CLI. This is best effort unless a memory limit was requested.
+#[cfg(windows)]
+fn assign_job_object(child: &Child, memory_limit: Option<u64>) -> Result<Option<JobObject>> {
+  let result = JobObject::new(memory_limit).and_then(|job_object| {
+    job_object.assign(child)?;
+    Ok(job_object)
+  });
+  match result {
+    Ok(job_object) => Ok(Some(job_object)),
+    Err(err) if memory_limit.is_some() => Err(err),
+    Err(_) => Ok(None),
+  }
+}
+
+#[cfg(target_os = "linux")]
+fn get_peak_rss_bytes(pid: u32) -> Option<u64> {
+  let text = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+  // the high water mark of the resident set size in kilobytes (ex. "VmHWM:   10240 kB")
+  let line = text.lines().find(|line| line.starts_with("VmHWM:"))?;
+  let kilobytes = line["VmHWM:".len()..].trim().trim_end_matches("kB").trim().parse::<u64>().ok()?;
+  Some(kilobytes * 1024)
+}
+
+#[cfg(all(not(windows), not(target_os = "linux")))]
+fn get_peak_rss_bytes(_pid: u32) -> Option<u64> {
+  None
+}
+
+/// Verifies the schema version and returns it along with the capabilities supported by both the CLI and the plugin.
 fn verify_plugin_schema_version<TRead: Read + Unpin, TWrite: Write + Unpin>(
   reader: &mut MessageReader<TRead>,
   writer: &mut MessageWriter<TWrite>,
-) -> Result<()> {
+) -> Result<(u32, u32)> {
   // since this is the setup, use a lot of contexts to find exactly where it failed
   writer.send_u32(0).context("Failed asking for schema version.")?; // ask for schema version
   writer.flush().context("Failed flushing schema version request.")?;
   let acknowledgement_response = reader.read_u32().context("Could not read success response.")?;
-  if acknowledgement_response != 0 {
+  // `1` is a success response from plugins that also send their capabilities
+  if acknowledgement_response != 0 && acknowledgement_response != 1 {
     bail!("Plugin response was unexpected ({acknowledgement_response}).");
   }
   let plugin_schema_version = reader.read_u32().context("Could not read schema version.")?;
@@ -339,8 +472,12 @@ fn verify_plugin_schema_version<TRead: Read + Unpin, TWrite: Write + Unpin>(
       PLUGIN_SCHEMA_VERSION
     );
   }
-
-  Ok(())
+  if acknowledgement_response == 1 {
+    let plugin_capabilities = reader.read_u32().context("Could not read plugin capabilities.")?;
+    Ok((plugin_schema_version, plugin_capabilities & SUPPORTED_CAPABILITIES))
+  } else {
+    Ok((plugin_schema_version, 0))
+  }
 }
 
 fn std_err_redirect(poisoner: Poisoner, shutdown_flag: ArcFlag, stderr: ChildStderr, on_std_err: impl Fn(String) + Send + Sync + 'static) {
@@ -501,3 +638,14 @@ async fn host_format(context: Context, message_id: u32, body: HostFormatMessageB
 
   result
 }
+
+#[cfg(test)]
+mod test {
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn should_get_peak_rss_bytes() {
+    let bytes = super::get_peak_rss_bytes(std::process::id()).unwrap();
+    assert!(bytes > 0);
+    assert_eq!(bytes % 1024, 0);
+  }
+}
@@ -0,0 +1,179 @@
+use anyhow::bail;
+use anyhow::Result;
+use std::os::windows::io::AsRawHandle;
+use std::process::Child;
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::minwindef::FALSE;
+use winapi::shared::minwindef::LPVOID;
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+use winapi::um::jobapi2::AssignProcessToJobObject;
+use winapi::um::jobapi2::CreateJobObjectW;
+use winapi::um::jobapi2::QueryInformationJobObject;
+use winapi::um::jobapi2::SetInformationJobObject;
+use winapi::um::processthreadsapi::OpenThread;
+use winapi::um::processthreadsapi::ResumeThread;
+use winapi::um::tlhelp32::CreateToolhelp32Snapshot;
+use winapi::um::tlhelp32::Thread32First;
+use winapi::um::tlhelp32::Thread32Next;
+use winapi::um::tlhelp32::TH32CS_SNAPTHREAD;
+use winapi::um::tlhelp32::THREADENTRY32;
+use winapi::um::winnt::JobObjectExtendedLimitInformation;
+use winapi::um::winnt::HANDLE;
+use winapi::um::winnt::JOBOBJECT_EXTENDED_LIMIT_INFORMATION;
+use winapi::um::winnt::JOB_OBJECT_LIMIT_DIE_ON_UNHANDLED_EXCEPTION;
+use winapi::um::winnt::JOB_OBJECT_LIMIT_JOB_MEMORY;
+use winapi::um::winnt::JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+use winapi::um::winnt::THREAD_SUSPEND_RESUME;
+
+/// A Windows Job Object that contains a process plugin and any processes it spawns.
+///
+/// When the job is closed (ex. the CLI exits or crashes), every process in the job is
+/// killed. Processes in the job can't break away from it because the breakaway limits
+/// are not set.
+pub(super) struct JobObject(HANDLE);
+
+// the handle is only used to assign processes and is closed on drop
+unsafe impl Send for JobObject {}
+unsafe impl Sync for JobObject {}
+
+impl Drop for JobObject {
+  fn drop(&mut self) {
+    unsafe {
+      CloseHandle(self.0);
+    }
+  }
+}
+
+impl JobObject {
+  pub fn new(memory_limit: Option<u64>) -> Result<JobObject> {
+    unsafe {
+      let handle = CreateJobObjectW(std::ptr::null_mut(), std::ptr::null());
+      if handle.is_null() {
+        bail!("Error creating job object. {}", std::io::Error::last_os_error());
+      }
+      let job = JobObject(handle);
+
+      let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+      info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE | JOB_OBJECT_LIMIT_DIE_ON_UNHANDLED_EXCEPTION;
+      if let Some(memory_limit) = memory_limit {
+        info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+        info.JobMemoryLimit = memory_limit as usize;
+      }
+      let result = SetInformationJobObject(
+        job.0,
+        JobObjectExtendedLimitInformation,
+        &mut info as *mut JOBOBJECT_EXTENDED_LIMIT_INFORMATION as LPVOID,
+        std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as DWORD,
+      );
+      if result == FALSE {
+        bail!("Error setting job object limits. {}", std::io::Error::last_os_error());
+      }
+      Ok(job)
+    }
+  }
+
+  /// Assigns the process to the job. Processes it spawns afterwards are also in the job.
+  pub fn assign(&self, child: &Child) -> Result<()> {
+    unsafe {
+      if AssignProcessToJobObject(self.0, child.as_raw_handle() as HANDLE) == FALSE {
+        bail!("Error assigning process to job object. {}", std::io::Error::last_os_error());
+      }
+    }
+    Ok(())
+  }
+
+  /// Gets the peak committed memory in bytes used by all the processes in the job.
+  pub fn peak_memory_used(&self) -> Option<u64> {
+    unsafe {
+      let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+      let result = QueryInformationJobObject(
+        self.0,
+        JobObjectExtendedLimitInformation,
+        &mut info as *mut JOBOBJECT_EXTENDED_LIMIT_INFORMATION as LPVOID,
+        std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as DWORD,
+        std::ptr::null_mut(),
+      );
+      if result == FALSE {
+        None
+      } else {
+        Some(info.PeakJobMemoryUsed as u64)
+      }
+    }
+  }
+}
+
+/// Resumes the threads of a process that was spawned with `CREATE_SUSPENDED`.
+///
+/// The standard library doesn't expose the handle of the main thread, so this
+/// finds the threads of the process in a snapshot of the system's threads.
+pub(super) fn resume_process(child: &Child) -> Result<()> {
+  unsafe {
+    let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+    if snapshot == INVALID_HANDLE_VALUE {
+      bail!("Error creating thread snapshot. {}", std::io::Error::last_os_error());
+    }
+    let result = resume_process_threads(snapshot, child.id());
+    CloseHandle(snapshot);
+    result
+  }
+}
+
+unsafe fn resume_process_threads(snapshot: HANDLE, process_id: DWORD) -> Result<()> {
+  let mut entry: THREADENTRY32 = std::mem::zeroed();
+  entry.dwSize = std::mem::size_of::<THREADENTRY32>() as DWORD;
+  let mut resumed_count = 0;
+  let mut has_entry = Thread32First(snapshot, &mut entry) != FALSE;
+  while has_entry {
+    if entry.th32OwnerProcessID == process_id {
+      let thread = OpenThread(THREAD_SUSPEND_RESUME, FALSE, entry.th32ThreadID);
+      if thread.is_null() {
+        bail!("Error opening thread of process. {}", std::io::Error::last_os_error());
+      }
+      let result = ResumeThread(thread);
+      CloseHandle(thread);
+      if result == u32::MAX {
+        bail!("Error resuming thread of process. {}", std::io::Error::last_os_error());
+      }
+      resumed_count += 1;
+    }
+    has_entry = Thread32Next(snapshot, &mut entry) != FALSE;
+  }
+  if resumed_count == 0 {
+    bail!("Error resuming process. Could not find its threads.");
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use std::process::Command;
+
+  use super::*;
+
+  #[test]
+  fn should_kill_process_when_job_closed() {
+    let mut child = Command::new("cmd").args(["/C", "ping -n 30 127.0.0.1 > NUL"]).spawn().unwrap();
+    let job = JobObject::new(Some(512 * 1024 * 1024)).unwrap();
+    job.assign(&child).unwrap();
+    assert!(child.try_wait().unwrap().is_none());
+    assert!(job.peak_memory_used().unwrap() > 0);
+    drop(job);
+    // the process should be killed by the job closing
+    let status = child.wait().unwrap();
+    assert!(!status.success());
+  }
+
+  #[test]
+  fn should_resume_suspended_process() {
+    use std::os::windows::process::CommandExt;
+    use winapi::um::winbase::CREATE_SUSPENDED;
+
+    let mut child = Command::new("cmd").args(["/C", "exit 0"]).creation_flags(CREATE_SUSPENDED).spawn().unwrap();
+    let job = JobObject::new(None).unwrap();
+    job.assign(&child).unwrap();
+    assert!(child.try_wait().unwrap().is_none());
+    resume_process(&child).unwrap();
+    assert!(child.wait().unwrap().success());
+  }
+}
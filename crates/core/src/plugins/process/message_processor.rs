@@ -17,7 +17,10 @@ use super::messages::MessageBody;
 use super::messages::ProcessPluginMessage;
 use super::messages::ResponseBody;
 use super::utils::setup_exit_process_panic_hook;
+use super::PLUGIN_CAPABILITIES_ENV_VAR_NAME;
 use super::PLUGIN_SCHEMA_VERSION;
+use super::SUPPORTED_CAPABILITIES;
+use super::ZSTD_COMPRESSION_CAPABILITY;
 use crate::communication::MessageReader;
 use crate::communication::MessageWriter;
 use crate::communication::SingleThreadMessageWriter;
@@ -38,7 +41,8 @@ pub async fn handle_process_stdio_messages<THandler: AsyncPluginHandler>(handler
     let mut stdin_reader = MessageReader::new(std::io::stdin());
     let mut stdout_writer = MessageWriter::new(std::io::stdout());
 
-    schema_establishment_phase(&mut stdin_reader, &mut stdout_writer)?;
+    let capabilities = schema_establishment_phase(&mut stdin_reader, &mut stdout_writer)?;
+    stdout_writer.set_compression_enabled(capabilities & ZSTD_COMPRESSION_CAPABILITY != 0);
 
     let handler = Arc::new(handler);
     let stdout_message_writer = SingleThreadMessageWriter::for_stdout(stdout_writer);
@@ -295,16 +299,33 @@ fn send_response_body<TConfiguration: Serialize + Clone + Send + Sync>(context:
 }
 
 /// For backwards compatibility asking for the schema version.
-fn schema_establishment_phase<TRead: Read + Unpin, TWrite: Write + Unpin>(stdin: &mut MessageReader<TRead>, stdout: &mut MessageWriter<TWrite>) -> Result<()> {
+///
+/// Returns the capabilities supported by both the CLI and the plugin.
+fn schema_establishment_phase<TRead: Read + Unpin, TWrite: Write + Unpin>(stdin: &mut MessageReader<TRead>, stdout: &mut MessageWriter<TWrite>) -> Result<u32> {
   // 1. An initial `0` (4 bytes) is sent asking for the schema version.
   if stdin.read_u32()? != 0 {
     bail!("Expected a schema version request of `0`.");
   }
 
   // 2. The client responds with `0` (4 bytes) for success, then `4` (4 bytes) for the schema version.
-  stdout.send_u32(0)?;
-  stdout.send_u32(PLUGIN_SCHEMA_VERSION)?;
+  //    When the CLI provided its capabilities, the client instead responds with `1` (4 bytes) for
+  //    success, then `4` (4 bytes) for the schema version, then the capabilities it also supports.
+  let cli_capabilities = std::env::var(PLUGIN_CAPABILITIES_ENV_VAR_NAME).ok().and_then(|value| value.parse::<u32>().ok());
+  let capabilities = match cli_capabilities {
+    Some(cli_capabilities) => {
+      let capabilities = cli_capabilities & SUPPORTED_CAPABILITIES;
+      stdout.send_u32(1)?;
+      stdout.send_u32(PLUGIN_SCHEMA_VERSION)?;
+      stdout.send_u32(capabilities)?;
+      capabilities
+    }
+    None => {
+      stdout.send_u32(0)?;
+      stdout.send_u32(PLUGIN_SCHEMA_VERSION)?;
+      0
+    }
+  };
   stdout.flush()?;
 
-  Ok(())
+  Ok(capabilities)
 }
@@ -11,6 +11,12 @@ use crate::plugins::FormatRange;
 use crate::communication::MessageReader;
 use crate::communication::MessageWriter;
 
+/// Format text payloads of at least this size are compressed when the other side supports it.
+#[cfg(feature = "process-compression")]
+const COMPRESSION_THRESHOLD_BYTES: usize = 32 * 1024;
+/// Payloads are never decompressed past this size. Larger payloads are sent uncompressed.
+const MAX_DECOMPRESSED_PAYLOAD_BYTES: usize = 256 * 1024 * 1024;
+
 #[derive(Debug)]
 pub struct ProcessPluginMessage {
   pub id: u32,
@@ -50,13 +56,14 @@ impl ProcessPluginMessage {
       8 => MessageBody::ReleaseConfig(reader.read_u32()?),
       9 => MessageBody::GetConfigDiagnostics(reader.read_u32()?),
       10 => MessageBody::GetResolvedConfig(reader.read_u32()?),
-      11 => {
+      // 15 is the same as 11, but with a compressed file text
+      11 | 15 => {
         let file_path = reader.read_sized_bytes()?;
         let start_byte_index = reader.read_u32()?;
         let end_byte_index = reader.read_u32()?;
         let config_id = reader.read_u32()?;
         let override_config = reader.read_sized_bytes()?;
-        let file_text = reader.read_sized_bytes()?;
+        let file_text = read_payload(reader, message_kind == 15)?;
         MessageBody::Format(FormatMessageBody {
           file_path: PathBuf::from(String::from_utf8_lossy(&file_path).to_string()),
           range: if start_byte_index == 0 && end_byte_index == file_text.len() as u32 {
@@ -72,12 +79,13 @@ impl ProcessPluginMessage {
           override_config,
         })
       }
-      12 => {
+      // 16 is the same as 12, but with a compressed formatted text
+      12 | 16 => {
         let message_id = reader.read_u32()?;
         let response_kind = reader.read_u32()?;
         let data = match response_kind {
           0 => None,
-          1 => Some(reader.read_sized_bytes()?),
+          1 => Some(read_payload(reader, message_kind == 16)?),
           _ => bail!("Unknown format response kind: {}", response_kind),
         };
         MessageBody::FormatResponse(ResponseBody { message_id, data })
@@ -167,16 +175,21 @@ impl Message for ProcessPluginMessage {
         writer.send_u32(*config_id)?;
       }
       MessageBody::Format(body) => {
-        writer.send_u32(11)?;
+        let compressed_file_text = get_compressed_payload(writer, &body.file_text)?;
+        writer.send_u32(if compressed_file_text.is_some() { 15 } else { 11 })?;
         writer.send_sized_bytes(body.file_path.to_string_lossy().as_bytes())?;
         writer.send_u32(body.range.as_ref().map(|r| r.start).unwrap_or(0) as u32)?;
         writer.send_u32(body.range.as_ref().map(|r| r.end).unwrap_or(body.file_text.len()) as u32)?;
         writer.send_u32(body.config_id)?;
         writer.send_sized_bytes(&body.override_config)?;
-        writer.send_sized_bytes(&body.file_text)?;
+        writer.send_sized_bytes(compressed_file_text.as_deref().unwrap_or(&body.file_text))?;
       }
       MessageBody::FormatResponse(response) => {
-        writer.send_u32(12)?;
+        let compressed_data = match &response.data {
+          Some(data) => get_compressed_payload(writer, data)?,
+          None => None,
+        };
+        writer.send_u32(if compressed_data.is_some() { 16 } else { 12 })?;
         writer.send_u32(response.message_id)?;
         match &response.data {
           None => {
@@ -184,7 +197,7 @@ impl Message for ProcessPluginMessage {
           }
           Some(data) => {
             writer.send_u32(1)?;
-            writer.send_sized_bytes(data)?;
+            writer.send_sized_bytes(compressed_data.as_deref().unwrap_or(data))?;
           }
         }
       }
@@ -207,6 +220,47 @@ impl Message for ProcessPluginMessage {
   }
 }
 
+fn read_payload<TRead: Read + Unpin>(reader: &mut MessageReader<TRead>, is_compressed: bool) -> Result<Vec<u8>> {
+  let data = reader.read_sized_bytes()?;
+  if is_compressed {
+    decompress_payload(&data, MAX_DECOMPRESSED_PAYLOAD_BYTES)
+  } else {
+    Ok(data)
+  }
+}
+
+#[cfg(feature = "process-compression")]
+fn decompress_payload(data: &[u8], max_size: usize) -> Result<Vec<u8>> {
+  let decoder = zstd::stream::read::Decoder::with_buffer(data)?;
+  let mut decompressed_data = Vec::new();
+  // read one byte past the maximum in order to detect when it's exceeded
+  decoder.take(max_size as u64 + 1).read_to_end(&mut decompressed_data)?;
+  if decompressed_data.len() > max_size {
+    bail!("The decompressed payload exceeded the maximum size of {} bytes.", max_size);
+  }
+  Ok(decompressed_data)
+}
+
+#[cfg(not(feature = "process-compression"))]
+fn decompress_payload(_data: &[u8], _max_size: usize) -> Result<Vec<u8>> {
+  bail!("Received a compressed payload, but the process-compression feature is not enabled.");
+}
+
+/// Gets the compressed data when the other side supports it and compressing is worth it.
+#[cfg(feature = "process-compression")]
+fn get_compressed_payload<TWrite: Write + Unpin>(writer: &MessageWriter<TWrite>, data: &[u8]) -> Result<Option<Vec<u8>>> {
+  if !writer.is_compression_enabled() || data.len() < COMPRESSION_THRESHOLD_BYTES || data.len() > MAX_DECOMPRESSED_PAYLOAD_BYTES {
+    return Ok(None);
+  }
+  let compressed_data = zstd::stream::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+  Ok(if compressed_data.len() < data.len() { Some(compressed_data) } else { None })
+}
+
+#[cfg(not(feature = "process-compression"))]
+fn get_compressed_payload<TWrite: Write + Unpin>(_writer: &MessageWriter<TWrite>, _data: &[u8]) -> Result<Option<Vec<u8>>> {
+  Ok(None)
+}
+
 #[derive(Debug)]
 pub enum MessageBody {
   Success(u32),
@@ -258,3 +312,89 @@ pub struct HostFormatMessageBody {
   pub override_config: Vec<u8>,
   pub file_text: Vec<u8>,
 }
+
+#[cfg(all(test, feature = "process-compression"))]
+mod test {
+  use super::*;
+
+  #[test]
+  fn should_compress_large_format_payloads_when_enabled() {
+    let file_text = "const t = 5;\n".repeat(10_000).into_bytes();
+    let message = ProcessPluginMessage {
+      id: 1,
+      body: MessageBody::Format(FormatMessageBody {
+        file_path: PathBuf::from("/file.ts"),
+        range: None,
+        config_id: 2,
+        override_config: Vec::new(),
+        file_text: file_text.clone(),
+      }),
+    };
+    let uncompressed_bytes = write_message(&message, false);
+    let compressed_bytes = write_message(&message, true);
+    assert_eq!(u32::from_be_bytes(uncompressed_bytes[4..8].try_into().unwrap()), 11);
+    assert_eq!(u32::from_be_bytes(compressed_bytes[4..8].try_into().unwrap()), 15);
+    assert!(compressed_bytes.len() < uncompressed_bytes.len());
+
+    for bytes in [uncompressed_bytes, compressed_bytes] {
+      let message = ProcessPluginMessage::read(&mut MessageReader::new(bytes.as_slice())).unwrap();
+      match message.body {
+        MessageBody::Format(body) => {
+          assert_eq!(body.range, None);
+          assert_eq!(body.config_id, 2);
+          assert_eq!(body.file_text, file_text);
+        }
+        _ => unreachable!(),
+      }
+    }
+  }
+
+  #[test]
+  fn should_compress_large_format_responses_when_enabled() {
+    let data = "a".repeat(COMPRESSION_THRESHOLD_BYTES).into_bytes();
+    let message = ProcessPluginMessage {
+      id: 1,
+      body: MessageBody::FormatResponse(ResponseBody {
+        message_id: 2,
+        data: Some(data.clone()),
+      }),
+    };
+    let bytes = write_message(&message, true);
+    assert_eq!(u32::from_be_bytes(bytes[4..8].try_into().unwrap()), 16);
+    let message = ProcessPluginMessage::read(&mut MessageReader::new(bytes.as_slice())).unwrap();
+    match message.body {
+      MessageBody::FormatResponse(response) => {
+        assert_eq!(response.message_id, 2);
+        assert_eq!(response.data, Some(data));
+      }
+      _ => unreachable!(),
+    }
+
+    // small payloads aren't compressed
+    let message = ProcessPluginMessage {
+      id: 1,
+      body: MessageBody::FormatResponse(ResponseBody {
+        message_id: 2,
+        data: Some(b"text".to_vec()),
+      }),
+    };
+    let bytes = write_message(&message, true);
+    assert_eq!(u32::from_be_bytes(bytes[4..8].try_into().unwrap()), 12);
+  }
+
+  #[test]
+  fn should_error_when_decompressed_payload_exceeds_max_size() {
+    let data = zstd::stream::encode_all("a".repeat(1025).as_bytes(), zstd::DEFAULT_COMPRESSION_LEVEL).unwrap();
+    assert_eq!(decompress_payload(&data, 1025).unwrap().len(), 1025);
+    let err = decompress_payload(&data, 1024).err().unwrap();
+    assert_eq!(err.to_string(), "The decompressed payload exceeded the maximum size of 1024 bytes.");
+  }
+
+  fn write_message(message: &ProcessPluginMessage, compression_enabled: bool) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut writer = MessageWriter::new(&mut bytes);
+    writer.set_compression_enabled(compression_enabled);
+    message.write(&mut writer).unwrap();
+    bytes
+  }
+}
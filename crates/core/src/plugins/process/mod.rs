@@ -1,13 +1,18 @@
 mod communicator;
 mod context;
+#[cfg(windows)]
+mod job_object;
 mod message_processor;
 mod messages;
 mod parent_process_checker;
+mod sandbox;
 mod shared_types;
 mod utils;
 
 pub use communicator::*;
 pub use message_processor::*;
 pub use parent_process_checker::*;
+pub use sandbox::ProcessPluginSandbox;
+pub use shared_types::PLUGIN_SCHEMA_VERSION;
 use shared_types::*;
 pub use utils::setup_exit_process_panic_hook;
@@ -32,18 +32,26 @@ pub fn get_parent_process_id_from_cli_args() -> Option<u32> {
   None
 }
 
+/// Forcefully kills the process, returning if it was signaled.
+#[cfg(unix)]
+pub fn kill_process(process_id: u32) -> bool {
+  unsafe { libc::kill(process_id as i32, libc::SIGKILL) == 0 }
+}
+
 // code below is from my implementation when adding this to Deno
 
+/// Gets if a process with the id exists.
 #[cfg(unix)]
-fn is_process_active(process_id: u32) -> bool {
+pub fn is_process_active(process_id: u32) -> bool {
   unsafe {
     // signal of 0 checks for the existence of the process id
     libc::kill(process_id as i32, 0) == 0
   }
 }
 
+/// Gets if a process with the id exists.
 #[cfg(windows)]
-fn is_process_active(process_id: u32) -> bool {
+pub fn is_process_active(process_id: u32) -> bool {
   use winapi::shared::minwindef::DWORD;
   use winapi::shared::minwindef::FALSE;
   use winapi::shared::ntdef::NULL;
@@ -0,0 +1,355 @@
+use anyhow::Result;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Restrictions applied to a process plugin when it's started.
+///
+/// This is only supported on Linux, where the filesystem is restricted with
+/// Landlock and network access is blocked with a seccomp filter.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProcessPluginSandbox {
+  /// Paths the process may read from and write to.
+  pub read_write_paths: Vec<PathBuf>,
+  /// Paths the process may read from and execute files in.
+  pub read_paths: Vec<PathBuf>,
+  /// Blocks creating sockets other than unix domain sockets, along with
+  /// io_uring and the x32 system calls that could get around it.
+  pub block_network: bool,
+}
+
+/// Keeps the resources needed to sandbox the process alive until it's spawned.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(super) struct SandboxGuard {
+  #[cfg(target_os = "linux")]
+  _path_fds: Vec<std::os::fd::OwnedFd>,
+}
+
+#[cfg(target_os = "linux")]
+pub(super) fn apply_sandbox(command: &mut Command, sandbox: &ProcessPluginSandbox) -> Result<SandboxGuard> {
+  linux::apply_sandbox(command, sandbox)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(super) fn apply_sandbox(_command: &mut Command, _sandbox: &ProcessPluginSandbox) -> Result<SandboxGuard> {
+  anyhow::bail!("Sandboxing process plugins is only supported on Linux.")
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+  use anyhow::Result;
+  use std::ffi::CString;
+  use std::os::fd::AsRawFd;
+  use std::os::fd::FromRawFd;
+  use std::os::fd::OwnedFd;
+  use std::os::unix::ffi::OsStrExt;
+  use std::os::unix::process::CommandExt;
+  use std::path::Path;
+  use std::process::Command;
+
+  use super::ProcessPluginSandbox;
+  use super::SandboxGuard;
+
+  // these are the same on every architecture
+  const SYS_LANDLOCK_CREATE_RULESET: libc::c_long = 444;
+  const SYS_LANDLOCK_ADD_RULE: libc::c_long = 445;
+  const SYS_LANDLOCK_RESTRICT_SELF: libc::c_long = 446;
+  const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+
+  // access rights of the first version of the Landlock ABI
+  const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+  const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+  const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+  const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+  const ACCESS_FS_ALL: u64 = (1 << 13) - 1;
+  const ACCESS_FS_FILE: u64 = ACCESS_FS_EXECUTE | ACCESS_FS_WRITE_FILE | ACCESS_FS_READ_FILE;
+  const ACCESS_FS_READ: u64 = ACCESS_FS_EXECUTE | ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR;
+
+  #[repr(C)]
+  struct LandlockRulesetAttr {
+    handled_access_fs: u64,
+  }
+
+  #[repr(C, packed)]
+  struct LandlockPathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+  }
+
+  #[cfg(target_arch = "x86_64")]
+  const AUDIT_ARCH: u32 = 0xc000_003e;
+  #[cfg(target_arch = "aarch64")]
+  const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+  const BPF_LD_W_ABS: u16 = 0x20; // BPF_LD | BPF_W | BPF_ABS
+  const BPF_JMP_JEQ_K: u16 = 0x15; // BPF_JMP | BPF_JEQ | BPF_K
+  const BPF_JMP_JGE_K: u16 = 0x35; // BPF_JMP | BPF_JGE | BPF_K
+  const BPF_RET_K: u16 = 0x06; // BPF_RET | BPF_K
+  const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+  const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+  const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+  // offsets in struct seccomp_data
+  const SECCOMP_DATA_NR_OFFSET: u32 = 0;
+  const SECCOMP_DATA_ARCH_OFFSET: u32 = 4;
+  const SECCOMP_DATA_ARG0_OFFSET: u32 = 16;
+  /// Set in the system call numbers of the x32 ABI, which has the same audit architecture as x86_64.
+  #[cfg(target_arch = "x86_64")]
+  const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+  pub(super) fn apply_sandbox(command: &mut Command, sandbox: &ProcessPluginSandbox) -> Result<SandboxGuard> {
+    // everything is prepared before forking because only
+    // async-signal-safe functions may be called in the child
+    let mut path_fds = Vec::new();
+    let mut rules = Vec::new();
+    let paths = sandbox
+      .read_write_paths
+      .iter()
+      .map(|path| (path, ACCESS_FS_ALL))
+      .chain(sandbox.read_paths.iter().map(|path| (path, ACCESS_FS_READ)));
+    for (path, access) in paths {
+      let (fd, is_dir) = match open_path(path) {
+        Some(value) => value,
+        None => continue, // the process can't access paths that don't exist
+      };
+      rules.push(LandlockPathBeneathAttr {
+        allowed_access: if is_dir { access } else { access & ACCESS_FS_FILE },
+        parent_fd: fd.as_raw_fd(),
+      });
+      path_fds.push(fd);
+    }
+    let restrict_fs = !sandbox.read_write_paths.is_empty() || !sandbox.read_paths.is_empty();
+    let filter = if sandbox.block_network { Some(get_network_filter()?) } else { None };
+
+    unsafe {
+      command.pre_exec(move || {
+        if restrict_fs {
+          restrict_filesystem(&rules)?;
+        }
+        if let Some(filter) = &filter {
+          block_network(filter)?;
+        }
+        Ok(())
+      });
+    }
+
+    Ok(SandboxGuard { _path_fds: path_fds })
+  }
+
+  fn open_path(path: &Path) -> Option<(OwnedFd, bool)> {
+    let is_dir = path.metadata().ok()?.is_dir();
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let fd = unsafe { libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+    if fd < 0 {
+      None
+    } else {
+      Some((unsafe { OwnedFd::from_raw_fd(fd) }, is_dir))
+    }
+  }
+
+  fn restrict_filesystem(rules: &[LandlockPathBeneathAttr]) -> std::io::Result<()> {
+    let attr = LandlockRulesetAttr {
+      handled_access_fs: ACCESS_FS_ALL,
+    };
+    unsafe {
+      let ruleset_fd = libc::syscall(
+        SYS_LANDLOCK_CREATE_RULESET,
+        &attr as *const LandlockRulesetAttr,
+        std::mem::size_of::<LandlockRulesetAttr>(),
+        0,
+      );
+      if ruleset_fd < 0 {
+        return Err(std::io::Error::last_os_error());
+      }
+      let ruleset_fd = ruleset_fd as libc::c_int;
+      let result = add_rules_and_restrict_self(ruleset_fd, rules);
+      libc::close(ruleset_fd);
+      result
+    }
+  }
+
+  unsafe fn add_rules_and_restrict_self(ruleset_fd: libc::c_int, rules: &[LandlockPathBeneathAttr]) -> std::io::Result<()> {
+    for rule in rules {
+      if libc::syscall(
+        SYS_LANDLOCK_ADD_RULE,
+        ruleset_fd,
+        LANDLOCK_RULE_PATH_BENEATH,
+        rule as *const LandlockPathBeneathAttr,
+        0,
+      ) != 0
+      {
+        return Err(std::io::Error::last_os_error());
+      }
+    }
+    set_no_new_privs()?;
+    if libc::syscall(SYS_LANDLOCK_RESTRICT_SELF, ruleset_fd, 0) != 0 {
+      return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+  }
+
+  fn block_network(filter: &[libc::sock_filter]) -> std::io::Result<()> {
+    let program = libc::sock_fprog {
+      len: filter.len() as libc::c_ushort,
+      filter: filter.as_ptr() as *mut libc::sock_filter,
+    };
+    unsafe {
+      set_no_new_privs()?;
+      if libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &program as *const libc::sock_fprog) != 0 {
+        return Err(std::io::Error::last_os_error());
+      }
+    }
+    Ok(())
+  }
+
+  unsafe fn set_no_new_privs() -> std::io::Result<()> {
+    // required in order to restrict an unprivileged process
+    if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+      Err(std::io::Error::last_os_error())
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Where a jump of the seccomp filter continues.
+  #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+  #[derive(Clone, Copy)]
+  enum FilterTarget {
+    Next,
+    Deny,
+    Allow,
+    Kill,
+  }
+
+  /// Gets a seccomp filter that only allows creating unix domain sockets.
+  #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+  fn get_network_filter() -> Result<Vec<libc::sock_filter>> {
+    use FilterTarget::*;
+
+    // (code, k, jump if true, jump if false) where loads don't jump
+    let mut checks = vec![
+      // kill the process when the architecture is unexpected
+      (BPF_LD_W_ABS, SECCOMP_DATA_ARCH_OFFSET, Next, Next),
+      (BPF_JMP_JEQ_K, AUDIT_ARCH, Next, Kill),
+      (BPF_LD_W_ABS, SECCOMP_DATA_NR_OFFSET, Next, Next),
+    ];
+    // the x32 system calls would otherwise get around the checks of the system call numbers below
+    #[cfg(target_arch = "x86_64")]
+    checks.push((BPF_JMP_JGE_K, X32_SYSCALL_BIT, Deny, Next));
+    // io_uring can create and connect sockets without the socket system call
+    for nr in [libc::SYS_io_uring_setup, libc::SYS_io_uring_enter, libc::SYS_io_uring_register] {
+      checks.push((BPF_JMP_JEQ_K, nr as u32, Deny, Next));
+    }
+    checks.extend([
+      // allow every other system call than socket
+      (BPF_JMP_JEQ_K, libc::SYS_socket as u32, Next, Allow),
+      // only allow unix domain sockets
+      (BPF_LD_W_ABS, SECCOMP_DATA_ARG0_OFFSET, Next, Next),
+      (BPF_JMP_JEQ_K, libc::AF_UNIX as u32, Allow, Deny),
+    ]);
+
+    // the filter ends with the instruction of each return value
+    let deny_index = checks.len();
+    let get_offset = |index: usize, target: FilterTarget| -> Result<u8> {
+      let target_index = match target {
+        Next => index + 1,
+        Deny => deny_index,
+        Allow => deny_index + 1,
+        Kill => deny_index + 2,
+      };
+      Ok(u8::try_from(target_index - index - 1)?)
+    };
+    let mut filter = Vec::with_capacity(checks.len() + 3);
+    for (index, (code, k, jt, jf)) in checks.into_iter().enumerate() {
+      filter.push(libc::sock_filter {
+        code,
+        jt: get_offset(index, jt)?,
+        jf: get_offset(index, jf)?,
+        k,
+      });
+    }
+    let stmt = |k: u32| libc::sock_filter {
+      code: BPF_RET_K,
+      jt: 0,
+      jf: 0,
+      k,
+    };
+    filter.push(stmt(SECCOMP_RET_ERRNO | libc::EACCES as u32));
+    filter.push(stmt(SECCOMP_RET_ALLOW));
+    filter.push(stmt(SECCOMP_RET_KILL_PROCESS));
+    Ok(filter)
+  }
+
+  #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+  fn get_network_filter() -> Result<Vec<libc::sock_filter>> {
+    anyhow::bail!("Blocking the network access of process plugins is not supported on this architecture.")
+  }
+
+  #[cfg(test)]
+  mod test {
+    use super::*;
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    #[test]
+    fn should_jump_within_network_filter() {
+      let filter = get_network_filter().unwrap();
+      for (index, instruction) in filter.iter().enumerate() {
+        if instruction.code == BPF_JMP_JEQ_K || instruction.code == BPF_JMP_JGE_K {
+          assert!(index + 1 + (instruction.jt as usize) < filter.len());
+          assert!(index + 1 + (instruction.jf as usize) < filter.len());
+        }
+      }
+      assert_eq!(filter.last().unwrap().k, SECCOMP_RET_KILL_PROCESS);
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    #[test]
+    fn should_only_allow_unix_domain_sockets_in_network_filter() {
+      let filter = get_network_filter().unwrap();
+      let denied = SECCOMP_RET_ERRNO | libc::EACCES as u32;
+      let socket = libc::SYS_socket as u32;
+      assert_eq!(run_filter(&filter, AUDIT_ARCH, socket, libc::AF_UNIX as u32), SECCOMP_RET_ALLOW);
+      assert_eq!(run_filter(&filter, AUDIT_ARCH, socket, libc::AF_INET as u32), denied);
+      assert_eq!(run_filter(&filter, AUDIT_ARCH, socket, libc::AF_INET6 as u32), denied);
+      assert_eq!(run_filter(&filter, AUDIT_ARCH, libc::SYS_read as u32, 0), SECCOMP_RET_ALLOW);
+      assert_eq!(run_filter(&filter, AUDIT_ARCH, libc::SYS_connect as u32, 0), SECCOMP_RET_ALLOW);
+      for nr in [libc::SYS_io_uring_setup, libc::SYS_io_uring_enter, libc::SYS_io_uring_register] {
+        assert_eq!(run_filter(&filter, AUDIT_ARCH, nr as u32, 0), denied);
+      }
+      #[cfg(target_arch = "x86_64")]
+      {
+        assert_eq!(run_filter(&filter, AUDIT_ARCH, X32_SYSCALL_BIT | socket, libc::AF_INET as u32), denied);
+        assert_eq!(run_filter(&filter, AUDIT_ARCH, X32_SYSCALL_BIT | libc::SYS_read as u32, 0), denied);
+      }
+      assert_eq!(run_filter(&filter, 0x4000_0003, socket, libc::AF_INET as u32), SECCOMP_RET_KILL_PROCESS);
+    }
+
+    /// Runs the classic BPF instructions used by the filter on a system call.
+    fn run_filter(filter: &[libc::sock_filter], arch: u32, nr: u32, arg0: u32) -> u32 {
+      let mut accumulator = 0;
+      let mut index = 0;
+      loop {
+        let instruction = &filter[index];
+        index += 1;
+        match instruction.code {
+          BPF_LD_W_ABS => {
+            accumulator = match instruction.k {
+              SECCOMP_DATA_NR_OFFSET => nr,
+              SECCOMP_DATA_ARCH_OFFSET => arch,
+              SECCOMP_DATA_ARG0_OFFSET => arg0,
+              offset => panic!("Unexpected offset {}", offset),
+            }
+          }
+          BPF_JMP_JEQ_K | BPF_JMP_JGE_K => {
+            let is_match = if instruction.code == BPF_JMP_JEQ_K {
+              accumulator == instruction.k
+            } else {
+              accumulator >= instruction.k
+            };
+            index += if is_match { instruction.jt } else { instruction.jf } as usize;
+          }
+          BPF_RET_K => return instruction.k,
+          code => panic!("Unexpected code {}", code),
+        }
+      }
+    }
+  }
+}
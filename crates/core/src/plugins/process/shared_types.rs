@@ -1,2 +1,15 @@
 /// The process plugin schema version.
 pub const PLUGIN_SCHEMA_VERSION: u32 = 4;
+
+/// The environment variable the CLI sets on the process plugin with the
+/// capabilities it supports. Plugins that receive it respond to the schema
+/// version request with the capabilities they also support.
+pub const PLUGIN_CAPABILITIES_ENV_VAR_NAME: &str = "DPRINT_PLUGIN_CAPABILITIES";
+/// Large format text payloads may be compressed with zstd.
+pub const ZSTD_COMPRESSION_CAPABILITY: u32 = 1;
+/// The capabilities supported by this side of the connection.
+pub const SUPPORTED_CAPABILITIES: u32 = if cfg!(feature = "process-compression") {
+  ZSTD_COMPRESSION_CAPABILITY
+} else {
+  0
+};
@@ -1,14 +1,47 @@
 use anyhow::bail;
 use anyhow::Result;
+use clap::parser::ValueSource;
 use clap::ArgMatches;
+use dprint_core::plugins::FormatRange;
+use std::path::PathBuf;
+use std::time::Duration;
 
+use crate::commands::DEFAULT_BROKER_IDLE_TIMEOUT_MINUTES;
+use crate::environment::CacheOverlayMode;
+use crate::utils::DiffColors;
+use crate::utils::DiffOptions;
+use crate::utils::SortOutputMode;
 use crate::utils::StdInReader;
 
 pub struct CliArgs {
   pub sub_command: SubCommand,
   pub verbose: bool,
+  /// Errors for any write to the file system (`--assert-no-writes`).
+  pub assert_no_writes: bool,
+  /// Writes to a temporary overlay of the cache directory that's merged or discarded at the end (`--cache-overlay`).
+  pub cache_overlay: Option<CacheOverlayMode>,
+  /// Answers yes to prompts such as trusting remote plugins (`--yes`).
+  pub yes: bool,
+  /// Errors when the remote plugins aren't pinned in an up to date lockfile (`--frozen`).
+  pub frozen: bool,
+  /// Outputs the listings in a stable order along with how to display paths (`--sort-output`).
+  pub sort_output: Option<SortOutputMode>,
   pub plugins: Vec<String>,
   pub config: Option<String>,
+  /// The configuration files after the first when `--config` is provided multiple times.
+  pub additional_configs: Vec<String>,
+  /// What to do when more than one of the configuration files includes the same file (`--config-conflicts`).
+  pub config_conflicts: ConfigConflictMode,
+}
+
+/// What to do when more than one configuration file provided via `--config` includes the same file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigConflictMode {
+  /// Errors with a report of the files and the configuration files that include them.
+  #[default]
+  Error,
+  /// Formats each file only with the configuration file in its nearest ancestor directory.
+  Nearest,
 }
 
 impl CliArgs {
@@ -17,8 +50,15 @@ impl CliArgs {
     Self {
       sub_command: SubCommand::Help("".to_string()),
       verbose: false,
+      assert_no_writes: false,
+      cache_overlay: None,
+      yes: false,
+      frozen: false,
+      sort_output: None,
       plugins: vec![],
       config: None,
+      additional_configs: vec![],
+      config_conflicts: Default::default(),
     }
   }
 
@@ -26,7 +66,18 @@ impl CliArgs {
     // these output json or other text that's read by stdout
     matches!(
       self.sub_command,
-      SubCommand::StdInFmt(..) | SubCommand::EditorInfo | SubCommand::OutputResolvedConfig
+      SubCommand::StdInFmt(..)
+        | SubCommand::EditorInfo
+        | SubCommand::OutputResolvedConfig
+        | SubCommand::Config(ConfigSubCommand::Flatten { output: None })
+        | SubCommand::Fmt(FmtSubCommand {
+          mode: FmtMode::Stdout | FmtMode::Diff,
+          ..
+        })
+        | SubCommand::Check(CheckSubCommand {
+          output_format: CheckOutputFormat::Json | CheckOutputFormat::Sarif,
+          ..
+        })
     )
   }
 
@@ -34,10 +85,34 @@ impl CliArgs {
     CliArgs {
       sub_command,
       verbose: false,
+      assert_no_writes: false,
+      cache_overlay: None,
+      yes: false,
+      frozen: false,
+      sort_output: None,
       config: None,
+      additional_configs: Vec::new(),
+      config_conflicts: Default::default(),
       plugins: Vec::new(),
     }
   }
+
+  /// Gets the arguments for running the sub command with only the provided configuration file.
+  pub fn with_single_config(&self, sub_command: SubCommand, config: String) -> CliArgs {
+    CliArgs {
+      sub_command,
+      verbose: self.verbose,
+      assert_no_writes: self.assert_no_writes,
+      cache_overlay: self.cache_overlay,
+      yes: self.yes,
+      frozen: self.frozen,
+      sort_output: self.sort_output,
+      config: Some(config),
+      additional_configs: Vec::new(),
+      config_conflicts: self.config_conflicts,
+      plugins: self.plugins.clone(),
+    }
+  }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -45,40 +120,120 @@ pub enum SubCommand {
   Check(CheckSubCommand),
   Fmt(FmtSubCommand),
   Config(ConfigSubCommand),
+  Quarantine(QuarantineSubCommand),
+  Cache(CacheSubCommand),
   ClearCache,
   OutputFilePaths(OutputFilePathsSubCommand),
   OutputResolvedConfig,
   OutputFormatTimes(OutputFormatTimesSubCommand),
+  WhySlow,
+  Stats(StatsSubCommand),
+  SuggestConfig(SuggestConfigSubCommand),
+  Coverage(CoverageSubCommand),
+  RunDiff(RunDiffSubCommand),
   Version,
   License,
   Help(String),
   EditorInfo,
   EditorService(EditorServiceSubCommand),
+  Lsp,
+  Broker(BrokerSubCommand),
   StdInFmt(StdInFmtSubCommand),
   Upgrade,
   #[cfg(target_os = "windows")]
   Hidden(HiddenSubCommand),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CheckSubCommand {
   pub patterns: FilePatternArgs,
   pub incremental: Option<bool>,
+  pub fail_fast: bool,
+  pub allow_plugin_failures: bool,
+  pub diff_options: DiffOptions,
+  pub group_by_directory: bool,
+  pub cache_results: bool,
+  pub failed: bool,
+  pub broker: bool,
+  pub summary_file: Option<String>,
+  pub fix_script: Option<String>,
+  pub deterministic: bool,
+  pub output_format: CheckOutputFormat,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// How the results of `dprint check` are output (`--output-format`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckOutputFormat {
+  /// Logs a diff of each file that isn't formatted.
+  Text,
+  /// Outputs a JSON document with the result of each checked file to stdout.
+  Json,
+  /// Outputs a SARIF log, such as for GitHub code scanning, to stdout.
+  Sarif,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FmtSubCommand {
+  pub mode: FmtMode,
   pub diff: bool,
   pub patterns: FilePatternArgs,
   pub incremental: Option<bool>,
   pub enable_stable_format: bool,
+  pub fail_fast: bool,
+  pub allow_plugin_failures: bool,
+  pub broker: bool,
+  pub summary_file: Option<String>,
+  /// Stops formatting new files after this duration (`--max-duration`).
+  pub max_duration: Option<Duration>,
+  /// Stops formatting new files after this many files (`--max-files`).
+  pub max_files: Option<usize>,
+  /// Only formats the quarantined files (`dprint quarantine retry`).
+  pub retry_quarantined: bool,
+  pub deterministic: bool,
+  /// Flushes each written file and its directory to the storage device (`--fsync`).
+  pub fsync: bool,
+  /// Keeps formatting the files as they change (`--watch`).
+  pub watch: bool,
+}
+
+/// What to do with the formatted text of each file (`--mode`).
+///
+/// `--mode check` is parsed as the check sub command, which errors
+/// when combined with flags that only apply to formatting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FmtMode {
+  /// Writes the formatted files to the file system.
+  Write,
+  /// Outputs the formatted text of a single file to stdout.
+  Stdout,
+  /// Outputs a unified diff of every file that would be formatted.
+  Diff,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum ConfigSubCommand {
   Init,
-  Update { yes: bool },
+  Update { yes: bool, preview: bool, lockfile: bool },
   Add(Option<String>),
+  Explain(Option<String>),
+  Flatten { output: Option<String> },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum QuarantineSubCommand {
+  List,
+  Clear,
+  Retry,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct StatsSubCommand {
+  pub history: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CacheSubCommand {
+  Log,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -86,6 +241,25 @@ pub struct OutputFilePathsSubCommand {
   pub patterns: FilePatternArgs,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct SuggestConfigSubCommand {
+  pub patterns: FilePatternArgs,
+  /// Writes the suggested values to the configuration file.
+  pub write: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CoverageSubCommand {
+  pub patterns: FilePatternArgs,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RunDiffSubCommand {
+  /// The run summary written by `--summary-file` to compare against.
+  pub summary_file: String,
+  pub patterns: FilePatternArgs,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct OutputFormatTimesSubCommand {
   pub patterns: FilePatternArgs,
@@ -96,10 +270,17 @@ pub struct EditorServiceSubCommand {
   pub parent_pid: u32,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+pub struct BrokerSubCommand {
+  pub idle_timeout_minutes: u64,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct StdInFmtSubCommand {
   pub file_name_or_path: String,
   pub file_text: String,
+  /// The byte range of the text to format (`--range` or `--line-range`).
+  pub range: FormatRange,
   pub patterns: FilePatternArgs,
 }
 
@@ -112,11 +293,52 @@ pub enum HiddenSubCommand {
   WindowsUninstall(String),
 }
 
-#[derive(Debug, Default, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct FilePatternArgs {
   pub file_patterns: Vec<String>,
   pub exclude_file_patterns: Vec<String>,
   pub allow_node_modules: bool,
+  /// Changed files used to scope the file paths to the affected workspace packages.
+  pub affected_by: Option<Vec<String>>,
+  /// Restricts the file paths to the files staged or changed in git.
+  pub git_selection: Option<GitFileSelection>,
+  /// Associations of file patterns to plugins for this invocation.
+  pub associations: Vec<AssociationArg>,
+  /// Files removed from the file paths because another configuration
+  /// file formats them (`--config-conflicts nearest`).
+  pub excluded_file_paths: Vec<PathBuf>,
+}
+
+/// Files selected from git (`--staged` or `--changed-since <ref>`).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum GitFileSelection {
+  /// The files staged in the index.
+  Staged,
+  /// The files changed compared to the merge base of the ref and `HEAD`.
+  ChangedSince(String),
+}
+
+/// A file pattern to format with a plugin (ex. `--associate '*.snap=json'`).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AssociationArg {
+  pub file_pattern: String,
+  /// The plugin name or configuration key.
+  pub plugin: String,
+}
+
+impl AssociationArg {
+  pub fn parse(text: &str) -> Result<AssociationArg> {
+    match text.rsplit_once('=') {
+      Some((file_pattern, plugin)) if !file_pattern.trim().is_empty() && !plugin.trim().is_empty() => Ok(AssociationArg {
+        file_pattern: file_pattern.trim().to_string(),
+        plugin: plugin.trim().to_string(),
+      }),
+      _ => bail!(
+        "Invalid association '{}'. Expected a file pattern and a plugin separated by an equals sign (ex. '*.snap=json').",
+        text
+      ),
+    }
+  }
 }
 
 pub fn parse_args<TStdInReader: StdInReader>(args: Vec<String>, std_in_reader: TStdInReader) -> Result<CliArgs> {
@@ -145,33 +367,112 @@ pub fn parse_args<TStdInReader: StdInReader>(args: Vec<String>, std_in_reader: T
           // convert extension to file path
           format!("file.{}", file_name_path_or_extension)
         };
+        let file_text = std_in_reader.read()?;
         SubCommand::StdInFmt(StdInFmtSubCommand {
           file_name_or_path,
-          file_text: std_in_reader.read()?,
+          range: parse_stdin_range(matches, &file_text)?,
+          file_text,
           patterns: parse_file_patterns(matches)?,
         })
+      } else if matches.get_one::<String>("mode").map(|mode| mode.as_str()) == Some("check") {
+        // these would otherwise be silently ignored by the check sub command
+        for fmt_only_arg in ["skip-stable-format", "max-duration", "max-files", "fsync", "watch"] {
+          if matches.value_source(fmt_only_arg) == Some(ValueSource::CommandLine) {
+            bail!("Cannot specify --{} with --mode check.", fmt_only_arg);
+          }
+        }
+        SubCommand::Check(CheckSubCommand {
+          patterns: parse_file_patterns(matches)?,
+          incremental: parse_incremental(matches),
+          fail_fast: matches.get_flag("fail-fast"),
+          allow_plugin_failures: matches.get_flag("allow-plugin-failures"),
+          diff_options: Default::default(),
+          group_by_directory: false,
+          cache_results: false,
+          failed: false,
+          broker: matches.get_flag("broker"),
+          summary_file: matches.get_one::<String>("summary-file").map(String::from),
+          fix_script: None,
+          deterministic: matches.get_flag("deterministic"),
+          output_format: CheckOutputFormat::Text,
+        })
       } else {
         SubCommand::Fmt(FmtSubCommand {
+          mode: match matches.get_one::<String>("mode").map(|mode| mode.as_str()) {
+            Some("stdout") => FmtMode::Stdout,
+            Some("diff") => FmtMode::Diff,
+            _ => FmtMode::Write,
+          },
           diff: matches.get_flag("diff"),
           patterns: parse_file_patterns(matches)?,
           incremental: parse_incremental(matches),
           enable_stable_format: !matches.get_flag("skip-stable-format"),
+          fail_fast: matches.get_flag("fail-fast"),
+          allow_plugin_failures: matches.get_flag("allow-plugin-failures"),
+          broker: matches.get_flag("broker"),
+          summary_file: matches.get_one::<String>("summary-file").map(String::from),
+          max_duration: match matches.get_one::<String>("max-duration") {
+            Some(value) => Some(parse_duration(value)?),
+            None => None,
+          },
+          max_files: match matches.get_one::<String>("max-files") {
+            Some(value) => match value.parse::<usize>() {
+              Ok(count) if count > 0 => Some(count),
+              _ => bail!("The max files must be a number greater than zero, but found '{}'.", value),
+            },
+            None => None,
+          },
+          retry_quarantined: false,
+          deterministic: matches.get_flag("deterministic"),
+          fsync: matches.get_flag("fsync"),
+          watch: matches.get_flag("watch"),
         })
       }
     }
     ("check", matches) => SubCommand::Check(CheckSubCommand {
       patterns: parse_file_patterns(matches)?,
       incremental: parse_incremental(matches),
+      fail_fast: matches.get_flag("fail-fast"),
+      allow_plugin_failures: matches.get_flag("allow-plugin-failures"),
+      diff_options: parse_diff_options(matches)?,
+      group_by_directory: matches.get_flag("group-by-directory"),
+      cache_results: matches.get_flag("cache-results"),
+      failed: matches.get_flag("failed"),
+      broker: matches.get_flag("broker"),
+      summary_file: matches.get_one::<String>("summary-file").map(String::from),
+      fix_script: matches.get_one::<String>("fix-script").map(String::from),
+      deterministic: matches.get_flag("deterministic"),
+      output_format: match matches.get_one::<String>("output-format").map(|format| format.as_str()) {
+        Some("json") => CheckOutputFormat::Json,
+        Some("sarif") => CheckOutputFormat::Sarif,
+        _ => CheckOutputFormat::Text,
+      },
     }),
     ("init", _) => SubCommand::Config(ConfigSubCommand::Init),
     ("config", matches) => SubCommand::Config(match matches.subcommand().unwrap() {
       ("init", _) => ConfigSubCommand::Init,
       ("add", matches) => ConfigSubCommand::Add(matches.get_one::<String>("url-or-plugin-name").map(String::from)),
+      ("explain", matches) => ConfigSubCommand::Explain(matches.get_one::<String>("key").map(String::from)),
+      ("flatten", matches) => ConfigSubCommand::Flatten {
+        output: matches.get_one::<String>("output").map(String::from),
+      },
       ("update", matches) => ConfigSubCommand::Update {
-        yes: *matches.get_one::<bool>("yes").unwrap(),
+        yes: matches.get_flag("yes"),
+        preview: matches.get_flag("preview"),
+        lockfile: matches.get_flag("lockfile"),
       },
       _ => unreachable!(),
     }),
+    ("quarantine", matches) => SubCommand::Quarantine(match matches.subcommand().unwrap() {
+      ("list", _) => QuarantineSubCommand::List,
+      ("clear", _) => QuarantineSubCommand::Clear,
+      ("retry", _) => QuarantineSubCommand::Retry,
+      _ => unreachable!(),
+    }),
+    ("cache", matches) => SubCommand::Cache(match matches.subcommand().unwrap() {
+      ("log", _) => CacheSubCommand::Log,
+      _ => unreachable!(),
+    }),
     ("clear-cache", _) => SubCommand::ClearCache,
     ("output-file-paths", matches) => SubCommand::OutputFilePaths(OutputFilePathsSubCommand {
       patterns: parse_file_patterns(matches)?,
@@ -180,12 +481,37 @@ pub fn parse_args<TStdInReader: StdInReader>(args: Vec<String>, std_in_reader: T
     ("output-format-times", matches) => SubCommand::OutputFormatTimes(OutputFormatTimesSubCommand {
       patterns: parse_file_patterns(matches)?,
     }),
+    ("why-slow", _) => SubCommand::WhySlow,
+    ("suggest-config", matches) => SubCommand::SuggestConfig(SuggestConfigSubCommand {
+      patterns: parse_file_patterns(matches)?,
+      write: matches.get_flag("write"),
+    }),
+    ("coverage", matches) => SubCommand::Coverage(CoverageSubCommand {
+      patterns: parse_file_patterns(matches)?,
+    }),
+    ("run-diff", matches) => SubCommand::RunDiff(RunDiffSubCommand {
+      summary_file: matches.get_one::<String>("summary-file").map(String::from).unwrap(),
+      patterns: parse_file_patterns(matches)?,
+    }),
+    ("stats", matches) => SubCommand::Stats(StatsSubCommand {
+      history: *matches.get_one::<bool>("history").unwrap(),
+    }),
     ("version", _) => SubCommand::Version,
     ("license", _) => SubCommand::License,
     ("editor-info", _) => SubCommand::EditorInfo,
+    ("lsp", _) => SubCommand::Lsp,
     ("editor-service", matches) => SubCommand::EditorService(EditorServiceSubCommand {
       parent_pid: matches.get_one::<String>("parent-pid").and_then(|v| v.parse::<u32>().ok()).unwrap(),
     }),
+    ("broker", matches) => SubCommand::Broker(BrokerSubCommand {
+      idle_timeout_minutes: match matches.get_one::<String>("idle-timeout") {
+        Some(value) => match value.parse::<u64>() {
+          Ok(minutes) if minutes > 0 => minutes,
+          _ => bail!("The idle timeout must be a number of minutes greater than zero, but found '{}'.", value),
+        },
+        None => DEFAULT_BROKER_IDLE_TIMEOUT_MINUTES,
+      },
+    }),
     ("upgrade", _) => SubCommand::Upgrade,
     #[cfg(target_os = "windows")]
     ("hidden", matches) => SubCommand::Hidden(match matches.subcommand().unwrap() {
@@ -198,14 +524,77 @@ pub fn parse_args<TStdInReader: StdInReader>(args: Vec<String>, std_in_reader: T
     }
   };
 
+  let mut configs = values_to_vec(matches.get_many("config")).into_iter();
   Ok(CliArgs {
     sub_command,
     verbose: matches.get_flag("verbose"),
-    config: matches.get_one::<String>("config").map(String::from),
+    assert_no_writes: matches.get_flag("assert-no-writes"),
+    cache_overlay: match matches.get_one::<String>("cache-overlay").map(|v| v.as_str()) {
+      Some("merge") => Some(CacheOverlayMode::Merge),
+      Some(_) => Some(CacheOverlayMode::Discard),
+      None => None,
+    },
+    yes: matches.get_flag("yes"),
+    frozen: matches.get_flag("frozen"),
+    sort_output: match matches.get_one::<String>("sort-output").map(|v| v.as_str()) {
+      Some("native") => Some(SortOutputMode::NativeSeparators),
+      Some(_) => Some(SortOutputMode::ForwardSlashes),
+      None => None,
+    },
+    config: configs.next(),
+    additional_configs: configs.collect(),
+    config_conflicts: match matches.get_one::<String>("config-conflicts").map(|v| v.as_str()) {
+      Some("nearest") => ConfigConflictMode::Nearest,
+      _ => ConfigConflictMode::Error,
+    },
     plugins: values_to_vec(matches.get_many("plugins")),
   })
 }
 
+/// Gets the byte range to format of the text provided to `--stdin`.
+fn parse_stdin_range(matches: &ArgMatches, file_text: &str) -> Result<FormatRange> {
+  if let Some(value) = matches.get_one::<String>("range") {
+    let (start, end) = parse_range_bounds(value, "range")?;
+    if start > end || end > file_text.len() {
+      bail!("The range {} must be within the {} bytes of the text.", value, file_text.len());
+    }
+    if !file_text.is_char_boundary(start) || !file_text.is_char_boundary(end) {
+      bail!("The range {} must not start or end within a character.", value);
+    }
+    Ok(Some(start..end))
+  } else if let Some(value) = matches.get_one::<String>("line-range") {
+    let (start_line, end_line) = parse_range_bounds(value, "line-range")?;
+    let mut line_starts = vec![0];
+    line_starts.extend(file_text.match_indices('\n').map(|(index, _)| index + 1));
+    if start_line == 0 || start_line > end_line || end_line > line_starts.len() {
+      bail!(
+        "The line range {} must be within the {} lines of the text, which start at line 1.",
+        value,
+        line_starts.len()
+      );
+    }
+    let start = line_starts[start_line - 1];
+    let end = line_starts.get(end_line).copied().unwrap_or(file_text.len());
+    Ok(Some(start..end))
+  } else {
+    Ok(None)
+  }
+}
+
+fn parse_range_bounds(value: &str, arg_name: &str) -> Result<(usize, usize)> {
+  let bounds = value
+    .split_once(':')
+    .and_then(|(start, end)| Some((start.trim().parse::<usize>().ok()?, end.trim().parse::<usize>().ok()?)));
+  match bounds {
+    Some(bounds) => Ok(bounds),
+    None => bail!(
+      "The --{} value must be in the format <start>:<end> (ex. 10:25), but found '{}'.",
+      arg_name,
+      value
+    ),
+  }
+}
+
 fn parse_file_patterns(matches: &ArgMatches) -> Result<FilePatternArgs> {
   let plugins = values_to_vec(matches.get_many("plugins"));
   let file_patterns = values_to_vec(matches.get_many("files"));
@@ -218,6 +607,29 @@ fn parse_file_patterns(matches: &ArgMatches) -> Result<FilePatternArgs> {
     allow_node_modules: matches.get_flag("allow-node-modules"),
     file_patterns,
     exclude_file_patterns: values_to_vec(matches.get_many("excludes")),
+    affected_by: if matches.contains_id("affected") {
+      // an empty value is no changed files (ex. `--affected "$(git diff --name-only main | paste -sd, -)"`)
+      Some(
+        values_to_vec(matches.get_many("affected"))
+          .into_iter()
+          .filter(|file| !file.is_empty())
+          .collect(),
+      )
+    } else {
+      None
+    },
+    git_selection: if matches.get_flag("staged") {
+      Some(GitFileSelection::Staged)
+    } else {
+      matches
+        .get_one::<String>("changed-since")
+        .map(|git_ref| GitFileSelection::ChangedSince(git_ref.to_string()))
+    },
+    associations: values_to_vec(matches.get_many("associate"))
+      .iter()
+      .map(|text| AssociationArg::parse(text))
+      .collect::<Result<Vec<_>>>()?,
+    excluded_file_paths: Vec::new(),
   })
 }
 
@@ -231,6 +643,41 @@ fn parse_incremental(matches: &ArgMatches) -> Option<bool> {
   }
 }
 
+/// Parses a duration with a unit (ex. `30s`, `5m`, or `1h`).
+fn parse_duration(text: &str) -> Result<Duration> {
+  let text = text.trim();
+  let (value, multiplier) = if let Some(value) = text.strip_suffix('s') {
+    (value, 1)
+  } else if let Some(value) = text.strip_suffix('m') {
+    (value, 60)
+  } else if let Some(value) = text.strip_suffix('h') {
+    (value, 60 * 60)
+  } else {
+    (text, 0)
+  };
+  match value.parse::<u64>() {
+    Ok(value) if value > 0 && multiplier > 0 => Ok(Duration::from_secs(value * multiplier)),
+    _ => bail!(
+      "Invalid duration '{}'. Expected a number greater than zero followed by s, m, or h (ex. '5m').",
+      text
+    ),
+  }
+}
+
+fn parse_diff_options(matches: &ArgMatches) -> Result<DiffOptions> {
+  let mut options = DiffOptions::default();
+  if let Some(context_lines) = matches.get_one::<usize>("diff-context") {
+    options.context_lines = *context_lines;
+  }
+  if let Some(word_diff) = matches.get_one::<String>("word-diff") {
+    options.word_diff = word_diff != "false";
+  }
+  if let Some(colors) = matches.get_one::<String>("diff-colors") {
+    options.colors = DiffColors::parse(colors)?;
+  }
+  Ok(options)
+}
+
 fn values_to_vec(values: Option<clap::parser::ValuesRef<String>>) -> Vec<String> {
   values.map(|x| x.map(std::string::ToString::to_string).collect()).unwrap_or_default()
 }
@@ -335,6 +782,10 @@ EXAMPLES:
         .about("Formats the source files and writes the result to the file system.")
         .add_resolve_file_path_args()
         .add_incremental_arg()
+        .add_allow_plugin_failures_arg()
+        .add_broker_arg()
+        .add_deterministic_arg()
+        .add_summary_file_arg()
         .arg(
           Arg::new("stdin")
             .long("stdin")
@@ -343,6 +794,25 @@ EXAMPLES:
             .required(false)
             .num_args(1)
         )
+        .arg(
+          Arg::new("range")
+            .long("range")
+            .value_name("start:end")
+            .help("Only formats the byte range of the text provided to --stdin (ex. 120:480).")
+            .requires("stdin")
+            .conflicts_with("line-range")
+            .num_args(1)
+            .required(false)
+        )
+        .arg(
+          Arg::new("line-range")
+            .long("line-range")
+            .value_name("start:end")
+            .help("Only formats the lines of the text provided to --stdin, which start at line 1 (ex. 10:25).")
+            .requires("stdin")
+            .num_args(1)
+            .required(false)
+        )
         .arg(
           Arg::new("diff")
             .long("diff")
@@ -350,6 +820,56 @@ EXAMPLES:
             .num_args(0)
             .required(false)
         )
+        .arg(
+          Arg::new("mode")
+            .long("mode")
+            .help("What to do with the formatted files: write them (default), check them like `dprint check`, output the formatted text of a single file to stdout, or output a unified diff.")
+            .value_parser(["write", "check", "stdout", "diff"])
+            .num_args(1)
+            .conflicts_with_all(["stdin", "diff"])
+            .required(false)
+        )
+        .arg(
+          Arg::new("fail-fast")
+            .long("fail-fast")
+            .help("Stops formatting and exits on the first error.")
+            .num_args(0)
+            .required(false)
+        )
+        .arg(
+          Arg::new("max-duration")
+            .long("max-duration")
+            .value_name("duration")
+            .help("Stops formatting new files after this duration (ex. '5m'). Files that changed in git are formatted first and the incremental cache is used to continue in the next run.")
+            .num_args(1)
+            .conflicts_with("stdin")
+            .required(false)
+        )
+        .arg(
+          Arg::new("max-files")
+            .long("max-files")
+            .value_name("count")
+            .help("Stops formatting new files after formatting this many files. Files that changed in git are formatted first and the incremental cache is used to continue in the next run.")
+            .num_args(1)
+            .conflicts_with("stdin")
+            .required(false)
+        )
+        .arg(
+          Arg::new("fsync")
+            .long("fsync")
+            .help("Flushes each formatted file and its directory to the storage device after writing. Slower, but useful on network file systems where writes were lost after a power event.")
+            .num_args(0)
+            .conflicts_with("stdin")
+            .required(false)
+        )
+        .arg(
+          Arg::new("watch")
+            .long("watch")
+            .help("Keeps running and formats the files as they change. Changes to the configuration file or local plugins resolve the plugins again.")
+            .num_args(0)
+            .conflicts_with_all(["stdin", "max-duration", "max-files"])
+            .required(false)
+        )
         .arg(
           Arg::new("skip-stable-format")
             .long("skip-stable-format")
@@ -365,6 +885,79 @@ EXAMPLES:
         .about("Checks for any files that haven't been formatted.")
         .add_resolve_file_path_args()
         .add_incremental_arg()
+        .add_allow_plugin_failures_arg()
+        .add_broker_arg()
+        .add_deterministic_arg()
+        .add_summary_file_arg()
+        .arg(
+          Arg::new("fail-fast")
+            .long("fail-fast")
+            .help("Stops checking and exits on the first file that isn't formatted or error.")
+            .num_args(0)
+            .required(false)
+        )
+        .arg(
+          Arg::new("diff-context")
+            .long("diff-context")
+            .value_name("lines")
+            .help("Number of unchanged lines to show around each difference. Defaults to 3.")
+            .value_parser(clap::value_parser!(usize))
+            .num_args(1)
+        )
+        .arg(
+          Arg::new("word-diff")
+            .long("word-diff")
+            .help("Whether to highlight the words that changed within changed lines. Defaults to true.")
+            .num_args(0..=1)
+            .value_parser(["true", "false"])
+            .require_equals(true)
+        )
+        .arg(
+          Arg::new("diff-colors")
+            .long("diff-colors")
+            .value_name("colors")
+            .help("Colors used in the diff. Either 'default', 'colorblind', or a list of colors (ex. 'removed=magenta,added=cyan').")
+            .num_args(1)
+        )
+        .arg(
+          Arg::new("group-by-directory")
+            .long("group-by-directory")
+            .help("Groups the files that aren't formatted by top level directory. The groups are collapsible on GitHub Actions.")
+            .num_args(0)
+            .required(false)
+        )
+        .arg(
+          Arg::new("cache-results")
+            .long("cache-results")
+            .help("Skips files that were already verified formatted with the same plugins and configuration, even in another directory. This is used instead of the incremental cache.")
+            .num_args(0)
+            .required(false)
+        )
+        .arg(
+          Arg::new("failed")
+            .long("failed")
+            .help("Only checks the files that weren't formatted in the last check.")
+            .num_args(0)
+            .required(false)
+        )
+        .arg(
+          Arg::new("fix-script")
+            .long("fix-script")
+            .value_name("file-path")
+            .help("Writes a shell script with the commands that format the files that aren't formatted to this file.")
+            .num_args(1)
+            .required(false)
+        )
+        .arg(
+          Arg::new("output-format")
+            .long("output-format")
+            .value_name("format")
+            .help("Outputs the results as diffs for people (default), a JSON document, or a SARIF log for code scanning. The JSON and SARIF output is written to stdout and includes the changed lines and expected text of each file.")
+            .value_parser(["text", "json", "sarif"])
+            .num_args(1)
+            .conflicts_with("group-by-directory")
+            .required(false)
+        )
     )
     .subcommand(
       Command::new("config")
@@ -377,7 +970,20 @@ EXAMPLES:
         .subcommand(
           Command::new("update")
             .about("Updates the plugins in the configuration file.")
-            .arg(Arg::new("yes").help("Upgrade process plugins without prompting to confirm checksums.").short('y').long("yes").action(clap::ArgAction::SetTrue))
+            .arg(
+              Arg::new("preview")
+                .long("preview")
+                .help("Formats a sample of the files with both the old and new version of each updated plugin and outputs a summary of the differences.")
+                .num_args(0)
+                .required(false)
+            )
+            .arg(
+              Arg::new("lockfile")
+                .long("lockfile")
+                .help("Pins the url, version, and checksum of every remote plugin in the lockfile (dprint.lock). This is done automatically once the lockfile pins checksums.")
+                .num_args(0)
+                .required(false)
+            )
         )
         .subcommand(
           Command::new("add")
@@ -388,6 +994,45 @@ EXAMPLES:
                 .num_args(1)
           )
         )
+        .subcommand(
+          Command::new("flatten")
+            .about("Outputs the configuration with the values of any extended configuration inlined and the plugins pinned.")
+            .arg(
+              Arg::new("output")
+                .long("output")
+                .value_name("file-path")
+                .help("Writes the configuration to this file instead of stdout.")
+                .required(false)
+                .num_args(1)
+          )
+        )
+        .subcommand(
+          Command::new("explain")
+            .about("Outputs which configuration file, flag, or default each resolved configuration value comes from.")
+            .arg(
+              Arg::new("key")
+                .help("Only outputs this property or the properties of this plugin configuration key (ex. lineWidth, typescript, or typescript.quoteStyle).")
+                .required(false)
+                .num_args(1)
+          )
+        )
+    )
+    .subcommand(
+      Command::new("quarantine")
+        .about("Manages the files that are skipped because they previously caused a critical error in a plugin.")
+        .subcommand_required(true)
+        .subcommand(
+          Command::new("list")
+            .about("Outputs the quarantined files and their errors.")
+        )
+        .subcommand(
+          Command::new("clear")
+            .about("Removes all the files from the quarantine.")
+        )
+        .subcommand(
+          Command::new("retry")
+            .about("Formats the quarantined files, keeping the ones that cause a critical error again in the quarantine.")
+        )
     )
     .subcommand(
       Command::new("output-file-paths")
@@ -403,6 +1048,51 @@ EXAMPLES:
         .about("Prints the amount of time it takes to format each file. Use this for debugging.")
         .add_resolve_file_path_args()
     )
+    .subcommand(
+      Command::new("why-slow")
+        .about("Analyzes the last fmt or check run and explains what made it slow.")
+    )
+    .subcommand(
+      Command::new("stats")
+        .about("Outputs the statistics of the last fmt or check run, which are only stored locally.")
+        .arg(Arg::new("history").long("history").help("Outputs a chart of the recent runs.").action(clap::ArgAction::SetTrue))
+    )
+    .subcommand(
+      Command::new("suggest-config")
+        .about("Samples the files to suggest configuration values that cause the fewest changes when adopting dprint.")
+        .add_resolve_file_path_args()
+        .arg(Arg::new("write").long("write").help("Writes the suggested values to the configuration file.").action(clap::ArgAction::SetTrue))
+    )
+    .subcommand(
+      Command::new("coverage")
+        .about("Outputs how many files of each file type are formatted by a plugin and suggests plugins for the unformatted ones.")
+        .add_resolve_file_path_args()
+    )
+    .subcommand(
+      Command::new("run-diff")
+        .about("Outputs the files whose formatted output changed since a run summary was written with --summary-file.")
+        .arg(
+          Arg::new("summary-file")
+            .value_name("summary-file")
+            .help("Path of the previous run summary.")
+            .required(true)
+            .num_args(1)
+        )
+        .add_resolve_file_path_args()
+    )
+    .subcommand(
+      Command::new("lsp")
+        .about("Runs a language server over stdio for formatting in editors with a Language Server Protocol client.")
+    )
+    .subcommand(
+      Command::new("cache")
+        .about("Inspects the plugin cache.")
+        .subcommand_required(true)
+        .subcommand(
+          Command::new("log")
+            .about("Outputs the log of every plugin artifact that entered the cache, including who fetched it, when, where from, and its checksum.")
+        )
+    )
     .subcommand(
       Command::new("clear-cache")
         .about("Deletes the plugin cache directory.")
@@ -429,11 +1119,32 @@ EXAMPLES:
             .num_args(1)
         )
     )
+    .subcommand(
+      Command::new("broker")
+        .hide(true)
+        .arg(
+          Arg::new("idle-timeout")
+            .long("idle-timeout")
+            .value_name("minutes")
+            .num_args(1)
+        )
+    )
     .arg(
       Arg::new("config")
         .long("config")
         .short('c')
-        .help("Path or url to JSON configuration file. Defaults to dprint.json or .dprint.json in current or ancestor directory when not provided.")
+        .help("Path or url to JSON configuration file. Defaults to dprint.json or .dprint.json in current or ancestor directory when not provided. Provide it multiple times to run fmt or check for each configuration.")
+        .global(true)
+        .num_args(1)
+        .action(clap::ArgAction::Append)
+    )
+    .arg(
+      Arg::new("config-conflicts")
+        .long("config-conflicts")
+        .value_name("mode")
+        .help("What to do when more than one configuration file provided via --config includes the same file: error (default) or format it with the configuration file nearest to it.")
+        .value_parser(["error", "nearest"])
+        .hide_possible_values(true)
         .global(true)
         .num_args(1)
     )
@@ -452,6 +1163,52 @@ EXAMPLES:
         .global(true)
         .num_args(0)
     )
+    .arg(
+      Arg::new("assert-no-writes")
+        .long("assert-no-writes")
+        .help("Errors if anything would be written to the file system, including the cache. Use this to run in a read-only build sandbox.")
+        .global(true)
+        .num_args(0)
+    )
+    .arg(
+      Arg::new("cache-overlay")
+        .long("cache-overlay")
+        .value_name("mode")
+        .help("Writes downloaded plugins and incremental data to a temporary overlay instead of the shared cache, then discards it (default) or merges it into the cache at the end.")
+        .global(true)
+        .num_args(0..=1)
+        .value_parser(["discard", "merge"])
+        .default_missing_value("discard")
+        .hide_possible_values(true)
+        .require_equals(true)
+    )
+    .arg(
+      Arg::new("yes")
+        .long("yes")
+        .short('y')
+        .help("Answers yes to prompts, such as trusting remote plugins that are run for the first time or upgrading process plugins without confirming their checksums.")
+        .global(true)
+        .num_args(0)
+    )
+    .arg(
+      Arg::new("frozen")
+        .long("frozen")
+        .help("Errors when a remote plugin is not pinned in the lockfile (dprint.lock) or the lockfile is out of date. Use this on CI.")
+        .global(true)
+        .num_args(0)
+    )
+    .arg(
+      Arg::new("sort-output")
+        .long("sort-output")
+        .value_name("paths")
+        .help("Outputs file lists, summaries, and plugin lists in a stable locale independent order with paths displayed using forward slashes (default) or the native separators, so logs diff cleanly across platforms.")
+        .global(true)
+        .num_args(0..=1)
+        .value_parser(["forward-slashes", "native"])
+        .default_missing_value("forward-slashes")
+        .hide_possible_values(true)
+        .require_equals(true)
+    )
     .subcommand(
       Command::new("hidden")
         .hide(true)
@@ -477,6 +1234,10 @@ EXAMPLES:
 trait ClapExtensions {
   fn add_resolve_file_path_args(self) -> Self;
   fn add_incremental_arg(self) -> Self;
+  fn add_allow_plugin_failures_arg(self) -> Self;
+  fn add_broker_arg(self) -> Self;
+  fn add_deterministic_arg(self) -> Self;
+  fn add_summary_file_arg(self) -> Self;
 }
 
 impl ClapExtensions for clap::Command {
@@ -501,6 +1262,38 @@ impl ClapExtensions for clap::Command {
           .help("Allows traversing node module directories (unstable - This flag will be renamed to be non-node specific in the future).")
           .num_args(0),
       )
+      .arg(
+        Arg::new("affected")
+          .long("affected")
+          .value_name("changed files")
+          .help("Only use the files of the workspace packages (package.json workspaces or Cargo workspace members) containing the provided comma separated changed files and the packages that depend on them. May be provided multiple times.")
+          .num_args(1)
+          .value_delimiter(',')
+          .action(clap::ArgAction::Append),
+      )
+      .arg(
+        Arg::new("staged")
+          .long("staged")
+          .help("Only use the files staged in git. The files formatted by `dprint fmt` are staged again.")
+          .num_args(0)
+          .conflicts_with_all(["changed-since", "affected"]),
+      )
+      .arg(
+        Arg::new("changed-since")
+          .long("changed-since")
+          .value_name("ref")
+          .help("Only use the files changed in git compared to where the current branch diverged from the provided ref (ex. 'origin/main').")
+          .num_args(1)
+          .conflicts_with("affected"),
+      )
+      .arg(
+        Arg::new("associate")
+          .long("associate")
+          .value_name("pattern=plugin")
+          .help("Formats the files matching a file pattern with a plugin (ex. '*.snap=json'). The plugin may be its name or configuration key. May be provided multiple times.")
+          .num_args(1)
+          .action(clap::ArgAction::Append),
+      )
   }
 
   fn add_incremental_arg(self) -> Self {
@@ -514,6 +1307,51 @@ impl ClapExtensions for clap::Command {
         .require_equals(true),
     )
   }
+
+  fn add_allow_plugin_failures_arg(self) -> Self {
+    use clap::Arg;
+    self.arg(
+      Arg::new("allow-plugin-failures")
+        .long("allow-plugin-failures")
+        .help("Skips the files of any plugins that fail to load instead of erroring.")
+        .num_args(0)
+        .required(false),
+    )
+  }
+
+  fn add_broker_arg(self) -> Self {
+    use clap::Arg;
+    self.arg(
+      Arg::new("broker")
+        .long("broker")
+        .help("Formats using a background process that keeps the plugins loaded between runs, starting it if necessary (Linux and Mac only).")
+        .num_args(0)
+        .required(false),
+    )
+  }
+
+  fn add_deterministic_arg(self) -> Self {
+    use clap::Arg;
+    self.arg(
+      Arg::new("deterministic")
+        .long("deterministic")
+        .help("Formats the files of each plugin one at a time in a canonical order so runs are reproducible (ex. for benchmarking or debugging).")
+        .num_args(0)
+        .required(false),
+    )
+  }
+
+  fn add_summary_file_arg(self) -> Self {
+    use clap::Arg;
+    self.arg(
+      Arg::new("summary-file")
+        .long("summary-file")
+        .value_name("file-path")
+        .help("Writes a deterministic summary of the configuration, plugins, and resulting file hashes to this file for build caching systems.")
+        .num_args(1)
+        .required(false),
+    )
+  }
 }
 
 #[cfg(test)]
@@ -570,6 +1408,272 @@ mod test {
     assert_eq!(fmt_cmd.incremental, Some(true));
   }
 
+  #[test]
+  fn mode_arg() {
+    assert_eq!(parse_fmt_sub_command(vec!["fmt"]).unwrap().mode, FmtMode::Write);
+    assert_eq!(parse_fmt_sub_command(vec!["fmt", "--mode", "write"]).unwrap().mode, FmtMode::Write);
+    assert_eq!(parse_fmt_sub_command(vec!["fmt", "--mode", "stdout"]).unwrap().mode, FmtMode::Stdout);
+    assert_eq!(parse_fmt_sub_command(vec!["fmt", "--mode", "diff"]).unwrap().mode, FmtMode::Diff);
+    let args = test_args(vec!["fmt", "--mode", "check", "--fail-fast"]).unwrap();
+    match args.sub_command {
+      SubCommand::Check(cmd) => assert!(cmd.fail_fast),
+      _ => unreachable!(),
+    }
+    assert!(test_args(vec!["fmt", "--mode", "diff", "--diff"]).is_err());
+    assert!(test_args(vec!["fmt", "--mode", "check", "--diff"]).is_err());
+  }
+
+  #[test]
+  fn mode_check_with_fmt_only_args() {
+    let get_err = |args: Vec<&str>| test_args(args).err().unwrap().to_string();
+    assert_eq!(
+      get_err(vec!["fmt", "--mode", "check", "--skip-stable-format"]),
+      "Cannot specify --skip-stable-format with --mode check."
+    );
+    assert_eq!(
+      get_err(vec!["fmt", "--mode", "check", "--max-duration", "5m"]),
+      "Cannot specify --max-duration with --mode check."
+    );
+    assert_eq!(
+      get_err(vec!["fmt", "--mode", "check", "--max-files", "5"]),
+      "Cannot specify --max-files with --mode check."
+    );
+    assert_eq!(get_err(vec!["fmt", "--mode", "check", "--fsync"]), "Cannot specify --fsync with --mode check.");
+    assert_eq!(get_err(vec!["fmt", "--mode", "check", "--watch"]), "Cannot specify --watch with --mode check.");
+  }
+
+  #[test]
+  fn output_format_arg() {
+    let get_output_format = |args: Vec<&str>| match test_args(args).unwrap().sub_command {
+      SubCommand::Check(cmd) => cmd.output_format,
+      _ => unreachable!(),
+    };
+    assert_eq!(get_output_format(vec!["check"]), CheckOutputFormat::Text);
+    assert_eq!(get_output_format(vec!["check", "--output-format", "json"]), CheckOutputFormat::Json);
+    assert_eq!(get_output_format(vec!["check", "--output-format", "sarif"]), CheckOutputFormat::Sarif);
+    assert!(test_args(vec!["check", "--output-format", "sarif"]).unwrap().is_stdout_machine_readable());
+    assert!(test_args(vec!["check", "--output-format", "json", "--group-by-directory"]).is_err());
+  }
+
+  #[test]
+  fn affected_arg() {
+    assert_eq!(parse_fmt_sub_command(vec!["fmt"]).unwrap().patterns.affected_by, None);
+    let fmt_cmd = parse_fmt_sub_command(vec!["fmt", "--affected", "a.ts", "**/*.ts"]).unwrap();
+    assert_eq!(fmt_cmd.patterns.affected_by, Some(vec!["a.ts".to_string()]));
+    assert_eq!(fmt_cmd.patterns.file_patterns, vec!["**/*.ts".to_string()]);
+    let fmt_cmd = parse_fmt_sub_command(vec!["fmt", "--affected", "a.ts,b/c.ts", "--affected", "d.ts"]).unwrap();
+    assert_eq!(
+      fmt_cmd.patterns.affected_by,
+      Some(vec!["a.ts".to_string(), "b/c.ts".to_string(), "d.ts".to_string()])
+    );
+    assert_eq!(fmt_cmd.patterns.file_patterns, Vec::<String>::new());
+    let fmt_cmd = parse_fmt_sub_command(vec!["fmt", "--affected", ""]).unwrap();
+    assert_eq!(fmt_cmd.patterns.affected_by, Some(Vec::new()));
+    assert!(test_args(vec!["fmt", "--affected"]).is_err());
+  }
+
+  #[test]
+  fn associate_arg() {
+    let fmt_cmd = parse_fmt_sub_command(vec!["fmt", "--associate", "*.snap=json", "--associate", "Jakefile = typescript"]).unwrap();
+    assert_eq!(
+      fmt_cmd.patterns.associations,
+      vec![
+        AssociationArg {
+          file_pattern: "*.snap".to_string(),
+          plugin: "json".to_string(),
+        },
+        AssociationArg {
+          file_pattern: "Jakefile".to_string(),
+          plugin: "typescript".to_string(),
+        },
+      ]
+    );
+    let err = parse_fmt_sub_command(vec!["fmt", "--associate", "*.snap"]).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      "Invalid association '*.snap'. Expected a file pattern and a plugin separated by an equals sign (ex. '*.snap=json')."
+    );
+  }
+
+  #[test]
+  fn budget_args() {
+    let fmt_cmd = parse_fmt_sub_command(vec!["fmt"]).unwrap();
+    assert_eq!(fmt_cmd.max_duration, None);
+    assert_eq!(fmt_cmd.max_files, None);
+    let fmt_cmd = parse_fmt_sub_command(vec!["fmt", "--max-duration", "5m", "--max-files", "100"]).unwrap();
+    assert_eq!(fmt_cmd.max_duration, Some(Duration::from_secs(300)));
+    assert_eq!(fmt_cmd.max_files, Some(100));
+    assert_eq!(
+      parse_fmt_sub_command(vec!["fmt", "--max-duration", "30s"]).unwrap().max_duration,
+      Some(Duration::from_secs(30))
+    );
+    assert_eq!(
+      parse_fmt_sub_command(vec!["fmt", "--max-duration", "2h"]).unwrap().max_duration,
+      Some(Duration::from_secs(7200))
+    );
+    let err = parse_fmt_sub_command(vec!["fmt", "--max-duration", "5"]).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      "Invalid duration '5'. Expected a number greater than zero followed by s, m, or h (ex. '5m')."
+    );
+    let err = parse_fmt_sub_command(vec!["fmt", "--max-files", "0"]).err().unwrap();
+    assert_eq!(err.to_string(), "The max files must be a number greater than zero, but found '0'.");
+  }
+
+  #[test]
+  fn fsync_arg() {
+    assert!(!parse_fmt_sub_command(vec!["fmt"]).unwrap().fsync);
+    assert!(parse_fmt_sub_command(vec!["fmt", "--fsync"]).unwrap().fsync);
+  }
+
+  #[test]
+  fn watch_arg() {
+    assert!(!parse_fmt_sub_command(vec!["fmt"]).unwrap().watch);
+    assert!(parse_fmt_sub_command(vec!["fmt", "--watch"]).unwrap().watch);
+    assert!(test_args(vec!["fmt", "--watch", "--max-files", "5"]).is_err());
+  }
+
+  #[test]
+  fn quarantine_sub_commands() {
+    assert_eq!(
+      test_args(vec!["quarantine", "list"]).unwrap().sub_command,
+      SubCommand::Quarantine(QuarantineSubCommand::List)
+    );
+    assert_eq!(
+      test_args(vec!["quarantine", "clear"]).unwrap().sub_command,
+      SubCommand::Quarantine(QuarantineSubCommand::Clear)
+    );
+    assert_eq!(
+      test_args(vec!["quarantine", "retry"]).unwrap().sub_command,
+      SubCommand::Quarantine(QuarantineSubCommand::Retry)
+    );
+    assert!(test_args(vec!["quarantine"]).is_err());
+  }
+
+  #[test]
+  fn stats_sub_command() {
+    assert_eq!(
+      test_args(vec!["stats"]).unwrap().sub_command,
+      SubCommand::Stats(StatsSubCommand { history: false })
+    );
+    assert_eq!(
+      test_args(vec!["stats", "--history"]).unwrap().sub_command,
+      SubCommand::Stats(StatsSubCommand { history: true })
+    );
+  }
+
+  #[test]
+  fn run_diff_sub_command() {
+    let args = test_args(vec!["run-diff", "old/summary.json", "**/*.txt"]).unwrap();
+    match args.sub_command {
+      SubCommand::RunDiff(cmd) => {
+        assert_eq!(cmd.summary_file, "old/summary.json");
+        assert_eq!(cmd.patterns.file_patterns, vec!["**/*.txt".to_string()]);
+      }
+      _ => unreachable!(),
+    }
+    assert!(test_args(vec!["run-diff"]).is_err());
+  }
+
+  #[test]
+  fn cache_sub_commands() {
+    assert_eq!(test_args(vec!["cache", "log"]).unwrap().sub_command, SubCommand::Cache(CacheSubCommand::Log));
+    assert!(test_args(vec!["cache"]).is_err());
+  }
+
+  #[test]
+  fn multiple_config_args() {
+    let args = test_args(vec!["check"]).unwrap();
+    assert_eq!(args.config, None);
+    assert_eq!(args.additional_configs, Vec::<String>::new());
+    let args = test_args(vec!["check", "--config", "a.json", "--config", "b.json", "-c", "c.json"]).unwrap();
+    assert_eq!(args.config, Some("a.json".to_string()));
+    assert_eq!(args.additional_configs, vec!["b.json".to_string(), "c.json".to_string()]);
+    let single_args = args.with_single_config(SubCommand::Version, "b.json".to_string());
+    assert_eq!(single_args.config, Some("b.json".to_string()));
+    assert!(single_args.additional_configs.is_empty());
+  }
+
+  #[test]
+  fn config_conflicts_arg() {
+    assert_eq!(test_args(vec!["check"]).unwrap().config_conflicts, ConfigConflictMode::Error);
+    assert_eq!(
+      test_args(vec!["fmt", "--config-conflicts", "nearest"]).unwrap().config_conflicts,
+      ConfigConflictMode::Nearest
+    );
+    assert!(test_args(vec!["fmt", "--config-conflicts", "other"]).is_err());
+  }
+
+  #[test]
+  fn cache_overlay_arg() {
+    assert_eq!(test_args(vec!["check"]).unwrap().cache_overlay, None);
+    assert_eq!(
+      test_args(vec!["check", "--cache-overlay"]).unwrap().cache_overlay,
+      Some(CacheOverlayMode::Discard)
+    );
+    assert_eq!(
+      test_args(vec!["fmt", "--cache-overlay=discard"]).unwrap().cache_overlay,
+      Some(CacheOverlayMode::Discard)
+    );
+    assert_eq!(
+      test_args(vec!["fmt", "--cache-overlay=merge"]).unwrap().cache_overlay,
+      Some(CacheOverlayMode::Merge)
+    );
+    assert!(test_args(vec!["fmt", "--cache-overlay=other"]).is_err());
+  }
+
+  #[test]
+  fn frozen_arg() {
+    assert!(!test_args(vec!["check"]).unwrap().frozen);
+    assert!(test_args(vec!["check", "--frozen"]).unwrap().frozen);
+    let args = test_args(vec!["config", "update", "--lockfile", "--frozen"]).unwrap();
+    assert!(args.frozen);
+    assert!(matches!(args.sub_command, SubCommand::Config(ConfigSubCommand::Update { lockfile: true, .. })));
+  }
+
+  #[test]
+  fn sort_output_arg() {
+    assert_eq!(test_args(vec!["check"]).unwrap().sort_output, None);
+    assert_eq!(
+      test_args(vec!["check", "--sort-output"]).unwrap().sort_output,
+      Some(SortOutputMode::ForwardSlashes)
+    );
+    assert_eq!(
+      test_args(vec!["output-file-paths", "--sort-output=native"]).unwrap().sort_output,
+      Some(SortOutputMode::NativeSeparators)
+    );
+    assert!(test_args(vec!["check", "--sort-output=other"]).is_err());
+  }
+
+  #[test]
+  fn stdin_range_args() {
+    let parse_range = |args: Vec<&str>| {
+      let mut args: Vec<String> = args.into_iter().map(String::from).collect();
+      args.insert(0, "".to_string());
+      parse_args(args, TestStdInReader::from("a\nbb\nc\n")).map(|args| match args.sub_command {
+        SubCommand::StdInFmt(cmd) => cmd.range,
+        _ => unreachable!(),
+      })
+    };
+    assert_eq!(parse_range(vec!["fmt", "--stdin", "txt"]).unwrap(), None);
+    assert_eq!(parse_range(vec!["fmt", "--stdin", "txt", "--range", "2:4"]).unwrap(), Some(2..4));
+    assert_eq!(parse_range(vec!["fmt", "--stdin", "txt", "--line-range", "2:3"]).unwrap(), Some(2..7));
+    assert_eq!(parse_range(vec!["fmt", "--stdin", "txt", "--line-range", "1:1"]).unwrap(), Some(0..2));
+    assert_eq!(
+      parse_range(vec!["fmt", "--stdin", "txt", "--range", "2:20"]).err().unwrap().to_string(),
+      "The range 2:20 must be within the 7 bytes of the text."
+    );
+    assert_eq!(
+      parse_range(vec!["fmt", "--stdin", "txt", "--line-range", "0:1"]).err().unwrap().to_string(),
+      "The line range 0:1 must be within the 4 lines of the text, which start at line 1."
+    );
+    assert_eq!(
+      parse_range(vec!["fmt", "--stdin", "txt", "--range", "2"]).err().unwrap().to_string(),
+      "The --range value must be in the format <start>:<end> (ex. 10:25), but found '2'."
+    );
+    assert!(test_args(vec!["fmt", "--range", "2:4"]).is_err());
+  }
+
   fn parse_fmt_sub_command(args: Vec<&str>) -> Result<FmtSubCommand> {
     let args = test_args(args)?;
     match args.sub_command {
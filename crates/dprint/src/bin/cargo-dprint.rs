@@ -0,0 +1,111 @@
+//! Runs dprint in the root directory of the current Cargo workspace
+//! when invoked as `cargo dprint <sub-command>`.
+
+use anyhow::bail;
+use anyhow::Result;
+use std::ffi::OsString;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+const CONFIG_FILE_NAMES: [&str; 2] = ["dprint.json", ".dprint.json"];
+
+fn main() {
+  match run() {
+    Ok(exit_code) => std::process::exit(exit_code),
+    Err(err) => {
+      eprintln!("{:#}", err);
+      std::process::exit(1);
+    }
+  }
+}
+
+fn run() -> Result<i32> {
+  let workspace_root = get_workspace_root()?;
+  let config_file_path = get_config_file_in_dir(&workspace_root);
+  let args = get_dprint_args(std::env::args_os().collect(), config_file_path.as_deref());
+  let status = match Command::new(get_dprint_exe_path()).args(args).current_dir(&workspace_root).status() {
+    Ok(status) => status,
+    Err(err) => bail!("Error running dprint. Ensure it's installed (ex. `cargo install --locked dprint`). {:#}", err),
+  };
+  Ok(status.code().unwrap_or(1))
+}
+
+fn get_workspace_root() -> Result<PathBuf> {
+  // cargo sets this to the path of the cargo executable for sub commands
+  let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+  let output = Command::new(cargo)
+    .args(["locate-project", "--workspace", "--message-format", "plain"])
+    .output()?;
+  if !output.status.success() {
+    bail!("Could not find the Cargo workspace. {}", String::from_utf8_lossy(&output.stderr).trim());
+  }
+  let manifest_path = PathBuf::from(String::from_utf8(output.stdout)?.trim());
+  match manifest_path.parent() {
+    Some(parent) => Ok(parent.to_path_buf()),
+    None => bail!("Could not find the Cargo workspace. Unexpected manifest path: {}", manifest_path.display()),
+  }
+}
+
+fn get_config_file_in_dir(dir: &Path) -> Option<PathBuf> {
+  CONFIG_FILE_NAMES.iter().map(|file_name| dir.join(file_name)).find(|path| path.is_file())
+}
+
+fn get_dprint_exe_path() -> PathBuf {
+  // prefer the dprint executable installed beside this one
+  let exe_name = format!("dprint{}", std::env::consts::EXE_SUFFIX);
+  if let Some(exe_dir) = std::env::current_exe().ok().as_ref().and_then(|path| path.parent()) {
+    let exe_path = exe_dir.join(&exe_name);
+    if exe_path.is_file() {
+      return exe_path;
+    }
+  }
+  PathBuf::from(exe_name)
+}
+
+fn get_dprint_args(args: Vec<OsString>, config_file_path: Option<&Path>) -> Vec<OsString> {
+  let mut args = args.into_iter().skip(1).peekable();
+  // cargo provides the sub command name as the first argument
+  if args.peek().map(|arg| arg == "dprint").unwrap_or(false) {
+    args.next();
+  }
+  let args = args.collect::<Vec<_>>();
+  let has_config_arg = args.iter().any(|arg| {
+    let arg = arg.to_string_lossy();
+    arg == "--config" || arg == "-c" || arg.starts_with("--config=")
+  });
+  match config_file_path {
+    Some(config_file_path) if !has_config_arg => {
+      let mut final_args = vec![OsString::from("--config"), config_file_path.as_os_str().to_os_string()];
+      final_args.extend(args);
+      final_args
+    }
+    _ => args,
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn should_get_dprint_args() {
+    let config_file_path = PathBuf::from("/project/dprint.json");
+    assert_eq!(
+      get_dprint_args(to_args(&["cargo-dprint", "dprint", "fmt", "--diff"]), Some(&config_file_path)),
+      to_args(&["--config", "/project/dprint.json", "fmt", "--diff"])
+    );
+    assert_eq!(get_dprint_args(to_args(&["cargo-dprint", "dprint", "check"]), None), to_args(&["check"]));
+    // when running the executable directly
+    assert_eq!(get_dprint_args(to_args(&["cargo-dprint", "check"]), None), to_args(&["check"]));
+    // config provided
+    assert_eq!(
+      get_dprint_args(to_args(&["cargo-dprint", "dprint", "fmt", "--config", "other.json"]), Some(&config_file_path)),
+      to_args(&["fmt", "--config", "other.json"])
+    );
+  }
+
+  fn to_args(args: &[&str]) -> Vec<OsString> {
+    args.iter().map(OsString::from).collect()
+  }
+}
@@ -0,0 +1,361 @@
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Result;
+use dprint_core::communication::IdGenerator;
+use dprint_core::communication::MessageReader;
+use dprint_core::communication::MessageWriter;
+use dprint_core::communication::Poisoner;
+use dprint_core::communication::SingleThreadMessageWriter;
+use dprint_core::plugins::BoxFuture;
+use dprint_core::plugins::FormatResult;
+use dprint_core::plugins::Host;
+use dprint_core::plugins::HostFormatRequest;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+
+use super::editor::messages::EditorMessage;
+use super::editor::messages::EditorMessageBody;
+use super::editor::messages::FormatEditorMessageBody;
+use crate::arg_parser::CliArgs;
+use crate::environment::CanonicalizedPathBuf;
+use crate::environment::Environment;
+use crate::plugins::PluginResolver;
+use crate::plugins::PluginsCollection;
+use crate::utils::get_bytes_hash;
+
+/// The number of minutes the broker waits for another connection before exiting.
+pub const DEFAULT_BROKER_IDLE_TIMEOUT_MINUTES: u64 = 10;
+#[cfg(unix)]
+const IDLE_TIMEOUT_ENV_VAR_NAME: &str = "DPRINT_BROKER_IDLE_TIMEOUT";
+
+/// Gets the path of the socket of the broker for the current directory and arguments.
+///
+/// Each project gets its own broker because the broker resolves the configuration
+/// the same way a CLI invocation in that directory would.
+pub fn get_broker_socket_path(args: &CliArgs, environment: &impl Environment) -> CanonicalizedPathBuf {
+  let key = format!("{}|{:?}|{:?}", environment.cwd().display(), args.config, args.plugins);
+  let broker_dir = environment.get_cache_dir().join_panic_relative("broker");
+  broker_dir.join_panic_relative(format!("{}.sock", get_bytes_hash(key.as_bytes())))
+}
+
+#[cfg(unix)]
+fn get_idle_timeout_minutes(environment: &impl Environment) -> Result<u64> {
+  match environment.env_var(IDLE_TIMEOUT_ENV_VAR_NAME) {
+    Some(value) => match value.trim().parse::<u64>() {
+      Ok(minutes) if minutes > 0 => Ok(minutes),
+      _ => bail!(
+        "The {} environment variable must be a number of minutes greater than zero (ex. 10), but found '{}'.",
+        IDLE_TIMEOUT_ENV_VAR_NAME,
+        value
+      ),
+    },
+    None => Ok(DEFAULT_BROKER_IDLE_TIMEOUT_MINUTES),
+  }
+}
+
+type ResponseSenders = Arc<Mutex<HashMap<u32, oneshot::Sender<Result<EditorMessageBody>>>>>;
+
+/// Formats files with a broker process that keeps the plugins initialized
+/// between CLI invocations.
+pub struct BrokerClient {
+  writer: SingleThreadMessageWriter<EditorMessage>,
+  id_generator: IdGenerator,
+  response_senders: ResponseSenders,
+}
+
+impl BrokerClient {
+  pub fn new(reader: Box<dyn Read + Send>, writer: Box<dyn Write + Send>) -> Self {
+    let response_senders: ResponseSenders = Default::default();
+    std::thread::spawn({
+      let response_senders = response_senders.clone();
+      move || {
+        let mut reader = MessageReader::new(reader);
+        while let Ok(message) = EditorMessage::read(&mut reader) {
+          let message_id = match &message.body {
+            EditorMessageBody::Error(message_id, _)
+            | EditorMessageBody::Success(message_id)
+            | EditorMessageBody::CanFormatResponse(message_id, _)
+            | EditorMessageBody::FormatResponse(message_id, _) => *message_id,
+            _ => continue,
+          };
+          let response = match message.body {
+            EditorMessageBody::Error(_, data) => Err(anyhow!("{}", String::from_utf8_lossy(&data))),
+            body => Ok(body),
+          };
+          if let Some(sender) = response_senders.lock().remove(&message_id) {
+            let _ignore = sender.send(response);
+          }
+        }
+        // drop the senders so that any pending requests error
+        response_senders.lock().clear();
+      }
+    });
+
+    BrokerClient {
+      writer: SingleThreadMessageWriter::for_stdin(MessageWriter::new(writer), Poisoner::default()),
+      id_generator: Default::default(),
+      response_senders,
+    }
+  }
+
+  /// Has the broker resolve the configuration again in case it changed since the last invocation.
+  pub async fn refresh_config(&self, file_path: &Path) -> Result<()> {
+    self.send(EditorMessageBody::CanFormat(file_path.to_path_buf())).await?;
+    Ok(())
+  }
+
+  pub async fn close(&self) -> Result<()> {
+    self.send(EditorMessageBody::Close).await?;
+    Ok(())
+  }
+
+  async fn send(&self, body: EditorMessageBody) -> Result<EditorMessageBody> {
+    let message_id = self.id_generator.next();
+    let (tx, rx) = oneshot::channel();
+    self.response_senders.lock().insert(message_id, tx);
+    self.writer.send(EditorMessage { id: message_id, body })?;
+    match rx.await {
+      Ok(response) => response,
+      Err(_) => bail!("The connection to the plugin broker was closed."),
+    }
+  }
+}
+
+impl Host for BrokerClient {
+  fn format(&self, request: HostFormatRequest) -> BoxFuture<FormatResult> {
+    Box::pin(async move {
+      let override_config = if request.override_config.is_empty() {
+        Vec::new()
+      } else {
+        serde_json::to_vec(&request.override_config)?
+      };
+      let response = self
+        .send(EditorMessageBody::Format(FormatEditorMessageBody {
+          file_path: request.file_path,
+          range: request.range,
+          override_config,
+          file_text: request.file_text.into_bytes(),
+        }))
+        .await?;
+      match response {
+        EditorMessageBody::FormatResponse(_, Some(bytes)) => Ok(Some(String::from_utf8(bytes)?)),
+        EditorMessageBody::FormatResponse(_, None) => Ok(None),
+        _ => bail!("Unexpected response from the plugin broker."),
+      }
+    })
+  }
+}
+
+/// Connects to the broker of the current directory, starting it if it's not running.
+#[cfg(unix)]
+pub async fn connect_to_broker(args: &CliArgs, environment: &impl Environment) -> Result<BrokerClient> {
+  use std::os::unix::net::UnixStream;
+  use std::time::Duration;
+
+  let socket_path = get_broker_socket_path(args, environment);
+  let stream = match UnixStream::connect(&socket_path) {
+    Ok(stream) => stream,
+    Err(_) => {
+      log_verbose!(environment, "Starting plugin broker at {}", socket_path.display());
+      start_broker(args, environment)?;
+      let mut attempts = 0;
+      loop {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        match UnixStream::connect(&socket_path) {
+          Ok(stream) => break stream,
+          Err(err) => {
+            attempts += 1;
+            if attempts == 200 {
+              bail!("Error connecting to the plugin broker at {}. {:#}", socket_path.display(), err);
+            }
+          }
+        }
+      }
+    }
+  };
+  let client = BrokerClient::new(Box::new(stream.try_clone()?), Box::new(stream));
+  client.refresh_config(environment.cwd().as_ref()).await?;
+  Ok(client)
+}
+
+#[cfg(not(unix))]
+pub async fn connect_to_broker(_args: &CliArgs, _environment: &impl Environment) -> Result<BrokerClient> {
+  bail!("The plugin broker is not supported on this operating system.")
+}
+
+#[cfg(unix)]
+fn start_broker(args: &CliArgs, environment: &impl Environment) -> Result<()> {
+  use std::process::Command;
+  use std::process::Stdio;
+
+  let mut command = Command::new(environment.current_exe()?);
+  command
+    .arg("broker")
+    .arg("--idle-timeout")
+    .arg(get_idle_timeout_minutes(environment)?.to_string());
+  if let Some(config) = &args.config {
+    command.arg("--config").arg(config);
+  }
+  if !args.plugins.is_empty() {
+    command.arg("--plugins").args(&args.plugins);
+  }
+  command
+    .current_dir(environment.cwd().as_ref())
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn()
+    .map_err(|err| anyhow!("Error starting the plugin broker. {:#}", err))?;
+  Ok(())
+}
+
+#[cfg(unix)]
+pub async fn run_broker<TEnvironment: Environment>(
+  args: &CliArgs,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+  plugin_pools: Arc<PluginsCollection<TEnvironment>>,
+  cmd: &crate::arg_parser::BrokerSubCommand,
+) -> Result<()> {
+  use std::io::ErrorKind;
+  use std::os::unix::net::UnixListener;
+  use std::os::unix::net::UnixStream;
+  use std::time::Duration;
+  use std::time::Instant;
+
+  use super::broker_snapshot::get_broker_snapshot_path;
+  use super::broker_snapshot::read_broker_snapshot;
+  use super::broker_snapshot::restore_broker_snapshot;
+  use super::broker_snapshot::write_broker_snapshot;
+  use super::editor::EditorService;
+
+  let socket_path = get_broker_socket_path(args, environment);
+  if UnixStream::connect(&socket_path).is_ok() {
+    log_verbose!(environment, "A plugin broker is already running at {}", socket_path.display());
+    return Ok(());
+  }
+  if let Some(parent) = socket_path.parent() {
+    environment.mk_dir_all(parent)?;
+  }
+  // remove the socket of a broker that didn't exit cleanly
+  let _ignore = environment.remove_file(&socket_path);
+  let listener = UnixListener::bind(&socket_path).map_err(|err| anyhow!("Error binding to {}. {:#}", socket_path.display(), err))?;
+  listener.set_nonblocking(true)?;
+
+  let snapshot_path = get_broker_snapshot_path(socket_path.as_ref());
+  let mut config = match read_broker_snapshot(&snapshot_path, environment) {
+    Some(snapshot) => match restore_broker_snapshot(snapshot, args, environment, plugin_resolver, &plugin_pools).await {
+      Ok(config) => config,
+      Err(err) => {
+        log_verbose!(environment, "Error restoring the broker snapshot. {:#}", err);
+        None
+      }
+    },
+    None => None,
+  };
+
+  let idle_timeout = Duration::from_secs(cmd.idle_timeout_minutes * 60);
+  let mut last_activity = Instant::now();
+  loop {
+    match listener.accept() {
+      Ok((stream, _)) => {
+        stream.set_nonblocking(false)?;
+        let reader = Box::new(stream.try_clone()?);
+        let writer = SingleThreadMessageWriter::for_stdin(MessageWriter::new(Box::new(stream) as Box<dyn Write + Send>), Poisoner::default());
+        let mut editor_service = EditorService::new_with_io(args, environment, plugin_resolver, plugin_pools.clone(), reader, writer);
+        editor_service.set_config(config.take());
+        if let Err(err) = editor_service.run().await {
+          log_verbose!(environment, "Plugin broker connection closed. {:#}", err);
+        }
+        config = editor_service.take_config();
+        // save after each connection in case the broker doesn't exit cleanly
+        if let Some(config) = &config {
+          write_broker_snapshot(&snapshot_path, config, &plugin_pools, environment);
+        }
+        last_activity = Instant::now();
+      }
+      Err(err) if err.kind() == ErrorKind::WouldBlock => {
+        if last_activity.elapsed() >= idle_timeout {
+          break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+      }
+      Err(err) => return Err(err.into()),
+    }
+  }
+
+  let _ignore = environment.remove_file(&socket_path);
+  plugin_pools.drop_and_shutdown_initialized().await;
+  Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn run_broker<TEnvironment: Environment>(
+  _args: &CliArgs,
+  _environment: &TEnvironment,
+  _plugin_resolver: &PluginResolver<TEnvironment>,
+  _plugin_pools: Arc<PluginsCollection<TEnvironment>>,
+  _cmd: &crate::arg_parser::BrokerSubCommand,
+) -> Result<()> {
+  bail!("The plugin broker is not supported on this operating system.")
+}
+
+#[cfg(test)]
+mod test {
+  use dprint_core::plugins::NullCancellationToken;
+  use pretty_assertions::assert_eq;
+  use std::path::PathBuf;
+
+  use super::*;
+  use crate::environment::TestEnvironment;
+  use crate::environment::TestEnvironmentBuilder;
+  use crate::test_helpers::run_test_cli;
+
+  #[test]
+  fn should_get_broker_socket_path() {
+    let environment = TestEnvironment::new();
+    let args = CliArgs::empty();
+    let socket_path = get_broker_socket_path(&args, &environment);
+    assert!(socket_path.to_string_lossy().starts_with("/cache/broker/"));
+    assert!(socket_path.to_string_lossy().ends_with(".sock"));
+    environment.set_cwd("/other");
+    assert_ne!(get_broker_socket_path(&args, &environment), socket_path);
+  }
+
+  #[test]
+  fn should_format_with_broker_client() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/file.txt", "text")
+      .build();
+    let stdin = environment.stdin_writer();
+    let stdout = environment.stdout_reader();
+
+    let result = std::thread::spawn(move || {
+      TestEnvironment::new().run_in_runtime(async move {
+        let client = BrokerClient::new(stdout, stdin);
+        client.refresh_config(Path::new("/file.txt")).await.unwrap();
+        let format = |file_text: &str| {
+          client.format(HostFormatRequest {
+            file_path: PathBuf::from("/file.txt"),
+            file_text: file_text.to_string(),
+            range: None,
+            override_config: Default::default(),
+            token: Arc::new(NullCancellationToken),
+          })
+        };
+        assert_eq!(format("text").await.unwrap(), Some("text_formatted".to_string()));
+        assert_eq!(format("text_formatted").await.unwrap(), None);
+        assert_eq!(format("should_error").await.err().unwrap().to_string(), "Did error.");
+        client.close().await.unwrap();
+      })
+    });
+
+    run_test_cli(vec!["editor-service", "--parent-pid", &std::process::id().to_string()], &environment).unwrap();
+    result.join().unwrap();
+  }
+}
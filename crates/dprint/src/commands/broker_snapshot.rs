@@ -0,0 +1,151 @@
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+use std::path::PathBuf;
+
+use super::editor::create_plugin_instances;
+use super::editor::resolve_latest_config;
+use crate::arg_parser::CliArgs;
+use crate::configuration::get_flattened_config_text;
+use crate::configuration::resolve_config_from_args;
+use crate::configuration::ResolvedConfig;
+use crate::environment::Environment;
+use crate::plugins::PluginResolver;
+use crate::plugins::PluginsCollection;
+use crate::utils::get_sha256_checksum;
+
+const SNAPSHOT_SCHEMA_VERSION: u32 = 1;
+
+/// The state of the broker that's used to get back to where it was
+/// when it restarts (ex. after an upgrade or crash).
+///
+/// The compiled Wasm modules are already stored in the plugin cache, so this
+/// only needs to store which plugins had instances for the configuration.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct BrokerSnapshot {
+  schema_version: u32,
+  /// Checksum of the resolved configuration with any extended configuration inlined.
+  config_hash: String,
+  warm_plugins: Vec<String>,
+}
+
+pub(super) fn get_broker_snapshot_path(socket_path: &Path) -> PathBuf {
+  socket_path.with_extension("json")
+}
+
+fn get_config_hash(config: &ResolvedConfig) -> Result<String> {
+  let config_text = get_flattened_config_text(config, config.plugins.iter().map(|plugin| plugin.to_full_string()).collect())?;
+  Ok(get_sha256_checksum(config_text.as_bytes()))
+}
+
+pub(super) fn read_broker_snapshot(file_path: &Path, environment: &impl Environment) -> Option<BrokerSnapshot> {
+  let file_text = environment.read_file(file_path).ok()?;
+  match serde_json::from_str::<BrokerSnapshot>(&file_text) {
+    Ok(snapshot) if snapshot.schema_version == SNAPSHOT_SCHEMA_VERSION => Some(snapshot),
+    Ok(_) => None,
+    Err(err) => {
+      log_verbose!(environment, "Error deserializing broker snapshot {}: {:#}", file_path.display(), err);
+      None
+    }
+  }
+}
+
+pub(super) fn write_broker_snapshot<TEnvironment: Environment>(
+  file_path: &Path,
+  config: &ResolvedConfig,
+  plugins_collection: &PluginsCollection<TEnvironment>,
+  environment: &TEnvironment,
+) {
+  let result = get_config_hash(config).and_then(|config_hash| {
+    let snapshot = BrokerSnapshot {
+      schema_version: SNAPSHOT_SCHEMA_VERSION,
+      config_hash,
+      warm_plugins: plugins_collection
+        .get_plugins()
+        .iter()
+        .filter(|plugin| plugin.is_initialized())
+        .map(|plugin| plugin.name().to_string())
+        .collect(),
+    };
+    environment.write_file(file_path, &serde_json::to_string(&snapshot)?)
+  });
+  if let Err(err) = result {
+    log_verbose!(environment, "Error saving broker snapshot {}: {:#}", file_path.display(), err);
+  }
+}
+
+/// Creates instances of the plugins that had instances before the broker
+/// restarted when the configuration hasn't changed since then.
+pub(super) async fn restore_broker_snapshot<TEnvironment: Environment>(
+  snapshot: BrokerSnapshot,
+  args: &CliArgs,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+  plugins_collection: &PluginsCollection<TEnvironment>,
+) -> Result<Option<ResolvedConfig>> {
+  let config = resolve_config_from_args(args, environment)?;
+  if get_config_hash(&config)? != snapshot.config_hash {
+    log_verbose!(environment, "Not restoring the broker snapshot because the configuration changed.");
+    return Ok(None);
+  }
+  let config = resolve_latest_config(None, args, environment, plugin_resolver, plugins_collection).await?;
+  let plugins = plugins_collection
+    .get_plugins()
+    .into_iter()
+    .filter(|plugin| snapshot.warm_plugins.iter().any(|name| name == plugin.name()))
+    .collect::<Vec<_>>();
+  create_plugin_instances(&plugins, environment).await?;
+  log_verbose!(environment, "Restored {} plugin(s) from the broker snapshot.", plugins.len());
+  Ok(Some(config))
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+  use std::sync::Arc;
+
+  use super::*;
+  use crate::environment::TestEnvironmentBuilder;
+  use crate::plugins::PluginCache;
+
+  #[test]
+  fn should_write_and_restore_broker_snapshot() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_and_process_plugin().build();
+    environment.run_in_runtime({
+      let environment = environment.clone();
+      async move {
+        let collection = Arc::new(PluginsCollection::new(environment.clone()));
+        let plugin_cache = Arc::new(PluginCache::new(environment.clone()));
+        let resolver = PluginResolver::new(environment.clone(), plugin_cache, collection.clone());
+        let args = CliArgs::empty();
+        let snapshot_path = get_broker_snapshot_path(Path::new("/cache/broker/1.sock"));
+        assert_eq!(snapshot_path, PathBuf::from("/cache/broker/1.json"));
+
+        // only the wasm plugin was used
+        let config = resolve_latest_config(None, &args, &environment, &resolver, &collection).await.unwrap();
+        create_plugin_instances(&[collection.get_plugin("test-plugin")], &environment).await.unwrap();
+        environment.mk_dir_all("/cache/broker").unwrap();
+        write_broker_snapshot(&snapshot_path, &config, &collection, &environment);
+        collection.drop_and_shutdown_initialized().await;
+
+        let snapshot = read_broker_snapshot(&snapshot_path, &environment).unwrap();
+        assert_eq!(snapshot.warm_plugins, vec!["test-plugin".to_string()]);
+        let restored_config = restore_broker_snapshot(snapshot, &args, &environment, &resolver, &collection).await.unwrap();
+        assert_eq!(restored_config, Some(config));
+        assert!(collection.get_plugin("test-plugin").is_initialized());
+        assert!(!collection.get_plugin("test-process-plugin").is_initialized());
+        collection.drop_and_shutdown_initialized().await;
+
+        // changing the configuration should cause it to not be restored
+        environment
+          .write_file("/dprint.json", r#"{ "plugins": ["https://plugins.dprint.dev/test-plugin.wasm"] }"#)
+          .unwrap();
+        let snapshot = read_broker_snapshot(&snapshot_path, &environment).unwrap();
+        let restored_config = restore_broker_snapshot(snapshot, &args, &environment, &resolver, &collection).await.unwrap();
+        assert_eq!(restored_config, None);
+      }
+    });
+  }
+}
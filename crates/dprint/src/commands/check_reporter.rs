@@ -0,0 +1,271 @@
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::arg_parser::CheckOutputFormat;
+use crate::arg_parser::CheckSubCommand;
+use crate::commands::get_difference_output;
+use crate::commands::output_grouped_differences;
+use crate::commands::output_sorted_differences;
+use crate::configuration::CheckSeverity;
+use crate::environment::CanonicalizedPathBuf;
+use crate::environment::Environment;
+use crate::utils::get_line_changes;
+use crate::utils::DiffOptions;
+use crate::utils::LineChange;
+use crate::utils::SortOutputMode;
+
+const SARIF_SCHEMA_URL: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const NOT_FORMATTED_RULE_ID: &str = "not-formatted";
+
+/// Reports the results of `dprint check` in the output format of the command.
+///
+/// The text output is logged as the files are checked unless it's grouped or
+/// sorted, while the JSON and SARIF documents are output once every file is checked.
+pub struct CheckReporter {
+  output_format: CheckOutputFormat,
+  base_path: CanonicalizedPathBuf,
+  diff_options: DiffOptions,
+  group_by_directory: bool,
+  sort_output: Option<SortOutputMode>,
+  buffered_differences: Option<Mutex<Vec<(PathBuf, String)>>>,
+  file_results: Mutex<Vec<CheckFileResult>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckFileResult {
+  /// The path relative to the configuration file's directory with forward slashes.
+  path: String,
+  formatted: bool,
+  /// The severity of the file not being formatted (`checkSeverity`).
+  #[serde(skip_serializing_if = "Option::is_none")]
+  severity: Option<&'static str>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  changes: Vec<LineChange>,
+}
+
+impl CheckReporter {
+  pub fn new(cmd: &CheckSubCommand, base_path: CanonicalizedPathBuf, sort_output: Option<SortOutputMode>) -> Self {
+    let is_buffering_differences = cmd.output_format == CheckOutputFormat::Text && (cmd.group_by_directory || sort_output.is_some());
+    CheckReporter {
+      output_format: cmd.output_format,
+      base_path,
+      diff_options: cmd.diff_options.clone(),
+      group_by_directory: cmd.group_by_directory,
+      sort_output,
+      buffered_differences: if is_buffering_differences { Some(Default::default()) } else { None },
+      file_results: Default::default(),
+    }
+  }
+
+  /// Reports a checked file that's formatted.
+  pub fn report_formatted(&self, file_path: &Path) {
+    if self.output_format != CheckOutputFormat::Text {
+      self.file_results.lock().push(CheckFileResult {
+        path: self.get_relative_path(file_path),
+        formatted: true,
+        severity: None,
+        changes: Vec::new(),
+      });
+    }
+  }
+
+  /// Reports a checked file that isn't formatted.
+  pub fn report_not_formatted(&self, file_path: &Path, file_text: &str, formatted_text: &str, severity: CheckSeverity, environment: &impl Environment) {
+    match self.output_format {
+      CheckOutputFormat::Text => {
+        let difference_output = get_difference_output(file_path, file_text, formatted_text, &self.diff_options, self.sort_output);
+        match &self.buffered_differences {
+          Some(buffered_differences) => buffered_differences.lock().push((file_path.to_path_buf(), difference_output)),
+          None => environment.log(&difference_output),
+        }
+      }
+      CheckOutputFormat::Json | CheckOutputFormat::Sarif => {
+        self.file_results.lock().push(CheckFileResult {
+          path: self.get_relative_path(file_path),
+          formatted: false,
+          severity: Some(match severity {
+            CheckSeverity::Error => "error",
+            CheckSeverity::Warn => "warn",
+          }),
+          changes: get_line_changes(file_text, formatted_text),
+        });
+      }
+    }
+  }
+
+  /// Outputs the results that weren't logged while checking the files.
+  pub fn finish(&self, environment: &impl Environment) -> Result<()> {
+    if let Some(buffered_differences) = &self.buffered_differences {
+      let differences = std::mem::take(&mut *buffered_differences.lock());
+      if self.group_by_directory {
+        output_grouped_differences(differences, self.base_path.as_ref(), environment);
+      } else {
+        output_sorted_differences(differences, self.sort_output, environment);
+      }
+    }
+
+    let mut file_results = std::mem::take(&mut *self.file_results.lock());
+    file_results.sort_by(|a, b| a.path.cmp(&b.path));
+    match self.output_format {
+      CheckOutputFormat::Text => {}
+      CheckOutputFormat::Json => {
+        environment.log_machine_readable(&serde_json::to_string_pretty(&json!({ "files": file_results }))?);
+      }
+      CheckOutputFormat::Sarif => {
+        environment.log_machine_readable(&serde_json::to_string_pretty(&get_sarif_log(&file_results, environment))?);
+      }
+    }
+    Ok(())
+  }
+
+  fn get_relative_path(&self, file_path: &Path) -> String {
+    let relative_path = file_path.strip_prefix(&self.base_path).unwrap_or(file_path);
+    relative_path.to_string_lossy().replace('\\', "/")
+  }
+}
+
+/// Gets a SARIF log with a result for each changed range of lines, which includes the fix.
+fn get_sarif_log(file_results: &[CheckFileResult], environment: &impl Environment) -> Value {
+  let mut results = Vec::new();
+  for file_result in file_results {
+    let level = match file_result.severity {
+      Some("warn") => "warning",
+      _ => "error",
+    };
+    for change in file_result.changes.iter() {
+      // inserted lines are reported on the line they're inserted before
+      let end_line = change.start_line + std::cmp::max(1, change.line_count) - 1;
+      results.push(json!({
+        "ruleId": NOT_FORMATTED_RULE_ID,
+        "level": level,
+        "message": { "text": "The file is not formatted. Run `dprint fmt` to format it." },
+        "locations": [{
+          "physicalLocation": {
+            "artifactLocation": { "uri": file_result.path },
+            "region": { "startLine": change.start_line, "endLine": end_line }
+          }
+        }],
+        "fixes": [{
+          "description": { "text": "Format the file." },
+          "artifactChanges": [{
+            "artifactLocation": { "uri": file_result.path },
+            "replacements": [{
+              "deletedRegion": {
+                "startLine": change.start_line,
+                "startColumn": 1,
+                "endLine": change.start_line + change.line_count,
+                "endColumn": 1
+              },
+              "insertedContent": { "text": change.expected_text }
+            }]
+          }]
+        }]
+      }));
+    }
+  }
+  json!({
+    "$schema": SARIF_SCHEMA_URL,
+    "version": "2.1.0",
+    "runs": [{
+      "tool": {
+        "driver": {
+          "name": "dprint",
+          "version": environment.cli_version(),
+          "informationUri": "https://dprint.dev",
+          "rules": [{
+            "id": NOT_FORMATTED_RULE_ID,
+            "shortDescription": { "text": "The file is not formatted." }
+          }]
+        }
+      },
+      "results": results
+    }]
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+  use serde_json::json;
+  use serde_json::Value;
+
+  use crate::environment::TestEnvironmentBuilder;
+  use crate::test_helpers::get_singular_check_text;
+  use crate::test_helpers::run_test_cli;
+
+  #[test]
+  fn should_output_check_results_as_json() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/b.txt", "const t=4;")
+      .write_file("/a.txt", "text_formatted")
+      .build();
+    let error_message = run_test_cli(vec!["check", "--output-format", "json", "**/*.txt"], &environment).err().unwrap();
+    assert_eq!(error_message.to_string(), get_singular_check_text());
+    let stdout_messages = environment.take_stdout_messages();
+    assert_eq!(stdout_messages.len(), 1);
+    assert_eq!(
+      serde_json::from_str::<Value>(&stdout_messages[0]).unwrap(),
+      json!({
+        "files": [{
+          "path": "a.txt",
+          "formatted": true
+        }, {
+          "path": "b.txt",
+          "formatted": false,
+          "severity": "error",
+          "changes": [{
+            "startLine": 1,
+            "lineCount": 1,
+            "expectedText": "const t=4;_formatted"
+          }]
+        }]
+      })
+    );
+  }
+
+  #[test]
+  fn should_output_check_results_as_sarif() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/file.txt", "const t=4;")
+      .build();
+    let error_message = run_test_cli(vec!["check", "--output-format", "sarif", "/file.txt"], &environment)
+      .err()
+      .unwrap();
+    assert_eq!(error_message.to_string(), get_singular_check_text());
+    let stdout_messages = environment.take_stdout_messages();
+    assert_eq!(stdout_messages.len(), 1);
+    let sarif_log = serde_json::from_str::<Value>(&stdout_messages[0]).unwrap();
+    assert_eq!(sarif_log["version"], "2.1.0");
+    assert_eq!(sarif_log["runs"][0]["tool"]["driver"]["name"], "dprint");
+    assert_eq!(
+      sarif_log["runs"][0]["results"],
+      json!([{
+        "ruleId": "not-formatted",
+        "level": "error",
+        "message": { "text": "The file is not formatted. Run `dprint fmt` to format it." },
+        "locations": [{
+          "physicalLocation": {
+            "artifactLocation": { "uri": "file.txt" },
+            "region": { "startLine": 1, "endLine": 1 }
+          }
+        }],
+        "fixes": [{
+          "description": { "text": "Format the file." },
+          "artifactChanges": [{
+            "artifactLocation": { "uri": "file.txt" },
+            "replacements": [{
+              "deletedRegion": { "startLine": 1, "startColumn": 1, "endLine": 2, "endColumn": 1 },
+              "insertedContent": { "text": "const t=4;_formatted" }
+            }]
+          }]
+        }]
+      }])
+    );
+  }
+}
@@ -10,19 +10,25 @@ use crate::arg_parser::CliArgs;
 use crate::configuration::get_init_config_file_text;
 use crate::configuration::*;
 use crate::environment::Environment;
+use crate::paths::get_and_resolve_file_paths;
 use crate::plugins::output_plugin_config_diagnostics;
 use crate::plugins::read_info_file;
 use crate::plugins::read_update_url;
+use crate::plugins::resolve_plugin_range;
 use crate::plugins::resolve_plugins;
 use crate::plugins::InfoFilePluginInfo;
 use crate::plugins::Plugin;
+use crate::plugins::PluginRangeReference;
 use crate::plugins::PluginResolver;
 use crate::plugins::PluginSourceReference;
+use crate::utils::get_sha256_checksum;
 use crate::utils::pretty_print_json_text;
 use crate::utils::CachedDownloader;
 use crate::utils::ErrorCountLogger;
 use crate::utils::PathSource;
 
+use super::update_preview::preview_plugin_update;
+
 pub fn init_config_file(environment: &impl Environment, config_arg: &Option<String>) -> Result<()> {
   let config_file_path = get_config_path(config_arg)?;
   return if !environment.path_exists(&config_file_path) {
@@ -166,14 +172,30 @@ pub async fn update_plugins_config_file<TEnvironment: Environment>(
   environment: &TEnvironment,
   plugin_resolver: &PluginResolver<TEnvironment>,
   no_prompt: bool,
+  preview: bool,
+  lockfile: bool,
 ) -> Result<()> {
+  // pin the version ranges first so the configuration can be resolved
+  let locked_plugins = update_plugin_ranges_in_lockfile(args, environment, no_prompt)?;
+  let should_pin_plugins = match resolve_main_config_remote_plugins(args, environment)? {
+    // a lockfile that only pins version ranges stays that way unless requested
+    Some(remote_plugins) => lockfile || read_lockfile(&remote_plugins.lockfile_path, environment)?.has_checksums(),
+    None => false,
+  };
+  if should_pin_plugins {
+    // refresh out of date pins so they don't fail resolving the configuration
+    pin_plugins_in_lockfile(args, environment, plugin_resolver).await?;
+  }
   let config = resolve_config_from_args(args, environment)?;
-  let config_path = match config.resolved_path.source {
-    PathSource::Local(source) => source.path,
+  let config_path = match &config.resolved_path.source {
+    PathSource::Local(source) => source.path.clone(),
     PathSource::Remote(_) => bail!("Cannot update plugins in a remote configuration."),
   };
   let mut file_text = environment.read_file(&config_path)?;
-  let plugins_to_update = get_plugins_to_update(environment, plugin_resolver, config.plugins).await?;
+  // plugins pinned in the lockfile are updated within their range above
+  let plugins = config.plugins.iter().filter(|plugin| !locked_plugins.contains(plugin)).cloned().collect();
+  let plugins_to_update = get_plugins_to_update(environment, plugin_resolver, plugins).await?;
+  let mut preview_file_paths = None;
 
   for result in plugins_to_update {
     match result {
@@ -193,6 +215,15 @@ pub async fn update_plugins_config_file<TEnvironment: Environment>(
 
         if should_update {
           environment.log_stderr(&format!("Updating {} {} to {}...", info.name, info.old_version, info.new_version));
+          if preview {
+            if preview_file_paths.is_none() {
+              preview_file_paths = Some(get_and_resolve_file_paths(&config, &Default::default(), environment).await?);
+            }
+            let file_paths = preview_file_paths.as_ref().unwrap();
+            if let Err(err) = preview_plugin_update(&info, &config, file_paths, environment, plugin_resolver).await {
+              environment.log_stderr(&format!("Error previewing the update of {}: {:#}", info.name, err));
+            }
+          }
           file_text = update_plugin_in_config(&file_text, info);
         }
       }
@@ -204,9 +235,152 @@ pub async fn update_plugins_config_file<TEnvironment: Environment>(
 
   environment.write_file(&config_path, &file_text)?;
 
+  if should_pin_plugins {
+    pin_plugins_in_lockfile(args, environment, plugin_resolver).await?;
+  }
+
+  Ok(())
+}
+
+/// Pins the url, version, and checksum of the remote plugins in the lockfile.
+async fn pin_plugins_in_lockfile<TEnvironment: Environment>(
+  args: &CliArgs,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+) -> Result<()> {
+  let MainConfigRemotePlugins {
+    lockfile_path,
+    plugins: remote_plugins,
+  } = match resolve_main_config_remote_plugins(args, environment)? {
+    Some(value) => value,
+    None => return Ok(()),
+  };
+  let old_lockfile = read_lockfile(&lockfile_path, environment)?;
+  let mut new_lockfile = Lockfile::default();
+
+  // the version ranges were resolved beforehand, so only ensure they have a checksum
+  for (text, plugin) in old_lockfile.plugins.iter() {
+    if PluginRangeReference::parse(text)?.is_some() {
+      let mut plugin = plugin.clone();
+      if plugin.checksum.is_none() {
+        plugin.checksum = Some(get_sha256_checksum(&environment.download_file_err_404(&plugin.url)?));
+      }
+      new_lockfile.plugins.insert(text.clone(), plugin);
+    }
+  }
+
+  for (text, reference) in remote_plugins {
+    let url = match &reference.path_source {
+      PathSource::Remote(source) => source.url.to_string(),
+      PathSource::Local(_) => continue,
+    };
+    if let Some(old_plugin) = old_lockfile.plugins.get(&text) {
+      let is_up_to_date = old_plugin.url == url && old_plugin.checksum.is_some() && (reference.checksum.is_none() || reference.checksum == old_plugin.checksum);
+      if is_up_to_date {
+        new_lockfile.plugins.insert(text, old_plugin.clone());
+        continue;
+      }
+    }
+    let checksum = match &reference.checksum {
+      Some(checksum) => checksum.clone(),
+      None => get_sha256_checksum(&environment.download_file_err_404(&url)?),
+    };
+    let plugin = plugin_resolver
+      .resolve_plugin(&PluginSourceReference {
+        path_source: reference.path_source.clone(),
+        checksum: Some(checksum.clone()),
+      })
+      .await?;
+    environment.log_stderr(&format!("Pinning {} {} in the lockfile...", plugin.name(), plugin.version()));
+    new_lockfile.plugins.insert(
+      text,
+      LockfilePlugin {
+        version: plugin.version().to_string(),
+        url,
+        checksum: Some(checksum),
+      },
+    );
+  }
+
+  if new_lockfile != old_lockfile || !environment.path_exists(&lockfile_path) {
+    write_lockfile(&lockfile_path, &new_lockfile, environment)?;
+  }
   Ok(())
 }
 
+fn update_plugin_ranges_in_lockfile(args: &CliArgs, environment: &impl Environment, no_prompt: bool) -> Result<Vec<PluginSourceReference>> {
+  let (lockfile_path, range_references) = resolve_main_config_plugin_ranges(args, environment)?;
+  let old_lockfile = read_lockfile(&lockfile_path, environment)?;
+  let mut new_lockfile = Lockfile::default();
+  // keep the pinned plugins that aren't version ranges
+  for (text, plugin) in old_lockfile.plugins.iter() {
+    if PluginRangeReference::parse(text)?.is_none() {
+      new_lockfile.plugins.insert(text.clone(), plugin.clone());
+    }
+  }
+
+  for reference in range_references {
+    let old_plugin = old_lockfile.plugins.get(&reference.text);
+    let new_plugin = match resolve_plugin_range(environment, &reference) {
+      Ok(info) => LockfilePlugin {
+        version: info.version,
+        url: info.url,
+        checksum: info.checksum,
+      },
+      Err(err) => {
+        environment.log_stderr(&format!("Error updating plugin {}: {:#}", reference.text, err));
+        if let Some(old_plugin) = old_plugin {
+          new_lockfile.plugins.insert(reference.text, old_plugin.clone());
+        }
+        continue;
+      }
+    };
+    let plugin = match old_plugin {
+      // keep the checksum pinned with `--lockfile` when the registry doesn't provide one
+      Some(old_plugin) if old_plugin.url == new_plugin.url && (new_plugin.checksum.is_none() || old_plugin.checksum == new_plugin.checksum) => {
+        old_plugin.clone()
+      }
+      Some(old_plugin) => {
+        let is_wasm = new_plugin.url.to_lowercase().ends_with(".wasm");
+        let should_update = if is_wasm || no_prompt {
+          true
+        } else {
+          // prompt for security reasons
+          environment.log_stderr(&format!(
+            "The process plugin {} {} has a new url: {}",
+            reference.name,
+            old_plugin.version,
+            new_plugin.as_source_reference()?.to_full_string(),
+          ));
+          environment.confirm("Do you wish to update it?", false)?
+        };
+        if should_update {
+          environment.log_stderr(&format!("Updating {} {} to {}...", reference.name, old_plugin.version, new_plugin.version));
+          new_plugin
+        } else {
+          old_plugin.clone()
+        }
+      }
+      None => {
+        environment.log_stderr(&format!("Pinning {} to {}...", reference.text, new_plugin.version));
+        new_plugin
+      }
+    };
+    new_lockfile.plugins.insert(reference.text, plugin);
+  }
+
+  if new_lockfile != old_lockfile {
+    write_lockfile(&lockfile_path, &new_lockfile, environment)?;
+  }
+
+  new_lockfile
+    .plugins
+    .iter()
+    .filter(|(text, _)| matches!(PluginRangeReference::parse(text), Ok(Some(_))))
+    .map(|(_, plugin)| plugin.as_source_reference())
+    .collect()
+}
+
 struct PluginUpdateError {
   name: String,
   error: Error,
@@ -315,6 +489,80 @@ pub async fn output_resolved_config<TEnvironment: Environment>(
   Ok(())
 }
 
+pub async fn explain_config<TEnvironment: Environment>(
+  args: &CliArgs,
+  key: Option<&String>,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+) -> Result<()> {
+  let mut sources = get_config_value_sources(args, environment)?;
+
+  // the plugins resolve the values that weren't specified
+  let config = resolve_config_from_args(args, environment)?;
+  let plugins = resolve_plugins(args, &config, environment, plugin_resolver).await?;
+  for plugin in plugins {
+    let initialized_plugin = plugin.initialize().await?;
+    let text = initialized_plugin.resolved_config().await?;
+    let resolved_config = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&text)?;
+    for (name, value) in resolved_config {
+      let plugin_key = format!("{}.{}", plugin.config_key(), name);
+      if sources.iter().any(|source| source.key == plugin_key) {
+        continue;
+      }
+      let origin = if sources.iter().any(|source| source.key == name) {
+        ConfigValueOrigin::Global(name)
+      } else {
+        ConfigValueOrigin::Default
+      };
+      sources.push(ConfigValueSource {
+        key: plugin_key,
+        value: value.to_string(),
+        origin,
+      });
+    }
+  }
+
+  if let Some(key) = key {
+    let plugin_prefix = format!("{}.", key);
+    sources.retain(|source| source.key == *key || source.key.starts_with(&plugin_prefix));
+    if sources.is_empty() {
+      bail!("Could not find the configuration property '{}'.", key);
+    }
+  }
+
+  sources.sort_by(|a, b| a.key.cmp(&b.key));
+  for source in sources {
+    environment.log(&format!("{}: {}\n  {}", source.key, source.value, source.origin));
+  }
+
+  Ok(())
+}
+
+pub fn flatten_config(args: &CliArgs, output: Option<&String>, environment: &impl Environment) -> Result<()> {
+  let config = resolve_config_from_args(args, environment)?;
+  let mut plugins = Vec::with_capacity(config.plugins.len());
+  for plugin in config.plugins.iter() {
+    plugins.push(match (&plugin.path_source, &plugin.checksum) {
+      // pin remote plugins by their checksum
+      (PathSource::Remote(remote), None) => {
+        let bytes = environment.download_file_err_404(remote.url.as_str())?;
+        format!("{}@{}", plugin.path_source, get_sha256_checksum(&bytes))
+      }
+      _ => plugin.to_full_string(),
+    });
+  }
+  let text = get_flattened_config_text(&config, plugins)?;
+  match output {
+    Some(output) => {
+      let output_path = environment.cwd().join(output);
+      environment.write_file(&output_path, &format!("{}\n", text))?;
+      environment.log_stderr(&format!("Wrote {}", output_path.display()));
+    }
+    None => environment.log_machine_readable(&text),
+  }
+  Ok(())
+}
+
 async fn get_config_file_plugins<TEnvironment: Environment>(
   plugin_resolver: &PluginResolver<TEnvironment>,
   current_plugins: Vec<PluginSourceReference>,
@@ -960,6 +1208,160 @@ mod test {
     );
   }
 
+  #[test]
+  fn config_update_should_preview_formatting_differences() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .with_info_file(|_| {})
+      .with_default_config(|config| {
+        config.add_remote_wasm_plugin().add_includes("**/*.txt");
+      })
+      .write_file("/file.txt", "text")
+      .add_remote_file(
+        "https://plugins.dprint.dev/dprint/test-plugin/latest.json",
+        &json!({
+          "schemaVersion": 1,
+          "url": "https://plugins.dprint.dev/test-plugin-2.wasm",
+          "version": "0.2.0"
+        })
+        .to_string(),
+      )
+      .initialize()
+      .build();
+    environment.add_remote_file("https://plugins.dprint.dev/test-plugin-2.wasm", test_helpers::WASM_PLUGIN_BYTES);
+    run_test_cli(vec!["config", "update", "--preview"], &environment).unwrap();
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec![
+        "Updating test-plugin 0.1.0 to 0.2.0...",
+        "Compiling https://plugins.dprint.dev/test-plugin-2.wasm",
+        "Previewed test-plugin 0.1.0 to 0.2.0 on 1 sample file(s): no formatting differences.",
+      ]
+    );
+    assert!(environment
+      .read_file("/dprint.json")
+      .unwrap()
+      .contains("https://plugins.dprint.dev/test-plugin-2.wasm"));
+  }
+
+  #[test]
+  fn config_update_should_pin_plugin_ranges_in_lockfile() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .with_info_file(|_| {})
+      .write_file("/dprint.json", r#"{ "plugins": ["test-plugin@^0.1"] }"#)
+      .add_remote_file(
+        "https://plugins.dprint.dev/dprint/test-plugin/versions.json",
+        &json!({
+          "schemaVersion": 1,
+          "versions": [
+            { "url": "https://plugins.dprint.dev/test-plugin.wasm", "version": "0.1.0" },
+            { "url": "https://plugins.dprint.dev/test-plugin-2.wasm", "version": "0.2.0" }
+          ]
+        })
+        .to_string(),
+      )
+      .build();
+    run_test_cli(vec!["config", "update"], &environment).unwrap();
+    assert_eq!(environment.take_stderr_messages(), vec!["Pinning test-plugin@^0.1 to 0.1.0..."]);
+    assert_eq!(
+      environment.read_file("/dprint.lock").unwrap(),
+      r#"{
+  "version": 1,
+  "plugins": {
+    "test-plugin@^0.1": {
+      "version": "0.1.0",
+      "url": "https://plugins.dprint.dev/test-plugin.wasm"
+    }
+  }
+}
+"#
+    );
+    // the configuration file keeps the range
+    assert_eq!(environment.read_file("/dprint.json").unwrap(), r#"{ "plugins": ["test-plugin@^0.1"] }"#);
+
+    // now a new version within the range is published
+    environment.add_remote_file_bytes(
+      "https://plugins.dprint.dev/dprint/test-plugin/versions.json",
+      json!({
+        "schemaVersion": 1,
+        "versions": [
+          { "url": "https://plugins.dprint.dev/test-plugin.wasm", "version": "0.1.0" },
+          { "url": "https://plugins.dprint.dev/test-plugin-1-1.wasm", "version": "0.1.1" },
+          { "url": "https://plugins.dprint.dev/test-plugin-2.wasm", "version": "0.2.0" }
+        ]
+      })
+      .to_string()
+      .into_bytes(),
+    );
+    run_test_cli(vec!["config", "update"], &environment).unwrap();
+    assert_eq!(environment.take_stderr_messages(), vec!["Updating test-plugin 0.1.0 to 0.1.1..."]);
+    assert!(environment
+      .read_file("/dprint.lock")
+      .unwrap()
+      .contains("\"url\": \"https://plugins.dprint.dev/test-plugin-1-1.wasm\""));
+  }
+
+  #[test]
+  fn config_update_should_pin_plugin_checksums_in_lockfile() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .with_info_file(|_| {})
+      .with_default_config(|config| {
+        config.add_remote_wasm_plugin();
+      })
+      .add_remote_file(
+        "https://plugins.dprint.dev/dprint/test-plugin/latest.json",
+        &json!({
+          "schemaVersion": 1,
+          "url": "https://plugins.dprint.dev/test-plugin.wasm",
+          "version": "0.1.0"
+        })
+        .to_string(),
+      )
+      .initialize()
+      .build();
+    let config_text = environment.read_file("/dprint.json").unwrap();
+    let err = run_test_cli(vec!["output-resolved-config", "--frozen"], &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      "The plugin 'https://plugins.dprint.dev/test-plugin.wasm' is not pinned in /dprint.lock. Run `dprint config update --lockfile` to pin it."
+    );
+
+    run_test_cli(vec!["config", "update", "--lockfile"], &environment).unwrap();
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec![
+        // the plugin cached before it was pinned is verified
+        "Compiling https://plugins.dprint.dev/test-plugin.wasm",
+        "Pinning test-plugin 0.1.0 in the lockfile...",
+      ]
+    );
+    assert_eq!(
+      environment.read_file("/dprint.lock").unwrap(),
+      format!(
+        r#"{{
+  "version": 1,
+  "plugins": {{
+    "https://plugins.dprint.dev/test-plugin.wasm": {{
+      "version": "0.1.0",
+      "url": "https://plugins.dprint.dev/test-plugin.wasm",
+      "checksum": "{}"
+    }}
+  }}
+}}
+"#,
+        test_helpers::get_test_wasm_plugin_checksum()
+      )
+    );
+    assert_eq!(environment.read_file("/dprint.json").unwrap(), config_text);
+    run_test_cli(vec!["output-resolved-config", "--frozen"], &environment).unwrap();
+
+    // nothing changes when running it again without the flag
+    run_test_cli(vec!["config", "update"], &environment).unwrap();
+    assert!(environment.take_stderr_messages().is_empty());
+  }
+
   #[test]
   fn should_output_resolved_config() {
     let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_and_process_plugin().build();
@@ -981,6 +1383,89 @@ mod test {
     );
   }
 
+  #[test]
+  fn should_explain_config() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file(
+        "/dprint.json",
+        r#"{
+  "extends": "./base.json",
+  "test-plugin": {
+    "ending": "custom"
+  },
+  "plugins": ["https://plugins.dprint.dev/test-plugin.wasm"]
+}"#,
+      )
+      .write_file("/base.json", "{\n  \"lineWidth\": 100\n}")
+      .build();
+    run_test_cli(vec!["config", "explain"], &environment).unwrap();
+    assert_eq!(
+      environment.take_stdout_messages(),
+      vec![
+        "lineWidth: 100\n  /base.json:2",
+        "plugins: \"https://plugins.dprint.dev/test-plugin.wasm\"\n  /dprint.json:6",
+        "test-plugin.ending: \"custom\"\n  /dprint.json:4",
+        "test-plugin.lineWidth: 100\n  global \"lineWidth\" property",
+      ]
+    );
+
+    run_test_cli(vec!["config", "explain", "test-plugin"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages().len(), 2);
+
+    let err = run_test_cli(vec!["config", "explain", "indentWidth"], &environment).err().unwrap();
+    assert_eq!(err.to_string(), "Could not find the configuration property 'indentWidth'.");
+  }
+
+  #[test]
+  fn should_flatten_config() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file(
+        "/dprint.json",
+        r#"{
+  "extends": "./base.json",
+  "includes": ["**/*.txt"],
+  "test-plugin": {
+    "ending": "custom"
+  },
+  "plugins": ["https://plugins.dprint.dev/test-plugin.wasm"]
+}"#,
+      )
+      .write_file(
+        "/base.json",
+        r#"{
+  "lineWidth": 100,
+  "excludes": ["**/node_modules"],
+  "test-plugin": {
+    "ending": "other",
+    "lineWidth": 90
+  }
+}"#,
+      )
+      .build();
+    run_test_cli(vec!["config", "flatten"], &environment).unwrap();
+    assert_eq!(
+      environment.take_stdout_messages(),
+      vec![format!(
+        concat!(
+          "{{\n",
+          "  \"includes\": [\n",
+          "    \"**/*.txt\"\n",
+          "  ],\n",
+          "  \"test-plugin\": {{\n",
+          "    \"ending\": \"custom\",\n",
+          "    \"lineWidth\": 90\n",
+          "  }},\n",
+          "  \"lineWidth\": 100,\n",
+          "  \"plugins\": [\n",
+          "    \"https://plugins.dprint.dev/test-plugin.wasm@{}\"\n",
+          "  ]\n",
+          "}}",
+        ),
+        test_helpers::get_test_wasm_plugin_checksum()
+      )]
+    );
+  }
+
   #[test]
   fn should_output_resolved_config_no_plugins() {
     let environment = TestEnvironmentBuilder::new().with_default_config(|_| {}).build();
@@ -0,0 +1,212 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::arg_parser::CliArgs;
+use crate::arg_parser::CoverageSubCommand;
+use crate::configuration::resolve_config_from_args;
+use crate::environment::Environment;
+use crate::paths::get_and_resolve_file_paths;
+use crate::paths::get_file_paths_by_plugins;
+use crate::patterns::get_patterns_as_glob_matcher;
+use crate::plugins::read_info_file;
+use crate::plugins::resolve_plugins_and_err_if_empty;
+use crate::plugins::InfoFilePluginInfo;
+use crate::plugins::PluginResolver;
+
+/// File types with at least this many unformatted files are highlighted.
+const SIGNIFICANT_UNFORMATTED_FILES_COUNT: usize = 5;
+
+#[derive(Default)]
+struct FileTypeCoverage {
+  formatted: usize,
+  /// Files a plugin formats, but that aren't matched by the includes.
+  not_included: usize,
+  /// Files that no plugin formats.
+  unmatched: usize,
+}
+
+impl FileTypeCoverage {
+  pub fn total(&self) -> usize {
+    self.formatted + self.not_included + self.unmatched
+  }
+
+  pub fn unformatted(&self) -> usize {
+    self.not_included + self.unmatched
+  }
+
+  pub fn display(&self) -> String {
+    let mut parts = Vec::new();
+    if self.formatted > 0 {
+      parts.push(format!("{} formatted", self.formatted));
+    }
+    if self.not_included > 0 {
+      parts.push(format!("{} not in includes", self.not_included));
+    }
+    if self.unmatched > 0 {
+      parts.push(format!("{} unmatched", self.unmatched));
+    }
+    parts.join(", ")
+  }
+}
+
+/// Outputs how many files of each file type are formatted (`dprint coverage`).
+pub async fn output_coverage<TEnvironment: Environment>(
+  cmd: &CoverageSubCommand,
+  args: &CliArgs,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+) -> Result<()> {
+  let config = resolve_config_from_args(args, environment)?;
+  let plugins = resolve_plugins_and_err_if_empty(args, &config, environment, plugin_resolver).await?;
+  // glob every file that isn't excluded in order to find the files that aren't formatted
+  let mut all_files_config = config.clone();
+  all_files_config.includes = vec!["**/*".to_string()];
+  let file_paths = get_and_resolve_file_paths(&all_files_config, &cmd.patterns, environment).await?;
+  if file_paths.is_empty() {
+    environment.log("No files found.");
+    return Ok(());
+  }
+  // the file patterns provided on the command line replace the includes
+  let includes_matcher = if cmd.patterns.file_patterns.is_empty() {
+    Some(get_patterns_as_glob_matcher(&config.includes, &config.base_path)?)
+  } else {
+    None
+  };
+  let plugin_file_paths = get_file_paths_by_plugins(&plugins, file_paths.clone(), &config.base_path, &config.overrides, &cmd.patterns.associations)?
+    .into_values()
+    .flatten()
+    .collect::<HashSet<_>>();
+
+  let mut coverage: BTreeMap<String, FileTypeCoverage> = BTreeMap::new();
+  for file_path in file_paths.iter() {
+    let file_type_coverage = coverage.entry(get_file_type(file_path)).or_default();
+    if !plugin_file_paths.contains(file_path) {
+      file_type_coverage.unmatched += 1;
+    } else if includes_matcher.as_ref().map(|matcher| matcher.matches(file_path)).unwrap_or(true) {
+      file_type_coverage.formatted += 1;
+    } else {
+      file_type_coverage.not_included += 1;
+    }
+  }
+
+  // the most common file types first
+  let mut file_types = coverage.into_iter().collect::<Vec<_>>();
+  file_types.sort_by(|a, b| b.1.total().cmp(&a.1.total()).then_with(|| a.0.cmp(&b.0)));
+  let formatted_count = file_types.iter().map(|(_, coverage)| coverage.formatted).sum::<usize>();
+  let mut text = format!(
+    "Formatted {} of {} files ({}%).\n\nFile types:\n",
+    formatted_count,
+    file_paths.len(),
+    formatted_count * 100 / file_paths.len()
+  );
+  let name_width = file_types.iter().map(|(name, _)| name.chars().count()).max().unwrap_or(0);
+  for (name, coverage) in file_types.iter() {
+    text.push_str(&format!("  {:<width$}  {}\n", name, coverage.display(), width = name_width));
+  }
+
+  let significant_file_types = file_types
+    .iter()
+    .filter(|(_, coverage)| coverage.unformatted() >= SIGNIFICANT_UNFORMATTED_FILES_COUNT)
+    .collect::<Vec<_>>();
+  if !significant_file_types.is_empty() {
+    let info_file = match read_info_file(environment) {
+      Ok(info_file) => Some(info_file),
+      Err(err) => {
+        log_verbose!(environment, "Error downloading info file. {:#}", err);
+        None
+      }
+    };
+    text.push_str("\nUnformatted file types:\n");
+    for (name, coverage) in significant_file_types {
+      let suggestion = if coverage.not_included > 0 {
+        "formatted by a configured plugin, but not matched by the includes".to_string()
+      } else {
+        let suggested_plugins = info_file
+          .iter()
+          .flat_map(|info_file| info_file.latest_plugins.iter())
+          .filter(|plugin| is_plugin_for_file_type(plugin, name))
+          .map(|plugin| format!("{} ({})", plugin.name, plugin.url))
+          .collect::<Vec<_>>();
+        if suggested_plugins.is_empty() {
+          "no plugin found".to_string()
+        } else {
+          format!("try {}", suggested_plugins.join(", "))
+        }
+      };
+      text.push_str(&format!("  {} ({} files): {}\n", name, coverage.unformatted(), suggestion));
+    }
+  }
+
+  environment.log(text.trim_end());
+  Ok(())
+}
+
+/// Gets the lowercase extension of the file with a leading period or its file name when it has none.
+fn get_file_type(file_path: &Path) -> String {
+  match file_path.extension() {
+    Some(extension) => format!(".{}", extension.to_string_lossy().to_lowercase()),
+    None => file_path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+  }
+}
+
+fn is_plugin_for_file_type(plugin: &InfoFilePluginInfo, file_type: &str) -> bool {
+  match file_type.strip_prefix('.') {
+    Some(extension) => plugin.file_extensions.iter().any(|e| e.eq_ignore_ascii_case(extension)),
+    None => plugin.file_names.iter().any(|name| name == file_type),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use crate::environment::TestEnvironmentBuilder;
+  use crate::environment::TestInfoFilePlugin;
+  use crate::test_helpers::run_test_cli;
+
+  #[test]
+  fn should_output_coverage_by_file_type() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .with_local_config("/project/dprint.json", |c| {
+        c.add_includes("src/**/*.txt").add_remote_wasm_plugin();
+      })
+      .with_info_file(|info| {
+        info.add_plugin(TestInfoFilePlugin {
+          name: "dprint-plugin-ruff".to_string(),
+          version: "0.1.0".to_string(),
+          url: "https://plugins.dprint.dev/ruff-0.1.0.wasm".to_string(),
+          file_extensions: vec!["py".to_string()],
+          ..Default::default()
+        });
+      })
+      .set_cwd("/project")
+      .write_file("/project/src/a.txt", "")
+      .write_file("/project/src/sub/b.txt", "")
+      .write_file("/project/other.txt", "")
+      .write_file("/project/scripts/1.py", "")
+      .write_file("/project/scripts/2.py", "")
+      .write_file("/project/scripts/3.py", "")
+      .write_file("/project/scripts/4.PY", "")
+      .write_file("/project/scripts/5.py", "")
+      .initialize()
+      .build();
+    run_test_cli(vec!["coverage"], &environment).unwrap();
+    assert_eq!(
+      environment.take_stdout_messages(),
+      vec![concat!(
+        "Formatted 2 of 9 files (22%).\n",
+        "\n",
+        "File types:\n",
+        "  .py    5 unmatched\n",
+        "  .txt   2 formatted, 1 not in includes\n",
+        "  .json  1 unmatched\n",
+        "\n",
+        "Unformatted file types:\n",
+        "  .py (5 files): try dprint-plugin-ruff (https://plugins.dprint.dev/ruff-0.1.0.wasm)",
+      )]
+    );
+  }
+}
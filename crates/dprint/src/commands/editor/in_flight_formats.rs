@@ -0,0 +1,131 @@
+use parking_lot::Mutex;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+struct InFlightFormat {
+  /// Id of the message that started the format.
+  leader_message_id: u32,
+  key: u64,
+  file_path: PathBuf,
+  /// Cancelled once none of the requests need the result.
+  token: Arc<CancellationToken>,
+  /// The messages waiting on the result along with their own tokens.
+  requests: Vec<(u32, Arc<CancellationToken>)>,
+}
+
+/// Tracks the format requests of the editor so that identical requests
+/// arriving concurrently are only formatted once, and a request for a
+/// document makes the previous requests for that document stale.
+#[derive(Default)]
+pub struct InFlightFormats {
+  formats: Mutex<Vec<InFlightFormat>>,
+}
+
+impl InFlightFormats {
+  /// Adds a request with a key of its path, text, range, and override config.
+  ///
+  /// Returns the token to format with when the request should be formatted or
+  /// `None` when it will receive the result of an identical request in flight.
+  pub fn add(&self, key: u64, file_path: &Path, message_id: u32, message_token: Arc<CancellationToken>) -> Option<Arc<CancellationToken>> {
+    let mut formats = self.formats.lock();
+    // only the latest text of a document is worth formatting
+    for format in formats.iter() {
+      if format.key != key && format.file_path == file_path {
+        format.token.cancel();
+        for (_, token) in format.requests.iter() {
+          token.cancel();
+        }
+      }
+    }
+    if let Some(format) = formats.iter_mut().find(|format| format.key == key && !format.token.is_cancelled()) {
+      format.requests.push((message_id, message_token));
+      return None;
+    }
+    let token = Arc::new(CancellationToken::new());
+    formats.push(InFlightFormat {
+      leader_message_id: message_id,
+      key,
+      file_path: file_path.to_path_buf(),
+      token: token.clone(),
+      requests: vec![(message_id, message_token)],
+    });
+    Some(token)
+  }
+
+  /// Cancels the request, which cancels the format when no other requests are waiting on it.
+  pub fn cancel(&self, message_id: u32) {
+    let formats = self.formats.lock();
+    for format in formats.iter() {
+      if let Some((_, token)) = format.requests.iter().find(|(id, _)| *id == message_id) {
+        token.cancel();
+        if format.requests.iter().all(|(_, token)| token.is_cancelled()) {
+          format.token.cancel();
+        }
+        return;
+      }
+    }
+  }
+
+  /// Removes the format started by the message, returning the requests to respond to.
+  pub fn finish(&self, leader_message_id: u32) -> Vec<(u32, Arc<CancellationToken>)> {
+    let mut formats = self.formats.lock();
+    match formats.iter().position(|format| format.leader_message_id == leader_message_id) {
+      Some(index) => formats.remove(index).requests,
+      None => Vec::new(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  fn get_ids(requests: Vec<(u32, Arc<CancellationToken>)>) -> Vec<(u32, bool)> {
+    requests.into_iter().map(|(id, token)| (id, token.is_cancelled())).collect()
+  }
+
+  #[test]
+  fn should_share_identical_formats() {
+    let formats = InFlightFormats::default();
+    let token = formats.add(1, Path::new("/file.txt"), 1, Default::default()).unwrap();
+    assert!(formats.add(1, Path::new("/file.txt"), 2, Default::default()).is_none());
+    // a different document doesn't affect it
+    assert!(formats.add(2, Path::new("/other.txt"), 3, Default::default()).is_some());
+
+    // cancelling one of the requests doesn't cancel the format
+    formats.cancel(2);
+    assert!(!token.is_cancelled());
+    assert_eq!(get_ids(formats.finish(1)), vec![(1, false), (2, true)]);
+    assert_eq!(get_ids(formats.finish(3)), vec![(3, false)]);
+    assert_eq!(get_ids(formats.finish(1)), vec![]);
+  }
+
+  #[test]
+  fn should_cancel_format_when_all_requests_cancelled() {
+    let formats = InFlightFormats::default();
+    let token = formats.add(1, Path::new("/file.txt"), 1, Default::default()).unwrap();
+    assert!(formats.add(1, Path::new("/file.txt"), 2, Default::default()).is_none());
+    formats.cancel(1);
+    formats.cancel(2);
+    assert!(token.is_cancelled());
+  }
+
+  #[test]
+  fn should_supersede_formats_of_older_text() {
+    let formats = InFlightFormats::default();
+    let old_token = formats.add(1, Path::new("/file.txt"), 1, Default::default()).unwrap();
+    let new_token = formats.add(2, Path::new("/file.txt"), 2, Default::default()).unwrap();
+    assert!(old_token.is_cancelled());
+    assert!(!new_token.is_cancelled());
+    // going back to the old text doesn't join the stale format
+    assert!(formats.add(1, Path::new("/file.txt"), 3, Default::default()).is_some());
+    assert!(new_token.is_cancelled());
+    assert_eq!(get_ids(formats.finish(1)), vec![(1, true)]);
+    assert_eq!(get_ids(formats.finish(2)), vec![(2, true)]);
+    assert_eq!(get_ids(formats.finish(3)), vec![(3, false)]);
+  }
+}
@@ -69,6 +69,8 @@ impl EditorMessage {
         EditorMessageBody::FormatResponse(message_id, data)
       }
       8 => EditorMessageBody::CancelFormat(reader.read_u32()?),
+      9 => EditorMessageBody::Warmup,
+      10 => EditorMessageBody::FormatCancelledResponse(reader.read_u32()?),
       _ => {
         let data = reader.read_bytes(body_length as usize)?;
         EditorMessageBody::Unknown(message_kind, data)
@@ -123,6 +125,10 @@ impl Message for EditorMessage {
       EditorMessageBody::CancelFormat(message_id) => {
         builder.add_number(*message_id);
       }
+      EditorMessageBody::Warmup => {}
+      EditorMessageBody::FormatCancelledResponse(message_id) => {
+        builder.add_number(*message_id);
+      }
       EditorMessageBody::Unknown(_, _) => unreachable!(), // should never be written
     }
     builder.write(writer)?;
@@ -202,6 +208,8 @@ pub enum EditorMessageBody {
   Format(FormatEditorMessageBody),
   FormatResponse(u32, Option<Vec<u8>>),
   CancelFormat(u32),
+  Warmup,
+  FormatCancelledResponse(u32),
   Unknown(u32, Vec<u8>),
 }
 
@@ -217,6 +225,8 @@ impl EditorMessageBody {
       EditorMessageBody::Format(_) => 6,
       EditorMessageBody::FormatResponse(_, _) => 7,
       EditorMessageBody::CancelFormat(_) => 8,
+      EditorMessageBody::Warmup => 9,
+      EditorMessageBody::FormatCancelledResponse(_) => 10,
       EditorMessageBody::Unknown(_, _) => unreachable!(),
     }
   }
@@ -1,6 +1,6 @@
 use anyhow::anyhow;
+use anyhow::bail;
 use anyhow::Result;
-use dprint_core::communication::ArcIdStore;
 use dprint_core::communication::IdGenerator;
 use dprint_core::communication::MessageReader;
 use dprint_core::communication::MessageWriter;
@@ -9,13 +9,15 @@ use dprint_core::plugins::Host;
 use dprint_core::plugins::HostFormatRequest;
 use std::io::Read;
 use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Semaphore;
 use tokio_util::sync::CancellationToken;
 
 use dprint_core::plugins::process::start_parent_process_checker_task;
 
-mod messages;
+mod in_flight_formats;
+pub(super) mod messages;
 
 use crate::arg_parser::CliArgs;
 use crate::arg_parser::EditorServiceSubCommand;
@@ -26,11 +28,17 @@ use crate::environment::Environment;
 use crate::patterns::FileMatcher;
 use crate::plugins::get_plugins_from_args;
 use crate::plugins::resolve_plugins;
+use crate::plugins::GetPluginResult;
 use crate::plugins::PluginResolver;
+use crate::plugins::PluginWrapper;
 use crate::plugins::PluginsCollection;
+use crate::utils::get_bytes_hash;
+use crate::utils::ErrorCountLogger;
 
+use self::in_flight_formats::InFlightFormats;
 use self::messages::EditorMessage;
 use self::messages::EditorMessageBody;
+use self::messages::FormatEditorMessageBody;
 
 pub async fn output_editor_info<TEnvironment: Environment>(
   args: &CliArgs,
@@ -104,10 +112,10 @@ pub async fn run_editor_service<TEnvironment: Environment>(
 struct EditorContext {
   pub id_generator: IdGenerator,
   pub writer: SingleThreadMessageWriter<EditorMessage>,
-  pub cancellation_tokens: ArcIdStore<Arc<CancellationToken>>,
+  pub in_flight_formats: InFlightFormats,
 }
 
-struct EditorService<'a, TEnvironment: Environment> {
+pub(super) struct EditorService<'a, TEnvironment: Environment> {
   reader: MessageReader<Box<dyn Read + Send>>,
   config: Option<ResolvedConfig>,
   args: &'a CliArgs,
@@ -127,8 +135,20 @@ impl<'a, TEnvironment: Environment> EditorService<'a, TEnvironment> {
   ) -> Self {
     let stdin = environment.stdin();
     let stdout = environment.stdout();
-    let reader = MessageReader::new(stdin);
     let writer = SingleThreadMessageWriter::for_stdout(MessageWriter::new(stdout));
+    Self::new_with_io(args, environment, plugin_resolver, plugin_pools, stdin, writer)
+  }
+
+  /// Creates a service that communicates over the provided reader and writer instead of stdin and stdout.
+  pub(super) fn new_with_io(
+    args: &'a CliArgs,
+    environment: &'a TEnvironment,
+    plugin_resolver: &'a PluginResolver<TEnvironment>,
+    plugin_pools: Arc<PluginsCollection<TEnvironment>>,
+    reader: Box<dyn Read + Send>,
+    writer: SingleThreadMessageWriter<EditorMessage>,
+  ) -> Self {
+    let reader = MessageReader::new(reader);
     let max_cores = environment.max_threads();
     let concurrency_limiter = Arc::new(Semaphore::new(std::cmp::max(1, max_cores - 1)));
 
@@ -141,7 +161,7 @@ impl<'a, TEnvironment: Environment> EditorService<'a, TEnvironment> {
       plugins_collection: plugin_pools,
       context: Arc::new(EditorContext {
         id_generator: Default::default(),
-        cancellation_tokens: Default::default(),
+        in_flight_formats: Default::default(),
         writer,
       }),
       concurrency_limiter,
@@ -175,61 +195,82 @@ impl<'a, TEnvironment: Environment> EditorService<'a, TEnvironment> {
           if self.config.is_none() {
             self.ensure_latest_config().await?;
           }
-          let token = Arc::new(CancellationToken::new());
-          let request = HostFormatRequest {
-            file_path: body.file_path,
-            range: body.range,
-            override_config: if body.override_config.is_empty() {
-              Default::default()
-            } else {
-              match serde_json::from_slice(&body.override_config) {
-                Ok(config) => config,
-                Err(err) => {
-                  send_error_response(&self.context, message.id, anyhow!("Error deserializing override config. {:#}", err));
-                  continue;
-                }
-              }
-            },
-            file_text: match String::from_utf8(body.file_text) {
-              Ok(text) => text,
+          let file_path = match resolve_buffer_file_path(&body.file_path, self.environment) {
+            Ok(file_path) => file_path,
+            Err(err) => {
+              send_error_response(&self.context, message.id, err);
+              continue;
+            }
+          };
+          let key = get_format_request_key(&file_path, &body);
+          let override_config = if body.override_config.is_empty() {
+            Default::default()
+          } else {
+            match serde_json::from_slice(&body.override_config) {
+              Ok(config) => config,
               Err(err) => {
-                send_error_response(&self.context, message.id, anyhow!("Error decoding text to utf8. {:#}", err));
+                send_error_response(&self.context, message.id, anyhow!("Error deserializing override config. {:#}", err));
                 continue;
               }
-            },
+            }
+          };
+          let file_text = match String::from_utf8(body.file_text) {
+            Ok(text) => text,
+            Err(err) => {
+              send_error_response(&self.context, message.id, anyhow!("Error decoding text to utf8. {:#}", err));
+              continue;
+            }
+          };
+          let message_token = Arc::new(CancellationToken::new());
+          let token = match self.context.in_flight_formats.add(key, &file_path, message.id, message_token) {
+            Some(token) => token,
+            // an identical request is being formatted, so this receives its result
+            None => continue,
+          };
+          let request = HostFormatRequest {
+            file_path,
+            range: body.range,
+            override_config,
+            file_text,
             token: token.clone(),
           };
 
-          self.context.cancellation_tokens.store(message.id, token.clone());
           let plugins_collection = self.plugins_collection.clone();
           let context = self.context.clone();
           let concurrency_limiter = self.concurrency_limiter.clone();
           let _ignore = tokio::task::spawn(async move {
             let _permit = concurrency_limiter.acquire().await;
-            if token.is_cancelled() {
-              return;
-            }
+            let result = if token.is_cancelled() {
+              None
+            } else {
+              Some(plugins_collection.format(request).await)
+            };
 
-            let result = plugins_collection.format(request).await;
-            context.cancellation_tokens.take(message.id);
-            if token.is_cancelled() {
-              return;
+            for (message_id, message_token) in context.in_flight_formats.finish(message.id) {
+              let body = match &result {
+                // don't send back output that's stale
+                _ if message_token.is_cancelled() => EditorMessageBody::FormatCancelledResponse(message_id),
+                None => EditorMessageBody::FormatCancelledResponse(message_id),
+                Some(Ok(text)) => EditorMessageBody::FormatResponse(message_id, text.clone().map(|t| t.into_bytes())),
+                Some(Err(err)) => EditorMessageBody::Error(message_id, format!("{:#}", err).into_bytes()),
+              };
+              send_response_body(&context, body);
             }
-
-            let body = match result {
-              Ok(text) => EditorMessageBody::FormatResponse(message.id, text.map(|t| t.into_bytes())),
-              Err(err) => EditorMessageBody::Error(message.id, format!("{:#}", err).into_bytes()),
-            };
-            send_response_body(&context, body);
           });
         }
         EditorMessageBody::FormatResponse(_, _) => {
           send_error_response(&self.context, message.id, anyhow!("CLI cannot handle a FormatResponse message."));
         }
+        EditorMessageBody::FormatCancelledResponse(_) => {
+          send_error_response(&self.context, message.id, anyhow!("CLI cannot handle a FormatCancelledResponse message."));
+        }
         EditorMessageBody::CancelFormat(message_id) => {
-          if let Some(token) = self.context.cancellation_tokens.take(message_id) {
-            token.cancel();
-          }
+          self.context.in_flight_formats.cancel(message_id);
+        }
+        EditorMessageBody::Warmup => {
+          // do this on the same thread so it's done before the next format
+          let result = self.warmup().await;
+          handle_message(&self.context, message.id, || result.map(|_| EditorMessageBody::Success(message.id)));
         }
         EditorMessageBody::Unknown(message_kind, _) => {
           send_error_response(&self.context, message.id, anyhow!("Unknown message with kind: {}", message_kind));
@@ -238,31 +279,122 @@ impl<'a, TEnvironment: Environment> EditorService<'a, TEnvironment> {
     }
   }
 
+  /// Sets the configuration the plugins were last resolved with so that they're
+  /// only resolved again when the configuration changes.
+  pub(super) fn set_config(&mut self, config: Option<ResolvedConfig>) {
+    self.config = config;
+  }
+
+  pub(super) fn take_config(&mut self) -> Option<ResolvedConfig> {
+    self.config.take()
+  }
+
   async fn can_format(&mut self, file_path: &Path) -> Result<bool> {
     self.ensure_latest_config().await?;
 
     let file_matcher = FileMatcher::new(self.config.as_ref().unwrap(), &FilePatternArgs::default(), self.environment)?;
-    // canonicalize the file path, then check if it's in the list of file paths.
-    let resolved_file_path = self.environment.canonicalize(file_path)?;
+    // resolve the file path, then check if it's in the list of file paths.
+    let resolved_file_path = resolve_buffer_file_path(file_path, self.environment)?;
     log_verbose!(self.environment, "Checking can format: {}", resolved_file_path.display());
     Ok(file_matcher.matches_and_dir_not_ignored(&resolved_file_path))
   }
 
+  /// Resolves the configuration and creates an instance of each plugin so
+  /// the first format request doesn't need to wait on that.
+  async fn warmup(&mut self) -> Result<()> {
+    self.ensure_latest_config().await?;
+    let plugins = self.plugins_collection.get_plugins();
+    create_plugin_instances(&plugins, self.environment).await?;
+    log_verbose!(self.environment, "Warmed up {} plugin(s).", plugins.len());
+    Ok(())
+  }
+
   async fn ensure_latest_config(&mut self) -> Result<()> {
     let last_config = self.config.take();
-    let config = resolve_config_from_args(self.args, self.environment)?;
+    let config = resolve_latest_config(last_config, self.args, self.environment, self.plugin_resolver, &self.plugins_collection).await?;
+    self.config = Some(config);
+    Ok(())
+  }
+}
 
-    let has_config_changed = last_config.is_none() || last_config.unwrap() != config;
-    if has_config_changed {
-      self.plugins_collection.drop_and_shutdown_initialized().await; // clear the existing plugins
-      let plugins = resolve_plugins(self.args, &config, self.environment, self.plugin_resolver).await?;
-      self.plugins_collection.set_plugins(plugins, &config.base_path)?;
+/// Resolves the path of an editor's buffer as if it were saved so the configuration,
+/// associations, and plugins behave the same as for the file on disk. The file and its
+/// directories may not exist yet when the buffer is unsaved.
+pub(super) fn resolve_buffer_file_path(file_path: &Path, environment: &impl Environment) -> Result<PathBuf> {
+  let file_path = if environment.is_absolute_path(file_path) {
+    file_path.to_path_buf()
+  } else {
+    environment.cwd().join(file_path)
+  };
+  let mut unsaved_components = Vec::new();
+  let mut current_path = file_path.as_path();
+  loop {
+    if let Ok(resolved_path) = environment.canonicalize(current_path) {
+      let mut resolved_path = resolved_path.into_path_buf();
+      for component in unsaved_components.into_iter().rev() {
+        resolved_path.push(component);
+      }
+      return Ok(resolved_path);
     }
+    match (current_path.parent(), current_path.file_name()) {
+      (Some(parent), Some(file_name)) => {
+        unsaved_components.push(file_name);
+        current_path = parent;
+      }
+      _ => bail!("Could not resolve the file path {}.", file_path.display()),
+    }
+  }
+}
 
-    self.config = Some(config);
+/// Gets a key of everything that affects the result of formatting so identical requests can share it.
+fn get_format_request_key(file_path: &Path, body: &FormatEditorMessageBody) -> u64 {
+  let mut bytes = file_path.to_string_lossy().into_owned().into_bytes();
+  bytes.push(0);
+  bytes.extend(format!("{:?}", body.range).into_bytes());
+  bytes.push(0);
+  bytes.extend(&body.override_config);
+  bytes.push(0);
+  bytes.extend(&body.file_text);
+  get_bytes_hash(&bytes)
+}
 
-    Ok(())
+/// Resolves the configuration and sets the plugins when it's different than the last configuration.
+pub(super) async fn resolve_latest_config<TEnvironment: Environment>(
+  last_config: Option<ResolvedConfig>,
+  args: &CliArgs,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+  plugins_collection: &PluginsCollection<TEnvironment>,
+) -> Result<ResolvedConfig> {
+  let config = resolve_config_from_args(args, environment)?;
+
+  let has_config_changed = last_config.is_none() || last_config.unwrap() != config;
+  if has_config_changed {
+    plugins_collection.drop_and_shutdown_initialized().await; // clear the existing plugins
+    let plugins = resolve_plugins(args, &config, environment, plugin_resolver).await?;
+    plugins_collection.set_plugins(plugins, &config.base_path, &config.overrides, &[])?;
   }
+
+  Ok(config)
+}
+
+/// Creates an instance of each plugin if it doesn't already have one.
+pub(super) async fn create_plugin_instances<TEnvironment: Environment>(plugins: &[Arc<PluginWrapper<TEnvironment>>], environment: &TEnvironment) -> Result<()> {
+  let error_logger = ErrorCountLogger::from_environment(environment);
+  let results = futures::future::join_all(
+    plugins
+      .iter()
+      .map(|plugin| plugin.get_or_create_checking_config_diagnostics(error_logger.clone())),
+  )
+  .await;
+  for (plugin, result) in plugins.iter().zip(results) {
+    match result {
+      Ok(GetPluginResult::Success(_)) => {}
+      Ok(GetPluginResult::HadDiagnostics) => bail!("Plugin {} had configuration errors.", plugin.name()),
+      Err(err) => bail!("Error creating plugin {}. {:#}", plugin.name(), err),
+    }
+  }
+  Ok(())
 }
 
 fn handle_message(context: &EditorContext, original_message_id: u32, action: impl FnOnce() -> Result<EditorMessageBody>) {
@@ -423,6 +555,19 @@ mod test {
       result.map(|maybe_text| maybe_text.map(|bytes| String::from_utf8(bytes).unwrap()))
     }
 
+    pub async fn warmup(&self) -> Result<()> {
+      let (tx, rx) = oneshot::channel::<Result<()>>();
+
+      self
+        .send_message(
+          EditorMessageBody::Warmup,
+          MessageResponseChannel::Success(tx),
+          rx,
+          Arc::new(CancellationToken::new()),
+        )
+        .await
+    }
+
     pub async fn exit(&self) -> Result<()> {
       let (tx, rx) = oneshot::channel::<Result<()>>();
 
@@ -436,7 +581,7 @@ mod test {
         .await
     }
 
-    async fn send_message<T: Default>(
+    async fn send_message<T>(
       &self,
       body: EditorMessageBody,
       response_channel: MessageResponseChannel,
@@ -446,18 +591,18 @@ mod test {
       let message_id = self.id_generator.next();
       self.messages.store(message_id, response_channel);
       self.writer.send(EditorMessage { id: message_id, body })?;
-      tokio::select! {
+      let mut receiver = receiver;
+      let response = tokio::select! {
         _ = token.cancelled() => {
           self.writer.send(EditorMessage { id: self.id_generator.next(), body: EditorMessageBody::CancelFormat(message_id) })?;
-          self.messages.take(message_id); // clear memory
-          Ok(Default::default())
-        }
-        response = receiver => {
-          match response {
-            Ok(data) => data,
-            Err(err) => panic!("{:#}", err)
-          }
+          // the service responds that it was cancelled or with the result if it already finished
+          receiver.await
         }
+        response = &mut receiver => response,
+      };
+      match response {
+        Ok(data) => data,
+        Err(err) => panic!("{:#}", err),
       }
     }
   }
@@ -495,6 +640,13 @@ mod test {
         Some(_) => unreachable!(),
         None => {}
       },
+      EditorMessageBody::FormatCancelledResponse(message_id) => match messages.take(message_id) {
+        Some(MessageResponseChannel::Format(channel)) => {
+          let _ignore = channel.send(Ok(None));
+        }
+        Some(_) => unreachable!(),
+        None => {}
+      },
       EditorMessageBody::CanFormatResponse(message_id, value) => match messages.take(message_id) {
         Some(MessageResponseChannel::CanFormat(channel)) => {
           let _ignore = channel.send(Ok(if value == 1 { true } else { false }));
@@ -739,6 +891,55 @@ mod test {
     result.join().unwrap();
   }
 
+  #[test]
+  fn should_warmup_for_editor_service() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .add_remote_process_plugin()
+      .with_default_config(|c| {
+        c.add_remote_wasm_plugin().add_remote_process_plugin();
+      })
+      .build(); // build only, don't initialize
+    let stdin = environment.stdin_writer();
+    let stdout = environment.stdout_reader();
+
+    let result = std::thread::spawn({
+      let environment = environment.clone();
+      move || {
+        TestEnvironment::new().run_in_runtime(async move {
+          let communicator = EditorServiceCommunicator::new(stdin, stdout);
+
+          communicator.warmup().await.unwrap();
+          // the plugins should be resolved and compiled before the first format
+          let mut stderr_messages = environment.take_stderr_messages();
+          stderr_messages.sort();
+          assert_eq!(
+            stderr_messages,
+            vec![
+              "Compiling https://plugins.dprint.dev/test-plugin.wasm",
+              "Extracting zip for test-process-plugin"
+            ]
+          );
+          assert_eq!(
+            communicator
+              .format_text(&PathBuf::from("/file.txt"), "testing", None, Default::default(), Default::default())
+              .await
+              .unwrap()
+              .unwrap(),
+            "testing_formatted"
+          );
+          assert_eq!(environment.take_stderr_messages(), Vec::<String>::new());
+
+          communicator.exit().await.unwrap();
+        });
+      }
+    });
+
+    run_test_cli(vec!["editor-service", "--parent-pid", &std::process::id().to_string()], &environment).unwrap();
+
+    result.join().unwrap();
+  }
+
   #[test]
   fn should_format_with_config_associations_for_editor_service() {
     let file_path1 = "/file1.txt";
@@ -843,6 +1044,18 @@ mod test {
             "text_wasm_ps"
           );
 
+          // unsaved buffer in a directory that doesn't exist yet with a relative path
+          let unsaved_file_path = "src/unsaved-dir/some_file_name";
+          assert_eq!(communicator.check_file(&unsaved_file_path).await.unwrap(), true);
+          assert_eq!(
+            communicator
+              .format_text(&unsaved_file_path, "text", None, Default::default(), Default::default())
+              .await
+              .unwrap()
+              .unwrap(),
+            "text_wasm"
+          );
+
           communicator.exit().await.unwrap();
         });
       }
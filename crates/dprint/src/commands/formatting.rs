@@ -1,33 +1,79 @@
 use anyhow::bail;
 use anyhow::Result;
 use crossterm::style::Stylize;
+use dprint_core::plugins::FormatRange;
 use dprint_core::plugins::Host;
 use dprint_core::plugins::HostFormatRequest;
 use dprint_core::plugins::NullCancellationToken;
 use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
 
 use crate::arg_parser::CheckSubCommand;
 use crate::arg_parser::CliArgs;
+use crate::arg_parser::FmtMode;
 use crate::arg_parser::FmtSubCommand;
+use crate::arg_parser::GitFileSelection;
 use crate::arg_parser::OutputFormatTimesSubCommand;
 use crate::arg_parser::StdInFmtSubCommand;
+use crate::commands::connect_to_broker;
+use crate::commands::format_watch;
+use crate::commands::save_quarantine;
+use crate::commands::skip_quarantined_file_paths;
+use crate::commands::take_quarantined_file_paths;
+use crate::commands::BrokerClient;
+use crate::commands::CheckReporter;
 use crate::configuration::resolve_config_from_args;
+use crate::configuration::CheckSeverity;
+use crate::configuration::ResolvedConfig;
 use crate::environment::Environment;
 use crate::format::run_parallelized;
 use crate::format::EnsureStableFormat;
+use crate::format::RunBudget;
+use crate::format::RunParallelizedOptions;
+use crate::incremental::get_check_results_file;
+use crate::incremental::get_failed_files_path;
 use crate::incremental::get_incremental_file;
+use crate::incremental::get_quarantine_path;
+use crate::incremental::read_failed_files;
+use crate::incremental::read_quarantine;
+use crate::incremental::write_failed_files;
 use crate::paths::get_and_resolve_file_paths;
 use crate::paths::get_file_paths_by_plugins_and_err_if_empty;
+use crate::paths::get_git_partially_staged_file_paths;
+use crate::paths::prioritize_changed_file_paths;
+use crate::paths::restage_git_file_paths;
+use crate::paths::PluginNames;
+use crate::patterns::get_patterns_as_glob_matcher;
 use crate::patterns::FileMatcher;
+use crate::plugins::resolve_plugins_allowing_failures_and_err_if_empty;
 use crate::plugins::resolve_plugins_and_err_if_empty;
+use crate::plugins::Plugin;
 use crate::plugins::PluginResolver;
 use crate::plugins::PluginsCollection;
-use crate::utils::get_difference;
+use crate::stats::get_stats_collector;
+use crate::stats::save_run_stats;
+use crate::stats::save_run_summary;
+use crate::transforms::FileTextTransforms;
+use crate::utils::display_path;
+use crate::utils::get_difference_with_options;
+use crate::utils::get_file_path_key;
+use crate::utils::get_fix_commands;
+use crate::utils::get_fix_script_text;
+use crate::utils::get_unified_diff;
+use crate::utils::BatchedFileWriter;
+use crate::utils::DiffOptions;
+use crate::utils::GeneratedFileSkipper;
+use crate::utils::GlobMatcher;
+use crate::utils::SortOutputMode;
 use crate::utils::BOM_CHAR;
 
 pub async fn stdin_fmt<TEnvironment: Environment>(
@@ -39,7 +85,7 @@ pub async fn stdin_fmt<TEnvironment: Environment>(
 ) -> Result<()> {
   let config = resolve_config_from_args(args, environment)?;
   let plugins = resolve_plugins_and_err_if_empty(args, &config, environment, plugin_resolver).await?;
-  plugin_pools.set_plugins(plugins, &config.base_path)?;
+  plugin_pools.set_plugins(plugins, &config.base_path, &config.overrides, &cmd.patterns.associations)?;
   // if the path is absolute, then apply exclusion rules
   if environment.is_absolute_path(&cmd.file_name_or_path) {
     let file_matcher = FileMatcher::new(&config, &cmd.patterns, environment)?;
@@ -51,12 +97,20 @@ pub async fn stdin_fmt<TEnvironment: Environment>(
       return Ok(());
     }
   }
-  output_stdin_format(PathBuf::from(&cmd.file_name_or_path), &cmd.file_text, environment, plugin_pools).await
+  output_stdin_format(
+    PathBuf::from(&cmd.file_name_or_path),
+    &cmd.file_text,
+    cmd.range.clone(),
+    environment,
+    plugin_pools,
+  )
+  .await
 }
 
 async fn output_stdin_format<TEnvironment: Environment>(
   file_name: PathBuf,
   file_text: &str,
+  range: FormatRange,
   environment: &TEnvironment,
   plugins_collection: Arc<PluginsCollection<TEnvironment>>,
 ) -> Result<()> {
@@ -64,7 +118,7 @@ async fn output_stdin_format<TEnvironment: Environment>(
     .format(HostFormatRequest {
       file_path: file_name,
       file_text: file_text.to_string(),
-      range: None,
+      range,
       override_config: Default::default(),
       token: Arc::new(NullCancellationToken),
     })
@@ -86,11 +140,12 @@ pub async fn output_format_times<TEnvironment: Environment>(
   let config = resolve_config_from_args(args, environment)?;
   let plugins = resolve_plugins_and_err_if_empty(args, &config, environment, plugin_resolver).await?;
   let file_paths = get_and_resolve_file_paths(&config, &cmd.patterns, environment).await?;
-  let file_paths_by_plugin = get_file_paths_by_plugins_and_err_if_empty(&plugins, file_paths, &config.base_path)?;
-  plugin_pools.set_plugins(plugins, &config.base_path)?;
+  let file_paths_by_plugin =
+    get_file_paths_by_plugins_and_err_if_empty(&plugins, file_paths, &config.base_path, &config.overrides, &cmd.patterns.associations)?;
+  plugin_pools.set_plugins(plugins, &config.base_path, &config.overrides, &cmd.patterns.associations)?;
   let durations: Arc<Mutex<Vec<(PathBuf, u128)>>> = Arc::new(Mutex::new(Vec::new()));
 
-  run_parallelized(file_paths_by_plugin, environment, plugin_pools, None, EnsureStableFormat(false), {
+  run_parallelized(file_paths_by_plugin, environment, plugin_pools, Default::default(), {
     let durations = durations.clone();
     move |file_path, _, _, _, start_instant, _| {
       let duration = start_instant.elapsed().as_millis();
@@ -102,9 +157,13 @@ pub async fn output_format_times<TEnvironment: Environment>(
   .await?;
 
   let mut durations = durations.lock();
-  durations.sort_by_key(|k| k.1);
+  if args.sort_output.is_some() {
+    durations.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+  } else {
+    durations.sort_by_key(|k| k.1);
+  }
   for (file_path, duration) in durations.iter() {
-    environment.log(&format!("{}ms - {}", duration, file_path.display()));
+    environment.log(&format!("{}ms - {}", duration, display_path(file_path, args.sort_output)));
   }
 
   Ok(())
@@ -118,58 +177,275 @@ pub async fn check<TEnvironment: Environment>(
   plugin_pools: Arc<PluginsCollection<TEnvironment>>,
 ) -> Result<()> {
   let config = resolve_config_from_args(args, environment)?;
-  let plugins = resolve_plugins_and_err_if_empty(args, &config, environment, plugin_resolver).await?;
-  let file_paths = get_and_resolve_file_paths(&config, &cmd.patterns, environment).await?;
-  let file_paths_by_plugin = get_file_paths_by_plugins_and_err_if_empty(&plugins, file_paths, &config.base_path)?;
-  plugin_pools.set_plugins(plugins, &config.base_path)?;
-
-  let incremental_file = get_incremental_file(cmd.incremental, &config, &plugin_pools, environment);
+  let plugins = resolve_plugins_for_run(args, &config, cmd.allow_plugin_failures, environment, plugin_resolver).await?;
+  let mut file_paths = get_and_resolve_file_paths(&config, &cmd.patterns, environment).await?;
+  let failed_files_path = get_failed_files_path(&config, environment);
+  if cmd.failed {
+    let failed_file_paths = match read_failed_files(&failed_files_path, environment) {
+      Some(failed_file_paths) => failed_file_paths.iter().map(|file_path| get_file_path_key(file_path)).collect::<HashSet<_>>(),
+      None => bail!("Could not find the failed files of a previous check. Run `dprint check` first."),
+    };
+    file_paths.retain(|file_path| failed_file_paths.contains(&get_file_path_key(file_path)));
+    if file_paths.is_empty() {
+      environment.log("No files failed the last check.");
+      return Ok(());
+    }
+  }
+  let quarantine_path = get_quarantine_path(&config, environment);
+  let quarantined_files = read_quarantine(&quarantine_path, environment);
+  skip_quarantined_file_paths(&mut file_paths, &quarantined_files, environment);
+  let file_paths_by_plugin =
+    get_file_paths_by_plugins_and_err_if_empty(&plugins, file_paths, &config.base_path, &config.overrides, &cmd.patterns.associations)?;
+  plugin_pools.set_plugins(plugins, &config.base_path, &config.overrides, &cmd.patterns.associations)?;
+  let summary_file_paths = get_summary_file_paths(cmd.summary_file.as_ref(), &file_paths_by_plugin);
+  let transforms = FileTextTransforms::from_config(&config)?.map(Arc::new);
+
+  // nothing is cached when asserting no writes
+  let incremental_file = if args.assert_no_writes {
+    None
+  } else if cmd.cache_results {
+    get_check_results_file(&config, &plugin_pools, &transforms, environment)
+  } else {
+    get_incremental_file(cmd.incremental, &config, &plugin_pools, &transforms, environment)
+  };
+  let broker = connect_to_broker_if_enabled(cmd.broker, args, environment).await?;
+  let stats = get_stats_collector("check", &config, incremental_file.is_some());
+  stats.add_compiled_wasm_plugins(plugin_resolver.take_compiled_wasm_plugin_names());
+  let warn_severity_matcher = Arc::new(get_warn_severity_matcher(&config)?);
   let not_formatted_files_count = Arc::new(AtomicUsize::new(0));
-
-  run_parallelized(
+  let warn_files_count = Arc::new(AtomicUsize::new(0));
+  let fail_fast_token = if cmd.fail_fast { Some(CancellationToken::new()) } else { None };
+  let reporter = Arc::new(CheckReporter::new(cmd, config.base_path.clone(), args.sort_output));
+  let failed_file_paths = Arc::new(Mutex::new(Vec::new()));
+  let new_quarantined_files = Arc::new(Mutex::new(Vec::new()));
+  let generated_file_skipper = get_generated_file_skipper(&config);
+
+  let result = run_parallelized(
     file_paths_by_plugin,
     environment,
-    plugin_pools,
-    incremental_file.clone(),
-    EnsureStableFormat(false),
+    plugin_pools.clone(),
+    RunParallelizedOptions {
+      incremental_file: incremental_file.clone(),
+      stats: Some(stats.clone()),
+      ensure_stable_format: EnsureStableFormat(false),
+      fail_fast_token: fail_fast_token.clone(),
+      allow_plugin_failures: cmd.allow_plugin_failures,
+      host: broker.clone().map(|broker| broker as Arc<dyn Host>),
+      budget: None,
+      quarantined_files: Some(new_quarantined_files.clone()),
+      deterministic: cmd.deterministic,
+      generated_file_skipper: generated_file_skipper.clone(),
+      transforms: transforms.clone(),
+    },
     {
       let not_formatted_files_count = not_formatted_files_count.clone();
+      let warn_files_count = warn_files_count.clone();
       let incremental_file = incremental_file.clone();
+      let reporter = reporter.clone();
+      let failed_file_paths = failed_file_paths.clone();
       move |file_path, file_text, formatted_text, _, _, environment| {
         if formatted_text != file_text {
-          not_formatted_files_count.fetch_add(1, Ordering::SeqCst);
-          output_difference(file_path, file_text, &formatted_text, environment);
+          let is_warn = match &*warn_severity_matcher {
+            Some(matcher) => matcher.matches(file_path),
+            None => false,
+          };
+          if is_warn {
+            warn_files_count.fetch_add(1, Ordering::SeqCst);
+          } else {
+            not_formatted_files_count.fetch_add(1, Ordering::SeqCst);
+            failed_file_paths.lock().push(file_path.to_path_buf());
+            if let Some(token) = &fail_fast_token {
+              token.cancel();
+            }
+          }
+          let severity = if is_warn { CheckSeverity::Warn } else { CheckSeverity::Error };
+          reporter.report_not_formatted(file_path, file_text, &formatted_text, severity, environment);
         } else {
+          reporter.report_formatted(file_path);
           // update the incremental cache when the file is formatted correctly
           // so that this runs faster next time, but don't update it with the
           // correctly formatted file because it hasn't undergone a stable
           // formatting check
           if let Some(incremental_file) = &incremental_file {
-            incremental_file.update_file(&formatted_text);
+            incremental_file.update_file(file_path, &formatted_text);
           }
         }
         Ok(())
       }
     },
   )
-  .await?;
-
+  .await;
+  if let Some(broker) = &broker {
+    broker.close().await?;
+  }
+  reporter.finish(environment)?;
+  if !args.assert_no_writes {
+    save_run_stats(&stats, &config, &plugin_pools, environment);
+    save_quarantine(&quarantine_path, quarantined_files, &new_quarantined_files, false, environment);
+  }
+  result?;
+  if let Some(generated_file_skipper) = &generated_file_skipper {
+    generated_file_skipper.log_summary(environment);
+  }
+
+  let failed_file_paths = std::mem::take(&mut *failed_file_paths.lock());
+  let fix_commands = get_fix_commands(&failed_file_paths, args.config.as_deref(), environment.cwd().as_ref());
+  if let Some(summary_file) = &cmd.summary_file {
+    save_run_summary(
+      summary_file,
+      "check",
+      &config,
+      &plugin_pools,
+      &summary_file_paths,
+      fix_commands.clone(),
+      environment,
+    )?;
+  }
+  if let Some(fix_script) = &cmd.fix_script {
+    let fix_script_path = environment.cwd().join(fix_script);
+    if let Some(parent) = fix_script_path.parent() {
+      environment.mk_dir_all(parent)?;
+    }
+    environment.write_file(&fix_script_path, &get_fix_script_text(&fix_commands))?;
+  }
   if let Some(incremental_file) = &incremental_file {
     incremental_file.write();
   }
+  if !args.assert_no_writes {
+    write_failed_files(&failed_files_path, failed_file_paths, environment);
+  }
+
+  let warn_files_count = warn_files_count.load(Ordering::SeqCst);
+  if warn_files_count > 0 {
+    let f = if warn_files_count == 1 { "file" } else { "files" };
+    environment.log(&format!(
+      "Found {} not formatted {} with warn severity.",
+      warn_files_count.to_string().bold(),
+      f
+    ));
+  }
 
   let not_formatted_files_count = not_formatted_files_count.load(Ordering::SeqCst);
   if not_formatted_files_count == 0 {
     Ok(())
   } else {
     let f = if not_formatted_files_count == 1 { "file" } else { "files" };
+    environment.log_stderr(&format!("To format the {}, run:\n  {}", f, fix_commands.join("\n  ")));
     bail!("Found {} not formatted {}.", not_formatted_files_count.to_string().bold().to_string(), f)
   }
 }
 
-fn output_difference(file_path: &Path, file_text: &str, formatted_text: &str, environment: &impl Environment) {
-  let difference_text = get_difference(file_text, formatted_text);
-  environment.log(&format!("{} {}:\n{}\n--", "from".bold().red(), file_path.display(), difference_text,));
+pub(super) async fn resolve_plugins_for_run<TEnvironment: Environment>(
+  args: &CliArgs,
+  config: &ResolvedConfig,
+  allow_plugin_failures: bool,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+) -> Result<Vec<Box<dyn Plugin>>> {
+  if allow_plugin_failures {
+    resolve_plugins_allowing_failures_and_err_if_empty(args, config, environment, plugin_resolver).await
+  } else {
+    resolve_plugins_and_err_if_empty(args, config, environment, plugin_resolver).await
+  }
+}
+
+async fn connect_to_broker_if_enabled(is_enabled: bool, args: &CliArgs, environment: &impl Environment) -> Result<Option<Arc<BrokerClient>>> {
+  Ok(if is_enabled {
+    Some(Arc::new(connect_to_broker(args, environment).await?))
+  } else {
+    None
+  })
+}
+
+/// Gets the file paths to include in the run summary, which is only necessary when writing one.
+fn get_summary_file_paths(summary_file: Option<&String>, file_paths_by_plugins: &HashMap<PluginNames, Vec<PathBuf>>) -> Vec<PathBuf> {
+  match summary_file {
+    Some(_) => file_paths_by_plugins.values().flatten().cloned().collect(),
+    None => Vec::new(),
+  }
+}
+
+pub(super) fn get_generated_file_skipper(config: &ResolvedConfig) -> Option<Arc<GeneratedFileSkipper>> {
+  if config.generated_files.skip {
+    Some(Arc::new(GeneratedFileSkipper::new(config.generated_files.markers.clone())))
+  } else {
+    None
+  }
+}
+
+fn get_warn_severity_matcher(config: &ResolvedConfig) -> Result<Option<GlobMatcher>> {
+  if !config.check_severity.values().any(|severity| *severity == CheckSeverity::Warn) {
+    return Ok(None);
+  }
+
+  // files matching an "error" glob are excluded from the "warn" globs
+  let patterns = config
+    .check_severity
+    .iter()
+    .map(|(pattern, severity)| match severity {
+      CheckSeverity::Warn => pattern.to_string(),
+      CheckSeverity::Error => format!("!{}", pattern),
+    })
+    .collect::<Vec<_>>();
+  Ok(Some(get_patterns_as_glob_matcher(&patterns, &config.base_path)?))
+}
+
+pub(super) fn get_difference_output(
+  file_path: &Path,
+  file_text: &str,
+  formatted_text: &str,
+  diff_options: &DiffOptions,
+  sort_output: Option<SortOutputMode>,
+) -> String {
+  let difference_text = get_difference_with_options(file_text, formatted_text, diff_options);
+  format!("{} {}:\n{}\n--", "from".bold().red(), display_path(file_path, sort_output), difference_text,)
+}
+
+/// Outputs the differences ordered by the displayed file path (`--sort-output`).
+pub(super) fn output_sorted_differences(mut differences: Vec<(PathBuf, String)>, sort_output: Option<SortOutputMode>, environment: &impl Environment) {
+  differences.sort_by_cached_key(|(file_path, _)| display_path(file_path, sort_output).into_bytes());
+  for (_, output) in differences {
+    environment.log(&output);
+  }
+}
+
+/// Outputs the differences in a section per top level directory, which
+/// is collapsible when running on GitHub Actions.
+pub(super) fn output_grouped_differences(differences: Vec<(PathBuf, String)>, base_path: &Path, environment: &impl Environment) {
+  let mut groups: BTreeMap<String, Vec<(PathBuf, String)>> = BTreeMap::new();
+  for (file_path, output) in differences {
+    groups
+      .entry(get_top_level_dir_name(&file_path, base_path))
+      .or_default()
+      .push((file_path, output));
+  }
+  let is_github_actions = environment.env_var("GITHUB_ACTIONS").as_deref() == Some("true");
+
+  for (dir_name, mut differences) in groups {
+    differences.sort_by(|a, b| a.0.cmp(&b.0));
+    let files_text = if differences.len() == 1 { "file" } else { "files" };
+    if is_github_actions {
+      environment.log(&format!("::group::{} ({} {})", dir_name, differences.len(), files_text));
+      for (_, output) in differences {
+        environment.log(&output);
+      }
+      environment.log("::endgroup::");
+    } else {
+      environment.log(&format!("{} ({} {}):", dir_name, differences.len(), files_text));
+      for (_, output) in differences {
+        environment.log(&output.lines().map(|line| format!("  {}", line)).collect::<Vec<_>>().join("\n"));
+      }
+    }
+  }
+}
+
+fn get_top_level_dir_name(file_path: &Path, base_path: &Path) -> String {
+  let relative_path = file_path.strip_prefix(base_path).unwrap_or(file_path);
+  let mut components = relative_path.components().filter(|c| matches!(c, Component::Normal(_)));
+  match (components.next(), components.next()) {
+    (Some(dir), Some(_)) => format!("{}/", dir.as_os_str().to_string_lossy()),
+    _ => "./".to_string(),
+  }
 }
 
 pub async fn format<TEnvironment: Environment>(
@@ -179,33 +455,137 @@ pub async fn format<TEnvironment: Environment>(
   plugin_resolver: &PluginResolver<TEnvironment>,
   plugin_pools: Arc<PluginsCollection<TEnvironment>>,
 ) -> Result<()> {
+  let has_budget = cmd.max_duration.is_some() || cmd.max_files.is_some();
+  if has_budget && cmd.mode != FmtMode::Write {
+    bail!("The --max-duration and --max-files flags are only supported when writing the formatted files.");
+  }
+  if args.assert_no_writes && cmd.mode == FmtMode::Write {
+    bail!("The --assert-no-writes flag is not supported when writing the formatted files. Use `dprint check` instead.");
+  }
+  if cmd.watch {
+    if cmd.mode != FmtMode::Write || cmd.retry_quarantined {
+      bail!("The --watch flag is only supported when writing the formatted files.");
+    }
+    return format_watch(cmd, args, environment, plugin_resolver, plugin_pools).await;
+  }
   let config = resolve_config_from_args(args, environment)?;
-  let plugins = resolve_plugins_and_err_if_empty(args, &config, environment, plugin_resolver).await?;
-  let file_paths = get_and_resolve_file_paths(&config, &cmd.patterns, environment).await?;
-  let file_paths_by_plugins = get_file_paths_by_plugins_and_err_if_empty(&plugins, file_paths, &config.base_path)?;
-  plugin_pools.set_plugins(plugins, &config.base_path)?;
+  let plugins = resolve_plugins_for_run(args, &config, cmd.allow_plugin_failures, environment, plugin_resolver).await?;
+  let mut file_paths = get_and_resolve_file_paths(&config, &cmd.patterns, environment).await?;
+  let quarantine_path = get_quarantine_path(&config, environment);
+  let mut quarantined_files = read_quarantine(&quarantine_path, environment);
+  if cmd.retry_quarantined {
+    if !take_quarantined_file_paths(&mut file_paths, &mut quarantined_files) {
+      environment.log("No quarantined files to retry.");
+      return Ok(());
+    }
+  } else {
+    skip_quarantined_file_paths(&mut file_paths, &quarantined_files, environment);
+  }
+  let mut file_paths_by_plugins =
+    get_file_paths_by_plugins_and_err_if_empty(&plugins, file_paths, &config.base_path, &config.overrides, &cmd.patterns.associations)?;
+  plugin_pools.set_plugins(plugins, &config.base_path, &config.overrides, &cmd.patterns.associations)?;
+  let summary_file_paths = get_summary_file_paths(cmd.summary_file.as_ref(), &file_paths_by_plugins);
+  if cmd.mode == FmtMode::Stdout {
+    let file_count = file_paths_by_plugins.values().map(|file_paths| file_paths.len()).sum::<usize>();
+    if file_count > 1 {
+      bail!(
+        "The stdout mode only supports formatting a single file, but found {} files. Provide the path of one file.",
+        file_count
+      );
+    }
+  }
 
-  let incremental_file = get_incremental_file(cmd.incremental, &config, &plugin_pools, environment);
+  let transforms = FileTextTransforms::from_config(&config)?.map(Arc::new);
+  // the incremental file is only updated when writing the formatted files
+  let incremental_file = if cmd.mode == FmtMode::Write {
+    get_incremental_file(cmd.incremental, &config, &plugin_pools, &transforms, environment)
+  } else {
+    None
+  };
+  let budget = if has_budget {
+    if incremental_file.is_none() {
+      bail!("The --max-duration and --max-files flags require incremental formatting in order for the next run to continue where it left off.");
+    }
+    prioritize_changed_file_paths(&mut file_paths_by_plugins, &config.base_path, environment);
+    Some(Arc::new(RunBudget::new(cmd.max_duration, cmd.max_files)))
+  } else {
+    None
+  };
+  // staging a file with unstaged changes would also stage those changes
+  let partially_staged_file_paths = if cmd.mode == FmtMode::Write && cmd.patterns.git_selection == Some(GitFileSelection::Staged) {
+    Some(get_git_partially_staged_file_paths(&config.base_path, environment)?)
+  } else {
+    None
+  };
+  let broker = connect_to_broker_if_enabled(cmd.broker, args, environment).await?;
+  let stats = get_stats_collector("fmt", &config, incremental_file.is_some());
+  stats.add_compiled_wasm_plugins(plugin_resolver.take_compiled_wasm_plugin_names());
   let formatted_files_count = Arc::new(AtomicUsize::new(0));
+  let formatted_file_paths = Arc::new(Mutex::new(Vec::new()));
   let output_diff = cmd.diff;
-
-  run_parallelized(
+  let sort_output = args.sort_output;
+  let sorted_differences = Arc::new(Mutex::new(Vec::new()));
+  let mode = cmd.mode;
+  let unified_diffs = Arc::new(Mutex::new(Vec::new()));
+  let new_quarantined_files = Arc::new(Mutex::new(Vec::new()));
+  let generated_file_skipper = get_generated_file_skipper(&config);
+  let file_writer = Arc::new(BatchedFileWriter::new(environment.clone(), cmd.fsync));
+
+  let result = run_parallelized(
     file_paths_by_plugins,
     environment,
-    plugin_pools,
-    incremental_file.clone(),
-    EnsureStableFormat(cmd.enable_stable_format),
+    plugin_pools.clone(),
+    RunParallelizedOptions {
+      incremental_file: incremental_file.clone(),
+      stats: Some(stats.clone()),
+      ensure_stable_format: EnsureStableFormat(cmd.enable_stable_format),
+      fail_fast_token: if cmd.fail_fast { Some(CancellationToken::new()) } else { None },
+      allow_plugin_failures: cmd.allow_plugin_failures,
+      host: broker.clone().map(|broker| broker as Arc<dyn Host>),
+      budget: budget.clone(),
+      quarantined_files: Some(new_quarantined_files.clone()),
+      deterministic: cmd.deterministic,
+      generated_file_skipper: generated_file_skipper.clone(),
+      transforms: transforms.clone(),
+    },
     {
       let formatted_files_count = formatted_files_count.clone();
+      let formatted_file_paths = formatted_file_paths.clone();
       let incremental_file = incremental_file.clone();
+      let unified_diffs = unified_diffs.clone();
+      let sorted_differences = sorted_differences.clone();
+      let base_path = config.base_path.clone();
+      let file_writer = file_writer.clone();
       move |file_path, file_text, formatted_text, had_bom, _, environment| {
+        match mode {
+          FmtMode::Write => {}
+          FmtMode::Stdout => {
+            environment.log_machine_readable(&formatted_text);
+            return Ok(());
+          }
+          FmtMode::Diff => {
+            if formatted_text != file_text {
+              let relative_path = file_path.strip_prefix(&base_path).unwrap_or(file_path);
+              let relative_path = relative_path.to_string_lossy().replace('\\', "/");
+              let unified_diff = get_unified_diff(&relative_path, file_text, &formatted_text);
+              unified_diffs.lock().push((file_path.to_path_buf(), unified_diff));
+            }
+            return Ok(());
+          }
+        }
+
         if let Some(incremental_file) = &incremental_file {
-          incremental_file.update_file(&formatted_text);
+          incremental_file.update_file(file_path, &formatted_text);
         }
 
         if formatted_text != file_text {
           if output_diff {
-            output_difference(file_path, file_text, &formatted_text, environment);
+            let difference_output = get_difference_output(file_path, file_text, &formatted_text, &Default::default(), sort_output);
+            if sort_output.is_some() {
+              sorted_differences.lock().push((file_path.to_path_buf(), difference_output));
+            } else {
+              environment.log(&difference_output);
+            }
           }
 
           let new_text = if had_bom {
@@ -216,25 +596,101 @@ pub async fn format<TEnvironment: Environment>(
           };
 
           formatted_files_count.fetch_add(1, Ordering::SeqCst);
-          environment.write_file(file_path, &new_text)?;
+          file_writer.write(file_path.to_path_buf(), new_text);
+          formatted_file_paths.lock().push(file_path.to_path_buf());
         }
 
         Ok(())
       }
     },
   )
-  .await?;
+  .await;
+  // write the remaining files even when formatting other files failed, but
+  // surface the write errors before the incremental file is saved below
+  let write_result = file_writer.finish();
+  if let Some(broker) = &broker {
+    broker.close().await?;
+  }
+  if !args.assert_no_writes {
+    save_run_stats(&stats, &config, &plugin_pools, environment);
+    save_quarantine(&quarantine_path, quarantined_files, &new_quarantined_files, cmd.retry_quarantined, environment);
+  }
+  result?;
+  write_result?;
+  if let Some(generated_file_skipper) = &generated_file_skipper {
+    generated_file_skipper.log_summary(environment);
+  }
+
+  if let Some(summary_file) = &cmd.summary_file {
+    save_run_summary(summary_file, "fmt", &config, &plugin_pools, &summary_file_paths, Vec::new(), environment)?;
+  }
+  output_sorted_differences(std::mem::take(&mut *sorted_differences.lock()), sort_output, environment);
+  let mut unified_diffs = std::mem::take(&mut *unified_diffs.lock());
+  unified_diffs.sort_by(|a, b| a.0.cmp(&b.0));
+  for (_, unified_diff) in unified_diffs {
+    environment.log_machine_readable(&unified_diff);
+  }
 
   let formatted_files_count = formatted_files_count.load(Ordering::SeqCst);
   if formatted_files_count > 0 {
     let suffix = if formatted_files_count == 1 { "file" } else { "files" };
     environment.log(&format!("Formatted {} {}.", formatted_files_count.to_string().bold(), suffix));
   }
+  if let Some(budget) = &budget {
+    let remaining_files_count = budget.remaining_files_count();
+    if remaining_files_count > 0 {
+      let suffix = if remaining_files_count == 1 { "file" } else { "files" };
+      environment.log(&format!(
+        "Stopped at the budget before formatting {} more {}. Run again to continue.",
+        remaining_files_count.to_string().bold(),
+        suffix
+      ));
+    }
+  }
 
   if let Some(incremental_file) = &incremental_file {
     incremental_file.write();
   }
 
+  let formatted_file_paths = std::mem::take(&mut *formatted_file_paths.lock());
+  if let Some(partially_staged_file_paths) = &partially_staged_file_paths {
+    let (restage_file_paths, skipped_file_paths): (Vec<_>, Vec<_>) = formatted_file_paths
+      .iter()
+      .cloned()
+      .partition(|file_path| !partially_staged_file_paths.contains(file_path));
+    for file_path in skipped_file_paths {
+      environment.log_stderr(&format!(
+        "WARNING: Did not stage {} again because it has unstaged changes.",
+        file_path.display()
+      ));
+    }
+    restage_git_file_paths(&config.base_path, &restage_file_paths, environment)?;
+  }
+  run_post_format_hooks(&config, formatted_file_paths, environment)?;
+
+  Ok(())
+}
+
+/// Runs the post-format commands of the configuration in the base directory
+/// with the paths of the formatted files provided via stdin, one per line.
+pub(super) fn run_post_format_hooks(config: &ResolvedConfig, mut file_paths: Vec<PathBuf>, environment: &impl Environment) -> Result<()> {
+  if file_paths.is_empty() || config.hooks.post_format.is_empty() {
+    return Ok(());
+  }
+  file_paths.sort();
+  let stdin_text = file_paths.iter().map(|file_path| format!("{}\n", file_path.display())).collect::<String>();
+  for hook_command in config.hooks.post_format.iter() {
+    let args = hook_command.args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    match environment.run_command_with_stdin(&hook_command.command, &args, config.base_path.as_ref(), &stdin_text) {
+      Ok(stdout) => {
+        let stdout = stdout.trim_end();
+        if !stdout.is_empty() {
+          environment.log(stdout);
+        }
+      }
+      Err(err) => bail!("Post-format hook failed. The files were formatted. {:#}", err),
+    }
+  }
   Ok(())
 }
 
@@ -242,10 +698,13 @@ pub async fn format<TEnvironment: Environment>(
 mod test {
   use crossterm::style::Stylize;
   use pretty_assertions::assert_eq;
+  use std::path::PathBuf;
 
   use crate::environment::Environment;
   use crate::environment::TestEnvironment;
   use crate::environment::TestEnvironmentBuilder;
+  use crate::stats::FileRunSummary;
+  use crate::stats::RunSummary;
   use crate::test_helpers::get_plural_check_text;
   use crate::test_helpers::get_plural_formatted_text;
   use crate::test_helpers::get_singular_check_text;
@@ -254,6 +713,11 @@ mod test {
   use crate::test_helpers::run_test_cli_with_stdin;
   use crate::test_helpers::{self};
   use crate::utils::get_difference;
+  use crate::utils::get_difference_with_options;
+  use crate::utils::get_sha256_checksum;
+  use crate::utils::get_unified_diff;
+  use crate::utils::DiffColors;
+  use crate::utils::DiffOptions;
   use crate::utils::TestStdInReader;
 
   #[test]
@@ -296,6 +760,24 @@ mod test {
     assert_eq!(environment.read_file(&file_path2).unwrap(), "text2_formatted_process");
   }
 
+  #[test]
+  fn should_sync_written_files_with_fsync() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/dir/a.txt", "a")
+      .write_file("/dir/b.txt", "b_formatted")
+      .build();
+    run_test_cli(vec!["fmt", "--fsync", "/dir/*.txt"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
+    assert_eq!(environment.read_file("/dir/a.txt").unwrap(), "a_formatted");
+    assert_eq!(environment.take_synced_paths(), vec![PathBuf::from("/dir/a.txt"), PathBuf::from("/dir")]);
+
+    // not synced by default
+    environment.write_file("/dir/a.txt", "a").unwrap();
+    run_test_cli(vec!["fmt", "/dir/*.txt"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
+    assert_eq!(environment.take_synced_paths(), Vec::<PathBuf>::new());
+  }
+
   #[test]
   fn should_format_plugin_explicitly_specified_files() {
     // this file name is mentioned in test-process-plugin's PluginInfo
@@ -340,6 +822,21 @@ mod test {
     assert_eq!(error_message.to_string(), "Had 1 error(s) formatting.");
   }
 
+  #[test]
+  fn should_stop_formatting_on_first_error_with_fail_fast() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/file1.txt", "should_error")
+      .write_file("/file2.txt", "should_error")
+      .build();
+    environment.set_max_threads(2); // one thread for formatting
+    let error_message = run_test_cli(vec!["fmt", "--fail-fast", "/file1.txt", "/file2.txt"], &environment)
+      .err()
+      .unwrap();
+    assert_eq!(environment.take_stdout_messages().len(), 0);
+    assert_eq!(environment.take_stderr_messages().len(), 1);
+    assert_eq!(error_message.to_string(), "Had 1 error(s) formatting.");
+  }
+
   #[test]
   fn should_handle_process_plugin_erroring() {
     let environment = TestEnvironmentBuilder::with_initialized_remote_process_plugin()
@@ -375,6 +872,183 @@ mod test {
     assert_eq!(environment.read_file("/file2.txt_ps").unwrap(), "test_formatted_process");
   }
 
+  #[test]
+  fn should_run_post_format_hooks_with_formatted_files() {
+    let environment = TestEnvironmentBuilder::with_remote_wasm_plugin()
+      .write_file("/file1.txt", "text")
+      .write_file("/file2.txt", "text_formatted")
+      .write_file("/sub/file3.txt", "text")
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt")
+          .add_remote_wasm_plugin()
+          .add_config_section("hooks", r#"{ "postFormat": [["node", "checksums.js"], ["touch", ".stamp"]] }"#);
+      })
+      .initialize()
+      .build();
+    environment.add_command_output("node checksums.js", "Updated checksums.\n");
+    environment.add_command_output("touch .stamp", "");
+    run_test_cli(vec!["fmt"], &environment).unwrap();
+    assert_eq!(
+      environment.take_stdout_messages(),
+      vec![get_plural_formatted_text(2), "Updated checksums.".to_string()]
+    );
+    assert_eq!(environment.take_run_commands(), vec!["node checksums.js", "touch .stamp"]);
+    assert_eq!(environment.take_run_command_stdins(), vec!["/file1.txt\n/sub/file3.txt\n"; 2]);
+
+    // not run when nothing was formatted or when not writing
+    run_test_cli(vec!["fmt"], &environment).unwrap();
+    run_test_cli(vec!["check"], &environment).unwrap();
+    assert_eq!(environment.take_run_commands(), Vec::<String>::new());
+
+    environment.write_file("/file1.txt", "text").unwrap();
+    environment.add_command_error("node checksums.js", "Error running `node checksums.js`. Oops.");
+    let error_message = run_test_cli(vec!["fmt"], &environment).err().unwrap();
+    assert_eq!(
+      error_message.to_string(),
+      "Post-format hook failed. The files were formatted. Error running `node checksums.js`. Oops."
+    );
+    assert_eq!(environment.take_run_commands(), vec!["node checksums.js"]);
+  }
+
+  #[test]
+  fn should_only_format_files_chosen_by_file_selector() {
+    let checksum = get_sha256_checksum(test_helpers::FILE_SELECTOR_WAT.as_bytes());
+    let environment = TestEnvironmentBuilder::with_remote_wasm_plugin()
+      .write_file("/selector.wasm", test_helpers::FILE_SELECTOR_WAT)
+      .write_file("/file1.txt", "text")
+      .write_file("/_file2.txt", "text")
+      .write_file("/sub/_file3.txt", "text")
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt")
+          .add_remote_wasm_plugin()
+          .add_config_section("fileSelector", &format!("\"./selector.wasm@{}\"", checksum));
+      })
+      .initialize()
+      .build();
+    run_test_cli(vec!["fmt"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_plural_formatted_text(2)]);
+    assert_eq!(environment.read_file("/file1.txt").unwrap(), "text_formatted");
+    assert_eq!(environment.read_file("/_file2.txt").unwrap(), "text");
+    assert_eq!(environment.read_file("/sub/_file3.txt").unwrap(), "text_formatted");
+
+    environment.write_file("/selector.wasm", "(module)").unwrap();
+    let error_message = run_test_cli(vec!["fmt"], &environment).err().unwrap();
+    assert!(
+      error_message
+        .to_string()
+        .starts_with("Error loading file selector /selector.wasm. The checksum did not match the expected checksum."),
+      "{}",
+      error_message
+    );
+  }
+
+  #[test]
+  fn should_transform_text_before_and_after_plugins() {
+    let environment = TestEnvironmentBuilder::with_remote_wasm_plugin()
+      .write_file("/file1.txt", "text")
+      .write_file("/file2.txt", "// old header\ntext")
+      .write_file("/file3.txt", "\u{FEFF}\u{FEFF}text_formatted")
+      .write_file("/sub/readme.txt", "text")
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt").add_remote_wasm_plugin().add_config_section(
+          "transforms",
+          r#"{
+            "file*.txt": { "licenseHeader": "// header", "removeHeaders": ["// old header"] },
+            "file3.txt": { "normalizeBom": true }
+          }"#,
+        );
+      })
+      .initialize()
+      .build();
+    run_test_cli(vec!["fmt"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_plural_formatted_text(4)]);
+    assert_eq!(environment.read_file("/file1.txt").unwrap(), "// header\ntext_formatted");
+    assert_eq!(environment.read_file("/file2.txt").unwrap(), "// header\ntext_formatted");
+    assert_eq!(environment.read_file("/file3.txt").unwrap(), "\u{FEFF}// header\ntext_formatted");
+    assert_eq!(environment.read_file("/sub/readme.txt").unwrap(), "text_formatted");
+
+    // the plugin doesn't see the header, so the output is stable
+    run_test_cli(vec!["check"], &environment).unwrap();
+    environment.write_file("/file1.txt", "text_formatted").unwrap();
+    assert!(run_test_cli(vec!["check"], &environment).is_err());
+  }
+
+  #[test]
+  fn should_skip_generated_files_when_enabled() {
+    let environment = TestEnvironmentBuilder::with_remote_wasm_plugin()
+      .write_file("/file1.txt", "// @generated\ntext")
+      .write_file("/file2.txt", "text")
+      .write_file("/file3.txt", "// autogenerated\ntext")
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt")
+          .add_remote_wasm_plugin()
+          .add_config_section("generatedFiles", r#"{ "skip": true }"#);
+      })
+      .initialize()
+      .build();
+    run_test_cli(vec!["fmt"], &environment).unwrap();
+    assert_eq!(
+      environment.take_stdout_messages(),
+      vec!["Skipped 1 generated file.".to_string(), get_plural_formatted_text(2)]
+    );
+    assert_eq!(environment.read_file("/file1.txt").unwrap(), "// @generated\ntext");
+    assert_eq!(environment.read_file("/file2.txt").unwrap(), "text_formatted");
+
+    run_test_cli(vec!["check"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["Skipped 1 generated file."]);
+  }
+
+  #[test]
+  fn should_skip_generated_files_with_custom_markers() {
+    let environment = TestEnvironmentBuilder::with_remote_wasm_plugin()
+      .write_file("/file1.txt", "// @generated\ntext")
+      .write_file("/file2.txt", "// autogenerated\ntext")
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt")
+          .add_remote_wasm_plugin()
+          .add_config_section("generatedFiles", r#"{ "skip": true, "markers": ["autogenerated"] }"#);
+      })
+      .initialize()
+      .build();
+    run_test_cli(vec!["fmt", "--verbose"], &environment).unwrap();
+    assert_eq!(
+      environment.take_stdout_messages(),
+      vec!["Skipped 1 generated file.".to_string(), get_singular_formatted_text()]
+    );
+    assert!(environment
+      .take_stderr_messages()
+      .contains(&"[VERBOSE] Skipped generated file: /file2.txt".to_string()));
+    assert_eq!(environment.read_file("/file2.txt").unwrap(), "// autogenerated\ntext");
+  }
+
+  #[test]
+  fn should_skip_quarantined_files_in_later_runs() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/file1.txt", "should_panic") // special text to make it panic
+      .write_file("/file2.txt", "test")
+      .build();
+    environment.set_max_threads(1);
+    let error_message = run_test_cli(vec!["fmt", "/file1.txt"], &environment).err().unwrap();
+    assert_eq!(error_message.to_string(), "Had 1 error(s) formatting.");
+    environment.clear_logs();
+
+    run_test_cli(vec!["fmt", "**/*.txt"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec![concat!(
+        "WARNING: Skipped 1 quarantined file that previously caused a critical error in a plugin. ",
+        "Run `dprint quarantine list` to see them or `dprint quarantine retry` to format them again."
+      )
+      .to_string()]
+    );
+    assert_eq!(environment.read_file("/file2.txt").unwrap(), "test_formatted");
+
+    // check also skips them
+    run_test_cli(vec!["check", "**/*.txt"], &environment).unwrap();
+    assert_eq!(environment.take_stderr_messages().len(), 1);
+  }
+
   #[test]
   fn should_format_calling_process_plugin_with_wasm_plugin_and_no_plugin_exists() {
     let file_path = "/file.txt";
@@ -703,62 +1377,280 @@ mod test {
   }
 
   #[test]
-  fn should_format_files_with_config_associations_multiple_plugins_same_files() {
+  fn should_format_files_with_associate_arg() {
     let file_path1 = "/file1.txt";
-    let file_path2 = "/file2.txt_ps";
-    let file_path3 = "/file2.other";
-    let file_path4 = "/src/some_file_name";
-    let file_path5 = "/src/sub-dir/test-process-plugin-exact-file";
-    let file_path6 = "/file6.txt";
-    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_and_process_plugin()
+    let file_path2 = "/file2.snap";
+    let file_path3 = "/file3.other";
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
       .with_local_config("/config.json", |c| {
-        c.set_incremental(false)
-          .add_remote_wasm_plugin()
-          .add_remote_process_plugin()
-          .add_config_section(
-            "test-plugin",
-            r#"{
-              "associations": [
-                "**/*.{txt,txt_ps,other}",
-                "some_file_name",
-                "test-process-plugin-exact-file"
-              ],
-              "ending": "wasm"
-            }"#,
-          )
-          .add_config_section(
-            "testProcessPlugin",
-            r#"{
-              "associations": [
-                "**/*.{txt,txt_ps,other}",
-                "some_file_name",
-                "test-process-plugin-exact-file"
-              ],
-              "ending": "ps"
-            }"#,
-          )
-          .add_includes("**/*");
+        c.add_remote_wasm_plugin().add_includes("**/*");
       })
-      .write_file(&file_path1, "text")
+      .write_file(&file_path1, "text1")
       .write_file(&file_path2, "text2")
       .write_file(&file_path3, "text3")
-      .write_file(&file_path4, "text4")
-      .write_file(&file_path5, "text5")
-      .write_file(&file_path6, "plugin: text6")
       .build();
 
-    run_test_cli(vec!["fmt", "--config", "/config.json", "--skip-stable-format"], &environment).unwrap();
+    run_test_cli(vec!["fmt", "--config", "/config.json", "--associate", "*.snap=test-plugin"], &environment).unwrap();
 
-    assert_eq!(environment.take_stdout_messages(), vec![get_plural_formatted_text(6)]);
+    assert_eq!(environment.take_stdout_messages(), vec![get_plural_formatted_text(2)]);
     assert_eq!(environment.take_stderr_messages().len(), 0);
-    assert_eq!(environment.read_file(&file_path1).unwrap(), "text_wasm_ps");
-    assert_eq!(environment.read_file(&file_path2).unwrap(), "text2_wasm_ps");
-    assert_eq!(environment.read_file(&file_path3).unwrap(), "text3_wasm_ps");
+    // the plugin still formats the files it would otherwise
+    assert_eq!(environment.read_file(&file_path1).unwrap(), "text1_formatted");
+    assert_eq!(environment.read_file(&file_path2).unwrap(), "text2_formatted");
+    assert_eq!(environment.read_file(&file_path3).unwrap(), "text3");
+
+    let err = run_test_cli(vec!["fmt", "--config", "/config.json", "--associate", "*.snap=json"], &environment)
+      .err()
+      .unwrap();
+    assert_eq!(
+      err.to_string(),
+      "Could not find a plugin with the name or configuration key 'json' for the association '*.snap=json'."
+    );
+  }
+
+  #[test]
+  fn should_ask_to_trust_remote_plugins_on_first_run() {
+    let environment = TestEnvironmentBuilder::with_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_remote_wasm_plugin().add_includes("**/*.txt");
+      })
+      .write_file("/file.txt", "text")
+      .build();
+    environment.set_interactive(true);
+    environment.set_confirm_results(vec![Ok(Some(false))]);
+    let err = run_test_cli(vec!["fmt"], &environment).err().unwrap();
+    assert_eq!(err.to_string(), "Declined to trust the remote plugins.");
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec![
+        concat!(
+          "The following remote plugins will be downloaded and run for the first time:\n",
+          "  * https://plugins.dprint.dev/test-plugin.wasm\n",
+          "    Publisher: dprint (plugins.dprint.dev)\n",
+          "    Checksum: none specified (Wasm plugins run sandboxed)",
+        ),
+        "Do you trust these plugins? N",
+      ]
+    );
+    assert_eq!(environment.read_file("/file.txt").unwrap(), "text");
+
+    run_test_cli(vec!["fmt", "--yes"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec!["Compiling https://plugins.dprint.dev/test-plugin.wasm"]
+    );
+    assert_eq!(environment.read_file("/file.txt").unwrap(), "text_formatted");
+  }
+
+  #[test]
+  fn should_format_and_check_for_each_config() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_local_config("/a.json", |c| {
+        c.add_remote_wasm_plugin().add_includes("a/*.txt");
+      })
+      .with_local_config("/b.json", |c| {
+        c.add_remote_wasm_plugin().add_includes("b/*.txt");
+      })
+      .write_file("/a/file.txt", "text1")
+      .write_file("/b/file.txt", "text2")
+      .write_file("/b/other.txt", "text3")
+      .build();
+
+    run_test_cli(vec!["fmt", "--config", "/a.json", "--config", "/b.json"], &environment).unwrap();
+    let mut stdout_messages = environment.take_stdout_messages();
+    stdout_messages.sort();
+    assert_eq!(
+      stdout_messages,
+      vec![
+        format!("[/a.json] {}", get_singular_formatted_text()),
+        format!("[/b.json] {}", get_plural_formatted_text(2)),
+      ]
+    );
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+    assert_eq!(environment.read_file("/a/file.txt").unwrap(), "text1_formatted");
+    assert_eq!(environment.read_file("/b/other.txt").unwrap(), "text3_formatted");
+
+    environment.write_file("/b/file.txt", "text2").unwrap();
+    let err = run_test_cli(vec!["check", "--config", "/a.json", "--config", "/b.json"], &environment)
+      .err()
+      .unwrap();
+    assert_eq!(err.to_string(), "Failed for 1 of 2 configurations.");
+    assert!(environment.take_stdout_messages().iter().all(|message| message.starts_with("[/b.json] ")));
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec![
+        "[/b.json] To format the file, run:\n[/b.json]   dprint fmt --config /b.json b/file.txt".to_string(),
+        format!("[/b.json] {}", get_singular_check_text()),
+      ]
+    );
+
+    // other commands use the first configuration
+    run_test_cli(vec!["output-file-paths", "--config", "/a.json", "--config", "/b.json"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["/a/file.txt"]);
+
+    let err = run_test_cli(vec!["fmt", "--mode", "diff", "--config", "/a.json", "--config", "/b.json"], &environment)
+      .err()
+      .unwrap();
+    assert_eq!(
+      err.to_string(),
+      "Specifying --config more than once is only supported by `dprint fmt` and `dprint check`."
+    );
+  }
+
+  #[test]
+  fn should_report_files_included_by_more_than_one_config() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_local_config("/a.json", |c| {
+        c.add_remote_wasm_plugin().add_includes("**/*.txt");
+      })
+      .with_local_config("/b/b.json", |c| {
+        c.add_remote_wasm_plugin()
+          .add_includes("b/*.txt")
+          .add_config_section("test-plugin", r#"{ "ending": "b" }"#);
+      })
+      .write_file("/a/file.txt", "text1")
+      .write_file("/b/file.txt", "text2")
+      .write_file("/b/other.txt", "text3")
+      .build();
+
+    let err = run_test_cli(vec!["fmt", "--config", "/a.json", "--config", "/b/b.json"], &environment)
+      .err()
+      .unwrap();
+    assert_eq!(
+      err.to_string(),
+      concat!(
+        "Found 2 files included by more than one configuration file, which would format them with different settings:\n",
+        "  /a.json, /b/b.json\n",
+        "    b/file.txt\n",
+        "    b/other.txt\n",
+        "Change the includes or excludes so each file is included by one configuration file or ",
+        "provide --config-conflicts nearest to format each file with the configuration file nearest to it."
+      )
+    );
+    assert_eq!(environment.read_file("/b/file.txt").unwrap(), "text2");
+
+    run_test_cli(
+      vec!["fmt", "--config", "/a.json", "--config", "/b/b.json", "--config-conflicts", "nearest"],
+      &environment,
+    )
+    .unwrap();
+    let mut stdout_messages = environment.take_stdout_messages();
+    stdout_messages.sort();
+    assert_eq!(
+      stdout_messages,
+      vec![
+        format!("[/a.json] {}", get_singular_formatted_text()),
+        format!("[/b/b.json] {}", get_plural_formatted_text(2)),
+      ]
+    );
+    assert_eq!(environment.read_file("/a/file.txt").unwrap(), "text1_formatted");
+    assert_eq!(environment.read_file("/b/file.txt").unwrap(), "text2_b");
+    assert_eq!(environment.read_file("/b/other.txt").unwrap(), "text3_b");
+  }
+
+  #[test]
+  fn should_format_files_with_config_associations_multiple_plugins_same_files() {
+    let file_path1 = "/file1.txt";
+    let file_path2 = "/file2.txt_ps";
+    let file_path3 = "/file2.other";
+    let file_path4 = "/src/some_file_name";
+    let file_path5 = "/src/sub-dir/test-process-plugin-exact-file";
+    let file_path6 = "/file6.txt";
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_and_process_plugin()
+      .with_local_config("/config.json", |c| {
+        c.set_incremental(false)
+          .add_remote_wasm_plugin()
+          .add_remote_process_plugin()
+          .add_config_section(
+            "test-plugin",
+            r#"{
+              "associations": [
+                "**/*.{txt,txt_ps,other}",
+                "some_file_name",
+                "test-process-plugin-exact-file"
+              ],
+              "ending": "wasm"
+            }"#,
+          )
+          .add_config_section(
+            "testProcessPlugin",
+            r#"{
+              "associations": [
+                "**/*.{txt,txt_ps,other}",
+                "some_file_name",
+                "test-process-plugin-exact-file"
+              ],
+              "ending": "ps"
+            }"#,
+          )
+          .add_includes("**/*");
+      })
+      .write_file(&file_path1, "text")
+      .write_file(&file_path2, "text2")
+      .write_file(&file_path3, "text3")
+      .write_file(&file_path4, "text4")
+      .write_file(&file_path5, "text5")
+      .write_file(&file_path6, "plugin: text6")
+      .build();
+
+    run_test_cli(vec!["fmt", "--config", "/config.json", "--skip-stable-format"], &environment).unwrap();
+
+    assert_eq!(environment.take_stdout_messages(), vec![get_plural_formatted_text(6)]);
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+    assert_eq!(environment.read_file(&file_path1).unwrap(), "text_wasm_ps");
+    assert_eq!(environment.read_file(&file_path2).unwrap(), "text2_wasm_ps");
+    assert_eq!(environment.read_file(&file_path3).unwrap(), "text3_wasm_ps");
     assert_eq!(environment.read_file(&file_path4).unwrap(), "text4_wasm_ps");
     assert_eq!(environment.read_file(&file_path5).unwrap(), "text5_wasm_ps");
     assert_eq!(environment.read_file(&file_path6).unwrap(), "plugin: text6_wasm_ps_wasm_ps_ps");
   }
 
+  #[test]
+  fn should_error_when_wasm_plugin_exceeds_memory_limit() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_config_section("test-plugin", r#"{ "maxMemoryMb": 1 }"#);
+      })
+      .write_file("/test.txt", "test")
+      .build();
+
+    let error_message = run_test_cli(vec!["fmt", "**/*.txt"], &environment).err().unwrap();
+
+    assert_eq!(error_message.to_string(), "Had 1 error(s) formatting.");
+    let stderr_messages = environment.take_stderr_messages();
+    assert_eq!(stderr_messages.len(), 1);
+    assert_eq!(
+      stderr_messages[0],
+      concat!(
+        "Error creating plugin test-plugin. Message: The test-plugin plugin requires 2 MB of memory when it starts, ",
+        "which exceeds its limit of 1 MB. The limit may be increased via \"maxMemoryMb\" in the plugin's configuration."
+      )
+    );
+    assert_eq!(environment.read_file("/test.txt").unwrap(), "test");
+  }
+
+  #[test]
+  fn should_format_with_wasm_plugin_with_fuel_limit() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_config_section("test-plugin", r#"{ "maxFuel": 1000 }"#);
+      })
+      .write_file("/test.txt", "test")
+      .build();
+
+    run_test_cli(vec!["fmt", "**/*.txt"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
+    assert_eq!(environment.take_stderr_messages(), vec!["Compiling test-plugin with fuel metering"]);
+    assert_eq!(environment.read_file("/test.txt").unwrap(), "test_formatted");
+
+    // uses the cached module the next time
+    environment.write_file("/test.txt", "test").unwrap();
+    run_test_cli(vec!["fmt", "**/*.txt"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+  }
+
   #[test]
   fn should_error_on_wasm_plugin_config_diagnostic() {
     let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
@@ -1422,6 +2314,90 @@ mod test {
     );
   }
 
+  #[test]
+  fn should_only_format_files_of_changed_plugins_incrementally() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_and_process_plugin()
+      .write_file("/file1.txt", "text_formatted")
+      .write_file("/file2.txt_ps", "text_formatted_process")
+      .build();
+    run_test_cli(vec!["fmt", "**/*.*"], &environment).unwrap();
+    environment.clear_logs();
+
+    // change the configuration of the process plugin
+    let config_text = environment.read_file("/dprint.json").unwrap();
+    let config_text = config_text.replacen('{', "{\n  \"testProcessPlugin\": { \"ending\": \"custom\" },", 1);
+    environment.write_file("/dprint.json", &config_text).unwrap();
+    run_test_cli(vec!["fmt", "--verbose", "**/*.*"], &environment).unwrap();
+    let stderr_messages = environment.take_stderr_messages();
+    assert!(stderr_messages.iter().any(|msg| msg.contains("No change: /file1.txt")));
+    assert!(!stderr_messages.iter().any(|msg| msg.contains("No change: /file2.txt_ps")));
+    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
+    assert_eq!(environment.read_file("/file2.txt_ps").unwrap(), "text_formatted_process_custom");
+  }
+
+  #[test]
+  fn should_cache_check_results_across_directories() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .with_local_config("/dir1/dprint.json", |c| {
+        c.add_remote_wasm_plugin().set_incremental(false);
+      })
+      .with_local_config("/dir2/dprint.json", |c| {
+        c.add_remote_wasm_plugin().set_incremental(false);
+      })
+      .write_file("/dir1/sub/file.txt", "text_formatted")
+      .write_file("/dir2/sub/file.txt", "text_formatted")
+      .write_file("/dir2/other/file.txt", "text_formatted")
+      .build();
+
+    environment.set_cwd("/dir1");
+    run_test_cli(vec!["check", "--cache-results", "**/*.txt"], &environment).unwrap();
+    environment.clear_logs();
+
+    // the file at the same relative path with the same text should be skipped
+    environment.set_cwd("/dir2");
+    run_test_cli(vec!["check", "--cache-results", "--verbose", "**/*.txt"], &environment).unwrap();
+    let stderr_messages = environment.take_stderr_messages();
+    assert!(stderr_messages.iter().any(|msg| msg.contains("No change: /dir2/sub/file.txt")));
+    assert!(!stderr_messages.iter().any(|msg| msg.contains("No change: /dir2/other/file.txt")));
+    assert_eq!(environment.dir_info("/cache/check-results").unwrap().len(), 1);
+  }
+
+  #[test]
+  fn should_check_only_failed_files() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt");
+      })
+      .write_file("/file1.txt", "text")
+      .write_file("/file2.txt", "text")
+      .write_file("/file3.txt", "text_formatted")
+      .build();
+    let error_message = run_test_cli(vec!["check", "--failed"], &environment).err().unwrap();
+    assert_eq!(
+      error_message.to_string(),
+      "Could not find the failed files of a previous check. Run `dprint check` first."
+    );
+
+    let error_message = run_test_cli(vec!["check"], &environment).err().unwrap();
+    assert_eq!(error_message.to_string(), get_plural_check_text(2));
+    environment.clear_logs();
+
+    // fix one of the files, then only the other one should be checked
+    environment.write_file("/file1.txt", "text_formatted").unwrap();
+    let error_message = run_test_cli(vec!["check", "--failed", "--verbose"], &environment).err().unwrap();
+    assert_eq!(error_message.to_string(), get_singular_check_text());
+    let stderr_messages = environment.take_stderr_messages();
+    assert!(!stderr_messages.iter().any(|msg| msg.contains("No change: /file3.txt")));
+    environment.clear_logs();
+
+    environment.write_file("/file2.txt", "text_formatted").unwrap();
+    run_test_cli(vec!["check", "--failed"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages().len(), 0);
+    run_test_cli(vec!["check", "--failed"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["No files failed the last check."]);
+  }
+
   #[test]
   fn should_format_incrementally_when_specified_via_config() {
     let file_path1 = "/file1.txt";
@@ -1444,6 +2420,25 @@ mod test {
     assert_eq!(environment.take_stderr_messages().iter().any(|msg| msg.contains("No change: /file1.txt")), true);
   }
 
+  #[test]
+  fn should_output_plugin_schema_compatibility_in_verbose_mode() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_and_process_plugin()
+      .write_file("/file.txt", "text_formatted")
+      .write_file("/file.txt_ps", "text_formatted_process")
+      .build();
+    run_test_cli(vec!["fmt", "--verbose", "**/*.*"], &environment).unwrap();
+    let stderr_messages = environment.take_stderr_messages();
+    assert!(stderr_messages
+      .iter()
+      .any(|msg| msg.contains("Plugin schema compatibility:\n  Wasm 2 - adapter")));
+    assert!(stderr_messages
+      .iter()
+      .any(|msg| msg.contains("Plugin test-plugin uses Wasm plugin schema version 3 (native).")));
+    assert!(stderr_messages
+      .iter()
+      .any(|msg| msg.contains("Plugin test-process-plugin uses process plugin schema version 4 (native).")));
+  }
+
   #[test]
   fn incremental_should_error_for_unstable_format() {
     let file_path1 = "/file1.txt";
@@ -1512,48 +2507,163 @@ mod test {
   }
 
   #[test]
-  fn should_format_without_incremental_when_specified() {
-    let file_path1 = "/subdir/file1.txt";
-    let no_change_msg = "No change:";
+  fn should_format_staged_files_and_stage_them_again() {
     let environment = TestEnvironmentBuilder::with_remote_wasm_plugin()
       .with_default_config(|c| {
         c.add_includes("**/*.txt").add_remote_wasm_plugin();
       })
-      .write_file(&file_path1, "text1")
+      .write_file("/file1.txt", "text1")
+      .write_file("/file2.txt", "text2")
+      .write_file("/file3.txt", "text3")
+      .write_file("/file4.md", "text4")
       .initialize()
       .build();
-
-    run_test_cli(vec!["fmt", "--incremental=false"], &environment).unwrap();
-
-    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
-    assert_eq!(environment.take_stderr_messages().len(), 0);
-    assert_eq!(environment.read_file(&file_path1).unwrap(), "text1_formatted");
-
-    environment.clear_logs();
-    run_test_cli(vec!["fmt", "--incremental=false", "--verbose"], &environment).unwrap();
-    assert!(!environment.take_stderr_messages().iter().any(|msg| msg.contains(no_change_msg)));
+    environment.add_command_output(
+      "git -C / diff --cached --name-only --relative --diff-filter=d -z",
+      "file1.txt\0file2.txt\0file4.md\0",
+    );
+    environment.add_command_output("git -C / diff --name-only --relative -z", "file2.txt\0");
+    environment.add_command_output("git -C / add -- /file1.txt", "");
+    run_test_cli(vec!["fmt", "--staged"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_plural_formatted_text(2)]);
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec!["WARNING: Did not stage /file2.txt again because it has unstaged changes."]
+    );
+    assert_eq!(environment.read_file("/file1.txt").unwrap(), "text1_formatted");
+    assert_eq!(environment.read_file("/file2.txt").unwrap(), "text2_formatted");
+    assert_eq!(environment.read_file("/file3.txt").unwrap(), "text3");
+    assert_eq!(environment.take_run_commands().last().unwrap(), "git -C / add -- /file1.txt");
   }
 
   #[test]
-  fn allow_skipping_stable_format() {
-    let file_path1 = "/file1.txt";
-    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
-      .write_file(&file_path1, "unstable_fmt_true")
+  fn should_check_files_changed_since_ref() {
+    let environment = TestEnvironmentBuilder::with_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt").add_remote_wasm_plugin();
+      })
+      .write_file("/file1.txt", "text1")
+      .write_file("/file2.txt", "text2")
+      .initialize()
       .build();
+    environment.add_command_output("git -C / merge-base origin/main HEAD", "abc123\n");
+    environment.add_command_output("git -C / diff --name-only --relative --diff-filter=d -z abc123", "file1.txt\0sub/deleted.txt\0");
+    let error_message = run_test_cli(vec!["check", "--changed-since", "origin/main"], &environment).err().unwrap();
+    assert_eq!(error_message.to_string(), get_singular_check_text());
+    let stdout_messages = environment.take_stdout_messages();
+    assert_eq!(stdout_messages.len(), 1);
+    assert!(stdout_messages[0].contains("/file1.txt"));
 
-    run_test_cli(vec!["fmt", "--skip-stable-format", "*.txt"], &environment).unwrap();
-
-    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
-    assert_eq!(environment.take_stderr_messages().len(), 0);
-    assert_eq!(environment.read_file(&file_path1).unwrap(), "unstable_fmt_false_formatted");
+    environment.add_command_error("git -C / merge-base origin/main HEAD", "fatal: Not a valid object name origin/main");
+    let error_message = run_test_cli(vec!["check", "--changed-since", "origin/main"], &environment).err().unwrap();
+    assert_eq!(
+      error_message.to_string(),
+      "Could not get the files changed since 'origin/main' from git. fatal: Not a valid object name origin/main"
+    );
   }
 
   #[test]
-  fn should_not_output_when_no_files_need_formatting() {
-    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
-      .write_file("/file.txt", "text_formatted")
+  fn should_format_within_max_files_and_continue_next_run() {
+    let environment = TestEnvironmentBuilder::with_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt").add_remote_wasm_plugin();
+      })
+      .write_file("/file1.txt", "text1")
+      .write_file("/file2.txt", "text2")
+      .write_file("/file3.txt", "text3")
+      .initialize()
       .build();
-    run_test_cli(vec!["fmt", "/file.txt"], &environment).unwrap();
+    // format one file at a time so the order is deterministic
+    environment.set_max_threads(1);
+    // the changed files in git are formatted first
+    environment.add_command_output("git -C / diff --name-only --relative -z HEAD", "file3.txt\0");
+    environment.add_command_output("git -C / ls-files --others --exclude-standard -z", "");
+
+    run_test_cli(vec!["fmt", "--max-files", "1"], &environment).unwrap();
+    assert_eq!(
+      environment.take_stdout_messages(),
+      vec![
+        get_singular_formatted_text(),
+        format!("Stopped at the budget before formatting {} more files. Run again to continue.", "2".bold()),
+      ]
+    );
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+    assert_eq!(environment.read_file("/file1.txt").unwrap(), "text1");
+    assert_eq!(environment.read_file("/file2.txt").unwrap(), "text2");
+    assert_eq!(environment.read_file("/file3.txt").unwrap(), "text3_formatted");
+
+    // files known to be formatted don't use up the budget
+    run_test_cli(vec!["fmt", "--max-files", "2"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_plural_formatted_text(2)]);
+    assert_eq!(environment.read_file("/file1.txt").unwrap(), "text1_formatted");
+    assert_eq!(environment.read_file("/file2.txt").unwrap(), "text2_formatted");
+  }
+
+  #[test]
+  fn should_error_for_max_files_without_incremental() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt");
+      })
+      .write_file("/file.txt", "text")
+      .build();
+    let error_message = run_test_cli(vec!["fmt", "--max-files", "1", "--incremental=false"], &environment)
+      .err()
+      .unwrap();
+    assert_eq!(
+      error_message.to_string(),
+      "The --max-duration and --max-files flags require incremental formatting in order for the next run to continue where it left off."
+    );
+    let error_message = run_test_cli(vec!["fmt", "--max-duration", "5m", "--mode", "diff"], &environment).err().unwrap();
+    assert_eq!(
+      error_message.to_string(),
+      "The --max-duration and --max-files flags are only supported when writing the formatted files."
+    );
+  }
+
+  #[test]
+  fn should_format_without_incremental_when_specified() {
+    let file_path1 = "/subdir/file1.txt";
+    let no_change_msg = "No change:";
+    let environment = TestEnvironmentBuilder::with_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt").add_remote_wasm_plugin();
+      })
+      .write_file(&file_path1, "text1")
+      .initialize()
+      .build();
+
+    run_test_cli(vec!["fmt", "--incremental=false"], &environment).unwrap();
+
+    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+    assert_eq!(environment.read_file(&file_path1).unwrap(), "text1_formatted");
+
+    environment.clear_logs();
+    run_test_cli(vec!["fmt", "--incremental=false", "--verbose"], &environment).unwrap();
+    assert!(!environment.take_stderr_messages().iter().any(|msg| msg.contains(no_change_msg)));
+  }
+
+  #[test]
+  fn allow_skipping_stable_format() {
+    let file_path1 = "/file1.txt";
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file(&file_path1, "unstable_fmt_true")
+      .build();
+
+    run_test_cli(vec!["fmt", "--skip-stable-format", "*.txt"], &environment).unwrap();
+
+    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+    assert_eq!(environment.read_file(&file_path1).unwrap(), "unstable_fmt_false_formatted");
+  }
+
+  #[test]
+  fn should_not_output_when_no_files_need_formatting() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/file.txt", "text_formatted")
+      .build();
+    run_test_cli(vec!["fmt", "/file.txt"], &environment).unwrap();
     assert_eq!(environment.take_stdout_messages().len(), 0);
     assert_eq!(environment.take_stderr_messages().len(), 0);
   }
@@ -1578,6 +2688,94 @@ mod test {
     assert_eq!(environment.take_stderr_messages().len(), 0);
   }
 
+  #[test]
+  fn should_format_with_stdout_mode() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt");
+      })
+      .write_file("/file.txt", "text")
+      .write_file("/file2.txt", "text2")
+      .build();
+    run_test_cli(vec!["fmt", "--mode", "stdout", "/file.txt"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["text_formatted"]);
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+    assert_eq!(environment.read_file("/file.txt").unwrap(), "text");
+
+    let err = run_test_cli(vec!["fmt", "--mode", "stdout"], &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      "The stdout mode only supports formatting a single file, but found 2 files. Provide the path of one file."
+    );
+  }
+
+  #[test]
+  fn should_format_with_diff_mode() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt");
+      })
+      .write_file("/sub/file.txt", "text\n")
+      .write_file("/file2.txt", "text2_formatted")
+      .build();
+    run_test_cli(vec!["fmt", "--mode", "diff"], &environment).unwrap();
+    assert_eq!(
+      environment.take_stdout_messages(),
+      vec![get_unified_diff("sub/file.txt", "text\n", "text\n_formatted")]
+    );
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+    assert_eq!(environment.read_file("/sub/file.txt").unwrap(), "text\n");
+  }
+
+  #[test]
+  fn should_write_summary_file() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt");
+      })
+      .write_file("/sub/file.txt", "text")
+      .write_file("/file2.txt", "text2_formatted")
+      .build();
+    run_test_cli(vec!["fmt", "--summary-file", "out/summary.json"], &environment).unwrap();
+    let summary = serde_json::from_str::<RunSummary>(&environment.read_file("/out/summary.json").unwrap()).unwrap();
+    assert_eq!(summary.command, "fmt");
+    assert_eq!(summary.plugins.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), vec!["test-plugin"]);
+    assert_eq!(
+      summary.files,
+      vec![
+        FileRunSummary {
+          file_path: "file2.txt".to_string(),
+          hash: get_sha256_checksum("text2_formatted".as_bytes()),
+        },
+        FileRunSummary {
+          file_path: "sub/file.txt".to_string(),
+          hash: get_sha256_checksum("text_formatted".as_bytes()),
+        },
+      ]
+    );
+
+    // running again with the same inputs should have the same summary
+    run_test_cli(vec!["check", "--summary-file", "out/summary.json"], &environment).unwrap();
+    let check_summary = serde_json::from_str::<RunSummary>(&environment.read_file("/out/summary.json").unwrap()).unwrap();
+    assert_eq!(check_summary.command, "check");
+    assert_eq!(check_summary.config_hash, summary.config_hash);
+    assert_eq!(check_summary.plugins, summary.plugins);
+    assert_eq!(check_summary.files, summary.files);
+  }
+
+  #[test]
+  fn should_check_with_check_mode() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt");
+      })
+      .write_file("/file.txt", "const t=4;")
+      .build();
+    let error_message = run_test_cli(vec!["fmt", "--mode", "check"], &environment).err().unwrap();
+    assert_eq!(error_message.to_string(), get_singular_check_text());
+    assert_eq!(environment.read_file("/file.txt").unwrap(), "const t=4;");
+  }
+
   #[test]
   fn should_not_output_when_no_files_need_formatting_for_check() {
     let file_path = "/file.txt";
@@ -1604,7 +2802,140 @@ mod test {
         get_difference("const t=4;", "const t=4;_formatted"),
       ),]
     );
-    assert_eq!(environment.take_stderr_messages().len(), 0);
+    assert_eq!(environment.take_stderr_messages(), vec!["To format the file, run:\n  dprint fmt file.txt"]);
+  }
+
+  #[test]
+  fn should_output_check_difference_with_diff_options() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/file.txt", "const t=4;")
+      .build();
+    let error_message = run_test_cli(
+      vec!["check", "--diff-context", "1", "--word-diff=false", "--diff-colors", "colorblind", "/file.txt"],
+      &environment,
+    )
+    .err()
+    .unwrap();
+    assert_eq!(error_message.to_string(), get_singular_check_text());
+    let diff_options = DiffOptions {
+      context_lines: 1,
+      word_diff: false,
+      colors: DiffColors::parse("colorblind").unwrap(),
+    };
+    assert_eq!(
+      environment.take_stdout_messages(),
+      vec![format!(
+        "{}\n{}\n--",
+        format!("{} /file.txt:", "from".bold().red().to_string()),
+        get_difference_with_options("const t=4;", "const t=4;_formatted", &diff_options),
+      ),]
+    );
+  }
+
+  #[test]
+  fn should_error_for_invalid_diff_colors() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/file.txt", "const t=4;")
+      .build();
+    let error_message = run_test_cli(vec!["check", "--diff-colors", "added=pink", "/file.txt"], &environment)
+      .err()
+      .unwrap();
+    assert_eq!(
+      error_message.to_string(),
+      "Invalid diff color 'pink'. Expected one of: black, red, green, yellow, blue, magenta, cyan, white, grey"
+    );
+  }
+
+  #[test]
+  fn should_group_check_output_by_directory() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/a/file1.txt", "1")
+      .write_file("/a/b/file2.txt", "2")
+      .write_file("/c/file3.txt", "3")
+      .write_file("/file4.txt", "4")
+      .build();
+    let error_message = run_test_cli(vec!["check", "--group-by-directory", "**/*.txt"], &environment).err().unwrap();
+    assert_eq!(error_message.to_string(), get_plural_check_text(4));
+    let get_output = |file_path: &str, text: &str| {
+      format!(
+        "{}\n{}\n--",
+        format!("{} {}:", "from".bold().red().to_string(), file_path),
+        get_difference(text, &format!("{}_formatted", text)),
+      )
+      .lines()
+      .map(|line| format!("  {}", line))
+      .collect::<Vec<_>>()
+      .join("\n")
+    };
+    assert_eq!(
+      environment.take_stdout_messages(),
+      vec![
+        "./ (1 file):".to_string(),
+        get_output("/file4.txt", "4"),
+        "a/ (2 files):".to_string(),
+        get_output("/a/b/file2.txt", "2"),
+        get_output("/a/file1.txt", "1"),
+        "c/ (1 file):".to_string(),
+        get_output("/c/file3.txt", "3"),
+      ]
+    );
+  }
+
+  #[test]
+  fn should_group_check_output_by_directory_on_github_actions() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/a/file1.txt", "1")
+      .write_file("/c/file2.txt", "2")
+      .build();
+    environment.set_env_var("GITHUB_ACTIONS", "true");
+    let error_message = run_test_cli(vec!["check", "--group-by-directory", "**/*.txt"], &environment).err().unwrap();
+    assert_eq!(error_message.to_string(), get_plural_check_text(2));
+    let get_output = |file_path: &str, text: &str| {
+      format!(
+        "{}\n{}\n--",
+        format!("{} {}:", "from".bold().red().to_string(), file_path),
+        get_difference(text, &format!("{}_formatted", text)),
+      )
+    };
+    assert_eq!(
+      environment.take_stdout_messages(),
+      vec![
+        "::group::a/ (1 file)".to_string(),
+        get_output("/a/file1.txt", "1"),
+        "::endgroup::".to_string(),
+        "::group::c/ (1 file)".to_string(),
+        get_output("/c/file2.txt", "2"),
+        "::endgroup::".to_string(),
+      ]
+    );
+  }
+
+  #[test]
+  fn should_sort_check_output() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/b.txt", "b")
+      .write_file("/a/file.txt", "a")
+      .write_file("/Z.txt", "Z")
+      .write_file("/c.txt", "c")
+      .build();
+    let error_message = run_test_cli(vec!["check", "--sort-output", "**/*.txt"], &environment).err().unwrap();
+    assert_eq!(error_message.to_string(), get_plural_check_text(4));
+    let get_output = |file_path: &str, text: &str| {
+      format!(
+        "{}\n{}\n--",
+        format!("{} {}:", "from".bold().red().to_string(), file_path),
+        get_difference(text, &format!("{}_formatted", text)),
+      )
+    };
+    assert_eq!(
+      environment.take_stdout_messages(),
+      vec![
+        get_output("/Z.txt", "Z"),
+        get_output("/a/file.txt", "a"),
+        get_output("/b.txt", "b"),
+        get_output("/c.txt", "c"),
+      ]
+    );
   }
 
   #[test]
@@ -1633,7 +2964,241 @@ mod test {
         ),
       ]
     );
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec!["To format the files, run:\n  dprint fmt file1.txt file2.txt"]
+    );
+  }
+
+  #[test]
+  fn should_stop_checking_on_first_not_formatted_file_with_fail_fast() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/file1.txt", "const t=4;")
+      .write_file("/file2.txt", "const t=5;")
+      .write_file("/file3.txt", "const t=6;")
+      .build();
+    environment.set_max_threads(2); // one thread for formatting
+    let error_message = run_test_cli(vec!["check", "--fail-fast", "/file1.txt", "/file2.txt", "/file3.txt"], &environment)
+      .err()
+      .unwrap();
+    assert_eq!(error_message.to_string(), get_singular_check_text());
+    assert_eq!(environment.take_stdout_messages().len(), 1);
+    assert_eq!(environment.take_stderr_messages().len(), 1);
+  }
+
+  #[test]
+  fn should_not_fail_check_for_files_with_warn_severity() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_remote_wasm_plugin()
+          .add_config_section("checkSeverity", r#"{ "vendor/**": "warn", "vendor/ours/**": "error" }"#);
+      })
+      .write_file("/vendor/file.txt", "const t=4;")
+      .initialize()
+      .build();
+    run_test_cli(vec!["check", "**/*.txt"], &environment).unwrap();
+    assert_eq!(
+      environment.take_stdout_messages(),
+      vec![
+        format!(
+          "{}\n{}\n--",
+          format!("{} /vendor/file.txt:", "from".bold().red().to_string()),
+          get_difference("const t=4;", "const t=4;_formatted"),
+        ),
+        format!("Found {} not formatted file with warn severity.", "1".bold().to_string()),
+      ]
+    );
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+  }
+
+  #[test]
+  fn should_fail_check_for_files_with_error_severity_and_report_warn_severity_count() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_remote_wasm_plugin()
+          .add_config_section("checkSeverity", r#"{ "vendor/**": "warn", "vendor/ours/**": "error" }"#);
+      })
+      .write_file("/vendor/file.txt", "const t=4;")
+      .write_file("/vendor/ours/file.txt", "const t=4;")
+      .initialize()
+      .build();
+    let error_message = run_test_cli(vec!["check", "**/*.txt"], &environment).err().unwrap();
+    assert_eq!(error_message.to_string(), get_singular_check_text());
+    let logged_messages = environment.take_stdout_messages();
+    assert_eq!(logged_messages.len(), 3);
+    assert_eq!(
+      logged_messages[2],
+      format!("Found {} not formatted file with warn severity.", "1".bold().to_string())
+    );
+    // only includes the files with error severity
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec!["To format the file, run:\n  dprint fmt vendor/ours/file.txt"]
+    );
+  }
+
+  #[test]
+  fn should_format_deterministically() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_and_process_plugin()
+      .write_file("/c.txt", "text")
+      .write_file("/a.txt", "text")
+      .write_file("/b.txt", "text")
+      .write_file("/d.txt_ps", "text")
+      .build();
+    environment.set_max_threads(8);
+    run_test_cli(vec!["fmt", "--deterministic", "--verbose", "**/*.{txt,txt_ps}"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_plural_formatted_text(4)]);
+    let stderr_messages = environment.take_stderr_messages();
+    // one thread for each plugin
+    assert!(stderr_messages.iter().any(|msg| msg == "[VERBOSE] Max threads: 8\nThread count: 2"));
+    let mut formatted_txt_files = stderr_messages
+      .iter()
+      .filter_map(|msg| msg.strip_prefix("[VERBOSE] Formatted file: "))
+      .filter_map(|msg| msg.split(' ').next())
+      .filter(|file_path| file_path.ends_with(".txt"))
+      .collect::<Vec<_>>();
+    formatted_txt_files.dedup(); // ensuring a stable format formats each file again
+    assert_eq!(formatted_txt_files, vec!["/a.txt", "/b.txt", "/c.txt"]);
+  }
+
+  #[test]
+  fn should_log_provenance_of_each_file_when_verbose() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/file.txt", "text")
+      .build();
+    let get_provenance_messages = |stderr_messages: Vec<String>| {
+      stderr_messages
+        .into_iter()
+        .filter_map(|msg| msg.strip_prefix("[VERBOSE] Provenance: ").map(|msg| msg.to_string()))
+        .collect::<Vec<_>>()
+    };
+    run_test_cli(vec!["fmt", "--verbose", "**/*.txt"], &environment).unwrap();
+    let provenance_messages = get_provenance_messages(environment.take_stderr_messages());
+    assert_eq!(provenance_messages.len(), 1);
+    assert!(provenance_messages[0].starts_with("/file.txt plugins=test-plugin@0.1.0 config_hash="));
+    assert!(provenance_messages[0].contains(" source=formatted duration_ms="));
+    assert!(provenance_messages[0].ends_with(" input_bytes=4 output_bytes=14"));
+
+    // now the incremental cache knows it's formatted
+    environment.clear_logs();
+    run_test_cli(vec!["fmt", "--verbose", "**/*.txt"], &environment).unwrap();
+    let provenance_messages = get_provenance_messages(environment.take_stderr_messages());
+    assert_eq!(provenance_messages.len(), 1);
+    assert!(provenance_messages[0].ends_with(" source=incremental duration_ms=0 input_bytes=14 output_bytes=14"));
+  }
+
+  #[test]
+  fn should_write_fix_commands_for_check() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/sub dir/file1.txt", "text")
+      .write_file("/file2.txt", "text_formatted")
+      .build();
+    let error_message = run_test_cli(
+      vec!["check", "--summary-file", "out/summary.json", "--fix-script", "out/fix.sh", "**/*.txt"],
+      &environment,
+    )
+    .err()
+    .unwrap();
+    assert_eq!(error_message.to_string(), get_singular_check_text());
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec!["To format the file, run:\n  dprint fmt 'sub dir/file1.txt'"]
+    );
+    assert_eq!(
+      environment.read_file("/out/fix.sh").unwrap(),
+      "#!/bin/sh\nset -e\ndprint fmt 'sub dir/file1.txt'\n"
+    );
+    let summary = serde_json::from_str::<RunSummary>(&environment.read_file("/out/summary.json").unwrap()).unwrap();
+    assert_eq!(summary.fix_commands, vec!["dprint fmt 'sub dir/file1.txt'"]);
+
+    // no commands once formatted
+    run_test_cli(vec!["fmt", "**/*.txt"], &environment).unwrap();
+    run_test_cli(
+      vec!["check", "--summary-file", "out/summary.json", "--fix-script", "out/fix.sh", "**/*.txt"],
+      &environment,
+    )
+    .unwrap();
+    assert_eq!(environment.read_file("/out/fix.sh").unwrap(), "#!/bin/sh\nset -e\n");
+    let summary = serde_json::from_str::<RunSummary>(&environment.read_file("/out/summary.json").unwrap()).unwrap();
+    assert_eq!(summary.fix_commands, Vec::<String>::new());
+  }
+
+  #[test]
+  fn should_check_without_writing_when_asserting_no_writes() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt");
+      })
+      .write_file("/file1.txt", "text_formatted")
+      .write_file("/file2.txt", "text")
+      .build();
+    // would error if the incremental file, stats, or failed files were saved
+    run_test_cli(vec!["check", "--assert-no-writes", "--incremental", "/file1.txt"], &environment).unwrap();
+
+    let error_message = run_test_cli(vec!["check", "--assert-no-writes", "**/*.txt"], &environment).err().unwrap();
+    assert_eq!(error_message.to_string(), get_singular_check_text());
+    environment.clear_logs();
+
+    // explicitly requested writes still fail loudly
+    let error_message = run_test_cli(vec!["check", "--assert-no-writes", "--fix-script", "fix.sh", "**/*.txt"], &environment)
+      .err()
+      .unwrap();
+    assert_eq!(
+      error_message.to_string(),
+      "Attempted to write to the file system when running with --assert-no-writes:\n  /fix.sh"
+    );
+    assert!(!environment.path_exists("/fix.sh"));
+    environment.clear_logs();
+
+    let error_message = run_test_cli(vec!["fmt", "--assert-no-writes"], &environment).err().unwrap();
+    assert_eq!(
+      error_message.to_string(),
+      "The --assert-no-writes flag is not supported when writing the formatted files. Use `dprint check` instead."
+    );
+    assert_eq!(environment.read_file("/file2.txt").unwrap(), "text");
+
+    // writes again without the flag
+    run_test_cli(vec!["fmt"], &environment).unwrap();
+    assert_eq!(environment.read_file("/file2.txt").unwrap(), "text_formatted");
+  }
+
+  #[test]
+  fn should_not_format_files_with_plugin_disabled_by_overrides() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_remote_wasm_plugin()
+          .add_config_section("overrides", r#"{ "docs/translations/**": { "disablePlugins": ["test-plugin"] } }"#);
+      })
+      .write_file("/docs/file.txt", "text")
+      .write_file("/docs/translations/file.txt", "text")
+      .initialize()
+      .build();
+    run_test_cli(vec!["fmt", "**/*.txt"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
     assert_eq!(environment.take_stderr_messages().len(), 0);
+    assert_eq!(environment.read_file("/docs/file.txt").unwrap(), "text_formatted");
+    assert_eq!(environment.read_file("/docs/translations/file.txt").unwrap(), "text");
+  }
+
+  #[test]
+  fn should_error_when_overrides_disable_unknown_plugin() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_remote_wasm_plugin()
+          .add_config_section("overrides", r#"{ "docs/**": { "disablePlugins": ["markdown"] } }"#);
+      })
+      .write_file("/docs/file.txt", "text")
+      .initialize()
+      .build();
+    let error_message = run_test_cli(vec!["fmt", "**/*.txt"], &environment).err().unwrap();
+    assert_eq!(
+      error_message.to_string(),
+      "Could not find a plugin with the name or configuration key 'markdown' to disable for 'docs/**' in 'overrides' property."
+    );
   }
 
   #[test]
@@ -1693,6 +3258,23 @@ mod test {
     assert_eq!(environment.take_stdout_messages(), vec!["plugin: format this text_formatted_process_formatted"]);
   }
 
+  #[test]
+  fn should_stdin_fmt_range() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_and_process_plugin().build();
+    // the process plugin formats the range
+    let test_std_in = TestStdInReader::from("testing");
+    run_test_cli_with_stdin(vec!["fmt", "--stdin", "file.txt_ps", "--range", "1:2"], &environment, test_std_in).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["t_formatted_process_sting_formatted_process"]);
+
+    // the Wasm plugin formats the entire text, but only the changes to the lines of the range are kept
+    let test_std_in = TestStdInReader::from("a\nb\nc");
+    run_test_cli_with_stdin(vec!["fmt", "--stdin", "file.txt", "--line-range", "1:2"], &environment, test_std_in).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["a\nb\nc"]);
+    let test_std_in = TestStdInReader::from("a\nb\nc");
+    run_test_cli_with_stdin(vec!["fmt", "--stdin", "file.txt", "--line-range", "3:3"], &environment, test_std_in).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["a\nb\nc_formatted"]);
+  }
+
   #[test]
   fn should_handle_error_for_stdin_fmt() {
     // it should not output anything when downloading plugins
@@ -1802,6 +3384,28 @@ mod test {
     );
   }
 
+  #[test]
+  fn should_format_with_other_plugins_when_allowing_plugin_failures() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .add_remote_process_plugin()
+      .with_default_config(|c| {
+        c.add_remote_wasm_plugin().add_plugin("https://plugins.dprint.dev/test-process.json");
+      })
+      .write_file("/test.txt", "text")
+      .write_file("/test.txt_ps", "text")
+      .build();
+    run_test_cli(vec!["fmt", "--allow-plugin-failures", "*.*"], &environment).unwrap();
+
+    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
+    assert!(environment.take_stderr_messages().iter().any(|msg| msg.starts_with(concat!(
+      "Skipping the files of a plugin that failed to load. ",
+      "Error resolving plugin https://plugins.dprint.dev/test-process.json: The plugin must have a checksum specified"
+    ))));
+    assert_eq!(environment.read_file("/test.txt").unwrap(), "text_formatted");
+    assert_eq!(environment.read_file("/test.txt_ps").unwrap(), "text");
+  }
+
   #[test]
   fn should_error_if_process_plugin_has_wrong_checksum_in_config() {
     let environment = TestEnvironmentBuilder::with_remote_process_plugin()
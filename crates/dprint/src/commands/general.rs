@@ -7,10 +7,15 @@ use crate::environment::Environment;
 use crate::paths::get_and_resolve_file_paths;
 use crate::paths::get_file_paths_by_plugins;
 use crate::plugins::get_plugins_from_args;
+use crate::plugins::get_provenance_log_path;
+use crate::plugins::read_provenance_log;
 use crate::plugins::resolve_plugins_and_err_if_empty;
 use crate::plugins::PluginResolver;
+use crate::utils::display_path;
 use crate::utils::get_table_text;
+use crate::utils::get_utc_time_text;
 use crate::utils::is_out_of_date;
+use crate::utils::sort_listing;
 
 pub fn output_version<TEnvironment: Environment>(environment: &TEnvironment) -> Result<()> {
   environment.log(&format!("{} {}", env!("CARGO_PKG_NAME"), environment.cli_version()));
@@ -30,8 +35,11 @@ pub async fn output_help<TEnvironment: Environment>(
   // now check for the plugins
   let plugins_result = get_plugins_from_args(args, environment, plugin_resolver).await;
   match plugins_result {
-    Ok(plugins) => {
+    Ok(mut plugins) => {
       if !plugins.is_empty() {
+        if args.sort_output.is_some() {
+          plugins.sort_by(|a, b| a.name().as_bytes().cmp(b.name().as_bytes()));
+        }
         let table_text = get_table_text(plugins.iter().map(|plugin| (plugin.name(), plugin.help_url())).collect());
         environment.log("\nPLUGINS HELP:");
         environment.log(&console_static_text::strip_ansi_codes(&table_text.render(
@@ -67,7 +75,11 @@ pub async fn output_license<TEnvironment: Environment>(
   environment.log(std::str::from_utf8(include_bytes!("../../LICENSE"))?);
 
   // now check for the plugins
-  for plugin in get_plugins_from_args(args, environment, plugin_resolver).await? {
+  let mut plugins = get_plugins_from_args(args, environment, plugin_resolver).await?;
+  if args.sort_output.is_some() {
+    plugins.sort_by(|a, b| a.name().as_bytes().cmp(b.name().as_bytes()));
+  }
+  for plugin in plugins {
     environment.log(&format!("\n==== {} LICENSE ====", plugin.name().to_uppercase()));
     let initialized_plugin = plugin.initialize().await?;
     environment.log(&initialized_plugin.license_text().await?);
@@ -76,9 +88,35 @@ pub async fn output_license<TEnvironment: Environment>(
   Ok(())
 }
 
+pub fn output_cache_log(environment: &impl Environment) -> Result<()> {
+  let entries = read_provenance_log(environment);
+  if entries.is_empty() {
+    environment.log("The provenance log is empty.");
+  }
+  for entry in entries {
+    environment.log(&format!(
+      "{} {} {} from {}\n  sha256: {}\n  user: {}",
+      get_utc_time_text(entry.time),
+      entry.plugin_name,
+      entry.plugin_version,
+      entry.source,
+      entry.checksum,
+      entry.user.as_deref().unwrap_or("unknown"),
+    ));
+  }
+  Ok(())
+}
+
 pub fn clear_cache(environment: &impl Environment) -> Result<()> {
   let cache_dir = environment.get_cache_dir();
+  // keep the provenance log since it's an audit trail of what was fetched
+  let provenance_log_path = get_provenance_log_path(environment);
+  let provenance_log_bytes = environment.read_file_bytes(&provenance_log_path).ok();
   environment.remove_dir_all(&cache_dir)?;
+  if let Some(provenance_log_bytes) = provenance_log_bytes {
+    environment.mk_dir_all(&cache_dir)?;
+    environment.write_file_bytes(&provenance_log_path, &provenance_log_bytes)?;
+  }
   environment.log_stderr(&format!("Deleted {}", cache_dir.display()));
   Ok(())
 }
@@ -92,11 +130,16 @@ pub async fn output_file_paths<TEnvironment: Environment>(
   let config = resolve_config_from_args(args, environment)?;
   let plugins = resolve_plugins_and_err_if_empty(args, &config, environment, plugin_resolver).await?;
   let resolved_file_paths = get_and_resolve_file_paths(&config, &cmd.patterns, environment).await?;
-  let file_paths_by_plugin = get_file_paths_by_plugins(&plugins, resolved_file_paths, &config.base_path)?;
-
-  let file_paths = file_paths_by_plugin.values().flat_map(|x| x.iter());
+  let file_paths_by_plugin = get_file_paths_by_plugins(&plugins, resolved_file_paths, &config.base_path, &config.overrides, &cmd.patterns.associations)?;
+
+  let mut file_paths = file_paths_by_plugin
+    .values()
+    .flat_map(|x| x.iter())
+    .map(|file_path| display_path(file_path, args.sort_output))
+    .collect::<Vec<_>>();
+  sort_listing(&mut file_paths, args.sort_output);
   for file_path in file_paths {
-    environment.log(&file_path.display().to_string())
+    environment.log(&file_path)
   }
   Ok(())
 }
@@ -206,6 +249,94 @@ mod test {
     assert_eq!(logged_messages, vec!["/file.txt", "/file2.txt", "/file3.txt_ps"]);
   }
 
+  #[test]
+  fn should_sort_output_file_paths() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/b.txt", "")
+      .write_file("/a/file.txt", "")
+      .write_file("/Z.txt", "")
+      .build();
+    run_test_cli(vec!["output-file-paths", "--sort-output", "**/*.txt"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["/Z.txt", "/a/file.txt", "/b.txt"]);
+  }
+
+  #[test]
+  fn should_warn_for_patterns_differing_by_case() {
+    let environment = TestEnvironmentBuilder::with_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_remote_wasm_plugin().add_includes("**/*.txt").add_includes("**/*.TXT");
+      })
+      .write_file("/file.txt", "")
+      .initialize()
+      .build();
+    run_test_cli(vec!["output-file-paths"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["/file.txt"]);
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec!["WARNING: The patterns '**/*.txt' and '**/*.TXT' in the 'includes' property only differ by case, so they match the same files on case-insensitive file systems."]
+    );
+  }
+
+  #[test]
+  fn should_output_file_paths_of_affected_workspace_packages() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/package.json", r#"{ "workspaces": ["packages/*"] }"#)
+      .write_file("/packages/a/package.json", r#"{ "name": "a", "dependencies": { "b": "^1.0.0" } }"#)
+      .write_file("/packages/a/file.txt", "")
+      .write_file("/packages/b/package.json", r#"{ "name": "b" }"#)
+      .write_file("/packages/b/file.txt", "")
+      .write_file("/packages/c/package.json", r#"{ "name": "c" }"#)
+      .write_file("/packages/c/file.txt", "")
+      .write_file("/file.txt", "")
+      .build();
+    run_test_cli(vec!["output-file-paths", "**/*.txt", "--affected", "packages/b/file.txt"], &environment).unwrap();
+    let mut logged_messages = environment.take_stdout_messages();
+    logged_messages.sort();
+    assert_eq!(logged_messages, vec!["/packages/a/file.txt", "/packages/b/file.txt"]);
+  }
+
+  #[test]
+  fn should_skip_affected_changed_files_not_on_disk() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/package.json", r#"{ "workspaces": ["packages/*"] }"#)
+      .write_file("/packages/a/package.json", r#"{ "name": "a" }"#)
+      .write_file("/packages/a/file.txt", "")
+      .write_file("/packages/b/package.json", r#"{ "name": "b" }"#)
+      .write_file("/packages/b/file.txt", "")
+      .build();
+    run_test_cli(
+      vec![
+        "output-file-paths",
+        "**/*.txt",
+        "--verbose",
+        "--affected",
+        "packages/a/deleted.txt,packages/sparse/dir/file.txt",
+      ],
+      &environment,
+    )
+    .unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["/packages/a/file.txt"]);
+    let stderr_messages = environment.take_stderr_messages();
+    assert!(stderr_messages
+      .iter()
+      .any(|m| m == "[VERBOSE] Skipping changed file not on disk (ex. sparse checkout): /packages/sparse/dir/file.txt"));
+    assert!(!stderr_messages
+      .iter()
+      .any(|m| m.contains("Skipping changed file not on disk") && m.contains("deleted.txt")));
+  }
+
+  #[test]
+  fn should_error_affected_when_no_workspace_packages() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/file.txt", "")
+      .build();
+    let error_message = run_test_cli(vec!["output-file-paths", "--affected", "file.txt"], &environment).err().unwrap();
+    assert_eq!(
+      error_message.to_string(),
+      "Could not find any workspace packages in /. Only package.json workspaces and Cargo workspaces are supported."
+    );
+  }
+
   #[test]
   fn should_not_output_file_paths_not_supported_by_plugins() {
     let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_and_process_plugin()
@@ -326,6 +457,68 @@ mod test {
     assert_eq!(environment.take_stderr_messages(), vec!["Deleted /cache"]);
     assert_eq!(environment.is_dir_deleted("/cache"), true);
   }
+
+  #[test]
+  fn should_recover_from_interrupted_runs() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt");
+      })
+      .write_file("/file.txt", "text_formatted")
+      .build();
+    environment.write_file("/cache/remote/1.50.tmp", "").unwrap();
+    environment
+      .write_file("/cache/processes/60.json", r#"{"cliProcessId":61,"executableFilePath":"/cache/plugins/a/a"}"#)
+      .unwrap();
+    environment.set_running_process(60, "/cache/plugins/a/a");
+
+    // nothing is recovered when writes are disallowed
+    run_test_cli(vec!["check", "--assert-no-writes", "--incremental=false"], &environment).unwrap();
+    environment.clear_logs();
+    assert!(environment.is_running_process(60));
+
+    // or for commands that don't use plugins
+    run_test_cli(vec!["--version"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![format!("dprint {}", environment.cli_version())]);
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+    assert!(environment.is_running_process(60));
+
+    run_test_cli(vec!["check"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages().len(), 0);
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec!["Recovered from a previous run that didn't exit cleanly: removed 1 incomplete download file and stopped 1 orphaned process plugin."]
+    );
+    assert!(!environment.is_running_process(60));
+    assert!(!environment.path_exists("/cache/remote/1.50.tmp"));
+    assert!(!environment.path_exists("/cache/processes/60.json"));
+
+    run_test_cli(vec!["check"], &environment).unwrap();
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+  }
+
+  #[test]
+  fn should_output_cache_log_and_keep_it_when_clearing_cache() {
+    let environment = TestEnvironmentBuilder::with_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_remote_wasm_plugin();
+      })
+      .build();
+    run_test_cli(vec!["cache", "log"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["The provenance log is empty."]);
+
+    run_test_cli(vec!["license"], &environment).unwrap(); // downloads the plugin
+    environment.clear_logs();
+    run_test_cli(vec!["cache", "log"], &environment).unwrap();
+    let messages = environment.take_stdout_messages();
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].contains(" test-plugin 0.1.0 from https://plugins.dprint.dev/test-plugin.wasm\n  sha256: "));
+
+    run_test_cli(vec!["clear-cache"], &environment).unwrap();
+    environment.clear_logs();
+    run_test_cli(vec!["cache", "log"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), messages);
+  }
   #[test]
   fn should_output_license_for_sub_command_with_no_plugins() {
     let environment = TestEnvironment::new();
@@ -0,0 +1,297 @@
+use anyhow::bail;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::BufRead;
+use std::io::Write;
+
+pub const PARSE_ERROR_CODE: i64 = -32700;
+pub const INVALID_REQUEST_CODE: i64 = -32600;
+pub const METHOD_NOT_FOUND_CODE: i64 = -32601;
+pub const INVALID_PARAMS_CODE: i64 = -32602;
+pub const REQUEST_FAILED_CODE: i64 = -32803;
+pub const REQUEST_CANCELLED_CODE: i64 = -32800;
+
+/// A JSON-RPC message of the Language Server Protocol.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LspMessage {
+  #[serde(default)]
+  pub jsonrpc: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub id: Option<Value>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub method: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub params: Option<Value>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub result: Option<Value>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<LspResponseError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LspResponseError {
+  pub code: i64,
+  pub message: String,
+}
+
+impl LspMessage {
+  pub fn response(id: Value, result: Value) -> Self {
+    LspMessage {
+      jsonrpc: "2.0".to_string(),
+      id: Some(id),
+      result: Some(result),
+      ..Default::default()
+    }
+  }
+
+  pub fn error_response(id: Value, code: i64, message: String) -> Self {
+    LspMessage {
+      jsonrpc: "2.0".to_string(),
+      id: Some(id),
+      error: Some(LspResponseError { code, message }),
+      ..Default::default()
+    }
+  }
+
+  #[cfg(test)]
+  pub fn request(id: u32, method: &str, params: Value) -> Self {
+    LspMessage {
+      jsonrpc: "2.0".to_string(),
+      id: Some(id.into()),
+      method: Some(method.to_string()),
+      params: Some(params),
+      ..Default::default()
+    }
+  }
+
+  #[cfg(test)]
+  pub fn notification(method: &str, params: Value) -> Self {
+    LspMessage {
+      jsonrpc: "2.0".to_string(),
+      method: Some(method.to_string()),
+      params: Some(params),
+      ..Default::default()
+    }
+  }
+
+  pub fn write(&self, writer: &mut impl Write) -> Result<()> {
+    let content = serde_json::to_vec(self)?;
+    write!(writer, "Content-Length: {}\r\n\r\n", content.len())?;
+    writer.write_all(&content)?;
+    writer.flush()?;
+    Ok(())
+  }
+}
+
+/// Reads the content of the next message, which is `None` when the input is closed.
+pub fn read_message_content(reader: &mut impl BufRead) -> Result<Option<Vec<u8>>> {
+  let mut content_length = None;
+  let mut has_read_header = false;
+  loop {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+      if has_read_header {
+        bail!("Unexpected end of input while reading the message headers.");
+      }
+      return Ok(None);
+    }
+    has_read_header = true;
+    let line = line.trim_end();
+    if line.is_empty() {
+      break;
+    }
+    if let Some((name, value)) = line.split_once(':') {
+      if name.trim().eq_ignore_ascii_case("Content-Length") {
+        match value.trim().parse::<usize>() {
+          Ok(length) => content_length = Some(length),
+          Err(_) => bail!("Invalid Content-Length header: {}", line),
+        }
+      }
+    }
+  }
+  let content_length = match content_length {
+    Some(content_length) => content_length,
+    None => bail!("Expected a Content-Length header for the message."),
+  };
+  let mut content = vec![0; content_length];
+  reader.read_exact(&mut content)?;
+  Ok(Some(content))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LspPosition {
+  pub line: u32,
+  /// Offset in UTF-16 code units.
+  pub character: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LspRange {
+  pub start: LspPosition,
+  pub end: LspPosition,
+}
+
+impl LspRange {
+  /// Gets the range of the entire text.
+  pub fn for_text(text: &str) -> Self {
+    let last_line_start = text.rfind('\n').map(|index| index + 1).unwrap_or(0);
+    LspRange {
+      start: LspPosition { line: 0, character: 0 },
+      end: LspPosition {
+        line: text.matches('\n').count() as u32,
+        character: text[last_line_start..].encode_utf16().count() as u32,
+      },
+    }
+  }
+
+  /// Gets the byte range in the text, clamping positions that are out of bounds.
+  pub fn to_byte_range(self, text: &str) -> std::ops::Range<usize> {
+    let start = get_byte_index(text, self.start);
+    let end = get_byte_index(text, self.end);
+    start..std::cmp::max(start, end)
+  }
+}
+
+fn get_byte_index(text: &str, position: LspPosition) -> usize {
+  let mut line_start = 0;
+  for _ in 0..position.line {
+    match text[line_start..].find('\n') {
+      Some(index) => line_start += index + 1,
+      None => return text.len(),
+    }
+  }
+  let line_end = text[line_start..].find('\n').map(|index| line_start + index).unwrap_or(text.len());
+  let mut utf16_count = 0;
+  for (index, c) in text[line_start..line_end].char_indices() {
+    if utf16_count >= position.character as usize {
+      return line_start + index;
+    }
+    utf16_count += c.len_utf16();
+  }
+  line_end
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextEdit {
+  pub range: LspRange,
+  pub new_text: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TextDocumentIdentifier {
+  pub uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TextDocumentItem {
+  pub uri: String,
+  pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidOpenTextDocumentParams {
+  pub text_document: TextDocumentItem,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TextDocumentContentChangeEvent {
+  pub range: Option<LspRange>,
+  pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidChangeTextDocumentParams {
+  pub text_document: TextDocumentIdentifier,
+  pub content_changes: Vec<TextDocumentContentChangeEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidCloseTextDocumentParams {
+  pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentFormattingParams {
+  pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentRangeFormattingParams {
+  pub text_document: TextDocumentIdentifier,
+  pub range: LspRange,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CancelParams {
+  pub id: Value,
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn should_read_and_write_messages() {
+    let mut bytes = Vec::new();
+    LspMessage::response(1.into(), Value::Null).write(&mut bytes).unwrap();
+    LspMessage::error_response("a".into(), METHOD_NOT_FOUND_CODE, "Unknown method.".to_string())
+      .write(&mut bytes)
+      .unwrap();
+    let text = String::from_utf8(bytes.clone()).unwrap();
+    assert_eq!(
+      text,
+      concat!(
+        "Content-Length: 38\r\n\r\n",
+        r#"{"jsonrpc":"2.0","id":1,"result":null}"#,
+        "Content-Length: 78\r\n\r\n",
+        r#"{"jsonrpc":"2.0","id":"a","error":{"code":-32601,"message":"Unknown method."}}"#,
+      )
+    );
+
+    let mut reader = std::io::BufReader::new(bytes.as_slice());
+    assert_eq!(
+      read_message_content(&mut reader).unwrap().unwrap(),
+      br#"{"jsonrpc":"2.0","id":1,"result":null}"#.to_vec()
+    );
+    assert!(read_message_content(&mut reader).unwrap().is_some());
+    assert!(read_message_content(&mut reader).unwrap().is_none());
+
+    let mut reader = std::io::BufReader::new("Content-Type: text\r\n\r\n{}".as_bytes());
+    assert_eq!(
+      read_message_content(&mut reader).err().unwrap().to_string(),
+      "Expected a Content-Length header for the message."
+    );
+  }
+
+  #[test]
+  fn should_convert_ranges() {
+    let text = "a\n😀b\nc";
+    let range = LspRange {
+      start: LspPosition { line: 1, character: 2 },
+      end: LspPosition { line: 2, character: 1 },
+    };
+    assert_eq!(&text[range.to_byte_range(text)], "b\nc");
+    let range = LspRange {
+      start: LspPosition { line: 0, character: 10 },
+      end: LspPosition { line: 10, character: 0 },
+    };
+    assert_eq!(range.to_byte_range(text), 1..text.len());
+    assert_eq!(
+      LspRange::for_text(text),
+      LspRange {
+        start: LspPosition { line: 0, character: 0 },
+        end: LspPosition { line: 2, character: 1 },
+      }
+    );
+  }
+}
@@ -0,0 +1,459 @@
+use anyhow::bail;
+use anyhow::Result;
+use dprint_core::plugins::Host;
+use dprint_core::plugins::HostFormatRequest;
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+mod messages;
+
+use crate::arg_parser::CliArgs;
+use crate::arg_parser::FilePatternArgs;
+use crate::configuration::ResolvedConfig;
+use crate::environment::Environment;
+use crate::patterns::FileMatcher;
+use crate::plugins::PluginResolver;
+use crate::plugins::PluginsCollection;
+
+use super::editor::resolve_buffer_file_path;
+use super::editor::resolve_latest_config;
+
+use self::messages::*;
+
+/// Runs a Language Server Protocol server over stdio (`dprint lsp`).
+pub async fn run_lsp_server<TEnvironment: Environment>(
+  args: &CliArgs,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+  plugin_pools: Arc<PluginsCollection<TEnvironment>>,
+) -> Result<()> {
+  let mut server = LspServer::new(args, environment, plugin_resolver, plugin_pools, environment.stdin(), environment.stdout());
+  server.run().await
+}
+
+struct LspContext {
+  writer: Mutex<Box<dyn Write + Send>>,
+  cancellation_tokens: Mutex<HashMap<String, Arc<CancellationToken>>>,
+}
+
+impl LspContext {
+  fn send(&self, message: LspMessage) {
+    if let Err(err) = message.write(&mut *self.writer.lock()) {
+      panic!("Error writing to the language client. {:#}", err);
+    }
+  }
+}
+
+/// A format request along with the text and name of the document being formatted.
+struct LspFormatRequest {
+  uri: String,
+  file_text: String,
+  token: Arc<CancellationToken>,
+  request: HostFormatRequest,
+}
+
+struct LspServer<'a, TEnvironment: Environment> {
+  messages: UnboundedReceiver<Result<Option<Vec<u8>>>>,
+  config: Option<ResolvedConfig>,
+  args: &'a CliArgs,
+  environment: &'a TEnvironment,
+  plugin_resolver: &'a PluginResolver<TEnvironment>,
+  plugins_collection: Arc<PluginsCollection<TEnvironment>>,
+  /// The text of the documents open in the client by uri.
+  documents: HashMap<String, String>,
+  context: Arc<LspContext>,
+  concurrency_limiter: Arc<Semaphore>,
+  is_shutdown: bool,
+}
+
+impl<'a, TEnvironment: Environment> LspServer<'a, TEnvironment> {
+  pub fn new(
+    args: &'a CliArgs,
+    environment: &'a TEnvironment,
+    plugin_resolver: &'a PluginResolver<TEnvironment>,
+    plugin_pools: Arc<PluginsCollection<TEnvironment>>,
+    reader: Box<dyn Read + Send>,
+    writer: Box<dyn Write + Send>,
+  ) -> Self {
+    let max_cores = environment.max_threads();
+    Self {
+      messages: spawn_message_reader(reader),
+      config: None,
+      args,
+      environment,
+      plugin_resolver,
+      plugins_collection: plugin_pools,
+      documents: Default::default(),
+      context: Arc::new(LspContext {
+        writer: Mutex::new(writer),
+        cancellation_tokens: Default::default(),
+      }),
+      concurrency_limiter: Arc::new(Semaphore::new(std::cmp::max(1, max_cores - 1))),
+      is_shutdown: false,
+    }
+  }
+
+  pub async fn run(&mut self) -> Result<()> {
+    loop {
+      let content = match self.messages.recv().await {
+        Some(result) => match result? {
+          Some(content) => content,
+          None => return Ok(()), // the client closed the input
+        },
+        None => return Ok(()),
+      };
+      let message = match serde_json::from_slice::<LspMessage>(&content) {
+        Ok(message) => message,
+        Err(err) => {
+          self.context.send(LspMessage::error_response(
+            Value::Null,
+            PARSE_ERROR_CODE,
+            format!("Error parsing message. {:#}", err),
+          ));
+          continue;
+        }
+      };
+      // ignore responses since the server doesn't send any requests
+      let method = match message.method {
+        Some(method) => method,
+        None => continue,
+      };
+      let id = match message.id {
+        Some(id) => id,
+        None => {
+          if method == "exit" {
+            self.plugins_collection.drop_and_shutdown_initialized().await;
+            if !self.is_shutdown {
+              bail!("Received the exit notification before the shutdown request.");
+            }
+            return Ok(());
+          }
+          if let Err(err) = self.handle_notification(&method, message.params) {
+            log_verbose!(self.environment, "Error handling {} notification: {:#}", method, err);
+          }
+          continue;
+        }
+      };
+      if self.is_shutdown {
+        self
+          .context
+          .send(LspMessage::error_response(id, INVALID_REQUEST_CODE, "The server was shut down.".to_string()));
+        continue;
+      }
+
+      match method.as_str() {
+        "initialize" => {
+          self.context.send(LspMessage::response(
+            id,
+            json!({
+              "capabilities": {
+                "textDocumentSync": 1, // full
+                "documentFormattingProvider": true,
+                "documentRangeFormattingProvider": true,
+              },
+              "serverInfo": {
+                "name": "dprint",
+                "version": self.environment.cli_version(),
+              },
+            }),
+          ));
+        }
+        "shutdown" => {
+          self.is_shutdown = true;
+          self.plugins_collection.drop_and_shutdown_initialized().await;
+          self.context.send(LspMessage::response(id, Value::Null));
+        }
+        "textDocument/formatting" => match parse_params::<DocumentFormattingParams>(message.params) {
+          Ok(params) => self.format(id, params.text_document.uri, None).await,
+          Err(err) => self.context.send(LspMessage::error_response(id, INVALID_PARAMS_CODE, format!("{:#}", err))),
+        },
+        "textDocument/rangeFormatting" => match parse_params::<DocumentRangeFormattingParams>(message.params) {
+          Ok(params) => self.format(id, params.text_document.uri, Some(params.range)).await,
+          Err(err) => self.context.send(LspMessage::error_response(id, INVALID_PARAMS_CODE, format!("{:#}", err))),
+        },
+        _ => {
+          self
+            .context
+            .send(LspMessage::error_response(id, METHOD_NOT_FOUND_CODE, format!("Unknown method: {}", method)));
+        }
+      }
+    }
+  }
+
+  fn handle_notification(&mut self, method: &str, params: Option<Value>) -> Result<()> {
+    match method {
+      "textDocument/didOpen" => {
+        let params = parse_params::<DidOpenTextDocumentParams>(params)?;
+        self.documents.insert(params.text_document.uri, params.text_document.text);
+      }
+      "textDocument/didChange" => {
+        let params = parse_params::<DidChangeTextDocumentParams>(params)?;
+        let text = self.documents.entry(params.text_document.uri).or_default();
+        for change in params.content_changes {
+          match change.range {
+            Some(range) => {
+              let byte_range = range.to_byte_range(text);
+              text.replace_range(byte_range, &change.text);
+            }
+            None => *text = change.text,
+          }
+        }
+      }
+      "textDocument/didClose" => {
+        let params = parse_params::<DidCloseTextDocumentParams>(params)?;
+        self.documents.remove(&params.text_document.uri);
+      }
+      "$/cancelRequest" => {
+        let params = parse_params::<CancelParams>(params)?;
+        if let Some(token) = self.context.cancellation_tokens.lock().remove(&params.id.to_string()) {
+          token.cancel();
+        }
+      }
+      _ => {} // other notifications aren't necessary for formatting
+    }
+    Ok(())
+  }
+
+  async fn format(&mut self, id: Value, uri: String, range: Option<LspRange>) {
+    let format_request = match self.create_format_request(uri, range).await {
+      Ok(Some(format_request)) => format_request,
+      Ok(None) => {
+        self.context.send(LspMessage::response(id, Value::Null));
+        return;
+      }
+      Err(err) => {
+        self.context.send(LspMessage::error_response(id, REQUEST_FAILED_CODE, format!("{:#}", err)));
+        return;
+      }
+    };
+
+    let token = format_request.token.clone();
+    self.context.cancellation_tokens.lock().insert(id.to_string(), token.clone());
+    let plugins_collection = self.plugins_collection.clone();
+    let context = self.context.clone();
+    let concurrency_limiter = self.concurrency_limiter.clone();
+    let _ignore = tokio::task::spawn(async move {
+      let _permit = concurrency_limiter.acquire().await;
+      let result = if token.is_cancelled() {
+        None
+      } else {
+        Some(plugins_collection.format(format_request.request).await)
+      };
+      context.cancellation_tokens.lock().remove(&id.to_string());
+      // don't send back output that's stale
+      let message = match result {
+        _ if token.is_cancelled() => LspMessage::error_response(id, REQUEST_CANCELLED_CODE, "The request was cancelled.".to_string()),
+        Some(Ok(Some(formatted_text))) => {
+          let edit = TextEdit {
+            // replace the entire document, which is what most formatters send back
+            range: LspRange::for_text(&format_request.file_text),
+            new_text: formatted_text,
+          };
+          LspMessage::response(id, json!([edit]))
+        }
+        Some(Ok(None)) | None => LspMessage::response(id, Value::Null),
+        Some(Err(err)) => LspMessage::error_response(id, REQUEST_FAILED_CODE, format!("Error formatting {}. {:#}", format_request.uri, err)),
+      };
+      context.send(message);
+    });
+  }
+
+  /// Gets the request to format the document or `None` when the document is excluded by the configuration.
+  async fn create_format_request(&mut self, uri: String, range: Option<LspRange>) -> Result<Option<LspFormatRequest>> {
+    let file_path = get_file_path_from_uri(&uri)?;
+    let file_path = resolve_buffer_file_path(&file_path, self.environment)?;
+    // resolve the configuration for every request so changes are picked up, which keeps the plugins when it's the same
+    let last_config = self.config.take();
+    let config = resolve_latest_config(last_config, self.args, self.environment, self.plugin_resolver, &self.plugins_collection).await?;
+    let file_matcher = FileMatcher::new(&config, &FilePatternArgs::default(), self.environment)?;
+    self.config = Some(config);
+    if !file_matcher.matches_and_dir_not_ignored(&file_path) {
+      log_verbose!(self.environment, "Not formatting excluded file: {}", file_path.display());
+      return Ok(None);
+    }
+
+    let file_text = match self.documents.get(&uri) {
+      Some(file_text) => file_text.clone(),
+      None => self.environment.read_file(&file_path)?,
+    };
+    let token = Arc::new(CancellationToken::new());
+    Ok(Some(LspFormatRequest {
+      request: HostFormatRequest {
+        file_path,
+        range: range.map(|range| range.to_byte_range(&file_text)),
+        override_config: Default::default(),
+        file_text: file_text.clone(),
+        token: token.clone(),
+      },
+      uri,
+      file_text,
+      token,
+    }))
+  }
+}
+
+/// Reads the messages from the client on a dedicated thread, since
+/// reading is blocking and would otherwise block the async runtime.
+fn spawn_message_reader(reader: Box<dyn Read + Send>) -> UnboundedReceiver<Result<Option<Vec<u8>>>> {
+  let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+  std::thread::spawn(move || {
+    let mut reader = BufReader::new(reader);
+    loop {
+      let result = read_message_content(&mut reader);
+      let is_end = !matches!(result, Ok(Some(_)));
+      if sender.send(result).is_err() || is_end {
+        break;
+      }
+    }
+  });
+  receiver
+}
+
+fn parse_params<T: DeserializeOwned>(params: Option<Value>) -> Result<T> {
+  match serde_json::from_value(params.unwrap_or(Value::Null)) {
+    Ok(params) => Ok(params),
+    Err(err) => bail!("Error deserializing params. {:#}", err),
+  }
+}
+
+fn get_file_path_from_uri(uri: &str) -> Result<PathBuf> {
+  let url = match Url::parse(uri) {
+    Ok(url) => url,
+    Err(err) => bail!("Error parsing document uri {}. {:#}", uri, err),
+  };
+  match url.to_file_path() {
+    Ok(file_path) => Ok(file_path),
+    Err(_) => bail!("Only file documents can be formatted, but found {}.", uri),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+  use serde_json::json;
+  use serde_json::Value;
+  use std::io::BufReader;
+  use std::io::Read;
+  use std::io::Write;
+
+  use crate::environment::TestEnvironmentBuilder;
+  use crate::test_helpers::run_test_cli;
+
+  use super::messages::read_message_content;
+  use super::messages::LspMessage;
+
+  struct TestLspClient {
+    writer: Box<dyn Write + Send>,
+    reader: BufReader<Box<dyn Read + Send>>,
+  }
+
+  impl TestLspClient {
+    fn request(&mut self, id: u32, method: &str, params: Value) -> Value {
+      LspMessage::request(id, method, params).write(&mut self.writer).unwrap();
+      let content = read_message_content(&mut self.reader).unwrap().unwrap();
+      let response = serde_json::from_slice::<Value>(&content).unwrap();
+      assert_eq!(response["id"], json!(id));
+      response
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+      LspMessage::notification(method, params).write(&mut self.writer).unwrap();
+    }
+  }
+
+  #[test]
+  fn should_format_with_lsp_server() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .add_remote_process_plugin()
+      .with_default_config(|c| {
+        c.add_remote_wasm_plugin()
+          .add_remote_process_plugin()
+          .add_includes("**/*.{txt,txt_ps}")
+          .add_excludes("ignored.txt");
+      })
+      .write_file("/file.txt_ps", "testing")
+      .write_file("/ignored.txt", "testing")
+      .initialize()
+      .build();
+    let mut client = TestLspClient {
+      writer: environment.stdin_writer(),
+      reader: BufReader::new(environment.stdout_reader()),
+    };
+
+    let result = std::thread::spawn(move || {
+      let response = client.request(1, "initialize", json!({ "capabilities": {} }));
+      assert_eq!(response["result"]["capabilities"]["documentFormattingProvider"], json!(true));
+      assert_eq!(response["result"]["capabilities"]["documentRangeFormattingProvider"], json!(true));
+      client.notify("initialized", json!({}));
+
+      // unsaved document
+      client.notify(
+        "textDocument/didOpen",
+        json!({ "textDocument": { "uri": "file:///file.txt", "languageId": "plaintext", "version": 1, "text": "testing" } }),
+      );
+      let response = client.request(
+        2,
+        "textDocument/formatting",
+        json!({ "textDocument": { "uri": "file:///file.txt" }, "options": {} }),
+      );
+      assert_eq!(
+        response["result"],
+        json!([{
+          "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 7 } },
+          "newText": "testing_formatted",
+        }])
+      );
+      client.notify(
+        "textDocument/didChange",
+        json!({ "textDocument": { "uri": "file:///file.txt", "version": 2 }, "contentChanges": [{ "text": "testing_formatted" }] }),
+      );
+      let response = client.request(
+        3,
+        "textDocument/formatting",
+        json!({ "textDocument": { "uri": "file:///file.txt" }, "options": {} }),
+      );
+      assert_eq!(response["result"], Value::Null);
+
+      // document that isn't open reads the file
+      let response = client.request(
+        4,
+        "textDocument/rangeFormatting",
+        json!({
+          "textDocument": { "uri": "file:///file.txt_ps" },
+          "range": { "start": { "line": 0, "character": 1 }, "end": { "line": 0, "character": 2 } },
+          "options": {},
+        }),
+      );
+      assert_eq!(response["result"][0]["newText"], json!("t_formatted_process_sting_formatted_process"));
+
+      let response = client.request(
+        5,
+        "textDocument/formatting",
+        json!({ "textDocument": { "uri": "file:///ignored.txt" }, "options": {} }),
+      );
+      assert_eq!(response["result"], Value::Null);
+      let response = client.request(6, "textDocument/hover", json!({}));
+      assert_eq!(response["error"]["code"], json!(-32601));
+
+      let response = client.request(7, "shutdown", Value::Null);
+      assert_eq!(response["result"], Value::Null);
+      client.notify("exit", Value::Null);
+    });
+
+    run_test_cli(vec!["lsp"], &environment).unwrap();
+    result.join().unwrap();
+  }
+}
@@ -1,15 +1,38 @@
+mod broker;
+#[cfg(unix)]
+mod broker_snapshot;
+mod check_reporter;
 mod config;
+mod coverage;
 mod editor;
 mod formatting;
 mod general;
+mod lsp;
+mod multi_config;
+mod quarantine;
+mod run_diff;
+mod stats;
+mod suggest_config;
+mod update_preview;
 mod upgrade;
+mod watch;
 #[cfg(target_os = "windows")]
 mod windows_install;
 
+pub use broker::*;
+pub use check_reporter::*;
 pub use config::*;
+pub use coverage::*;
 pub use editor::*;
 pub use formatting::*;
 pub use general::*;
+pub use lsp::*;
+pub use multi_config::*;
+pub use quarantine::*;
+pub use run_diff::*;
+pub use stats::*;
+pub use suggest_config::*;
 pub use upgrade::*;
+pub use watch::*;
 #[cfg(target_os = "windows")]
 pub use windows_install::*;
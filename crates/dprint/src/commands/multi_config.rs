@@ -0,0 +1,198 @@
+use anyhow::bail;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::arg_parser::CliArgs;
+use crate::arg_parser::ConfigConflictMode;
+use crate::arg_parser::FilePatternArgs;
+use crate::arg_parser::FmtMode;
+use crate::arg_parser::SubCommand;
+use crate::configuration::resolve_config_from_args;
+use crate::environment::Environment;
+use crate::paths::get_and_resolve_file_paths;
+use crate::plugins::PluginResolver;
+use crate::plugins::PluginsCollection;
+use crate::utils::display_path;
+
+/// Runs `dprint fmt` or `dprint check` concurrently for each configuration file
+/// provided via `--config`. Each run has its own plugins, but the runs share
+/// the plugin cache and the output of each run is prefixed with its configuration.
+pub async fn run_for_each_config<TEnvironment: Environment>(
+  args: &CliArgs,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+) -> Result<()> {
+  let configs = args.config.iter().chain(args.additional_configs.iter()).cloned().collect::<Vec<_>>();
+  let mut runs = Vec::with_capacity(configs.len());
+  for config in configs {
+    let sub_command = match &args.sub_command {
+      SubCommand::Check(cmd) => SubCommand::Check(cmd.clone()),
+      SubCommand::Fmt(cmd) if cmd.mode == FmtMode::Write => SubCommand::Fmt(cmd.clone()),
+      _ => bail!("Specifying --config more than once is only supported by `dprint fmt` and `dprint check`."),
+    };
+    let environment = environment.with_log_prefix(&format!("[{}] ", config));
+    let plugin_pools = Arc::new(PluginsCollection::new(environment.clone()));
+    let plugin_resolver = plugin_resolver.with_plugins_collection(environment.clone(), plugin_pools.clone());
+    runs.push(ConfigRun {
+      args: args.with_single_config(sub_command, config),
+      environment,
+      plugin_resolver,
+      plugin_pools,
+    });
+  }
+
+  // populate the cache one configuration at a time so that the concurrent
+  // runs don't download the same plugin at the same time
+  let mut run_indexes_by_file_path: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+  let mut config_dir_paths = Vec::with_capacity(runs.len());
+  for (index, run) in runs.iter().enumerate() {
+    let config = resolve_config_from_args(&run.args, &run.environment)?;
+    for plugin_reference in config.plugins.iter() {
+      run.plugin_resolver.cache_plugin(plugin_reference).await?;
+    }
+    for file_path in get_and_resolve_file_paths(&config, run.patterns(), &run.environment).await? {
+      run_indexes_by_file_path.entry(file_path).or_default().push(index);
+    }
+    // the base path is the cwd when providing --config, so use the directory of the file
+    let config_dir_path = if config.resolved_path.is_local() {
+      config.resolved_path.file_path.parent()
+    } else {
+      None
+    };
+    config_dir_paths.push(config_dir_path.unwrap_or(config.base_path).into_path_buf());
+  }
+
+  // formatting a file with more than one configuration would format it with different settings
+  let conflicts = run_indexes_by_file_path
+    .into_iter()
+    .filter(|(_, run_indexes)| run_indexes.len() > 1)
+    .collect::<Vec<_>>();
+  if !conflicts.is_empty() {
+    match args.config_conflicts {
+      ConfigConflictMode::Error => bail!("{}", get_conflict_report(&conflicts, &runs, args, environment)),
+      ConfigConflictMode::Nearest => {
+        for (file_path, run_indexes) in conflicts {
+          let nearest_index = get_nearest_config_index(&file_path, &run_indexes, &config_dir_paths);
+          log_verbose!(
+            environment,
+            "Formatting {} only with {} because it's the nearest configuration file.",
+            file_path.display(),
+            runs[nearest_index].config_name()
+          );
+          for run_index in run_indexes {
+            if run_index != nearest_index {
+              runs[run_index].patterns_mut().excluded_file_paths.push(file_path.clone());
+            }
+          }
+        }
+      }
+    }
+  }
+
+  let results = futures::future::join_all(runs.iter().map(|run| run.run())).await;
+  let failed_count = results.iter().filter(|result| result.is_err()).count();
+  for (run, result) in runs.iter().zip(results) {
+    if let Err(err) = result {
+      run.environment.log_stderr(&format!("{:#}", err));
+    }
+  }
+  if failed_count > 0 {
+    let configurations = if runs.len() == 1 { "configuration" } else { "configurations" };
+    bail!("Failed for {} of {} {}.", failed_count, runs.len(), configurations);
+  }
+  Ok(())
+}
+
+/// Gets a report of the files included by more than one configuration file
+/// grouped by the configuration files that include them.
+fn get_conflict_report<TEnvironment: Environment>(
+  conflicts: &[(PathBuf, Vec<usize>)],
+  runs: &[ConfigRun<TEnvironment>],
+  args: &CliArgs,
+  environment: &TEnvironment,
+) -> String {
+  let cwd = environment.cwd();
+  let mut file_paths_by_run_indexes: BTreeMap<&Vec<usize>, Vec<&Path>> = BTreeMap::new();
+  for (file_path, run_indexes) in conflicts {
+    file_paths_by_run_indexes.entry(run_indexes).or_default().push(file_path);
+  }
+  let f = if conflicts.len() == 1 { "file" } else { "files" };
+  let mut text = format!(
+    "Found {} {} included by more than one configuration file, which would format them with different settings:\n",
+    conflicts.len(),
+    f
+  );
+  for (run_indexes, file_paths) in file_paths_by_run_indexes {
+    let config_names = run_indexes.iter().map(|index| runs[*index].config_name()).collect::<Vec<_>>();
+    text.push_str(&format!("  {}\n", config_names.join(", ")));
+    for file_path in file_paths {
+      let display_file_path = file_path.strip_prefix(&cwd).unwrap_or(file_path);
+      text.push_str(&format!("    {}\n", display_path(display_file_path, args.sort_output)));
+    }
+  }
+  text.push_str(concat!(
+    "Change the includes or excludes so each file is included by one configuration file or ",
+    "provide --config-conflicts nearest to format each file with the configuration file nearest to it."
+  ));
+  text
+}
+
+/// Gets the run of the configuration file in the deepest directory containing the file,
+/// which is the first of them when they're in the same directory.
+fn get_nearest_config_index(file_path: &Path, run_indexes: &[usize], config_dir_paths: &[PathBuf]) -> usize {
+  let mut nearest_index = run_indexes[0];
+  let mut nearest_depth = 0;
+  for run_index in run_indexes {
+    let dir_path = &config_dir_paths[*run_index];
+    if file_path.starts_with(dir_path) {
+      let depth = dir_path.components().count();
+      if depth > nearest_depth {
+        nearest_index = *run_index;
+        nearest_depth = depth;
+      }
+    }
+  }
+  nearest_index
+}
+
+struct ConfigRun<TEnvironment: Environment> {
+  args: CliArgs,
+  environment: TEnvironment,
+  plugin_resolver: PluginResolver<TEnvironment>,
+  plugin_pools: Arc<PluginsCollection<TEnvironment>>,
+}
+
+impl<TEnvironment: Environment> ConfigRun<TEnvironment> {
+  fn config_name(&self) -> &str {
+    self.args.config.as_deref().unwrap_or_default()
+  }
+
+  fn patterns(&self) -> &FilePatternArgs {
+    match &self.args.sub_command {
+      SubCommand::Check(cmd) => &cmd.patterns,
+      SubCommand::Fmt(cmd) => &cmd.patterns,
+      _ => unreachable!(),
+    }
+  }
+
+  fn patterns_mut(&mut self) -> &mut FilePatternArgs {
+    match &mut self.args.sub_command {
+      SubCommand::Check(cmd) => &mut cmd.patterns,
+      SubCommand::Fmt(cmd) => &mut cmd.patterns,
+      _ => unreachable!(),
+    }
+  }
+
+  async fn run(&self) -> Result<()> {
+    let result = match &self.args.sub_command {
+      SubCommand::Check(cmd) => super::check(cmd, &self.args, &self.environment, &self.plugin_resolver, self.plugin_pools.clone()).await,
+      SubCommand::Fmt(cmd) => super::format(cmd, &self.args, &self.environment, &self.plugin_resolver, self.plugin_pools.clone()).await,
+      _ => unreachable!(),
+    };
+    self.plugin_pools.drop_and_shutdown_initialized().await;
+    result
+  }
+}
@@ -0,0 +1,179 @@
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::format;
+use crate::arg_parser::CliArgs;
+use crate::arg_parser::FmtMode;
+use crate::arg_parser::FmtSubCommand;
+use crate::configuration::resolve_config_from_args;
+use crate::environment::Environment;
+use crate::incremental::get_quarantine_path;
+use crate::incremental::read_quarantine;
+use crate::incremental::write_quarantine;
+use crate::incremental::QuarantinedFile;
+use crate::plugins::PluginResolver;
+use crate::plugins::PluginsCollection;
+use crate::utils::display_path;
+use crate::utils::get_file_path_key;
+use crate::utils::sort_listing;
+
+pub fn output_quarantine(args: &CliArgs, environment: &impl Environment) -> Result<()> {
+  let config = resolve_config_from_args(args, environment)?;
+  let quarantined_files = read_quarantine(&get_quarantine_path(&config, environment), environment);
+  if quarantined_files.is_empty() {
+    environment.log("No files are quarantined.");
+  }
+  let mut lines = quarantined_files
+    .iter()
+    .map(|file| format!("{}\n  {}", display_path(&file.file_path, args.sort_output), file.reason.replace('\n', "\n  ")))
+    .collect::<Vec<_>>();
+  sort_listing(&mut lines, args.sort_output);
+  for line in lines {
+    environment.log(&line);
+  }
+  Ok(())
+}
+
+pub fn clear_quarantine(args: &CliArgs, environment: &impl Environment) -> Result<()> {
+  let config = resolve_config_from_args(args, environment)?;
+  let quarantine_path = get_quarantine_path(&config, environment);
+  let count = read_quarantine(&quarantine_path, environment).len();
+  if environment.path_exists(&quarantine_path) {
+    environment.remove_file(&quarantine_path)?;
+  }
+  let suffix = if count == 1 { "file" } else { "files" };
+  environment.log(&format!("Cleared {} quarantined {}.", count, suffix));
+  Ok(())
+}
+
+/// Formats the quarantined files, which are added back to the quarantine
+/// when they cause a critical error again.
+pub async fn retry_quarantine<TEnvironment: Environment>(
+  args: &CliArgs,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+  plugin_pools: Arc<PluginsCollection<TEnvironment>>,
+) -> Result<()> {
+  let cmd = FmtSubCommand {
+    mode: FmtMode::Write,
+    diff: false,
+    patterns: Default::default(),
+    incremental: None,
+    enable_stable_format: true,
+    fail_fast: false,
+    allow_plugin_failures: false,
+    broker: false,
+    summary_file: None,
+    max_duration: None,
+    max_files: None,
+    retry_quarantined: true,
+    deterministic: false,
+    fsync: false,
+    watch: false,
+  };
+  format(&cmd, args, environment, plugin_resolver, plugin_pools).await
+}
+
+/// Removes the quarantined files from the file paths, warning when any are skipped.
+pub(super) fn skip_quarantined_file_paths(file_paths: &mut Vec<PathBuf>, quarantined_files: &[QuarantinedFile], environment: &impl Environment) {
+  if quarantined_files.is_empty() {
+    return;
+  }
+  let quarantined_file_paths = quarantined_files.iter().map(|file| get_file_path_key(&file.file_path)).collect::<HashSet<_>>();
+  let original_len = file_paths.len();
+  file_paths.retain(|file_path| !quarantined_file_paths.contains(&get_file_path_key(file_path)));
+  let skipped_count = original_len - file_paths.len();
+  if skipped_count > 0 {
+    let suffix = if skipped_count == 1 { "file" } else { "files" };
+    environment.log_stderr(&format!(
+      concat!(
+        "WARNING: Skipped {} quarantined {} that previously caused a critical error in a plugin. ",
+        "Run `dprint quarantine list` to see them or `dprint quarantine retry` to format them again."
+      ),
+      skipped_count, suffix,
+    ));
+  }
+}
+
+/// Only keeps the quarantined file paths and removes them from the quarantine.
+///
+/// Returns false when no file paths are quarantined.
+pub(super) fn take_quarantined_file_paths(file_paths: &mut Vec<PathBuf>, quarantined_files: &mut Vec<QuarantinedFile>) -> bool {
+  let quarantined_file_paths = quarantined_files.iter().map(|file| get_file_path_key(&file.file_path)).collect::<HashSet<_>>();
+  file_paths.retain(|file_path| quarantined_file_paths.contains(&get_file_path_key(file_path)));
+  let retried_file_paths = file_paths.iter().map(|file_path| get_file_path_key(file_path)).collect::<HashSet<_>>();
+  quarantined_files.retain(|file| !retried_file_paths.contains(&get_file_path_key(&file.file_path)));
+  !file_paths.is_empty()
+}
+
+/// Adds the files that caused a critical error in this run to the quarantine.
+pub(super) fn save_quarantine(
+  quarantine_path: &Path,
+  mut quarantined_files: Vec<QuarantinedFile>,
+  new_quarantined_files: &Mutex<Vec<QuarantinedFile>>,
+  has_changed: bool,
+  environment: &impl Environment,
+) {
+  let new_quarantined_files = std::mem::take(&mut *new_quarantined_files.lock());
+  if has_changed || !new_quarantined_files.is_empty() {
+    quarantined_files.extend(new_quarantined_files);
+    write_quarantine(quarantine_path, quarantined_files, environment);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use crate::environment::Environment;
+  use crate::environment::TestEnvironmentBuilder;
+  use crate::test_helpers::get_singular_formatted_text;
+  use crate::test_helpers::run_test_cli;
+
+  #[test]
+  fn should_list_retry_and_clear_quarantine() {
+    let environment = TestEnvironmentBuilder::with_remote_wasm_plugin()
+      .write_file("/file.txt", "should_panic") // special text to make it panic
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt").add_remote_wasm_plugin();
+      })
+      .initialize()
+      .build();
+    run_test_cli(vec!["quarantine", "list"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["No files are quarantined."]);
+    run_test_cli(vec!["quarantine", "retry"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["No quarantined files to retry."]);
+
+    assert!(run_test_cli(vec!["fmt"], &environment).is_err());
+    environment.clear_logs();
+    run_test_cli(vec!["quarantine", "list"], &environment).unwrap();
+    let messages = environment.take_stdout_messages();
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].starts_with("/file.txt\n  Originally panicked in test-plugin"));
+
+    // still causes a critical error, so it stays quarantined
+    assert!(run_test_cli(vec!["quarantine", "retry"], &environment).is_err());
+    environment.clear_logs();
+    run_test_cli(vec!["quarantine", "list"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages().len(), 1);
+
+    // fixed, so it's removed from the quarantine
+    environment.write_file("/file.txt", "text").unwrap();
+    run_test_cli(vec!["quarantine", "retry"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
+    assert_eq!(environment.read_file("/file.txt").unwrap(), "text_formatted");
+    run_test_cli(vec!["quarantine", "list"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["No files are quarantined."]);
+
+    environment.write_file("/file.txt", "should_panic").unwrap();
+    assert!(run_test_cli(vec!["fmt"], &environment).is_err());
+    environment.clear_logs();
+    run_test_cli(vec!["quarantine", "clear"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["Cleared 1 quarantined file."]);
+    assert!(run_test_cli(vec!["fmt"], &environment).is_err());
+  }
+}
@@ -0,0 +1,184 @@
+use anyhow::bail;
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::arg_parser::CliArgs;
+use crate::arg_parser::RunDiffSubCommand;
+use crate::commands::get_generated_file_skipper;
+use crate::configuration::resolve_config_from_args;
+use crate::environment::Environment;
+use crate::format::run_parallelized;
+use crate::format::RunParallelizedOptions;
+use crate::paths::get_and_resolve_file_paths;
+use crate::paths::get_file_paths_by_plugins_and_err_if_empty;
+use crate::plugins::resolve_plugins_and_err_if_empty;
+use crate::plugins::PluginResolver;
+use crate::plugins::PluginsCollection;
+use crate::stats::get_run_summary_file_changes;
+use crate::stats::read_run_summary;
+use crate::stats::FileRunSummary;
+use crate::stats::PluginRunSummary;
+use crate::stats::RunSummaryFileChange;
+use crate::transforms::FileTextTransforms;
+use crate::utils::get_sha256_checksum;
+use crate::utils::BOM_CHAR;
+
+/// Outputs the files whose formatted output changed since a run summary
+/// was written with `--summary-file` (`dprint run-diff`).
+///
+/// The files are formatted in memory, so nothing is written.
+pub async fn output_run_diff<TEnvironment: Environment>(
+  cmd: &RunDiffSubCommand,
+  args: &CliArgs,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+  plugin_pools: Arc<PluginsCollection<TEnvironment>>,
+) -> Result<()> {
+  let old_summary = read_run_summary(&environment.cwd().join(&cmd.summary_file), environment)?;
+  let config = resolve_config_from_args(args, environment)?;
+  let plugins = resolve_plugins_and_err_if_empty(args, &config, environment, plugin_resolver).await?;
+  let file_paths = get_and_resolve_file_paths(&config, &cmd.patterns, environment).await?;
+  let file_paths_by_plugins =
+    get_file_paths_by_plugins_and_err_if_empty(&plugins, file_paths, &config.base_path, &config.overrides, &cmd.patterns.associations)?;
+  plugin_pools.set_plugins(plugins, &config.base_path, &config.overrides, &cmd.patterns.associations)?;
+  let file_paths = file_paths_by_plugins.values().flatten().cloned().collect::<Vec<_>>();
+  let formatted_hashes: Arc<Mutex<HashMap<PathBuf, String>>> = Default::default();
+
+  run_parallelized(
+    file_paths_by_plugins,
+    environment,
+    plugin_pools.clone(),
+    RunParallelizedOptions {
+      generated_file_skipper: get_generated_file_skipper(&config),
+      transforms: FileTextTransforms::from_config(&config)?.map(Arc::new),
+      ..Default::default()
+    },
+    {
+      let formatted_hashes = formatted_hashes.clone();
+      move |file_path, _, formatted_text, had_bom, _, _| {
+        // hash the text that would be written by `dprint fmt`
+        let formatted_text = if had_bom { format!("{}{}", BOM_CHAR, formatted_text) } else { formatted_text };
+        formatted_hashes
+          .lock()
+          .insert(file_path.to_path_buf(), get_sha256_checksum(formatted_text.as_bytes()));
+        Ok(())
+      }
+    },
+  )
+  .await?;
+
+  let mut formatted_hashes = std::mem::take(&mut *formatted_hashes.lock());
+  let mut files = Vec::with_capacity(file_paths.len());
+  for file_path in file_paths {
+    // skipped files, such as generated files, are left as-is
+    let hash = match formatted_hashes.remove(&file_path) {
+      Some(hash) => hash,
+      None => get_sha256_checksum(&environment.read_file_bytes(&file_path)?),
+    };
+    let relative_path = file_path.strip_prefix(&config.base_path).unwrap_or(&file_path);
+    files.push(FileRunSummary {
+      file_path: relative_path.to_string_lossy().replace('\\', "/"),
+      hash,
+    });
+  }
+
+  let current_plugins = plugin_pools
+    .get_plugins()
+    .iter()
+    .map(|plugin| PluginRunSummary {
+      name: plugin.name().to_string(),
+      version: plugin.version().to_string(),
+      hash: format!("{:016x}", plugin.get_hash()),
+    })
+    .collect::<Vec<_>>();
+  for plugin_text in get_plugin_change_texts(&old_summary.plugins, &current_plugins) {
+    environment.log_stderr(&plugin_text);
+  }
+
+  let changes = get_run_summary_file_changes(&old_summary.files, &files);
+  if changes.is_empty() {
+    environment.log("No formatted output changed since the run summary.");
+    return Ok(());
+  }
+  let mut text = String::new();
+  for change in changes.iter() {
+    let (kind, file_path) = match change {
+      RunSummaryFileChange::Changed(file_path) => ("changed", file_path),
+      RunSummaryFileChange::Added(file_path) => ("added", file_path),
+      RunSummaryFileChange::Removed(file_path) => ("removed", file_path),
+    };
+    text.push_str(&format!("{}: {}\n", kind, file_path));
+  }
+  environment.log(text.trim_end());
+  let f = if changes.len() == 1 { "file" } else { "files" };
+  bail!("Found {} {} with changed formatted output since the run summary.", changes.len(), f)
+}
+
+/// Gets a description of each plugin that was added, removed, or had its version or configuration
+/// changed, which explains why the formatted output changed.
+fn get_plugin_change_texts(old_plugins: &[PluginRunSummary], new_plugins: &[PluginRunSummary]) -> Vec<String> {
+  let mut texts = Vec::new();
+  for new_plugin in new_plugins {
+    match old_plugins.iter().find(|old_plugin| old_plugin.name == new_plugin.name) {
+      Some(old_plugin) if old_plugin.version != new_plugin.version => {
+        texts.push(format!(
+          "Plugin {} changed from {} to {}.",
+          new_plugin.name, old_plugin.version, new_plugin.version
+        ));
+      }
+      Some(old_plugin) if old_plugin.hash != new_plugin.hash => texts.push(format!("Plugin {} changed its configuration.", new_plugin.name)),
+      Some(_) => {}
+      None => texts.push(format!("Plugin {} was added.", new_plugin.name)),
+    }
+  }
+  for old_plugin in old_plugins {
+    if !new_plugins.iter().any(|new_plugin| new_plugin.name == old_plugin.name) {
+      texts.push(format!("Plugin {} was removed.", old_plugin.name));
+    }
+  }
+  texts
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use crate::environment::Environment;
+  use crate::environment::TestEnvironmentBuilder;
+  use crate::test_helpers::run_test_cli;
+
+  #[test]
+  fn should_output_files_with_changed_formatted_output() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt");
+      })
+      .write_file("/a.txt", "a")
+      .write_file("/b.txt", "b")
+      .write_file("/c.txt", "c")
+      .build();
+    run_test_cli(vec!["check", "--summary-file", "summary.json"], &environment).err().unwrap();
+    // a check summary contains the unformatted text, so these all differ
+    let error_message = run_test_cli(vec!["run-diff", "summary.json"], &environment).err().unwrap();
+    assert_eq!(error_message.to_string(), "Found 3 files with changed formatted output since the run summary.");
+    environment.clear_logs();
+
+    run_test_cli(vec!["fmt", "--summary-file", "summary.json"], &environment).unwrap();
+    environment.clear_logs();
+    run_test_cli(vec!["run-diff", "summary.json"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec!["No formatted output changed since the run summary."]);
+
+    environment.write_file("/b.txt", "other").unwrap();
+    environment.remove_file("/c.txt").unwrap();
+    environment.write_file("/d.txt", "d").unwrap();
+    let error_message = run_test_cli(vec!["run-diff", "summary.json"], &environment).err().unwrap();
+    assert_eq!(error_message.to_string(), "Found 3 files with changed formatted output since the run summary.");
+    assert_eq!(environment.take_stdout_messages(), vec!["changed: b.txt\nremoved: c.txt\nadded: d.txt"]);
+    assert_eq!(environment.take_stderr_messages(), Vec::<String>::new());
+    // nothing was written
+    assert_eq!(environment.read_file("/d.txt").unwrap(), "d");
+  }
+}
@@ -0,0 +1,164 @@
+use anyhow::bail;
+use anyhow::Result;
+
+use crate::arg_parser::CliArgs;
+use crate::arg_parser::StatsSubCommand;
+use crate::configuration::resolve_config_from_args;
+use crate::environment::Environment;
+use crate::stats::get_memory_text;
+use crate::stats::get_run_history_chart_text;
+use crate::stats::get_run_issues;
+use crate::stats::get_stats_file_path;
+use crate::stats::get_stats_history_file_path;
+use crate::stats::read_run_history;
+use crate::stats::read_run_stats;
+use crate::stats::RunStats;
+
+pub fn output_stats(cmd: &StatsSubCommand, args: &CliArgs, environment: &impl Environment) -> Result<()> {
+  let config = resolve_config_from_args(args, environment)?;
+  if cmd.history {
+    let entries = read_run_history(&get_stats_history_file_path(&config, environment), environment);
+    if entries.is_empty() {
+      bail!(
+        "No stats history found for {}. Run `dprint fmt` or `dprint check` first.",
+        config.base_path.display()
+      );
+    }
+    environment.log(&get_run_history_chart_text(&entries));
+  } else {
+    let stats = match read_run_stats(&get_stats_file_path(&config, environment), environment) {
+      Some(stats) => stats,
+      None => bail!("No stats found for {}. Run `dprint fmt` or `dprint check` first.", config.base_path.display()),
+    };
+    environment.log(&get_last_run_text(&stats));
+  }
+  Ok(())
+}
+
+pub fn output_why_slow(args: &CliArgs, environment: &impl Environment) -> Result<()> {
+  let config = resolve_config_from_args(args, environment)?;
+  let stats = match read_run_stats(&get_stats_file_path(&config, environment), environment) {
+    Some(stats) => stats,
+    None => bail!("No stats found for {}. Run `dprint fmt` or `dprint check` first.", config.base_path.display()),
+  };
+
+  let mut text = get_last_run_text(&stats);
+  if !stats.slowest_files.is_empty() {
+    text.push_str("\n\nSlowest files:");
+    for file in stats.slowest_files.iter() {
+      text.push_str(&format!("\n  {}ms - {}", file.duration_ms, file.file_path));
+    }
+  }
+  if !stats.slowest_dirs.is_empty() {
+    text.push_str("\n\nSlowest directories:");
+    for dir in stats.slowest_dirs.iter() {
+      text.push_str(&format!("\n  {}ms - {} ({} files)", dir.duration_ms, dir.dir_path, dir.files_count));
+    }
+  }
+  if !stats.plugins.is_empty() {
+    text.push_str("\n\nPlugins:");
+    for plugin in stats.plugins.iter() {
+      text.push_str(&format!("\n  {}ms - {} ({} files", plugin.format_ms, plugin.name, plugin.files_count));
+      if let Some(startup_ms) = plugin.startup_ms {
+        text.push_str(&format!(", {}ms startup", startup_ms));
+      }
+      if let Some(peak_memory_bytes) = plugin.peak_memory_bytes {
+        text.push_str(&format!(", {} peak memory", get_memory_text(peak_memory_bytes)));
+      }
+      if plugin.is_process_plugin {
+        text.push_str(", process plugin");
+      }
+      text.push(')');
+    }
+  }
+
+  let issues = get_run_issues(&stats);
+  if issues.is_empty() {
+    text.push_str("\n\nNo issues detected.");
+  } else {
+    text.push_str("\n\nIssues:");
+    for issue in issues {
+      text.push_str(&format!("\n  * {}\n    {}", issue.title, issue.recommendation));
+    }
+  }
+
+  environment.log(&text);
+  Ok(())
+}
+
+fn get_last_run_text(stats: &RunStats) -> String {
+  format!(
+    "Last run: dprint {} took {}ms ({} formatted, {} skipped by incremental, {} threads)",
+    stats.command, stats.total_ms, stats.formatted_files_count, stats.incremental_skipped_files_count, stats.thread_count,
+  )
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use crate::environment::TestEnvironmentBuilder;
+  use crate::test_helpers::run_test_cli;
+
+  #[test]
+  fn should_error_when_no_stats() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin().build();
+    let err = run_test_cli(vec!["why-slow"], &environment).err().unwrap();
+    assert_eq!(err.to_string(), "No stats found for /. Run `dprint fmt` or `dprint check` first.");
+  }
+
+  #[test]
+  fn should_output_why_slow_after_check() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .with_default_config(|config| {
+        config.add_remote_wasm_plugin().set_incremental(false);
+      })
+      .write_file("/src/file.txt", "text_formatted")
+      .initialize()
+      .build();
+    run_test_cli(vec!["check", "**/*.txt"], &environment).unwrap();
+    run_test_cli(vec!["why-slow"], &environment).unwrap();
+    let text = environment.take_stdout_messages().join("\n");
+    assert!(text.starts_with("Last run: dprint check took "), "{}", text);
+    assert!(text.contains("ms - src/file.txt"), "{}", text);
+    assert!(text.contains("ms - src (1 files)"), "{}", text);
+    assert!(text.contains("ms - test-plugin (1 files"), "{}", text);
+    assert!(text.contains("  * No incremental cache"), "{}", text);
+  }
+
+  #[test]
+  fn should_output_stats_history() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .with_default_config(|config| {
+        config.add_remote_wasm_plugin();
+      })
+      .write_file("/src/file.txt", "text_formatted")
+      .initialize()
+      .build();
+    let err = run_test_cli(vec!["stats", "--history"], &environment).err().unwrap();
+    assert_eq!(err.to_string(), "No stats history found for /. Run `dprint fmt` or `dprint check` first.");
+
+    run_test_cli(vec!["check", "**/*.txt"], &environment).unwrap();
+    run_test_cli(vec!["check", "**/*.txt"], &environment).unwrap();
+    environment.clear_logs();
+    run_test_cli(vec!["stats"], &environment).unwrap();
+    let messages = environment.take_stdout_messages();
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].starts_with("Last run: dprint check took "), "{}", messages[0]);
+    assert!(messages[0].contains("(0 formatted, 1 skipped by incremental"), "{}", messages[0]);
+
+    run_test_cli(vec!["stats", "--history"], &environment).unwrap();
+    let lines = environment
+      .take_stdout_messages()
+      .join("\n")
+      .lines()
+      .map(ToString::to_string)
+      .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("1970-01-02 10:17:36 UTC check "), "{}", lines[0]);
+    assert!(lines[0].ends_with(" 1 formatted, 0% cached"), "{}", lines[0]);
+    assert!(lines[1].ends_with(" 0 formatted, 100% cached"), "{}", lines[1]);
+  }
+}
@@ -0,0 +1,335 @@
+use anyhow::bail;
+use anyhow::Result;
+use serde_json::Map;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::arg_parser::CliArgs;
+use crate::arg_parser::SuggestConfigSubCommand;
+use crate::configuration::resolve_config_from_args;
+use crate::configuration::set_config_value;
+use crate::environment::Environment;
+use crate::paths::get_and_resolve_file_paths;
+use crate::paths::get_file_paths_by_plugins;
+use crate::plugins::resolve_plugins_and_err_if_empty;
+use crate::plugins::PluginResolver;
+use crate::utils::PathSource;
+
+/// The maximum number of files to read, which are spread evenly across the sorted file paths.
+const MAX_SAMPLED_FILES: usize = 500;
+/// The share of the lines that should fit within the suggested line width.
+const LINE_WIDTH_LINES_PERCENTAGE: usize = 98;
+const LINE_WIDTH_CANDIDATES: [usize; 6] = [80, 100, 120, 140, 160, 180];
+
+pub async fn suggest_config<TEnvironment: Environment>(
+  cmd: &SuggestConfigSubCommand,
+  args: &CliArgs,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+) -> Result<()> {
+  let config = resolve_config_from_args(args, environment)?;
+  let plugins = resolve_plugins_and_err_if_empty(args, &config, environment, plugin_resolver).await?;
+  let resolved_file_paths = get_and_resolve_file_paths(&config, &cmd.patterns, environment).await?;
+  let file_paths_by_plugins = get_file_paths_by_plugins(&plugins, resolved_file_paths, &config.base_path, &config.overrides, &cmd.patterns.associations)?;
+
+  // the config key of the plugin that formats each file
+  let mut file_paths: Vec<(PathBuf, String)> = Vec::new();
+  for (plugin_names, plugin_file_paths) in file_paths_by_plugins.into_iter() {
+    let plugin_name = plugin_names.names().next().unwrap_or_default().to_string();
+    let config_key = match plugins.iter().find(|p| p.name() == plugin_name) {
+      Some(plugin) => plugin.config_key().to_string(),
+      None => continue,
+    };
+    file_paths.extend(plugin_file_paths.into_iter().map(|file_path| (file_path, config_key.clone())));
+  }
+  file_paths.sort();
+  let step = std::cmp::max(1, (file_paths.len() + MAX_SAMPLED_FILES - 1) / MAX_SAMPLED_FILES);
+
+  let mut stats = TextStats::default();
+  let mut plugin_stats: Vec<(String, TextStats)> = Vec::new();
+  let mut sampled_count = 0;
+  for (file_path, config_key) in file_paths.iter().step_by(step) {
+    let file_text = match environment.read_file(file_path) {
+      Ok(file_text) => file_text,
+      Err(err) => {
+        log_verbose!(environment, "Skipped sampling {}: {:#}", file_path.display(), err);
+        continue;
+      }
+    };
+    sampled_count += 1;
+    stats.add_text(&file_text);
+    match plugin_stats.iter_mut().find(|(key, _)| key == config_key) {
+      Some((_, stats)) => stats.add_text(&file_text),
+      None => {
+        let mut stats = TextStats::default();
+        stats.add_text(&file_text);
+        plugin_stats.push((config_key.clone(), stats));
+      }
+    }
+  }
+  if sampled_count == 0 {
+    bail!("No files found to sample.");
+  }
+  plugin_stats.sort_by(|a, b| a.0.cmp(&b.0));
+
+  let suggestions = get_suggestions(&stats, &plugin_stats);
+  environment.log(&format!(
+    "Suggested configuration based on {} sampled file{}:\n{}",
+    sampled_count,
+    if sampled_count == 1 { "" } else { "s" },
+    serde_json::to_string_pretty(&suggestions)?
+  ));
+
+  if cmd.write {
+    let config_path = match config.resolved_path.source {
+      PathSource::Local(source) => source.path,
+      PathSource::Remote(_) => bail!("Cannot write the suggested values to a remote configuration."),
+    };
+    let mut file_text = environment.read_file(&config_path)?;
+    for (key, value) in suggestions.iter() {
+      match value {
+        Value::Object(plugin_suggestions) => {
+          for (property_name, value) in plugin_suggestions.iter() {
+            file_text = set_config_value(&file_text, &format!("{}.{}", key, property_name), &value.to_string())?;
+          }
+        }
+        value => file_text = set_config_value(&file_text, key, &value.to_string())?,
+      }
+    }
+    environment.write_file(&config_path, &file_text)?;
+    environment.log_stderr(&format!("Updated {}", config_path.display()));
+  }
+
+  Ok(())
+}
+
+/// Gets the global values along with the values of each plugin that differ from them.
+fn get_suggestions(stats: &TextStats, plugin_stats: &[(String, TextStats)]) -> Map<String, Value> {
+  let global_values = stats.get_values();
+  let mut suggestions = global_values.clone();
+  for (config_key, stats) in plugin_stats {
+    let mut plugin_values = stats.get_values();
+    plugin_values.retain(|key, value| key != "newLineKind" && global_values.get(key) != Some(&*value));
+    // only the typescript plugin has this property
+    if config_key == "typescript" {
+      if let Some(quote_style) = stats.get_quote_style() {
+        plugin_values.insert("quoteStyle".to_string(), quote_style.into());
+      }
+    }
+    if !plugin_values.is_empty() {
+      suggestions.insert(config_key.to_string(), plugin_values.into());
+    }
+  }
+  suggestions
+}
+
+#[derive(Default)]
+struct TextStats {
+  line_lengths: Vec<usize>,
+  /// How often each increase in the leading spaces occurs.
+  indent_width_counts: HashMap<usize, usize>,
+  tab_indented_lines: usize,
+  space_indented_lines: usize,
+  crlf_line_endings: usize,
+  lf_line_endings: usize,
+  single_quote_lines: usize,
+  double_quote_lines: usize,
+}
+
+impl TextStats {
+  pub fn add_text(&mut self, text: &str) {
+    let mut last_indent_width = 0;
+    for line in text.split_inclusive('\n') {
+      if line.ends_with("\r\n") {
+        self.crlf_line_endings += 1;
+      } else if line.ends_with('\n') {
+        self.lf_line_endings += 1;
+      }
+      let line = line.trim_end();
+      if line.is_empty() {
+        continue;
+      }
+      self.line_lengths.push(line.chars().count());
+
+      let trimmed_line = line.trim_start();
+      if line.starts_with('\t') {
+        self.tab_indented_lines += 1;
+      } else {
+        let indent_width = line.len() - trimmed_line.len();
+        if indent_width > 0 {
+          self.space_indented_lines += 1;
+        }
+        // ignore alignment, such as the leading space of a block comment's lines
+        if indent_width > last_indent_width && indent_width - last_indent_width > 1 {
+          *self.indent_width_counts.entry(indent_width - last_indent_width).or_default() += 1;
+        }
+        last_indent_width = indent_width;
+      }
+
+      match trimmed_line.chars().find(|c| *c == '\'' || *c == '"') {
+        Some('\'') => self.single_quote_lines += 1,
+        Some(_) => self.double_quote_lines += 1,
+        None => {}
+      }
+    }
+  }
+
+  pub fn get_values(&self) -> Map<String, Value> {
+    let mut values = Map::new();
+    if let Some(line_width) = self.get_line_width() {
+      values.insert("lineWidth".to_string(), line_width.into());
+    }
+    let use_tabs = self.tab_indented_lines > self.space_indented_lines;
+    if !use_tabs {
+      if let Some(indent_width) = self.get_indent_width() {
+        values.insert("indentWidth".to_string(), indent_width.into());
+      }
+    }
+    if self.tab_indented_lines + self.space_indented_lines > 0 {
+      values.insert("useTabs".to_string(), use_tabs.into());
+    }
+    if self.crlf_line_endings + self.lf_line_endings > 0 {
+      let new_line_kind = if self.crlf_line_endings > self.lf_line_endings { "crlf" } else { "lf" };
+      values.insert("newLineKind".to_string(), new_line_kind.into());
+    }
+    values
+  }
+
+  /// Gets the smallest common line width that most lines fit within.
+  fn get_line_width(&self) -> Option<usize> {
+    if self.line_lengths.is_empty() {
+      return None;
+    }
+    let mut line_lengths = self.line_lengths.clone();
+    line_lengths.sort_unstable();
+    let index = (line_lengths.len() * LINE_WIDTH_LINES_PERCENTAGE + 99) / 100 - 1;
+    let line_length = line_lengths[index];
+    Some(
+      LINE_WIDTH_CANDIDATES
+        .iter()
+        .copied()
+        .find(|line_width| *line_width >= line_length)
+        .unwrap_or(*LINE_WIDTH_CANDIDATES.last().unwrap()),
+    )
+  }
+
+  /// Gets the most common indent width, preferring the smaller width when tied.
+  fn get_indent_width(&self) -> Option<usize> {
+    self
+      .indent_width_counts
+      .iter()
+      .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+      .map(|(indent_width, _)| *indent_width)
+  }
+
+  fn get_quote_style(&self) -> Option<&'static str> {
+    if self.single_quote_lines + self.double_quote_lines == 0 {
+      None
+    } else if self.single_quote_lines > self.double_quote_lines {
+      Some("preferSingle")
+    } else {
+      Some("preferDouble")
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+  use serde_json::json;
+
+  use super::*;
+  use crate::environment::Environment;
+  use crate::environment::TestEnvironmentBuilder;
+  use crate::test_helpers::run_test_cli;
+
+  #[test]
+  fn should_get_values_from_text() {
+    let mut stats = TextStats::default();
+    stats.add_text("function test() {\r\n    if (true) {\r\n        return 'a';\r\n    }\r\n  /**\r\n   * b\r\n   */\r\n}\r\n");
+    assert_eq!(
+      Value::from(stats.get_values()),
+      json!({
+        "lineWidth": 80,
+        "indentWidth": 4,
+        "useTabs": false,
+        "newLineKind": "crlf",
+      })
+    );
+    assert_eq!(stats.get_quote_style(), Some("preferSingle"));
+
+    let mut stats = TextStats::default();
+    stats.add_text(&format!("{}\n\tconst a = \"b\";\n\t\tconst c = 'd';\n\t\"e\";\n", "a".repeat(110)));
+    assert_eq!(stats.get_line_width(), Some(120));
+    assert_eq!(
+      Value::from(stats.get_values()),
+      json!({
+        "lineWidth": 120,
+        "useTabs": true,
+        "newLineKind": "lf",
+      })
+    );
+    assert_eq!(stats.get_quote_style(), Some("preferDouble"));
+  }
+
+  #[test]
+  fn should_get_plugin_values_that_differ() {
+    let mut stats = TextStats::default();
+    stats.add_text("a\n  b\n");
+    let mut typescript_stats = TextStats::default();
+    typescript_stats.add_text("a\n  'b'\n");
+    let mut markdown_stats = TextStats::default();
+    markdown_stats.add_text(&format!("{}\n    b\n", "a".repeat(150)));
+    let suggestions = get_suggestions(
+      &stats,
+      &[("markdown".to_string(), markdown_stats), ("typescript".to_string(), typescript_stats)],
+    );
+    assert_eq!(
+      Value::from(suggestions),
+      json!({
+        "lineWidth": 80,
+        "indentWidth": 2,
+        "useTabs": false,
+        "newLineKind": "lf",
+        "markdown": {
+          "lineWidth": 160,
+          "indentWidth": 4,
+        },
+        "typescript": {
+          "quoteStyle": "preferSingle",
+        },
+      })
+    );
+  }
+
+  #[test]
+  fn should_suggest_and_write_config() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_remote_wasm_plugin().add_includes("**/*.txt");
+      })
+      .write_file("/file.txt", &format!("{}\n    b\n", "a".repeat(90)))
+      .write_file("/file2.txt", "a\n    b\n        c\n")
+      .build();
+    run_test_cli(vec!["suggest-config"], &environment).unwrap();
+    let expected_text = concat!(
+      "Suggested configuration based on 2 sampled files:\n",
+      "{\n",
+      "  \"lineWidth\": 100,\n",
+      "  \"indentWidth\": 4,\n",
+      "  \"useTabs\": false,\n",
+      "  \"newLineKind\": \"lf\"\n",
+      "}"
+    );
+    assert_eq!(environment.take_stdout_messages(), vec![expected_text]);
+
+    run_test_cli(vec!["suggest-config", "--write"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![expected_text]);
+    assert_eq!(environment.take_stderr_messages(), vec!["Updated /dprint.json"]);
+    let config_text = environment.read_file("/dprint.json").unwrap();
+    assert!(config_text.contains("\"lineWidth\": 100"));
+    assert!(config_text.contains("\"indentWidth\": 4"));
+    assert!(config_text.contains("\"newLineKind\": \"lf\""));
+  }
+}
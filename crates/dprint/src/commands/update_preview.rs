@@ -0,0 +1,216 @@
+use anyhow::Result;
+use dprint_core::configuration::ConfigKeyMap;
+use dprint_core::plugins::NullCancellationToken;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::configuration::get_global_config;
+use crate::configuration::get_plugin_config_map;
+use crate::configuration::GetGlobalConfigOptions;
+use crate::configuration::PluginUpdateInfo;
+use crate::configuration::ResolvedConfig;
+use crate::environment::Environment;
+use crate::paths::get_file_paths_by_plugins;
+use crate::plugins::get_process_plugin_env_vars;
+use crate::plugins::InitializedPlugin;
+use crate::plugins::InitializedPluginFormatRequest;
+use crate::plugins::Plugin;
+use crate::plugins::PluginResolver;
+use crate::plugins::PluginSourceReference;
+use crate::utils::get_changed_line_counts;
+use crate::utils::get_unified_diff;
+
+/// The maximum number of files to format with both versions of a plugin,
+/// which are spread evenly across the sorted file paths.
+const MAX_PREVIEW_FILES: usize = 20;
+
+/// How a sample file would be formatted differently by the new plugin version.
+#[derive(Debug, PartialEq, Eq)]
+enum PreviewFileChange {
+  Lines { added_count: usize, removed_count: usize },
+  Error { version: String, message: String },
+}
+
+/// Formats a sample of the files with both the old and new version of the
+/// plugin side by side, then logs a summary of the files that would be
+/// formatted differently after the update (`dprint config update --preview`).
+pub async fn preview_plugin_update<TEnvironment: Environment>(
+  info: &PluginUpdateInfo,
+  config: &ResolvedConfig,
+  file_paths: &[PathBuf],
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+) -> Result<()> {
+  let old_plugin = resolve_plugin_with_config(&info.old_reference, config, environment, plugin_resolver).await?;
+  let new_plugin = resolve_plugin_with_config(&info.new_reference, config, environment, plugin_resolver).await?;
+  let plugins = vec![old_plugin];
+  let mut sample_file_paths = get_file_paths_by_plugins(&plugins, file_paths.to_vec(), &config.base_path, &config.overrides, &[])?
+    .into_values()
+    .flatten()
+    .collect::<Vec<_>>();
+  sample_file_paths.sort();
+  if sample_file_paths.is_empty() {
+    environment.log_stderr(&format!("No files found to preview the update of {}.", info.name));
+    return Ok(());
+  }
+  let step = std::cmp::max(1, (sample_file_paths.len() + MAX_PREVIEW_FILES - 1) / MAX_PREVIEW_FILES);
+
+  let old_initialized_plugin = plugins[0].initialize().await?;
+  let new_initialized_plugin = match new_plugin.initialize().await {
+    Ok(plugin) => plugin,
+    Err(err) => {
+      old_initialized_plugin.shutdown().await;
+      return Err(err);
+    }
+  };
+  let mut sampled_count = 0;
+  let mut changes = Vec::new();
+  for file_path in sample_file_paths.iter().step_by(step) {
+    let file_text = match environment.read_file(file_path) {
+      Ok(file_text) => file_text,
+      Err(err) => {
+        log_verbose!(environment, "Skipped previewing {}: {:#}", file_path.display(), err);
+        continue;
+      }
+    };
+    sampled_count += 1;
+    let relative_path = file_path.strip_prefix(&config.base_path).unwrap_or(file_path);
+    let relative_path = relative_path.to_string_lossy().replace('\\', "/");
+    let old_result = format_with_plugin(&old_initialized_plugin, file_path, &file_text).await;
+    let new_result = format_with_plugin(&new_initialized_plugin, file_path, &file_text).await;
+    let change = match (old_result, new_result) {
+      (Ok(old_text), Ok(new_text)) => {
+        if old_text == new_text {
+          continue;
+        }
+        log_verbose!(environment, "{}", get_unified_diff(&relative_path, &old_text, &new_text));
+        let (added_count, removed_count) = get_changed_line_counts(&old_text, &new_text);
+        PreviewFileChange::Lines { added_count, removed_count }
+      }
+      (Err(err), _) => PreviewFileChange::Error {
+        version: info.old_version.clone(),
+        message: format!("{:#}", err),
+      },
+      (_, Err(err)) => PreviewFileChange::Error {
+        version: info.new_version.clone(),
+        message: format!("{:#}", err),
+      },
+    };
+    changes.push((relative_path, change));
+  }
+  old_initialized_plugin.shutdown().await;
+  new_initialized_plugin.shutdown().await;
+
+  environment.log_stderr(&get_preview_summary_text(info, sampled_count, &changes));
+  Ok(())
+}
+
+async fn resolve_plugin_with_config<TEnvironment: Environment>(
+  reference: &PluginSourceReference,
+  config: &ResolvedConfig,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+) -> Result<Box<dyn Plugin>> {
+  let mut plugin = plugin_resolver.resolve_plugin(reference).await?;
+  let mut config_map = config.config_map.clone();
+  let plugin_config = get_plugin_config_map(&*plugin, &mut config_map)?;
+  let global_config = get_global_config(
+    config_map,
+    environment,
+    &GetGlobalConfigOptions {
+      // the configuration of the other plugins is still in the map
+      check_unknown_property_diagnostics: false,
+    },
+  )?;
+  plugin.set_config(plugin_config, global_config);
+  if let Some(env_vars) = get_process_plugin_env_vars(&*plugin, config, environment)? {
+    plugin.set_env_vars(env_vars);
+  }
+  Ok(plugin)
+}
+
+async fn format_with_plugin(plugin: &Arc<dyn InitializedPlugin>, file_path: &Path, file_text: &str) -> Result<String> {
+  let result = plugin
+    .format_text(InitializedPluginFormatRequest {
+      file_path: file_path.to_path_buf(),
+      file_text: file_text.to_string(),
+      range: None,
+      override_config: ConfigKeyMap::new(),
+      token: Arc::new(NullCancellationToken),
+    })
+    .await?;
+  Ok(result.unwrap_or_else(|| file_text.to_string()))
+}
+
+fn get_preview_summary_text(info: &PluginUpdateInfo, sampled_count: usize, changes: &[(String, PreviewFileChange)]) -> String {
+  let mut text = format!(
+    "Previewed {} {} to {} on {} sample file(s): ",
+    info.name, info.old_version, info.new_version, sampled_count
+  );
+  if changes.is_empty() {
+    text.push_str("no formatting differences.");
+    return text;
+  }
+  text.push_str(&format!("{} would format differently.", changes.len()));
+  for (relative_path, change) in changes {
+    match change {
+      PreviewFileChange::Lines { added_count, removed_count } => {
+        text.push_str(&format!("\n  {} (+{} -{} lines)", relative_path, added_count, removed_count));
+      }
+      PreviewFileChange::Error { version, message } => {
+        text.push_str(&format!("\n  {} (error formatting with {}: {})", relative_path, version, message));
+      }
+    }
+  }
+  text
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn should_get_preview_summary_text() {
+    let info = PluginUpdateInfo {
+      name: "test-plugin".to_string(),
+      old_version: "0.1.0".to_string(),
+      old_reference: PluginSourceReference::new_remote_from_str("https://plugins.dprint.dev/test-plugin.wasm"),
+      new_version: "0.2.0".to_string(),
+      new_reference: PluginSourceReference::new_remote_from_str("https://plugins.dprint.dev/test-plugin-2.wasm"),
+    };
+    assert_eq!(
+      get_preview_summary_text(&info, 3, &[]),
+      "Previewed test-plugin 0.1.0 to 0.2.0 on 3 sample file(s): no formatting differences."
+    );
+    assert_eq!(
+      get_preview_summary_text(
+        &info,
+        3,
+        &[
+          (
+            "src/a.ts".to_string(),
+            PreviewFileChange::Lines {
+              added_count: 2,
+              removed_count: 1,
+            }
+          ),
+          (
+            "src/b.ts".to_string(),
+            PreviewFileChange::Error {
+              version: "0.2.0".to_string(),
+              message: "Panicked.".to_string(),
+            }
+          ),
+        ]
+      ),
+      concat!(
+        "Previewed test-plugin 0.1.0 to 0.2.0 on 3 sample file(s): 2 would format differently.\n",
+        "  src/a.ts (+2 -1 lines)\n",
+        "  src/b.ts (error formatting with 0.2.0: Panicked.)",
+      )
+    );
+  }
+}
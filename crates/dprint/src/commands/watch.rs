@@ -0,0 +1,325 @@
+use anyhow::Result;
+use crossterm::style::Stylize;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use crate::arg_parser::CliArgs;
+use crate::arg_parser::FmtSubCommand;
+use crate::commands::get_difference_output;
+use crate::commands::get_generated_file_skipper;
+use crate::commands::resolve_plugins_for_run;
+use crate::commands::run_post_format_hooks;
+use crate::configuration::resolve_config_from_args;
+use crate::configuration::ResolvedConfig;
+use crate::environment::Environment;
+use crate::format::run_parallelized;
+use crate::format::EnsureStableFormat;
+use crate::format::RunParallelizedOptions;
+use crate::paths::get_and_resolve_file_paths;
+use crate::paths::PluginNames;
+use crate::plugins::PluginResolver;
+use crate::plugins::PluginsCollection;
+use crate::transforms::FileTextTransforms;
+use crate::utils::BatchedFileWriter;
+use crate::utils::PathSource;
+use crate::utils::BOM_CHAR;
+
+/// How often the files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Formats the files and then keeps formatting the files that change (`dprint fmt --watch`).
+///
+/// The files are polled for changes to their modified time. The plugins stay
+/// instantiated between changes and are only resolved again when the
+/// configuration file or a local plugin changes.
+pub async fn format_watch<TEnvironment: Environment>(
+  cmd: &FmtSubCommand,
+  args: &CliArgs,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+  plugin_pools: Arc<PluginsCollection<TEnvironment>>,
+) -> Result<()> {
+  let mut watcher = FormatWatcher::default();
+  // nothing can be watched without a configuration
+  watcher.update_config(cmd, args, environment, plugin_resolver, &plugin_pools).await?;
+  let mut last_error_message = None;
+  let mut is_first_run = true;
+  loop {
+    let result = match watcher.update_config(cmd, args, environment, plugin_resolver, &plugin_pools).await {
+      Ok(()) => watcher.format_changed_files(cmd, environment, &plugin_pools).await,
+      Err(err) => Err(err),
+    };
+    match result {
+      Ok(()) => last_error_message = None,
+      Err(err) => {
+        // don't repeat the same error on every poll
+        let message = format!("{:#}", err);
+        if last_error_message.as_ref() != Some(&message) {
+          environment.log_stderr(&message);
+          last_error_message = Some(message);
+        }
+      }
+    }
+    if is_first_run {
+      environment.log("Watching for changes. Press Ctrl+C to exit.");
+      is_first_run = false;
+    }
+    tokio::time::sleep(POLL_INTERVAL).await;
+  }
+}
+
+/// The modified time of a file or `None` when it doesn't exist.
+type FileStamp = (PathBuf, Option<SystemTime>);
+
+#[derive(Default)]
+struct FormatWatcher {
+  config: Option<ResolvedConfig>,
+  /// The configuration file and local plugins.
+  config_stamps: Vec<FileStamp>,
+  /// The modified times of the files when they were last formatted.
+  file_stamps: HashMap<PathBuf, SystemTime>,
+}
+
+impl FormatWatcher {
+  /// Resolves the configuration and plugins again when the configuration file or a local plugin changed.
+  async fn update_config<TEnvironment: Environment>(
+    &mut self,
+    cmd: &FmtSubCommand,
+    args: &CliArgs,
+    environment: &TEnvironment,
+    plugin_resolver: &PluginResolver<TEnvironment>,
+    plugin_pools: &PluginsCollection<TEnvironment>,
+  ) -> Result<()> {
+    if !self.config_stamps.is_empty() {
+      let config_stamps = get_file_stamps(self.config_stamps.iter().map(|(file_path, _)| file_path.clone()), environment);
+      if config_stamps == self.config_stamps {
+        return Ok(());
+      }
+      // when this fails, wait for another change before trying again
+      self.config_stamps = config_stamps;
+    }
+
+    let config = resolve_config_from_args(args, environment)?;
+    self.config_stamps = get_file_stamps(get_config_file_paths(&config).into_iter(), environment);
+    plugin_pools.drop_and_shutdown_initialized().await;
+    self.config = None;
+    let plugins = resolve_plugins_for_run(args, &config, cmd.allow_plugin_failures, environment, plugin_resolver).await?;
+    plugin_pools.set_plugins(plugins, &config.base_path, &config.overrides, &cmd.patterns.associations)?;
+    self.config = Some(config);
+    // the files may format differently now
+    self.file_stamps.clear();
+    Ok(())
+  }
+
+  /// Formats the files that were added or modified since they were last formatted.
+  async fn format_changed_files<TEnvironment: Environment>(
+    &mut self,
+    cmd: &FmtSubCommand,
+    environment: &TEnvironment,
+    plugin_pools: &Arc<PluginsCollection<TEnvironment>>,
+  ) -> Result<()> {
+    let config = match &self.config {
+      Some(config) => config,
+      None => return Ok(()),
+    };
+    let file_paths = get_and_resolve_file_paths(config, &cmd.patterns, environment).await?;
+    let mut changed_file_paths = Vec::new();
+    let mut file_stamps = HashMap::with_capacity(file_paths.len());
+    for file_path in file_paths {
+      // the file may have been removed after getting the file paths
+      if let Ok(modified_time) = environment.file_modified_time(&file_path) {
+        if self.file_stamps.get(&file_path) != Some(&modified_time) {
+          changed_file_paths.push(file_path.clone());
+        }
+        file_stamps.insert(file_path, modified_time);
+      }
+    }
+    self.file_stamps = file_stamps;
+    if changed_file_paths.is_empty() {
+      return Ok(());
+    }
+
+    let formatted_file_paths = Arc::new(Mutex::new(Vec::new()));
+    let output_diff = cmd.diff;
+    let file_writer = Arc::new(BatchedFileWriter::new(environment.clone(), cmd.fsync));
+    let result = run_parallelized(
+      get_file_paths_by_plugin_names(changed_file_paths, plugin_pools),
+      environment,
+      plugin_pools.clone(),
+      RunParallelizedOptions {
+        ensure_stable_format: EnsureStableFormat(cmd.enable_stable_format),
+        allow_plugin_failures: cmd.allow_plugin_failures,
+        deterministic: cmd.deterministic,
+        generated_file_skipper: get_generated_file_skipper(config),
+        transforms: FileTextTransforms::from_config(config)?.map(Arc::new),
+        ..Default::default()
+      },
+      {
+        let formatted_file_paths = formatted_file_paths.clone();
+        let file_writer = file_writer.clone();
+        move |file_path, file_text, formatted_text, had_bom, _, environment| {
+          if formatted_text != file_text {
+            if output_diff {
+              environment.log(&get_difference_output(file_path, file_text, &formatted_text, &Default::default(), None));
+            }
+            let new_text = if had_bom {
+              // add back the BOM
+              format!("{}{}", BOM_CHAR, formatted_text)
+            } else {
+              formatted_text
+            };
+            file_writer.write(file_path.to_path_buf(), new_text);
+            formatted_file_paths.lock().push(file_path.to_path_buf());
+          }
+          Ok(())
+        }
+      },
+    )
+    .await;
+    let write_result = file_writer.finish();
+    let formatted_file_paths = std::mem::take(&mut *formatted_file_paths.lock());
+    // writing the formatted files isn't a change to format again
+    for file_path in formatted_file_paths.iter() {
+      if let Ok(modified_time) = environment.file_modified_time(file_path) {
+        self.file_stamps.insert(file_path.clone(), modified_time);
+      }
+    }
+    result?;
+    write_result?;
+
+    if !formatted_file_paths.is_empty() {
+      let suffix = if formatted_file_paths.len() == 1 { "file" } else { "files" };
+      environment.log(&format!("Formatted {} {}.", formatted_file_paths.len().to_string().bold(), suffix));
+    }
+    run_post_format_hooks(config, formatted_file_paths, environment)
+  }
+}
+
+/// Gets the paths of the configuration file and local plugins, which are what resolve the plugins.
+fn get_config_file_paths(config: &ResolvedConfig) -> Vec<PathBuf> {
+  let mut file_paths = Vec::new();
+  if config.resolved_path.is_local() {
+    file_paths.push(config.resolved_path.file_path.clone().into_path_buf());
+  }
+  for plugin in config.plugins.iter() {
+    if let PathSource::Local(source) = &plugin.path_source {
+      file_paths.push(source.path.clone().into_path_buf());
+    }
+  }
+  file_paths
+}
+
+fn get_file_stamps(file_paths: impl Iterator<Item = PathBuf>, environment: &impl Environment) -> Vec<FileStamp> {
+  file_paths
+    .map(|file_path| {
+      let modified_time = environment.file_modified_time(&file_path).ok();
+      (file_path, modified_time)
+    })
+    .collect()
+}
+
+fn get_file_paths_by_plugin_names<TEnvironment: Environment>(
+  file_paths: Vec<PathBuf>,
+  plugin_pools: &PluginsCollection<TEnvironment>,
+) -> HashMap<PluginNames, Vec<PathBuf>> {
+  let mut file_paths_by_plugins: HashMap<PluginNames, Vec<PathBuf>> = HashMap::new();
+  for file_path in file_paths {
+    let plugin_names = plugin_pools.get_plugin_names_from_file_path(&file_path);
+    if !plugin_names.is_empty() {
+      file_paths_by_plugins
+        .entry(PluginNames::from_plugin_names(&plugin_names))
+        .or_default()
+        .push(file_path);
+    }
+  }
+  file_paths_by_plugins
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::arg_parser::parse_args;
+  use crate::arg_parser::SubCommand;
+  use crate::environment::TestEnvironmentBuilder;
+  use crate::plugins::PluginCache;
+  use crate::test_helpers::get_plural_formatted_text;
+  use crate::test_helpers::get_singular_formatted_text;
+  use crate::utils::TestStdInReader;
+
+  #[test]
+  fn should_format_changed_files_with_the_same_plugins() {
+    let environment = TestEnvironmentBuilder::new()
+      .add_remote_wasm_plugin()
+      .with_default_config(|c| {
+        c.add_includes("**/*.txt").add_remote_wasm_plugin();
+      })
+      .write_file("/file1.txt", "text1")
+      .write_file("/file2.txt", "text2")
+      .initialize()
+      .build();
+    environment.run_in_runtime({
+      let environment = environment.clone();
+      async move {
+        let collection = Arc::new(PluginsCollection::new(environment.clone()));
+        let plugin_cache = Arc::new(PluginCache::new(environment.clone()));
+        let resolver = PluginResolver::new(environment.clone(), plugin_cache, collection.clone());
+        let args = parse_args(vec!["".to_string(), "fmt".to_string(), "--watch".to_string()], TestStdInReader::default()).unwrap();
+        let cmd = match &args.sub_command {
+          SubCommand::Fmt(cmd) => cmd.clone(),
+          _ => unreachable!(),
+        };
+        let mut watcher = FormatWatcher::default();
+        watcher.update_config(&cmd, &args, &environment, &resolver, &collection).await.unwrap();
+        watcher.format_changed_files(&cmd, &environment, &collection).await.unwrap();
+        assert_eq!(environment.take_stdout_messages(), vec![get_plural_formatted_text(2)]);
+        assert_eq!(environment.read_file("/file1.txt").unwrap(), "text1_formatted");
+        let plugin = collection.get_plugin("test-plugin");
+        assert!(plugin.is_initialized());
+
+        // nothing changed, including the files that were written
+        watcher.update_config(&cmd, &args, &environment, &resolver, &collection).await.unwrap();
+        watcher.format_changed_files(&cmd, &environment, &collection).await.unwrap();
+        assert_eq!(environment.take_stdout_messages(), Vec::<String>::new());
+
+        // only the changed and added files are formatted with the same plugin instance
+        environment.write_file("/file1.txt", "changed").unwrap();
+        environment.write_file("/file3.txt", "text3").unwrap();
+        watcher.update_config(&cmd, &args, &environment, &resolver, &collection).await.unwrap();
+        watcher.format_changed_files(&cmd, &environment, &collection).await.unwrap();
+        assert_eq!(environment.take_stdout_messages(), vec![get_plural_formatted_text(2)]);
+        assert_eq!(environment.read_file("/file1.txt").unwrap(), "changed_formatted");
+        assert_eq!(environment.read_file("/file3.txt").unwrap(), "text3_formatted");
+        assert!(Arc::ptr_eq(&plugin, &collection.get_plugin("test-plugin")));
+
+        // changing the configuration resolves the plugins again
+        let config_text = environment.read_file("/dprint.json").unwrap();
+        environment.write_file("/dprint.json", &config_text).unwrap();
+        environment.write_file("/file2.txt", "other").unwrap();
+        watcher.update_config(&cmd, &args, &environment, &resolver, &collection).await.unwrap();
+        watcher.format_changed_files(&cmd, &environment, &collection).await.unwrap();
+        assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
+        assert_eq!(environment.read_file("/file2.txt").unwrap(), "other_formatted");
+        assert!(!Arc::ptr_eq(&plugin, &collection.get_plugin("test-plugin")));
+
+        collection.drop_and_shutdown_initialized().await;
+      }
+    });
+  }
+
+  #[test]
+  fn should_error_watching_without_writing() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_wasm_plugin()
+      .write_file("/file.txt", "text")
+      .build();
+    let err = crate::test_helpers::run_test_cli(vec!["fmt", "--watch", "--mode", "diff"], &environment)
+      .err()
+      .unwrap();
+    assert_eq!(err.to_string(), "The --watch flag is only supported when writing the formatted files.");
+  }
+}
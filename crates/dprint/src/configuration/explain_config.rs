@@ -0,0 +1,246 @@
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use jsonc_parser::ast::Object;
+use jsonc_parser::ast::Value;
+use jsonc_parser::common::Ranged;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::arg_parser::CliArgs;
+use crate::environment::Environment;
+use crate::utils::resolve_url_or_file_path;
+use crate::utils::ResolvedPath;
+
+use super::resolve_main_config_path::resolve_main_config_path;
+
+/// Where a resolved configuration value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigValueOrigin {
+  /// A file path or url in the extends chain and the line number of the property.
+  File { source: String, line: usize },
+  /// A command line flag.
+  CliFlag(&'static str),
+  /// A plugin property that wasn't specified, so it uses the global property of the same name.
+  Global(String),
+  /// The plugin's default value.
+  Default,
+}
+
+impl fmt::Display for ConfigValueOrigin {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ConfigValueOrigin::File { source, line } => write!(f, "{}:{}", source, line),
+      ConfigValueOrigin::CliFlag(flag) => write!(f, "{} flag", flag),
+      ConfigValueOrigin::Global(key) => write!(f, "global \"{}\" property", key),
+      ConfigValueOrigin::Default => write!(f, "default"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigValueSource {
+  /// The property name, which is prefixed with the plugin's
+  /// configuration key for plugin properties (ex. `typescript.lineWidth`).
+  pub key: String,
+  /// The value as JSON text.
+  pub value: String,
+  pub origin: ConfigValueOrigin,
+}
+
+/// Gets where each value in the configuration file and its extended
+/// configuration files comes from.
+///
+/// This follows the same precedence as resolving the configuration, where a
+/// value in a configuration file wins over the values in the files it extends.
+pub fn get_config_value_sources<TEnvironment: Environment>(args: &CliArgs, environment: &TEnvironment) -> Result<Vec<ConfigValueSource>> {
+  let resolved_config_path = resolve_main_config_path(args, environment)?;
+  let mut sources = ConfigValueSources::default();
+  for plugin in args.plugins.iter() {
+    sources.push_plugin(plugin.to_string(), ConfigValueOrigin::CliFlag("--plugins"));
+  }
+  sources.add_file(&resolved_config_path.resolved_path, true, args, environment)?;
+  Ok(sources.items)
+}
+
+#[derive(Default)]
+struct ConfigValueSources {
+  items: Vec<ConfigValueSource>,
+  keys: HashSet<String>,
+}
+
+impl ConfigValueSources {
+  fn push_plugin(&mut self, value: String, origin: ConfigValueOrigin) {
+    // plugins are combined rather than overridden
+    self.items.push(ConfigValueSource {
+      key: "plugins".to_string(),
+      value,
+      origin,
+    });
+  }
+
+  fn push_if_not_set(&mut self, key: String, value: String, origin: ConfigValueOrigin) {
+    if self.keys.insert(key.clone()) {
+      self.items.push(ConfigValueSource { key, value, origin });
+    }
+  }
+
+  fn add_file(&mut self, resolved_path: &ResolvedPath, is_main: bool, args: &CliArgs, environment: &impl Environment) -> Result<()> {
+    let file_text = environment.read_file(&resolved_path.file_path)?;
+    let json_file =
+      jsonc_parser::parse_to_ast(&file_text, &Default::default(), &Default::default()).with_context(|| "Error parsing config file.".to_string())?;
+    let root_obj = match json_file.value {
+      Some(Value::Object(obj)) => obj,
+      _ => bail!("Expected a root object in the json"),
+    };
+    let source = resolved_path.source.display();
+    let get_origin = |range_start: usize| ConfigValueOrigin::File {
+      source: source.clone(),
+      line: file_text[..range_start].matches('\n').count() + 1,
+    };
+    // includes and excludes are only used from the local main configuration file
+    let allows_file_patterns = is_main && resolved_path.is_local();
+
+    let mut extends = Vec::new();
+    for prop in root_obj.properties.iter() {
+      let name = prop.name.as_str();
+      match name {
+        "$schema" | "projectType" => {}
+        "extends" => extends.extend(get_string_values(&prop.value)),
+        "includes"
+        | "excludes"
+        | "checkSeverity"
+        | "overrides"
+        | "generatedFiles"
+        | "skipBuildArtifacts"
+        | "hooks"
+        | "execPlugins"
+        | "processPluginEnv"
+        | "transforms"
+        | "remoteConfigFallback"
+          if !allows_file_patterns => {}
+        "plugins" => {
+          // the --plugins flag overrides the plugins of the main configuration file
+          if !is_main || args.plugins.is_empty() {
+            if let Value::Array(array) = &prop.value {
+              for element in array.elements.iter() {
+                self.push_plugin(get_value_text(&file_text, element), get_origin(element.start()));
+              }
+            }
+          }
+        }
+        _ => match &prop.value {
+          Value::Object(plugin_obj) => self.add_plugin_obj(name, plugin_obj, &file_text, &get_origin),
+          value => self.push_if_not_set(name.to_string(), get_value_text(&file_text, value), get_origin(prop.start())),
+        },
+      }
+    }
+
+    for url_or_file_path in extends {
+      let extends_path = resolve_url_or_file_path(&url_or_file_path, &resolved_path.source.parent(), environment)?;
+      if let Err(err) = self.add_file(&extends_path, false, args, environment) {
+        bail!("Error with '{}'. {:#}", extends_path.source.display(), err);
+      }
+    }
+    Ok(())
+  }
+
+  fn add_plugin_obj(&mut self, config_key: &str, plugin_obj: &Object, file_text: &str, get_origin: &impl Fn(usize) -> ConfigValueOrigin) {
+    for prop in plugin_obj.properties.iter() {
+      let name = prop.name.as_str();
+      if name == "locked" {
+        continue;
+      }
+      self.push_if_not_set(
+        format!("{}.{}", config_key, name),
+        get_value_text(file_text, &prop.value),
+        get_origin(prop.start()),
+      );
+    }
+  }
+}
+
+fn get_string_values(value: &Value) -> Vec<String> {
+  match value {
+    Value::StringLit(lit) => vec![lit.value.to_string()],
+    Value::Array(array) => array
+      .elements
+      .iter()
+      .filter_map(|element| match element {
+        Value::StringLit(lit) => Some(lit.value.to_string()),
+        _ => None,
+      })
+      .collect(),
+    _ => Vec::new(),
+  }
+}
+
+/// Gets the text of the value on a single line.
+fn get_value_text(file_text: &str, value: &Value) -> String {
+  let text = &file_text[value.start()..value.end()];
+  text
+    .lines()
+    .map(|line| line.trim())
+    .filter(|line| !line.is_empty())
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::environment::TestEnvironment;
+
+  #[test]
+  fn should_get_sources_from_extends_chain() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        "/dprint.json",
+        r#"{
+  "extends": "./base.json",
+  "lineWidth": 80,
+  "typescript": {
+    "quoteStyle": "preferSingle"
+  },
+  "plugins": ["https://plugins.dprint.dev/typescript.wasm"]
+}"#,
+      )
+      .unwrap();
+    environment
+      .write_file(
+        "/base.json",
+        r#"{
+  "lineWidth": 100,
+  "includes": ["**/*.ts"],
+  "typescript": {
+    "quoteStyle": "alwaysDouble",
+    "semiColons": "asi"
+  }
+}"#,
+      )
+      .unwrap();
+
+    let sources = get_config_value_sources(&CliArgs::empty(), &environment).unwrap();
+    let file = |source: &str, line: usize| ConfigValueOrigin::File {
+      source: source.to_string(),
+      line,
+    };
+    assert_eq!(
+      sources.into_iter().map(|source| (source.key, source.value, source.origin)).collect::<Vec<_>>(),
+      vec![
+        ("lineWidth".to_string(), "80".to_string(), file("/dprint.json", 3)),
+        ("typescript.quoteStyle".to_string(), "\"preferSingle\"".to_string(), file("/dprint.json", 5)),
+        (
+          "plugins".to_string(),
+          "\"https://plugins.dprint.dev/typescript.wasm\"".to_string(),
+          file("/dprint.json", 7)
+        ),
+        // includes are not inherited
+        ("typescript.semiColons".to_string(), "\"asi\"".to_string(), file("/base.json", 6)),
+      ]
+    );
+  }
+}
@@ -0,0 +1,163 @@
+use anyhow::Result;
+use serde_json::Map;
+use serde_json::Value;
+
+use super::CheckSeverity;
+use super::ConfigMapValue;
+use super::ExecPluginConfig;
+use super::ExecPluginMode;
+use super::GeneratedFilesConfig;
+use super::RawPluginConfig;
+use super::ResolvedConfig;
+use super::UnicodeNormalizationForm;
+
+/// Gets the text of a configuration file with the values of the resolved
+/// configuration, which no longer extends any other configuration.
+///
+/// The plugins are provided separately so they may be pinned.
+pub fn get_flattened_config_text(config: &ResolvedConfig, plugins: Vec<String>) -> Result<String> {
+  let mut obj = Map::new();
+  if let Some(incremental) = config.incremental {
+    obj.insert("incremental".to_string(), Value::Bool(incremental));
+  }
+  if !config.includes.is_empty() {
+    obj.insert("includes".to_string(), serde_json::to_value(&config.includes)?);
+  }
+  if !config.excludes.is_empty() {
+    obj.insert("excludes".to_string(), serde_json::to_value(&config.excludes)?);
+  }
+  if !config.check_severity.is_empty() {
+    let mut severities = Map::new();
+    for (pattern, severity) in config.check_severity.iter() {
+      let severity = match severity {
+        CheckSeverity::Error => "error",
+        CheckSeverity::Warn => "warn",
+      };
+      severities.insert(pattern.to_string(), Value::String(severity.to_string()));
+    }
+    obj.insert("checkSeverity".to_string(), Value::Object(severities));
+  }
+  if !config.overrides.is_empty() {
+    let mut overrides = Map::new();
+    for (pattern, config_override) in config.overrides.iter() {
+      let mut obj = Map::new();
+      if !config_override.disable_plugins.is_empty() {
+        obj.insert("disablePlugins".to_string(), serde_json::to_value(&config_override.disable_plugins)?);
+      }
+      overrides.insert(pattern.to_string(), Value::Object(obj));
+    }
+    obj.insert("overrides".to_string(), Value::Object(overrides));
+  }
+  if config.generated_files != GeneratedFilesConfig::default() {
+    let mut generated_files = Map::new();
+    generated_files.insert("skip".to_string(), Value::Bool(config.generated_files.skip));
+    generated_files.insert("markers".to_string(), serde_json::to_value(&config.generated_files.markers)?);
+    obj.insert("generatedFiles".to_string(), Value::Object(generated_files));
+  }
+  if !config.skip_build_artifacts {
+    obj.insert("skipBuildArtifacts".to_string(), Value::Bool(false));
+  }
+  if !config.hooks.post_format.is_empty() {
+    let mut hooks = Map::new();
+    let post_format = config
+      .hooks
+      .post_format
+      .iter()
+      .map(|hook_command| {
+        let mut parts = vec![hook_command.command.clone()];
+        parts.extend(hook_command.args.iter().cloned());
+        serde_json::to_value(parts)
+      })
+      .collect::<Result<Vec<_>, _>>()?;
+    hooks.insert("postFormat".to_string(), Value::Array(post_format));
+    obj.insert("hooks".to_string(), Value::Object(hooks));
+  }
+  if !config.exec_plugins.is_empty() {
+    let mut exec_plugins = Map::new();
+    for exec_plugin in config.exec_plugins.iter() {
+      exec_plugins.insert(exec_plugin.name.to_string(), get_exec_plugin_value(exec_plugin)?);
+    }
+    obj.insert("execPlugins".to_string(), Value::Object(exec_plugins));
+  }
+  if !config.process_plugin_env.is_empty() {
+    obj.insert("processPluginEnv".to_string(), serde_json::to_value(&config.process_plugin_env)?);
+  }
+  if !config.transforms.is_empty() {
+    let mut transforms = Map::new();
+    for (pattern, transform) in config.transforms.iter() {
+      let mut obj = Map::new();
+      if let Some(license_header) = &transform.license_header {
+        obj.insert("licenseHeader".to_string(), Value::String(license_header.to_string()));
+      }
+      if !transform.remove_headers.is_empty() {
+        obj.insert("removeHeaders".to_string(), serde_json::to_value(&transform.remove_headers)?);
+      }
+      if transform.normalize_bom {
+        obj.insert("normalizeBom".to_string(), Value::Bool(true));
+      }
+      if let Some(form) = transform.unicode_normalization {
+        let form = match form {
+          UnicodeNormalizationForm::Nfc => "nfc",
+          UnicodeNormalizationForm::Nfd => "nfd",
+        };
+        obj.insert("unicodeNormalization".to_string(), Value::String(form.to_string()));
+      }
+      if let Some(tab_width) = transform.tabs_to_spaces {
+        obj.insert("tabsToSpaces".to_string(), Value::from(tab_width));
+      }
+      if transform.trim_trailing_whitespace {
+        obj.insert("trimTrailingWhitespace".to_string(), Value::Bool(true));
+      }
+      transforms.insert(pattern.to_string(), Value::Object(obj));
+    }
+    obj.insert("transforms".to_string(), Value::Object(transforms));
+  }
+  for (key, value) in config.config_map.iter() {
+    let value = match value {
+      ConfigMapValue::KeyValue(value) => serde_json::to_value(value)?,
+      ConfigMapValue::Vec(items) => serde_json::to_value(items)?,
+      ConfigMapValue::PluginConfig(plugin_config) => get_plugin_config_value(plugin_config)?,
+    };
+    obj.insert(key.to_string(), value);
+  }
+  obj.insert("plugins".to_string(), serde_json::to_value(plugins)?);
+  Ok(serde_json::to_string_pretty(&obj)?)
+}
+
+fn get_exec_plugin_value(exec_plugin: &ExecPluginConfig) -> Result<Value> {
+  let mut obj = Map::new();
+  obj.insert("command".to_string(), Value::String(exec_plugin.command.to_string()));
+  if !exec_plugin.args.is_empty() {
+    obj.insert("args".to_string(), serde_json::to_value(&exec_plugin.args)?);
+  }
+  if !exec_plugin.file_extensions.is_empty() {
+    obj.insert("fileExtensions".to_string(), serde_json::to_value(&exec_plugin.file_extensions)?);
+  }
+  if !exec_plugin.file_names.is_empty() {
+    obj.insert("fileNames".to_string(), serde_json::to_value(&exec_plugin.file_names)?);
+  }
+  if let Some(associations) = &exec_plugin.associations {
+    obj.insert("associations".to_string(), serde_json::to_value(associations)?);
+  }
+  if exec_plugin.mode != ExecPluginMode::Stdin {
+    obj.insert("mode".to_string(), Value::String("tempFile".to_string()));
+  }
+  if exec_plugin.timeout_secs != ExecPluginConfig::DEFAULT_TIMEOUT_SECS {
+    obj.insert("timeout".to_string(), Value::from(exec_plugin.timeout_secs));
+  }
+  Ok(Value::Object(obj))
+}
+
+fn get_plugin_config_value(plugin_config: &RawPluginConfig) -> Result<Value> {
+  let mut obj = Map::new();
+  if let Some(associations) = &plugin_config.associations {
+    obj.insert("associations".to_string(), serde_json::to_value(associations)?);
+  }
+  if plugin_config.locked {
+    obj.insert("locked".to_string(), Value::Bool(true));
+  }
+  for (key, value) in plugin_config.properties.iter() {
+    obj.insert(key.to_string(), serde_json::to_value(value)?);
+  }
+  Ok(Value::Object(obj))
+}
@@ -1,5 +1,6 @@
 use anyhow::bail;
 use anyhow::Result;
+use dprint_core::configuration::ConfigKeyValue;
 
 use super::ConfigMap;
 use super::ConfigMapValue;
@@ -18,6 +19,7 @@ fn get_plugin_config_map_inner(plugin: &dyn Plugin, config_map: &mut ConfigMap)
 
   if let Some(plugin_config) = config_map.remove(config_key) {
     if let ConfigMapValue::PluginConfig(plugin_config) = plugin_config {
+      verify_experiments(plugin, &plugin_config)?;
       Ok(plugin_config)
     } else {
       bail!("Expected the configuration property '{}' to be an object.", config_key)
@@ -27,6 +29,40 @@ fn get_plugin_config_map_inner(plugin: &dyn Plugin, config_map: &mut ConfigMap)
   }
 }
 
+/// Ensures the experiments in the configuration are advertised by the plugin.
+///
+/// The experiments are left in the configuration so the plugin receives them.
+fn verify_experiments(plugin: &dyn Plugin, plugin_config: &RawPluginConfig) -> Result<()> {
+  let values = match plugin_config.properties.get("experiments") {
+    Some(ConfigKeyValue::Array(values)) => values,
+    Some(_) => bail!("The 'experiments' property in a plugin configuration must be an array of strings."),
+    None => return Ok(()),
+  };
+  for value in values {
+    let experiment = match value {
+      ConfigKeyValue::String(value) => value,
+      _ => bail!("The 'experiments' property in a plugin configuration must be an array of strings."),
+    };
+    if !plugin.experiments().contains(experiment) {
+      if plugin.experiments().is_empty() {
+        bail!(
+          "Unknown experiment '{}' for {}. The plugin doesn't have any experiments.",
+          experiment,
+          plugin.name()
+        );
+      } else {
+        bail!(
+          "Unknown experiment '{}' for {}. Expected one of: {}",
+          experiment,
+          plugin.name(),
+          plugin.experiments().join(", ")
+        );
+      }
+    }
+  }
+  Ok(())
+}
+
 #[cfg(test)]
 mod tests {
   use crate::plugins::TestPlugin;
@@ -61,6 +97,42 @@ mod tests {
     assert_errors(&mut config_map, "Expected the configuration property 'typescript' to be an object.");
   }
 
+  #[test]
+  fn should_verify_experiments() {
+    let get_config_map = |experiments: Vec<ConfigKeyValue>| {
+      let mut config_map = ConfigMap::new();
+      let ts_plugin = RawPluginConfig {
+        associations: None,
+        locked: false,
+        properties: ConfigKeyMap::from([("experiments".to_string(), ConfigKeyValue::Array(experiments))]),
+      };
+      config_map.insert(String::from("typescript"), ConfigMapValue::PluginConfig(ts_plugin));
+      config_map
+    };
+    let plugin = create_plugin().with_experiments(vec!["tailwind-sorting", "other"]);
+    let result = get_plugin_config_map(&plugin, &mut get_config_map(vec![ConfigKeyValue::from_str("tailwind-sorting")])).unwrap();
+    // kept so the plugin receives it
+    assert_eq!(
+      result.properties.get("experiments"),
+      Some(&ConfigKeyValue::Array(vec![ConfigKeyValue::from_str("tailwind-sorting")]))
+    );
+
+    let result = get_plugin_config_map(&plugin, &mut get_config_map(vec![ConfigKeyValue::from_str("unknown")]));
+    assert_eq!(
+      result.err().unwrap().to_string(),
+      "Error initializing from configuration file. Unknown experiment 'unknown' for dprint-plugin-typescript. Expected one of: tailwind-sorting, other"
+    );
+    let result = get_plugin_config_map(&plugin, &mut get_config_map(vec![ConfigKeyValue::from_i32(1)]));
+    assert_eq!(
+      result.err().unwrap().to_string(),
+      "Error initializing from configuration file. The 'experiments' property in a plugin configuration must be an array of strings."
+    );
+    assert_errors(
+      &mut get_config_map(vec![ConfigKeyValue::from_str("tailwind-sorting")]),
+      "Unknown experiment 'tailwind-sorting' for dprint-plugin-typescript. The plugin doesn't have any experiments.",
+    );
+  }
+
   fn assert_errors(config_map: &mut ConfigMap, message: &str) {
     let test_plugin = create_plugin();
     let result = get_plugin_config_map(&test_plugin, config_map);
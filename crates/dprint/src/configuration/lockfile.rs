@@ -0,0 +1,140 @@
+use anyhow::bail;
+use anyhow::Result;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+use std::path::PathBuf;
+use url::Url;
+
+use crate::environment::Environment;
+use crate::plugins::PluginSourceReference;
+use crate::utils::PathSource;
+
+const LOCKFILE_NAME: &str = "dprint.lock";
+const LOCKFILE_VERSION: u32 = 1;
+
+/// Pinned resolutions of the plugin version ranges in a configuration file.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Lockfile {
+  version: u32,
+  /// Keyed by the plugin reference as written in the configuration file.
+  pub plugins: IndexMap<String, LockfilePlugin>,
+}
+
+impl Default for Lockfile {
+  fn default() -> Self {
+    Lockfile {
+      version: LOCKFILE_VERSION,
+      plugins: IndexMap::new(),
+    }
+  }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LockfilePlugin {
+  pub version: String,
+  pub url: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub checksum: Option<String>,
+}
+
+impl Lockfile {
+  /// Gets if any plugin is pinned to a checksum, which is the case once
+  /// the lockfile is created with `dprint config update --lockfile`.
+  pub fn has_checksums(&self) -> bool {
+    self.plugins.values().any(|plugin| plugin.checksum.is_some())
+  }
+}
+
+impl LockfilePlugin {
+  pub fn as_source_reference(&self) -> Result<PluginSourceReference> {
+    Ok(PluginSourceReference {
+      path_source: PathSource::new_remote(Url::parse(&self.url)?),
+      checksum: self.checksum.clone(),
+    })
+  }
+}
+
+/// Gets the path of the lockfile that sits beside the configuration file.
+pub fn get_lockfile_path(config_file_path: &Path) -> PathBuf {
+  config_file_path.with_file_name(LOCKFILE_NAME)
+}
+
+pub fn read_lockfile(file_path: &Path, environment: &impl Environment) -> Result<Lockfile> {
+  if !environment.path_exists(file_path) {
+    return Ok(Lockfile::default());
+  }
+  let text = environment.read_file(file_path)?;
+  let lockfile = match serde_json::from_str::<Lockfile>(&text) {
+    Ok(lockfile) => lockfile,
+    Err(err) => bail!("Error deserializing {}. {:#}", file_path.display(), err),
+  };
+  if lockfile.version != LOCKFILE_VERSION {
+    bail!(
+      "Unsupported lockfile version {} in {}. Expected {}. You may need to upgrade dprint.",
+      lockfile.version,
+      file_path.display(),
+      LOCKFILE_VERSION
+    );
+  }
+  Ok(lockfile)
+}
+
+pub fn write_lockfile(file_path: &Path, lockfile: &Lockfile, environment: &impl Environment) -> Result<()> {
+  let text = serde_json::to_string_pretty(lockfile)?;
+  environment.write_file(file_path, &format!("{}\n", text))
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::environment::TestEnvironment;
+
+  #[test]
+  fn should_round_trip_lockfile() {
+    let environment = TestEnvironment::new();
+    let file_path = get_lockfile_path(Path::new("/dprint.json"));
+    assert_eq!(file_path, PathBuf::from("/dprint.lock"));
+    assert_eq!(read_lockfile(&file_path, &environment).unwrap(), Lockfile::default());
+
+    let mut lockfile = Lockfile::default();
+    lockfile.plugins.insert(
+      "typescript@^0.91".to_string(),
+      LockfilePlugin {
+        version: "0.91.2".to_string(),
+        url: "https://plugins.dprint.dev/typescript-0.91.2.wasm".to_string(),
+        checksum: None,
+      },
+    );
+    write_lockfile(&file_path, &lockfile, &environment).unwrap();
+    assert_eq!(
+      environment.read_file(&file_path).unwrap(),
+      r#"{
+  "version": 1,
+  "plugins": {
+    "typescript@^0.91": {
+      "version": "0.91.2",
+      "url": "https://plugins.dprint.dev/typescript-0.91.2.wasm"
+    }
+  }
+}
+"#
+    );
+    assert_eq!(read_lockfile(&file_path, &environment).unwrap(), lockfile);
+  }
+
+  #[test]
+  fn should_error_for_unknown_version() {
+    let environment = TestEnvironment::new();
+    environment.write_file("/dprint.lock", r#"{ "version": 2, "plugins": {} }"#).unwrap();
+    assert_eq!(
+      read_lockfile(Path::new("/dprint.lock"), &environment).err().unwrap().to_string(),
+      "Unsupported lockfile version 2 in /dprint.lock. Expected 1. You may need to upgrade dprint."
+    );
+  }
+}
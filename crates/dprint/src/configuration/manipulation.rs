@@ -4,6 +4,7 @@ use anyhow::Context;
 use anyhow::Result;
 use jsonc_parser::ast::Array;
 use jsonc_parser::ast::Object;
+use jsonc_parser::ast::Value;
 use jsonc_parser::common::Ranged;
 
 use crate::plugins::PluginSourceReference;
@@ -65,6 +66,74 @@ pub fn add_to_plugins_array(file_text: &str, url: &str) -> Result<String> {
   Ok(final_text)
 }
 
+/// Sets the value of a property (ex. `lineWidth`) or of a plugin's property
+/// (ex. `typescript.quoteStyle`) in the config file to the json text.
+pub fn set_config_value(file_text: &str, key: &str, value_text: &str) -> Result<String> {
+  let obj = parse_file(file_text)?;
+  let indentation_text = get_indentation_text(file_text, &obj);
+  let newline_char = get_newline_char(file_text);
+  let (name, plugin_property_name) = match key.split_once('.') {
+    Some((config_key, property_name)) => (config_key, Some(property_name)),
+    None => (key, None),
+  };
+  let prop = obj.properties.iter().find(|p| p.name.as_str() == name);
+  Ok(match (prop, plugin_property_name) {
+    (Some(prop), None) => replace_value(file_text, &prop.value, value_text),
+    (Some(prop), Some(property_name)) => match &prop.value {
+      Value::Object(plugin_obj) => match plugin_obj.properties.iter().find(|p| p.name.as_str() == property_name) {
+        Some(plugin_prop) => replace_value(file_text, &plugin_prop.value, value_text),
+        None => insert_property(
+          file_text,
+          plugin_obj,
+          &format!("\"{}\": {}", property_name, value_text),
+          &indentation_text.repeat(2),
+          &indentation_text,
+          &newline_char,
+        ),
+      },
+      _ => bail!("Expected the '{}' property in the config file to be an object.", name),
+    },
+    (None, None) => insert_property(file_text, &obj, &format!("\"{}\": {}", name, value_text), &indentation_text, "", &newline_char),
+    (None, Some(property_name)) => {
+      let property_text = format!(
+        "\"{}\": {{{}{}{}\"{}\": {}{}{}}}",
+        name, newline_char, indentation_text, indentation_text, property_name, value_text, newline_char, indentation_text
+      );
+      insert_property(file_text, &obj, &property_text, &indentation_text, "", &newline_char)
+    }
+  })
+}
+
+fn replace_value(file_text: &str, value: &Value, value_text: &str) -> String {
+  format!("{}{}{}", &file_text[..value.start()], value_text, &file_text[value.end()..])
+}
+
+fn insert_property(file_text: &str, obj: &Object, property_text: &str, indentation_text: &str, closing_indentation_text: &str, newline_char: &str) -> String {
+  match obj.properties.last() {
+    Some(last_property) => {
+      let pos = last_property.end();
+      format!(
+        "{},{}{}{}{}",
+        &file_text[..pos],
+        newline_char,
+        indentation_text,
+        property_text,
+        &file_text[pos..]
+      )
+    }
+    None => format!(
+      "{}{}{}{}{}{}{}",
+      &file_text[..obj.start() + 1],
+      newline_char,
+      indentation_text,
+      property_text,
+      newline_char,
+      closing_indentation_text,
+      &file_text[obj.end() - 1..]
+    ),
+  }
+}
+
 fn parse_file(file_text: &str) -> Result<Object<'_>> {
   let json_file = jsonc_parser::parse_to_ast(file_text, &Default::default(), &Default::default()).with_context(|| "Error parsing config file.".to_string())?;
   match json_file.value {
@@ -105,6 +174,45 @@ fn get_indentation_text(file_text: &str, root_obj: &Object) -> String {
 mod test {
   use super::*;
 
+  #[test]
+  pub fn set_config_values() {
+    let file_text = r#"{
+  "lineWidth": 80,
+  "typescript": {
+    "semiColons": "asi"
+  },
+  "json": {},
+  "plugins": []
+}"#;
+    let file_text = set_config_value(file_text, "lineWidth", "120").unwrap();
+    let file_text = set_config_value(&file_text, "indentWidth", "4").unwrap();
+    let file_text = set_config_value(&file_text, "typescript.quoteStyle", r#""preferSingle""#).unwrap();
+    let file_text = set_config_value(&file_text, "typescript.semiColons", r#""always""#).unwrap();
+    let file_text = set_config_value(&file_text, "json.indentWidth", "2").unwrap();
+    let file_text = set_config_value(&file_text, "markdown.lineWidth", "100").unwrap();
+    assert_eq!(
+      file_text,
+      r#"{
+  "lineWidth": 120,
+  "typescript": {
+    "semiColons": "always",
+    "quoteStyle": "preferSingle"
+  },
+  "json": {
+    "indentWidth": 2
+  },
+  "plugins": [],
+  "indentWidth": 4,
+  "markdown": {
+    "lineWidth": 100
+  }
+}"#
+    );
+
+    let err = set_config_value(&file_text, "lineWidth.value", "1").err().unwrap();
+    assert_eq!(err.to_string(), "Expected the 'lineWidth' property in the config file to be an object.");
+  }
+
   #[test]
   pub fn add_plugins_array_empty() {
     let final_text = add_to_plugins_array(
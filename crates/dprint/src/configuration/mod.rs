@@ -1,16 +1,22 @@
 mod deserialize_config;
+mod explain_config;
+mod flatten_config;
 mod get_global_config;
 mod get_init_config_file_text;
 mod get_plugin_config_map;
+mod lockfile;
 mod manipulation;
 mod resolve_config;
 mod resolve_main_config_path;
 mod types;
 
 use deserialize_config::*;
+pub use explain_config::*;
+pub use flatten_config::*;
 pub use get_global_config::*;
 pub use get_init_config_file_text::*;
 pub use get_plugin_config_map::*;
+pub use lockfile::*;
 pub use manipulation::*;
 pub use resolve_config::*;
 pub use types::*;
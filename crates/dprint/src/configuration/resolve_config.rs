@@ -2,20 +2,29 @@ use anyhow::bail;
 use anyhow::Result;
 use crossterm::style::Stylize;
 use dprint_core::configuration::ConfigKeyValue;
+use indexmap::IndexMap;
 use std::path::Path;
+use std::path::PathBuf;
 
 use crate::arg_parser::CliArgs;
 use crate::configuration::deserialize_config;
+use crate::configuration::get_lockfile_path;
+use crate::configuration::read_lockfile;
 use crate::configuration::ConfigMap;
 use crate::configuration::ConfigMapValue;
+use crate::configuration::Lockfile;
 use crate::environment::CanonicalizedPathBuf;
 use crate::environment::Environment;
 use crate::plugins::parse_plugin_source_reference;
+use crate::plugins::PluginRangeReference;
 use crate::plugins::PluginSourceReference;
-use crate::utils::resolve_url_or_file_path;
+use crate::utils::resolve_url_or_file_path_with_fallback;
 use crate::utils::PathSource;
 use crate::utils::PluginKind;
+use crate::utils::RemoteConfigFallback;
 use crate::utils::ResolvedPath;
+use crate::utils::Version;
+use crate::utils::DEFAULT_GENERATED_FILE_MARKERS;
 
 use super::resolve_main_config_path::resolve_main_config_path;
 
@@ -28,9 +37,122 @@ pub struct ResolvedConfig {
   pub excludes: Vec<String>,
   pub plugins: Vec<PluginSourceReference>,
   pub incremental: Option<bool>,
+  /// Severity of a check failure by glob. Files not matched are errors.
+  pub check_severity: IndexMap<String, CheckSeverity>,
+  /// Configuration for the files matching a glob.
+  pub overrides: IndexMap<String, ConfigOverride>,
+  pub generated_files: GeneratedFilesConfig,
+  /// Skips the directories of build artifacts and dependencies when globbing
+  /// unless they're explicitly included (`"skipBuildArtifacts"`).
+  pub skip_build_artifacts: bool,
+  pub hooks: HooksConfig,
+  /// Plugins that format the text of their files by running an external command.
+  pub exec_plugins: Vec<ExecPluginConfig>,
+  /// Environment variables to start the process plugins with by the plugin's name or
+  /// configuration key. The values may reference the CLI's environment (ex. `${env:JAVA_HOME}`).
+  pub process_plugin_env: IndexMap<String, IndexMap<String, String>>,
+  /// Host-side transforms of the text of the files matching a glob.
+  pub transforms: IndexMap<String, TransformConfig>,
+  /// Checksum pinned Wasm module that decides which of the matched files are formatted.
+  pub file_selector: Option<PluginSourceReference>,
   pub config_map: ConfigMap,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CheckSeverity {
+  Error,
+  Warn,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GeneratedFilesConfig {
+  /// Skips formatting the files that contain a marker near their start.
+  pub skip: bool,
+  pub markers: Vec<String>,
+}
+
+impl Default for GeneratedFilesConfig {
+  fn default() -> Self {
+    GeneratedFilesConfig {
+      skip: false,
+      markers: DEFAULT_GENERATED_FILE_MARKERS.iter().map(|marker| marker.to_string()).collect(),
+    }
+  }
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct HooksConfig {
+  /// Commands run after `dprint fmt` successfully formats any files,
+  /// which receive the paths of the formatted files via stdin.
+  pub post_format: Vec<HookCommand>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HookCommand {
+  pub command: String,
+  pub args: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ExecPluginConfig {
+  /// The name of the plugin, which is also its configuration key.
+  pub name: String,
+  pub command: String,
+  /// The arguments, where `{{filePath}}` is replaced with the path of the file.
+  pub args: Vec<String>,
+  pub file_extensions: Vec<String>,
+  pub file_names: Vec<String>,
+  pub associations: Option<Vec<String>>,
+  pub mode: ExecPluginMode,
+  pub timeout_secs: u64,
+  /// The command and arguments that output the version of the formatter,
+  /// which is included in the incremental cache hash.
+  pub version_command: Option<Vec<String>>,
+}
+
+impl ExecPluginConfig {
+  pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExecPluginMode {
+  /// The text is provided via stdin and the formatted text is read from stdout.
+  Stdin,
+  /// The text is written to a temporary file that the command formats in place.
+  TempFile,
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Hash, Debug)]
+pub struct TransformConfig {
+  /// Header that's removed before the text is formatted and
+  /// added back at the start of the formatted text.
+  pub license_header: Option<String>,
+  /// Headers that are removed before the text is formatted (ex. outdated license headers).
+  pub remove_headers: Vec<String>,
+  /// Removes any extra or incorrectly decoded byte order marks
+  /// at the start of the text before it's formatted.
+  pub normalize_bom: bool,
+  /// Unicode normalization form the text is converted to before it's formatted.
+  pub unicode_normalization: Option<UnicodeNormalizationForm>,
+  /// Replaces the tabs in the indentation of the formatted text with spaces
+  /// up to the next tab stop of this width.
+  pub tabs_to_spaces: Option<u32>,
+  /// Removes the spaces and tabs at the end of the lines of the formatted text.
+  pub trim_trailing_whitespace: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum UnicodeNormalizationForm {
+  Nfc,
+  Nfd,
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct ConfigOverride {
+  /// Names or configuration keys of the plugins that shouldn't format the files.
+  pub disable_plugins: Vec<String>,
+}
+
 pub fn resolve_config_from_args<TEnvironment: Environment>(args: &CliArgs, environment: &TEnvironment) -> Result<ResolvedConfig> {
   let resolved_config_path = resolve_main_config_path(args, environment)?;
   let base_source = resolved_config_path.resolved_path.source.parent();
@@ -53,7 +175,14 @@ pub fn resolve_config_from_args<TEnvironment: Environment>(args: &CliArgs, envir
     }
   };
 
-  let plugins_vec = take_plugins_array_from_config_map(&mut main_config_map, &base_source, environment)?; // always take this out of the config map
+  let lockfile_path = if resolved_config_path.resolved_path.is_local() {
+    Some(get_lockfile_path(config_file_path.as_ref()))
+  } else if args.frozen {
+    bail!("--frozen requires a local configuration file.");
+  } else {
+    None
+  };
+  let plugins_vec = take_plugins_array_from_config_map(&mut main_config_map, &base_source, lockfile_path.as_deref(), args.frozen, environment)?; // always take this out of the config map
   let plugins = filter_duplicate_plugin_sources(if args.plugins.is_empty() {
     // filter out any non-wasm plugins from remote config
     if !resolved_config_path.resolved_path.is_local() {
@@ -81,6 +210,16 @@ pub fn resolve_config_from_args<TEnvironment: Environment>(args: &CliArgs, envir
     // Careful! Don't be fancy and ensure both of these are removed.
     let removed_includes = main_config_map.remove("includes").is_some(); // NEVER REMOVE THIS STATEMENT
     let removed_excludes = main_config_map.remove("excludes").is_some(); // NEVER REMOVE THIS STATEMENT
+    main_config_map.remove("checkSeverity"); // the end user decides what fails the build
+    main_config_map.remove("overrides"); // the end user decides which plugins format their files
+    main_config_map.remove("generatedFiles"); // the end user decides which files are skipped
+    main_config_map.remove("skipBuildArtifacts"); // the end user decides which files are skipped
+    main_config_map.remove("hooks"); // NEVER REMOVE THIS STATEMENT - remote config must not run commands
+    main_config_map.remove("execPlugins"); // NEVER REMOVE THIS STATEMENT - remote config must not run commands
+    main_config_map.remove("processPluginEnv"); // NEVER REMOVE THIS STATEMENT - remote config must not read the environment
+    main_config_map.remove("transforms"); // the end user decides how the text of their files is changed
+    main_config_map.remove("fileSelector"); // the end user decides what files get formatted
+    main_config_map.remove("remoteConfigFallback"); // the end user decides whether outdated configuration fails the run
     let was_removed = removed_includes || removed_excludes;
     if was_removed && resolved_config_path.resolved_path.is_first_download {
       environment.log_stderr(&get_warn_includes_excludes_message());
@@ -91,6 +230,18 @@ pub fn resolve_config_from_args<TEnvironment: Environment>(args: &CliArgs, envir
   let includes = take_array_from_config_map(&mut main_config_map, "includes")?;
   let excludes = take_array_from_config_map(&mut main_config_map, "excludes")?;
   let incremental = take_bool_from_config_map(&mut main_config_map, "incremental")?;
+  let check_severity = take_check_severity_from_config_map(&mut main_config_map)?;
+  let overrides = take_overrides_from_config_map(&mut main_config_map)?;
+  let generated_files = take_generated_files_from_config_map(&mut main_config_map)?;
+  let skip_build_artifacts = take_bool_from_config_map(&mut main_config_map, "skipBuildArtifacts")?.unwrap_or(true);
+  let hooks = take_hooks_from_config_map(&mut main_config_map)?;
+  let exec_plugins = take_exec_plugins_from_config_map(&mut main_config_map)?;
+  // the plugins provided on the command line replace the configured plugins
+  let exec_plugins = if args.plugins.is_empty() { exec_plugins } else { Vec::new() };
+  let process_plugin_env = take_process_plugin_env_from_config_map(&mut main_config_map)?;
+  let transforms = take_transforms_from_config_map(&mut main_config_map)?;
+  let file_selector = take_file_selector_from_config_map(&mut main_config_map, &base_source, environment)?;
+  let remote_config_fallback = take_remote_config_fallback_from_config_map(&mut main_config_map)?;
   main_config_map.remove("projectType"); // this was an old config property that's no longer used
   let extends = take_extends(&mut main_config_map)?;
   let mut resolved_config = ResolvedConfig {
@@ -101,23 +252,95 @@ pub fn resolve_config_from_args<TEnvironment: Environment>(args: &CliArgs, envir
     excludes,
     plugins,
     incremental,
+    check_severity,
+    overrides,
+    generated_files,
+    skip_build_artifacts,
+    hooks,
+    exec_plugins,
+    process_plugin_env,
+    transforms,
+    file_selector,
   };
 
   // resolve extends
-  resolve_extends(&mut resolved_config, extends, &base_source, environment)?;
+  resolve_extends(&mut resolved_config, extends, &base_source, remote_config_fallback, environment)?;
 
   Ok(resolved_config)
 }
 
+/// Gets the plugin version ranges in the main configuration file along with
+/// the path to the lockfile they should be pinned in.
+pub fn resolve_main_config_plugin_ranges<TEnvironment: Environment>(
+  args: &CliArgs,
+  environment: &TEnvironment,
+) -> Result<(PathBuf, Vec<PluginRangeReference>)> {
+  let resolved_config_path = resolve_main_config_path(args, environment)?;
+  let config_file_path = &resolved_config_path.resolved_path.file_path;
+  let lockfile_path = get_lockfile_path(config_file_path.as_ref());
+  if !resolved_config_path.resolved_path.is_local() {
+    return Ok((lockfile_path, Vec::new()));
+  }
+  let mut config_map = match get_config_map_from_path(config_file_path, environment)? {
+    Ok(config_map) => config_map,
+    Err(_) => return Ok((lockfile_path, Vec::new())), // surfaced when resolving the config
+  };
+  let mut range_references = Vec::new();
+  for url_or_file_path in take_array_from_config_map(&mut config_map, "plugins")? {
+    if let Some(range_reference) = PluginRangeReference::parse(&url_or_file_path)? {
+      range_references.push(range_reference);
+    }
+  }
+  Ok((lockfile_path, range_references))
+}
+
+/// The remote plugins in the main configuration file that aren't version ranges.
+pub struct MainConfigRemotePlugins {
+  /// The path to the lockfile the plugins should be pinned in.
+  pub lockfile_path: PathBuf,
+  /// The plugins keyed by the text as written in the configuration file.
+  pub plugins: Vec<(String, PluginSourceReference)>,
+}
+
+/// Gets the remote plugins in the main configuration file that aren't version
+/// ranges. This is `None` when the configuration file isn't local.
+pub fn resolve_main_config_remote_plugins<TEnvironment: Environment>(args: &CliArgs, environment: &TEnvironment) -> Result<Option<MainConfigRemotePlugins>> {
+  let resolved_config_path = resolve_main_config_path(args, environment)?;
+  let config_file_path = &resolved_config_path.resolved_path.file_path;
+  if !resolved_config_path.resolved_path.is_local() {
+    return Ok(None);
+  }
+  let mut config_map = match get_config_map_from_path(config_file_path, environment)? {
+    Ok(config_map) => config_map,
+    Err(_) => return Ok(None), // surfaced when resolving the config
+  };
+  let base_source = resolved_config_path.resolved_path.source.parent();
+  let mut remote_plugins = Vec::new();
+  for url_or_file_path in take_array_from_config_map(&mut config_map, "plugins")? {
+    if PluginRangeReference::parse(&url_or_file_path)?.is_some() {
+      continue;
+    }
+    let reference = parse_plugin_source_reference(&url_or_file_path, &base_source, environment)?;
+    if let PathSource::Remote(_) = &reference.path_source {
+      remote_plugins.push((url_or_file_path, reference));
+    }
+  }
+  Ok(Some(MainConfigRemotePlugins {
+    lockfile_path: get_lockfile_path(config_file_path.as_ref()),
+    plugins: remote_plugins,
+  }))
+}
+
 fn resolve_extends<TEnvironment: Environment>(
   resolved_config: &mut ResolvedConfig,
   extends: Vec<String>,
   base_path: &PathSource,
+  remote_config_fallback: RemoteConfigFallback,
   environment: &TEnvironment,
 ) -> Result<()> {
   for url_or_file_path in extends {
-    let resolved_path = resolve_url_or_file_path(&url_or_file_path, base_path, environment)?;
-    match handle_config_file(&resolved_path, resolved_config, environment) {
+    let resolved_path = resolve_url_or_file_path_with_fallback(&url_or_file_path, base_path, remote_config_fallback, environment)?;
+    match handle_config_file(&resolved_path, resolved_config, remote_config_fallback, environment) {
       Ok(extends) => extends,
       Err(err) => bail!("Error with '{}'. {:#}", resolved_path.source.display(), err),
     }
@@ -125,7 +348,12 @@ fn resolve_extends<TEnvironment: Environment>(
   Ok(())
 }
 
-fn handle_config_file<TEnvironment: Environment>(resolved_path: &ResolvedPath, resolved_config: &mut ResolvedConfig, environment: &TEnvironment) -> Result<()> {
+fn handle_config_file<TEnvironment: Environment>(
+  resolved_path: &ResolvedPath,
+  resolved_config: &mut ResolvedConfig,
+  remote_config_fallback: RemoteConfigFallback,
+  environment: &TEnvironment,
+) -> Result<()> {
   let config_file_path = &resolved_path.file_path;
   let mut new_config_map = match get_config_map_from_path(config_file_path, environment)? {
     Ok(config_map) => config_map,
@@ -135,6 +363,16 @@ fn handle_config_file<TEnvironment: Environment>(resolved_path: &ResolvedPath, r
 
   // Discard any properties that shouldn't be inherited
   new_config_map.remove("projectType");
+  new_config_map.remove("checkSeverity");
+  new_config_map.remove("overrides");
+  new_config_map.remove("generatedFiles");
+  new_config_map.remove("skipBuildArtifacts");
+  new_config_map.remove("hooks"); // NEVER REMOVE THIS STATEMENT - only the main configuration may run commands
+  new_config_map.remove("execPlugins"); // NEVER REMOVE THIS STATEMENT - only the main configuration may run commands
+  new_config_map.remove("processPluginEnv"); // NEVER REMOVE THIS STATEMENT - only the main configuration may read the environment
+  new_config_map.remove("transforms");
+  new_config_map.remove("fileSelector");
+  new_config_map.remove("remoteConfigFallback");
   // IMPORTANT
   // =========
   // Remove the includes and excludes from all referenced configuration since
@@ -145,7 +383,7 @@ fn handle_config_file<TEnvironment: Environment>(resolved_path: &ResolvedPath, r
   new_config_map.remove("excludes"); // NEVER REMOVE THIS STATEMENT
                                      // Also remove any non-wasm plugins, but only for remote configurations.
                                      // The assumption here is that the user won't be malicious to themselves.
-  let plugins = take_plugins_array_from_config_map(&mut new_config_map, &resolved_path.source.parent(), environment)?;
+  let plugins = take_plugins_array_from_config_map(&mut new_config_map, &resolved_path.source.parent(), None, false, environment)?;
   let plugins = if !resolved_path.is_local() {
     filter_non_wasm_plugins(plugins, environment)
   } else {
@@ -197,7 +435,7 @@ fn handle_config_file<TEnvironment: Environment>(resolved_path: &ResolvedPath, r
     }
   }
 
-  resolve_extends(resolved_config, extends, &resolved_path.source.parent(), environment)?;
+  resolve_extends(resolved_config, extends, &resolved_path.source.parent(), remote_config_fallback, environment)?;
 
   Ok(())
 }
@@ -228,16 +466,112 @@ fn get_config_map_from_path(file_path: impl AsRef<Path>, environment: &impl Envi
 fn take_plugins_array_from_config_map(
   config_map: &mut ConfigMap,
   base_path: &PathSource,
+  lockfile_path: Option<&Path>,
+  frozen: bool,
   environment: &impl Environment,
 ) -> Result<Vec<PluginSourceReference>> {
   let plugin_url_or_file_paths = take_array_from_config_map(config_map, "plugins")?;
   let mut plugins = Vec::with_capacity(plugin_url_or_file_paths.len());
-  for url_or_file_path in plugin_url_or_file_paths {
-    plugins.push(parse_plugin_source_reference(&url_or_file_path, base_path, environment)?);
+  let lockfile = match lockfile_path {
+    Some(lockfile_path) => Some((read_lockfile(lockfile_path, environment)?, lockfile_path)),
+    None => None,
+  };
+  for url_or_file_path in plugin_url_or_file_paths.iter() {
+    if let Some(range_reference) = PluginRangeReference::parse(url_or_file_path)? {
+      let (lockfile, lockfile_path) = match &lockfile {
+        Some(lockfile) => lockfile,
+        None => bail!(
+          "The plugin '{}' uses a version range, which is only supported in a local main configuration file.",
+          range_reference.text
+        ),
+      };
+      let reference = get_locked_plugin(&range_reference, lockfile, lockfile_path)?;
+      if frozen && reference.checksum.is_none() {
+        bail!(
+          "The plugin '{}' is not pinned to a checksum in {}. Run `dprint config update --lockfile` to pin it.",
+          url_or_file_path,
+          lockfile_path.display()
+        );
+      }
+      plugins.push(reference);
+    } else {
+      let mut reference = parse_plugin_source_reference(url_or_file_path, base_path, environment)?;
+      if let Some((lockfile, lockfile_path)) = &lockfile {
+        apply_pinned_checksum(url_or_file_path, &mut reference, lockfile, lockfile_path, frozen)?;
+      }
+      plugins.push(reference);
+    }
+  }
+  if let Some((lockfile, lockfile_path)) = &lockfile {
+    if frozen {
+      if let Some(text) = lockfile.plugins.keys().find(|text| !plugin_url_or_file_paths.contains(*text)) {
+        bail!(
+          "The plugin '{}' pinned in {} is no longer in the configuration file. Run `dprint config update --lockfile` to update the lockfile.",
+          text,
+          lockfile_path.display()
+        );
+      }
+    }
   }
   Ok(plugins)
 }
 
+/// Verifies the remote plugin against the checksum pinned in the lockfile,
+/// using the pinned checksum when the configuration file doesn't have one.
+fn apply_pinned_checksum(text: &str, reference: &mut PluginSourceReference, lockfile: &Lockfile, lockfile_path: &Path, frozen: bool) -> Result<()> {
+  let url = match &reference.path_source {
+    PathSource::Remote(source) => source.url.as_str(),
+    PathSource::Local(_) => return Ok(()),
+  };
+  let locked_plugin = match lockfile.plugins.get(text) {
+    Some(locked_plugin) if locked_plugin.url == url && locked_plugin.checksum.is_some() => locked_plugin,
+    _ => {
+      if frozen {
+        bail!(
+          "The plugin '{}' is not pinned in {}. Run `dprint config update --lockfile` to pin it.",
+          text,
+          lockfile_path.display()
+        );
+      }
+      // the lockfile is refreshed on the next `dprint config update`
+      return Ok(());
+    }
+  };
+  if reference.checksum.is_none() {
+    reference.checksum = locked_plugin.checksum.clone();
+  } else if reference.checksum != locked_plugin.checksum {
+    bail!(
+      "The checksum of the plugin '{}' does not match the checksum pinned in {}. Run `dprint config update --lockfile` to update the lockfile.",
+      text,
+      lockfile_path.display()
+    );
+  }
+  Ok(())
+}
+
+fn get_locked_plugin(range_reference: &PluginRangeReference, lockfile: &Lockfile, lockfile_path: &Path) -> Result<PluginSourceReference> {
+  let locked_plugin = match lockfile.plugins.get(&range_reference.text) {
+    Some(locked_plugin) => locked_plugin,
+    None => bail!(
+      "The plugin '{}' is not pinned in {}. Run `dprint config update` to resolve it.",
+      range_reference.text,
+      lockfile_path.display()
+    ),
+  };
+  let satisfies_range = Version::parse(&locked_plugin.version)
+    .map(|version| range_reference.range.matches(&version))
+    .unwrap_or(false);
+  if !satisfies_range {
+    bail!(
+      "The version {} pinned in {} does not satisfy '{}'. Run `dprint config update` to resolve it.",
+      locked_plugin.version,
+      lockfile_path.display(),
+      range_reference.text
+    );
+  }
+  locked_plugin.as_source_reference()
+}
+
 fn take_array_from_config_map(config_map: &mut ConfigMap, property_name: &str) -> Result<Vec<String>> {
   let mut result = Vec::new();
   if let Some(value) = config_map.remove(property_name) {
@@ -262,6 +596,402 @@ fn take_bool_from_config_map(config_map: &mut ConfigMap, property_name: &str) ->
   }
 }
 
+fn take_remote_config_fallback_from_config_map(config_map: &mut ConfigMap) -> Result<RemoteConfigFallback> {
+  Ok(match config_map.remove("remoteConfigFallback") {
+    Some(ConfigMapValue::KeyValue(ConfigKeyValue::String(value))) if value == "error" => RemoteConfigFallback::Error,
+    Some(ConfigMapValue::KeyValue(ConfigKeyValue::String(value))) if value == "warn" => RemoteConfigFallback::Warn,
+    Some(_) => bail!("Expected \"error\" or \"warn\" in 'remoteConfigFallback' property."),
+    None => RemoteConfigFallback::default(),
+  })
+}
+
+fn take_check_severity_from_config_map(config_map: &mut ConfigMap) -> Result<IndexMap<String, CheckSeverity>> {
+  let mut result = IndexMap::new();
+  if let Some(value) = config_map.remove("checkSeverity") {
+    match value {
+      ConfigMapValue::PluginConfig(obj) => {
+        for (pattern, value) in obj.properties {
+          let severity = match value {
+            ConfigKeyValue::String(value) if value == "error" => CheckSeverity::Error,
+            ConfigKeyValue::String(value) if value == "warn" => CheckSeverity::Warn,
+            _ => bail!("Expected \"error\" or \"warn\" for '{}' in 'checkSeverity' property.", pattern),
+          };
+          result.insert(pattern, severity);
+        }
+      }
+      _ => bail!("Expected object in 'checkSeverity' property."),
+    }
+  }
+  Ok(result)
+}
+
+fn take_overrides_from_config_map(config_map: &mut ConfigMap) -> Result<IndexMap<String, ConfigOverride>> {
+  let mut result = IndexMap::new();
+  if let Some(value) = config_map.remove("overrides") {
+    match value {
+      ConfigMapValue::PluginConfig(obj) => {
+        for (pattern, value) in obj.properties {
+          let properties = match value {
+            ConfigKeyValue::Object(properties) => properties,
+            _ => bail!("Expected object for '{}' in 'overrides' property.", pattern),
+          };
+          let mut config_override = ConfigOverride::default();
+          for (key, value) in properties {
+            match key.as_str() {
+              "disablePlugins" => {
+                config_override.disable_plugins = match value {
+                  ConfigKeyValue::Array(values) => values
+                    .into_iter()
+                    .map(|value| match value {
+                      ConfigKeyValue::String(value) => Ok(value),
+                      _ => bail!("Expected array of strings in 'disablePlugins' for '{}' in 'overrides' property.", pattern),
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+                  _ => bail!("Expected array of strings in 'disablePlugins' for '{}' in 'overrides' property.", pattern),
+                }
+              }
+              _ => bail!("Unknown property '{}' for '{}' in 'overrides' property.", key, pattern),
+            }
+          }
+          result.insert(pattern, config_override);
+        }
+      }
+      _ => bail!("Expected object in 'overrides' property."),
+    }
+  }
+  Ok(result)
+}
+
+fn take_generated_files_from_config_map(config_map: &mut ConfigMap) -> Result<GeneratedFilesConfig> {
+  let mut result = GeneratedFilesConfig::default();
+  if let Some(value) = config_map.remove("generatedFiles") {
+    match value {
+      ConfigMapValue::PluginConfig(obj) => {
+        for (key, value) in obj.properties {
+          match key.as_str() {
+            "skip" => {
+              result.skip = match value {
+                ConfigKeyValue::Bool(value) => value,
+                _ => bail!("Expected boolean in 'skip' of 'generatedFiles' property."),
+              }
+            }
+            "markers" => {
+              result.markers = match value {
+                ConfigKeyValue::Array(values) => values
+                  .into_iter()
+                  .map(|value| match value {
+                    ConfigKeyValue::String(value) if !value.is_empty() => Ok(value),
+                    _ => bail!("Expected array of non-empty strings in 'markers' of 'generatedFiles' property."),
+                  })
+                  .collect::<Result<Vec<_>>>()?,
+                _ => bail!("Expected array of non-empty strings in 'markers' of 'generatedFiles' property."),
+              }
+            }
+            _ => bail!("Unknown property '{}' in 'generatedFiles' property.", key),
+          }
+        }
+      }
+      _ => bail!("Expected object in 'generatedFiles' property."),
+    }
+  }
+  Ok(result)
+}
+
+fn take_transforms_from_config_map(config_map: &mut ConfigMap) -> Result<IndexMap<String, TransformConfig>> {
+  let mut result = IndexMap::new();
+  if let Some(value) = config_map.remove("transforms") {
+    match value {
+      ConfigMapValue::PluginConfig(obj) => {
+        for (pattern, value) in obj.properties {
+          let properties = match value {
+            ConfigKeyValue::Object(properties) => properties,
+            _ => bail!("Expected object for '{}' in 'transforms' property.", pattern),
+          };
+          let mut transform = TransformConfig::default();
+          for (key, value) in properties {
+            match key.as_str() {
+              "licenseHeader" => {
+                transform.license_header = match value {
+                  ConfigKeyValue::String(value) if !value.is_empty() => Some(value),
+                  _ => bail!("Expected non-empty string in 'licenseHeader' for '{}' in 'transforms' property.", pattern),
+                }
+              }
+              "removeHeaders" => {
+                transform.remove_headers = match value {
+                  ConfigKeyValue::Array(values) => values
+                    .into_iter()
+                    .map(|value| match value {
+                      ConfigKeyValue::String(value) if !value.is_empty() => Ok(value),
+                      _ => bail!(
+                        "Expected array of non-empty strings in 'removeHeaders' for '{}' in 'transforms' property.",
+                        pattern
+                      ),
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+                  _ => bail!(
+                    "Expected array of non-empty strings in 'removeHeaders' for '{}' in 'transforms' property.",
+                    pattern
+                  ),
+                }
+              }
+              "normalizeBom" => {
+                transform.normalize_bom = match value {
+                  ConfigKeyValue::Bool(value) => value,
+                  _ => bail!("Expected boolean in 'normalizeBom' for '{}' in 'transforms' property.", pattern),
+                }
+              }
+              "unicodeNormalization" => {
+                transform.unicode_normalization = Some(match value {
+                  ConfigKeyValue::String(value) if value == "nfc" => UnicodeNormalizationForm::Nfc,
+                  ConfigKeyValue::String(value) if value == "nfd" => UnicodeNormalizationForm::Nfd,
+                  _ => bail!(
+                    "Expected \"nfc\" or \"nfd\" in 'unicodeNormalization' for '{}' in 'transforms' property.",
+                    pattern
+                  ),
+                })
+              }
+              "tabsToSpaces" => {
+                transform.tabs_to_spaces = match value {
+                  ConfigKeyValue::Number(value) if value > 0 => Some(value as u32),
+                  _ => bail!(
+                    "Expected number greater than zero in 'tabsToSpaces' for '{}' in 'transforms' property.",
+                    pattern
+                  ),
+                }
+              }
+              "trimTrailingWhitespace" => {
+                transform.trim_trailing_whitespace = match value {
+                  ConfigKeyValue::Bool(value) => value,
+                  _ => bail!("Expected boolean in 'trimTrailingWhitespace' for '{}' in 'transforms' property.", pattern),
+                }
+              }
+              _ => bail!("Unknown property '{}' for '{}' in 'transforms' property.", key, pattern),
+            }
+          }
+          result.insert(pattern, transform);
+        }
+      }
+      _ => bail!("Expected object in 'transforms' property."),
+    }
+  }
+  Ok(result)
+}
+
+fn take_file_selector_from_config_map(
+  config_map: &mut ConfigMap,
+  base_source: &PathSource,
+  environment: &impl Environment,
+) -> Result<Option<PluginSourceReference>> {
+  let text = match config_map.remove("fileSelector") {
+    Some(ConfigMapValue::KeyValue(ConfigKeyValue::String(text))) => text,
+    Some(_) => bail!("Expected string in 'fileSelector' property."),
+    None => return Ok(None),
+  };
+  let reference = parse_plugin_source_reference(&text, base_source, environment)?;
+  if reference.plugin_kind() != Some(PluginKind::Wasm) {
+    bail!("Expected a Wasm module (.wasm) in 'fileSelector' property, but found '{}'.", text);
+  }
+  if reference.checksum.is_none() {
+    bail!(
+      "The 'fileSelector' property must be pinned to the checksum of the module (ex. '{}@<checksum>').",
+      text
+    );
+  }
+  Ok(Some(reference))
+}
+
+fn take_hooks_from_config_map(config_map: &mut ConfigMap) -> Result<HooksConfig> {
+  let mut result = HooksConfig::default();
+  if let Some(value) = config_map.remove("hooks") {
+    match value {
+      ConfigMapValue::PluginConfig(obj) => {
+        for (key, value) in obj.properties {
+          match key.as_str() {
+            "postFormat" => result.post_format = get_hook_commands(value, &key)?,
+            _ => bail!("Unknown property '{}' in 'hooks' property.", key),
+          }
+        }
+      }
+      _ => bail!("Expected object in 'hooks' property."),
+    }
+  }
+  Ok(result)
+}
+
+fn get_hook_commands(value: ConfigKeyValue, property_name: &str) -> Result<Vec<HookCommand>> {
+  fn get_hook_command(value: ConfigKeyValue) -> Option<HookCommand> {
+    let mut parts = match value {
+      ConfigKeyValue::Array(parts) => parts
+        .into_iter()
+        .map(|part| match part {
+          ConfigKeyValue::String(part) => Some(part),
+          _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?,
+      _ => return None,
+    };
+    if parts.is_empty() || parts[0].is_empty() {
+      return None;
+    }
+    let command = parts.remove(0);
+    Some(HookCommand { command, args: parts })
+  }
+
+  let commands = match value {
+    ConfigKeyValue::Array(commands) => commands.into_iter().map(get_hook_command).collect::<Option<Vec<_>>>(),
+    _ => None,
+  };
+  match commands {
+    Some(commands) => Ok(commands),
+    None => bail!(
+      "Expected an array of commands in '{}' of 'hooks' property, where each command is an array of the command and its arguments (ex. [\"node\", \"script.js\"]).",
+      property_name
+    ),
+  }
+}
+
+fn take_exec_plugins_from_config_map(config_map: &mut ConfigMap) -> Result<Vec<ExecPluginConfig>> {
+  fn get_string_array(value: ConfigKeyValue, key: &str, name: &str) -> Result<Vec<String>> {
+    let values = match value {
+      ConfigKeyValue::Array(values) => values
+        .into_iter()
+        .map(|value| match value {
+          ConfigKeyValue::String(value) if !value.is_empty() => Some(value),
+          _ => None,
+        })
+        .collect::<Option<Vec<_>>>(),
+      _ => None,
+    };
+    match values {
+      Some(values) => Ok(values),
+      None => bail!("Expected array of non-empty strings in '{}' for '{}' in 'execPlugins' property.", key, name),
+    }
+  }
+
+  let mut result = Vec::new();
+  if let Some(value) = config_map.remove("execPlugins") {
+    match value {
+      ConfigMapValue::PluginConfig(obj) => {
+        for (name, value) in obj.properties {
+          let properties = match value {
+            ConfigKeyValue::Object(properties) => properties,
+            _ => bail!("Expected object for '{}' in 'execPlugins' property.", name),
+          };
+          let mut command = None;
+          let mut exec_plugin = ExecPluginConfig {
+            name: name.clone(),
+            command: String::new(),
+            args: Vec::new(),
+            file_extensions: Vec::new(),
+            file_names: Vec::new(),
+            associations: None,
+            mode: ExecPluginMode::Stdin,
+            timeout_secs: ExecPluginConfig::DEFAULT_TIMEOUT_SECS,
+            version_command: None,
+          };
+          for (key, value) in properties {
+            match key.as_str() {
+              "command" => {
+                command = match value {
+                  ConfigKeyValue::String(value) if !value.is_empty() => Some(value),
+                  _ => bail!("Expected non-empty string in 'command' for '{}' in 'execPlugins' property.", name),
+                }
+              }
+              "args" => exec_plugin.args = get_string_array(value, &key, &name)?,
+              "fileExtensions" => {
+                exec_plugin.file_extensions = get_string_array(value, &key, &name)?
+                  .into_iter()
+                  .map(|extension| extension.trim_start_matches('.').to_string())
+                  .collect()
+              }
+              "fileNames" => exec_plugin.file_names = get_string_array(value, &key, &name)?,
+              "associations" => exec_plugin.associations = Some(get_string_array(value, &key, &name)?),
+              "mode" => {
+                exec_plugin.mode = match value {
+                  ConfigKeyValue::String(value) if value == "stdin" => ExecPluginMode::Stdin,
+                  ConfigKeyValue::String(value) if value == "tempFile" => ExecPluginMode::TempFile,
+                  _ => bail!("Expected \"stdin\" or \"tempFile\" in 'mode' for '{}' in 'execPlugins' property.", name),
+                }
+              }
+              "timeout" => {
+                exec_plugin.timeout_secs = match value {
+                  ConfigKeyValue::Number(value) if value > 0 => value as u64,
+                  _ => bail!("Expected positive number of seconds in 'timeout' for '{}' in 'execPlugins' property.", name),
+                }
+              }
+              "versionCommand" => {
+                let version_command = get_string_array(value, &key, &name)?;
+                if version_command.is_empty() {
+                  bail!(
+                    "Expected the command and its arguments in 'versionCommand' for '{}' in 'execPlugins' property.",
+                    name
+                  );
+                }
+                exec_plugin.version_command = Some(version_command);
+              }
+              _ => bail!("Unknown property '{}' for '{}' in 'execPlugins' property.", key, name),
+            }
+          }
+          exec_plugin.command = match command {
+            Some(command) => command,
+            None => bail!("Expected a 'command' for '{}' in 'execPlugins' property.", name),
+          };
+          if exec_plugin.file_extensions.is_empty() && exec_plugin.file_names.is_empty() && exec_plugin.associations.is_none() {
+            bail!(
+              "Expected 'fileExtensions', 'fileNames', or 'associations' for '{}' in 'execPlugins' property so it formats some files.",
+              name
+            );
+          }
+          result.push(exec_plugin);
+        }
+      }
+      _ => bail!("Expected object in 'execPlugins' property."),
+    }
+  }
+  // the order of the properties isn't preserved when parsing the config file
+  result.sort_by(|a, b| a.name.cmp(&b.name));
+  Ok(result)
+}
+
+fn take_process_plugin_env_from_config_map(config_map: &mut ConfigMap) -> Result<IndexMap<String, IndexMap<String, String>>> {
+  let mut result = IndexMap::new();
+  if let Some(value) = config_map.remove("processPluginEnv") {
+    match value {
+      ConfigMapValue::PluginConfig(obj) => {
+        for (plugin_name, value) in obj.properties {
+          let properties = match value {
+            ConfigKeyValue::Object(properties) => properties,
+            _ => bail!("Expected object of environment variables for '{}' in 'processPluginEnv' property.", plugin_name),
+          };
+          let mut env_vars = IndexMap::new();
+          for (name, value) in properties {
+            if name.is_empty() || name.contains('=') {
+              bail!(
+                "Invalid environment variable name '{}' for '{}' in 'processPluginEnv' property.",
+                name,
+                plugin_name
+              );
+            }
+            match value {
+              ConfigKeyValue::String(value) => {
+                env_vars.insert(name, value);
+              }
+              _ => bail!(
+                "Expected string value for environment variable '{}' of '{}' in 'processPluginEnv' property.",
+                name,
+                plugin_name
+              ),
+            }
+          }
+          result.insert(plugin_name, env_vars);
+        }
+      }
+      _ => bail!("Expected object in 'processPluginEnv' property."),
+    }
+  }
+  Ok(result)
+}
+
 fn filter_non_wasm_plugins(plugins: Vec<PluginSourceReference>, environment: &impl Environment) -> Vec<PluginSourceReference> {
   if plugins.iter().any(|plugin| plugin.plugin_kind() != Some(PluginKind::Wasm)) {
     environment.log_stderr(&get_warn_non_wasm_plugins_message());
@@ -814,6 +1544,53 @@ mod tests {
     assert_eq!(result.config_map, expected_config_map);
   }
 
+  #[test]
+  fn should_handle_remote_config_fallback() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "extends": "https://dprint.dev/test.json",
+            "remoteConfigFallback": "error"
+        }"#,
+      )
+      .unwrap();
+    environment.add_remote_file(
+      "https://dprint.dev/test.json",
+      r#"{
+            "remoteConfigFallback": "warn",
+            "prop": 1
+        }"#
+        .as_bytes(),
+    );
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(result.config_map, ConfigMap::from([(String::from("prop"), ConfigMapValue::from_i32(1))]));
+
+    // the cached copy is outdated and can't be downloaded again
+    environment.set_time_secs(30 * 86_400);
+    environment.add_remote_file_error("https://dprint.dev/test.json", "Connection refused.");
+    assert_eq!(
+      get_result("/test.json", &environment).err().unwrap().to_string(),
+      "Connection refused.\n  The cached copy from 29 days ago was not used because 'remoteConfigFallback' is \"error\"."
+    );
+
+    environment
+      .write_file(&PathBuf::from("/test.json"), r#"{ "extends": "https://dprint.dev/test.json" }"#)
+      .unwrap();
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(result.config_map, ConfigMap::from([(String::from("prop"), ConfigMapValue::from_i32(1))]));
+    assert_eq!(environment.take_stderr_messages().len(), 1);
+
+    environment
+      .write_file(&PathBuf::from("/test.json"), r#"{ "remoteConfigFallback": "never" }"#)
+      .unwrap();
+    assert_eq!(
+      get_result("/test.json", &environment).err().unwrap().to_string(),
+      "Expected \"error\" or \"warn\" in 'remoteConfigFallback' property."
+    );
+  }
+
   #[test]
   fn should_handle_relative_local_extends() {
     let environment = TestEnvironment::new();
@@ -1269,6 +2046,917 @@ mod tests {
     assert_eq!(result.incremental, Some(false));
   }
 
+  #[test]
+  fn should_get_check_severity() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "checkSeverity": {
+              "vendor/**": "warn",
+              "vendor/ours/**": "error"
+            },
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages().len(), 0);
+    let mut expected = IndexMap::new();
+    expected.insert("vendor/**".to_string(), CheckSeverity::Warn);
+    expected.insert("vendor/ours/**".to_string(), CheckSeverity::Error);
+    assert_eq!(result.check_severity, expected);
+    assert_eq!(result.config_map.is_empty(), true);
+  }
+
+  #[test]
+  fn should_error_for_invalid_check_severity() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "checkSeverity": {
+              "vendor/**": "info"
+            },
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+
+    let err = get_result("/test.json", &environment).err().unwrap();
+    assert_eq!(err.to_string(), "Expected \"error\" or \"warn\" for 'vendor/**' in 'checkSeverity' property.");
+  }
+
+  #[test]
+  fn should_not_inherit_check_severity_from_extends() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "extends": "./other.json",
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+    environment
+      .write_file(
+        &PathBuf::from("/other.json"),
+        r#"{
+            "checkSeverity": {
+              "**/*": "warn"
+            }
+        }"#,
+      )
+      .unwrap();
+
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages().len(), 0);
+    assert_eq!(result.check_severity.is_empty(), true);
+    assert_eq!(result.config_map.is_empty(), true);
+  }
+
+  #[test]
+  fn should_get_overrides() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "overrides": {
+              "docs/translations/**": {
+                "disablePlugins": ["markdown"]
+              }
+            },
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages().len(), 0);
+    let mut expected = IndexMap::new();
+    expected.insert(
+      "docs/translations/**".to_string(),
+      ConfigOverride {
+        disable_plugins: vec!["markdown".to_string()],
+      },
+    );
+    assert_eq!(result.overrides, expected);
+    assert_eq!(result.config_map.is_empty(), true);
+  }
+
+  #[test]
+  fn should_error_for_invalid_overrides() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "overrides": {
+              "docs/**": {
+                "disablePlugins": "markdown"
+              }
+            },
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+
+    let err = get_result("/test.json", &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      "Expected array of strings in 'disablePlugins' for 'docs/**' in 'overrides' property."
+    );
+  }
+
+  #[test]
+  fn should_get_generated_files() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "generatedFiles": {
+              "skip": true,
+              "markers": ["autogenerated"]
+            },
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+    environment
+      .write_file(&PathBuf::from("/other.json"), r#"{ "plugins": ["./testing/asdf.wasm"] }"#)
+      .unwrap();
+
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(
+      result.generated_files,
+      GeneratedFilesConfig {
+        skip: true,
+        markers: vec!["autogenerated".to_string()],
+      }
+    );
+    assert_eq!(result.config_map.is_empty(), true);
+    let result = get_result("/other.json", &environment).unwrap();
+    assert_eq!(result.generated_files, GeneratedFilesConfig::default());
+    assert_eq!(result.generated_files.markers, vec!["@generated", "DO NOT EDIT"]);
+  }
+
+  #[test]
+  fn should_get_skip_build_artifacts() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{ "skipBuildArtifacts": false, "plugins": ["./testing/asdf.wasm"] }"#,
+      )
+      .unwrap();
+    environment
+      .write_file(
+        &PathBuf::from("/other.json"),
+        r#"{ "extends": "./test.json", "plugins": ["./testing/asdf.wasm"] }"#,
+      )
+      .unwrap();
+
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(result.skip_build_artifacts, false);
+    assert_eq!(result.config_map.is_empty(), true);
+    // defaults to skipping and isn't inherited
+    let result = get_result("/other.json", &environment).unwrap();
+    assert_eq!(result.skip_build_artifacts, true);
+  }
+
+  #[test]
+  fn should_error_for_invalid_generated_files() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "generatedFiles": {
+              "markers": [""]
+            },
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+
+    let err = get_result("/test.json", &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      "Expected array of non-empty strings in 'markers' of 'generatedFiles' property."
+    );
+  }
+
+  #[test]
+  fn should_get_hooks() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "extends": "./other.json",
+            "hooks": {
+              "postFormat": [["node", "scripts/checksums.js", "--update"], ["touch"]]
+            },
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+    environment
+      .write_file(
+        &PathBuf::from("/other.json"),
+        r#"{
+            "hooks": {
+              "postFormat": [["rm", "-rf", "/"]]
+            }
+        }"#,
+      )
+      .unwrap();
+
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(
+      result.hooks.post_format,
+      vec![
+        HookCommand {
+          command: "node".to_string(),
+          args: vec!["scripts/checksums.js".to_string(), "--update".to_string()],
+        },
+        HookCommand {
+          command: "touch".to_string(),
+          args: Vec::new(),
+        },
+      ]
+    );
+    assert_eq!(result.config_map.is_empty(), true);
+
+    // not inherited
+    let result = get_result("/other.json", &environment).unwrap();
+    assert_eq!(result.hooks.post_format.len(), 1);
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{ "extends": "./other.json", "plugins": ["./testing/asdf.wasm"] }"#,
+      )
+      .unwrap();
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(result.hooks, HooksConfig::default());
+  }
+
+  #[test]
+  fn should_get_exec_plugins() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "extends": "./other.json",
+            "execPlugins": {
+              "clang-format": {
+                "command": "clang-format",
+                "args": ["--assume-filename={{filePath}}"],
+                "fileExtensions": [".c", "h"]
+              },
+              "black": {
+                "command": "black",
+                "args": ["--quiet", "{{filePath}}"],
+                "associations": ["**/*.py"],
+                "mode": "tempFile",
+                "timeout": 5,
+                "versionCommand": ["black", "--version"]
+              }
+            },
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+    environment
+      .write_file(
+        &PathBuf::from("/other.json"),
+        r#"{
+            "execPlugins": {
+              "rm": { "command": "rm", "args": ["-rf", "/"], "fileNames": ["file"] }
+            }
+        }"#,
+      )
+      .unwrap();
+
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(
+      result.exec_plugins,
+      vec![
+        ExecPluginConfig {
+          name: "black".to_string(),
+          command: "black".to_string(),
+          args: vec!["--quiet".to_string(), "{{filePath}}".to_string()],
+          file_extensions: Vec::new(),
+          file_names: Vec::new(),
+          associations: Some(vec!["**/*.py".to_string()]),
+          mode: ExecPluginMode::TempFile,
+          timeout_secs: 5,
+          version_command: Some(vec!["black".to_string(), "--version".to_string()]),
+        },
+        ExecPluginConfig {
+          name: "clang-format".to_string(),
+          command: "clang-format".to_string(),
+          args: vec!["--assume-filename={{filePath}}".to_string()],
+          file_extensions: vec!["c".to_string(), "h".to_string()],
+          file_names: Vec::new(),
+          associations: None,
+          mode: ExecPluginMode::Stdin,
+          timeout_secs: ExecPluginConfig::DEFAULT_TIMEOUT_SECS,
+          version_command: None,
+        },
+      ]
+    );
+    assert_eq!(result.config_map.is_empty(), true);
+
+    // not inherited
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{ "extends": "./other.json", "plugins": ["./testing/asdf.wasm"] }"#,
+      )
+      .unwrap();
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(result.exec_plugins, Vec::new());
+  }
+
+  #[test]
+  fn should_error_for_invalid_exec_plugins() {
+    let environment = TestEnvironment::new();
+    let assert_error = |exec_plugins_text: &str, expected_message: &str| {
+      environment
+        .write_file(
+          &PathBuf::from("/test.json"),
+          &format!(r#"{{ "execPlugins": {}, "plugins": ["./testing/asdf.wasm"] }}"#, exec_plugins_text),
+        )
+        .unwrap();
+      let err = get_result("/test.json", &environment).err().unwrap();
+      assert_eq!(err.to_string(), expected_message);
+    };
+
+    assert_error(
+      r#"{ "gofmt": { "fileExtensions": ["go"] } }"#,
+      "Expected a 'command' for 'gofmt' in 'execPlugins' property.",
+    );
+    assert_error(
+      r#"{ "gofmt": { "command": "gofmt" } }"#,
+      "Expected 'fileExtensions', 'fileNames', or 'associations' for 'gofmt' in 'execPlugins' property so it formats some files.",
+    );
+    assert_error(
+      r#"{ "gofmt": { "command": "gofmt", "fileExtensions": ["go"], "mode": "file" } }"#,
+      "Expected \"stdin\" or \"tempFile\" in 'mode' for 'gofmt' in 'execPlugins' property.",
+    );
+    assert_error(
+      r#"{ "gofmt": { "command": "gofmt", "fileExtensions": ["go"], "versionCommand": [] } }"#,
+      "Expected the command and its arguments in 'versionCommand' for 'gofmt' in 'execPlugins' property.",
+    );
+    assert_error(
+      r#"{ "gofmt": { "command": "gofmt", "fileExtensions": ["go"], "cwd": "src" } }"#,
+      "Unknown property 'cwd' for 'gofmt' in 'execPlugins' property.",
+    );
+  }
+
+  #[test]
+  fn should_get_process_plugin_env() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "extends": "./other.json",
+            "processPluginEnv": {
+              "java": { "JAVA_HOME": "${env:JAVA_HOME}", "JAVA_OPTS": "-Xmx1g" }
+            },
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+    environment
+      .write_file(
+        &PathBuf::from("/other.json"),
+        r#"{
+            "processPluginEnv": {
+              "other": { "TOKEN": "${env:GITHUB_TOKEN}" }
+            }
+        }"#,
+      )
+      .unwrap();
+
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(
+      result.process_plugin_env,
+      IndexMap::from([(
+        "java".to_string(),
+        IndexMap::from([
+          ("JAVA_HOME".to_string(), "${env:JAVA_HOME}".to_string()),
+          ("JAVA_OPTS".to_string(), "-Xmx1g".to_string()),
+        ])
+      )])
+    );
+    assert_eq!(result.config_map.is_empty(), true);
+  }
+
+  #[test]
+  fn should_error_for_invalid_process_plugin_env() {
+    let environment = TestEnvironment::new();
+    let assert_error = |process_plugin_env_text: &str, expected_message: &str| {
+      environment
+        .write_file(
+          &PathBuf::from("/test.json"),
+          &format!(r#"{{ "processPluginEnv": {}, "plugins": ["./testing/asdf.wasm"] }}"#, process_plugin_env_text),
+        )
+        .unwrap();
+      let err = get_result("/test.json", &environment).err().unwrap();
+      assert_eq!(err.to_string(), expected_message);
+    };
+
+    assert_error(
+      r#"{ "java": "JAVA_HOME" }"#,
+      "Expected object of environment variables for 'java' in 'processPluginEnv' property.",
+    );
+    assert_error(
+      r#"{ "java": { "JAVA_HOME": 1 } }"#,
+      "Expected string value for environment variable 'JAVA_HOME' of 'java' in 'processPluginEnv' property.",
+    );
+    assert_error(
+      r#"{ "java": { "A=B": "" } }"#,
+      "Invalid environment variable name 'A=B' for 'java' in 'processPluginEnv' property.",
+    );
+  }
+
+  #[test]
+  fn should_get_transforms() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "transforms": {
+              "src/**": {
+                "licenseHeader": "// header",
+                "removeHeaders": ["// old header"],
+                "normalizeBom": true
+              },
+              "**/*.txt": {
+                "unicodeNormalization": "nfc",
+                "tabsToSpaces": 4,
+                "trimTrailingWhitespace": true
+              }
+            },
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+
+    let result = get_result("/test.json", &environment).unwrap();
+    let mut expected = IndexMap::new();
+    expected.insert(
+      "src/**".to_string(),
+      TransformConfig {
+        license_header: Some("// header".to_string()),
+        remove_headers: vec!["// old header".to_string()],
+        normalize_bom: true,
+        ..Default::default()
+      },
+    );
+    expected.insert(
+      "**/*.txt".to_string(),
+      TransformConfig {
+        unicode_normalization: Some(UnicodeNormalizationForm::Nfc),
+        tabs_to_spaces: Some(4),
+        trim_trailing_whitespace: true,
+        ..Default::default()
+      },
+    );
+    assert_eq!(result.transforms, expected);
+    assert_eq!(result.config_map.is_empty(), true);
+
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "transforms": { "src/**": { "stripComments": true } },
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+    let err = get_result("/test.json", &environment).err().unwrap();
+    assert_eq!(err.to_string(), "Unknown property 'stripComments' for 'src/**' in 'transforms' property.");
+
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "transforms": { "src/**": { "unicodeNormalization": "nfkc" } },
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+    let err = get_result("/test.json", &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      "Expected \"nfc\" or \"nfd\" in 'unicodeNormalization' for 'src/**' in 'transforms' property."
+    );
+  }
+
+  #[test]
+  fn should_get_file_selector() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "fileSelector": "./selector.wasm@123",
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(
+      result.file_selector,
+      Some(PluginSourceReference {
+        path_source: PathSource::new_local(CanonicalizedPathBuf::new_for_testing("/selector.wasm")),
+        checksum: Some("123".to_string()),
+      })
+    );
+    assert_eq!(result.config_map.is_empty(), true);
+
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "fileSelector": "https://example.com/selector.wasm",
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+    let err = get_result("/test.json", &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      "The 'fileSelector' property must be pinned to the checksum of the module (ex. 'https://example.com/selector.wasm@<checksum>')."
+    );
+  }
+
+  #[test]
+  fn should_error_for_invalid_hooks() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "hooks": {
+              "postFormat": ["node scripts/checksums.js"]
+            },
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+
+    let err = get_result("/test.json", &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      concat!(
+        "Expected an array of commands in 'postFormat' of 'hooks' property, where each command is ",
+        "an array of the command and its arguments (ex. [\"node\", \"script.js\"])."
+      )
+    );
+  }
+
+  #[test]
+  fn should_not_inherit_overrides_from_extends() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "extends": "./other.json",
+            "plugins": ["./testing/asdf.wasm"],
+        }"#,
+      )
+      .unwrap();
+    environment
+      .write_file(
+        &PathBuf::from("/other.json"),
+        r#"{
+            "overrides": {
+              "**/*": { "disablePlugins": ["markdown"] }
+            }
+        }"#,
+      )
+      .unwrap();
+
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages().len(), 0);
+    assert_eq!(result.overrides.is_empty(), true);
+    assert_eq!(result.config_map.is_empty(), true);
+  }
+
+  #[test]
+  fn should_resolve_plugin_range_from_lockfile() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "plugins": ["typescript@^0.91"]
+        }"#,
+      )
+      .unwrap();
+    environment
+      .write_file(
+        &PathBuf::from("/dprint.lock"),
+        r#"{
+            "version": 1,
+            "plugins": {
+              "typescript@^0.91": {
+                "version": "0.91.2",
+                "url": "https://plugins.dprint.dev/typescript-0.91.2.wasm"
+              }
+            }
+        }"#,
+      )
+      .unwrap();
+
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(
+      result.plugins,
+      vec![PluginSourceReference::new_remote_from_str("https://plugins.dprint.dev/typescript-0.91.2.wasm")]
+    );
+  }
+
+  #[test]
+  fn should_use_checksum_pinned_in_lockfile() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "plugins": ["https://plugins.dprint.dev/a.wasm", "https://plugins.dprint.dev/b.wasm", "https://plugins.dprint.dev/c.wasm@other"]
+        }"#,
+      )
+      .unwrap();
+    environment
+      .write_file(
+        &PathBuf::from("/dprint.lock"),
+        r#"{
+            "version": 1,
+            "plugins": {
+              "https://plugins.dprint.dev/a.wasm": {
+                "version": "0.1.0",
+                "url": "https://plugins.dprint.dev/a.wasm",
+                "checksum": "checksum-a"
+              },
+              "https://plugins.dprint.dev/b.wasm": {
+                "version": "0.1.0",
+                "url": "https://plugins.dprint.dev/old-b.wasm",
+                "checksum": "checksum-b"
+              }
+            }
+        }"#,
+      )
+      .unwrap();
+
+    let result = get_result("/test.json", &environment).unwrap();
+    assert_eq!(
+      result.plugins,
+      vec![
+        PluginSourceReference {
+          path_source: PathSource::new_remote_from_str("https://plugins.dprint.dev/a.wasm"),
+          checksum: Some("checksum-a".to_string()),
+        },
+        // the stale entry is ignored when not frozen
+        PluginSourceReference::new_remote_from_str("https://plugins.dprint.dev/b.wasm"),
+        PluginSourceReference {
+          path_source: PathSource::new_remote_from_str("https://plugins.dprint.dev/c.wasm"),
+          checksum: Some("other".to_string()),
+        },
+      ]
+    );
+
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "plugins": ["https://plugins.dprint.dev/a.wasm@other"]
+        }"#,
+      )
+      .unwrap();
+    environment
+      .write_file(
+        &PathBuf::from("/dprint.lock"),
+        r#"{
+            "version": 1,
+            "plugins": {
+              "https://plugins.dprint.dev/a.wasm@other": {
+                "version": "0.1.0",
+                "url": "https://plugins.dprint.dev/a.wasm",
+                "checksum": "checksum-a"
+              }
+            }
+        }"#,
+      )
+      .unwrap();
+    let err = get_result("/test.json", &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      concat!(
+        "The checksum of the plugin 'https://plugins.dprint.dev/a.wasm@other' does not match the checksum pinned in /dprint.lock. ",
+        "Run `dprint config update --lockfile` to update the lockfile."
+      )
+    );
+  }
+
+  #[test]
+  fn should_error_for_plugins_not_pinned_when_frozen() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "plugins": ["https://plugins.dprint.dev/a.wasm", "./local.wasm"]
+        }"#,
+      )
+      .unwrap();
+    let get_frozen_result = || {
+      let args = parse_args(
+        vec![
+          String::from(""),
+          String::from("check"),
+          String::from("--frozen"),
+          String::from("-c"),
+          String::from("/test.json"),
+        ],
+        TestStdInReader::default(),
+      )
+      .unwrap();
+      resolve_config_from_args(&args, &environment)
+    };
+
+    assert_eq!(
+      get_frozen_result().err().unwrap().to_string(),
+      "The plugin 'https://plugins.dprint.dev/a.wasm' is not pinned in /dprint.lock. Run `dprint config update --lockfile` to pin it."
+    );
+
+    environment
+      .write_file(
+        &PathBuf::from("/dprint.lock"),
+        r#"{
+            "version": 1,
+            "plugins": {
+              "https://plugins.dprint.dev/a.wasm": {
+                "version": "0.1.0",
+                "url": "https://plugins.dprint.dev/a.wasm",
+                "checksum": "checksum-a"
+              },
+              "https://plugins.dprint.dev/removed.wasm": {
+                "version": "0.1.0",
+                "url": "https://plugins.dprint.dev/removed.wasm",
+                "checksum": "checksum-removed"
+              }
+            }
+        }"#,
+      )
+      .unwrap();
+    assert_eq!(
+      get_frozen_result().err().unwrap().to_string(),
+      concat!(
+        "The plugin 'https://plugins.dprint.dev/removed.wasm' pinned in /dprint.lock is no longer in the configuration file. ",
+        "Run `dprint config update --lockfile` to update the lockfile."
+      )
+    );
+    // not an error when not frozen
+    assert!(get_result("/test.json", &environment).is_ok());
+
+    environment
+      .write_file(
+        &PathBuf::from("/dprint.lock"),
+        r#"{
+            "version": 1,
+            "plugins": {
+              "https://plugins.dprint.dev/a.wasm": {
+                "version": "0.1.0",
+                "url": "https://plugins.dprint.dev/a.wasm",
+                "checksum": "checksum-a"
+              }
+            }
+        }"#,
+      )
+      .unwrap();
+    let result = get_frozen_result().unwrap();
+    assert_eq!(
+      result.plugins,
+      vec![
+        PluginSourceReference {
+          path_source: PathSource::new_remote_from_str("https://plugins.dprint.dev/a.wasm"),
+          checksum: Some("checksum-a".to_string()),
+        },
+        PluginSourceReference::new_local("/local.wasm"),
+      ]
+    );
+  }
+
+  #[test]
+  fn should_error_when_frozen_with_remote_config() {
+    let environment = TestEnvironment::new();
+    environment.add_remote_file(
+      "https://dprint.dev/test.json",
+      r#"{
+            "plugins": ["https://plugins.dprint.dev/a.wasm"]
+        }"#
+        .as_bytes(),
+    );
+    let args = parse_args(
+      vec![
+        String::from(""),
+        String::from("check"),
+        String::from("--frozen"),
+        String::from("-c"),
+        String::from("https://dprint.dev/test.json"),
+      ],
+      TestStdInReader::default(),
+    )
+    .unwrap();
+    assert_eq!(
+      resolve_config_from_args(&args, &environment).err().unwrap().to_string(),
+      "--frozen requires a local configuration file."
+    );
+    // not an error when not frozen
+    assert!(get_result("https://dprint.dev/test.json", &environment).is_ok());
+  }
+
+  #[test]
+  fn should_error_for_plugin_range_not_in_lockfile() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "plugins": ["typescript@^0.92"]
+        }"#,
+      )
+      .unwrap();
+
+    let err = get_result("/test.json", &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      "The plugin 'typescript@^0.92' is not pinned in /dprint.lock. Run `dprint config update` to resolve it."
+    );
+
+    environment
+      .write_file(
+        &PathBuf::from("/dprint.lock"),
+        r#"{
+            "version": 1,
+            "plugins": {
+              "typescript@^0.92": {
+                "version": "0.91.2",
+                "url": "https://plugins.dprint.dev/typescript-0.91.2.wasm"
+              }
+            }
+        }"#,
+      )
+      .unwrap();
+    let err = get_result("/test.json", &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      "The version 0.91.2 pinned in /dprint.lock does not satisfy 'typescript@^0.92'. Run `dprint config update` to resolve it."
+    );
+  }
+
+  #[test]
+  fn should_error_for_plugin_range_in_extends() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        &PathBuf::from("/test.json"),
+        r#"{
+            "extends": "./other.json",
+            "plugins": ["./testing/asdf.wasm"]
+        }"#,
+      )
+      .unwrap();
+    environment
+      .write_file(
+        &PathBuf::from("/other.json"),
+        r#"{
+            "plugins": ["typescript@^0.91"]
+        }"#,
+      )
+      .unwrap();
+
+    let err = get_result("/test.json", &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      "Error with '/other.json'. The plugin 'typescript@^0.91' uses a version range, which is only supported in a local main configuration file."
+    );
+  }
+
   #[test]
   fn should_ignore_non_wasm_plugins_in_remote_config() {
     let environment = TestEnvironment::new();
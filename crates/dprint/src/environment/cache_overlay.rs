@@ -0,0 +1,244 @@
+use anyhow::Context;
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::DirEntry;
+use super::DirEntryKind;
+use super::WriteGuard;
+
+/// What to do with the overlay directory once the command finishes (`--cache-overlay`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheOverlayMode {
+  /// Deletes the overlay, leaving the shared cache untouched.
+  Discard,
+  /// Applies the changes in the overlay to the shared cache.
+  Merge,
+}
+
+/// Copy-on-write view of the shared cache directory when running with `--cache-overlay`.
+///
+/// Everything is written to the overlay directory while reads of paths that are
+/// not in the overlay fall back to the shared cache directory. Removals are
+/// tracked so the shared files below a removed path are no longer visible.
+/// Writes go through the environment's write guard like any other write.
+#[derive(Clone)]
+pub struct CacheOverlay {
+  mode: CacheOverlayMode,
+  shared_dir: PathBuf,
+  overlay_dir: PathBuf,
+  removed_paths: Arc<Mutex<Vec<PathBuf>>>,
+  write_guard: WriteGuard,
+}
+
+impl CacheOverlay {
+  pub fn new(mode: CacheOverlayMode, shared_dir: PathBuf, overlay_dir: PathBuf, write_guard: WriteGuard) -> Self {
+    CacheOverlay {
+      mode,
+      shared_dir,
+      overlay_dir,
+      removed_paths: Default::default(),
+      write_guard,
+    }
+  }
+
+  pub fn overlay_dir(&self) -> &Path {
+    &self.overlay_dir
+  }
+
+  /// Gets the path in the shared cache directory for a path in the overlay
+  /// when it's still visible (it wasn't removed during this run).
+  pub fn get_shared_path(&self, path: &Path) -> Option<PathBuf> {
+    let relative_path = path.strip_prefix(&self.overlay_dir).ok()?;
+    if self.removed_paths.lock().iter().any(|removed_path| path.starts_with(removed_path)) {
+      None
+    } else {
+      Some(self.shared_dir.join(relative_path))
+    }
+  }
+
+  /// Gets the path to read from, which is the shared path when
+  /// the path only exists in the shared cache directory.
+  pub fn resolve_read_path(&self, path: &Path) -> PathBuf {
+    if !path.exists() {
+      if let Some(shared_path) = self.get_shared_path(path) {
+        if shared_path.exists() {
+          return shared_path;
+        }
+      }
+    }
+    path.to_path_buf()
+  }
+
+  /// Copies a file only in the shared cache directory to the overlay so it may be modified.
+  pub fn copy_up(&self, path: &Path) -> Result<()> {
+    let read_path = self.resolve_read_path(path);
+    if read_path != path && read_path.is_file() {
+      self.write_guard.ensure_can_write(path)?;
+      if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+      }
+      fs::copy(&read_path, path).with_context(|| format!("Error copying {} to the cache overlay.", read_path.display()))?;
+    }
+    Ok(())
+  }
+
+  pub fn mark_removed(&self, path: &Path) {
+    if path.starts_with(&self.overlay_dir) {
+      self.removed_paths.lock().push(path.to_path_buf());
+    }
+  }
+
+  /// Adds the entries of the shared cache directory that aren't in the overlay.
+  pub fn add_shared_dir_entries(&self, dir_path: &Path, entries: &mut Vec<DirEntry>) -> Result<()> {
+    let shared_dir_path = match self.get_shared_path(dir_path) {
+      Some(path) if path.is_dir() => path,
+      _ => return Ok(()),
+    };
+    for entry in fs::read_dir(&shared_dir_path)? {
+      let entry = entry?;
+      let file_type = entry.file_type()?;
+      let kind = if file_type.is_dir() {
+        DirEntryKind::Directory
+      } else if file_type.is_file() {
+        DirEntryKind::File
+      } else {
+        continue;
+      };
+      let path = dir_path.join(entry.file_name());
+      if self.get_shared_path(&path).is_some() && !entries.iter().any(|e| e.path == path) {
+        entries.push(DirEntry { kind, path });
+      }
+    }
+    Ok(())
+  }
+
+  /// Merges or discards the overlay directory based on the mode.
+  pub fn finish(&self) -> Result<()> {
+    if self.mode == CacheOverlayMode::Merge {
+      self.merge()?;
+    }
+    match fs::remove_dir_all(&self.overlay_dir) {
+      Ok(_) => Ok(()),
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+      Err(err) => Err(err).with_context(|| format!("Error removing cache overlay directory {}", self.overlay_dir.display())),
+    }
+  }
+
+  fn merge(&self) -> Result<()> {
+    for removed_path in self.removed_paths.lock().iter() {
+      let shared_path = self.shared_dir.join(removed_path.strip_prefix(&self.overlay_dir).unwrap());
+      self.write_guard.ensure_can_write(&shared_path)?;
+      let result = if shared_path.is_dir() {
+        fs::remove_dir_all(&shared_path)
+      } else {
+        fs::remove_file(&shared_path)
+      };
+      match result {
+        Ok(_) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err).with_context(|| format!("Error removing {} from the cache.", shared_path.display())),
+      }
+    }
+    if self.overlay_dir.is_dir() {
+      copy_dir_files(&self.overlay_dir, &self.shared_dir, &self.write_guard)?;
+    }
+    Ok(())
+  }
+}
+
+fn copy_dir_files(from_dir: &Path, to_dir: &Path, write_guard: &WriteGuard) -> Result<()> {
+  write_guard.ensure_can_write(to_dir)?;
+  fs::create_dir_all(to_dir)?;
+  for entry in fs::read_dir(from_dir)? {
+    let entry = entry?;
+    let file_type = entry.file_type()?;
+    let to_path = to_dir.join(entry.file_name());
+    if file_type.is_dir() {
+      copy_dir_files(&entry.path(), &to_path, write_guard)?;
+    } else if file_type.is_file() {
+      write_guard.ensure_can_write(&to_path)?;
+      // copy to a temporary file first so other processes never read a partially copied file
+      let temp_path = to_dir.join(format!("{}.{}.tmp", entry.file_name().to_string_lossy(), std::process::id()));
+      fs::copy(entry.path(), &temp_path).with_context(|| format!("Error copying {} to the cache.", entry.path().display()))?;
+      fs::rename(&temp_path, &to_path).with_context(|| format!("Error copying {} to the cache.", entry.path().display()))?;
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn should_read_through_and_merge_or_discard() {
+    let root_dir = std::env::temp_dir().join(format!("dprint-cache-overlay-test-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root_dir);
+    let shared_dir = root_dir.join("shared");
+    let overlay_dir = root_dir.join("overlay");
+    fs::create_dir_all(shared_dir.join("plugins")).unwrap();
+    fs::write(shared_dir.join("plugins").join("a.wasm"), "a").unwrap();
+    fs::write(shared_dir.join("plugins").join("b.wasm"), "b").unwrap();
+    fs::write(shared_dir.join("manifest.json"), "{}").unwrap();
+
+    for mode in [CacheOverlayMode::Discard, CacheOverlayMode::Merge] {
+      fs::create_dir_all(overlay_dir.join("plugins")).unwrap();
+      let overlay = CacheOverlay::new(mode, shared_dir.clone(), overlay_dir.clone(), WriteGuard::default());
+      assert_eq!(overlay.resolve_read_path(&overlay_dir.join("manifest.json")), shared_dir.join("manifest.json"));
+      assert_eq!(overlay.resolve_read_path(&overlay_dir.join("other.json")), overlay_dir.join("other.json"));
+
+      fs::write(overlay_dir.join("plugins").join("c.wasm"), "c").unwrap();
+      overlay.mark_removed(&overlay_dir.join("plugins").join("a.wasm"));
+      let mut entries = Vec::new();
+      overlay.add_shared_dir_entries(&overlay_dir.join("plugins"), &mut entries).unwrap();
+      assert_eq!(entries.len(), 1);
+      assert_eq!(entries[0].path, overlay_dir.join("plugins").join("b.wasm"));
+      assert_eq!(
+        overlay.resolve_read_path(&overlay_dir.join("plugins").join("a.wasm")),
+        overlay_dir.join("plugins").join("a.wasm")
+      );
+
+      overlay.finish().unwrap();
+      assert!(!overlay_dir.exists());
+      let is_merge = mode == CacheOverlayMode::Merge;
+      assert_eq!(shared_dir.join("plugins").join("a.wasm").exists(), !is_merge);
+      assert_eq!(shared_dir.join("plugins").join("c.wasm").exists(), is_merge);
+      assert!(shared_dir.join("plugins").join("b.wasm").exists());
+    }
+
+    fs::remove_dir_all(&root_dir).unwrap();
+  }
+
+  #[test]
+  fn should_not_write_when_write_guard_enabled() {
+    let root_dir = std::env::temp_dir().join(format!("dprint-cache-overlay-guard-test-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root_dir);
+    let shared_dir = root_dir.join("shared");
+    let overlay_dir = root_dir.join("overlay");
+    fs::create_dir_all(&shared_dir).unwrap();
+    fs::write(shared_dir.join("manifest.json"), "{}").unwrap();
+    fs::create_dir_all(&overlay_dir).unwrap();
+    fs::write(overlay_dir.join("a.wasm"), "a").unwrap();
+
+    let write_guard = WriteGuard::new(true);
+    let overlay = CacheOverlay::new(CacheOverlayMode::Merge, shared_dir.clone(), overlay_dir.clone(), write_guard.clone());
+    let err = overlay.copy_up(&overlay_dir.join("manifest.json")).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      format!(
+        "Cannot write to {} when running with --assert-no-writes.",
+        overlay_dir.join("manifest.json").display()
+      )
+    );
+    assert!(!overlay_dir.join("manifest.json").exists());
+    assert!(overlay.finish().is_err());
+    assert!(!shared_dir.join("a.wasm").exists());
+    assert_eq!(write_guard.attempted_paths().len(), 2);
+
+    fs::remove_dir_all(&root_dir).unwrap();
+  }
+}
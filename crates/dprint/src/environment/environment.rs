@@ -4,6 +4,8 @@ use std::io::Read;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
 
 use crate::plugins::CompilationResult;
 
@@ -58,14 +60,22 @@ pub trait Environment: Clone + Send + Sync + UrlDownloader + 'static {
   fn read_file_bytes(&self, file_path: impl AsRef<Path>) -> Result<Vec<u8>>;
   fn write_file(&self, file_path: impl AsRef<Path>, file_text: &str) -> Result<()>;
   fn write_file_bytes(&self, file_path: impl AsRef<Path>, bytes: &[u8]) -> Result<()>;
+  /// Appends the text to the end of the file, creating it if it doesn't exist.
+  fn append_file(&self, file_path: impl AsRef<Path>, file_text: &str) -> Result<()>;
   fn rename(&self, path_from: impl AsRef<Path>, path_to: impl AsRef<Path>) -> Result<()>;
   fn remove_file(&self, file_path: impl AsRef<Path>) -> Result<()>;
+  /// Flushes the file or directory to the storage device (`--fsync`).
+  fn fsync(&self, path: impl AsRef<Path>) -> Result<()>;
   fn remove_dir_all(&self, dir_path: impl AsRef<Path>) -> Result<()>;
   fn dir_info(&self, dir_path: impl AsRef<Path>) -> Result<Vec<DirEntry>>;
   fn path_exists(&self, file_path: impl AsRef<Path>) -> bool;
+  /// Gets the path to use for reading the file outside of the environment, which is
+  /// in the shared cache directory for files not yet in the `--cache-overlay`.
+  fn resolve_read_path(&self, path: &Path) -> PathBuf;
   fn canonicalize(&self, path: impl AsRef<Path>) -> Result<CanonicalizedPathBuf>;
   fn is_absolute_path(&self, path: impl AsRef<Path>) -> bool;
   fn file_permissions(&self, path: impl AsRef<Path>) -> Result<FilePermissions>;
+  fn file_modified_time(&self, path: impl AsRef<Path>) -> Result<SystemTime>;
   fn set_file_permissions(&self, path: impl AsRef<Path>, permissions: FilePermissions) -> Result<()>;
   fn mk_dir_all(&self, path: impl AsRef<Path>) -> Result<()>;
   fn cwd(&self) -> CanonicalizedPathBuf;
@@ -80,6 +90,9 @@ pub trait Environment: Clone + Send + Sync + UrlDownloader + 'static {
   fn log_stderr_with_context(&self, text: &str, context_name: &str);
   /// Information to force output when the environment is in "machine readable mode".
   fn log_machine_readable(&self, text: &str);
+  /// Gets an environment that adds the prefix to each line it logs, which
+  /// isn't added to the machine readable output.
+  fn with_log_prefix(&self, prefix: &str) -> Self;
   fn log_action_with_progress<TResult: Send + Sync, TCreate: FnOnce(Box<dyn Fn(usize)>) -> TResult + Send + Sync>(
     &self,
     message: &str,
@@ -98,8 +111,30 @@ pub trait Environment: Clone + Send + Sync + UrlDownloader + 'static {
   fn get_selection(&self, prompt_message: &str, item_indent_width: u16, items: &[String]) -> Result<usize>;
   fn get_multi_selection(&self, prompt_message: &str, item_indent_width: u16, items: &[(bool, String)]) -> Result<Vec<usize>>;
   fn confirm(&self, prompt_message: &str, default_value: bool) -> Result<bool>;
+  /// Gets the paths that couldn't be written to because of `--assert-no-writes`.
+  fn attempted_write_paths(&self) -> Vec<PathBuf>;
+  /// Gets if writing to the file system is disallowed (`--assert-no-writes`).
+  fn is_read_only(&self) -> bool;
   fn is_verbose(&self) -> bool;
+  /// Gets if the user may be prompted in the terminal.
+  fn is_interactive(&self) -> bool;
   fn compile_wasm(&self, wasm_bytes: &[u8]) -> Result<CompilationResult>;
+  /// Runs the command to completion and returns its stdout.
+  fn run_command(&self, command: &str, args: &[&str]) -> Result<String>;
+  /// Runs the command in the directory with the provided text as its
+  /// stdin to completion and returns its stdout.
+  fn run_command_with_stdin(&self, command: &str, args: &[&str], cwd: &Path, stdin_text: &str) -> Result<String>;
+  /// Runs the command like `run_command_with_stdin`, but kills it and
+  /// errors when it doesn't exit within the timeout.
+  fn run_command_with_timeout(&self, command: &str, args: &[&str], cwd: &Path, stdin_text: &str, timeout: Duration) -> Result<String>;
+  /// Gets the value of an environment variable.
+  fn env_var(&self, name: &str) -> Option<String>;
+  fn is_process_active(&self, process_id: u32) -> bool;
+  /// Kills the process when it's still running the executable, returning if it was killed.
+  ///
+  /// This is only supported on Linux. On Windows the job object of a process
+  /// plugin already ends it along with the CLI.
+  fn kill_orphaned_process(&self, process_id: u32, executable_file_path: &Path) -> bool;
   fn stdout(&self) -> Box<dyn Write + Send>;
   fn stdin(&self) -> Box<dyn Read + Send>;
   fn runtime_handle(&self) -> tokio::runtime::Handle;
@@ -109,6 +144,11 @@ pub trait Environment: Clone + Send + Sync + UrlDownloader + 'static {
   fn remove_system_path(&self, directory_path: &str) -> Result<()>;
 }
 
+/// Adds the prefix to the start of each line of the text.
+pub fn get_text_with_line_prefix(prefix: &str, text: &str) -> String {
+  text.split('\n').map(|line| format!("{}{}", prefix, line)).collect::<Vec<_>>().join("\n")
+}
+
 // use a macro here so the expression provided is only evaluated when in verbose mode
 macro_rules! log_verbose {
     ($logger:expr, $($arg:tt)*) => {
@@ -1,18 +1,19 @@
+mod cache_overlay;
 mod canonicalized_path_buf;
 #[allow(clippy::module_inception)]
 #[macro_use]
 mod environment;
 mod real_environment;
-#[cfg(test)]
 mod test_environment;
 #[cfg(test)]
 mod test_environment_builder;
+mod write_guard;
 
+pub use cache_overlay::*;
 pub use canonicalized_path_buf::*;
 pub use environment::*;
 pub use real_environment::*;
-
-#[cfg(test)]
 pub use test_environment::*;
 #[cfg(test)]
 pub use test_environment_builder::*;
+pub use write_guard::*;
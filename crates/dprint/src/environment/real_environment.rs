@@ -7,14 +7,20 @@ use std::num::NonZeroUsize;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 use std::time::SystemTime;
 
+use super::get_text_with_line_prefix;
+use super::CacheOverlay;
+use super::CacheOverlayMode;
 use super::CanonicalizedPathBuf;
 use super::DirEntry;
 use super::DirEntryKind;
 use super::Environment;
 use super::FilePermissions;
 use super::UrlDownloader;
+use super::WriteGuard;
 use crate::plugins::CompilationResult;
 use crate::utils::log_action_with_progress;
 use crate::utils::show_confirm;
@@ -28,6 +34,10 @@ use crate::utils::RealUrlDownloader;
 pub struct RealEnvironmentOptions {
   pub is_verbose: bool,
   pub is_stdout_machine_readable: bool,
+  /// Errors for any write to the file system (`--assert-no-writes`).
+  pub assert_no_writes: bool,
+  /// Writes to a temporary overlay of the cache directory (`--cache-overlay`).
+  pub cache_overlay: Option<CacheOverlayMode>,
   pub runtime_handle: Arc<tokio::runtime::Handle>,
 }
 
@@ -37,6 +47,9 @@ pub struct RealEnvironment {
   runtime_handle: Arc<tokio::runtime::Handle>,
   url_downloader: RealUrlDownloader,
   logger: Logger,
+  write_guard: WriteGuard,
+  log_prefix: Option<String>,
+  cache_overlay: Option<CacheOverlay>,
 }
 
 impl RealEnvironment {
@@ -48,25 +61,146 @@ impl RealEnvironment {
     });
     let progress_bars = ProgressBars::new(&logger);
     let url_downloader = RealUrlDownloader::new(progress_bars.clone(), logger.clone(), |env_var_name| std::env::var(env_var_name).ok())?;
+    // ensure the cache directory is created
+    let cache_dir = match (*CACHE_DIR).as_ref() {
+      Ok(cache_dir) => cache_dir,
+      Err(err) => {
+        bail!("Error creating cache directory: {:#}", err);
+      }
+    };
+    let write_guard = WriteGuard::new(options.assert_no_writes);
+    let cache_overlay = match options.cache_overlay {
+      Some(mode) => Some(create_cache_overlay(mode, cache_dir, write_guard.clone()).context("Error creating cache overlay directory.")?),
+      None => None,
+    };
+
     let environment = RealEnvironment {
       url_downloader,
       logger,
       progress_bars,
       runtime_handle: options.runtime_handle,
+      write_guard,
+      log_prefix: None,
+      cache_overlay,
     };
 
-    // ensure the cache directory is created
-    match (*CACHE_DIR).as_ref() {
-      Ok(cache_dir) => cache_dir,
-      Err(err) => {
-        bail!("Error creating cache directory: {:#}", err);
+    Ok(environment)
+  }
+
+  /// Merges or discards the cache overlay directory when running with `--cache-overlay`.
+  ///
+  /// This should be called once everything using the cache has shut down.
+  pub fn finish_cache_overlay(&self) -> Result<()> {
+    match &self.cache_overlay {
+      Some(cache_overlay) => cache_overlay.finish(),
+      None => Ok(()),
+    }
+  }
+
+  fn resolve_read_path_buf(&self, path: &Path) -> PathBuf {
+    match &self.cache_overlay {
+      Some(cache_overlay) => cache_overlay.resolve_read_path(path),
+      None => path.to_path_buf(),
+    }
+  }
+
+  fn mark_removed(&self, path: &Path) {
+    if let Some(cache_overlay) = &self.cache_overlay {
+      cache_overlay.mark_removed(path);
+    }
+  }
+
+  fn run_command_with_stdin_and_timeout(&self, command: &str, args: &[&str], cwd: &Path, stdin_text: &str, timeout: Option<Duration>) -> Result<String> {
+    use std::io::Read;
+    use std::io::Write;
+    use std::process::Stdio;
+
+    log_verbose!(self, "Running command in {}: {} {}", cwd.display(), command, args.join(" "));
+    let mut child = std::process::Command::new(command)
+      .args(args)
+      .current_dir(cwd)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .with_context(|| format!("Error running `{}`.", command))?;
+    // write on another thread so the command doesn't block writing
+    // to its stdout while this blocks writing to its stdin
+    let mut stdin = child.stdin.take().unwrap();
+    let stdin_text = stdin_text.to_string();
+    let stdin_thread = std::thread::spawn(move || stdin.write_all(stdin_text.as_bytes()));
+    let output = match timeout {
+      Some(timeout) => {
+        fn read_on_thread(mut reader: impl Read + Send + 'static) -> std::thread::JoinHandle<Vec<u8>> {
+          std::thread::spawn(move || {
+            let mut bytes = Vec::new();
+            let _ = reader.read_to_end(&mut bytes);
+            bytes
+          })
+        }
+
+        // read the output on other threads so the command doesn't block
+        // writing to a full pipe while this waits for it to exit
+        let stdout_thread = read_on_thread(child.stdout.take().unwrap());
+        let stderr_thread = read_on_thread(child.stderr.take().unwrap());
+        let start_time = Instant::now();
+        let status = loop {
+          if let Some(status) = child.try_wait().with_context(|| format!("Error running `{}`.", command))? {
+            break status;
+          }
+          if start_time.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("Running `{} {}` timed out after {} seconds.", command, args.join(" "), timeout.as_secs());
+          }
+          std::thread::sleep(Duration::from_millis(10));
+        };
+        std::process::Output {
+          status,
+          stdout: stdout_thread.join().unwrap_or_default(),
+          stderr: stderr_thread.join().unwrap_or_default(),
+        }
       }
+      None => child.wait_with_output().with_context(|| format!("Error running `{}`.", command))?,
     };
-
-    Ok(environment)
+    // ignore the error of a command that exits without reading its stdin
+    let _ = stdin_thread.join();
+    if !output.status.success() {
+      bail!(
+        "Error running `{} {}`. {}",
+        command,
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+      );
+    }
+    match String::from_utf8(output.stdout) {
+      Ok(text) => Ok(text),
+      Err(_) => bail!("The output of `{} {}` was not valid UTF-8.", command, args.join(" ")),
+    }
   }
 }
 
+fn get_cache_overlay_dir() -> PathBuf {
+  std::env::temp_dir().join(format!("dprint-cache-overlay-{}", std::process::id()))
+}
+
+fn create_cache_overlay(mode: CacheOverlayMode, cache_dir: &CanonicalizedPathBuf, write_guard: WriteGuard) -> Result<CacheOverlay> {
+  let overlay_dir = get_cache_overlay_dir();
+  write_guard.ensure_can_write(&overlay_dir)?;
+  // a previous process with the same id may have left it behind
+  if overlay_dir.exists() {
+    fs::remove_dir_all(&overlay_dir)?;
+  }
+  fs::create_dir_all(&overlay_dir)?;
+  let overlay_dir = canonicalize_path(overlay_dir)?;
+  Ok(CacheOverlay::new(
+    mode,
+    cache_dir.as_ref().to_path_buf(),
+    overlay_dir.as_ref().to_path_buf(),
+    write_guard,
+  ))
+}
+
 impl UrlDownloader for RealEnvironment {
   fn download_file(&self, url: &str) -> Result<Option<Vec<u8>>> {
     log_verbose!(self, "Downloading url: {}", url);
@@ -86,7 +220,7 @@ impl Environment for RealEnvironment {
 
   fn read_file_bytes(&self, file_path: impl AsRef<Path>) -> Result<Vec<u8>> {
     log_verbose!(self, "Reading file: {}", file_path.as_ref().display());
-    match fs::read(&file_path) {
+    match fs::read(self.resolve_read_path_buf(file_path.as_ref())) {
       Ok(bytes) => Ok(bytes),
       Err(err) => bail!("Error reading file {}: {:#}", file_path.as_ref().display(), err),
     }
@@ -97,6 +231,7 @@ impl Environment for RealEnvironment {
   }
 
   fn write_file_bytes(&self, file_path: impl AsRef<Path>, bytes: &[u8]) -> Result<()> {
+    self.write_guard.ensure_can_write(file_path.as_ref())?;
     log_verbose!(self, "Writing file: {}", file_path.as_ref().display());
     match fs::write(&file_path, bytes) {
       Ok(_) => Ok(()),
@@ -104,12 +239,39 @@ impl Environment for RealEnvironment {
     }
   }
 
+  fn append_file(&self, file_path: impl AsRef<Path>, file_text: &str) -> Result<()> {
+    use std::io::Write;
+
+    self.write_guard.ensure_can_write(file_path.as_ref())?;
+    log_verbose!(self, "Appending to file: {}", file_path.as_ref().display());
+    if let Some(cache_overlay) = &self.cache_overlay {
+      cache_overlay.copy_up(file_path.as_ref())?;
+    }
+    let result = fs::OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(&file_path)
+      .and_then(|mut file| file.write_all(file_text.as_bytes()));
+    match result {
+      Ok(_) => Ok(()),
+      Err(err) => bail!("Error appending to file {}: {:#}", file_path.as_ref().display(), err),
+    }
+  }
+
   fn rename(&self, path_from: impl AsRef<Path>, path_to: impl AsRef<Path>) -> Result<()> {
+    self.write_guard.ensure_can_write(path_to.as_ref())?;
+    if let Some(cache_overlay) = &self.cache_overlay {
+      // never move a file out of the shared cache directory
+      cache_overlay.copy_up(path_from.as_ref())?;
+    }
+    self.mark_removed(path_from.as_ref());
     fs::rename(&path_from, &path_to).with_context(|| format!("Error renaming {} to {}", path_from.as_ref().display(), path_to.as_ref().display()))
   }
 
   fn remove_file(&self, file_path: impl AsRef<Path>) -> Result<()> {
+    self.write_guard.ensure_can_write(file_path.as_ref())?;
     log_verbose!(self, "Deleting file: {}", file_path.as_ref().display());
+    self.mark_removed(file_path.as_ref());
     match fs::remove_file(&file_path) {
       Ok(_) => Ok(()),
       Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
@@ -117,8 +279,26 @@ impl Environment for RealEnvironment {
     }
   }
 
+  fn fsync(&self, path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    // opening a file for writing in order to sync it is a write
+    self.write_guard.ensure_can_write(path)?;
+    log_verbose!(self, "Syncing: {}", path.display());
+    let result = if path.is_dir() {
+      sync_dir(path)
+    } else {
+      fs::OpenOptions::new().write(true).open(path).and_then(|file| file.sync_all())
+    };
+    match result {
+      Ok(_) => Ok(()),
+      Err(err) => bail!("Error syncing {}: {:#}", path.display(), err),
+    }
+  }
+
   fn remove_dir_all(&self, dir_path: impl AsRef<Path>) -> Result<()> {
+    self.write_guard.ensure_can_write(dir_path.as_ref())?;
     log_verbose!(self, "Deleting directory: {}", dir_path.as_ref().display());
+    self.mark_removed(dir_path.as_ref());
     match fs::remove_dir_all(&dir_path) {
       Ok(_) => Ok(()),
       Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
@@ -132,6 +312,12 @@ impl Environment for RealEnvironment {
     let dir_info = match std::fs::read_dir(&dir_path) {
       Ok(result) => result,
       Err(err) => {
+        if let Some(cache_overlay) = &self.cache_overlay {
+          if err.kind() == std::io::ErrorKind::NotFound && cache_overlay.get_shared_path(dir_path.as_ref()).is_some() {
+            cache_overlay.add_shared_dir_entries(dir_path.as_ref(), &mut entries)?;
+            return Ok(entries);
+          }
+        }
         if is_system_volume_error(dir_path.as_ref(), &err) {
           return Ok(Vec::with_capacity(0));
         } else {
@@ -156,12 +342,20 @@ impl Environment for RealEnvironment {
       }
     }
 
+    if let Some(cache_overlay) = &self.cache_overlay {
+      cache_overlay.add_shared_dir_entries(dir_path.as_ref(), &mut entries)?;
+    }
+
     Ok(entries)
   }
 
   fn path_exists(&self, file_path: impl AsRef<Path>) -> bool {
     log_verbose!(self, "Checking path exists: {}", file_path.as_ref().display());
-    file_path.as_ref().exists()
+    self.resolve_read_path_buf(file_path.as_ref()).exists()
+  }
+
+  fn resolve_read_path(&self, path: &Path) -> PathBuf {
+    self.resolve_read_path_buf(path)
   }
 
   fn canonicalize(&self, path: impl AsRef<Path>) -> Result<CanonicalizedPathBuf> {
@@ -174,13 +368,20 @@ impl Environment for RealEnvironment {
 
   fn file_permissions(&self, path: impl AsRef<Path>) -> Result<FilePermissions> {
     Ok(FilePermissions::Std(
-      fs::metadata(&path)
+      fs::metadata(self.resolve_read_path_buf(path.as_ref()))
         .with_context(|| format!("Error getting file permissions for: {}", path.as_ref().display()))?
         .permissions(),
     ))
   }
 
+  fn file_modified_time(&self, path: impl AsRef<Path>) -> Result<SystemTime> {
+    fs::metadata(self.resolve_read_path_buf(path.as_ref()))
+      .and_then(|metadata| metadata.modified())
+      .with_context(|| format!("Error getting file modified time for: {}", path.as_ref().display()))
+  }
+
   fn set_file_permissions(&self, path: impl AsRef<Path>, permissions: FilePermissions) -> Result<()> {
+    self.write_guard.ensure_can_write(path.as_ref())?;
     let permissions = match permissions {
       FilePermissions::Std(p) => p,
       _ => panic!("Programming error. Permissions did not contain an std permission."),
@@ -190,6 +391,10 @@ impl Environment for RealEnvironment {
   }
 
   fn mk_dir_all(&self, path: impl AsRef<Path>) -> Result<()> {
+    // creating a directory that already exists doesn't write anything
+    if !path.as_ref().is_dir() {
+      self.write_guard.ensure_can_write(path.as_ref())?;
+    }
     log_verbose!(self, "Creating directory: {}", path.as_ref().display());
     match fs::create_dir_all(&path) {
       Ok(_) => Ok(()),
@@ -208,7 +413,10 @@ impl Environment for RealEnvironment {
   }
 
   fn log(&self, text: &str) {
-    self.logger.log(text, "dprint");
+    match &self.log_prefix {
+      Some(prefix) => self.logger.log(&get_text_with_line_prefix(prefix, text), "dprint"),
+      None => self.logger.log(text, "dprint"),
+    }
   }
 
   fn log_machine_readable(&self, text: &str) {
@@ -216,7 +424,16 @@ impl Environment for RealEnvironment {
   }
 
   fn log_stderr_with_context(&self, text: &str, context_name: &str) {
-    self.logger.log_stderr_with_context(text, context_name);
+    match &self.log_prefix {
+      Some(prefix) => self.logger.log_stderr_with_context(&get_text_with_line_prefix(prefix, text), context_name),
+      None => self.logger.log_stderr_with_context(text, context_name),
+    }
+  }
+
+  fn with_log_prefix(&self, prefix: &str) -> Self {
+    let mut environment = self.clone();
+    environment.log_prefix = Some(prefix.to_string());
+    environment
   }
 
   fn log_action_with_progress<TResult: Send + Sync, TCreate: FnOnce(Box<dyn Fn(usize)>) -> TResult + Send + Sync>(
@@ -229,8 +446,11 @@ impl Environment for RealEnvironment {
   }
 
   fn get_cache_dir(&self) -> CanonicalizedPathBuf {
-    // ok to unwrap because this would have errored in the constructor
-    (*CACHE_DIR.as_ref().unwrap()).clone()
+    match &self.cache_overlay {
+      Some(cache_overlay) => CanonicalizedPathBuf::new(cache_overlay.overlay_dir().to_path_buf()),
+      // ok to unwrap because this would have errored in the constructor
+      None => (*CACHE_DIR.as_ref().unwrap()).clone(),
+    }
   }
 
   fn cpu_arch(&self) -> String {
@@ -276,15 +496,81 @@ impl Environment for RealEnvironment {
     show_confirm(&self.logger, "dprint", prompt_message, default_value)
   }
 
+  fn attempted_write_paths(&self) -> Vec<PathBuf> {
+    self.write_guard.attempted_paths()
+  }
+
+  #[inline]
+  fn is_read_only(&self) -> bool {
+    self.write_guard.is_enabled()
+  }
+
   #[inline]
   fn is_verbose(&self) -> bool {
     self.logger.is_verbose()
   }
 
+  fn is_interactive(&self) -> bool {
+    use crossterm::tty::IsTty;
+    std::io::stdin().is_tty() && std::io::stderr().is_tty()
+  }
+
   fn compile_wasm(&self, wasm_bytes: &[u8]) -> Result<CompilationResult> {
     crate::plugins::compile_wasm(wasm_bytes)
   }
 
+  fn env_var(&self, name: &str) -> Option<String> {
+    std::env::var(name).ok()
+  }
+
+  fn is_process_active(&self, process_id: u32) -> bool {
+    dprint_core::plugins::process::is_process_active(process_id)
+  }
+
+  fn kill_orphaned_process(&self, process_id: u32, executable_file_path: &Path) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+      // ensure the process id wasn't reused by another process
+      let process_executable_path = std::fs::read_link(format!("/proc/{}/exe", process_id)).ok();
+      let executable_file_path = std::fs::canonicalize(executable_file_path).ok();
+      if process_executable_path.is_none() || process_executable_path != executable_file_path {
+        return false;
+      }
+      log_verbose!(self, "Killing orphaned process {} ({}).", process_id, executable_file_path.unwrap().display());
+      dprint_core::plugins::process::kill_process(process_id)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+      let _ = (process_id, executable_file_path);
+      false
+    }
+  }
+
+  fn run_command(&self, command: &str, args: &[&str]) -> Result<String> {
+    log_verbose!(self, "Running command: {} {}", command, args.join(" "));
+    let output = std::process::Command::new(command)
+      .args(args)
+      .output()
+      .with_context(|| format!("Error running `{}`.", command))?;
+    if !output.status.success() {
+      bail!(
+        "Error running `{} {}`. {}",
+        command,
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+      );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+  }
+
+  fn run_command_with_stdin(&self, command: &str, args: &[&str], cwd: &Path, stdin_text: &str) -> Result<String> {
+    self.run_command_with_stdin_and_timeout(command, args, cwd, stdin_text, None)
+  }
+
+  fn run_command_with_timeout(&self, command: &str, args: &[&str], cwd: &Path, stdin_text: &str, timeout: Duration) -> Result<String> {
+    self.run_command_with_stdin_and_timeout(command, args, cwd, stdin_text, Some(timeout))
+  }
+
   fn stdout(&self) -> Box<dyn std::io::Write + Send> {
     Box::new(std::io::stdout())
   }
@@ -360,6 +646,18 @@ fn resolve_max_threads(env_var: Option<String>, available_parallelism: Option<No
   }
 }
 
+#[cfg(unix)]
+fn sync_dir(dir_path: &Path) -> std::io::Result<()> {
+  fs::File::open(dir_path)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir_path: &Path) -> std::io::Result<()> {
+  // directories can't be opened for syncing on Windows, where the
+  // directory entries are persisted along with the file metadata
+  Ok(())
+}
+
 fn canonicalize_path(path: impl AsRef<Path>) -> Result<CanonicalizedPathBuf> {
   // use this to avoid //?//C:/etc... like paths on windows (UNC)
   match dunce::canonicalize(path.as_ref()) {
@@ -429,6 +727,20 @@ mod test {
     );
   }
 
+  #[test]
+  fn should_error_creating_cache_overlay_when_asserting_no_writes() {
+    let cache_dir = CanonicalizedPathBuf::new_for_testing(std::env::temp_dir());
+    let write_guard = WriteGuard::new(true);
+    let err = create_cache_overlay(CacheOverlayMode::Discard, &cache_dir, write_guard.clone()).err().unwrap();
+    let overlay_dir = get_cache_overlay_dir();
+    assert_eq!(
+      err.to_string(),
+      format!("Cannot write to {} when running with --assert-no-writes.", overlay_dir.display())
+    );
+    assert_eq!(write_guard.attempted_paths(), vec![overlay_dir.clone()]);
+    assert!(!overlay_dir.exists());
+  }
+
   #[test]
   fn should_resolve_num_threads() {
     assert_eq!(resolve_max_threads(None, None), 4);
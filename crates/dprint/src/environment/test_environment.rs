@@ -13,13 +13,17 @@ use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
 
+use super::get_text_with_line_prefix;
 use super::CanonicalizedPathBuf;
 use super::DirEntry;
 use super::DirEntryKind;
 use super::Environment;
 use super::FilePermissions;
 use super::UrlDownloader;
+use super::WriteGuard;
 use crate::plugins::CompilationResult;
 
 #[derive(Default)]
@@ -55,8 +59,18 @@ impl Drop for TestPipeWriter {
 }
 
 impl Read for TestPipeReader {
-  fn read(&mut self, _: &mut [u8]) -> Result<usize, std::io::Error> {
-    panic!("Not implemented");
+  fn read(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+    let mut buffer_data = self.0.buffer_data.lock();
+
+    while buffer_data.data.len() == buffer_data.read_pos && !buffer_data.closed {
+      self.0.cond_var.wait(&mut buffer_data);
+    }
+
+    let read_count = std::cmp::min(buf.len(), buffer_data.data.len() - buffer_data.read_pos);
+    buf[..read_count].copy_from_slice(&buffer_data.data[buffer_data.read_pos..buffer_data.read_pos + read_count]);
+    buffer_data.read_pos += read_count;
+
+    Ok(read_count)
   }
 
   fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), std::io::Error> {
@@ -92,17 +106,28 @@ impl Write for TestPipeWriter {
   }
 }
 
+/// An environment that keeps the files, logs, remote files, and command
+/// outputs in memory, which is used for testing and by embedders that
+/// format virtual files (exported as `MemoryEnvironment`).
 #[derive(Clone)]
 pub struct TestEnvironment {
   is_verbose: Arc<Mutex<bool>>,
+  is_interactive: Arc<Mutex<bool>>,
   cwd: Arc<Mutex<String>>,
   files: Arc<Mutex<HashMap<PathBuf, Vec<u8>>>>,
   file_permissions: Arc<Mutex<HashMap<PathBuf, FilePermissions>>>,
+  file_modified_times: Arc<Mutex<HashMap<PathBuf, SystemTime>>>,
+  file_write_count: Arc<Mutex<u64>>,
   stdout_messages: Arc<Mutex<Vec<String>>>,
   stderr_messages: Arc<Mutex<Vec<String>>>,
   remote_files: Arc<Mutex<HashMap<String, Result<Vec<u8>>>>>,
+  command_outputs: Arc<Mutex<HashMap<String, Result<String>>>>,
+  run_commands: Arc<Mutex<Vec<String>>>,
+  run_command_stdins: Arc<Mutex<Vec<String>>>,
   deleted_directories: Arc<Mutex<Vec<PathBuf>>>,
+  synced_paths: Arc<Mutex<Vec<PathBuf>>>,
   selection_result: Arc<Mutex<usize>>,
+  time_secs: Arc<Mutex<u64>>,
   multi_selection_result: Arc<Mutex<Option<Vec<usize>>>>,
   confirm_results: Arc<Mutex<Vec<Result<Option<bool>>>>>,
   is_stdout_machine_readable: Arc<Mutex<bool>>,
@@ -116,20 +141,38 @@ pub struct TestEnvironment {
   cpu_arch: Arc<Mutex<String>>,
   max_threads_count: Arc<Mutex<usize>>,
   current_exe_path: Arc<Mutex<PathBuf>>,
+  env_vars: Arc<Mutex<HashMap<String, String>>>,
+  running_processes: Arc<Mutex<HashMap<u32, PathBuf>>>,
+  write_guard: WriteGuard,
+  log_prefix: Option<String>,
+}
+
+impl Default for TestEnvironment {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl TestEnvironment {
   pub fn new() -> TestEnvironment {
     TestEnvironment {
       is_verbose: Arc::new(Mutex::new(false)),
+      is_interactive: Arc::new(Mutex::new(false)),
       cwd: Arc::new(Mutex::new(String::from("/"))),
       files: Default::default(),
       file_permissions: Default::default(),
+      file_modified_times: Default::default(),
+      file_write_count: Default::default(),
       stdout_messages: Default::default(),
       stderr_messages: Default::default(),
       remote_files: Default::default(),
+      command_outputs: Default::default(),
+      run_commands: Default::default(),
+      run_command_stdins: Default::default(),
       deleted_directories: Default::default(),
+      synced_paths: Default::default(),
       selection_result: Arc::new(Mutex::new(0)),
+      time_secs: Arc::new(Mutex::new(123456)),
       multi_selection_result: Arc::new(Mutex::new(None)),
       confirm_results: Default::default(),
       is_stdout_machine_readable: Arc::new(Mutex::new(false)),
@@ -149,6 +192,10 @@ impl TestEnvironment {
       cpu_arch: Arc::new(Mutex::new("x86_64".to_string())),
       max_threads_count: Arc::new(Mutex::new(std::thread::available_parallelism().map(|p| p.get()).unwrap_or(4))),
       current_exe_path: Arc::new(Mutex::new(PathBuf::from("/dprint"))),
+      env_vars: Default::default(),
+      running_processes: Default::default(),
+      write_guard: Default::default(),
+      log_prefix: None,
     }
   }
 
@@ -179,11 +226,37 @@ impl TestEnvironment {
     remote_files.insert(String::from(path), Err(anyhow!("{}", err)));
   }
 
+  pub fn add_command_output(&self, command_line: &str, stdout: &str) {
+    let mut command_outputs = self.command_outputs.lock();
+    command_outputs.insert(String::from(command_line), Ok(stdout.to_string()));
+  }
+
+  pub fn add_command_error(&self, command_line: &str, err: &str) {
+    let mut command_outputs = self.command_outputs.lock();
+    command_outputs.insert(String::from(command_line), Err(anyhow!("{}", err)));
+  }
+
+  pub fn take_run_commands(&self) -> Vec<String> {
+    self.run_commands.lock().drain(..).collect()
+  }
+
+  pub fn take_run_command_stdins(&self) -> Vec<String> {
+    self.run_command_stdins.lock().drain(..).collect()
+  }
+
+  pub fn take_synced_paths(&self) -> Vec<PathBuf> {
+    self.synced_paths.lock().drain(..).collect()
+  }
+
   pub fn is_dir_deleted(&self, path: impl AsRef<Path>) -> bool {
     let deleted_directories = self.deleted_directories.lock();
     deleted_directories.contains(&path.as_ref().to_path_buf())
   }
 
+  pub fn set_time_secs(&self, secs: u64) {
+    *self.time_secs.lock() = secs;
+  }
+
   pub fn set_selection_result(&self, index: usize) {
     let mut selection_result = self.selection_result.lock();
     *selection_result = index;
@@ -208,11 +281,20 @@ impl TestEnvironment {
     *self.is_stdout_machine_readable.lock() = value;
   }
 
+  pub fn set_assert_no_writes(&self, value: bool) {
+    self.write_guard.set_enabled(value);
+  }
+
   pub fn set_verbose(&self, value: bool) {
     let mut is_verbose = self.is_verbose.lock();
     *is_verbose = value;
   }
 
+  pub fn set_interactive(&self, value: bool) {
+    *self.is_interactive.lock() = value;
+  }
+
+  /// Sets the result of compiling any Wasm plugin instead of compiling it.
   pub fn set_wasm_compile_result(&self, value: CompilationResult) {
     let mut wasm_compile_result = self.wasm_compile_result.lock();
     *wasm_compile_result = Some(value);
@@ -248,10 +330,31 @@ impl TestEnvironment {
     *self.max_threads_count.lock() = value;
   }
 
+  pub fn set_env_var(&self, name: &str, value: &str) {
+    self.env_vars.lock().insert(name.to_string(), value.to_string());
+  }
+
+  /// Marks a process other than the current one as running the executable.
+  pub fn set_running_process(&self, process_id: u32, executable_file_path: impl AsRef<Path>) {
+    self.running_processes.lock().insert(process_id, executable_file_path.as_ref().to_path_buf());
+  }
+
+  pub fn is_running_process(&self, process_id: u32) -> bool {
+    self.running_processes.lock().contains_key(&process_id)
+  }
+
   pub fn set_runtime_handle(&self, handle: tokio::runtime::Handle) {
     *self.runtime_handle.lock() = Some(handle);
   }
 
+  /// Marks the file as modified, which gives it a later time than any previous write.
+  fn mark_modified(&self, file_path: PathBuf) {
+    let mut file_write_count = self.file_write_count.lock();
+    *file_write_count += 1;
+    let modified_time = SystemTime::UNIX_EPOCH + Duration::from_secs(*file_write_count);
+    self.file_modified_times.lock().insert(file_path, modified_time);
+  }
+
   /// Remember to drop the plugins collection manually if using this with one.
   pub fn run_in_runtime<T>(&self, future: impl Future<Output = T>) -> T {
     let rt = tokio::runtime::Builder::new_multi_thread().enable_time().build().unwrap();
@@ -259,6 +362,13 @@ impl TestEnvironment {
     rt.block_on(future)
   }
 
+  fn get_text_with_log_prefix(&self, text: &str) -> String {
+    match &self.log_prefix {
+      Some(prefix) => get_text_with_line_prefix(prefix, text),
+      None => text.to_string(),
+    }
+  }
+
   fn clean_path(&self, path: impl AsRef<Path>) -> PathBuf {
     // temporary until https://github.com/danreeves/path-clean/issues/4 is fixed in path-clean
     let file_path = PathBuf::from(path.as_ref().to_string_lossy().replace("\\", "/"));
@@ -271,6 +381,7 @@ impl TestEnvironment {
   }
 }
 
+#[cfg(test)]
 impl Drop for TestEnvironment {
   fn drop(&mut self) {
     // If this panics that means the logged messages or errors weren't inspected for a test.
@@ -326,13 +437,23 @@ impl Environment for TestEnvironment {
   }
 
   fn write_file_bytes(&self, file_path: impl AsRef<Path>, bytes: &[u8]) -> Result<()> {
+    self.write_guard.ensure_can_write(file_path.as_ref())?;
     let file_path = self.clean_path(file_path);
-    let mut files = self.files.lock();
-    files.insert(file_path, Vec::from(bytes));
+    self.files.lock().insert(file_path.clone(), Vec::from(bytes));
+    self.mark_modified(file_path);
+    Ok(())
+  }
+
+  fn append_file(&self, file_path: impl AsRef<Path>, file_text: &str) -> Result<()> {
+    self.write_guard.ensure_can_write(file_path.as_ref())?;
+    let file_path = self.clean_path(file_path);
+    self.files.lock().entry(file_path.clone()).or_default().extend(file_text.as_bytes());
+    self.mark_modified(file_path);
     Ok(())
   }
 
   fn rename(&self, path_from: impl AsRef<Path>, path_to: impl AsRef<Path>) -> Result<()> {
+    self.write_guard.ensure_can_write(path_to.as_ref())?;
     let path_from = self.clean_path(path_from);
     let path_to = self.clean_path(path_to);
     {
@@ -347,17 +468,33 @@ impl Environment for TestEnvironment {
         file_permissions.insert(path_to.clone(), perms);
       }
     }
+    {
+      let mut file_modified_times = self.file_modified_times.lock();
+      if let Some(modified_time) = file_modified_times.remove(&path_from) {
+        file_modified_times.insert(path_to, modified_time);
+      }
+    }
     Ok(())
   }
 
   fn remove_file(&self, file_path: impl AsRef<Path>) -> Result<()> {
+    self.write_guard.ensure_can_write(file_path.as_ref())?;
     let file_path = self.clean_path(file_path);
     self.files.lock().remove(&file_path);
     self.file_permissions.lock().remove(&file_path);
+    self.file_modified_times.lock().remove(&file_path);
+    Ok(())
+  }
+
+  fn fsync(&self, path: impl AsRef<Path>) -> Result<()> {
+    self.write_guard.ensure_can_write(path.as_ref())?;
+    let path = self.clean_path(path);
+    self.synced_paths.lock().push(path);
     Ok(())
   }
 
   fn remove_dir_all(&self, dir_path: impl AsRef<Path>) -> Result<()> {
+    self.write_guard.ensure_can_write(dir_path.as_ref())?;
     let dir_path = self.clean_path(dir_path);
     {
       let mut deleted_directories = self.deleted_directories.lock();
@@ -420,6 +557,10 @@ impl Environment for TestEnvironment {
     files.contains_key(&self.clean_path(file_path))
   }
 
+  fn resolve_read_path(&self, path: &Path) -> PathBuf {
+    path.to_path_buf()
+  }
+
   fn canonicalize(&self, path: impl AsRef<Path>) -> Result<CanonicalizedPathBuf> {
     Ok(CanonicalizedPathBuf::new(self.clean_path(path)))
   }
@@ -440,13 +581,28 @@ impl Environment for TestEnvironment {
     }
   }
 
+  fn file_modified_time(&self, path: impl AsRef<Path>) -> Result<SystemTime> {
+    let path = self.clean_path(path);
+    if !self.files.lock().contains_key(&path) {
+      bail!("File not found.")
+    }
+    Ok(self.file_modified_times.lock().get(&path).copied().unwrap_or(SystemTime::UNIX_EPOCH))
+  }
+
   fn set_file_permissions(&self, path: impl AsRef<Path>, permissions: FilePermissions) -> Result<()> {
+    self.write_guard.ensure_can_write(path.as_ref())?;
     let path = self.clean_path(path);
     self.file_permissions.lock().insert(path, permissions);
     Ok(())
   }
 
-  fn mk_dir_all(&self, _: impl AsRef<Path>) -> Result<()> {
+  fn mk_dir_all(&self, path: impl AsRef<Path>) -> Result<()> {
+    // creating a directory that already exists doesn't write anything
+    let path = self.clean_path(path);
+    let is_existing_dir = self.files.lock().keys().any(|file_path| file_path != &path && file_path.starts_with(&path));
+    if !is_existing_dir {
+      self.write_guard.ensure_can_write(&path)?;
+    }
     Ok(())
   }
 
@@ -463,11 +619,17 @@ impl Environment for TestEnvironment {
     if *self.is_stdout_machine_readable.lock() {
       return;
     }
-    self.stdout_messages.lock().push(String::from(text));
+    self.stdout_messages.lock().push(self.get_text_with_log_prefix(text));
   }
 
   fn log_stderr_with_context(&self, text: &str, _: &str) {
-    self.stderr_messages.lock().push(String::from(text));
+    self.stderr_messages.lock().push(self.get_text_with_log_prefix(text));
+  }
+
+  fn with_log_prefix(&self, prefix: &str) -> Self {
+    let mut environment = self.clone();
+    environment.log_prefix = Some(prefix.to_string());
+    environment
   }
 
   fn log_machine_readable(&self, text: &str) {
@@ -506,7 +668,7 @@ impl Environment for TestEnvironment {
   }
 
   fn get_time_secs(&self) -> u64 {
-    123456
+    *self.time_secs.lock()
   }
 
   fn get_selection(&self, prompt_message: &str, _: u16, _: &[String]) -> Result<usize> {
@@ -539,13 +701,68 @@ impl Environment for TestEnvironment {
     result
   }
 
+  fn attempted_write_paths(&self) -> Vec<PathBuf> {
+    self.write_guard.attempted_paths()
+  }
+
+  fn is_read_only(&self) -> bool {
+    self.write_guard.is_enabled()
+  }
+
   fn is_verbose(&self) -> bool {
     *self.is_verbose.lock()
   }
 
-  fn compile_wasm(&self, _: &[u8]) -> Result<CompilationResult> {
-    let wasm_compile_result = self.wasm_compile_result.lock();
-    Ok(wasm_compile_result.clone().expect("Expected compilation result to be set."))
+  fn is_interactive(&self) -> bool {
+    *self.is_interactive.lock()
+  }
+
+  fn compile_wasm(&self, wasm_bytes: &[u8]) -> Result<CompilationResult> {
+    match self.wasm_compile_result.lock().clone() {
+      Some(wasm_compile_result) => Ok(wasm_compile_result),
+      None => crate::plugins::compile_wasm(wasm_bytes),
+    }
+  }
+
+  fn run_command(&self, command: &str, args: &[&str]) -> Result<String> {
+    let command_line = if args.is_empty() {
+      command.to_string()
+    } else {
+      format!("{} {}", command, args.join(" "))
+    };
+    self.run_commands.lock().push(command_line.clone());
+    match self.command_outputs.lock().get(&command_line) {
+      Some(Ok(stdout)) => Ok(stdout.clone()),
+      Some(Err(err)) => Err(anyhow!("{:#}", err)),
+      None => bail!("Command not found: {}", command_line),
+    }
+  }
+
+  fn run_command_with_stdin(&self, command: &str, args: &[&str], _cwd: &Path, stdin_text: &str) -> Result<String> {
+    self.run_command_stdins.lock().push(stdin_text.to_string());
+    self.run_command(command, args)
+  }
+
+  fn run_command_with_timeout(&self, command: &str, args: &[&str], cwd: &Path, stdin_text: &str, _timeout: Duration) -> Result<String> {
+    self.run_command_with_stdin(command, args, cwd, stdin_text)
+  }
+
+  fn env_var(&self, name: &str) -> Option<String> {
+    self.env_vars.lock().get(name).cloned()
+  }
+
+  fn is_process_active(&self, process_id: u32) -> bool {
+    process_id == std::process::id() || self.is_running_process(process_id)
+  }
+
+  fn kill_orphaned_process(&self, process_id: u32, executable_file_path: &Path) -> bool {
+    let mut running_processes = self.running_processes.lock();
+    if running_processes.get(&process_id).map(|path| path.as_path()) == Some(executable_file_path) {
+      running_processes.remove(&process_id);
+      true
+    } else {
+      false
+    }
   }
 
   fn stdout(&self) -> Box<dyn Write + Send> {
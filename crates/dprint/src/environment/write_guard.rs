@@ -0,0 +1,49 @@
+use anyhow::bail;
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// Disallows writing to the file system when running with `--assert-no-writes`.
+///
+/// The attempted writes are stored so the run may fail even when the
+/// error of a write is handled (ex. failing to save the incremental file).
+#[derive(Clone, Default)]
+pub struct WriteGuard {
+  is_enabled: Arc<AtomicBool>,
+  attempted_paths: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl WriteGuard {
+  pub fn new(is_enabled: bool) -> Self {
+    WriteGuard {
+      is_enabled: Arc::new(AtomicBool::new(is_enabled)),
+      attempted_paths: Default::default(),
+    }
+  }
+
+  /// Also clears the attempted writes of a previous run.
+  pub fn set_enabled(&self, value: bool) {
+    self.is_enabled.store(value, Ordering::SeqCst);
+    self.attempted_paths.lock().clear();
+  }
+
+  pub fn is_enabled(&self) -> bool {
+    self.is_enabled.load(Ordering::SeqCst)
+  }
+
+  pub fn ensure_can_write(&self, path: &Path) -> Result<()> {
+    if self.is_enabled.load(Ordering::SeqCst) {
+      self.attempted_paths.lock().push(path.to_path_buf());
+      bail!("Cannot write to {} when running with --assert-no-writes.", path.display());
+    }
+    Ok(())
+  }
+
+  pub fn attempted_paths(&self) -> Vec<PathBuf> {
+    self.attempted_paths.lock().clone()
+  }
+}
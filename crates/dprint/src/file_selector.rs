@@ -0,0 +1,119 @@
+use anyhow::bail;
+use anyhow::Result;
+use std::path::PathBuf;
+
+use crate::environment::CanonicalizedPathBuf;
+use crate::environment::Environment;
+use crate::plugins::PluginSourceReference;
+use crate::plugins::WasmFileSelector;
+use crate::utils::verify_sha256_checksum;
+use crate::utils::PathSource;
+
+/// Filters the file paths matched by the globs with the
+/// Wasm module of the `fileSelector` configuration property.
+pub fn select_file_paths(
+  reference: &PluginSourceReference,
+  base_path: &CanonicalizedPathBuf,
+  file_paths: Vec<PathBuf>,
+  environment: &impl Environment,
+) -> Result<Vec<PathBuf>> {
+  if file_paths.is_empty() {
+    return Ok(file_paths);
+  }
+  let selector = match get_file_selector_bytes(reference, environment).and_then(|bytes| WasmFileSelector::new(&bytes)) {
+    Ok(selector) => selector,
+    Err(err) => bail!("Error loading file selector {}. {:#}", reference.display(), err),
+  };
+  let relative_paths = file_paths
+    .iter()
+    .map(|file_path| file_path.strip_prefix(base_path).unwrap_or(file_path).to_string_lossy().replace('\\', "/"))
+    .collect::<Vec<_>>();
+  let selections = match selector.select(&relative_paths) {
+    Ok(selections) => selections,
+    Err(err) => bail!("Error selecting files with {}. {:#}", reference.display(), err),
+  };
+
+  let mut selected_file_paths = Vec::with_capacity(file_paths.len());
+  for ((file_path, relative_path), is_selected) in file_paths.into_iter().zip(relative_paths).zip(selections) {
+    if is_selected {
+      selected_file_paths.push(file_path);
+    } else {
+      log_verbose!(environment, "Excluded by the file selector: {}", relative_path);
+    }
+  }
+  Ok(selected_file_paths)
+}
+
+fn get_file_selector_bytes(reference: &PluginSourceReference, environment: &impl Environment) -> Result<Vec<u8>> {
+  // a checksum is required when resolving the configuration
+  let checksum = reference.checksum.as_deref().unwrap_or_default();
+  match &reference.path_source {
+    PathSource::Local(source) => {
+      let bytes = environment.read_file_bytes(&source.path)?;
+      verify_sha256_checksum(&bytes, checksum)?;
+      Ok(bytes)
+    }
+    PathSource::Remote(source) => {
+      // the module is pinned to its checksum, so it only needs to be downloaded once
+      let cache_dir_path = environment.get_cache_dir().join("file-selectors");
+      let cache_file_path = cache_dir_path.join(format!("{}.wasm", checksum));
+      if let Ok(bytes) = environment.read_file_bytes(&cache_file_path) {
+        if verify_sha256_checksum(&bytes, checksum).is_ok() {
+          return Ok(bytes);
+        }
+      }
+      let bytes = environment.download_file_err_404(source.url.as_str())?;
+      verify_sha256_checksum(&bytes, checksum)?;
+      let result = environment
+        .mk_dir_all(&cache_dir_path)
+        .and_then(|_| environment.write_file_bytes(&cache_file_path, &bytes));
+      if let Err(err) = result {
+        log_verbose!(environment, "Error caching file selector: {:#}", err);
+      }
+      Ok(bytes)
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::environment::TestEnvironment;
+  use crate::test_helpers::FILE_SELECTOR_WAT;
+  use crate::utils::get_sha256_checksum;
+
+  #[test]
+  fn should_select_with_remote_file_selector() {
+    let environment = TestEnvironment::new();
+    environment.add_remote_file("https://example.com/selector.wasm", FILE_SELECTOR_WAT.as_bytes());
+    let checksum = get_sha256_checksum(FILE_SELECTOR_WAT.as_bytes());
+    let reference = PluginSourceReference {
+      path_source: PathSource::new_remote_from_str("https://example.com/selector.wasm"),
+      checksum: Some(checksum.clone()),
+    };
+    let base_path = environment.canonicalize("/").unwrap();
+    let file_paths = vec![PathBuf::from("/a.txt"), PathBuf::from("/_b.txt"), PathBuf::from("/sub/_c.txt")];
+    let selected = select_file_paths(&reference, &base_path, file_paths, &environment).unwrap();
+    assert_eq!(selected, vec![PathBuf::from("/a.txt"), PathBuf::from("/sub/_c.txt")]);
+    // cached by its checksum
+    let cache_file_path = environment.get_cache_dir().join("file-selectors").join(format!("{}.wasm", checksum));
+    assert_eq!(environment.read_file(cache_file_path).unwrap(), FILE_SELECTOR_WAT);
+
+    let reference = PluginSourceReference {
+      path_source: PathSource::new_remote_from_str("https://example.com/selector.wasm"),
+      checksum: Some("other".to_string()),
+    };
+    let err = select_file_paths(&reference, &base_path, vec![PathBuf::from("/a.txt")], &environment)
+      .err()
+      .unwrap();
+    assert!(
+      err
+        .to_string()
+        .starts_with("Error loading file selector https://example.com/selector.wasm. The checksum did not match the expected checksum."),
+      "{}",
+      err
+    );
+  }
+}
@@ -2,12 +2,16 @@ use anyhow::bail;
 use anyhow::Result;
 use dprint_core::configuration::ConfigKeyMap;
 use dprint_core::plugins::CriticalFormatError;
+use dprint_core::plugins::Host;
+use dprint_core::plugins::HostFormatRequest;
 use dprint_core::plugins::NullCancellationToken;
 use parking_lot::Mutex;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 use std::time::Instant;
@@ -16,14 +20,18 @@ use tokio_util::sync::CancellationToken;
 
 use crate::environment::Environment;
 use crate::incremental::IncrementalFile;
+use crate::incremental::QuarantinedFile;
 use crate::paths::PluginNames;
 use crate::plugins::GetPluginResult;
 use crate::plugins::InitializedPlugin;
 use crate::plugins::InitializedPluginFormatRequest;
 use crate::plugins::PluginWrapper;
 use crate::plugins::PluginsCollection;
+use crate::stats::RunStatsCollector;
+use crate::transforms::FileTextTransforms;
 use crate::utils::ErrorCountLogger;
 use crate::utils::FileText;
+use crate::utils::GeneratedFileSkipper;
 
 struct TaskWork<TEnvironment: Environment> {
   semaphore: Arc<Semaphore>,
@@ -40,28 +48,153 @@ struct StoredSemaphore {
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct EnsureStableFormat(pub bool);
 
+/// Limits the number of files formatted in a run by time and/or count.
+///
+/// Files known to be formatted by the incremental cache don't use up the budget.
+pub struct RunBudget {
+  start_instant: Instant,
+  max_duration: Option<Duration>,
+  max_files: Option<usize>,
+  taken_files_count: AtomicUsize,
+  remaining_files_count: AtomicUsize,
+}
+
+impl RunBudget {
+  pub fn new(max_duration: Option<Duration>, max_files: Option<usize>) -> Self {
+    RunBudget {
+      start_instant: Instant::now(),
+      max_duration,
+      max_files,
+      taken_files_count: AtomicUsize::new(0),
+      remaining_files_count: AtomicUsize::new(0),
+    }
+  }
+
+  /// Takes a file from the budget or returns false when the budget is used up.
+  fn try_take_file(&self) -> bool {
+    let is_out_of_time = self
+      .max_duration
+      .map(|max_duration| self.start_instant.elapsed() >= max_duration)
+      .unwrap_or(false);
+    let is_within_budget = !is_out_of_time
+      && self
+        .max_files
+        .map(|max_files| self.taken_files_count.fetch_add(1, Ordering::SeqCst) < max_files)
+        .unwrap_or(true);
+    if !is_within_budget {
+      self.remaining_files_count.fetch_add(1, Ordering::SeqCst);
+    }
+    is_within_budget
+  }
+
+  /// The number of files that weren't formatted because the budget was used up.
+  pub fn remaining_files_count(&self) -> usize {
+    self.remaining_files_count.load(Ordering::SeqCst)
+  }
+}
+
+pub struct RunParallelizedOptions<TEnvironment: Environment> {
+  pub incremental_file: Option<Arc<IncrementalFile<TEnvironment>>>,
+  pub stats: Option<Arc<RunStatsCollector>>,
+  pub ensure_stable_format: EnsureStableFormat,
+  /// When provided, any error cancels this token and no new files
+  /// are formatted once it's cancelled.
+  pub fail_fast_token: Option<CancellationToken>,
+  /// Skips the files of plugins that fail to initialize instead of erroring.
+  pub allow_plugin_failures: bool,
+  /// Formats the files with this host instead of initializing
+  /// the plugins in this process (ex. a plugin broker).
+  pub host: Option<Arc<dyn Host>>,
+  /// Skips formatting the remaining files once this is used up.
+  pub budget: Option<Arc<RunBudget>>,
+  /// Files that cause a critical error are added to this so they
+  /// may be skipped in later runs.
+  pub quarantined_files: Option<Arc<Mutex<Vec<QuarantinedFile>>>>,
+  /// Formats the files of each set of plugins one at a time in a canonical
+  /// order without giving the threads of finished plugins to the others,
+  /// so runs are reproducible.
+  pub deterministic: bool,
+  /// Skips formatting the generated files.
+  pub generated_file_skipper: Option<Arc<GeneratedFileSkipper>>,
+  /// Transforms the text before and after it's formatted.
+  pub transforms: Option<Arc<FileTextTransforms>>,
+}
+
+#[derive(Clone)]
+enum FileFormatter {
+  Plugins(Arc<Vec<Arc<dyn InitializedPlugin>>>),
+  Host(Arc<dyn Host>),
+}
+
+impl<TEnvironment: Environment> Default for RunParallelizedOptions<TEnvironment> {
+  fn default() -> Self {
+    RunParallelizedOptions {
+      incremental_file: None,
+      stats: None,
+      ensure_stable_format: EnsureStableFormat(false),
+      fail_fast_token: None,
+      allow_plugin_failures: false,
+      host: None,
+      budget: None,
+      quarantined_files: None,
+      deterministic: false,
+      generated_file_skipper: None,
+      transforms: None,
+    }
+  }
+}
+
 pub async fn run_parallelized<F, TEnvironment: Environment>(
   file_paths_by_plugins: HashMap<PluginNames, Vec<PathBuf>>,
   environment: &TEnvironment,
   plugins_collection: Arc<PluginsCollection<TEnvironment>>,
-  incremental_file: Option<Arc<IncrementalFile<TEnvironment>>>,
-  ensure_stable_format: EnsureStableFormat,
+  options: RunParallelizedOptions<TEnvironment>,
   f: F,
 ) -> Result<()>
 where
   F: Fn(&Path, &str, String, bool, Instant, &TEnvironment) -> Result<()> + Send + Sync + 'static + Clone,
 {
+  let RunParallelizedOptions {
+    incremental_file,
+    stats,
+    ensure_stable_format,
+    fail_fast_token,
+    allow_plugin_failures,
+    host,
+    budget,
+    quarantined_files,
+    deterministic,
+    generated_file_skipper,
+    transforms,
+  } = options;
   let max_threads = environment.max_threads();
   let number_process_plugins = plugins_collection.process_plugin_count();
   let reduction_count = number_process_plugins + 1; // + 1 for each process plugin's possible runtime thread and this runtime's thread
-  let number_threads = if max_threads > reduction_count { max_threads - reduction_count } else { 1 };
+  let number_threads = if deterministic {
+    // one thread per set of plugins
+    std::cmp::max(1, file_paths_by_plugins.len())
+  } else if max_threads > reduction_count {
+    max_threads - reduction_count
+  } else {
+    1
+  };
   log_verbose!(environment, "Max threads: {}\nThread count: {}", max_threads, number_threads);
+  if let Some(stats) = &stats {
+    stats.set_thread_count(number_threads);
+  }
 
   let error_logger = ErrorCountLogger::from_environment(environment);
 
   let mut file_paths_by_plugins = file_paths_by_plugins.into_iter().collect::<Vec<_>>();
-  // favour giving semaphore permits to ones with more items at the start
-  file_paths_by_plugins.sort_by_key(|(_, file_paths)| 0i32 - file_paths.len() as i32);
+  if deterministic {
+    file_paths_by_plugins.sort_by(|a, b| a.0.cmp(&b.0));
+    for (_, file_paths) in file_paths_by_plugins.iter_mut() {
+      file_paths.sort();
+    }
+  } else {
+    // favour giving semaphore permits to ones with more items at the start
+    file_paths_by_plugins.sort_by_key(|(_, file_paths)| 0i32 - file_paths.len() as i32);
+  }
   let collection_count = file_paths_by_plugins.len();
   let mut semaphores = Vec::with_capacity(collection_count);
   let mut task_works = Vec::with_capacity(collection_count);
@@ -88,41 +221,89 @@ where
       let error_logger = error_logger.clone();
       let environment = environment.clone();
       let incremental_file = incremental_file.clone();
+      let stats = stats.clone();
+      let fail_fast_token = fail_fast_token.clone();
       let f = f.clone();
       let semaphores = semaphores.clone();
+      let host = host.clone();
+      let budget = budget.clone();
+      let quarantined_files = quarantined_files.clone();
+      let generated_file_skipper = generated_file_skipper.clone();
+      let transforms = transforms.clone();
       async move {
-        let _semaphore_permits = SemaphorePermitReleaser { index, semaphores };
+        let _semaphore_permits = SemaphorePermitReleaser {
+          index,
+          semaphores,
+          redistribute: !deterministic,
+        };
+        let plugins_provenance = Arc::new(get_plugins_provenance(&task_work.plugins));
         // resolve the plugins
         let mut plugins = Vec::with_capacity(task_work.plugins.len());
-        for plugin_wrapper in task_work.plugins {
+        let plugin_wrappers = if host.is_some() { Vec::new() } else { task_work.plugins };
+        for plugin_wrapper in plugin_wrappers {
           let result = match plugin_wrapper.get_or_create_checking_config_diagnostics(error_logger.clone()).await {
             Ok(result) => result,
+            Err(err) if allow_plugin_failures => {
+              environment.log_stderr(&format!(
+                "Skipping {} file(s) of plugin {}. Error creating plugin. Message: {}",
+                task_work.file_paths.len(),
+                plugin_wrapper.name(),
+                err
+              ));
+              for file_path in task_work.file_paths.iter() {
+                log_verbose!(environment, "Skipped: {}", file_path.display());
+              }
+              return;
+            }
             Err(err) => {
               error_logger.log_error(&format!("Error creating plugin {}. Message: {}", plugin_wrapper.name(), err));
+              if let Some(token) = &fail_fast_token {
+                token.cancel();
+              }
               return;
             }
           };
+          if let Some(stats) = &stats {
+            stats.record_plugin_startup(plugin_wrapper.name(), plugin_wrapper.is_process_plugin(), plugin_wrapper.startup_duration_ms());
+          }
           plugins.push(match result {
             GetPluginResult::HadDiagnostics => {
+              if let Some(token) = &fail_fast_token {
+                token.cancel();
+              }
               return;
             }
             GetPluginResult::Success(plugin) => plugin,
           })
         }
 
-        let plugins = Arc::new(plugins);
+        let formatter = match host {
+          Some(host) => FileFormatter::Host(host),
+          None => FileFormatter::Plugins(Arc::new(plugins)),
+        };
+
         let mut format_handles = Vec::with_capacity(task_work.file_paths.len());
         for file_path in task_work.file_paths.into_iter() {
           let permit = match task_work.semaphore.clone().acquire_owned().await {
             Ok(permit) => permit,
             Err(_) => return, // semaphore was closed, so stop working
           };
+          if fail_fast_token.as_ref().map(|t| t.is_cancelled()).unwrap_or(false) {
+            break; // failing fast, so don't format any more files
+          }
           let semaphore = task_work.semaphore.clone();
           let environment = environment.clone();
           let incremental_file = incremental_file.clone();
+          let stats = stats.clone();
+          let fail_fast_token = fail_fast_token.clone();
           let f = f.clone();
-          let plugins = plugins.clone();
+          let formatter = formatter.clone();
           let error_logger = error_logger.clone();
+          let budget = budget.clone();
+          let quarantined_files = quarantined_files.clone();
+          let generated_file_skipper = generated_file_skipper.clone();
+          let transforms = transforms.clone();
+          let plugins_provenance = plugins_provenance.clone();
           format_handles.push(tokio::task::spawn(async move {
             let long_format_token = CancellationToken::new();
             tokio::task::spawn({
@@ -140,7 +321,20 @@ where
                 }
               }
             });
-            let result = run_for_file_path(environment, incremental_file, plugins, file_path.clone(), ensure_stable_format, f).await;
+            let result = run_for_file_path(
+              environment,
+              incremental_file,
+              stats,
+              budget,
+              generated_file_skipper,
+              transforms,
+              formatter,
+              &plugins_provenance,
+              file_path.clone(),
+              ensure_stable_format,
+              f,
+            )
+            .await;
             long_format_token.cancel();
             if let Err(err) = result {
               if let Some(err) = err.downcast_ref::<CriticalFormatError>() {
@@ -149,10 +343,19 @@ where
                   file_path.display(),
                   err
                 ));
+                if let Some(quarantined_files) = &quarantined_files {
+                  quarantined_files.lock().push(QuarantinedFile {
+                    file_path: file_path.clone(),
+                    reason: format!("{:#}", err),
+                  });
+                }
                 semaphore.close(); // stop formatting
               } else {
                 error_logger.log_error(&format!("Error formatting {}. Message: {:#}", file_path.display(), err));
               }
+              if let Some(token) = &fail_fast_token {
+                token.cancel();
+              }
             }
             // drop the semaphore permit when we're all done
             drop(permit);
@@ -172,10 +375,16 @@ where
   };
 
   #[inline]
+  #[allow(clippy::too_many_arguments)]
   async fn run_for_file_path<F, TEnvironment: Environment>(
     environment: TEnvironment,
     incremental_file: Option<Arc<IncrementalFile<TEnvironment>>>,
-    plugins: Arc<Vec<Arc<dyn InitializedPlugin>>>,
+    stats: Option<Arc<RunStatsCollector>>,
+    budget: Option<Arc<RunBudget>>,
+    generated_file_skipper: Option<Arc<GeneratedFileSkipper>>,
+    transforms: Option<Arc<FileTextTransforms>>,
+    formatter: FileFormatter,
+    plugins_provenance: &str,
     file_path: PathBuf,
     ensure_stable_format: EnsureStableFormat,
     f: F,
@@ -185,20 +394,66 @@ where
   {
     let file_text = FileText::new(environment.read_file(&file_path)?);
 
+    if let Some(generated_file_skipper) = &generated_file_skipper {
+      if generated_file_skipper.should_skip(&file_path, file_text.as_str()) {
+        return Ok(());
+      }
+    }
+
     if let Some(incremental_file) = &incremental_file {
-      if incremental_file.is_file_known_formatted(file_text.as_str()) {
+      if incremental_file.is_file_known_formatted(&file_path, file_text.as_str()) {
         log_verbose!(environment, "No change: {}", file_path.display());
+        log_file_provenance(
+          &environment,
+          &file_path,
+          plugins_provenance,
+          FileProvenanceSource::Incremental,
+          Duration::ZERO,
+          file_text.as_str().len(),
+          file_text.as_str().len(),
+        );
+        if let Some(stats) = &stats {
+          stats.record_incremental_skip();
+        }
+        return Ok(());
+      }
+    }
+
+    if let Some(budget) = &budget {
+      if !budget.try_take_file() {
+        log_verbose!(environment, "Budget used up: {}", file_path.display());
         return Ok(());
       }
     }
 
-    let (start_instant, formatted_text) = run_single_pass_for_file_path(environment.clone(), plugins.clone(), file_path.clone(), file_text.as_str()).await?;
+    let text_to_format = match &transforms {
+      Some(transforms) => transforms.before_format(&file_path, file_text.as_str()),
+      None => Cow::Borrowed(file_text.as_str()),
+    };
+    let (start_instant, formatted_text) = run_single_pass_for_file_path(environment.clone(), &formatter, file_path.clone(), &text_to_format).await?;
 
-    let formatted_text = if ensure_stable_format.0 && formatted_text != file_text.as_str() {
-      get_stabilized_format_text(environment.clone(), plugins, file_path.clone(), formatted_text).await?
+    let formatted_text = if ensure_stable_format.0 && formatted_text != text_to_format.as_ref() {
+      get_stabilized_format_text(environment.clone(), &formatter, file_path.clone(), formatted_text).await?
     } else {
       formatted_text
     };
+    let formatted_text = match &transforms {
+      Some(transforms) => transforms.after_format(&file_path, formatted_text),
+      None => formatted_text,
+    };
+
+    if let Some(stats) = &stats {
+      stats.record_file(&file_path, start_instant.elapsed());
+    }
+    log_file_provenance(
+      &environment,
+      &file_path,
+      plugins_provenance,
+      FileProvenanceSource::Formatted,
+      start_instant.elapsed(),
+      file_text.as_str().len(),
+      formatted_text.len(),
+    );
 
     f(&file_path, file_text.as_str(), formatted_text, file_text.has_bom(), start_instant, &environment)?;
 
@@ -207,14 +462,14 @@ where
 
   async fn get_stabilized_format_text<TEnvironment: Environment>(
     environment: TEnvironment,
-    plugins: Arc<Vec<Arc<dyn InitializedPlugin>>>,
+    formatter: &FileFormatter,
     file_path: PathBuf,
     mut formatted_text: String,
   ) -> Result<String> {
     log_verbose!(environment, "Ensuring stable format: {}", file_path.display());
     let mut count = 0;
     loop {
-      match run_single_pass_for_file_path(environment.clone(), plugins.clone(), file_path.clone(), &formatted_text).await {
+      match run_single_pass_for_file_path(environment.clone(), formatter, file_path.clone(), &formatted_text).await {
         Ok((_, next_pass_text)) => {
           if next_pass_text == formatted_text {
             return Ok(formatted_text);
@@ -249,11 +504,33 @@ where
 
   async fn run_single_pass_for_file_path<TEnvironment: Environment>(
     environment: TEnvironment,
-    plugins: Arc<Vec<Arc<dyn InitializedPlugin>>>,
+    formatter: &FileFormatter,
     file_path: PathBuf,
     file_text: &str,
   ) -> Result<(Instant, String)> {
     let start_instant = Instant::now();
+    let plugins = match formatter {
+      FileFormatter::Plugins(plugins) => plugins,
+      FileFormatter::Host(host) => {
+        let format_text_result = host
+          .format(HostFormatRequest {
+            file_path: file_path.clone(),
+            file_text: file_text.to_string(),
+            range: None,
+            override_config: ConfigKeyMap::new(),
+            token: Arc::new(NullCancellationToken),
+          })
+          .await;
+        log_verbose!(
+          environment,
+          "Formatted file: {} in {}ms",
+          file_path.display(),
+          start_instant.elapsed().as_millis()
+        );
+        let formatted_text = format_text_result?.unwrap_or_else(|| file_text.to_string());
+        return Ok((start_instant, formatted_text));
+      }
+    };
     let mut file_text = Cow::Borrowed(file_text);
     let plugins_len = plugins.len();
     for (i, plugin) in plugins.iter().enumerate() {
@@ -286,11 +563,57 @@ where
   }
 }
 
+#[derive(Clone, Copy)]
+enum FileProvenanceSource {
+  /// The plugins formatted the file.
+  Formatted,
+  /// The incremental cache knew the file was formatted.
+  Incremental,
+}
+
+/// Gets the plugins that format a set of files along with the
+/// hashes of their configuration for the provenance of each file.
+fn get_plugins_provenance<TEnvironment: Environment>(plugins: &[Arc<PluginWrapper<TEnvironment>>]) -> String {
+  let names = plugins
+    .iter()
+    .map(|plugin| format!("{}@{}", plugin.name(), plugin.version()))
+    .collect::<Vec<_>>();
+  let config_hashes = plugins.iter().map(|plugin| format!("{:016x}", plugin.get_hash())).collect::<Vec<_>>();
+  format!("plugins={} config_hash={}", names.join(","), config_hashes.join(","))
+}
+
+/// Logs a single line with `--verbose` that explains how the formatted output
+/// of a file was produced, such as when debugging why a file changed.
+fn log_file_provenance(
+  environment: &impl Environment,
+  file_path: &Path,
+  plugins_provenance: &str,
+  source: FileProvenanceSource,
+  duration: Duration,
+  input_bytes: usize,
+  output_bytes: usize,
+) {
+  log_verbose!(
+    environment,
+    "Provenance: {} {} source={} duration_ms={} input_bytes={} output_bytes={}",
+    file_path.display(),
+    plugins_provenance,
+    match source {
+      FileProvenanceSource::Formatted => "formatted",
+      FileProvenanceSource::Incremental => "incremental",
+    },
+    duration.as_millis(),
+    input_bytes,
+    output_bytes,
+  );
+}
+
 /// Ensures all semaphores are released on drop
 /// so that other threads can do more work.
 struct SemaphorePermitReleaser {
   index: usize,
   semaphores: Arc<Mutex<Vec<StoredSemaphore>>>,
+  redistribute: bool,
 }
 
 impl Drop for SemaphorePermitReleaser {
@@ -298,6 +621,9 @@ impl Drop for SemaphorePermitReleaser {
     // release the permits to other semaphores so other tasks start doing more work
     let mut semaphores = self.semaphores.lock();
     semaphores[self.index].finished = true;
+    if !self.redistribute {
+      return;
+    }
     let permits = semaphores[self.index].permits;
     let mut remaining_semaphores = semaphores.iter_mut().filter(|s| !s.finished).collect::<Vec<_>>();
     // favour giving permits to tasks with less permits... this should more ideally
@@ -0,0 +1,72 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::configuration::ResolvedConfig;
+use crate::environment::Environment;
+use crate::utils::get_bytes_hash;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedFailedFiles {
+  file_paths: Vec<PathBuf>,
+}
+
+/// Gets the path of the file that stores which files weren't formatted in the
+/// last `dprint check`, which is keyed based on the root directory.
+pub fn get_failed_files_path(config: &ResolvedConfig, environment: &impl Environment) -> PathBuf {
+  let failed_files_dir = environment.get_cache_dir().join_panic_relative("check-failures");
+  failed_files_dir
+    .join_panic_relative(get_bytes_hash(config.base_path.to_string_lossy().as_bytes()).to_string())
+    .into_path_buf()
+}
+
+/// Reads the files that failed the last check or `None` when there was no previous check.
+pub fn read_failed_files(file_path: &Path, environment: &impl Environment) -> Option<Vec<PathBuf>> {
+  let file_text = environment.read_file(file_path).ok()?;
+  match serde_json::from_str::<SerializedFailedFiles>(&file_text) {
+    Ok(failed_files) => Some(failed_files.file_paths),
+    Err(err) => {
+      log_verbose!(environment, "Error deserializing failed files {}: {:#}", file_path.display(), err);
+      None
+    }
+  }
+}
+
+pub fn write_failed_files(file_path: &Path, mut file_paths: Vec<PathBuf>, environment: &impl Environment) {
+  file_paths.sort();
+  let failed_files = SerializedFailedFiles { file_paths };
+  let result = serde_json::to_string(&failed_files).map_err(anyhow::Error::from).and_then(|json_text| {
+    if let Some(parent) = file_path.parent() {
+      environment.mk_dir_all(parent)?;
+    }
+    environment.write_file(file_path, &json_text)
+  });
+  if let Err(err) = result {
+    log_verbose!(environment, "Error saving failed files {}: {:#}", file_path.display(), err);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::environment::TestEnvironment;
+
+  #[test]
+  fn should_read_and_write_failed_files() {
+    let environment = TestEnvironment::new();
+    let file_path = PathBuf::from("/cache/check-failures/1");
+    assert_eq!(read_failed_files(&file_path, &environment), None);
+    write_failed_files(&file_path, vec![PathBuf::from("/b.txt"), PathBuf::from("/a.txt")], &environment);
+    assert_eq!(
+      read_failed_files(&file_path, &environment),
+      Some(vec![PathBuf::from("/a.txt"), PathBuf::from("/b.txt")])
+    );
+    write_failed_files(&file_path, Vec::new(), &environment);
+    assert_eq!(read_failed_files(&file_path, &environment), Some(Vec::new()));
+  }
+}
@@ -1,77 +1,130 @@
 use parking_lot::Mutex;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::num::Wrapping;
 use std::path::Path;
+use std::sync::Arc;
 
 use crate::environment::CanonicalizedPathBuf;
 use crate::environment::Environment;
+use crate::plugins::PluginsCollection;
+use crate::transforms::FileTextTransforms;
 use crate::utils::get_bytes_hash;
+use crate::utils::get_file_path_key;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct IncrementalFileData {
-  plugins_hash: u64,
-  file_hashes: HashSet<u64>,
+  /// The hashes of the formatted files grouped by a hash of the plugins that
+  /// format them, so that when a plugin is updated or its configuration changes
+  /// only the files of that plugin need to be formatted again.
+  ///
+  /// Defaults for files written before the files were grouped, which are then
+  /// formatted again once.
+  #[serde(default)]
+  file_hashes_by_plugins: HashMap<u64, HashSet<u64>>,
 }
 
-impl IncrementalFileData {
-  pub fn new(plugins_hash: u64) -> IncrementalFileData {
-    IncrementalFileData {
-      plugins_hash,
-      file_hashes: Default::default(),
-    }
-  }
+/// What the hashes of the formatted files are based on.
+pub enum IncrementalFileKey {
+  /// Only the file text since the incremental file is specific to a directory.
+  FileText,
+  /// The file path relative to the provided base path and the file text, which
+  /// allows the results to be shared between different directories (ex. checkouts
+  /// of a repository on CI).
+  RelativePathAndFileText(CanonicalizedPathBuf),
 }
 
 pub struct IncrementalFile<TEnvironment: Environment> {
   file_path: CanonicalizedPathBuf,
+  key: IncrementalFileKey,
+  plugins: Arc<PluginsCollection<TEnvironment>>,
+  plugin_hashes: HashMap<String, u64>,
+  plugin_name_resolution_hash: u64,
+  transforms: Option<Arc<FileTextTransforms>>,
   read_data: IncrementalFileData,
   write_data: Mutex<IncrementalFileData>,
   environment: TEnvironment,
 }
 
 impl<TEnvironment: Environment> IncrementalFile<TEnvironment> {
-  pub fn new(file_path: CanonicalizedPathBuf, plugins_hash: u64, environment: TEnvironment) -> Self {
-    let read_data = read_incremental(&file_path, &environment);
-    let read_data = if let Some(read_data) = read_data {
-      if read_data.plugins_hash == plugins_hash {
-        read_data
-      } else {
-        log_verbose!(environment, "Plugins changed. Creating new incremental file.");
-        IncrementalFileData::new(plugins_hash)
-      }
-    } else {
-      IncrementalFileData::new(plugins_hash)
-    };
+  pub fn new(
+    file_path: CanonicalizedPathBuf,
+    plugins: Arc<PluginsCollection<TEnvironment>>,
+    transforms: Option<Arc<FileTextTransforms>>,
+    key: IncrementalFileKey,
+    environment: TEnvironment,
+  ) -> Self {
+    let read_data = read_incremental(&file_path, &environment).unwrap_or_default();
+    // store these up front because getting the hash of a plugin serializes its configuration
+    let plugin_hashes = plugins.get_plugin_hashes();
+    let plugin_name_resolution_hash = plugins.get_plugin_name_resolution_hash();
     IncrementalFile {
       file_path,
+      key,
+      plugins,
+      plugin_hashes,
+      plugin_name_resolution_hash,
+      transforms,
       read_data,
-      write_data: Mutex::new(IncrementalFileData::new(plugins_hash)),
+      write_data: Default::default(),
       environment,
     }
   }
 
   /// If the file text is known to be formatted.
-  pub fn is_file_known_formatted(&self, file_text: &str) -> bool {
-    let hash = get_bytes_hash(file_text.as_bytes());
-    if self.read_data.file_hashes.contains(&hash) {
+  pub fn is_file_known_formatted(&self, file_path: &Path, file_text: &str) -> bool {
+    let plugins_hash = self.get_plugins_hash(file_path);
+    let hash = self.get_hash(file_path, file_text);
+    let is_known_formatted = match self.read_data.file_hashes_by_plugins.get(&plugins_hash) {
+      Some(file_hashes) => file_hashes.contains(&hash),
+      None => false,
+    };
+    if is_known_formatted {
       // the file is the same, so save it in the write data
-      self.add_to_write_data(hash);
-      true
-    } else {
-      false
+      self.add_to_write_data(plugins_hash, hash);
     }
+    is_known_formatted
+  }
+
+  pub fn update_file(&self, file_path: &Path, file_text: &str) {
+    let plugins_hash = self.get_plugins_hash(file_path);
+    let hash = self.get_hash(file_path, file_text);
+    self.add_to_write_data(plugins_hash, hash)
   }
 
-  pub fn update_file(&self, file_text: &str) {
-    let hash = get_bytes_hash(file_text.as_bytes());
-    self.add_to_write_data(hash)
+  /// Gets a hash of the plugins and transforms that format the file.
+  fn get_plugins_hash(&self, file_path: &Path) -> u64 {
+    // adding hashes isn't right, but it's done for the same reason as PluginsCollection::get_plugins_hash
+    let mut hash_sum = Wrapping(self.plugin_name_resolution_hash);
+    for plugin_name in self.plugins.get_plugin_names_from_file_path(file_path) {
+      hash_sum += Wrapping(self.plugin_hashes.get(&plugin_name).copied().unwrap_or_default());
+    }
+    if let Some(transforms) = &self.transforms {
+      hash_sum += Wrapping(transforms.get_hash(file_path));
+    }
+    hash_sum.0
+  }
+
+  fn get_hash(&self, file_path: &Path, file_text: &str) -> u64 {
+    match &self.key {
+      IncrementalFileKey::FileText => get_bytes_hash(file_text.as_bytes()),
+      IncrementalFileKey::RelativePathAndFileText(base_path) => {
+        let relative_path = file_path.strip_prefix(base_path).unwrap_or(file_path);
+        // keep the hash the same after a case-only rename on case-insensitive file systems
+        let mut bytes = get_file_path_key(relative_path).to_string_lossy().replace('\\', "/").into_bytes();
+        bytes.push(0);
+        bytes.extend(file_text.as_bytes());
+        get_bytes_hash(&bytes)
+      }
+    }
   }
 
-  fn add_to_write_data(&self, hash: u64) {
+  fn add_to_write_data(&self, plugins_hash: u64, hash: u64) {
     let mut write_data = self.write_data.lock();
-    write_data.file_hashes.insert(hash);
+    write_data.file_hashes_by_plugins.entry(plugins_hash).or_default().insert(hash);
   }
 
   pub fn write(&self) {
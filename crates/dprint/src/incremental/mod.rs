@@ -1,18 +1,25 @@
+mod failed_files;
 mod incremental_file;
+mod quarantine;
 
+pub use failed_files::*;
 pub use incremental_file::IncrementalFile;
+pub use incremental_file::IncrementalFileKey;
+pub use quarantine::*;
 
 use std::sync::Arc;
 
 use crate::configuration::ResolvedConfig;
 use crate::environment::Environment;
 use crate::plugins::PluginsCollection;
+use crate::transforms::FileTextTransforms;
 use crate::utils::get_bytes_hash;
 
 pub fn get_incremental_file<TEnvironment: Environment>(
   incremental_cli_arg: Option<bool>,
   config: &ResolvedConfig,
-  plugin_pools: &PluginsCollection<TEnvironment>,
+  plugin_pools: &Arc<PluginsCollection<TEnvironment>>,
+  transforms: &Option<Arc<FileTextTransforms>>,
   environment: &TEnvironment,
 ) -> Option<Arc<IncrementalFile<TEnvironment>>> {
   if let Some(incremental_arg) = incremental_cli_arg.or(config.incremental) {
@@ -29,5 +36,37 @@ pub fn get_incremental_file<TEnvironment: Environment>(
 
   let base_path = config.base_path.clone();
   let file_path = incremental_dir.join_panic_relative(get_bytes_hash(base_path.to_string_lossy().as_bytes()).to_string());
-  Some(Arc::new(IncrementalFile::new(file_path, plugin_pools.get_plugins_hash(), environment.clone())))
+  Some(Arc::new(IncrementalFile::new(
+    file_path,
+    plugin_pools.clone(),
+    transforms.clone(),
+    IncrementalFileKey::FileText,
+    environment.clone(),
+  )))
+}
+
+/// Gets the file that stores which files were verified formatted by `dprint check`.
+///
+/// Unlike the incremental file, this is keyed on the plugins and their configuration
+/// rather than the directory, so it's shared between different directories.
+pub fn get_check_results_file<TEnvironment: Environment>(
+  config: &ResolvedConfig,
+  plugin_pools: &Arc<PluginsCollection<TEnvironment>>,
+  transforms: &Option<Arc<FileTextTransforms>>,
+  environment: &TEnvironment,
+) -> Option<Arc<IncrementalFile<TEnvironment>>> {
+  let check_results_dir = environment.get_cache_dir().join_panic_relative("check-results");
+  if environment.mk_dir_all(&check_results_dir).is_err() {
+    return None;
+  }
+
+  let plugins_hash = plugin_pools.get_plugins_hash();
+  let file_path = check_results_dir.join_panic_relative(plugins_hash.to_string());
+  Some(Arc::new(IncrementalFile::new(
+    file_path,
+    plugin_pools.clone(),
+    transforms.clone(),
+    IncrementalFileKey::RelativePathAndFileText(config.base_path.clone()),
+    environment.clone(),
+  )))
 }
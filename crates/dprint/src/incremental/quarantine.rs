@@ -0,0 +1,91 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::configuration::ResolvedConfig;
+use crate::environment::Environment;
+use crate::utils::get_bytes_hash;
+
+/// A file that caused a critical error in a plugin (ex. a panic), which is
+/// skipped in later runs until it's removed from the quarantine.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantinedFile {
+  pub file_path: PathBuf,
+  /// The error message of the critical error.
+  pub reason: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedQuarantine {
+  files: Vec<QuarantinedFile>,
+}
+
+/// Gets the path of the file that stores the quarantined files, which is
+/// keyed based on the root directory.
+pub fn get_quarantine_path(config: &ResolvedConfig, environment: &impl Environment) -> PathBuf {
+  let quarantine_dir = environment.get_cache_dir().join_panic_relative("quarantine");
+  quarantine_dir
+    .join_panic_relative(get_bytes_hash(config.base_path.to_string_lossy().as_bytes()).to_string())
+    .into_path_buf()
+}
+
+pub fn read_quarantine(file_path: &Path, environment: &impl Environment) -> Vec<QuarantinedFile> {
+  let file_text = match environment.read_file(file_path) {
+    Ok(file_text) => file_text,
+    Err(_) => return Vec::new(),
+  };
+  match serde_json::from_str::<SerializedQuarantine>(&file_text) {
+    Ok(quarantine) => quarantine.files,
+    Err(err) => {
+      log_verbose!(environment, "Error deserializing quarantine {}: {:#}", file_path.display(), err);
+      Vec::new()
+    }
+  }
+}
+
+pub fn write_quarantine(file_path: &Path, mut files: Vec<QuarantinedFile>, environment: &impl Environment) {
+  files.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+  files.dedup_by(|a, b| a.file_path == b.file_path);
+  let quarantine = SerializedQuarantine { files };
+  let result = serde_json::to_string(&quarantine).map_err(anyhow::Error::from).and_then(|json_text| {
+    if let Some(parent) = file_path.parent() {
+      environment.mk_dir_all(parent)?;
+    }
+    environment.write_file(file_path, &json_text)
+  });
+  if let Err(err) = result {
+    log_verbose!(environment, "Error saving quarantine {}: {:#}", file_path.display(), err);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::environment::TestEnvironment;
+
+  #[test]
+  fn should_read_and_write_quarantine() {
+    let environment = TestEnvironment::new();
+    let file_path = PathBuf::from("/cache/quarantine/1");
+    assert_eq!(read_quarantine(&file_path, &environment), Vec::new());
+    let file = |path: &str, reason: &str| QuarantinedFile {
+      file_path: PathBuf::from(path),
+      reason: reason.to_string(),
+    };
+    write_quarantine(
+      &file_path,
+      vec![file("/b.txt", "Panicked."), file("/a.txt", "Panicked."), file("/b.txt", "Panicked again.")],
+      &environment,
+    );
+    assert_eq!(
+      read_quarantine(&file_path, &environment),
+      vec![file("/a.txt", "Panicked."), file("/b.txt", "Panicked.")]
+    );
+  }
+}
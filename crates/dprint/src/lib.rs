@@ -0,0 +1,40 @@
+//! The dprint CLI as a library.
+//!
+//! Embedders may run the CLI against virtual files by providing a
+//! [`MemoryEnvironment`] instead of the [`RealEnvironment`], which
+//! keeps the files, logs, and downloads in memory.
+
+#[macro_use]
+mod environment;
+
+mod arg_parser;
+mod commands;
+mod configuration;
+mod file_selector;
+mod format;
+mod incremental;
+mod paths;
+mod patterns;
+mod plugins;
+mod run_cli;
+mod stats;
+mod transforms;
+mod utils;
+mod workspace;
+
+#[cfg(test)]
+mod test_helpers;
+
+pub use arg_parser::parse_args;
+pub use arg_parser::CliArgs;
+pub use environment::Environment;
+pub use environment::RealEnvironment;
+pub use environment::RealEnvironmentOptions;
+pub use environment::TestEnvironment as MemoryEnvironment;
+pub use plugins::get_assume_remote_plugins_trusted;
+pub use plugins::PluginCache;
+pub use plugins::PluginResolver;
+pub use plugins::PluginsCollection;
+pub use run_cli::run_cli;
+pub use utils::RealStdInReader;
+pub use utils::StdInReader;
@@ -1,26 +1,9 @@
-#[macro_use]
-mod environment;
-
 use anyhow::Result;
+use dprint::RealEnvironment;
+use dprint::RealEnvironmentOptions;
+use dprint::RealStdInReader;
 use dprint_core::plugins::process::setup_exit_process_panic_hook;
-use environment::RealEnvironment;
-use environment::RealEnvironmentOptions;
 use std::sync::Arc;
-use utils::RealStdInReader;
-
-mod arg_parser;
-mod commands;
-mod configuration;
-mod format;
-mod incremental;
-mod paths;
-mod patterns;
-mod plugins;
-mod run_cli;
-mod utils;
-
-#[cfg(test)]
-mod test_helpers;
 
 fn main() {
   setup_exit_process_panic_hook();
@@ -38,17 +21,22 @@ fn main() {
 }
 
 async fn run(runtime_handle: tokio::runtime::Handle) -> Result<()> {
-  let args = arg_parser::parse_args(std::env::args().collect(), RealStdInReader)?;
+  let args = dprint::parse_args(std::env::args().collect(), RealStdInReader)?;
   let environment = RealEnvironment::new(RealEnvironmentOptions {
     is_verbose: args.verbose,
     is_stdout_machine_readable: args.is_stdout_machine_readable(),
+    assert_no_writes: args.assert_no_writes,
+    cache_overlay: args.cache_overlay,
     runtime_handle: Arc::new(runtime_handle),
   })?;
-  let plugin_cache = Arc::new(plugins::PluginCache::new(environment.clone()));
-  let plugin_pools = Arc::new(plugins::PluginsCollection::new(environment.clone()));
-  let plugin_resolver = plugins::PluginResolver::new(environment.clone(), plugin_cache, plugin_pools.clone());
+  let mut plugin_cache = dprint::PluginCache::new(environment.clone());
+  plugin_cache.set_assume_trusted(dprint::get_assume_remote_plugins_trusted(args.yes, &environment));
+  let plugin_pools = Arc::new(dprint::PluginsCollection::new(environment.clone()));
+  let plugin_resolver = dprint::PluginResolver::new(environment.clone(), Arc::new(plugin_cache), plugin_pools.clone());
 
-  let result = run_cli::run_cli(&args, &environment, &plugin_resolver, plugin_pools.clone()).await;
+  let result = dprint::run_cli(&args, &environment, &plugin_resolver, plugin_pools.clone()).await;
   plugin_pools.drop_and_shutdown_initialized().await;
-  result
+  let overlay_result = environment.finish_cache_overlay();
+  result?;
+  overlay_result
 }
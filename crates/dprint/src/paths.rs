@@ -1,21 +1,36 @@
 use anyhow::bail;
 use anyhow::Result;
+use indexmap::IndexMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
 use std::path::PathBuf;
 use std::str::Split;
 
+use crate::arg_parser::AssociationArg;
 use crate::arg_parser::FilePatternArgs;
+use crate::arg_parser::GitFileSelection;
+use crate::configuration::ConfigOverride;
 use crate::configuration::ResolvedConfig;
 use crate::environment::CanonicalizedPathBuf;
 use crate::environment::Environment;
+use crate::file_selector::select_file_paths;
 use crate::patterns::get_all_file_patterns;
 use crate::plugins::Plugin;
 use crate::plugins::PluginNameResolutionMaps;
+use crate::utils::get_file_path_key;
+use crate::utils::get_patterns_differing_by_case;
 use crate::utils::glob;
+use crate::utils::is_case_insensitive_file_system;
+use crate::utils::GlobMatcher;
+use crate::utils::GlobMatcherOptions;
+use crate::utils::GlobOptions;
+use crate::utils::GlobPatterns;
+use crate::workspace::WorkspaceGraph;
 
 /// Struct that allows using plugin names as a key
 /// in a hash map.
-#[derive(Debug, Eq, PartialEq, Hash)]
+#[derive(Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub struct PluginNames(String);
 
 impl PluginNames {
@@ -34,8 +49,10 @@ pub fn get_file_paths_by_plugins_and_err_if_empty(
   plugins: &[Box<dyn Plugin>],
   file_paths: Vec<PathBuf>,
   config_base_path: &CanonicalizedPathBuf,
+  overrides: &IndexMap<String, ConfigOverride>,
+  associations: &[AssociationArg],
 ) -> Result<HashMap<PluginNames, Vec<PathBuf>>> {
-  let result = get_file_paths_by_plugins(plugins, file_paths, config_base_path)?;
+  let result = get_file_paths_by_plugins(plugins, file_paths, config_base_path, overrides, associations)?;
   if result.is_empty() {
     bail!("No files found to format with the specified plugins. You may want to try using `dprint output-file-paths` to see which files it's finding.");
   }
@@ -46,8 +63,10 @@ pub fn get_file_paths_by_plugins(
   plugins: &[Box<dyn Plugin>],
   file_paths: Vec<PathBuf>,
   config_base_path: &CanonicalizedPathBuf,
+  overrides: &IndexMap<String, ConfigOverride>,
+  associations: &[AssociationArg],
 ) -> Result<HashMap<PluginNames, Vec<PathBuf>>> {
-  let plugin_name_maps = PluginNameResolutionMaps::from_plugins(plugins, config_base_path)?;
+  let plugin_name_maps = PluginNameResolutionMaps::from_plugins(plugins, config_base_path, overrides, associations)?;
 
   let mut file_paths_by_plugin: HashMap<PluginNames, Vec<PathBuf>> = HashMap::new();
 
@@ -64,14 +83,252 @@ pub fn get_file_paths_by_plugins(
   Ok(file_paths_by_plugin)
 }
 
+/// Orders the file paths so the files that changed in git are first.
+pub fn prioritize_changed_file_paths(
+  file_paths_by_plugins: &mut HashMap<PluginNames, Vec<PathBuf>>,
+  dir_path: &CanonicalizedPathBuf,
+  environment: &impl Environment,
+) {
+  let changed_file_paths = get_git_changed_file_paths(dir_path, environment);
+  if changed_file_paths.is_empty() {
+    return;
+  }
+  for file_paths in file_paths_by_plugins.values_mut() {
+    // stable sort, so the remaining order is kept
+    file_paths.sort_by_key(|file_path| !changed_file_paths.contains(&get_file_path_key(file_path)));
+  }
+}
+
+/// Gets the files in the directory that changed in git compared to `HEAD` along
+/// with the untracked files that aren't ignored.
+///
+/// This is empty when the directory isn't in a git repository.
+fn get_git_changed_file_paths(dir_path: &CanonicalizedPathBuf, environment: &impl Environment) -> HashSet<PathBuf> {
+  let dir_text = dir_path.to_string_lossy();
+  let results = [
+    environment.run_command("git", &["-C", &dir_text, "diff", "--name-only", "--relative", "-z", "HEAD"]),
+    environment.run_command("git", &["-C", &dir_text, "ls-files", "--others", "--exclude-standard", "-z"]),
+  ];
+  let mut file_paths = HashSet::new();
+  for result in results {
+    match result {
+      Ok(stdout) => file_paths.extend(parse_git_file_paths(dir_path, &stdout).map(|file_path| get_file_path_key(&file_path))),
+      Err(err) => {
+        log_verbose!(environment, "Could not get the changed files from git. {:#}", err);
+        return HashSet::new();
+      }
+    }
+  }
+  file_paths
+}
+
+/// Gets the files in the directory that are staged in git, but also have unstaged changes.
+pub fn get_git_partially_staged_file_paths(dir_path: &CanonicalizedPathBuf, environment: &impl Environment) -> Result<HashSet<PathBuf>> {
+  let dir_text = dir_path.to_string_lossy();
+  let unstaged_file_paths = match environment.run_command("git", &["-C", &dir_text, "diff", "--name-only", "--relative", "-z"]) {
+    Ok(stdout) => parse_git_file_paths(dir_path, &stdout).collect::<HashSet<_>>(),
+    Err(err) => bail!("Could not get the unstaged files from git. {:#}", err),
+  };
+  let staged_file_paths = get_git_selected_file_paths(&GitFileSelection::Staged, dir_path, environment)?;
+  Ok(
+    staged_file_paths
+      .into_iter()
+      .filter(|file_path| unstaged_file_paths.contains(file_path))
+      .collect(),
+  )
+}
+
+/// Stages the files in git again after they were formatted.
+pub fn restage_git_file_paths(dir_path: &CanonicalizedPathBuf, file_paths: &[PathBuf], environment: &impl Environment) -> Result<()> {
+  if file_paths.is_empty() {
+    return Ok(());
+  }
+  let dir_text = dir_path.to_string_lossy();
+  let file_path_texts = file_paths.iter().map(|file_path| file_path.to_string_lossy()).collect::<Vec<_>>();
+  let mut args: Vec<&str> = vec!["-C", &dir_text, "add", "--"];
+  args.extend(file_path_texts.iter().map(|text| &**text));
+  match environment.run_command("git", &args) {
+    Ok(_) => Ok(()),
+    Err(err) => bail!("Could not stage the formatted files in git. The files were formatted. {:#}", err),
+  }
+}
+
+/// Gets the files in the directory selected via `--staged` or `--changed-since`,
+/// excluding the deleted files.
+fn get_git_selected_file_paths(selection: &GitFileSelection, dir_path: &CanonicalizedPathBuf, environment: &impl Environment) -> Result<Vec<PathBuf>> {
+  let dir_text = dir_path.to_string_lossy();
+  let stdout = match selection {
+    GitFileSelection::Staged => {
+      match environment.run_command(
+        "git",
+        &["-C", &dir_text, "diff", "--cached", "--name-only", "--relative", "--diff-filter=d", "-z"],
+      ) {
+        Ok(stdout) => stdout,
+        Err(err) => bail!("Could not get the staged files from git. {:#}", err),
+      }
+    }
+    GitFileSelection::ChangedSince(git_ref) => {
+      let result = environment
+        .run_command("git", &["-C", &dir_text, "merge-base", git_ref, "HEAD"])
+        .and_then(|merge_base| {
+          environment.run_command(
+            "git",
+            &["-C", &dir_text, "diff", "--name-only", "--relative", "--diff-filter=d", "-z", merge_base.trim()],
+          )
+        });
+      match result {
+        Ok(stdout) => stdout,
+        Err(err) => bail!("Could not get the files changed since '{}' from git. {:#}", git_ref, err),
+      }
+    }
+  };
+  Ok(parse_git_file_paths(dir_path, &stdout).collect())
+}
+
+/// Parses the null separated file paths output by git relative to the directory.
+fn parse_git_file_paths<'a>(dir_path: &'a CanonicalizedPathBuf, stdout: &'a str) -> impl Iterator<Item = PathBuf> + 'a {
+  stdout.split('\0').filter(|line| !line.is_empty()).map(|line| dir_path.join(line))
+}
+
+/// Gets the files selected from git that match the file patterns without globbing the file system.
+fn filter_git_selected_file_paths(
+  environment: &impl Environment,
+  base_dir: &CanonicalizedPathBuf,
+  file_patterns: GlobPatterns,
+  selection: &GitFileSelection,
+) -> Result<Vec<PathBuf>> {
+  let glob_matcher = GlobMatcher::new(
+    file_patterns,
+    &GlobMatcherOptions {
+      case_sensitive: !is_case_insensitive_file_system(),
+    },
+  )?;
+  let mut file_paths = get_git_selected_file_paths(selection, base_dir, environment)?;
+  file_paths.retain(|file_path| glob_matcher.matches_and_dir_not_ignored(file_path) && environment.path_exists(file_path));
+  log_verbose!(environment, "File(s) selected from git: {:?}", file_paths);
+  Ok(file_paths)
+}
+
 pub async fn get_and_resolve_file_paths(config: &ResolvedConfig, args: &FilePatternArgs, environment: &impl Environment) -> Result<Vec<PathBuf>> {
+  warn_patterns_differing_by_case(config, environment);
   let cwd = environment.cwd();
   let file_patterns = get_all_file_patterns(config, args, &cwd);
   let is_in_sub_dir = cwd != config.base_path && cwd.starts_with(&config.base_path);
   let base_dir = if is_in_sub_dir { cwd } else { config.base_path.clone() };
   let environment = environment.clone();
+  let affected_by = args.affected_by.clone();
+  let git_selection = args.git_selection.clone();
+  let config_base_path = config.base_path.clone();
+  let file_selector = config.file_selector.clone();
+  let glob_options = GlobOptions {
+    skip_build_artifacts: config.skip_build_artifacts,
+  };
+  let excluded_file_paths = args.excluded_file_paths.iter().cloned().collect::<HashSet<_>>();
 
   // This is intensive so do it in a blocking task
   // Eventually this could should maybe be changed to use tokio tasks
-  tokio::task::spawn_blocking(move || glob(&environment, &base_dir, file_patterns)).await.unwrap()
+  tokio::task::spawn_blocking(move || {
+    let mut file_paths = match (git_selection, affected_by) {
+      (Some(git_selection), _) => filter_git_selected_file_paths(&environment, &base_dir, file_patterns, &git_selection),
+      (None, Some(changed_files)) => glob_affected_packages(&environment, &config_base_path, &base_dir, file_patterns, &glob_options, &changed_files),
+      (None, None) => glob(&environment, &base_dir, file_patterns, &glob_options),
+    }?;
+    if !excluded_file_paths.is_empty() {
+      file_paths.retain(|file_path| !excluded_file_paths.contains(file_path));
+    }
+    match &file_selector {
+      Some(file_selector) => select_file_paths(file_selector, &config_base_path, file_paths, &environment),
+      None => Ok(file_paths),
+    }
+  })
+  .await
+  .unwrap()
+}
+
+fn warn_patterns_differing_by_case(config: &ResolvedConfig, environment: &impl Environment) {
+  for (property_name, patterns) in [("includes", &config.includes), ("excludes", &config.excludes)] {
+    for (pattern, other_pattern) in get_patterns_differing_by_case(patterns) {
+      environment.log_stderr(&format!(
+        "WARNING: The patterns '{}' and '{}' in the '{}' property only differ by case, so they match the same files on case-insensitive file systems.",
+        pattern, other_pattern, property_name,
+      ));
+    }
+  }
+}
+
+/// Globs only the directories of the workspace packages affected by the changed files.
+fn glob_affected_packages(
+  environment: &impl Environment,
+  config_base_path: &CanonicalizedPathBuf,
+  base_dir: &CanonicalizedPathBuf,
+  file_patterns: GlobPatterns,
+  glob_options: &GlobOptions,
+  changed_files: &[String],
+) -> Result<Vec<PathBuf>> {
+  let graph = WorkspaceGraph::discover(config_base_path, environment)?;
+  if graph.packages().is_empty() {
+    bail!(
+      "Could not find any workspace packages in {}. Only package.json workspaces and Cargo workspaces are supported.",
+      config_base_path.display()
+    );
+  }
+
+  let cwd = environment.cwd();
+  let mut changed_files = changed_files.iter().map(|file_path| cwd.join(file_path)).collect::<Vec<_>>();
+  // In a sparse checkout, the changed files may exist in the git history, but not
+  // on disk. A deleted file will still have its directory, so only skip a changed
+  // file when its directory isn't on disk either.
+  changed_files.retain(|file_path| {
+    let is_on_disk = environment.path_exists(file_path) || file_path.parent().map(|dir| is_non_empty_dir(environment, dir)).unwrap_or(false);
+    if !is_on_disk {
+      log_verbose!(environment, "Skipping changed file not on disk (ex. sparse checkout): {}", file_path.display());
+    }
+    is_on_disk
+  });
+  for file_path in changed_files.iter() {
+    if graph.get_owning_package(file_path).is_none() {
+      log_verbose!(environment, "Changed file not in a workspace package: {}", file_path.display());
+    }
+  }
+  let affected_packages = graph.get_affected_packages(&changed_files);
+  log_verbose!(
+    environment,
+    "Affected packages: {}",
+    affected_packages.iter().map(|p| p.name.as_str()).collect::<Vec<_>>().join(", ")
+  );
+
+  // only glob the top-most directories within the base directory
+  let mut glob_dirs: Vec<&Path> = Vec::new();
+  for package in affected_packages.iter() {
+    let dir_path = if package.dir_path.starts_with(base_dir) {
+      package.dir_path.as_path()
+    } else if base_dir.as_ref().starts_with(&package.dir_path) {
+      base_dir.as_ref()
+    } else {
+      continue;
+    };
+    if !glob_dirs.iter().any(|d| dir_path.starts_with(d)) {
+      glob_dirs.retain(|d| !d.starts_with(dir_path));
+      glob_dirs.push(dir_path);
+    }
+  }
+
+  let mut file_paths = Vec::new();
+  for dir_path in glob_dirs {
+    for file_path in glob(environment, dir_path, file_patterns.clone(), glob_options)? {
+      // exclude files of nested packages that weren't affected
+      let is_affected = match graph.get_owning_package(&file_path) {
+        Some(owner) => affected_packages.iter().any(|p| p.dir_path == owner.dir_path),
+        None => false,
+      };
+      if is_affected {
+        file_paths.push(file_path);
+      }
+    }
+  }
+  Ok(file_paths)
+}
+
+fn is_non_empty_dir(environment: &impl Environment, dir_path: &Path) -> bool {
+  environment.dir_info(dir_path).map(|entries| !entries.is_empty()).unwrap_or(false)
 }
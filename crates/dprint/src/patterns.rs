@@ -7,6 +7,7 @@ use crate::configuration::ResolvedConfig;
 use crate::environment::CanonicalizedPathBuf;
 use crate::environment::Environment;
 use crate::utils::is_absolute_pattern;
+use crate::utils::is_case_insensitive_file_system;
 use crate::utils::is_negated_glob;
 use crate::utils::GlobMatcher;
 use crate::utils::GlobMatcherOptions;
@@ -24,7 +25,7 @@ impl FileMatcher {
     let glob_matcher = GlobMatcher::new(
       patterns,
       &GlobMatcherOptions {
-        case_sensitive: !cfg!(windows),
+        case_sensitive: !is_case_insensitive_file_system(),
       },
     )?;
 
@@ -52,7 +53,7 @@ pub fn get_patterns_as_glob_matcher(patterns: &[String], config_base_path: &Cano
       excludes: GlobPattern::new_vec(excludes, config_base_path.clone()),
     },
     &GlobMatcherOptions {
-      case_sensitive: !cfg!(windows),
+      case_sensitive: !is_case_insensitive_file_system(),
     },
   )
 }
@@ -1,10 +1,15 @@
 use anyhow::bail;
 use anyhow::Result;
+use parking_lot::Mutex;
 use parking_lot::RwLock;
 use std::path::PathBuf;
 
 use dprint_core::plugins::PluginInfo;
 
+use super::append_provenance_log_entry;
+use super::ensure_downloaded_plugin_trusted;
+use super::ensure_remote_plugins_trusted;
+use super::get_provenance_log_user;
 use super::implementations::cleanup_plugin;
 use super::implementations::get_file_path_from_plugin_info;
 use super::implementations::setup_plugin;
@@ -12,6 +17,7 @@ use super::read_manifest;
 use super::write_manifest;
 use super::PluginCacheManifest;
 use super::PluginCacheManifestItem;
+use super::ProvenanceLogEntry;
 use crate::environment::Environment;
 use crate::plugins::PluginSourceReference;
 use crate::utils::get_bytes_hash;
@@ -28,6 +34,9 @@ pub struct PluginCacheItem {
 pub struct PluginCache<TEnvironment: Environment> {
   environment: TEnvironment,
   manifest: RwLock<PluginCacheManifest>,
+  compiled_wasm_plugin_names: Mutex<Vec<String>>,
+  assume_trusted: bool,
+  trust_lock: Mutex<()>,
 }
 
 impl<TEnvironment> PluginCache<TEnvironment>
@@ -36,7 +45,42 @@ where
 {
   pub fn new(environment: TEnvironment) -> Self {
     let manifest = RwLock::new(read_manifest(&environment));
-    PluginCache { environment, manifest }
+    PluginCache {
+      environment,
+      manifest,
+      compiled_wasm_plugin_names: Default::default(),
+      assume_trusted: false,
+      trust_lock: Default::default(),
+    }
+  }
+
+  /// Trusts the remote plugins without prompting (`--yes` or `DPRINT_TRUST_REMOTE_PLUGINS=1`).
+  pub fn set_assume_trusted(&mut self, value: bool) {
+    self.assume_trusted = value;
+  }
+
+  /// Ensures the user trusts the remote plugins that aren't in the cache
+  /// before they're downloaded and run for the first time.
+  pub fn ensure_trusted(&self, source_references: &[PluginSourceReference]) -> Result<()> {
+    // only prompt once at a time
+    let _trust_lock = self.trust_lock.lock();
+    let mut uncached_references = Vec::new();
+    {
+      let manifest = self.manifest.read();
+      for source_reference in source_references {
+        if let PathSource::Remote(_) = &source_reference.path_source {
+          if manifest.get_item(&self.get_cache_key(&source_reference.path_source)?).is_none() {
+            uncached_references.push(source_reference);
+          }
+        }
+      }
+    }
+    ensure_remote_plugins_trusted(&uncached_references, self.assume_trusted, &self.environment)
+  }
+
+  /// Takes the names of the Wasm plugins that were compiled since this was last called.
+  pub fn take_compiled_wasm_plugin_names(&self) -> Vec<String> {
+    std::mem::take(&mut *self.compiled_wasm_plugin_names.lock())
   }
 
   pub fn forget(&self, source_reference: &PluginSourceReference) -> Result<()> {
@@ -88,6 +132,10 @@ where
         } else {
           self.forget(&source_reference)?;
         }
+      } else if source_reference.checksum.is_some() && source_reference.checksum != cache_item.checksum {
+        // the pinned checksum changed or the plugin was cached before it was
+        // pinned, so download it again in order to verify it
+        self.forget(&source_reference)?;
       } else {
         return Ok(PluginCacheItem {
           file_path,
@@ -119,10 +167,20 @@ where
       );
     }
 
+    if let PathSource::Remote(remote) = &source_reference.path_source {
+      // only prompt once at a time
+      let _trust_lock = self.trust_lock.lock();
+      ensure_downloaded_plugin_trusted(&remote.url, &get_sha256_checksum(&file_bytes), self.assume_trusted, &self.environment)?;
+    }
+
     let setup_result = setup_plugin(&source_reference.path_source, &file_bytes, &self.environment).await?;
+    if source_reference.plugin_kind() == Some(PluginKind::Wasm) {
+      self.compiled_wasm_plugin_names.lock().push(setup_result.plugin_info.name.clone());
+    }
     let cache_item = PluginCacheManifestItem {
       info: setup_result.plugin_info.clone(),
       file_hash: if check_file_hash { Some(get_bytes_hash(&file_bytes)) } else { None },
+      checksum: source_reference.checksum.clone(),
       created_time: self.environment.get_time_secs(),
     };
 
@@ -130,6 +188,18 @@ where
     manifest.add_item(cache_key, cache_item);
     write_manifest(&manifest, &self.environment)?;
 
+    append_provenance_log_entry(
+      &ProvenanceLogEntry {
+        time: self.environment.get_time_secs(),
+        user: get_provenance_log_user(&self.environment),
+        source: source_reference.path_source.display(),
+        checksum: get_sha256_checksum(&file_bytes),
+        plugin_name: setup_result.plugin_info.name.clone(),
+        plugin_version: setup_result.plugin_info.version.clone(),
+      },
+      &self.environment,
+    )?;
+
     Ok(PluginCacheItem {
       file_path: setup_result.file_path,
       info: setup_result.plugin_info,
@@ -159,6 +229,7 @@ fn get_file_bytes<TEnvironment: Environment>(path_source: PathSource, environmen
 mod test {
   use super::*;
   use crate::environment::TestEnvironment;
+  use crate::plugins::read_provenance_log;
   use crate::plugins::CompilationResult;
   use crate::plugins::PluginSourceReference;
   use anyhow::Result;
@@ -173,7 +244,8 @@ mod test {
     environment.set_wasm_compile_result(create_compilation_result("t".as_bytes()));
     environment.set_cpu_arch("aarch64");
 
-    let plugin_cache = PluginCache::new(environment.clone());
+    let mut plugin_cache = PluginCache::new(environment.clone());
+    plugin_cache.set_assume_trusted(true);
     let plugin_source = PluginSourceReference::new_remote_from_str("https://plugins.dprint.dev/test.wasm");
     let file_path = plugin_cache.get_plugin_cache_item(&plugin_source).await?.file_path;
     let expected_file_path = PathBuf::from("/cache").join("plugins").join("test-plugin").join("0.1.0-2.3.0-aarch64");
@@ -185,10 +257,23 @@ mod test {
     let file_path = plugin_cache.get_plugin_cache_item(&plugin_source).await?.file_path;
     assert_eq!(file_path, expected_file_path);
 
+    // should have logged the artifact only once
+    assert_eq!(
+      read_provenance_log(&environment),
+      vec![ProvenanceLogEntry {
+        time: 123456,
+        user: None,
+        source: "https://plugins.dprint.dev/test.wasm".to_string(),
+        checksum: get_sha256_checksum("t".as_bytes()),
+        plugin_name: "test-plugin".to_string(),
+        plugin_version: "0.1.0".to_string(),
+      }]
+    );
+
     // should have saved the manifest
     assert_eq!(
       environment.read_file(&environment.get_cache_dir().join("plugin-cache-manifest.json")).unwrap(),
-      r#"{"schemaVersion":7,"wasmCacheVersion":"2.3.0","plugins":{"remote:https://plugins.dprint.dev/test.wasm":{"createdTime":123456,"info":{"name":"test-plugin","version":"0.1.0","configKey":"test-plugin","fileExtensions":["txt","dat"],"fileNames":[],"helpUrl":"test-url","configSchemaUrl":"schema-url","updateUrl":"update-url"}}}}"#,
+      r#"{"schemaVersion":8,"wasmCacheVersion":"2.3.0","plugins":{"remote:https://plugins.dprint.dev/test.wasm":{"createdTime":123456,"info":{"name":"test-plugin","version":"0.1.0","configKey":"test-plugin","fileExtensions":["txt","dat"],"fileNames":[],"helpUrl":"test-url","configSchemaUrl":"schema-url","updateUrl":"update-url"}}}}"#,
     );
 
     // should forget it afterwards
@@ -198,7 +283,7 @@ mod test {
     // should have saved the manifest
     assert_eq!(
       environment.read_file(&environment.get_cache_dir().join("plugin-cache-manifest.json")).unwrap(),
-      r#"{"schemaVersion":7,"wasmCacheVersion":"2.3.0","plugins":{}}"#,
+      r#"{"schemaVersion":8,"wasmCacheVersion":"2.3.0","plugins":{}}"#,
     );
 
     Ok(())
@@ -229,7 +314,7 @@ mod test {
     assert_eq!(
       environment.read_file(&environment.get_cache_dir().join("plugin-cache-manifest.json")).unwrap(),
       concat!(
-        r#"{"schemaVersion":7,"wasmCacheVersion":"2.3.0","plugins":{"local:/test.wasm":{"createdTime":123456,"fileHash":10632242795325663332,"info":{"#,
+        r#"{"schemaVersion":8,"wasmCacheVersion":"2.3.0","plugins":{"local:/test.wasm":{"createdTime":123456,"fileHash":10632242795325663332,"info":{"#,
         r#""name":"test-plugin","version":"0.1.0","configKey":"test-plugin","#,
         r#""fileExtensions":["txt","dat"],"fileNames":[],"helpUrl":"test-url","configSchemaUrl":"schema-url","updateUrl":"update-url"}}}}"#,
       )
@@ -251,7 +336,7 @@ mod test {
     assert_eq!(
       environment.read_file(&environment.get_cache_dir().join("plugin-cache-manifest.json")).unwrap(),
       concat!(
-        r#"{"schemaVersion":7,"wasmCacheVersion":"2.3.0","plugins":{"local:/test.wasm":{"createdTime":123456,"fileHash":6989588595861227504,"info":{"#,
+        r#"{"schemaVersion":8,"wasmCacheVersion":"2.3.0","plugins":{"local:/test.wasm":{"createdTime":123456,"fileHash":6989588595861227504,"info":{"#,
         r#""name":"test-plugin","version":"0.1.0","configKey":"test-plugin","#,
         r#""fileExtensions":["txt","dat"],"fileNames":[],"helpUrl":"test-url","configSchemaUrl":"schema-url","updateUrl":"update-url"}}}}"#,
       )
@@ -266,7 +351,45 @@ mod test {
     // should have saved the manifest
     assert_eq!(
       environment.read_file(&environment.get_cache_dir().join("plugin-cache-manifest.json")).unwrap(),
-      r#"{"schemaVersion":7,"wasmCacheVersion":"2.3.0","plugins":{}}"#,
+      r#"{"schemaVersion":8,"wasmCacheVersion":"2.3.0","plugins":{}}"#,
+    );
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn should_download_again_when_pinned_checksum_changes() -> Result<()> {
+    let environment = TestEnvironment::new();
+    environment.add_remote_file("https://plugins.dprint.dev/test.wasm", "t".as_bytes());
+    environment.set_wasm_compile_result(create_compilation_result("t".as_bytes()));
+
+    let mut plugin_cache = PluginCache::new(environment.clone());
+    plugin_cache.set_assume_trusted(true);
+    let unpinned_source = PluginSourceReference::new_remote_from_str("https://plugins.dprint.dev/test.wasm");
+    plugin_cache.get_plugin_cache_item(&unpinned_source).await?;
+    assert_eq!(environment.take_stderr_messages(), vec!["Compiling https://plugins.dprint.dev/test.wasm"]);
+
+    // verifies the plugin cached before it was pinned
+    let pinned_source = PluginSourceReference {
+      path_source: unpinned_source.path_source.clone(),
+      checksum: Some(get_sha256_checksum("t".as_bytes())),
+    };
+    plugin_cache.get_plugin_cache_item(&pinned_source).await?;
+    assert_eq!(environment.take_stderr_messages(), vec!["Compiling https://plugins.dprint.dev/test.wasm"]);
+    plugin_cache.get_plugin_cache_item(&pinned_source).await?;
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+
+    let other_source = PluginSourceReference {
+      path_source: unpinned_source.path_source.clone(),
+      checksum: Some(get_sha256_checksum("u".as_bytes())),
+    };
+    let err = plugin_cache.get_plugin_cache_item(&other_source).await.err().unwrap();
+    assert!(
+      err
+        .to_string()
+        .starts_with("Invalid checksum specified in configuration file. Check the plugin's release notes for what the expected checksum is."),
+      "{}",
+      err
     );
 
     Ok(())
@@ -289,6 +412,7 @@ mod test {
       help_url: String::from("test-url"),
       config_schema_url: String::from("schema-url"),
       update_url: Some(String::from("update-url")),
+      experiments: vec![],
     }
   }
 }
@@ -9,7 +9,7 @@ use dprint_core::plugins::PluginInfo;
 use super::implementations::WASMER_COMPILER_VERSION;
 use crate::environment::Environment;
 
-const PLUGIN_CACHE_SCHEMA_VERSION: usize = 7;
+const PLUGIN_CACHE_SCHEMA_VERSION: usize = 8;
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
@@ -76,6 +76,9 @@ pub struct PluginCacheManifestItem {
   pub created_time: u64,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub file_hash: Option<u64>,
+  /// Checksum the plugin was pinned to when it was downloaded.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub checksum: Option<String>,
   pub info: PluginInfo,
 }
 
@@ -135,7 +138,7 @@ mod test {
       .write_file(
         &environment.get_cache_dir().join("plugin-cache-manifest.json"),
         r#"{
-    "schemaVersion": 7,
+    "schemaVersion": 8,
     "wasmCacheVersion": "99.9.9",
     "plugins": {
         "a": {
@@ -188,6 +191,7 @@ mod test {
       PluginCacheManifestItem {
         created_time: 123,
         file_hash: None,
+        checksum: None,
         info: PluginInfo {
           name: "dprint-plugin-typescript".to_string(),
           version: "0.1.0".to_string(),
@@ -197,6 +201,7 @@ mod test {
           help_url: "help url".to_string(),
           config_schema_url: "schema url".to_string(),
           update_url: None,
+          experiments: vec![],
         },
       },
     );
@@ -205,6 +210,7 @@ mod test {
       PluginCacheManifestItem {
         created_time: 456,
         file_hash: Some(10),
+        checksum: None,
         info: PluginInfo {
           name: "dprint-plugin-json".to_string(),
           version: "0.2.0".to_string(),
@@ -214,6 +220,7 @@ mod test {
           help_url: "help url 2".to_string(),
           config_schema_url: "schema url 2".to_string(),
           update_url: None,
+          experiments: vec![],
         },
       },
     );
@@ -222,6 +229,7 @@ mod test {
       PluginCacheManifestItem {
         created_time: 210530,
         file_hash: Some(1226),
+        checksum: None,
         info: PluginInfo {
           name: "dprint-plugin-cargo".to_string(),
           version: "0.2.1".to_string(),
@@ -231,6 +239,7 @@ mod test {
           help_url: "cargo help url".to_string(),
           config_schema_url: "cargo schema url".to_string(),
           update_url: Some("cargo update url".to_string()),
+          experiments: vec![],
         },
       },
     );
@@ -245,7 +254,7 @@ mod test {
       .write_file(
         &environment.get_cache_dir().join("plugin-cache-manifest.json"),
         r#"{
-    "schemaVersion": 7,
+    "schemaVersion": 8,
     "wasmCacheVersion": "0.1.0",
     "plugins": {
         "a": {
@@ -328,6 +337,7 @@ mod test {
       PluginCacheManifestItem {
         created_time: 456,
         file_hash: Some(256),
+        checksum: None,
         info: PluginInfo {
           name: "dprint-plugin-typescript".to_string(),
           version: "0.1.0".to_string(),
@@ -337,6 +347,7 @@ mod test {
           help_url: "help url".to_string(),
           config_schema_url: "schema url".to_string(),
           update_url: Some("update url".to_string()),
+          experiments: vec![],
         },
       },
     );
@@ -345,6 +356,7 @@ mod test {
       PluginCacheManifestItem {
         created_time: 456,
         file_hash: None,
+        checksum: None,
         info: PluginInfo {
           name: "dprint-plugin-json".to_string(),
           version: "0.2.0".to_string(),
@@ -354,6 +366,7 @@ mod test {
           help_url: "help url 2".to_string(),
           config_schema_url: "schema url 2".to_string(),
           update_url: None,
+          experiments: vec![],
         },
       },
     );
@@ -4,6 +4,7 @@ use dprint_core::plugins::FormatResult;
 use dprint_core::plugins::Host;
 use dprint_core::plugins::HostFormatRequest;
 use futures::FutureExt;
+use indexmap::IndexMap;
 use parking_lot::Mutex;
 use parking_lot::RwLock;
 use std::collections::HashMap;
@@ -18,14 +19,18 @@ use super::output_plugin_config_diagnostics;
 use super::InitializedPlugin;
 use super::InitializedPluginFormatRequest;
 use super::Plugin;
+use crate::arg_parser::AssociationArg;
+use crate::configuration::ConfigOverride;
 use crate::environment::CanonicalizedPathBuf;
 use crate::environment::Environment;
+use crate::utils::splice_formatted_range;
 use crate::utils::ErrorCountLogger;
 
 pub struct PluginsCollection<TEnvironment: Environment> {
   environment: TEnvironment,
   plugins: Mutex<HashMap<String, Arc<PluginWrapper<TEnvironment>>>>,
   plugin_name_maps: RwLock<PluginNameResolutionMaps>,
+  config_base_path: RwLock<Option<CanonicalizedPathBuf>>,
 }
 
 impl<TEnvironment: Environment> PluginsCollection<TEnvironment> {
@@ -34,6 +39,7 @@ impl<TEnvironment: Environment> PluginsCollection<TEnvironment> {
       environment,
       plugins: Default::default(),
       plugin_name_maps: Default::default(),
+      config_base_path: Default::default(),
     }
   }
 
@@ -49,16 +55,28 @@ impl<TEnvironment: Environment> PluginsCollection<TEnvironment> {
     }
   }
 
-  pub fn set_plugins(&self, plugins: Vec<Box<dyn Plugin>>, config_base_path: &CanonicalizedPathBuf) -> Result<()> {
+  pub fn set_plugins(
+    &self,
+    plugins: Vec<Box<dyn Plugin>>,
+    config_base_path: &CanonicalizedPathBuf,
+    overrides: &IndexMap<String, ConfigOverride>,
+    associations: &[AssociationArg],
+  ) -> Result<()> {
     let mut self_plugins = self.plugins.lock();
-    let plugin_name_maps = PluginNameResolutionMaps::from_plugins(&plugins, config_base_path)?;
+    let plugin_name_maps = PluginNameResolutionMaps::from_plugins(&plugins, config_base_path, overrides, associations)?;
     for plugin in plugins {
       self_plugins.insert(plugin.name().to_string(), Arc::new(PluginWrapper::new(plugin, self.environment.clone())));
     }
     *self.plugin_name_maps.write() = plugin_name_maps;
+    *self.config_base_path.write() = Some(config_base_path.clone());
     Ok(())
   }
 
+  /// The directory of the configuration file of the current plugins.
+  pub fn config_base_path(&self) -> Option<CanonicalizedPathBuf> {
+    self.config_base_path.read().clone()
+  }
+
   pub fn process_plugin_count(&self) -> usize {
     self.plugins.lock().values().filter(|p| p.plugin.is_process_plugin()).count()
   }
@@ -72,6 +90,13 @@ impl<TEnvironment: Environment> PluginsCollection<TEnvironment> {
       .unwrap_or_else(|| panic!("Expected to find plugin in collection: {}", plugin_name))
   }
 
+  /// Gets all the plugins sorted by name.
+  pub fn get_plugins(&self) -> Vec<Arc<PluginWrapper<TEnvironment>>> {
+    let mut plugins = self.plugins.lock().values().cloned().collect::<Vec<_>>();
+    plugins.sort_by(|a, b| a.name().cmp(b.name()));
+    plugins
+  }
+
   pub fn get_plugin_names_from_file_path(&self, file_path: &Path) -> Vec<String> {
     self.plugin_name_maps.read().get_plugin_names_from_file_path(file_path)
   }
@@ -81,13 +106,25 @@ impl<TEnvironment: Environment> PluginsCollection<TEnvironment> {
     use std::num::Wrapping;
     // yeah, I know adding hashes isn't right, but the chance of this not working
     // in order to tell when a plugin has changed is super low.
-    let plugins = self.plugins.lock();
-    let mut hash_sum = Wrapping(0);
-    for plugin in plugins.values() {
-      hash_sum += Wrapping(plugin.plugin.get_hash());
+    let mut hash_sum = Wrapping(self.get_plugin_name_resolution_hash());
+    for hash in self.get_plugin_hashes().values() {
+      hash_sum += Wrapping(*hash);
     }
     hash_sum.0
   }
+
+  /// Gets the hash of each plugin by name to tell which plugins have changed.
+  pub fn get_plugin_hashes(&self) -> HashMap<String, u64> {
+    let plugins = self.plugins.lock();
+    plugins.iter().map(|(name, plugin)| (name.clone(), plugin.plugin.get_hash())).collect()
+  }
+
+  /// Gets a hash of what affects which plugins format a file other than the plugins themselves.
+  pub fn get_plugin_name_resolution_hash(&self) -> u64 {
+    use std::num::Wrapping;
+    let plugin_name_maps = self.plugin_name_maps.read();
+    (Wrapping(plugin_name_maps.arg_associations_hash()) + Wrapping(plugin_name_maps.overrides_hash())).0
+  }
 }
 
 impl<TEnvironment: Environment> Host for PluginsCollection<TEnvironment> {
@@ -103,22 +140,41 @@ impl<TEnvironment: Environment> Host for PluginsCollection<TEnvironment> {
     );
     async move {
       let mut file_text = request.file_text;
+      let mut range = request.range;
       let mut had_change = false;
       for plugin_name in plugin_names {
+        // don't run the remaining plugins when the request was cancelled
+        if request.token.is_cancelled() {
+          return Ok(None);
+        }
         let plugin = self.get_plugin(&plugin_name);
         let error_logger = ErrorCountLogger::from_environment(&self.environment);
         match plugin.get_or_create_checking_config_diagnostics(error_logger.clone()).await {
           Ok(GetPluginResult::Success(initialized_plugin)) => {
+            let supports_range_formatting = initialized_plugin.supports_range_formatting();
             let result = initialized_plugin
               .format_text(InitializedPluginFormatRequest {
                 file_path: request.file_path.clone(),
                 file_text: file_text.clone(),
-                range: request.range.clone(),
+                range: if supports_range_formatting { range.clone() } else { None },
                 override_config: request.override_config.clone(),
                 token: request.token.clone(),
               })
               .await;
             if let Some(new_text) = result? {
+              let new_text = match range.as_mut() {
+                Some(range) => {
+                  let new_text = if supports_range_formatting {
+                    new_text
+                  } else {
+                    splice_formatted_range(&file_text, &new_text, range)
+                  };
+                  // keep the range over the same text for the next plugin
+                  range.end = std::cmp::max(range.start, (range.end + new_text.len()).saturating_sub(file_text.len()));
+                  new_text
+                }
+                None => new_text,
+              };
               file_text = new_text;
               had_change = true;
             }
@@ -145,6 +201,7 @@ pub struct PluginWrapper<TEnvironment: Environment> {
   plugin: Box<dyn Plugin>,
   initialized_plugin: tokio::sync::Mutex<Option<Arc<dyn InitializedPlugin>>>,
   checked_diagnostics: Mutex<Option<bool>>,
+  startup_duration_ms: Mutex<Option<u64>>,
 }
 
 impl<TEnvironment: Environment> PluginWrapper<TEnvironment> {
@@ -155,6 +212,7 @@ impl<TEnvironment: Environment> PluginWrapper<TEnvironment> {
       plugin,
       initialized_plugin: Default::default(),
       checked_diagnostics: Default::default(),
+      startup_duration_ms: Default::default(),
     }
   }
 
@@ -162,6 +220,40 @@ impl<TEnvironment: Environment> PluginWrapper<TEnvironment> {
     self.name.as_str()
   }
 
+  pub fn version(&self) -> &str {
+    self.plugin.version()
+  }
+
+  /// Gets the hash of the plugin and its configuration.
+  pub fn get_hash(&self) -> u64 {
+    self.plugin.get_hash()
+  }
+
+  pub fn is_process_plugin(&self) -> bool {
+    self.plugin.is_process_plugin()
+  }
+
+  /// The time it took to create the first instance of the plugin.
+  pub fn startup_duration_ms(&self) -> Option<u64> {
+    *self.startup_duration_ms.lock()
+  }
+
+  /// The peak memory usage of the initialized plugin in bytes if known.
+  pub fn peak_memory_bytes(&self) -> Option<u64> {
+    // don't wait if the plugin is currently being initialized
+    let initialized_plugin = self.initialized_plugin.try_lock().ok()?;
+    initialized_plugin.as_ref()?.peak_memory_bytes()
+  }
+
+  /// Gets if the plugin has an instance or is currently creating one.
+  #[cfg(unix)]
+  pub fn is_initialized(&self) -> bool {
+    match self.initialized_plugin.try_lock() {
+      Ok(initialized_plugin) => initialized_plugin.is_some(),
+      Err(_) => true,
+    }
+  }
+
   pub async fn take_initialized(&self) -> Option<Arc<dyn InitializedPlugin>> {
     self.initialized_plugin.lock().await.take()
   }
@@ -204,6 +296,7 @@ impl<TEnvironment: Environment> PluginWrapper<TEnvironment> {
     let plugin = self.plugin.initialize().await?;
     let startup_duration = start_instant.elapsed().as_millis() as u64;
     log_verbose!(self.environment, "Created instance of {} in {}ms", self.plugin.name(), startup_duration);
+    self.startup_duration_ms.lock().get_or_insert(startup_duration);
     Ok(plugin)
   }
 }
@@ -0,0 +1,111 @@
+use dprint_core::plugins::process::PLUGIN_SCHEMA_VERSION as PROCESS_PLUGIN_SCHEMA_VERSION;
+use dprint_core::plugins::wasm::PLUGIN_SYSTEM_SCHEMA_VERSION as WASM_PLUGIN_SCHEMA_VERSION;
+
+/// The oldest Wasm plugin schema version that can be loaded via an adapter.
+const MIN_WASM_ADAPTER_SCHEMA_VERSION: u32 = 2;
+
+/// How a plugin built against a certain plugin schema version is loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaCompatibility {
+  /// The schema version this CLI was built for.
+  Native,
+  /// An older schema version loaded with an adapter, which may
+  /// not support all the functionality of the current schema.
+  Adapter,
+  /// A newer schema version that is loaded as if it were the current
+  /// schema version when it has all the exports the CLI requires.
+  Newer,
+  Unsupported,
+}
+
+impl SchemaCompatibility {
+  pub fn description(&self) -> &'static str {
+    match self {
+      SchemaCompatibility::Native => "native",
+      SchemaCompatibility::Adapter => "adapter (no override configuration)",
+      SchemaCompatibility::Newer => "newer than the CLI (loaded with the current schema)",
+      SchemaCompatibility::Unsupported => "unsupported",
+    }
+  }
+}
+
+pub fn get_wasm_schema_compatibility(schema_version: u32) -> SchemaCompatibility {
+  if schema_version == WASM_PLUGIN_SCHEMA_VERSION {
+    SchemaCompatibility::Native
+  } else if schema_version > WASM_PLUGIN_SCHEMA_VERSION {
+    SchemaCompatibility::Newer
+  } else if schema_version >= MIN_WASM_ADAPTER_SCHEMA_VERSION {
+    SchemaCompatibility::Adapter
+  } else {
+    SchemaCompatibility::Unsupported
+  }
+}
+
+pub fn get_process_schema_compatibility(schema_version: u32) -> SchemaCompatibility {
+  // the process plugin schema is verified during the handshake,
+  // so there are no adapters for it
+  if schema_version == PROCESS_PLUGIN_SCHEMA_VERSION {
+    SchemaCompatibility::Native
+  } else {
+    SchemaCompatibility::Unsupported
+  }
+}
+
+/// Gets the text of which plugin schema versions are supported for outputting in verbose mode.
+pub fn get_compatibility_matrix_text() -> String {
+  let mut lines = vec!["Plugin schema compatibility:".to_string()];
+  for schema_version in MIN_WASM_ADAPTER_SCHEMA_VERSION..=WASM_PLUGIN_SCHEMA_VERSION {
+    lines.push(format!(
+      "  Wasm {} - {}",
+      schema_version,
+      get_wasm_schema_compatibility(schema_version).description()
+    ));
+  }
+  lines.push(format!(
+    "  Wasm {}+ - {}",
+    WASM_PLUGIN_SCHEMA_VERSION + 1,
+    SchemaCompatibility::Newer.description()
+  ));
+  lines.push(format!(
+    "  Process {} - {}",
+    PROCESS_PLUGIN_SCHEMA_VERSION,
+    get_process_schema_compatibility(PROCESS_PLUGIN_SCHEMA_VERSION).description()
+  ));
+  lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn should_get_wasm_schema_compatibility() {
+    assert_eq!(get_wasm_schema_compatibility(1), SchemaCompatibility::Unsupported);
+    assert_eq!(get_wasm_schema_compatibility(2), SchemaCompatibility::Adapter);
+    assert_eq!(get_wasm_schema_compatibility(3), SchemaCompatibility::Native);
+    assert_eq!(get_wasm_schema_compatibility(4), SchemaCompatibility::Newer);
+  }
+
+  #[test]
+  fn should_get_process_schema_compatibility() {
+    assert_eq!(get_process_schema_compatibility(3), SchemaCompatibility::Unsupported);
+    assert_eq!(get_process_schema_compatibility(4), SchemaCompatibility::Native);
+    assert_eq!(get_process_schema_compatibility(5), SchemaCompatibility::Unsupported);
+  }
+
+  #[test]
+  fn should_get_compatibility_matrix_text() {
+    assert_eq!(
+      get_compatibility_matrix_text(),
+      concat!(
+        "Plugin schema compatibility:\n",
+        "  Wasm 2 - adapter (no override configuration)\n",
+        "  Wasm 3 - native\n",
+        "  Wasm 4+ - newer than the CLI (loaded with the current schema)\n",
+        "  Process 4 - native",
+      )
+    );
+  }
+}
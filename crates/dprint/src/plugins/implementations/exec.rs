@@ -0,0 +1,357 @@
+use anyhow::Result;
+use dprint_core::configuration::ConfigurationDiagnostic;
+use dprint_core::configuration::GlobalConfiguration;
+use dprint_core::plugins::FormatResult;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use once_cell::sync::OnceCell;
+use std::path::Path;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::configuration::ExecPluginConfig;
+use crate::configuration::ExecPluginMode;
+use crate::configuration::RawPluginConfig;
+use crate::environment::CanonicalizedPathBuf;
+use crate::environment::Environment;
+use crate::plugins::InitializedPlugin;
+use crate::plugins::InitializedPluginFormatRequest;
+use crate::plugins::Plugin;
+
+const FILE_PATH_PLACEHOLDER: &str = "{{filePath}}";
+static TEMP_DIR_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// A plugin that formats the text by running an external command
+/// configured in the `"execPlugins"` property of the configuration file.
+pub struct ExecPlugin<TEnvironment: Environment> {
+  environment: TEnvironment,
+  exec_config: Arc<ExecPluginConfig>,
+  /// The directory the command is run in.
+  cwd: CanonicalizedPathBuf,
+  no_experiments: Vec<String>,
+  config: Option<(RawPluginConfig, GlobalConfiguration)>,
+  /// The output of the version command, which is only run once.
+  version_output: OnceCell<String>,
+}
+
+impl<TEnvironment: Environment> ExecPlugin<TEnvironment> {
+  pub fn new(environment: TEnvironment, exec_config: ExecPluginConfig, cwd: CanonicalizedPathBuf) -> Self {
+    ExecPlugin {
+      environment,
+      exec_config: Arc::new(exec_config),
+      cwd,
+      no_experiments: Vec::new(),
+      config: None,
+      version_output: OnceCell::new(),
+    }
+  }
+
+  /// Gets the output of the configured version command so upgrading the formatter invalidates the incremental cache.
+  fn get_version_output(&self) -> Option<&str> {
+    let version_command = self.exec_config.version_command.as_ref()?;
+    let output = self.version_output.get_or_init(|| {
+      let args = version_command[1..].iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+      let timeout = Duration::from_secs(self.exec_config.timeout_secs);
+      match self
+        .environment
+        .run_command_with_timeout(&version_command[0], &args, self.cwd.as_ref(), "", timeout)
+      {
+        Ok(output) => output,
+        Err(err) => {
+          self.environment.log_stderr(&format!(
+            "WARNING: Error getting the version of the {} exec plugin, so its files will not be incrementally formatted. {:#}",
+            self.exec_config.name, err
+          ));
+          // change the hash on every run since the version is unknown
+          format!("unknown-{}", self.environment.get_time_secs())
+        }
+      }
+    });
+    Some(output)
+  }
+}
+
+impl<TEnvironment: Environment> Plugin for ExecPlugin<TEnvironment> {
+  fn name(&self) -> &str {
+    &self.exec_config.name
+  }
+
+  fn version(&self) -> &str {
+    "exec"
+  }
+
+  fn config_key(&self) -> &str {
+    &self.exec_config.name
+  }
+
+  fn file_extensions(&self) -> &Vec<String> {
+    &self.exec_config.file_extensions
+  }
+
+  fn file_names(&self) -> &Vec<String> {
+    &self.exec_config.file_names
+  }
+
+  fn help_url(&self) -> &str {
+    "https://dprint.dev/config/#exec-plugins"
+  }
+
+  fn config_schema_url(&self) -> &str {
+    ""
+  }
+
+  fn update_url(&self) -> Option<&str> {
+    None
+  }
+
+  fn experiments(&self) -> &Vec<String> {
+    &self.no_experiments
+  }
+
+  fn set_config(&mut self, mut plugin_config: RawPluginConfig, global_config: GlobalConfiguration) {
+    // the associations of the plugin's configuration take precedence
+    if plugin_config.associations.is_none() {
+      plugin_config.associations = self.exec_config.associations.clone();
+    }
+    self.config = Some((plugin_config, global_config));
+  }
+
+  fn get_config(&self) -> &(RawPluginConfig, GlobalConfiguration) {
+    self.config.as_ref().expect("Call set_config first.")
+  }
+
+  fn is_process_plugin(&self) -> bool {
+    false
+  }
+
+  fn get_hash(&self) -> u64 {
+    let config = self.get_config();
+    let exec_config = &self.exec_config;
+    let mut hash_str = String::new();
+    // the command and its arguments are what affect the formatting
+    hash_str.push_str(&exec_config.name);
+    hash_str.push_str(&exec_config.command);
+    hash_str.push_str(&serde_json::to_string(&exec_config.args).unwrap());
+    hash_str.push_str(&format!("{:?}", exec_config.mode));
+    hash_str.push_str(&serde_json::to_string(&config.0.associations).unwrap());
+    if let Some(version_output) = self.get_version_output() {
+      hash_str.push_str(version_output);
+    }
+    crate::utils::get_bytes_hash(hash_str.as_bytes())
+  }
+
+  fn initialize(&self) -> BoxFuture<'static, Result<Arc<dyn InitializedPlugin>>> {
+    let config = self.config.as_ref().expect("Call set_config first.");
+    let plugin: Arc<dyn InitializedPlugin> = Arc::new(InitializedExecPlugin {
+      environment: self.environment.clone(),
+      exec_config: self.exec_config.clone(),
+      cwd: self.cwd.clone(),
+      unknown_property_names: config.0.properties.keys().cloned().collect(),
+    });
+    futures::future::ready(Ok(plugin)).boxed()
+  }
+}
+
+pub struct InitializedExecPlugin<TEnvironment: Environment> {
+  environment: TEnvironment,
+  exec_config: Arc<ExecPluginConfig>,
+  cwd: CanonicalizedPathBuf,
+  /// The command receives no configuration, so all the properties are unknown.
+  unknown_property_names: Vec<String>,
+}
+
+impl<TEnvironment: Environment> InitializedPlugin for InitializedExecPlugin<TEnvironment> {
+  fn license_text(&self) -> BoxFuture<'static, Result<String>> {
+    let text = format!(
+      "The {} plugin runs `{}`. Refer to the license of that command.",
+      self.exec_config.name, self.exec_config.command
+    );
+    futures::future::ready(Ok(text)).boxed()
+  }
+
+  fn resolved_config(&self) -> BoxFuture<'static, Result<String>> {
+    let exec_config = &self.exec_config;
+    let result: Result<String> = serde_json::to_string(&serde_json::json!({
+      "command": exec_config.command,
+      "args": exec_config.args,
+      "mode": match exec_config.mode {
+        ExecPluginMode::Stdin => "stdin",
+        ExecPluginMode::TempFile => "tempFile",
+      },
+      "timeout": exec_config.timeout_secs
+    }))
+    .map_err(|err| err.into());
+    futures::future::ready(result).boxed()
+  }
+
+  fn config_diagnostics(&self) -> BoxFuture<'static, Result<Vec<ConfigurationDiagnostic>>> {
+    let diagnostics = self
+      .unknown_property_names
+      .iter()
+      .map(|property_name| ConfigurationDiagnostic {
+        property_name: property_name.clone(),
+        message: format!(
+          "Unknown property in configuration. Exec plugins are configured in the 'execPlugins' property (ex. \"execPlugins\": {{ \"{}\": {{ ... }} }}).",
+          self.exec_config.name
+        ),
+      })
+      .collect();
+    futures::future::ready(Ok(diagnostics)).boxed()
+  }
+
+  fn format_text(&self, request: InitializedPluginFormatRequest) -> BoxFuture<'static, FormatResult> {
+    // commands only format the entire file
+    if request.range.is_some() || request.token.is_cancelled() {
+      return futures::future::ready(Ok(None)).boxed();
+    }
+    let environment = self.environment.clone();
+    let exec_config = self.exec_config.clone();
+    let cwd = self.cwd.clone();
+    async move { tokio::task::spawn_blocking(move || run_exec_command(&exec_config, &request.file_path, &request.file_text, cwd.as_ref(), &environment)).await? }
+      .boxed()
+  }
+
+  fn shutdown(&self) -> BoxFuture<'static, ()> {
+    Box::pin(futures::future::ready(()))
+  }
+
+  fn peak_memory_bytes(&self) -> Option<u64> {
+    None
+  }
+
+  fn supports_range_formatting(&self) -> bool {
+    false
+  }
+}
+
+/// Runs the command on the text, returning the formatted text when it changed.
+fn run_exec_command(exec_config: &ExecPluginConfig, file_path: &Path, file_text: &str, cwd: &Path, environment: &impl Environment) -> Result<Option<String>> {
+  let timeout = Duration::from_secs(exec_config.timeout_secs);
+  let formatted_text = match exec_config.mode {
+    ExecPluginMode::Stdin => {
+      let args = get_args(&exec_config.args, file_path);
+      let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+      environment.run_command_with_timeout(&exec_config.command, &args, cwd, file_text, timeout)?
+    }
+    ExecPluginMode::TempFile => {
+      // keep the file name so the command can tell what kind of file it is
+      let temp_dir = environment
+        .get_cache_dir()
+        .join("exec")
+        .join(format!("{}-{}", std::process::id(), TEMP_DIR_COUNT.fetch_add(1, Ordering::SeqCst)));
+      let temp_file_path = temp_dir.join(file_path.file_name().unwrap_or_else(|| "file".as_ref()));
+      environment.mk_dir_all(&temp_dir)?;
+      let result = (|| -> Result<String> {
+        environment.write_file(&temp_file_path, file_text)?;
+        let args = get_args(&exec_config.args, &temp_file_path);
+        let args = args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+        environment.run_command_with_timeout(&exec_config.command, &args, cwd, "", timeout)?;
+        environment.read_file(&temp_file_path)
+      })();
+      let _ = environment.remove_dir_all(&temp_dir);
+      result?
+    }
+  };
+  Ok(if formatted_text == file_text { None } else { Some(formatted_text) })
+}
+
+fn get_args(args: &[String], file_path: &Path) -> Vec<String> {
+  let file_path = file_path.to_string_lossy();
+  args.iter().map(|arg| arg.replace(FILE_PATH_PLACEHOLDER, &file_path)).collect()
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::environment::TestEnvironment;
+  use crate::environment::TestEnvironmentBuilder;
+  use crate::test_helpers::get_singular_formatted_text;
+  use crate::test_helpers::run_test_cli;
+
+  fn get_exec_config(mode: ExecPluginMode) -> ExecPluginConfig {
+    ExecPluginConfig {
+      name: "clang-format".to_string(),
+      command: "clang-format".to_string(),
+      args: vec!["--assume-filename={{filePath}}".to_string()],
+      file_extensions: vec!["c".to_string()],
+      file_names: Vec::new(),
+      associations: None,
+      mode,
+      timeout_secs: 30,
+      version_command: None,
+    }
+  }
+
+  #[test]
+  fn should_format_with_stdin() {
+    let environment = TestEnvironment::new();
+    environment.add_command_output("clang-format --assume-filename=/src/file.c", "int a;\n");
+    let exec_config = get_exec_config(ExecPluginMode::Stdin);
+    let result = run_exec_command(&exec_config, Path::new("/src/file.c"), "int  a;\n", Path::new("/"), &environment).unwrap();
+    assert_eq!(result, Some("int a;\n".to_string()));
+    assert_eq!(environment.take_run_command_stdins(), vec!["int  a;\n"]);
+
+    // unchanged
+    let result = run_exec_command(&exec_config, Path::new("/src/file.c"), "int a;\n", Path::new("/"), &environment).unwrap();
+    assert_eq!(result, None);
+  }
+
+  #[test]
+  fn should_format_with_temp_file() {
+    let environment = TestEnvironment::new();
+    let exec_config = get_exec_config(ExecPluginMode::TempFile);
+    let temp_file_path = environment
+      .get_cache_dir()
+      .join("exec")
+      .join(format!("{}-{}", std::process::id(), TEMP_DIR_COUNT.load(Ordering::SeqCst)))
+      .join("file.c");
+    environment.add_command_output(&format!("clang-format --assume-filename={}", temp_file_path.display()), "");
+    // the command doesn't change the file in the test environment
+    let result = run_exec_command(&exec_config, Path::new("/src/file.c"), "int a;\n", Path::new("/"), &environment).unwrap();
+    assert_eq!(result, None);
+    assert!(!environment.path_exists(&temp_file_path));
+  }
+
+  #[test]
+  fn should_include_version_output_in_hash() {
+    let environment = TestEnvironment::new();
+    let cwd = environment.canonicalize("/").unwrap();
+    let get_hash = |exec_config: ExecPluginConfig| {
+      let mut plugin = ExecPlugin::new(environment.clone(), exec_config, cwd.clone());
+      plugin.set_config(Default::default(), Default::default());
+      plugin.get_hash()
+    };
+    let mut exec_config = get_exec_config(ExecPluginMode::Stdin);
+    exec_config.version_command = Some(vec!["clang-format".to_string(), "--version".to_string()]);
+    environment.add_command_output("clang-format --version", "clang-format version 15.0.0\n");
+    let first_hash = get_hash(exec_config.clone());
+    assert_eq!(get_hash(exec_config.clone()), first_hash);
+    assert_eq!(environment.take_run_commands(), vec!["clang-format --version", "clang-format --version"]);
+
+    environment.add_command_output("clang-format --version", "clang-format version 16.0.0\n");
+    assert_ne!(get_hash(exec_config), first_hash);
+    assert_ne!(get_hash(get_exec_config(ExecPluginMode::Stdin)), first_hash);
+  }
+
+  #[test]
+  fn should_format_files_with_exec_plugin() {
+    let environment = TestEnvironmentBuilder::new()
+      .with_default_config(|c| {
+        c.add_includes("**/*.c").add_config_section(
+          "execPlugins",
+          r#"{ "clang-format": { "command": "clang-format", "args": ["--assume-filename={{filePath}}"], "fileExtensions": ["c"] } }"#,
+        );
+      })
+      .write_file("/file.c", "int  a;\n")
+      .build();
+    environment.add_command_output("clang-format --assume-filename=/file.c", "int a;\n");
+    run_test_cli(vec!["fmt"], &environment).unwrap();
+    assert_eq!(environment.take_stdout_messages(), vec![get_singular_formatted_text()]);
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+    assert_eq!(environment.read_file("/file.c").unwrap(), "int a;\n");
+  }
+}
@@ -1,8 +1,11 @@
+mod exec;
 mod process;
 mod public;
 mod wasm;
 
+pub use process::recover_orphaned_process_plugins;
 pub use public::*;
+pub use wasm::WasmFileSelector;
 pub use wasm::WASMER_COMPILER_VERSION;
 
 pub use wasm::compile as compile_wasm;
@@ -40,7 +43,7 @@ mod test {
         for plugin in plugins.iter_mut() {
           plugin.set_config(Default::default(), Default::default());
         }
-        collection.set_plugins(plugins, &config.base_path).unwrap();
+        collection.set_plugins(plugins, &config.base_path, &config.overrides, &[]).unwrap();
 
         let token = Arc::new(CancellationToken::new());
         tokio::task::spawn({
@@ -1,30 +1,88 @@
+use super::remove_process_record;
+use super::write_process_record;
 use crate::environment::Environment;
 use crate::plugins::InitializedPluginFormatRequest;
 use crate::plugins::PluginsCollection;
+use anyhow::bail;
 use anyhow::Result;
 use dprint_core::configuration::ConfigKeyMap;
 use dprint_core::configuration::ConfigurationDiagnostic;
 use dprint_core::configuration::GlobalConfiguration;
 use dprint_core::plugins::process::ProcessPluginCommunicator;
+use dprint_core::plugins::process::ProcessPluginOptions;
+use dprint_core::plugins::process::ProcessPluginSandbox;
 use dprint_core::plugins::FormatResult;
+use parking_lot::Mutex;
+use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 
 // We only need to support having one configuration set at a time
 // so hardcode this.
 const CONFIG_ID: u32 = 1;
+const SANDBOX_ENV_VAR_NAME: &str = "DPRINT_SANDBOX_PROCESS_PLUGINS";
+const MAX_MEMORY_ENV_VAR_NAME: &str = "DPRINT_PROCESS_PLUGIN_MAX_MEMORY";
+const IDLE_TIMEOUT_ENV_VAR_NAME: &str = "DPRINT_PROCESS_PLUGIN_IDLE_TIMEOUT";
+const KEEP_ALIVE_INTERVAL_ENV_VAR_NAME: &str = "DPRINT_PROCESS_PLUGIN_KEEP_ALIVE_INTERVAL";
+const KEEP_ALIVE_RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a process plugin is kept running when it's not used.
+///
+/// Both are disabled by default because the CLI only runs for a short time.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct ProcessPluginLifetime {
+  /// Shuts down the process after it hasn't received a request for this long.
+  idle_timeout: Option<Duration>,
+  /// Pings the idle process at this interval and discards it when it doesn't respond.
+  keep_alive_interval: Option<Duration>,
+}
+
+impl ProcessPluginLifetime {
+  pub fn check_interval(&self) -> Option<Duration> {
+    match (self.idle_timeout, self.keep_alive_interval) {
+      (Some(idle_timeout), Some(keep_alive_interval)) => Some(std::cmp::min(idle_timeout, keep_alive_interval)),
+      (Some(interval), None) | (None, Some(interval)) => Some(interval),
+      (None, None) => None,
+    }
+  }
+}
 
 struct ProcessRestartInfo<TEnvironment: Environment> {
   environment: TEnvironment,
   plugin_name: String,
   executable_file_path: PathBuf,
   config: (GlobalConfiguration, ConfigKeyMap),
+  /// Environment variables declared in the configuration to start the process with.
+  env_vars: Option<Vec<(String, String)>>,
   plugin_collection: Arc<PluginsCollection<TEnvironment>>,
 }
 
+/// Tracks a request to the process plugin until it's dropped.
+struct ActivityGuard<'a> {
+  active_requests: &'a AtomicUsize,
+  last_activity: &'a Mutex<Instant>,
+}
+
+impl Drop for ActivityGuard<'_> {
+  fn drop(&mut self) {
+    *self.last_activity.lock() = Instant::now();
+    self.active_requests.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
 pub struct InitializedProcessPluginCommunicator<TEnvironment: Environment> {
-  communicator: tokio::sync::RwLock<Arc<ProcessPluginCommunicator>>,
+  /// The running process, which is `None` after it was shut down for being idle.
+  communicator: tokio::sync::RwLock<Option<Arc<ProcessPluginCommunicator>>>,
   restart_info: ProcessRestartInfo<TEnvironment>,
+  lifetime: ProcessPluginLifetime,
+  active_requests: AtomicUsize,
+  last_activity: Mutex<Instant>,
+  is_shutdown: AtomicBool,
 }
 
 impl<TEnvironment: Environment> InitializedProcessPluginCommunicator<TEnvironment> {
@@ -32,20 +90,27 @@ impl<TEnvironment: Environment> InitializedProcessPluginCommunicator<TEnvironmen
     plugin_name: String,
     executable_file_path: PathBuf,
     config: (GlobalConfiguration, ConfigKeyMap),
+    env_vars: Option<Vec<(String, String)>>,
     environment: TEnvironment,
     plugin_collection: Arc<PluginsCollection<TEnvironment>>,
   ) -> Result<Self> {
+    let lifetime = get_process_plugin_lifetime(&environment)?;
     let restart_info = ProcessRestartInfo {
       environment,
       plugin_name,
       executable_file_path,
       config,
+      env_vars,
       plugin_collection,
     };
     let communicator = create_new_communicator(&restart_info).await?;
     let initialized_communicator = Self {
-      communicator: tokio::sync::RwLock::new(Arc::new(communicator)),
+      communicator: tokio::sync::RwLock::new(Some(Arc::new(communicator))),
       restart_info,
+      lifetime,
+      active_requests: AtomicUsize::new(0),
+      last_activity: Mutex::new(Instant::now()),
+      is_shutdown: AtomicBool::new(false),
     };
 
     Ok(initialized_communicator)
@@ -63,6 +128,7 @@ impl<TEnvironment: Environment> InitializedProcessPluginCommunicator<TEnvironmen
       "test-process-plugin".to_string(),
       test_plugin_file_path,
       (Default::default(), plugin_config),
+      None,
       environment.clone(),
       collection,
     )
@@ -70,26 +136,65 @@ impl<TEnvironment: Environment> InitializedProcessPluginCommunicator<TEnvironmen
     .unwrap()
   }
 
+  /// Starts a task that shuts down the process when it's idle and pings it
+  /// to ensure it's still responding, when configured to do so.
+  pub fn monitor_lifetime(communicator: &Arc<Self>) {
+    let lifetime = communicator.lifetime;
+    let check_interval = match lifetime.check_interval() {
+      Some(check_interval) => check_interval,
+      None => return,
+    };
+    let communicator = Arc::downgrade(communicator);
+    tokio::task::spawn(async move {
+      loop {
+        tokio::time::sleep(check_interval).await;
+        let communicator = match communicator.upgrade() {
+          Some(communicator) => communicator,
+          None => return,
+        };
+        if communicator.is_shutdown.load(Ordering::SeqCst) {
+          return;
+        }
+        if let Some(idle_timeout) = lifetime.idle_timeout {
+          if communicator.shutdown_if_idle(idle_timeout).await {
+            continue;
+          }
+        }
+        if lifetime.keep_alive_interval.is_some() {
+          communicator.keep_alive().await;
+        }
+      }
+    });
+  }
+
   pub async fn shutdown(&self) {
-    self.get_inner().await.shutdown().await
+    self.is_shutdown.store(true, Ordering::SeqCst);
+    let communicator = self.communicator.write().await.take();
+    if let Some(communicator) = communicator {
+      shutdown_communicator(&communicator, &self.restart_info.environment).await;
+    }
   }
 
   pub async fn get_license_text(&self) -> Result<String> {
-    self.get_inner().await.license_text().await
+    let _activity = self.start_activity();
+    self.get_inner().await?.license_text().await
   }
 
   pub async fn get_resolved_config(&self) -> Result<String> {
-    self.get_inner().await.resolved_config(CONFIG_ID).await
+    let _activity = self.start_activity();
+    self.get_inner().await?.resolved_config(CONFIG_ID).await
   }
 
   pub async fn get_config_diagnostics(&self) -> Result<Vec<ConfigurationDiagnostic>> {
-    self.get_inner().await.config_diagnostics(CONFIG_ID).await
+    let _activity = self.start_activity();
+    self.get_inner().await?.config_diagnostics(CONFIG_ID).await
   }
 
   pub async fn format_text(&self, request: InitializedPluginFormatRequest) -> FormatResult {
+    let _activity = self.start_activity();
     match self
       .get_inner()
-      .await
+      .await?
       .format_text(
         request.file_path,
         request.file_text,
@@ -104,18 +209,126 @@ impl<TEnvironment: Environment> InitializedProcessPluginCommunicator<TEnvironmen
       Err(err) => {
         // attempt to restart the communicator if this fails and it's no longer alive
         let mut communicator = self.communicator.write().await;
-        if communicator.is_process_alive().await {
+        let current = match communicator.as_ref() {
+          Some(current) => current.clone(),
+          // shut down for being idle in the meantime
+          None => return Err(err),
+        };
+        if current.is_process_alive().await {
           Err(err)
         } else {
-          *communicator = Arc::new(create_new_communicator(&self.restart_info).await?);
+          if let Some(process_id) = current.process_id() {
+            remove_process_record(process_id, &self.restart_info.environment);
+          }
+          *communicator = Some(Arc::new(create_new_communicator(&self.restart_info).await?));
           Err(err)
         }
       }
     }
   }
 
-  pub async fn get_inner(&self) -> Arc<ProcessPluginCommunicator> {
-    self.communicator.read().await.clone()
+  /// Gets the peak memory usage of the current process, which is unknown while it's
+  /// restarting or after it was shut down for being idle.
+  pub fn peak_memory_bytes(&self) -> Option<u64> {
+    self
+      .communicator
+      .try_read()
+      .ok()
+      .and_then(|communicator| communicator.as_ref().and_then(|communicator| communicator.peak_memory_bytes()))
+  }
+
+  /// Gets the running process, starting it again if it was shut down for being idle.
+  pub async fn get_inner(&self) -> Result<Arc<ProcessPluginCommunicator>> {
+    if let Some(communicator) = self.communicator.read().await.as_ref() {
+      return Ok(communicator.clone());
+    }
+
+    let mut communicator = self.communicator.write().await;
+    match communicator.as_ref() {
+      // another request started it in the meantime
+      Some(communicator) => Ok(communicator.clone()),
+      None => {
+        if self.is_shutdown.load(Ordering::SeqCst) {
+          bail!("The {} process plugin was shut down.", self.restart_info.plugin_name);
+        }
+        log_verbose!(
+          self.restart_info.environment,
+          "Starting idle process plugin {} again.",
+          self.restart_info.plugin_name
+        );
+        let new_communicator = Arc::new(create_new_communicator(&self.restart_info).await?);
+        *communicator = Some(new_communicator.clone());
+        Ok(new_communicator)
+      }
+    }
+  }
+
+  fn start_activity(&self) -> ActivityGuard<'_> {
+    // increment before getting the process so it's not shut down while starting the request
+    self.active_requests.fetch_add(1, Ordering::SeqCst);
+    *self.last_activity.lock() = Instant::now();
+    ActivityGuard {
+      active_requests: &self.active_requests,
+      last_activity: &self.last_activity,
+    }
+  }
+
+  /// Shuts down the process when it hasn't received a request within the idle timeout.
+  /// It's started again on the next request.
+  async fn shutdown_if_idle(&self, idle_timeout: Duration) -> bool {
+    let mut communicator = self.communicator.write().await;
+    if communicator.is_none() || self.active_requests.load(Ordering::SeqCst) > 0 || self.last_activity.lock().elapsed() < idle_timeout {
+      return false;
+    }
+    let idle_communicator = communicator.take().unwrap();
+    log_verbose!(
+      self.restart_info.environment,
+      "Shutting down process plugin {} after being idle for {}s.",
+      self.restart_info.plugin_name,
+      idle_timeout.as_secs()
+    );
+    shutdown_communicator(&idle_communicator, &self.restart_info.environment).await;
+    true
+  }
+
+  /// Pings the process when it's idle and discards it when it doesn't respond
+  /// so that it's started again on the next request.
+  async fn keep_alive(&self) {
+    if self.active_requests.load(Ordering::SeqCst) > 0 {
+      return;
+    }
+    let current = match self.communicator.read().await.as_ref() {
+      Some(communicator) => communicator.clone(),
+      None => return,
+    };
+    let is_alive = tokio::time::timeout(KEEP_ALIVE_RESPONSE_TIMEOUT, current.ask_is_alive()).await.unwrap_or(false);
+    if is_alive {
+      return;
+    }
+
+    let mut communicator = self.communicator.write().await;
+    // ensure it wasn't already replaced
+    if communicator.as_ref().map(|communicator| Arc::ptr_eq(communicator, &current)).unwrap_or(false) {
+      log_verbose!(
+        self.restart_info.environment,
+        "Process plugin {} did not respond to a keep-alive ping, so it will be started again on the next request.",
+        self.restart_info.plugin_name
+      );
+      let process_id = current.process_id();
+      current.kill();
+      if let Some(process_id) = process_id {
+        remove_process_record(process_id, &self.restart_info.environment);
+      }
+      *communicator = None;
+    }
+  }
+}
+
+async fn shutdown_communicator<TEnvironment: Environment>(communicator: &ProcessPluginCommunicator, environment: &TEnvironment) {
+  let process_id = communicator.process_id();
+  communicator.shutdown().await;
+  if let Some(process_id) = process_id {
+    remove_process_record(process_id, environment);
   }
 }
 
@@ -123,18 +336,101 @@ async fn create_new_communicator<TEnvironment: Environment>(restart_info: &Proce
   // ensure it's initialized each time
   let plugin_name = restart_info.plugin_name.to_string();
   let environment = restart_info.environment.clone();
-  let communicator = ProcessPluginCommunicator::new(
-    &restart_info.executable_file_path,
-    move |error_message| {
-      environment.log_stderr_with_context(&error_message, &plugin_name);
-    },
-    restart_info.plugin_collection.clone(),
-  )
-  .await?;
+  let on_std_err = move |error_message: String| {
+    environment.log_stderr_with_context(&error_message, &plugin_name);
+  };
+  let options = ProcessPluginOptions {
+    sandbox: get_process_plugin_sandbox(
+      &restart_info.environment,
+      &restart_info.executable_file_path,
+      restart_info.plugin_collection.config_base_path().as_ref().map(|p| p.as_ref()),
+    ),
+    job_memory_limit: get_job_memory_limit(&restart_info.environment)?,
+    env_vars: restart_info.env_vars.clone(),
+  };
+  if options.sandbox.is_some() {
+    log_verbose!(restart_info.environment, "Starting process plugin {} in a sandbox.", restart_info.plugin_name);
+  }
+  let communicator =
+    ProcessPluginCommunicator::new_with_options(&restart_info.executable_file_path, &options, on_std_err, restart_info.plugin_collection.clone()).await?;
+  if let Some(process_id) = communicator.process_id() {
+    if let Err(err) = write_process_record(process_id, &restart_info.executable_file_path, &restart_info.environment) {
+      log_verbose!(
+        restart_info.environment,
+        "Error writing process record for {}: {:#}",
+        restart_info.plugin_name,
+        err
+      );
+    }
+  }
   communicator.register_config(CONFIG_ID, &restart_info.config.0, &restart_info.config.1).await?;
   Ok(communicator)
 }
 
+/// Gets the restrictions to start the process plugin with when sandboxing is enabled.
+///
+/// The plugin may only write within the project directory and read its own
+/// directory and the system directories needed to load shared libraries.
+fn get_process_plugin_sandbox(environment: &impl Environment, executable_file_path: &Path, config_base_path: Option<&Path>) -> Option<ProcessPluginSandbox> {
+  match environment.env_var(SANDBOX_ENV_VAR_NAME) {
+    Some(value) if value == "1" || value == "true" => {}
+    _ => return None,
+  }
+
+  let mut read_write_paths = vec![PathBuf::from("/dev/null")];
+  if let Some(config_base_path) = config_base_path {
+    read_write_paths.push(config_base_path.to_path_buf());
+  }
+  let mut read_paths = ["/usr", "/lib", "/lib64", "/etc"].iter().map(PathBuf::from).collect::<Vec<_>>();
+  if let Some(plugin_dir) = executable_file_path.parent() {
+    read_paths.push(plugin_dir.to_path_buf());
+  }
+  Some(ProcessPluginSandbox {
+    read_write_paths,
+    read_paths,
+    block_network: true,
+  })
+}
+
+/// Gets the memory limit in bytes of the job object the process plugin and its
+/// child processes are placed in on Windows.
+fn get_job_memory_limit(environment: &impl Environment) -> Result<Option<u64>> {
+  match environment.env_var(MAX_MEMORY_ENV_VAR_NAME) {
+    Some(value) => match value.trim().parse::<u64>() {
+      Ok(megabytes) if megabytes > 0 => Ok(Some(megabytes * 1024 * 1024)),
+      _ => bail!(
+        "The {} environment variable must be a number of megabytes greater than zero (ex. 512), but found '{}'.",
+        MAX_MEMORY_ENV_VAR_NAME,
+        value
+      ),
+    },
+    None => Ok(None),
+  }
+}
+
+/// Gets how long process plugins are kept running when they're not used,
+/// which is useful for reclaiming memory in long running editor sessions.
+fn get_process_plugin_lifetime(environment: &impl Environment) -> Result<ProcessPluginLifetime> {
+  Ok(ProcessPluginLifetime {
+    idle_timeout: get_seconds_env_var(environment, IDLE_TIMEOUT_ENV_VAR_NAME)?,
+    keep_alive_interval: get_seconds_env_var(environment, KEEP_ALIVE_INTERVAL_ENV_VAR_NAME)?,
+  })
+}
+
+fn get_seconds_env_var(environment: &impl Environment, name: &str) -> Result<Option<Duration>> {
+  match environment.env_var(name) {
+    Some(value) => match value.trim().parse::<u64>() {
+      Ok(seconds) if seconds > 0 => Ok(Some(Duration::from_secs(seconds))),
+      _ => bail!(
+        "The {} environment variable must be a number of seconds greater than zero (ex. 600), but found '{}'.",
+        name,
+        value
+      ),
+    },
+    None => Ok(None),
+  }
+}
+
 #[cfg(test)]
 mod test {
   use std::time::Duration;
@@ -186,7 +482,7 @@ mod test {
         }
 
         // spawn a task to kill the process plugin after a bit of time
-        let inner_communicator = communicator.get_inner().await;
+        let inner_communicator = communicator.get_inner().await.unwrap();
         tokio::task::spawn(async move {
           // give everything some time to queue up then kill the process
           tokio::time::sleep(Duration::from_millis(100)).await;
@@ -259,4 +555,98 @@ mod test {
       }
     })
   }
+  #[test]
+  fn should_shutdown_idle_process_plugin_and_start_it_again() {
+    let environment = TestEnvironmentBuilder::with_initialized_remote_process_plugin().build();
+    environment.run_in_runtime({
+      let environment = environment.clone();
+      async move {
+        let collection = Arc::new(PluginsCollection::new(environment.clone()));
+        let mut config = ConfigKeyMap::new();
+        config.insert("ending".to_string(), "custom".to_string().into());
+        let communicator = InitializedProcessPluginCommunicator::new_test_plugin_communicator(environment.clone(), collection.clone(), config).await;
+
+        assert!(!communicator.shutdown_if_idle(Duration::from_secs(600)).await);
+        assert!(communicator.shutdown_if_idle(Duration::ZERO).await);
+        assert!(communicator.communicator.read().await.is_none());
+        // already shut down
+        assert!(!communicator.shutdown_if_idle(Duration::ZERO).await);
+
+        // it's started again with the same config on the next request
+        let formatted_text = communicator
+          .format_text(InitializedPluginFormatRequest {
+            file_path: PathBuf::from("test.txt"),
+            file_text: "testing".to_string(),
+            range: None,
+            override_config: Default::default(),
+            token: Arc::new(NullCancellationToken),
+          })
+          .await
+          .unwrap();
+        assert_eq!(formatted_text, Some("testing_custom".to_string()));
+        assert!(communicator.communicator.read().await.is_some());
+
+        collection.drop_and_shutdown_initialized().await;
+        communicator.shutdown().await;
+        assert_eq!(
+          communicator.get_inner().await.err().unwrap().to_string(),
+          "The test-process-plugin process plugin was shut down."
+        );
+      }
+    })
+  }
+
+  #[test]
+  fn should_get_process_plugin_lifetime() {
+    let environment = TestEnvironmentBuilder::new().build();
+    let lifetime = get_process_plugin_lifetime(&environment).unwrap();
+    assert_eq!(lifetime, ProcessPluginLifetime::default());
+    assert_eq!(lifetime.check_interval(), None);
+
+    environment.set_env_var(IDLE_TIMEOUT_ENV_VAR_NAME, "600");
+    environment.set_env_var(KEEP_ALIVE_INTERVAL_ENV_VAR_NAME, "60");
+    let lifetime = get_process_plugin_lifetime(&environment).unwrap();
+    assert_eq!(
+      lifetime,
+      ProcessPluginLifetime {
+        idle_timeout: Some(Duration::from_secs(600)),
+        keep_alive_interval: Some(Duration::from_secs(60)),
+      }
+    );
+    assert_eq!(lifetime.check_interval(), Some(Duration::from_secs(60)));
+
+    environment.set_env_var(IDLE_TIMEOUT_ENV_VAR_NAME, "0");
+    assert_eq!(
+      get_process_plugin_lifetime(&environment).err().unwrap().to_string(),
+      "The DPRINT_PROCESS_PLUGIN_IDLE_TIMEOUT environment variable must be a number of seconds greater than zero (ex. 600), but found '0'."
+    );
+  }
+
+  #[test]
+  fn should_get_process_plugin_sandbox_when_enabled() {
+    let environment = TestEnvironmentBuilder::new().build();
+    let executable_file_path = PathBuf::from("/cache/plugins/test-process-plugin/0.1.0/test-process-plugin");
+    assert_eq!(
+      get_process_plugin_sandbox(&environment, &executable_file_path, Some(Path::new("/project"))),
+      None
+    );
+
+    environment.set_env_var(SANDBOX_ENV_VAR_NAME, "1");
+    let sandbox = get_process_plugin_sandbox(&environment, &executable_file_path, Some(Path::new("/project"))).unwrap();
+    assert_eq!(sandbox.read_write_paths, vec![PathBuf::from("/dev/null"), PathBuf::from("/project")]);
+    assert!(sandbox.read_paths.contains(&PathBuf::from("/cache/plugins/test-process-plugin/0.1.0")));
+    assert!(sandbox.block_network);
+  }
+  #[test]
+  fn should_get_job_memory_limit() {
+    let environment = TestEnvironmentBuilder::new().build();
+    assert_eq!(get_job_memory_limit(&environment).unwrap(), None);
+    environment.set_env_var(MAX_MEMORY_ENV_VAR_NAME, "512");
+    assert_eq!(get_job_memory_limit(&environment).unwrap(), Some(512 * 1024 * 1024));
+    environment.set_env_var(MAX_MEMORY_ENV_VAR_NAME, "lots");
+    assert_eq!(
+      get_job_memory_limit(&environment).err().unwrap().to_string(),
+      "The DPRINT_PROCESS_PLUGIN_MAX_MEMORY environment variable must be a number of megabytes greater than zero (ex. 512), but found 'lots'."
+    );
+  }
 }
@@ -1,7 +1,9 @@
 mod communicator;
 mod plugin;
+mod process_records;
 mod setup_process_plugin;
 
 use communicator::*;
 pub use plugin::*;
+pub use process_records::*;
 pub use setup_process_plugin::*;
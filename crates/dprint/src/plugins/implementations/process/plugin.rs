@@ -10,6 +10,7 @@ use std::sync::Arc;
 
 use crate::configuration::RawPluginConfig;
 use crate::environment::Environment;
+use crate::plugins::get_process_schema_compatibility;
 use crate::plugins::InitializedPlugin;
 use crate::plugins::InitializedPluginFormatRequest;
 use crate::plugins::Plugin;
@@ -22,7 +23,7 @@ static PLUGIN_FILE_INITIALIZE: std::sync::Once = std::sync::Once::new();
 /// Use this to get an executable file name that also works in the tests.
 pub fn get_test_safe_executable_path(executable_file_path: PathBuf, environment: &impl Environment) -> PathBuf {
   if environment.is_real() {
-    executable_file_path
+    environment.resolve_read_path(&executable_file_path)
   } else {
     // do this so that we can launch the process in the tests
     let tmp_dir = PathBuf::from("temp");
@@ -48,6 +49,7 @@ pub struct ProcessPlugin<TEnvironment: Environment> {
   executable_file_path: PathBuf,
   plugin_info: PluginInfo,
   config: Option<(RawPluginConfig, GlobalConfiguration)>,
+  env_vars: Option<Vec<(String, String)>>,
   plugins_collection: Arc<PluginsCollection<TEnvironment>>,
 }
 
@@ -63,6 +65,7 @@ impl<TEnvironment: Environment> ProcessPlugin<TEnvironment> {
       executable_file_path,
       plugin_info,
       config: None,
+      env_vars: None,
       plugins_collection,
     }
   }
@@ -101,6 +104,10 @@ impl<TEnvironment: Environment> Plugin for ProcessPlugin<TEnvironment> {
     self.plugin_info.update_url.as_deref()
   }
 
+  fn experiments(&self) -> &Vec<String> {
+    &self.plugin_info.experiments
+  }
+
   fn set_config(&mut self, plugin_config: RawPluginConfig, global_config: GlobalConfiguration) {
     self.config = Some((plugin_config, global_config));
   }
@@ -113,17 +120,37 @@ impl<TEnvironment: Environment> Plugin for ProcessPlugin<TEnvironment> {
     true
   }
 
+  fn set_env_vars(&mut self, env_vars: Vec<(String, String)>) {
+    self.env_vars = Some(env_vars);
+  }
+
   fn initialize(&self) -> BoxFuture<'static, Result<Arc<dyn InitializedPlugin>>> {
     let config = self.config.as_ref().expect("Call set_config first.");
     let plugin_name = self.plugin_info.name.clone();
     let executable_file_path = self.executable_file_path.clone();
     let config = (config.1.clone(), config.0.properties.clone());
+    let env_vars = self.env_vars.clone();
     let environment = self.environment.clone();
     let plugins_collection = self.plugins_collection.clone();
     async move {
-      let communicator =
-        InitializedProcessPluginCommunicator::new(plugin_name, executable_file_path, config, environment.clone(), plugins_collection.clone()).await?;
+      let communicator = InitializedProcessPluginCommunicator::new(
+        plugin_name.clone(),
+        executable_file_path,
+        config,
+        env_vars,
+        environment.clone(),
+        plugins_collection.clone(),
+      )
+      .await?;
+      let schema_version = communicator.get_inner().await?.schema_version();
       let process_plugin = InitializedProcessPlugin::new(communicator)?;
+      log_verbose!(
+        environment,
+        "Plugin {} uses process plugin schema version {} ({}).",
+        plugin_name,
+        schema_version,
+        get_process_schema_compatibility(schema_version).description()
+      );
 
       let result: Arc<dyn InitializedPlugin> = Arc::new(process_plugin);
       Ok(result)
@@ -139,9 +166,9 @@ pub struct InitializedProcessPlugin<TEnvironment: Environment> {
 
 impl<TEnvironment: Environment> InitializedProcessPlugin<TEnvironment> {
   pub fn new(communicator: InitializedProcessPluginCommunicator<TEnvironment>) -> Result<Self> {
-    Ok(Self {
-      communicator: Arc::new(communicator),
-    })
+    let communicator = Arc::new(communicator);
+    InitializedProcessPluginCommunicator::monitor_lifetime(&communicator);
+    Ok(Self { communicator })
   }
 }
 
@@ -170,4 +197,11 @@ impl<TEnvironment: Environment> InitializedPlugin for InitializedProcessPlugin<T
     let communicator = self.communicator.clone();
     async move { communicator.shutdown().await }.boxed()
   }
+  fn peak_memory_bytes(&self) -> Option<u64> {
+    self.communicator.peak_memory_bytes()
+  }
+
+  fn supports_range_formatting(&self) -> bool {
+    true
+  }
 }
@@ -0,0 +1,122 @@
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::environment::DirEntryKind;
+use crate::environment::Environment;
+
+/// A record of a running process plugin, which is removed when the
+/// plugin shuts down. Records that are left over are from a CLI that
+/// didn't exit cleanly and may point to orphaned process plugins.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct ProcessRecord {
+  cli_process_id: u32,
+  executable_file_path: PathBuf,
+}
+
+fn get_process_records_dir(environment: &impl Environment) -> PathBuf {
+  environment.get_cache_dir().join("processes")
+}
+
+fn get_process_record_path(process_id: u32, environment: &impl Environment) -> PathBuf {
+  get_process_records_dir(environment).join(format!("{}.json", process_id))
+}
+
+/// Records the running process plugin, which isn't done when writing is disallowed.
+pub fn write_process_record(process_id: u32, executable_file_path: &Path, environment: &impl Environment) -> Result<()> {
+  if environment.is_read_only() {
+    return Ok(());
+  }
+  let record = ProcessRecord {
+    cli_process_id: std::process::id(),
+    executable_file_path: executable_file_path.to_path_buf(),
+  };
+  environment.mk_dir_all(get_process_records_dir(environment))?;
+  environment.write_file(get_process_record_path(process_id, environment), &serde_json::to_string(&record)?)
+}
+
+pub fn remove_process_record(process_id: u32, environment: &impl Environment) {
+  if environment.is_read_only() {
+    return;
+  }
+  let _ = environment.remove_file(get_process_record_path(process_id, environment));
+}
+
+/// Removes the records of the CLI processes that are no longer running,
+/// killing their process plugins that are still running.
+///
+/// Returns the number of killed process plugins.
+pub fn recover_orphaned_process_plugins(environment: &impl Environment) -> usize {
+  let entries = match environment.dir_info(get_process_records_dir(environment)) {
+    Ok(entries) => entries,
+    Err(_) => return 0,
+  };
+  let mut killed_count = 0;
+  for entry in entries {
+    if !matches!(entry.kind, DirEntryKind::File) {
+      continue;
+    }
+    let process_id = match entry.path.file_stem().and_then(|stem| stem.to_str()).and_then(|stem| stem.parse::<u32>().ok()) {
+      Some(process_id) => process_id,
+      None => continue,
+    };
+    let record = environment
+      .read_file(&entry.path)
+      .ok()
+      .and_then(|text| serde_json::from_str::<ProcessRecord>(&text).ok());
+    if let Some(record) = &record {
+      if environment.is_process_active(record.cli_process_id) {
+        continue;
+      }
+      if environment.kill_orphaned_process(process_id, &record.executable_file_path) {
+        killed_count += 1;
+      }
+    }
+    log_verbose!(environment, "Removing stale process record: {}", entry.path.display());
+    let _ = environment.remove_file(&entry.path);
+  }
+  killed_count
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::environment::TestEnvironment;
+
+  #[test]
+  fn should_kill_orphaned_process_plugins() {
+    let environment = TestEnvironment::new();
+    write_process_record(10, Path::new("/cache/plugins/a/a"), &environment).unwrap();
+    write_process_record(11, Path::new("/cache/plugins/b/b"), &environment).unwrap();
+    remove_process_record(11, &environment);
+    // recorded by a cli that's no longer running
+    let record_text = |executable_file_path: &str| {
+      serde_json::to_string(&ProcessRecord {
+        cli_process_id: 1,
+        executable_file_path: PathBuf::from(executable_file_path),
+      })
+      .unwrap()
+    };
+    environment.write_file("/cache/processes/20.json", &record_text("/cache/plugins/c/c")).unwrap();
+    environment.write_file("/cache/processes/21.json", &record_text("/cache/plugins/d/d")).unwrap();
+    environment.write_file("/cache/processes/22.json", &record_text("/cache/plugins/e/e")).unwrap();
+    environment.set_running_process(20, "/cache/plugins/c/c");
+    // the process id was reused by another executable
+    environment.set_running_process(21, "/other");
+
+    assert_eq!(recover_orphaned_process_plugins(&environment), 1);
+    assert!(!environment.is_running_process(20));
+    assert!(environment.is_running_process(21));
+    assert!(environment.path_exists("/cache/processes/10.json"));
+    assert!(!environment.path_exists("/cache/processes/11.json"));
+    assert!(!environment.path_exists("/cache/processes/20.json"));
+    assert!(!environment.path_exists("/cache/processes/21.json"));
+    assert!(!environment.path_exists("/cache/processes/22.json"));
+    assert_eq!(recover_orphaned_process_plugins(&environment), 0);
+  }
+}
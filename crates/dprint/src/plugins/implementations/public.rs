@@ -5,8 +5,11 @@ use std::sync::Arc;
 
 use dprint_core::plugins::PluginInfo;
 
+use super::exec;
 use super::process;
 use super::wasm;
+use crate::configuration::ExecPluginConfig;
+use crate::environment::CanonicalizedPathBuf;
 use crate::environment::Environment;
 use crate::plugins::Plugin;
 use crate::plugins::PluginCache;
@@ -99,7 +102,7 @@ pub async fn create_plugin<TEnvironment: Environment>(
         }
       };
 
-      Ok(Box::new(wasm::WasmPlugin::new(&file_bytes, cache_item.info, environment, plugins_collection)?))
+      Ok(Box::new(wasm::WasmPlugin::new(file_bytes, cache_item.info, environment, plugins_collection)?))
     }
     Some(PluginKind::Process) => {
       let cache_item = if !environment.path_exists(&cache_item.file_path) {
@@ -129,3 +132,11 @@ pub async fn create_plugin<TEnvironment: Environment>(
     }
   }
 }
+
+/// Creates a plugin that runs the command configured in the `"execPlugins"` property
+/// in the directory of the configuration file.
+///
+/// Unlike the other plugins, there's nothing to download or cache.
+pub fn create_exec_plugin<TEnvironment: Environment>(environment: TEnvironment, exec_config: ExecPluginConfig, cwd: CanonicalizedPathBuf) -> Box<dyn Plugin> {
+  Box::new(exec::ExecPlugin::new(environment, exec_config, cwd))
+}
@@ -6,6 +6,7 @@ use wasmer::Module;
 use wasmer::Store;
 
 use super::create_identity_import_object;
+use super::create_metered_store;
 use super::InitializedWasmPlugin;
 use crate::plugins::CompilationResult;
 
@@ -13,10 +14,7 @@ use crate::plugins::CompilationResult;
 pub fn compile(wasm_bytes: &[u8]) -> Result<CompilationResult> {
   let store = Store::default();
   let module = Module::new(&store, wasm_bytes)?;
-  let bytes = match module.serialize() {
-    Ok(bytes) => bytes,
-    Err(err) => bail!("Error serializing wasm module: {:#}", err),
-  };
+  let bytes = serialize_module(&module)?;
 
   // load the plugin and get the info
   let plugin = InitializedWasmPlugin::new(
@@ -25,8 +23,25 @@ pub fn compile(wasm_bytes: &[u8]) -> Result<CompilationResult> {
     Arc::new(move || create_identity_import_object(&store)), // we're not formatting anything so this is ok
     Default::default(),
     Default::default(),
+    Default::default(),
+    false,
+    Vec::new(),
   );
   let plugin_info = plugin.get_plugin_info()?;
 
   Ok(CompilationResult { bytes, plugin_info })
 }
+
+/// Compiles a Wasm module with fuel metering, which is only done for
+/// plugins configured with a fuel limit since it slows down formatting.
+pub fn compile_with_metering(wasm_bytes: &[u8]) -> Result<Vec<u8>> {
+  let module = Module::new(&create_metered_store(), wasm_bytes)?;
+  serialize_module(&module)
+}
+
+fn serialize_module(module: &Module) -> Result<Vec<u8>> {
+  match module.serialize() {
+    Ok(bytes) => Ok(bytes),
+    Err(err) => bail!("Error serializing wasm module: {:#}", err),
+  }
+}
@@ -0,0 +1,117 @@
+use anyhow::bail;
+use anyhow::Result;
+use wasmer::Array;
+use wasmer::Instance;
+use wasmer::Memory;
+use wasmer::Module;
+use wasmer::NativeFunc;
+use wasmer::Store;
+use wasmer::WasmPtr;
+use wasmer::WasmTypeList;
+
+use super::create_identity_import_object;
+use super::load_instance;
+
+/// A Wasm module from the `fileSelector` configuration property that
+/// decides which of the files matched by the globs are formatted.
+///
+/// The module exports its `memory` along with:
+///
+/// * `dprint_file_selector_alloc(size: u32) -> u32` - Gets a pointer to
+///   write the input of the provided size to.
+/// * `dprint_file_selector_select(ptr: u32, size: u32) -> u32` - Receives
+///   the paths of the candidate files relative to the configuration file's
+///   directory separated by newlines and returns a pointer to one byte per
+///   path, which is `1` to include the file or `0` to exclude it.
+pub struct WasmFileSelector {
+  memory: Memory,
+  alloc_func: NativeFunc<u32, u32>,
+  select_func: NativeFunc<(u32, u32), u32>,
+}
+
+impl WasmFileSelector {
+  pub fn new(wasm_bytes: &[u8]) -> Result<Self> {
+    let store = Store::default();
+    let module = match Module::new(&store, wasm_bytes) {
+      Ok(module) => module,
+      Err(err) => bail!("Error compiling file selector module: {:#}", err),
+    };
+    // provide the plugin imports in case the module was built with the plugin tooling
+    let instance = load_instance(&module, &create_identity_import_object(&store))?;
+    let memory = match instance.exports.get_memory("memory") {
+      Ok(memory) => memory.clone(),
+      Err(err) => bail!("Could not find the memory export in file selector module. Message: {:#}", err),
+    };
+    Ok(WasmFileSelector {
+      memory,
+      alloc_func: get_export(&instance, "dprint_file_selector_alloc")?,
+      select_func: get_export(&instance, "dprint_file_selector_select")?,
+    })
+  }
+
+  /// Gets whether to include each of the relative file paths.
+  pub fn select(&self, relative_paths: &[String]) -> Result<Vec<bool>> {
+    if relative_paths.is_empty() {
+      return Ok(Vec::new());
+    }
+    let input = relative_paths.join("\n");
+    let input_len = input.len() as u32;
+    let input_ptr = self.alloc_func.call(input_len)?;
+    let input_cells = match WasmPtr::<u8, Array>::new(input_ptr).deref(&self.memory, 0, input_len) {
+      Some(cells) => cells,
+      None => bail!("The file selector module allocated memory that was out of bounds."),
+    };
+    for (cell, byte) in input_cells.iter().zip(input.as_bytes()) {
+      cell.set(*byte);
+    }
+
+    let output_ptr = self.select_func.call(input_ptr, input_len)?;
+    let output_cells = match WasmPtr::<u8, Array>::new(output_ptr).deref(&self.memory, 0, relative_paths.len() as u32) {
+      Some(cells) => cells,
+      None => bail!("The file selector module returned a selection that was out of bounds."),
+    };
+    output_cells
+      .iter()
+      .map(|cell| match cell.get() {
+        0 => Ok(false),
+        1 => Ok(true),
+        value => bail!("The file selector module returned an unknown selection value: {}", value),
+      })
+      .collect()
+  }
+}
+
+fn get_export<Args, Rets>(instance: &Instance, name: &str) -> Result<NativeFunc<Args, Rets>>
+where
+  Args: WasmTypeList,
+  Rets: WasmTypeList,
+{
+  match instance.exports.get_native_function::<Args, Rets>(name) {
+    Ok(native_func) => Ok(native_func),
+    Err(err) => bail!("Could not find export in file selector module with name '{}'. Message: {:#}", name, err),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::test_helpers::FILE_SELECTOR_WAT;
+
+  #[test]
+  fn should_select_files() {
+    let selector = WasmFileSelector::new(FILE_SELECTOR_WAT.as_bytes()).unwrap();
+    let paths = vec!["a.txt".to_string(), "_b.txt".to_string(), "sub/c.txt".to_string()];
+    assert_eq!(selector.select(&paths).unwrap(), vec![true, false, true]);
+    assert_eq!(selector.select(&[]).unwrap(), Vec::<bool>::new());
+  }
+
+  #[test]
+  fn should_error_for_missing_exports() {
+    let err = WasmFileSelector::new(br#"(module (memory (export "memory") 1))"#).err().unwrap();
+    assert!(err
+      .to_string()
+      .starts_with("Could not find export in file selector module with name 'dprint_file_selector_alloc'."));
+  }
+}
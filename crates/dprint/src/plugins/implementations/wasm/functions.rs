@@ -10,6 +10,9 @@ use wasmer::WasmTypeList;
 
 use dprint_core::plugins::wasm::PLUGIN_SYSTEM_SCHEMA_VERSION;
 
+use crate::plugins::get_wasm_schema_compatibility;
+use crate::plugins::SchemaCompatibility;
+
 pub enum WasmFormatResult {
   NoChange = 0,
   Change = 1,
@@ -30,30 +33,80 @@ impl From<u8> for WasmFormatResult {
 pub struct WasmFunctions {
   instance: Instance,
   memory: Memory,
+  schema_version: u32,
+  supports_override_config: bool,
 }
 
 impl WasmFunctions {
   pub fn new(instance: Instance) -> Result<Self> {
-    match get_plugin_schema_version(&instance) {
-      Ok(plugin_schema_version) => {
-        if plugin_schema_version != PLUGIN_SYSTEM_SCHEMA_VERSION {
-          bail!(
-            "Invalid schema version: {} -- Expected: {}. This may indicate you should upgrade your dprint CLI or plugin.",
-            plugin_schema_version,
-            PLUGIN_SYSTEM_SCHEMA_VERSION
-          );
-        }
-      }
+    let schema_version = match get_plugin_schema_version(&instance) {
+      Ok(plugin_schema_version) => plugin_schema_version,
       Err(err) => {
         bail!(
           "Error determining plugin schema version. Are you sure this is a dprint plugin? {}",
           err.to_string()
         );
       }
+    };
+    let compatibility = get_wasm_schema_compatibility(schema_version);
+    if compatibility == SchemaCompatibility::Unsupported {
+      bail!(
+        "Invalid schema version: {} -- Expected: {}. This may indicate you should upgrade your dprint CLI or plugin.",
+        schema_version,
+        PLUGIN_SYSTEM_SCHEMA_VERSION
+      );
     }
     let memory = instance.exports.get_memory("memory")?.clone();
+    let supports_override_config = instance.exports.get_function("set_override_config").is_ok();
+
+    let functions = WasmFunctions {
+      instance,
+      memory,
+      schema_version,
+      supports_override_config,
+    };
+    // older and newer plugins load when they provide everything the CLI uses
+    if let Err(err) = functions.verify_required_exports() {
+      bail!(
+        "Plugin schema version {} is not compatible with the CLI's schema version {} ({:#}). This may indicate you should upgrade your dprint CLI or plugin.",
+        schema_version,
+        PLUGIN_SYSTEM_SCHEMA_VERSION,
+        err,
+      );
+    }
+    Ok(functions)
+  }
+
+  /// Verifies the exports required by every supported schema version
+  /// exist with the signatures the CLI calls them with.
+  fn verify_required_exports(&self) -> Result<()> {
+    self.get_export::<(), ()>("set_global_config")?;
+    self.get_export::<(), ()>("set_plugin_config")?;
+    self.get_export::<(), u32>("get_plugin_info")?;
+    self.get_export::<(), u32>("get_license_text")?;
+    self.get_export::<(), u32>("get_resolved_config")?;
+    self.get_export::<(), u32>("get_config_diagnostics")?;
+    self.get_export::<(), ()>("set_file_path")?;
+    self.get_export::<(), u8>("format")?;
+    self.get_export::<(), u32>("get_formatted_text")?;
+    self.get_export::<(), u32>("get_error_text")?;
+    self.get_export::<u32, ()>("clear_shared_bytes")?;
+    self.get_export::<(), u32>("get_wasm_memory_buffer_size")?;
+    self.get_export::<(), WasmPtr<u8, Array>>("get_wasm_memory_buffer")?;
+    self.get_export::<(u32, u32), ()>("set_buffer_with_shared_bytes")?;
+    self.get_export::<u32, ()>("add_to_shared_bytes_from_buffer")?;
+    if self.supports_override_config {
+      self.get_export::<(), ()>("set_override_config")?;
+    }
+    Ok(())
+  }
 
-    Ok(WasmFunctions { instance, memory })
+  pub fn schema_version(&self) -> u32 {
+    self.schema_version
+  }
+
+  pub fn supports_override_config(&self) -> bool {
+    self.supports_override_config
   }
 
   #[inline]
@@ -127,6 +180,11 @@ impl WasmFunctions {
     &self.memory
   }
 
+  #[inline]
+  pub fn instance(&self) -> &Instance {
+    &self.instance
+  }
+
   #[inline]
   pub fn clear_shared_bytes(&self, capacity: usize) -> Result<()> {
     let clear_shared_bytes_func = self.get_export::<u32, ()>("clear_shared_bytes")?;
@@ -0,0 +1,153 @@
+use dprint_core::configuration::ConfigKeyMap;
+use dprint_core::configuration::ConfigKeyValue;
+use dprint_core::configuration::ConfigurationDiagnostic;
+use std::time::Duration;
+use wasmer::Pages;
+
+const MAX_MEMORY_PROPERTY_NAME: &str = "maxMemoryMb";
+const FORMAT_TIMEOUT_PROPERTY_NAME: &str = "formatTimeout";
+const MAX_FUEL_PROPERTY_NAME: &str = "maxFuel";
+const FUEL_PER_CONFIG_UNIT: u64 = 1_000_000;
+/// The maximum number of pages of a 32-bit linear memory.
+const MAX_WASM_PAGES: u64 = 65536;
+
+/// Limits of the resources an instance of a Wasm plugin may use.
+///
+/// An instance that exceeds a limit fails formatting the file and
+/// is discarded, so the next file is formatted with a new instance.
+/// The memory can't grow beyond its limit and a call that runs out of
+/// fuel traps, so a plugin can't take down the process. A call that runs
+/// out of time is interrupted the same way when fuel is limited and is
+/// otherwise left to finish on its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WasmPluginLimits {
+  /// Maximum size of the linear memory in megabytes.
+  pub max_memory_mb: u64,
+  /// Maximum time formatting a single file may take.
+  pub format_timeout: Duration,
+  /// Maximum fuel formatting a single file may use, where
+  /// one unit of fuel is used for each instruction.
+  ///
+  /// Modules are only compiled with fuel metering when this is set.
+  pub max_fuel: Option<u64>,
+}
+
+impl Default for WasmPluginLimits {
+  fn default() -> Self {
+    WasmPluginLimits {
+      max_memory_mb: 2048,
+      format_timeout: Duration::from_secs(60),
+      max_fuel: None,
+    }
+  }
+}
+
+impl WasmPluginLimits {
+  pub fn max_memory_bytes(&self) -> u64 {
+    self.max_memory_mb * 1024 * 1024
+  }
+
+  pub fn max_memory_pages(&self) -> Pages {
+    let pages = self.max_memory_bytes() / wasmer::WASM_PAGE_SIZE as u64;
+    Pages(std::cmp::min(pages, MAX_WASM_PAGES) as u32)
+  }
+
+  /// Takes the limits out of the plugin's configuration since they're
+  /// handled by the CLI, returning diagnostics for invalid values.
+  pub fn take_from_config(properties: &mut ConfigKeyMap) -> (WasmPluginLimits, Vec<ConfigurationDiagnostic>) {
+    let mut limits = WasmPluginLimits::default();
+    let mut diagnostics = Vec::new();
+    if let Some(value) = properties.remove(MAX_MEMORY_PROPERTY_NAME) {
+      match get_positive_number(value) {
+        Some(value) => limits.max_memory_mb = value,
+        None => diagnostics.push(get_expected_positive_number_diagnostic(MAX_MEMORY_PROPERTY_NAME)),
+      }
+    }
+    if let Some(value) = properties.remove(FORMAT_TIMEOUT_PROPERTY_NAME) {
+      match get_positive_number(value) {
+        Some(value) => limits.format_timeout = Duration::from_secs(value),
+        None => diagnostics.push(get_expected_positive_number_diagnostic(FORMAT_TIMEOUT_PROPERTY_NAME)),
+      }
+    }
+    // in millions of instructions since the configuration only supports 32-bit numbers
+    if let Some(value) = properties.remove(MAX_FUEL_PROPERTY_NAME) {
+      match get_positive_number(value) {
+        Some(value) => limits.max_fuel = Some(value * FUEL_PER_CONFIG_UNIT),
+        None => diagnostics.push(get_expected_positive_number_diagnostic(MAX_FUEL_PROPERTY_NAME)),
+      }
+    }
+    (limits, diagnostics)
+  }
+}
+
+fn get_positive_number(value: ConfigKeyValue) -> Option<u64> {
+  match value {
+    ConfigKeyValue::Number(value) if value > 0 => Some(value as u64),
+    _ => None,
+  }
+}
+
+fn get_expected_positive_number_diagnostic(property_name: &str) -> ConfigurationDiagnostic {
+  ConfigurationDiagnostic {
+    property_name: property_name.to_string(),
+    message: "Expected a number greater than zero.".to_string(),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn should_take_limits_from_config() {
+    let mut properties = ConfigKeyMap::from([
+      ("maxMemoryMb".to_string(), ConfigKeyValue::from_i32(512)),
+      ("formatTimeout".to_string(), ConfigKeyValue::from_i32(5)),
+      ("maxFuel".to_string(), ConfigKeyValue::from_i32(100)),
+      ("lineWidth".to_string(), ConfigKeyValue::from_i32(80)),
+    ]);
+    let (limits, diagnostics) = WasmPluginLimits::take_from_config(&mut properties);
+    assert_eq!(
+      limits,
+      WasmPluginLimits {
+        max_memory_mb: 512,
+        format_timeout: Duration::from_secs(5),
+        max_fuel: Some(100_000_000),
+      }
+    );
+    assert_eq!(limits.max_memory_pages(), Pages(8192));
+    assert_eq!(diagnostics.len(), 0);
+    assert_eq!(properties, ConfigKeyMap::from([("lineWidth".to_string(), ConfigKeyValue::from_i32(80))]));
+  }
+
+  #[test]
+  fn should_get_diagnostics_for_invalid_limits() {
+    let mut properties = ConfigKeyMap::from([
+      ("maxMemoryMb".to_string(), ConfigKeyValue::from_i32(0)),
+      ("formatTimeout".to_string(), ConfigKeyValue::String("5s".to_string())),
+      ("maxFuel".to_string(), ConfigKeyValue::from_i32(-1)),
+    ]);
+    let (limits, diagnostics) = WasmPluginLimits::take_from_config(&mut properties);
+    assert_eq!(limits, WasmPluginLimits::default());
+    assert_eq!(limits.max_memory_pages(), Pages(32768));
+    assert_eq!(
+      diagnostics,
+      vec![
+        ConfigurationDiagnostic {
+          property_name: "maxMemoryMb".to_string(),
+          message: "Expected a number greater than zero.".to_string(),
+        },
+        ConfigurationDiagnostic {
+          property_name: "formatTimeout".to_string(),
+          message: "Expected a number greater than zero.".to_string(),
+        },
+        ConfigurationDiagnostic {
+          property_name: "maxFuel".to_string(),
+          message: "Expected a number greater than zero.".to_string(),
+        }
+      ]
+    );
+  }
+}
@@ -4,6 +4,10 @@ use wasmer::ImportObject;
 use wasmer::Instance;
 use wasmer::Module;
 use wasmer::Store;
+use wasmer::Universal;
+
+use super::MemoryLimitingTunables;
+use super::WasmPluginLimits;
 
 /// Loads a compiled wasm module from the specified bytes.
 pub fn load_instance(module: &Module, import_object: &ImportObject) -> Result<Instance> {
@@ -14,8 +18,11 @@ pub fn load_instance(module: &Module, import_object: &ImportObject) -> Result<In
   }
 }
 
-pub fn create_module(compiled_module_bytes: &[u8]) -> Result<Module> {
-  let store = Store::default();
+/// Creates a module from the compiled bytes whose instances' memory can't
+/// grow beyond the memory limit.
+pub fn create_module(compiled_module_bytes: &[u8], limits: &WasmPluginLimits) -> Result<Module> {
+  let engine = Universal::headless().engine();
+  let store = Store::new_with_tunables(&engine, MemoryLimitingTunables::new(limits.max_memory_pages()));
 
   unsafe {
     match Module::deserialize(&store, compiled_module_bytes) {
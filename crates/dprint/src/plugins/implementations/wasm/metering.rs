@@ -0,0 +1,79 @@
+use std::sync::Arc;
+use wasmer::wasmparser::Operator;
+use wasmer::CompilerConfig;
+use wasmer::Cranelift;
+use wasmer::Global;
+use wasmer::Instance;
+use wasmer::Store;
+use wasmer::Universal;
+use wasmer::Val;
+use wasmer_middlewares::metering::get_remaining_points;
+use wasmer_middlewares::metering::set_remaining_points;
+use wasmer_middlewares::metering::MeteringPoints;
+use wasmer_middlewares::Metering;
+
+/// The exported global the metering middleware stores the remaining fuel in.
+const REMAINING_FUEL_GLOBAL_NAME: &str = "wasmer_metering_remaining_points";
+
+/// Creates a store that compiles modules with fuel metering, which uses one
+/// unit of fuel per instruction and traps once a call runs out of fuel.
+///
+/// Instances start out with unlimited fuel because only formatting is
+/// limited, so the fuel is set before each call to format.
+pub fn create_metered_store() -> Store {
+  let metering = Arc::new(Metering::new(u64::MAX, |_: &Operator| -> u64 { 1 }));
+  let mut compiler_config = Cranelift::default();
+  compiler_config.push_middleware(metering);
+  Store::new(&Universal::new(compiler_config).engine())
+}
+
+/// The fuel of an instance that was compiled with metering.
+pub struct WasmInstanceFuel {
+  instance: Instance,
+  remaining_fuel_global: Global,
+}
+
+impl WasmInstanceFuel {
+  /// Gets the fuel of the instance, which is `None` when it
+  /// was compiled without metering.
+  pub fn new(instance: &Instance) -> Option<Self> {
+    let remaining_fuel_global = instance.exports.get_global(REMAINING_FUEL_GLOBAL_NAME).ok()?.clone();
+    Some(WasmInstanceFuel {
+      instance: instance.clone(),
+      remaining_fuel_global,
+    })
+  }
+
+  pub fn set_remaining(&self, fuel: u64) {
+    set_remaining_points(&self.instance, fuel);
+  }
+
+  pub fn is_exhausted(&self) -> bool {
+    matches!(get_remaining_points(&self.instance), MeteringPoints::Exhausted)
+  }
+
+  /// Gets an interrupter that may be used from another thread.
+  pub fn interrupter(&self) -> WasmInstanceInterrupter {
+    WasmInstanceInterrupter(self.remaining_fuel_global.clone())
+  }
+}
+
+/// Interrupts a call into an instance from another thread by using up its
+/// fuel, so it traps the next time the instance checks its fuel.
+///
+/// Wasmer 2.3 has no way of interrupting a running instance, so this sets the
+/// global the metering middleware stores the remaining fuel in while the call
+/// runs on another thread. `Global` is `Send + Sync` and `Global::set` only
+/// synchronizes with other gets and sets from the host, but the instance reads
+/// and writes the global directly. It's an aligned 64-bit value, so the
+/// instance sees either the previous value or zero and never a torn value. It
+/// may overwrite the zero with what it had remaining minus what it just used
+/// though, which is why interrupting should be retried until the call returns.
+#[derive(Clone)]
+pub struct WasmInstanceInterrupter(Global);
+
+impl WasmInstanceInterrupter {
+  pub fn interrupt(&self) {
+    let _ = self.0.set(Val::I64(0));
+  }
+}
@@ -1,13 +1,23 @@
 mod compile;
+mod file_selector;
 mod functions;
 mod import_object;
+mod limits;
 mod load_instance;
+mod metering;
 mod plugin;
 mod setup_wasm_plugin;
+mod snapshot;
+mod tunables;
 
 pub use compile::*;
+pub use file_selector::*;
 use functions::*;
 pub use import_object::*;
+pub use limits::*;
 use load_instance::*;
+use metering::*;
 pub use plugin::*;
 pub use setup_wasm_plugin::*;
+use snapshot::*;
+use tunables::*;
@@ -1,55 +1,128 @@
 use anyhow::anyhow;
+use anyhow::bail;
 use anyhow::Result;
 use dprint_core::plugins::BoxFuture;
 use dprint_core::plugins::CriticalFormatError;
 use dprint_core::plugins::FormatResult;
 use futures::FutureExt;
 use parking_lot::Mutex;
+use std::cell::Cell;
 use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 
 use dprint_core::configuration::ConfigKeyMap;
 use dprint_core::configuration::ConfigurationDiagnostic;
 use dprint_core::configuration::GlobalConfiguration;
 use dprint_core::plugins::PluginInfo;
 
+use super::compile_with_metering;
 use super::create_module;
 use super::create_pools_import_object;
+use super::get_metered_file_path_from_plugin_info;
+use super::get_wasm_file_path_from_plugin_info;
 use super::load_instance;
+use super::take_instance_snapshots_from_config;
 use super::ImportObjectEnvironment;
 use super::WasmFormatResult;
 use super::WasmFunctions;
+use super::WasmInstanceFuel;
+use super::WasmInstanceInterrupter;
+use super::WasmInstanceSnapshot;
+use super::WasmPluginLimits;
 use crate::configuration::RawPluginConfig;
 use crate::environment::Environment;
+use crate::plugins::get_wasm_schema_compatibility;
 use crate::plugins::InitializedPlugin;
 use crate::plugins::InitializedPluginFormatRequest;
 use crate::plugins::Plugin;
 use crate::plugins::PluginsCollection;
 
+/// How long to wait for a timed out call to trap after interrupting it.
+const INTERRUPT_RETRY_DELAY: Duration = Duration::from_millis(10);
+/// How many times a timed out call is interrupted before it's left to finish on its own.
+const MAX_INTERRUPT_ATTEMPTS: usize = 100;
+
+enum WasmPluginModule {
+  Compiled(Vec<u8>),
+  Created(wasmer::Module),
+}
+
 pub struct WasmPlugin<TEnvironment: Environment> {
-  module: wasmer::Module,
+  /// The module is created once the limits are known from the configuration.
+  module: Mutex<WasmPluginModule>,
   plugin_info: PluginInfo,
   config: Option<(RawPluginConfig, GlobalConfiguration)>,
+  limits: WasmPluginLimits,
+  instance_snapshots: bool,
+  /// Diagnostics of the properties of the plugin's configuration that are handled by the CLI.
+  cli_property_diagnostics: Vec<ConfigurationDiagnostic>,
   environment: TEnvironment,
   plugin_pools: Arc<PluginsCollection<TEnvironment>>,
 }
 
 impl<TEnvironment: Environment> WasmPlugin<TEnvironment> {
   pub fn new(
-    compiled_wasm_bytes: &[u8],
+    compiled_wasm_bytes: Vec<u8>,
     plugin_info: PluginInfo,
     environment: TEnvironment,
     plugin_pools: Arc<PluginsCollection<TEnvironment>>,
   ) -> Result<Self> {
-    let module = create_module(compiled_wasm_bytes)?;
     Ok(WasmPlugin {
       environment,
       plugin_pools,
-      module,
+      module: Mutex::new(WasmPluginModule::Compiled(compiled_wasm_bytes)),
       plugin_info,
       config: None,
+      limits: Default::default(),
+      instance_snapshots: false,
+      cli_property_diagnostics: Vec::new(),
     })
   }
+
+  fn get_or_create_module(&self) -> Result<wasmer::Module> {
+    let mut module = self.module.lock();
+    let created_module = match &*module {
+      WasmPluginModule::Created(module) => return Ok(module.clone()),
+      WasmPluginModule::Compiled(compiled_wasm_bytes) => match self.limits.max_fuel {
+        Some(_) => create_module(&self.get_metered_compiled_wasm_bytes()?, &self.limits)?,
+        None => create_module(compiled_wasm_bytes, &self.limits)?,
+      },
+    };
+    // the compiled bytes are no longer necessary
+    *module = WasmPluginModule::Created(created_module.clone());
+    Ok(created_module)
+  }
+
+  /// Gets the module compiled with fuel metering, which is compiled
+  /// from the plugin's Wasm file and cached the first time.
+  fn get_metered_compiled_wasm_bytes(&self) -> Result<Vec<u8>> {
+    let metered_file_path = get_metered_file_path_from_plugin_info(&self.plugin_info, &self.environment);
+    if self.environment.path_exists(&metered_file_path) {
+      return self.environment.read_file_bytes(&metered_file_path);
+    }
+
+    let wasm_file_path = get_wasm_file_path_from_plugin_info(&self.plugin_info, &self.environment);
+    if !self.environment.path_exists(&wasm_file_path) {
+      bail!(
+        "The {} plugin was cached without its Wasm file, which is necessary for \"maxFuel\". Run `dprint clear-cache` to fix this.",
+        self.name(),
+      );
+    }
+    let wasm_bytes = self.environment.read_file_bytes(&wasm_file_path)?;
+    let compiled_wasm_bytes = self.environment.log_action_with_progress(
+      &format!("Compiling {} with fuel metering", self.name()),
+      |_| compile_with_metering(&wasm_bytes),
+      1,
+    )?;
+    if let Err(err) = self.environment.write_file_bytes(&metered_file_path, &compiled_wasm_bytes) {
+      log_verbose!(self.environment, "Error caching the metered module of the {} plugin: {:#}", self.name(), err);
+    }
+    Ok(compiled_wasm_bytes)
+  }
 }
 
 impl<TEnvironment: Environment> Plugin for WasmPlugin<TEnvironment> {
@@ -85,7 +158,17 @@ impl<TEnvironment: Environment> Plugin for WasmPlugin<TEnvironment> {
     self.plugin_info.update_url.as_deref()
   }
 
-  fn set_config(&mut self, plugin_config: RawPluginConfig, global_config: GlobalConfiguration) {
+  fn experiments(&self) -> &Vec<String> {
+    &self.plugin_info.experiments
+  }
+
+  fn set_config(&mut self, mut plugin_config: RawPluginConfig, global_config: GlobalConfiguration) {
+    let (limits, mut cli_property_diagnostics) = WasmPluginLimits::take_from_config(&mut plugin_config.properties);
+    let (instance_snapshots, snapshot_diagnostics) = take_instance_snapshots_from_config(&mut plugin_config.properties);
+    cli_property_diagnostics.extend(snapshot_diagnostics);
+    self.limits = limits;
+    self.instance_snapshots = instance_snapshots;
+    self.cli_property_diagnostics = cli_property_diagnostics;
     self.config = Some((plugin_config, global_config));
   }
 
@@ -98,12 +181,16 @@ impl<TEnvironment: Environment> Plugin for WasmPlugin<TEnvironment> {
   }
 
   fn initialize(&self) -> BoxFuture<'static, Result<Arc<dyn InitializedPlugin>>> {
-    let store = wasmer::Store::default();
+    let module = match self.get_or_create_module() {
+      Ok(module) => module,
+      Err(err) => return Box::pin(futures::future::ready(Err(err))),
+    };
+    let store = module.store().clone();
     // need to call set_config first to ensure this doesn't fail
     let (plugin_config, global_config) = self.config.as_ref().unwrap();
     let plugin = InitializedWasmPlugin::new(
       self.name().to_string(),
-      self.module.clone(),
+      module,
       Arc::new({
         let plugin_pools = self.plugin_pools.clone();
         let environment = self.environment.clone();
@@ -114,8 +201,20 @@ impl<TEnvironment: Environment> Plugin for WasmPlugin<TEnvironment> {
       }),
       global_config.clone(),
       plugin_config.properties.clone(),
+      self.limits,
+      self.instance_snapshots,
+      self.cli_property_diagnostics.clone(),
     );
+    let environment = self.environment.clone();
     async move {
+      let schema_version = plugin.get_plugin_schema_version()?;
+      log_verbose!(
+        environment,
+        "Plugin {} uses Wasm plugin schema version {} ({}).",
+        plugin.0.name,
+        schema_version,
+        get_wasm_schema_compatibility(schema_version).description()
+      );
       let result: Arc<dyn InitializedPlugin> = Arc::new(plugin);
       Ok(result)
     }
@@ -126,6 +225,10 @@ impl<TEnvironment: Environment> Plugin for WasmPlugin<TEnvironment> {
 struct InitializedWasmPluginInstance {
   wasm_functions: WasmFunctions,
   buffer_size: usize,
+  /// The fuel of the instance, which is `None` for modules compiled without metering.
+  fuel: Option<WasmInstanceFuel>,
+  /// Set when the instance exceeded a limit, so it's discarded instead of reused.
+  exceeded_limit: Cell<bool>,
 }
 
 impl InitializedWasmPluginInstance {
@@ -175,6 +278,12 @@ impl InitializedWasmPluginInstance {
   fn inner_format_text(&self, file_path: &Path, file_text: &str, override_config: &ConfigKeyMap) -> Result<FormatResult> {
     // send override config if necessary
     if !override_config.is_empty() {
+      if !self.wasm_functions.supports_override_config() {
+        return Ok(Err(anyhow!(
+          "Override configuration is not supported by plugin schema version {}. Upgrade the plugin to use it.",
+          self.wasm_functions.schema_version()
+        )));
+      }
       self.send_string(&match serde_json::to_string(override_config) {
         Ok(text) => text,
         Err(err) => return Ok(Err(err.into())),
@@ -261,31 +370,47 @@ impl InitializedWasmPluginInstance {
 struct InitializedWasmPluginInner {
   name: String,
   instances: Mutex<Vec<InitializedWasmPluginInstance>>,
+  /// The largest linear memory size in bytes of any released instance.
+  peak_memory_bytes: AtomicU64,
   // below is for recreating an instance after panic
   module: wasmer::Module,
   create_import_object: Arc<dyn Fn() -> wasmer::ImportObject + Send + Sync>,
   global_config: GlobalConfiguration,
   plugin_config: ConfigKeyMap,
+  limits: WasmPluginLimits,
+  cli_property_diagnostics: Vec<ConfigurationDiagnostic>,
+  /// Restores the state of the first initialized instance in new instances when enabled.
+  snapshot: Mutex<Option<Arc<WasmInstanceSnapshot>>>,
+  instance_snapshots: AtomicBool,
 }
 
 #[derive(Clone)]
 pub struct InitializedWasmPlugin(Arc<InitializedWasmPluginInner>);
 
 impl InitializedWasmPlugin {
+  #[allow(clippy::too_many_arguments)]
   pub fn new(
     name: String,
     module: wasmer::Module,
     create_import_object: Arc<dyn Fn() -> wasmer::ImportObject + Send + Sync>,
     global_config: GlobalConfiguration,
     plugin_config: ConfigKeyMap,
+    limits: WasmPluginLimits,
+    instance_snapshots: bool,
+    cli_property_diagnostics: Vec<ConfigurationDiagnostic>,
   ) -> Self {
     Self(Arc::new(InitializedWasmPluginInner {
       name,
       instances: Default::default(),
+      peak_memory_bytes: Default::default(),
       module,
       create_import_object,
       global_config,
       plugin_config,
+      limits,
+      cli_property_diagnostics,
+      snapshot: Default::default(),
+      instance_snapshots: AtomicBool::new(instance_snapshots),
     }))
   }
 
@@ -293,6 +418,10 @@ impl InitializedWasmPlugin {
     self.with_instance(|instance| instance.plugin_info())
   }
 
+  pub fn get_plugin_schema_version(&self) -> Result<u32> {
+    self.with_instance(|instance| Ok(instance.wasm_functions.schema_version()))
+  }
+
   fn with_instance<T>(&self, action: impl Fn(&InitializedWasmPluginInstance) -> Result<T>) -> Result<T> {
     let instance = match self.get_or_create_instance() {
       Ok(instance) => instance,
@@ -341,6 +470,38 @@ impl InitializedWasmPlugin {
     }
   }
 
+  /// Errors and marks the instance to be discarded when it exceeded a limit.
+  fn check_limits(&self, instance: &InitializedWasmPluginInstance, timed_out: &AtomicBool, trapped: bool) -> Result<()> {
+    let limits = &self.0.limits;
+    if timed_out.load(Ordering::SeqCst) {
+      // the timeout was already reported
+      instance.exceeded_limit.set(true);
+      return Err(anyhow!("The {} plugin timed out.", self.0.name));
+    }
+    if let (Some(fuel), Some(max_fuel)) = (&instance.fuel, limits.max_fuel) {
+      if fuel.is_exhausted() {
+        instance.exceeded_limit.set(true);
+        return Err(anyhow!(
+          "The {} plugin ran out of fuel, which is limited to {} million instructions. The limit may be increased via \"maxFuel\" in the plugin's configuration.",
+          self.0.name,
+          max_fuel / 1_000_000,
+        ));
+      }
+    }
+    // growing the memory beyond the limit fails, which the plugin will most likely panic on
+    let memory_bytes = instance.wasm_functions.get_memory().data_size();
+    if trapped && memory_bytes >= limits.max_memory_bytes() / 2 {
+      instance.exceeded_limit.set(true);
+      return Err(anyhow!(
+        "The {} plugin failed while using {} MB of memory, which may be because it reached its limit of {} MB. The limit may be increased via \"maxMemoryMb\" in the plugin's configuration.",
+        self.0.name,
+        memory_bytes / 1024 / 1024,
+        limits.max_memory_mb,
+      ));
+    }
+    Ok(())
+  }
+
   fn get_or_create_instance(&self) -> Result<InitializedWasmPluginInstance> {
     match self.0.instances.lock().pop() {
       Some(instance) => Ok(instance),
@@ -349,18 +510,66 @@ impl InitializedWasmPlugin {
   }
 
   fn release_instance(&self, plugin: InitializedWasmPluginInstance) {
-    self.0.instances.lock().push(plugin);
+    // linear memory never shrinks, so its current size is the peak
+    let memory_bytes = plugin.wasm_functions.get_memory().data_size();
+    self.0.peak_memory_bytes.fetch_max(memory_bytes, Ordering::Relaxed);
+    if !plugin.exceeded_limit.get() {
+      self.0.instances.lock().push(plugin);
+    }
   }
 
   fn create_instance(&self) -> Result<InitializedWasmPluginInstance> {
+    let limits = &self.0.limits;
+    let max_memory_pages = limits.max_memory_pages();
+    if let Some(minimum_pages) = self.0.module.exports().memories().map(|memory| memory.ty().minimum).max() {
+      if minimum_pages > max_memory_pages {
+        let minimum_bytes = minimum_pages.0 as u64 * wasmer::WASM_PAGE_SIZE as u64;
+        return Err(anyhow!(
+          "The {} plugin requires {} MB of memory when it starts, which exceeds its limit of {} MB. The limit may be increased via \"maxMemoryMb\" in the plugin's configuration.",
+          self.0.name,
+          (minimum_bytes + 1024 * 1024 - 1) / 1024 / 1024,
+          limits.max_memory_mb,
+        ));
+      }
+    }
+
     let instance = load_instance(&self.0.module, &(self.0.create_import_object)())?;
+    let fuel = WasmInstanceFuel::new(&instance);
     let wasm_functions = WasmFunctions::new(instance)?;
     let buffer_size = wasm_functions.get_wasm_memory_buffer_size()?;
 
-    let mut instance = InitializedWasmPluginInstance { wasm_functions, buffer_size };
+    let mut instance = InitializedWasmPluginInstance {
+      wasm_functions,
+      buffer_size,
+      fuel,
+      exceeded_limit: Cell::new(false),
+    };
 
-    instance.set_global_config(&self.0.global_config)?;
-    instance.set_plugin_config(&self.0.plugin_config)?;
+    let snapshot = self.0.snapshot.lock().clone();
+    let restored_snapshot = match snapshot {
+      Some(snapshot) => match snapshot.restore(instance.wasm_functions.instance(), instance.wasm_functions.get_memory()) {
+        Ok(()) => true,
+        Err(_) => {
+          // the snapshot is validated before anything is restored, so
+          // initialize this instance and the instances from now on instead
+          self.0.instance_snapshots.store(false, Ordering::SeqCst);
+          *self.0.snapshot.lock() = None;
+          false
+        }
+      },
+      None => false,
+    };
+    if !restored_snapshot {
+      instance.set_global_config(&self.0.global_config)?;
+      instance.set_plugin_config(&self.0.plugin_config)?;
+      if self.0.instance_snapshots.load(Ordering::SeqCst) {
+        // plugins with an exported global that can't be restored are initialized every time
+        match WasmInstanceSnapshot::take(instance.wasm_functions.instance(), instance.wasm_functions.get_memory()) {
+          Some(snapshot) => *self.0.snapshot.lock() = Some(Arc::new(snapshot)),
+          None => self.0.instance_snapshots.store(false, Ordering::SeqCst),
+        }
+      }
+    }
 
     Ok(instance)
   }
@@ -390,9 +599,12 @@ impl InitializedPlugin for InitializedWasmPlugin {
   fn config_diagnostics(&self) -> BoxFuture<'static, Result<Vec<ConfigurationDiagnostic>>> {
     let plugin = self.clone();
     async move {
-      tokio::task::spawn_blocking(move || plugin.with_instance(move |instance| instance.config_diagnostics()))
+      let mut diagnostics = plugin.0.cli_property_diagnostics.clone();
+      let plugin_diagnostics = tokio::task::spawn_blocking(move || plugin.with_instance(move |instance| instance.config_diagnostics()))
         .await
-        .unwrap()
+        .unwrap()?;
+      diagnostics.extend(plugin_diagnostics);
+      Ok(diagnostics)
     }
     .boxed()
   }
@@ -413,7 +625,55 @@ impl InitializedPlugin for InitializedWasmPlugin {
       }
 
       // todo: support cancellation in Wasm plugins
-      tokio::task::spawn_blocking(move || plugin.with_instance(move |instance| instance.format_text(&file_path, &file_text, &override_config))).await?
+      let name = plugin.0.name.clone();
+      let format_timeout = plugin.0.limits.format_timeout;
+      let max_fuel = plugin.0.limits.max_fuel;
+      let timed_out = Arc::new(AtomicBool::new(false));
+      let interrupter: Arc<Mutex<Option<WasmInstanceInterrupter>>> = Default::default();
+      let mut handle = tokio::task::spawn_blocking({
+        let timed_out = timed_out.clone();
+        let interrupter = interrupter.clone();
+        move || {
+          plugin.with_instance(|instance| {
+            if let (Some(fuel), Some(max_fuel)) = (&instance.fuel, max_fuel) {
+              fuel.set_remaining(max_fuel);
+              *interrupter.lock() = Some(fuel.interrupter());
+            }
+            let result = instance.format_text(&file_path, &file_text, &override_config);
+            interrupter.lock().take();
+            let trapped = matches!(&result, Err(err) if err.downcast_ref::<CriticalFormatError>().is_some());
+            plugin.check_limits(instance, &timed_out, trapped)?;
+            if let Some(fuel) = &instance.fuel {
+              // only formatting is limited
+              fuel.set_remaining(u64::MAX);
+            }
+            result
+          })
+        }
+      });
+      match tokio::time::timeout(format_timeout, &mut handle).await {
+        Ok(result) => result?,
+        Err(_) => {
+          timed_out.store(true, Ordering::SeqCst);
+          // the instance may not see its fuel being used up the first time, so keep interrupting
+          // it for a while until the call traps. Otherwise, the call is left to finish on its own
+          // and the instance is discarded once it does.
+          for _ in 0..MAX_INTERRUPT_ATTEMPTS {
+            match interrupter.lock().as_ref() {
+              Some(interrupter) => interrupter.interrupt(),
+              None => break, // not metered or already returned
+            }
+            if tokio::time::timeout(INTERRUPT_RETRY_DELAY, &mut handle).await.is_ok() {
+              break;
+            }
+          }
+          Err(anyhow!(
+            "The {} plugin took longer than its time limit of {} seconds. The limit may be increased via \"formatTimeout\" in the plugin's configuration.",
+            name,
+            format_timeout.as_secs(),
+          ))
+        }
+      }
     }
     .boxed()
   }
@@ -421,4 +681,14 @@ impl InitializedPlugin for InitializedWasmPlugin {
   fn shutdown(&self) -> BoxFuture<'static, ()> {
     Box::pin(futures::future::ready(()))
   }
+  fn peak_memory_bytes(&self) -> Option<u64> {
+    match self.0.peak_memory_bytes.load(Ordering::Relaxed) {
+      0 => None,
+      bytes => Some(bytes),
+    }
+  }
+
+  fn supports_range_formatting(&self) -> bool {
+    false
+  }
 }
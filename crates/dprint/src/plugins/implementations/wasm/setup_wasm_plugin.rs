@@ -1,4 +1,5 @@
 use crate::utils::PathSource;
+use std::ffi::OsString;
 use std::path::PathBuf;
 
 use anyhow::Result;
@@ -17,6 +18,23 @@ pub fn get_file_path_from_plugin_info(plugin_info: &PluginInfo, environment: &im
   plugin_cache_dir_path.join(format!("{}-{}-{}", plugin_info.version, WASMER_COMPILER_VERSION, environment.cpu_arch()))
 }
 
+/// Gets the path of the plugin's Wasm file, which is kept in order to compile
+/// the module with fuel metering once a plugin is configured with a fuel limit.
+pub fn get_wasm_file_path_from_plugin_info(plugin_info: &PluginInfo, environment: &impl Environment) -> PathBuf {
+  with_file_name_suffix(get_file_path_from_plugin_info(plugin_info, environment), ".wasm")
+}
+
+/// Gets the path of the plugin's module that was compiled with fuel metering.
+pub fn get_metered_file_path_from_plugin_info(plugin_info: &PluginInfo, environment: &impl Environment) -> PathBuf {
+  with_file_name_suffix(get_file_path_from_plugin_info(plugin_info, environment), "-metered")
+}
+
+fn with_file_name_suffix(file_path: PathBuf, suffix: &str) -> PathBuf {
+  let mut file_path = OsString::from(file_path);
+  file_path.push(suffix);
+  PathBuf::from(file_path)
+}
+
 pub fn setup_wasm_plugin<TEnvironment: Environment>(url_or_file_path: &PathSource, file_bytes: &[u8], environment: &TEnvironment) -> Result<SetupPluginResult> {
   let compile_result = environment.log_action_with_progress(
     &format!("Compiling {}", url_or_file_path.display()),
@@ -27,6 +45,7 @@ pub fn setup_wasm_plugin<TEnvironment: Environment>(url_or_file_path: &PathSourc
   let plugin_cache_file_path = get_file_path_from_plugin_info(&plugin_info, environment);
   environment.mk_dir_all(plugin_cache_file_path.parent().unwrap())?;
   environment.write_file_bytes(&plugin_cache_file_path, &compile_result.bytes)?;
+  environment.write_file_bytes(get_wasm_file_path_from_plugin_info(&plugin_info, environment), file_bytes)?;
 
   Ok(SetupPluginResult {
     plugin_info,
@@ -37,5 +56,14 @@ pub fn setup_wasm_plugin<TEnvironment: Environment>(url_or_file_path: &PathSourc
 pub fn cleanup_wasm_plugin(plugin_info: &PluginInfo, environment: &impl Environment) -> Result<()> {
   let plugin_file_path = get_file_path_from_plugin_info(plugin_info, environment);
   environment.remove_file(plugin_file_path)?;
+  // these don't exist for plugins cached by older versions or that were never configured with a fuel limit
+  for file_path in [
+    get_wasm_file_path_from_plugin_info(plugin_info, environment),
+    get_metered_file_path_from_plugin_info(plugin_info, environment),
+  ] {
+    if environment.path_exists(&file_path) {
+      environment.remove_file(file_path)?;
+    }
+  }
   Ok(())
 }
@@ -0,0 +1,152 @@
+use anyhow::bail;
+use anyhow::Result;
+use dprint_core::configuration::ConfigKeyMap;
+use dprint_core::configuration::ConfigKeyValue;
+use dprint_core::configuration::ConfigurationDiagnostic;
+use wasmer::Extern;
+use wasmer::Instance;
+use wasmer::Memory;
+use wasmer::Mutability;
+use wasmer::Pages;
+use wasmer::Val;
+
+const INSTANCE_SNAPSHOTS_PROPERTY_NAME: &str = "instanceSnapshots";
+
+/// The state of a Wasm plugin instance after it was initialized with its configuration.
+///
+/// New instances restore this state instead of being initialized again, which is
+/// much faster for plugins that do a lot of work on startup (ex. loading grammars).
+/// This relies on the plugin keeping its state in its linear memory and exported globals.
+/// Other mutable globals, such as the stack pointer, are expected to be the same as when
+/// the instance was created once a call into the plugin returns.
+pub struct WasmInstanceSnapshot {
+  memory_pages: Pages,
+  memory_bytes: Vec<u8>,
+  globals: Vec<(String, SnapshotGlobalValue)>,
+}
+
+#[derive(Clone, Copy)]
+enum SnapshotGlobalValue {
+  I32(i32),
+  I64(i64),
+  F32(f32),
+  F64(f64),
+}
+
+impl WasmInstanceSnapshot {
+  /// Takes a snapshot of the instance, which is `None` when it has
+  /// an exported mutable global that can't be restored.
+  pub fn take(instance: &Instance, memory: &Memory) -> Option<Self> {
+    let mut globals = Vec::new();
+    for (name, export) in instance.exports.iter() {
+      if let Extern::Global(global) = export {
+        if global.ty().mutability == Mutability::Var {
+          let value = match global.get() {
+            Val::I32(value) => SnapshotGlobalValue::I32(value),
+            Val::I64(value) => SnapshotGlobalValue::I64(value),
+            Val::F32(value) => SnapshotGlobalValue::F32(value),
+            Val::F64(value) => SnapshotGlobalValue::F64(value),
+            _ => return None,
+          };
+          globals.push((name.to_string(), value));
+        }
+      }
+    }
+    // the instance isn't running, so nothing else is accessing its memory
+    let memory_bytes = unsafe { memory.data_unchecked() }.to_vec();
+    Some(WasmInstanceSnapshot {
+      memory_pages: memory.size(),
+      memory_bytes,
+      globals,
+    })
+  }
+
+  /// Restores the snapshot in a new instance of the same module.
+  ///
+  /// The instance is left as it was created when this errors, other than
+  /// its memory possibly having grown, so it may be initialized instead.
+  pub fn restore(&self, instance: &Instance, memory: &Memory) -> Result<()> {
+    let current_pages = memory.size();
+    if current_pages.0 > self.memory_pages.0 {
+      bail!("Expected a new instance to restore the snapshot in.");
+    }
+    let mut globals = Vec::with_capacity(self.globals.len());
+    for (name, value) in self.globals.iter() {
+      let global = instance.exports.get_global(name)?;
+      let value = match *value {
+        SnapshotGlobalValue::I32(value) => Val::I32(value),
+        SnapshotGlobalValue::I64(value) => Val::I64(value),
+        SnapshotGlobalValue::F32(value) => Val::F32(value),
+        SnapshotGlobalValue::F64(value) => Val::F64(value),
+      };
+      if global.ty().mutability != Mutability::Var || global.ty().ty != value.ty() {
+        bail!("Expected global '{}' of the snapshot to be mutable and of the same type.", name);
+      }
+      globals.push((name, global, value));
+    }
+    if current_pages.0 < self.memory_pages.0 {
+      if let Err(err) = memory.grow(Pages(self.memory_pages.0 - current_pages.0)) {
+        bail!("Error growing memory to restore the snapshot: {:#}", err);
+      }
+    }
+    // the instance was just created, so nothing else is accessing its memory
+    let memory_data = unsafe { memory.data_unchecked_mut() };
+    memory_data[..self.memory_bytes.len()].copy_from_slice(&self.memory_bytes);
+    for (name, global, value) in globals {
+      if let Err(err) = global.set(value) {
+        bail!("Error restoring global '{}' of the snapshot: {:#}", name, err);
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Takes whether new instances restore a snapshot of the first
+/// initialized instance out of the plugin's configuration.
+pub fn take_instance_snapshots_from_config(properties: &mut ConfigKeyMap) -> (bool, Vec<ConfigurationDiagnostic>) {
+  match properties.remove(INSTANCE_SNAPSHOTS_PROPERTY_NAME) {
+    Some(ConfigKeyValue::Bool(value)) => (value, Vec::new()),
+    Some(_) => (
+      false,
+      vec![ConfigurationDiagnostic {
+        property_name: INSTANCE_SNAPSHOTS_PROPERTY_NAME.to_string(),
+        message: "Expected a boolean.".to_string(),
+      }],
+    ),
+    None => (false, Vec::new()),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn should_take_instance_snapshots_from_config() {
+    let mut properties = ConfigKeyMap::from([
+      ("instanceSnapshots".to_string(), ConfigKeyValue::Bool(true)),
+      ("lineWidth".to_string(), ConfigKeyValue::from_i32(80)),
+    ]);
+    let (instance_snapshots, diagnostics) = take_instance_snapshots_from_config(&mut properties);
+    assert_eq!(instance_snapshots, true);
+    assert_eq!(diagnostics.len(), 0);
+    assert_eq!(properties, ConfigKeyMap::from([("lineWidth".to_string(), ConfigKeyValue::from_i32(80))]));
+
+    let (instance_snapshots, diagnostics) = take_instance_snapshots_from_config(&mut properties);
+    assert_eq!(instance_snapshots, false);
+    assert_eq!(diagnostics.len(), 0);
+
+    let mut properties = ConfigKeyMap::from([("instanceSnapshots".to_string(), ConfigKeyValue::from_i32(1))]);
+    let (instance_snapshots, diagnostics) = take_instance_snapshots_from_config(&mut properties);
+    assert_eq!(instance_snapshots, false);
+    assert_eq!(
+      diagnostics,
+      vec![ConfigurationDiagnostic {
+        property_name: "instanceSnapshots".to_string(),
+        message: "Expected a boolean.".to_string(),
+      }]
+    );
+  }
+}
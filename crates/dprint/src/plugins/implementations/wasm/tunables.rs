@@ -0,0 +1,91 @@
+use loupe::MemoryUsage;
+use std::ptr::NonNull;
+use std::sync::Arc;
+use wasmer::vm;
+use wasmer::vm::MemoryError;
+use wasmer::vm::MemoryStyle;
+use wasmer::vm::TableStyle;
+use wasmer::vm::VMMemoryDefinition;
+use wasmer::vm::VMTableDefinition;
+use wasmer::BaseTunables;
+use wasmer::MemoryType;
+use wasmer::Pages;
+use wasmer::TableType;
+use wasmer::Target;
+use wasmer::Tunables;
+
+/// Tunables that cap the maximum size of the linear memory of an instance,
+/// so growing the memory beyond it fails within the plugin.
+#[derive(MemoryUsage)]
+pub struct MemoryLimitingTunables {
+  max_pages: Pages,
+  base: BaseTunables,
+}
+
+impl MemoryLimitingTunables {
+  pub fn new(max_pages: Pages) -> Self {
+    MemoryLimitingTunables {
+      max_pages,
+      base: BaseTunables::for_target(&Target::default()),
+    }
+  }
+
+  fn adjust_memory(&self, requested: &MemoryType) -> MemoryType {
+    let mut adjusted = *requested;
+    adjusted.maximum = Some(match requested.maximum {
+      Some(maximum) => std::cmp::min(maximum, self.max_pages),
+      None => self.max_pages,
+    });
+    adjusted
+  }
+
+  fn validate_memory(&self, ty: &MemoryType) -> Result<(), MemoryError> {
+    if ty.minimum > self.max_pages {
+      return Err(MemoryError::Generic(format!(
+        "The minimum memory of {} pages exceeds the limit of {} pages.",
+        ty.minimum.0, self.max_pages.0
+      )));
+    }
+    Ok(())
+  }
+}
+
+impl Tunables for MemoryLimitingTunables {
+  fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+    self.base.memory_style(&self.adjust_memory(memory))
+  }
+
+  fn table_style(&self, table: &TableType) -> TableStyle {
+    self.base.table_style(table)
+  }
+
+  fn create_host_memory(&self, ty: &MemoryType, style: &MemoryStyle) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+    let adjusted = self.adjust_memory(ty);
+    self.validate_memory(&adjusted)?;
+    self.base.create_host_memory(&adjusted, style)
+  }
+
+  unsafe fn create_vm_memory(
+    &self,
+    ty: &MemoryType,
+    style: &MemoryStyle,
+    vm_definition_location: NonNull<VMMemoryDefinition>,
+  ) -> Result<Arc<dyn vm::Memory>, MemoryError> {
+    let adjusted = self.adjust_memory(ty);
+    self.validate_memory(&adjusted)?;
+    self.base.create_vm_memory(&adjusted, style, vm_definition_location)
+  }
+
+  fn create_host_table(&self, ty: &TableType, style: &TableStyle) -> Result<Arc<dyn vm::Table>, String> {
+    self.base.create_host_table(ty, style)
+  }
+
+  unsafe fn create_vm_table(
+    &self,
+    ty: &TableType,
+    style: &TableStyle,
+    vm_definition_location: NonNull<VMTableDefinition>,
+  ) -> Result<Arc<dyn vm::Table>, String> {
+    self.base.create_vm_table(ty, style, vm_definition_location)
+  }
+}
@@ -1,24 +1,32 @@
 mod cache;
 mod cache_manifest;
 mod collection;
+mod compatibility;
 mod helpers;
 mod implementations;
 mod name_resolution;
 mod plugin;
+mod provenance_log;
 mod repo;
 mod resolver;
+mod trust;
 mod types;
 
 pub use cache::*;
 use cache_manifest::*;
 pub use collection::*;
+pub use compatibility::*;
 pub use helpers::*;
 pub use plugin::*;
+pub use provenance_log::*;
 pub use repo::*;
 pub use resolver::*;
+pub use trust::*;
 pub use types::*;
 
 pub use implementations::compile_wasm;
+pub use implementations::recover_orphaned_process_plugins;
+pub use implementations::WasmFileSelector;
 pub use name_resolution::PluginNameResolutionMaps;
 
 use anyhow::bail;
@@ -28,6 +36,7 @@ use crate::configuration::get_global_config;
 use crate::configuration::get_plugin_config_map;
 use crate::configuration::GetGlobalConfigOptions;
 use crate::environment::Environment;
+use crate::utils::expand_env_var_references;
 
 use crate::arg_parser::CliArgs;
 use crate::configuration::resolve_config_from_args;
@@ -57,6 +66,28 @@ pub async fn resolve_plugins_and_err_if_empty<TEnvironment: Environment>(
   Ok(plugins)
 }
 
+/// Resolves the plugins, but skips the plugins that fail to resolve
+/// so the files of the other plugins may still be formatted.
+pub async fn resolve_plugins_allowing_failures_and_err_if_empty<TEnvironment: Environment>(
+  args: &CliArgs,
+  config: &ResolvedConfig,
+  environment: &TEnvironment,
+  plugin_resolver: &PluginResolver<TEnvironment>,
+) -> Result<Vec<Box<dyn Plugin>>> {
+  let (mut plugins, errors) = plugin_resolver.resolve_plugins_allowing_failures(config.plugins.clone()).await;
+  plugins.extend(plugin_resolver.resolve_exec_plugins(&config.exec_plugins, &config.base_path));
+  for err in errors.iter() {
+    environment.log_stderr(&format!("Skipping the files of a plugin that failed to load. {:#}", err));
+  }
+  // the configuration of the failed plugins is unknown, so don't
+  // report it as unknown properties
+  let plugins = set_plugins_config(plugins, args, config, environment, errors.is_empty())?;
+  if plugins.is_empty() {
+    bail!("No formatting plugins found. Ensure at least one is specified in the 'plugins' array of the configuration file.");
+  }
+  Ok(plugins)
+}
+
 pub async fn resolve_plugins<TEnvironment: Environment>(
   args: &CliArgs,
   config: &ResolvedConfig,
@@ -64,7 +95,39 @@ pub async fn resolve_plugins<TEnvironment: Environment>(
   plugin_resolver: &PluginResolver<TEnvironment>,
 ) -> Result<Vec<Box<dyn Plugin>>> {
   // resolve the plugins
-  let plugins = plugin_resolver.resolve_plugins(config.plugins.clone()).await?;
+  let mut plugins = plugin_resolver.resolve_plugins(config.plugins.clone()).await?;
+  plugins.extend(plugin_resolver.resolve_exec_plugins(&config.exec_plugins, &config.base_path));
+  set_plugins_config(plugins, args, config, environment, true)
+}
+
+/// Gets the environment variables of the `"processPluginEnv"` property to start
+/// the plugin's process with, which are declared by its name or configuration key.
+pub fn get_process_plugin_env_vars(plugin: &dyn Plugin, config: &ResolvedConfig, environment: &impl Environment) -> Result<Option<Vec<(String, String)>>> {
+  let process_plugin_env = &config.process_plugin_env;
+  let env_vars = match process_plugin_env.get(plugin.config_key()).or_else(|| process_plugin_env.get(plugin.name())) {
+    Some(env_vars) => env_vars,
+    None => return Ok(None),
+  };
+  if !plugin.is_process_plugin() {
+    bail!(
+      "Environment variables were provided for '{}' in the 'processPluginEnv' property, but it's not a process plugin.",
+      plugin.name()
+    );
+  }
+  let env_vars = env_vars
+    .iter()
+    .map(|(name, value)| Ok((name.to_string(), expand_env_var_references(value, |name| environment.env_var(name))?)))
+    .collect::<Result<Vec<_>>>()?;
+  Ok(Some(env_vars))
+}
+
+fn set_plugins_config<TEnvironment: Environment>(
+  plugins: Vec<Box<dyn Plugin>>,
+  args: &CliArgs,
+  config: &ResolvedConfig,
+  environment: &TEnvironment,
+  check_unknown_property_diagnostics: bool,
+) -> Result<Vec<Box<dyn Plugin>>> {
   let mut config_map = config.config_map.clone();
 
   // resolve each plugin's configuration
@@ -81,7 +144,7 @@ pub async fn resolve_plugins<TEnvironment: Environment>(
       // Skip checking these diagnostics when the user provides
       // plugins from the CLI args. They may be doing this to filter
       // to only specific plugins.
-      check_unknown_property_diagnostics: args.plugins.is_empty(),
+      check_unknown_property_diagnostics: check_unknown_property_diagnostics && args.plugins.is_empty(),
     },
   )?;
 
@@ -90,8 +153,24 @@ pub async fn resolve_plugins<TEnvironment: Environment>(
   for (plugin_config, plugin) in plugins_with_config {
     let mut plugin = plugin;
     plugin.set_config(plugin_config, global_config.clone());
+    if let Some(env_vars) = get_process_plugin_env_vars(&*plugin, config, environment)? {
+      plugin.set_env_vars(env_vars);
+    }
     plugins.push(plugin);
   }
+  // the plugins may be filtered by the CLI args or have failed to resolve
+  if check_unknown_property_diagnostics && args.plugins.is_empty() {
+    let unknown_plugin_name = config.process_plugin_env.keys().find(|name| {
+      !plugins
+        .iter()
+        .any(|plugin| plugin.config_key() == name.as_str() || plugin.name() == name.as_str())
+    });
+    if let Some(plugin_name) = unknown_plugin_name {
+      bail!("Unknown plugin '{}' in 'processPluginEnv' property.", plugin_name);
+    }
+  }
+
+  log_verbose!(environment, "{}", get_compatibility_matrix_text());
 
   Ok(plugins)
 }
@@ -1,10 +1,15 @@
+use anyhow::bail;
 use anyhow::Result;
+use indexmap::IndexMap;
 use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
 
+use crate::arg_parser::AssociationArg;
+use crate::configuration::ConfigOverride;
 use crate::environment::CanonicalizedPathBuf;
 use crate::patterns::get_patterns_as_glob_matcher;
+use crate::utils::get_bytes_hash;
 use crate::utils::get_lowercase_file_extension;
 use crate::utils::get_lowercase_file_name;
 use crate::utils::GlobMatcher;
@@ -20,11 +25,71 @@ pub struct PluginNameResolutionMaps {
   association_matchers: Vec<(String, Arc<GlobMatcher>)>,
   /// Associations matchers in a map.
   association_matchers_map: HashMap<String, Arc<GlobMatcher>>,
+  /// Associations provided for the invocation, which take precedence.
+  arg_association_matchers: Vec<(String, GlobMatcher)>,
+  arg_associations_hash: u64,
+  /// Plugins that shouldn't format the files matching a glob.
+  disabled_plugins_matchers: Vec<(GlobMatcher, Vec<String>)>,
+  overrides_hash: u64,
 }
 
 impl PluginNameResolutionMaps {
-  pub fn from_plugins(plugins: &[Box<dyn Plugin>], config_base_path: &CanonicalizedPathBuf) -> Result<Self> {
+  pub fn from_plugins(
+    plugins: &[Box<dyn Plugin>],
+    config_base_path: &CanonicalizedPathBuf,
+    overrides: &IndexMap<String, ConfigOverride>,
+    associations: &[AssociationArg],
+  ) -> Result<Self> {
     let mut plugin_name_maps = PluginNameResolutionMaps::default();
+    for association in associations {
+      let plugin = match plugins.iter().find(|p| p.name() == association.plugin || p.config_key() == association.plugin) {
+        Some(plugin) => plugin,
+        None => bail!(
+          "Could not find a plugin with the name or configuration key '{}' for the association '{}={}'.",
+          association.plugin,
+          association.file_pattern,
+          association.plugin,
+        ),
+      };
+      let matcher = get_patterns_as_glob_matcher(&[association.file_pattern.clone()], config_base_path)?;
+      plugin_name_maps.arg_association_matchers.push((plugin.name().to_string(), matcher));
+    }
+    if !associations.is_empty() {
+      let text = associations
+        .iter()
+        .map(|a| format!("{}={}", a.file_pattern, a.plugin))
+        .collect::<Vec<_>>()
+        .join("\n");
+      plugin_name_maps.arg_associations_hash = get_bytes_hash(text.as_bytes());
+    }
+
+    for (pattern, config_override) in overrides {
+      if config_override.disable_plugins.is_empty() {
+        continue;
+      }
+      let mut plugin_names = Vec::with_capacity(config_override.disable_plugins.len());
+      for name_or_config_key in config_override.disable_plugins.iter() {
+        match plugins.iter().find(|p| p.name() == name_or_config_key || p.config_key() == name_or_config_key) {
+          Some(plugin) => plugin_names.push(plugin.name().to_string()),
+          None => bail!(
+            "Could not find a plugin with the name or configuration key '{}' to disable for '{}' in 'overrides' property.",
+            name_or_config_key,
+            pattern,
+          ),
+        }
+      }
+      let matcher = get_patterns_as_glob_matcher(&[pattern.clone()], config_base_path)?;
+      plugin_name_maps.disabled_plugins_matchers.push((matcher, plugin_names));
+    }
+    if !plugin_name_maps.disabled_plugins_matchers.is_empty() {
+      let text = overrides
+        .iter()
+        .map(|(pattern, config_override)| format!("{}={}", pattern, config_override.disable_plugins.join(",")))
+        .collect::<Vec<_>>()
+        .join("\n");
+      plugin_name_maps.overrides_hash = get_bytes_hash(text.as_bytes());
+    }
+
     for plugin in plugins {
       let plugin_name = plugin.name();
 
@@ -52,11 +117,34 @@ impl PluginNameResolutionMaps {
     Ok(plugin_name_maps)
   }
 
+  /// A hash of the associations provided for the invocation or 0 when there are none.
+  pub fn arg_associations_hash(&self) -> u64 {
+    self.arg_associations_hash
+  }
+
+  /// A hash of the plugins disabled by the overrides or 0 when there are none.
+  pub fn overrides_hash(&self) -> u64 {
+    self.overrides_hash
+  }
+
   pub fn get_plugin_names_from_file_path(&self, file_path: &Path) -> Vec<String> {
     let mut plugin_names = Vec::new();
+    // disabled plugins are treated as if they weren't in the configuration for the file
+    let disabled_plugin_names = self.get_disabled_plugin_names(file_path);
+    let is_enabled = |plugin_name: &String| !disabled_plugin_names.contains(&plugin_name);
+
+    for (plugin_name, matcher) in self.arg_association_matchers.iter() {
+      if is_enabled(plugin_name) && matcher.matches(file_path) && !plugin_names.contains(plugin_name) {
+        plugin_names.push(plugin_name.to_owned());
+      }
+    }
+
+    if !plugin_names.is_empty() {
+      return plugin_names;
+    }
 
     for (plugin_name, matcher) in self.association_matchers.iter() {
-      if matcher.matches(file_path) {
+      if is_enabled(plugin_name) && matcher.matches(file_path) {
         plugin_names.push(plugin_name.to_owned());
       }
     }
@@ -68,7 +156,7 @@ impl PluginNameResolutionMaps {
     if let Some(file_name) = get_lowercase_file_name(file_path) {
       if let Some(plugin_names) = self.file_name_to_plugin_names_map.get(&file_name) {
         for plugin_name in plugin_names {
-          if self.is_not_associations_excluded(plugin_name, file_path) {
+          if is_enabled(plugin_name) && self.is_not_associations_excluded(plugin_name, file_path) {
             return vec![plugin_name.clone()];
           }
         }
@@ -78,7 +166,7 @@ impl PluginNameResolutionMaps {
     if let Some(ext) = get_lowercase_file_extension(file_path) {
       if let Some(plugin_names) = self.extension_to_plugin_names_map.get(&ext) {
         for plugin_name in plugin_names {
-          if self.is_not_associations_excluded(plugin_name, file_path) {
+          if is_enabled(plugin_name) && self.is_not_associations_excluded(plugin_name, file_path) {
             return vec![plugin_name.clone()];
           }
         }
@@ -88,6 +176,16 @@ impl PluginNameResolutionMaps {
     plugin_names
   }
 
+  fn get_disabled_plugin_names(&self, file_path: &Path) -> Vec<&String> {
+    let mut plugin_names = Vec::new();
+    for (matcher, disabled_plugin_names) in self.disabled_plugins_matchers.iter() {
+      if matcher.matches(file_path) {
+        plugin_names.extend(disabled_plugin_names.iter());
+      }
+    }
+    plugin_names
+  }
+
   fn is_not_associations_excluded(&self, plugin_name: &str, file_path: &Path) -> bool {
     if let Some(matcher) = self.association_matchers_map.get(plugin_name) {
       matcher.has_only_excludes() && matcher.matches_detail(file_path) == GlobMatchesDetail::NotMatched
@@ -30,6 +30,8 @@ pub trait Plugin: Send + Sync {
   fn config_schema_url(&self) -> &str;
   /// Gets the update url if it exists.
   fn update_url(&self) -> Option<&str>;
+  /// Gets the names of the experiments that may be enabled in the configuration.
+  fn experiments(&self) -> &Vec<String>;
   /// Sets the configuration for the plugin.
   fn set_config(&mut self, plugin_config: RawPluginConfig, global_config: GlobalConfiguration);
   /// Initializes the plugin.
@@ -40,6 +42,10 @@ pub trait Plugin: Send + Sync {
   /// Gets if this is a process plugin.
   fn is_process_plugin(&self) -> bool;
 
+  /// Sets the environment variables the plugin's process is started with
+  /// instead of the environment of the CLI. Only process plugins run in a process.
+  fn set_env_vars(&mut self, _env_vars: Vec<(String, String)>) {}
+
   /// Gets a hash that represents the current state of the plugin.
   /// This is used for the "incremental" feature to tell if a plugin has changed state.
   fn get_hash(&self) -> u64 {
@@ -80,6 +86,11 @@ pub trait InitializedPlugin: Send + Sync {
   fn format_text(&self, format_request: InitializedPluginFormatRequest) -> BoxFuture<'static, FormatResult>;
   /// Shuts down the plugin. This is used for process plugins.
   fn shutdown(&self) -> BoxFuture<'static, ()>;
+  /// Gets the peak memory usage of the plugin in bytes if known.
+  fn peak_memory_bytes(&self) -> Option<u64>;
+  /// Gets if the plugin formats only the range of a format request. Otherwise the
+  /// entire text is formatted and only the changes within the range are kept.
+  fn supports_range_formatting(&self) -> bool;
 }
 
 #[cfg(test)]
@@ -88,6 +99,7 @@ pub struct TestPlugin {
   config_key: String,
   file_extensions: Vec<String>,
   file_names: Vec<String>,
+  experiments: Vec<String>,
   initialized_test_plugin: Option<InitializedTestPlugin>,
   config: (RawPluginConfig, GlobalConfiguration),
 }
@@ -100,6 +112,7 @@ impl TestPlugin {
       config_key: String::from(config_key),
       file_extensions: file_extensions.into_iter().map(String::from).collect(),
       file_names: file_names.into_iter().map(String::from).collect(),
+      experiments: Vec::new(),
       initialized_test_plugin: Some(InitializedTestPlugin::new()),
       config: (
         Default::default(),
@@ -112,6 +125,11 @@ impl TestPlugin {
       ),
     }
   }
+
+  pub fn with_experiments(mut self, experiments: Vec<&'static str>) -> TestPlugin {
+    self.experiments = experiments.into_iter().map(String::from).collect();
+    self
+  }
 }
 
 #[cfg(test)]
@@ -136,6 +154,10 @@ impl Plugin for TestPlugin {
     None
   }
 
+  fn experiments(&self) -> &Vec<String> {
+    &self.experiments
+  }
+
   fn config_key(&self) -> &str {
     &self.config_key
   }
@@ -205,4 +227,12 @@ impl InitializedPlugin for InitializedTestPlugin {
   fn shutdown(&self) -> BoxFuture<'static, ()> {
     Box::pin(futures::future::ready(()))
   }
+
+  fn peak_memory_bytes(&self) -> Option<u64> {
+    None
+  }
+
+  fn supports_range_formatting(&self) -> bool {
+    false
+  }
 }
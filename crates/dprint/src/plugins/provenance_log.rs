@@ -0,0 +1,94 @@
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::environment::Environment;
+
+/// An artifact of a plugin that entered the cache.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProvenanceLogEntry {
+  /// Seconds since the unix epoch.
+  pub time: u64,
+  /// The user running dprint, when known.
+  pub user: Option<String>,
+  /// The url or file path the artifact was fetched from.
+  pub source: String,
+  /// The sha256 checksum of the artifact.
+  pub checksum: String,
+  pub plugin_name: String,
+  pub plugin_version: String,
+}
+
+/// Gets the path of the append-only log of the plugin artifacts that entered the cache.
+pub fn get_provenance_log_path(environment: &impl Environment) -> PathBuf {
+  environment.get_cache_dir().join("provenance.log")
+}
+
+pub fn get_provenance_log_user(environment: &impl Environment) -> Option<String> {
+  environment
+    .env_var("USER")
+    .or_else(|| environment.env_var("USERNAME"))
+    .filter(|user| !user.trim().is_empty())
+}
+
+/// Appends the entry to the log as a line of json.
+pub fn append_provenance_log_entry(entry: &ProvenanceLogEntry, environment: &impl Environment) -> Result<()> {
+  let file_path = get_provenance_log_path(environment);
+  if let Some(parent) = file_path.parent() {
+    environment.mk_dir_all(parent)?;
+  }
+  environment.append_file(&file_path, &format!("{}\n", serde_json::to_string(entry)?))
+}
+
+pub fn read_provenance_log(environment: &impl Environment) -> Vec<ProvenanceLogEntry> {
+  let file_path = get_provenance_log_path(environment);
+  let file_text = match environment.read_file(&file_path) {
+    Ok(file_text) => file_text,
+    Err(_) => return Vec::new(),
+  };
+  let mut entries = Vec::new();
+  for (index, line) in file_text.lines().enumerate() {
+    if line.trim().is_empty() {
+      continue;
+    }
+    match serde_json::from_str::<ProvenanceLogEntry>(line) {
+      Ok(entry) => entries.push(entry),
+      Err(err) => environment.log_stderr(&format!(
+        "WARNING: Skipped line {} of the provenance log {} that couldn't be read: {:#}",
+        index + 1,
+        file_path.display(),
+        err
+      )),
+    }
+  }
+  entries
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::environment::TestEnvironment;
+
+  #[test]
+  fn should_append_and_read_provenance_log() {
+    let environment = TestEnvironment::new();
+    assert_eq!(read_provenance_log(&environment), Vec::new());
+    let entry = |time: u64| ProvenanceLogEntry {
+      time,
+      user: Some("david".to_string()),
+      source: "https://plugins.dprint.dev/test.wasm".to_string(),
+      checksum: "abc".to_string(),
+      plugin_name: "test-plugin".to_string(),
+      plugin_version: "0.1.0".to_string(),
+    };
+    append_provenance_log_entry(&entry(1), &environment).unwrap();
+    append_provenance_log_entry(&entry(2), &environment).unwrap();
+    environment.append_file("/cache/provenance.log", "{ invalid\n").unwrap();
+    assert_eq!(read_provenance_log(&environment), vec![entry(1), entry(2)]);
+    assert_eq!(environment.take_stderr_messages().len(), 1);
+  }
+}
@@ -1,5 +1,7 @@
 mod read_info_file;
 mod read_update_url;
+mod resolve_plugin_range;
 
 pub use read_info_file::*;
 pub use read_update_url::*;
+pub use resolve_plugin_range::*;
@@ -2,6 +2,7 @@ use anyhow::anyhow;
 use anyhow::bail;
 use anyhow::Result;
 use jsonc_parser::parse_to_value;
+use jsonc_parser::JsonObject;
 use jsonc_parser::JsonValue;
 use url::Url;
 
@@ -47,12 +48,44 @@ impl PluginUpdateUrlInfo {
 }
 
 pub fn read_update_url(downloader: &impl UrlDownloader, url: &str) -> Result<Option<PluginUpdateUrlInfo>> {
+  let info_text = match read_update_url_text(downloader, url)? {
+    Some(info_text) => info_text,
+    None => return Ok(None),
+  };
+  let obj = parse_update_url_object(&info_text)?;
+  Ok(Some(take_update_url_info(obj)?))
+}
+
+/// Reads all the published versions of a plugin.
+pub fn read_update_versions_url(downloader: &impl UrlDownloader, url: &str) -> Result<Option<Vec<PluginUpdateUrlInfo>>> {
+  let info_text = match read_update_url_text(downloader, url)? {
+    Some(info_text) => info_text,
+    None => return Ok(None),
+  };
+  let mut obj = parse_update_url_object(&info_text)?;
+  let versions = obj
+    .take_array("versions")
+    .ok_or_else(|| anyhow!("Expected to find a versions property in the data."))?;
+  let mut result = Vec::with_capacity(versions.len());
+  for value in versions.into_iter() {
+    match value {
+      JsonValue::Object(obj) => result.push(take_update_url_info(obj)?),
+      _ => bail!("Expected object in versions array."),
+    }
+  }
+  Ok(Some(result))
+}
+
+fn read_update_url_text(downloader: &impl UrlDownloader, url: &str) -> Result<Option<String>> {
   let info_bytes = match downloader.download_file(url)? {
     Some(info_bytes) => info_bytes,
     None => return Ok(None),
   };
-  let info_text = String::from_utf8(info_bytes.to_vec())?;
-  let json_value = parse_to_value(&info_text, &Default::default())?;
+  Ok(Some(String::from_utf8(info_bytes.to_vec())?))
+}
+
+fn parse_update_url_object(info_text: &str) -> Result<JsonObject<'_>> {
+  let json_value = parse_to_value(info_text, &Default::default())?;
   let mut obj = match json_value {
     Some(JsonValue::Object(obj)) => obj,
     _ => bail!("Expected object in root element."),
@@ -75,17 +108,21 @@ pub fn read_update_url(downloader: &impl UrlDownloader, url: &str) -> Result<Opt
     );
   }
 
+  Ok(obj)
+}
+
+fn take_update_url_info(mut obj: JsonObject) -> Result<PluginUpdateUrlInfo> {
   let version = obj
     .take_string("version")
     .ok_or_else(|| anyhow!("Expected to find a version property in the data."))?;
   let url = obj.take_string("url").ok_or_else(|| anyhow!("Expected to find a url property in the data."))?;
   let checksum = obj.take_string("checksum");
 
-  Ok(Some(PluginUpdateUrlInfo {
+  Ok(PluginUpdateUrlInfo {
     version: version.to_string(),
     url: url.to_string(),
     checksum: checksum.map(|c| c.to_string()),
-  }))
+  })
 }
 
 #[cfg(test)]
@@ -147,4 +184,33 @@ mod test {
       None,
     );
   }
+
+  #[test]
+  fn should_get_versions() {
+    let mut builder = TestEnvironmentBuilder::new();
+    builder.add_remote_file(
+      "https://plugins.dprint.dev/plugin/versions.json",
+      r#"{ "schemaVersion": 1, "versions": [{ "url": "url1", "version": "1.0.0" }, { "url": "url2", "version": "1.1.0", "checksum": "checksum" }] }"#,
+    );
+    let environment = builder.build();
+    assert_eq!(
+      read_update_versions_url(&environment, "https://plugins.dprint.dev/plugin/versions.json").unwrap(),
+      Some(vec![
+        PluginUpdateUrlInfo {
+          version: "1.0.0".to_string(),
+          url: "url1".to_string(),
+          checksum: None,
+        },
+        PluginUpdateUrlInfo {
+          version: "1.1.0".to_string(),
+          url: "url2".to_string(),
+          checksum: Some("checksum".to_string()),
+        }
+      ])
+    );
+    assert_eq!(
+      read_update_versions_url(&environment, "https://plugins.dprint.dev/other/versions.json").unwrap(),
+      None,
+    );
+  }
 }
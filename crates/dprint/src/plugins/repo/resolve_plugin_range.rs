@@ -0,0 +1,89 @@
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Result;
+
+use super::read_update_url;
+use super::read_update_versions_url;
+use super::PluginUpdateUrlInfo;
+use crate::environment::UrlDownloader;
+use crate::plugins::PluginRangeReference;
+use crate::utils::Version;
+
+/// Gets the latest version of the plugin in the registry that satisfies the range.
+pub fn resolve_plugin_range(downloader: &impl UrlDownloader, reference: &PluginRangeReference) -> Result<PluginUpdateUrlInfo> {
+  let registry_name = reference.registry_name();
+  let versions_url = format!("https://plugins.dprint.dev/{}/versions.json", registry_name);
+  let versions = match read_update_versions_url(downloader, &versions_url)? {
+    Some(versions) => versions,
+    // fall back to only the latest version for registries that don't publish all versions
+    None => match read_update_url(downloader, &format!("https://plugins.dprint.dev/{}/latest.json", registry_name))? {
+      Some(info) => vec![info],
+      None => bail!("Could not find plugin with name '{}' in the registry.", reference.name),
+    },
+  };
+
+  versions
+    .into_iter()
+    .filter_map(|info| {
+      let version = Version::parse(&info.version)?;
+      if reference.range.matches(&version) {
+        Some((version, info))
+      } else {
+        None
+      }
+    })
+    .max_by_key(|(version, _)| *version)
+    .map(|(_, info)| info)
+    .ok_or_else(|| {
+      anyhow!(
+        "Could not find a version of {} in the registry that satisfies '{}'.",
+        reference.name,
+        reference.text
+      )
+    })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::environment::TestEnvironmentBuilder;
+
+  #[test]
+  fn should_resolve_latest_matching_version() {
+    let mut builder = TestEnvironmentBuilder::new();
+    builder.add_remote_file(
+      "https://plugins.dprint.dev/dprint/typescript/versions.json",
+      r#"{ "schemaVersion": 1, "versions": [
+        { "url": "https://plugins.dprint.dev/typescript-0.90.0.wasm", "version": "0.90.0" },
+        { "url": "https://plugins.dprint.dev/typescript-0.91.2.wasm", "version": "0.91.2" },
+        { "url": "https://plugins.dprint.dev/typescript-0.91.1.wasm", "version": "0.91.1" },
+        { "url": "https://plugins.dprint.dev/typescript-0.92.0.wasm", "version": "0.92.0" }
+      ] }"#,
+    );
+    let environment = builder.build();
+    let reference = PluginRangeReference::parse("typescript@^0.91").unwrap().unwrap();
+    assert_eq!(resolve_plugin_range(&environment, &reference).unwrap().version, "0.91.2");
+    let reference = PluginRangeReference::parse("typescript@^1").unwrap().unwrap();
+    assert_eq!(
+      resolve_plugin_range(&environment, &reference).err().unwrap().to_string(),
+      "Could not find a version of typescript in the registry that satisfies 'typescript@^1'."
+    );
+  }
+
+  #[test]
+  fn should_fall_back_to_latest() {
+    let mut builder = TestEnvironmentBuilder::new();
+    builder.add_remote_file(
+      "https://plugins.dprint.dev/owner/plugin/latest.json",
+      r#"{ "schemaVersion": 1, "url": "https://plugins.dprint.dev/owner/plugin-1.2.0.wasm", "version": "1.2.0" }"#,
+    );
+    let environment = builder.build();
+    let reference = PluginRangeReference::parse("owner/plugin@~1.2").unwrap().unwrap();
+    assert_eq!(resolve_plugin_range(&environment, &reference).unwrap().version, "1.2.0");
+    let reference = PluginRangeReference::parse("other@^1").unwrap().unwrap();
+    assert_eq!(
+      resolve_plugin_range(&environment, &reference).err().unwrap().to_string(),
+      "Could not find plugin with name 'other' in the registry."
+    );
+  }
+}
@@ -2,7 +2,10 @@ use anyhow::bail;
 use anyhow::Result;
 use std::sync::Arc;
 
+use super::implementations::create_exec_plugin;
 use super::implementations::create_plugin;
+use crate::configuration::ExecPluginConfig;
+use crate::environment::CanonicalizedPathBuf;
 use crate::environment::Environment;
 use crate::plugins::Plugin;
 use crate::plugins::PluginCache;
@@ -25,7 +28,59 @@ impl<TEnvironment: Environment> PluginResolver<TEnvironment> {
     }
   }
 
+  /// Gets a resolver that creates the plugins in the provided collection,
+  /// but shares the plugin cache with this resolver.
+  pub fn with_plugins_collection(&self, environment: TEnvironment, plugins_collection: Arc<PluginsCollection<TEnvironment>>) -> Self {
+    PluginResolver {
+      environment,
+      plugin_cache: self.plugin_cache.clone(),
+      plugins_collection,
+    }
+  }
+
+  /// Ensures the plugin is in the cache without creating it.
+  pub async fn cache_plugin(&self, plugin_reference: &PluginSourceReference) -> Result<()> {
+    self.plugin_cache.ensure_trusted(std::slice::from_ref(plugin_reference))?;
+    self.plugin_cache.get_plugin_cache_item(plugin_reference).await?;
+    Ok(())
+  }
+
+  pub fn take_compiled_wasm_plugin_names(&self) -> Vec<String> {
+    self.plugin_cache.take_compiled_wasm_plugin_names()
+  }
+
   pub async fn resolve_plugins(&self, plugin_references: Vec<PluginSourceReference>) -> Result<Vec<Box<dyn Plugin>>> {
+    let results = self.resolve_plugin_results(plugin_references).await;
+    let mut plugins = Vec::with_capacity(results.len());
+    for result in results {
+      plugins.push(result?);
+    }
+
+    Ok(plugins)
+  }
+
+  /// Resolves the plugins, but returns the errors of the plugins
+  /// that failed to resolve instead of failing.
+  pub async fn resolve_plugins_allowing_failures(&self, plugin_references: Vec<PluginSourceReference>) -> (Vec<Box<dyn Plugin>>, Vec<anyhow::Error>) {
+    let results = self.resolve_plugin_results(plugin_references).await;
+    let mut plugins = Vec::with_capacity(results.len());
+    let mut errors = Vec::new();
+    for result in results {
+      match result {
+        Ok(plugin) => plugins.push(plugin),
+        Err(err) => errors.push(err),
+      }
+    }
+
+    (plugins, errors)
+  }
+
+  async fn resolve_plugin_results(&self, plugin_references: Vec<PluginSourceReference>) -> Vec<Result<Box<dyn Plugin>>> {
+    // ask about all the untrusted plugins at once
+    if let Err(err) = self.plugin_cache.ensure_trusted(&plugin_references) {
+      return vec![Err(err)];
+    }
+
     let handles = plugin_references
       .into_iter()
       .map(|plugin_ref| {
@@ -34,16 +89,26 @@ impl<TEnvironment: Environment> PluginResolver<TEnvironment> {
       })
       .collect::<Vec<_>>();
 
-    let results = futures::future::join_all(handles).await;
-    let mut plugins = Vec::with_capacity(results.len());
-    for result in results {
-      plugins.push(result??);
-    }
+    futures::future::join_all(handles)
+      .await
+      .into_iter()
+      .map(|result| match result {
+        Ok(result) => result,
+        Err(err) => Err(err.into()),
+      })
+      .collect()
+  }
 
-    Ok(plugins)
+  /// Creates the plugins configured in the `"execPlugins"` property, which run their commands in the base path.
+  pub fn resolve_exec_plugins(&self, exec_plugins: &[ExecPluginConfig], base_path: &CanonicalizedPathBuf) -> Vec<Box<dyn Plugin>> {
+    exec_plugins
+      .iter()
+      .map(|exec_config| create_exec_plugin(self.environment.clone(), exec_config.clone(), base_path.clone()))
+      .collect()
   }
 
   pub async fn resolve_plugin(&self, plugin_reference: &PluginSourceReference) -> Result<Box<dyn Plugin>> {
+    self.plugin_cache.ensure_trusted(std::slice::from_ref(plugin_reference))?;
     match create_plugin(self.plugins_collection.clone(), &self.plugin_cache, self.environment.clone(), plugin_reference).await {
       Ok(plugin) => Ok(plugin),
       Err(err) => {
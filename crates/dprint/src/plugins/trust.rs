@@ -0,0 +1,384 @@
+use anyhow::bail;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::PathBuf;
+use url::Url;
+
+use crate::environment::Environment;
+use crate::plugins::PluginSourceReference;
+use crate::utils::PathSource;
+use crate::utils::PluginKind;
+
+const TRUST_REMOTE_PLUGINS_ENV_VAR_NAME: &str = "DPRINT_TRUST_REMOTE_PLUGINS";
+
+/// A decision on whether to run a remote plugin, which is made once per url and checksum.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginTrustDecision {
+  pub url: String,
+  /// The sha256 checksum of the downloaded plugin, which is `None` until a
+  /// plugin without a checksum in the configuration is downloaded.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub checksum: Option<String>,
+  pub trusted: bool,
+}
+
+/// Gets if the remote plugins are trusted without a prompt, which is the case when `--yes`
+/// is provided or the `DPRINT_TRUST_REMOTE_PLUGINS` environment variable is `1` (ex. on CI).
+pub fn get_assume_remote_plugins_trusted(yes: bool, environment: &impl Environment) -> bool {
+  yes || environment.env_var(TRUST_REMOTE_PLUGINS_ENV_VAR_NAME).as_deref() == Some("1")
+}
+
+fn get_plugin_trust_file_path(environment: &impl Environment) -> PathBuf {
+  environment.get_cache_dir().join("plugin-trust.json")
+}
+
+pub fn read_plugin_trust_decisions(environment: &impl Environment) -> Vec<PluginTrustDecision> {
+  let file_path = get_plugin_trust_file_path(environment);
+  match environment.read_file(file_path) {
+    Ok(text) => match serde_json::from_str(&text) {
+      Ok(decisions) => decisions,
+      Err(err) => {
+        log_verbose!(environment, "Ignoring plugin trust file that couldn't be read: {:#}", err);
+        Vec::new()
+      }
+    },
+    Err(_) => Vec::new(),
+  }
+}
+
+fn write_plugin_trust_decisions(decisions: &[PluginTrustDecision], environment: &impl Environment) -> Result<()> {
+  environment.write_file(get_plugin_trust_file_path(environment), &serde_json::to_string_pretty(decisions)?)
+}
+
+/// Asks the user whether to trust the remote plugins that will be downloaded and run for
+/// the first time, then stores the decision for each url and checksum.
+///
+/// The plugins are trusted without a prompt when `assume_trusted` is set (`--yes`) and
+/// otherwise this errors when not running in an interactive terminal (ex. CI).
+pub fn ensure_remote_plugins_trusted(references: &[&PluginSourceReference], assume_trusted: bool, environment: &impl Environment) -> Result<()> {
+  let mut decisions = read_plugin_trust_decisions(environment);
+  let mut undecided = Vec::new();
+  for reference in references {
+    let url = match &reference.path_source {
+      PathSource::Remote(remote) => &remote.url,
+      PathSource::Local(_) => continue,
+    };
+    // a plugin without a checksum in the configuration is verified once it's downloaded
+    let decision = decisions
+      .iter()
+      .filter(|d| d.url == url.as_str() && (reference.checksum.is_none() || is_same_checksum(d.checksum.as_deref(), reference.checksum.as_deref())))
+      .max_by_key(|d| d.trusted);
+    match decision {
+      Some(decision) if decision.trusted => {}
+      Some(_) if !assume_trusted => bail!("Previously declined to trust the plugin at {}. Provide --yes to trust it.", reference.display()),
+      _ => {
+        if !undecided.iter().any(|(_, r): &(&Url, &PluginSourceReference)| r == reference) {
+          undecided.push((url, *reference));
+        }
+      }
+    }
+  }
+
+  if undecided.is_empty() {
+    return Ok(());
+  }
+  if !assume_trusted && !environment.is_interactive() {
+    let urls = undecided.iter().map(|(url, _)| format!("\n  * {}", url)).collect::<String>();
+    bail!(
+      "The following remote plugins haven't been trusted:{}\n\nRun with --yes to trust them or set the {} environment variable to 1.",
+      urls,
+      TRUST_REMOTE_PLUGINS_ENV_VAR_NAME,
+    );
+  }
+
+  let trusted = if assume_trusted {
+    true
+  } else {
+    let mut text = String::from("The following remote plugins will be downloaded and run for the first time:");
+    for (url, reference) in undecided.iter() {
+      text.push_str(&format!("\n  * {}", url));
+      text.push_str(&format!("\n    Publisher: {}", get_publisher_text(url)));
+      text.push_str(&format!("\n    Checksum: {}", get_checksum_status_text(reference)));
+    }
+    environment.log_stderr(&text);
+    environment.confirm("Do you trust these plugins?", false)?
+  };
+
+  for (url, reference) in undecided.iter() {
+    decisions.retain(|d| !(d.url == url.as_str() && is_same_checksum(d.checksum.as_deref(), reference.checksum.as_deref())));
+    decisions.push(PluginTrustDecision {
+      url: url.to_string(),
+      checksum: reference.checksum.clone(),
+      trusted,
+    });
+  }
+  write_plugin_trust_decisions(&decisions, environment)?;
+
+  if !trusted {
+    bail!("Declined to trust the remote plugins.");
+  }
+  Ok(())
+}
+
+/// Ensures the downloaded bytes of a remote plugin are what was trusted before it's run.
+///
+/// This prompts again when a plugin without a checksum in the configuration changed
+/// since it was trusted and stores the checksum of the bytes with the decision.
+pub fn ensure_downloaded_plugin_trusted(url: &Url, checksum: &str, assume_trusted: bool, environment: &impl Environment) -> Result<()> {
+  let mut decisions = read_plugin_trust_decisions(environment);
+  let url_decisions = decisions.iter().filter(|d| d.url == url.as_str()).collect::<Vec<_>>();
+  if url_decisions
+    .iter()
+    .any(|d| d.trusted && is_same_checksum(d.checksum.as_deref(), Some(checksum)))
+  {
+    return Ok(());
+  }
+  if !assume_trusted
+    && url_decisions
+      .iter()
+      .any(|d| !d.trusted && is_same_checksum(d.checksum.as_deref(), Some(checksum)))
+  {
+    bail!("Previously declined to trust the plugin at {}. Provide --yes to trust it.", url);
+  }
+  // trusted before it was downloaded, so its checksum wasn't known
+  let was_trusted_before_download = url_decisions.iter().any(|d| d.trusted && d.checksum.is_none());
+  let is_changed = url_decisions.iter().any(|d| d.trusted && d.checksum.is_some());
+
+  let trusted = if assume_trusted || was_trusted_before_download {
+    true
+  } else {
+    let heading = if is_changed {
+      "The following remote plugin changed since it was trusted:"
+    } else {
+      "The following remote plugin will be run for the first time:"
+    };
+    if !environment.is_interactive() {
+      bail!(
+        "{}\n  * {}\n\nRun with --yes to trust it or set the {} environment variable to 1.",
+        heading,
+        url,
+        TRUST_REMOTE_PLUGINS_ENV_VAR_NAME,
+      );
+    }
+    let mut text = heading.to_string();
+    text.push_str(&format!("\n  * {}", url));
+    text.push_str(&format!("\n    Publisher: {}", get_publisher_text(url)));
+    text.push_str(&format!("\n    Checksum: {}", checksum));
+    environment.log_stderr(&text);
+    environment.confirm("Do you trust this plugin?", false)?
+  };
+
+  decisions.retain(|d| !(d.url == url.as_str() && (d.checksum.is_none() || is_same_checksum(d.checksum.as_deref(), Some(checksum)))));
+  decisions.push(PluginTrustDecision {
+    url: url.to_string(),
+    checksum: Some(checksum.to_lowercase()),
+    trusted,
+  });
+  write_plugin_trust_decisions(&decisions, environment)?;
+
+  if !trusted {
+    bail!("Declined to trust the remote plugin at {}.", url);
+  }
+  Ok(())
+}
+
+fn is_same_checksum(a: Option<&str>, b: Option<&str>) -> bool {
+  match (a, b) {
+    (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+    (None, None) => true,
+    _ => false,
+  }
+}
+
+fn get_checksum_status_text(reference: &PluginSourceReference) -> String {
+  match &reference.checksum {
+    Some(checksum) => format!("{} (verified after downloading)", checksum),
+    None if reference.plugin_kind() == Some(PluginKind::Wasm) => "none specified (Wasm plugins run sandboxed)".to_string(),
+    None => "none specified".to_string(),
+  }
+}
+
+/// Gets who published the plugin based on its url.
+fn get_publisher_text(url: &Url) -> String {
+  let host = url.host_str().unwrap_or("unknown host");
+  // the first path segment of a url with more than one segment
+  let owner = url
+    .path_segments()
+    .and_then(|mut segments| {
+      let first = segments.next()?;
+      segments.next().map(|_| first)
+    })
+    .filter(|owner| !owner.is_empty());
+  match (host, owner) {
+    ("plugins.dprint.dev", None) => "dprint (plugins.dprint.dev)".to_string(),
+    ("plugins.dprint.dev" | "github.com", Some(owner)) => format!("{} ({})", owner, host),
+    _ => host.to_string(),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::environment::TestEnvironment;
+
+  #[test]
+  fn should_get_publisher_text() {
+    let get = |url: &str| get_publisher_text(&Url::parse(url).unwrap());
+    assert_eq!(get("https://plugins.dprint.dev/json-0.1.0.wasm"), "dprint (plugins.dprint.dev)");
+    assert_eq!(get("https://plugins.dprint.dev/owner/plugin-0.1.0.wasm"), "owner (plugins.dprint.dev)");
+    assert_eq!(get("https://github.com/owner/repo/releases/download/0.1.0/plugin.wasm"), "owner (github.com)");
+    assert_eq!(get("https://example.com/plugin.wasm"), "example.com");
+  }
+
+  #[test]
+  fn should_store_trust_decisions() {
+    let environment = TestEnvironment::new();
+    environment.set_interactive(true);
+    let wasm_plugin = PluginSourceReference::new_remote_from_str("https://plugins.dprint.dev/test.wasm");
+    let process_plugin = PluginSourceReference {
+      path_source: PathSource::new_remote_from_str("https://plugins.dprint.dev/owner/process.json"),
+      checksum: Some("checksum".to_string()),
+    };
+
+    // declined
+    environment.set_confirm_results(vec![Ok(Some(false))]);
+    let err = ensure_remote_plugins_trusted(&[&wasm_plugin], false, &environment).err().unwrap();
+    assert_eq!(err.to_string(), "Declined to trust the remote plugins.");
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec![
+        concat!(
+          "The following remote plugins will be downloaded and run for the first time:\n",
+          "  * https://plugins.dprint.dev/test.wasm\n",
+          "    Publisher: dprint (plugins.dprint.dev)\n",
+          "    Checksum: none specified (Wasm plugins run sandboxed)",
+        ),
+        "Do you trust these plugins? N",
+      ]
+    );
+    // not asked again
+    let err = ensure_remote_plugins_trusted(&[&wasm_plugin], false, &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      "Previously declined to trust the plugin at https://plugins.dprint.dev/test.wasm. Provide --yes to trust it."
+    );
+
+    // trusted
+    environment.set_confirm_results(vec![Ok(Some(true))]);
+    ensure_remote_plugins_trusted(&[&process_plugin], false, &environment).unwrap();
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec![
+        concat!(
+          "The following remote plugins will be downloaded and run for the first time:\n",
+          "  * https://plugins.dprint.dev/owner/process.json\n",
+          "    Publisher: owner (plugins.dprint.dev)\n",
+          "    Checksum: checksum (verified after downloading)",
+        ),
+        "Do you trust these plugins? Y",
+      ]
+    );
+    ensure_remote_plugins_trusted(&[&process_plugin], false, &environment).unwrap();
+
+    // --yes trusts the previously declined plugin
+    ensure_remote_plugins_trusted(&[&wasm_plugin, &process_plugin], true, &environment).unwrap();
+    assert_eq!(environment.take_stderr_messages(), Vec::<String>::new());
+    assert_eq!(
+      read_plugin_trust_decisions(&environment),
+      vec![
+        PluginTrustDecision {
+          url: "https://plugins.dprint.dev/owner/process.json".to_string(),
+          checksum: Some("checksum".to_string()),
+          trusted: true,
+        },
+        PluginTrustDecision {
+          url: "https://plugins.dprint.dev/test.wasm".to_string(),
+          checksum: None,
+          trusted: true,
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn should_error_when_not_interactive() {
+    let environment = TestEnvironment::new();
+    let wasm_plugin = PluginSourceReference::new_remote_from_str("https://plugins.dprint.dev/test.wasm");
+    let err = ensure_remote_plugins_trusted(&[&wasm_plugin], false, &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      concat!(
+        "The following remote plugins haven't been trusted:\n",
+        "  * https://plugins.dprint.dev/test.wasm\n\n",
+        "Run with --yes to trust them or set the DPRINT_TRUST_REMOTE_PLUGINS environment variable to 1.",
+      )
+    );
+    assert_eq!(read_plugin_trust_decisions(&environment), Vec::new());
+
+    assert_eq!(get_assume_remote_plugins_trusted(false, &environment), false);
+    assert_eq!(get_assume_remote_plugins_trusted(true, &environment), true);
+    environment.set_env_var("DPRINT_TRUST_REMOTE_PLUGINS", "1");
+    assert_eq!(get_assume_remote_plugins_trusted(false, &environment), true);
+  }
+
+  #[test]
+  fn should_prompt_again_when_downloaded_plugin_changed() {
+    let environment = TestEnvironment::new();
+    environment.set_interactive(true);
+    let url = Url::parse("https://plugins.dprint.dev/test.wasm").unwrap();
+    let wasm_plugin = PluginSourceReference::new_remote_from_str(url.as_str());
+
+    // trusted before it's downloaded, then the checksum of the download is stored
+    environment.set_confirm_results(vec![Ok(Some(true))]);
+    ensure_remote_plugins_trusted(&[&wasm_plugin], false, &environment).unwrap();
+    environment.clear_logs();
+    ensure_downloaded_plugin_trusted(&url, "checksum1", false, &environment).unwrap();
+    assert_eq!(environment.take_stderr_messages(), Vec::<String>::new());
+    assert_eq!(
+      read_plugin_trust_decisions(&environment),
+      vec![PluginTrustDecision {
+        url: url.to_string(),
+        checksum: Some("checksum1".to_string()),
+        trusted: true,
+      }]
+    );
+    // not asked before downloading it again
+    ensure_remote_plugins_trusted(&[&wasm_plugin], false, &environment).unwrap();
+    ensure_downloaded_plugin_trusted(&url, "checksum1", false, &environment).unwrap();
+    assert_eq!(environment.take_stderr_messages(), Vec::<String>::new());
+
+    // the plugin changed
+    environment.set_confirm_results(vec![Ok(Some(false))]);
+    let err = ensure_downloaded_plugin_trusted(&url, "checksum2", false, &environment).err().unwrap();
+    assert_eq!(err.to_string(), "Declined to trust the remote plugin at https://plugins.dprint.dev/test.wasm.");
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec![
+        concat!(
+          "The following remote plugin changed since it was trusted:\n",
+          "  * https://plugins.dprint.dev/test.wasm\n",
+          "    Publisher: dprint (plugins.dprint.dev)\n",
+          "    Checksum: checksum2",
+        ),
+        "Do you trust this plugin? N",
+      ]
+    );
+
+    // errors when not interactive
+    environment.set_interactive(false);
+    let err = ensure_downloaded_plugin_trusted(&url, "checksum3", false, &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      concat!(
+        "The following remote plugin changed since it was trusted:\n",
+        "  * https://plugins.dprint.dev/test.wasm\n\n",
+        "Run with --yes to trust it or set the DPRINT_TRUST_REMOTE_PLUGINS environment variable to 1.",
+      )
+    );
+    ensure_downloaded_plugin_trusted(&url, "checksum3", true, &environment).unwrap();
+  }
+}
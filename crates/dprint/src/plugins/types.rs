@@ -1,5 +1,6 @@
 use std::fmt;
 
+use anyhow::bail;
 use anyhow::Result;
 use dprint_core::plugins::PluginInfo;
 
@@ -8,6 +9,7 @@ use crate::utils::parse_checksum_path_or_url;
 use crate::utils::resolve_url_or_file_path_to_path_source;
 use crate::utils::PathSource;
 use crate::utils::PluginKind;
+use crate::utils::VersionRange;
 
 #[derive(Clone)]
 pub struct CompilationResult {
@@ -81,6 +83,53 @@ pub fn parse_plugin_source_reference(text: &str, base: &PathSource, environment:
   })
 }
 
+/// A reference to a plugin in the plugin registry by a semver range (ex. `typescript@^0.91`).
+///
+/// These are resolved against the registry by `dprint config update` and pinned in the lockfile.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PluginRangeReference {
+  /// The text as written in the configuration file.
+  pub text: String,
+  pub name: String,
+  pub range: VersionRange,
+}
+
+impl PluginRangeReference {
+  /// Parses the text if it looks like a registry plugin name with a version range.
+  pub fn parse(text: &str) -> Result<Option<PluginRangeReference>> {
+    let (name, range_text) = match text.split_once('@') {
+      Some(value) => value,
+      None => return Ok(None),
+    };
+    let is_valid_name_part = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    let is_registry_name = match name.split_once('/') {
+      Some((owner, name)) => is_valid_name_part(owner) && is_valid_name_part(name),
+      None => is_valid_name_part(name),
+    };
+    if !is_registry_name {
+      return Ok(None);
+    }
+    let range = match VersionRange::parse(range_text) {
+      Ok(range) => range,
+      Err(err) => bail!("Invalid plugin reference '{}'. {:#} Expected something like {}@^1.0", text, err, name),
+    };
+    Ok(Some(PluginRangeReference {
+      text: text.to_string(),
+      name: name.to_string(),
+      range,
+    }))
+  }
+
+  /// The name of the plugin in the registry where plugins without an owner are dprint's.
+  pub fn registry_name(&self) -> String {
+    if self.name.contains('/') {
+      self.name.clone()
+    } else {
+      format!("dprint/{}", self.name)
+    }
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use crate::environment::CanonicalizedPathBuf;
@@ -174,4 +223,21 @@ mod tests {
       }
     );
   }
+
+  #[test]
+  fn should_parse_plugin_range_reference() {
+    let reference = PluginRangeReference::parse("typescript@^0.91").unwrap().unwrap();
+    assert_eq!(reference.text, "typescript@^0.91");
+    assert_eq!(reference.registry_name(), "dprint/typescript");
+    let reference = PluginRangeReference::parse("owner/plugin@~1.2").unwrap().unwrap();
+    assert_eq!(reference.registry_name(), "owner/plugin");
+
+    assert_eq!(PluginRangeReference::parse("typescript").unwrap(), None);
+    assert_eq!(PluginRangeReference::parse("./plugin.wasm@checksum").unwrap(), None);
+    assert_eq!(PluginRangeReference::parse("http://dprint.dev/plugin.json@checksum").unwrap(), None);
+    assert_eq!(
+      PluginRangeReference::parse("typescript@latest").err().unwrap().to_string(),
+      "Invalid plugin reference 'typescript@latest'. Invalid version range 'latest'. Expected something like typescript@^1.0"
+    );
+  }
 }
@@ -1,12 +1,17 @@
+use anyhow::bail;
 use anyhow::Result;
 use std::sync::Arc;
 
 use crate::environment::Environment;
+use crate::plugins;
 use crate::plugins::PluginResolver;
 use crate::plugins::PluginsCollection;
+use crate::utils;
 
+use crate::arg_parser::CacheSubCommand;
 use crate::arg_parser::CliArgs;
 use crate::arg_parser::ConfigSubCommand;
+use crate::arg_parser::QuarantineSubCommand;
 use crate::arg_parser::SubCommand;
 use crate::commands;
 
@@ -16,22 +21,47 @@ pub async fn run_cli<TEnvironment: Environment>(
   plugin_resolver: &PluginResolver<TEnvironment>,
   plugin_pools: Arc<PluginsCollection<TEnvironment>>,
 ) -> Result<()> {
-  match &args.sub_command {
+  if !environment.is_read_only() && downloads_or_starts_plugins(&args.sub_command) {
+    recover_from_interrupted_runs(environment);
+  }
+
+  let result = match &args.sub_command {
+    // other commands use the first configuration
+    SubCommand::Fmt(_) | SubCommand::Check(_) if !args.additional_configs.is_empty() => commands::run_for_each_config(args, environment, plugin_resolver).await,
     SubCommand::Help(help_text) => commands::output_help(args, environment, plugin_resolver, help_text).await,
     SubCommand::License => commands::output_license(args, environment, plugin_resolver).await,
     SubCommand::EditorInfo => commands::output_editor_info(args, environment, plugin_resolver).await,
     SubCommand::EditorService(cmd) => commands::run_editor_service(args, environment, plugin_resolver, plugin_pools, cmd).await,
+    SubCommand::Lsp => commands::run_lsp_server(args, environment, plugin_resolver, plugin_pools).await,
+    SubCommand::Broker(cmd) => commands::run_broker(args, environment, plugin_resolver, plugin_pools, cmd).await,
+    SubCommand::Cache(cmd) => match cmd {
+      CacheSubCommand::Log => commands::output_cache_log(environment),
+    },
     SubCommand::ClearCache => commands::clear_cache(environment),
     SubCommand::Config(cmd) => match cmd {
       ConfigSubCommand::Init => commands::init_config_file(environment, &args.config),
       ConfigSubCommand::Add(plugin_name_or_url) => commands::add_plugin_config_file(args, plugin_name_or_url.as_ref(), environment, plugin_resolver).await,
-      ConfigSubCommand::Update { yes } => commands::update_plugins_config_file(args, environment, plugin_resolver, *yes).await,
+      ConfigSubCommand::Update { yes, preview, lockfile } => {
+        commands::update_plugins_config_file(args, environment, plugin_resolver, *yes, *preview, *lockfile).await
+      }
+      ConfigSubCommand::Explain(key) => commands::explain_config(args, key.as_ref(), environment, plugin_resolver).await,
+      ConfigSubCommand::Flatten { output } => commands::flatten_config(args, output.as_ref(), environment),
+    },
+    SubCommand::Quarantine(cmd) => match cmd {
+      QuarantineSubCommand::List => commands::output_quarantine(args, environment),
+      QuarantineSubCommand::Clear => commands::clear_quarantine(args, environment),
+      QuarantineSubCommand::Retry => commands::retry_quarantine(args, environment, plugin_resolver, plugin_pools).await,
     },
     SubCommand::Version => commands::output_version(environment),
     SubCommand::StdInFmt(cmd) => commands::stdin_fmt(cmd, args, environment, plugin_resolver, plugin_pools).await,
     SubCommand::OutputResolvedConfig => commands::output_resolved_config(args, environment, plugin_resolver).await,
     SubCommand::OutputFilePaths(cmd) => commands::output_file_paths(cmd, args, environment, plugin_resolver).await,
     SubCommand::OutputFormatTimes(cmd) => commands::output_format_times(cmd, args, environment, plugin_resolver, plugin_pools).await,
+    SubCommand::WhySlow => commands::output_why_slow(args, environment),
+    SubCommand::Stats(cmd) => commands::output_stats(cmd, args, environment),
+    SubCommand::SuggestConfig(cmd) => commands::suggest_config(cmd, args, environment, plugin_resolver).await,
+    SubCommand::Coverage(cmd) => commands::output_coverage(cmd, args, environment, plugin_resolver).await,
+    SubCommand::RunDiff(cmd) => commands::output_run_diff(cmd, args, environment, plugin_resolver, plugin_pools).await,
     SubCommand::Check(cmd) => commands::check(cmd, args, environment, plugin_resolver, plugin_pools).await,
     SubCommand::Fmt(cmd) => commands::format(cmd, args, environment, plugin_resolver, plugin_pools).await,
     SubCommand::Upgrade => commands::upgrade(environment).await,
@@ -40,5 +70,59 @@ pub async fn run_cli<TEnvironment: Environment>(
       crate::arg_parser::HiddenSubCommand::WindowsInstall(install_path) => commands::handle_windows_install(environment, install_path),
       crate::arg_parser::HiddenSubCommand::WindowsUninstall(install_path) => commands::handle_windows_uninstall(environment, install_path),
     },
+  };
+
+  // fail loudly even when the error of a write was handled
+  let mut attempted_write_paths = environment.attempted_write_paths();
+  if !attempted_write_paths.is_empty() {
+    attempted_write_paths.sort();
+    attempted_write_paths.dedup();
+    bail!(
+      "Attempted to write to the file system when running with --assert-no-writes:\n  {}",
+      attempted_write_paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join("\n  ")
+    );
+  }
+  result
+}
+
+/// Gets if the command may download plugins or start process plugins, which are
+/// the commands that recover from previous runs so other commands start quickly.
+fn downloads_or_starts_plugins(sub_command: &SubCommand) -> bool {
+  matches!(
+    sub_command,
+    SubCommand::Fmt(_)
+      | SubCommand::Check(_)
+      | SubCommand::StdInFmt(_)
+      | SubCommand::Lsp
+      | SubCommand::Broker(_)
+      | SubCommand::OutputFormatTimes(_)
+      | SubCommand::RunDiff(_)
+      | SubCommand::Quarantine(QuarantineSubCommand::Retry)
+      | SubCommand::Config(ConfigSubCommand::Add(_) | ConfigSubCommand::Update { .. })
+  )
+}
+
+/// Cleans up what a previous run that crashed or was killed left behind in the cache.
+fn recover_from_interrupted_runs(environment: &impl Environment) {
+  let removed_files_count = utils::remove_interrupted_download_files(environment);
+  let killed_processes_count = plugins::recover_orphaned_process_plugins(environment);
+  let mut recovered_items = Vec::new();
+  if removed_files_count > 0 {
+    let files = if removed_files_count == 1 { "file" } else { "files" };
+    recovered_items.push(format!("removed {} incomplete download {}", removed_files_count, files));
+  }
+  if killed_processes_count > 0 {
+    let plugins = if killed_processes_count == 1 { "plugin" } else { "plugins" };
+    recovered_items.push(format!("stopped {} orphaned process {}", killed_processes_count, plugins));
+  }
+  if !recovered_items.is_empty() {
+    environment.log_stderr(&format!(
+      "Recovered from a previous run that didn't exit cleanly: {}.",
+      recovered_items.join(" and ")
+    ));
   }
 }
@@ -0,0 +1,115 @@
+use super::RunStats;
+
+/// A detected reason for a slow run along with how to fix it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RunIssue {
+  pub title: String,
+  pub recommendation: String,
+}
+
+/// The share of the formatting time a process plugin needs to be considered a bottleneck.
+const BOTTLENECK_PERCENT: u64 = 50;
+/// Ignore plugins that don't take long to format in total.
+const MIN_BOTTLENECK_MS: u64 = 100;
+
+pub fn get_run_issues(stats: &RunStats) -> Vec<RunIssue> {
+  let mut issues = Vec::new();
+
+  if !stats.compiled_wasm_plugins.is_empty() {
+    issues.push(RunIssue {
+      title: format!("Cold Wasm compilation ({})", stats.compiled_wasm_plugins.join(", ")),
+      recommendation: concat!(
+        "Wasm plugins are compiled the first time they're used and the result is stored in the cache. ",
+        "If this happens on every run (ex. on CI), persist the dprint cache directory between runs."
+      )
+      .to_string(),
+    });
+  }
+
+  let total_format_ms = stats.plugins.iter().map(|p| p.format_ms).sum::<u64>();
+  if total_format_ms > 0 && stats.thread_count > 1 {
+    for plugin in stats.plugins.iter().filter(|p| p.is_process_plugin && p.format_ms >= MIN_BOTTLENECK_MS) {
+      let percent = plugin.format_ms * 100 / total_format_ms;
+      if percent >= BOTTLENECK_PERCENT {
+        issues.push(RunIssue {
+          title: format!("Process plugin bottleneck ({} took {}% of the formatting time)", plugin.name, percent),
+          recommendation: concat!(
+            "Process plugins format in a single separate process, so they may not take advantage of all the threads. ",
+            "Check if a Wasm version of the plugin is available or exclude files it doesn't need to format."
+          )
+          .to_string(),
+        });
+      }
+    }
+  }
+
+  if !stats.is_incremental && stats.formatted_files_count > 0 {
+    issues.push(RunIssue {
+      title: "No incremental cache".to_string(),
+      recommendation: concat!(
+        "Every file was formatted. Remove \"incremental\": false from the configuration file or ",
+        "--incremental=false from the command so unchanged files are skipped."
+      )
+      .to_string(),
+    });
+  }
+
+  issues
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::stats::PluginRunStats;
+
+  fn get_stats() -> RunStats {
+    RunStats {
+      schema_version: 1,
+      command: "check".to_string(),
+      total_ms: 1000,
+      is_incremental: true,
+      thread_count: 4,
+      formatted_files_count: 10,
+      incremental_skipped_files_count: 0,
+      compiled_wasm_plugins: Vec::new(),
+      plugins: Vec::new(),
+      slowest_files: Vec::new(),
+      slowest_dirs: Vec::new(),
+    }
+  }
+
+  fn get_plugin(name: &str, is_process_plugin: bool, format_ms: u64) -> PluginRunStats {
+    PluginRunStats {
+      name: name.to_string(),
+      is_process_plugin,
+      startup_ms: None,
+      format_ms,
+      files_count: 1,
+      peak_memory_bytes: None,
+    }
+  }
+
+  #[test]
+  fn should_not_have_issues_for_fast_run() {
+    let mut stats = get_stats();
+    stats.plugins = vec![get_plugin("wasm", false, 900), get_plugin("process", true, 100)];
+    assert_eq!(get_run_issues(&stats), Vec::new());
+  }
+
+  #[test]
+  fn should_detect_issues() {
+    let mut stats = get_stats();
+    stats.is_incremental = false;
+    stats.compiled_wasm_plugins = vec!["wasm".to_string()];
+    stats.plugins = vec![get_plugin("process", true, 800), get_plugin("wasm", false, 200)];
+    let titles = get_run_issues(&stats).into_iter().map(|i| i.title).collect::<Vec<_>>();
+    assert_eq!(
+      titles,
+      vec![
+        "Cold Wasm compilation (wasm)",
+        "Process plugin bottleneck (process took 80% of the formatting time)",
+        "No incremental cache",
+      ]
+    );
+  }
+}
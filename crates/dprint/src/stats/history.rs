@@ -0,0 +1,166 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+
+use super::RunStats;
+use crate::environment::Environment;
+use crate::utils::get_utc_time_text;
+
+/// The number of runs to keep in the history. The oldest runs are
+/// removed when the history exceeds this.
+const MAX_HISTORY_ENTRIES: usize = 100;
+/// The width of the largest bar in the history chart.
+const MAX_BAR_WIDTH: usize = 40;
+
+/// Anonymized stats of a run, which don't include any file paths
+/// or plugin names. These are only ever stored locally.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RunHistoryEntry {
+  /// Seconds since the unix epoch.
+  pub time: u64,
+  pub command: String,
+  pub total_ms: u64,
+  pub formatted_files_count: usize,
+  pub incremental_skipped_files_count: usize,
+}
+
+impl RunHistoryEntry {
+  pub fn from_run_stats(time: u64, stats: &RunStats) -> Self {
+    RunHistoryEntry {
+      time,
+      command: stats.command.clone(),
+      total_ms: stats.total_ms,
+      formatted_files_count: stats.formatted_files_count,
+      incremental_skipped_files_count: stats.incremental_skipped_files_count,
+    }
+  }
+
+  /// Gets the percentage of files that were skipped by the incremental cache.
+  pub fn cache_hit_percent(&self) -> usize {
+    let total_count = self.formatted_files_count + self.incremental_skipped_files_count;
+    if total_count == 0 {
+      0
+    } else {
+      self.incremental_skipped_files_count * 100 / total_count
+    }
+  }
+}
+
+pub fn read_run_history(file_path: &Path, environment: &impl Environment) -> Vec<RunHistoryEntry> {
+  let file_text = match environment.read_file(file_path) {
+    Ok(file_text) => file_text,
+    Err(_) => return Vec::new(),
+  };
+  match serde_json::from_str::<Vec<RunHistoryEntry>>(&file_text) {
+    Ok(entries) => entries,
+    Err(err) => {
+      log_verbose!(environment, "Error deserializing stats history file {}: {:#}", file_path.display(), err);
+      Vec::new()
+    }
+  }
+}
+
+/// Adds the entry to the history, removing the oldest entries when it's full.
+pub fn add_run_history_entry(file_path: &Path, entry: RunHistoryEntry, environment: &impl Environment) {
+  let mut entries = read_run_history(file_path, environment);
+  entries.push(entry);
+  if entries.len() > MAX_HISTORY_ENTRIES {
+    entries.drain(..entries.len() - MAX_HISTORY_ENTRIES);
+  }
+  let result = serde_json::to_string(&entries).map_err(anyhow::Error::from).and_then(|json_text| {
+    if let Some(parent) = file_path.parent() {
+      environment.mk_dir_all(parent)?;
+    }
+    environment.write_file(file_path, &json_text)
+  });
+  if let Err(err) = result {
+    log_verbose!(environment, "Error saving stats history file {}: {:#}", file_path.display(), err);
+  }
+}
+
+/// Gets a text bar chart of the duration of each run.
+pub fn get_run_history_chart_text(entries: &[RunHistoryEntry]) -> String {
+  let max_ms = entries.iter().map(|entry| entry.total_ms).max().unwrap_or(0);
+  let max_ms_len = max_ms.to_string().len();
+  let max_command_len = entries.iter().map(|entry| entry.command.len()).max().unwrap_or(0);
+  let mut lines = Vec::with_capacity(entries.len());
+  for entry in entries {
+    let bar_width = if max_ms == 0 {
+      0
+    } else {
+      std::cmp::max(1, (entry.total_ms * MAX_BAR_WIDTH as u64 / max_ms) as usize)
+    };
+    lines.push(format!(
+      "{} {:<command_width$} {:>ms_width$}ms {:<chart_width$} {} formatted, {}% cached",
+      get_utc_time_text(entry.time),
+      entry.command,
+      entry.total_ms,
+      "#".repeat(bar_width),
+      entry.formatted_files_count,
+      entry.cache_hit_percent(),
+      command_width = max_command_len,
+      ms_width = max_ms_len,
+      chart_width = MAX_BAR_WIDTH,
+    ));
+  }
+  lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::environment::TestEnvironment;
+
+  #[test]
+  fn should_add_and_rotate_history() {
+    let environment = TestEnvironment::new();
+    let file_path = Path::new("/cache/stats/1.history");
+    assert_eq!(read_run_history(file_path, &environment), Vec::new());
+    let entry = |time: u64| RunHistoryEntry {
+      time,
+      command: "check".to_string(),
+      total_ms: 10,
+      formatted_files_count: 1,
+      incremental_skipped_files_count: 3,
+    };
+    for time in 0..MAX_HISTORY_ENTRIES as u64 + 5 {
+      add_run_history_entry(file_path, entry(time), &environment);
+    }
+    let entries = read_run_history(file_path, &environment);
+    assert_eq!(entries.len(), MAX_HISTORY_ENTRIES);
+    assert_eq!(entries[0], entry(5));
+    assert_eq!(entries[0].cache_hit_percent(), 75);
+  }
+
+  #[test]
+  fn should_get_history_chart_text() {
+    let entries = vec![
+      RunHistoryEntry {
+        time: 0,
+        command: "check".to_string(),
+        total_ms: 1000,
+        formatted_files_count: 10,
+        incremental_skipped_files_count: 0,
+      },
+      RunHistoryEntry {
+        time: 60,
+        command: "fmt".to_string(),
+        total_ms: 50,
+        formatted_files_count: 1,
+        incremental_skipped_files_count: 9,
+      },
+    ];
+    assert_eq!(
+      get_run_history_chart_text(&entries),
+      format!(
+        "1970-01-01 00:00:00 UTC check 1000ms {} 10 formatted, 0% cached\n1970-01-01 00:01:00 UTC fmt     50ms {}{} 1 formatted, 90% cached",
+        "#".repeat(40),
+        "##",
+        " ".repeat(38),
+      )
+    );
+  }
+}
@@ -0,0 +1,75 @@
+mod analysis;
+mod history;
+mod run_stats;
+mod run_summary;
+
+pub use analysis::*;
+pub use history::*;
+pub use run_stats::*;
+pub use run_summary::*;
+
+use anyhow::Result;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::configuration::ResolvedConfig;
+use crate::environment::Environment;
+use crate::plugins::PluginsCollection;
+use crate::utils::get_bytes_hash;
+
+/// Gets the path of the stats of the last run, which is stored in the
+/// cache with a key based on the root directory.
+pub fn get_stats_file_path(config: &ResolvedConfig, environment: &impl Environment) -> PathBuf {
+  let stats_dir = environment.get_cache_dir().join_panic_relative("stats");
+  stats_dir
+    .join_panic_relative(get_bytes_hash(config.base_path.to_string_lossy().as_bytes()).to_string())
+    .into_path_buf()
+}
+
+/// Gets the path of the anonymized stats of the recent runs, which are never transmitted.
+pub fn get_stats_history_file_path(config: &ResolvedConfig, environment: &impl Environment) -> PathBuf {
+  let mut file_path = get_stats_file_path(config, environment);
+  file_path.set_extension("history");
+  file_path
+}
+
+pub fn get_stats_collector(command: &str, config: &ResolvedConfig, is_incremental: bool) -> Arc<RunStatsCollector> {
+  Arc::new(RunStatsCollector::new(command, config.base_path.clone(), is_incremental))
+}
+
+pub fn save_run_stats<TEnvironment: Environment>(
+  stats: &RunStatsCollector,
+  config: &ResolvedConfig,
+  plugins_collection: &PluginsCollection<TEnvironment>,
+  environment: &TEnvironment,
+) {
+  let run_stats = stats.finish(
+    |file_path| plugins_collection.get_plugin_names_from_file_path(file_path),
+    |plugin_name| plugins_collection.get_plugin(plugin_name).peak_memory_bytes(),
+  );
+  for plugin in run_stats.plugins.iter() {
+    if let Some(peak_memory_bytes) = plugin.peak_memory_bytes {
+      log_verbose!(environment, "Plugin {} peak memory: {}", plugin.name, get_memory_text(peak_memory_bytes));
+    }
+  }
+  write_run_stats(&get_stats_file_path(config, environment), &run_stats, environment);
+  add_run_history_entry(
+    &get_stats_history_file_path(config, environment),
+    RunHistoryEntry::from_run_stats(environment.get_time_secs(), &run_stats),
+    environment,
+  );
+}
+
+/// Writes the summary of the run to the provided file path relative to the current directory.
+pub fn save_run_summary<TEnvironment: Environment>(
+  summary_file: &str,
+  command: &str,
+  config: &ResolvedConfig,
+  plugins_collection: &PluginsCollection<TEnvironment>,
+  file_paths: &[PathBuf],
+  fix_commands: Vec<String>,
+  environment: &TEnvironment,
+) -> Result<()> {
+  let summary = get_run_summary(command, config, plugins_collection, file_paths, fix_commands, environment)?;
+  write_run_summary(&environment.cwd().join(summary_file), &summary, environment)
+}
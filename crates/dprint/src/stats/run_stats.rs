@@ -0,0 +1,301 @@
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::environment::CanonicalizedPathBuf;
+use crate::environment::Environment;
+
+const STATS_SCHEMA_VERSION: u32 = 1;
+/// The number of slowest files and directories to store.
+const MAX_ITEMS: usize = 20;
+
+/// Timing information about a formatting run.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RunStats {
+  pub schema_version: u32,
+  pub command: String,
+  pub total_ms: u64,
+  pub is_incremental: bool,
+  pub thread_count: usize,
+  pub formatted_files_count: usize,
+  pub incremental_skipped_files_count: usize,
+  /// Wasm plugins that had to be compiled during the run.
+  pub compiled_wasm_plugins: Vec<String>,
+  pub plugins: Vec<PluginRunStats>,
+  pub slowest_files: Vec<FileRunStats>,
+  pub slowest_dirs: Vec<DirRunStats>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginRunStats {
+  pub name: String,
+  pub is_process_plugin: bool,
+  pub startup_ms: Option<u64>,
+  pub format_ms: u64,
+  pub files_count: usize,
+  /// The peak Wasm linear memory or process plugin memory in bytes.
+  pub peak_memory_bytes: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileRunStats {
+  /// Path relative to the base directory.
+  pub file_path: String,
+  pub duration_ms: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DirRunStats {
+  /// Path relative to the base directory.
+  pub dir_path: String,
+  pub duration_ms: u64,
+  pub files_count: usize,
+}
+
+#[derive(Default)]
+struct CollectorState {
+  thread_count: usize,
+  incremental_skipped_files_count: usize,
+  compiled_wasm_plugins: Vec<String>,
+  plugins: Vec<PluginRunStats>,
+  files: Vec<(PathBuf, Duration)>,
+}
+
+/// Collects the stats while formatting.
+pub struct RunStatsCollector {
+  command: String,
+  base_path: CanonicalizedPathBuf,
+  is_incremental: bool,
+  start_instant: Instant,
+  state: Mutex<CollectorState>,
+}
+
+impl RunStatsCollector {
+  pub fn new(command: &str, base_path: CanonicalizedPathBuf, is_incremental: bool) -> Self {
+    RunStatsCollector {
+      command: command.to_string(),
+      base_path,
+      is_incremental,
+      start_instant: Instant::now(),
+      state: Default::default(),
+    }
+  }
+
+  pub fn set_thread_count(&self, thread_count: usize) {
+    self.state.lock().thread_count = thread_count;
+  }
+
+  pub fn add_compiled_wasm_plugins(&self, names: Vec<String>) {
+    self.state.lock().compiled_wasm_plugins.extend(names);
+  }
+
+  pub fn record_plugin_startup(&self, name: &str, is_process_plugin: bool, startup_ms: Option<u64>) {
+    let mut state = self.state.lock();
+    if !state.plugins.iter().any(|p| p.name == name) {
+      state.plugins.push(PluginRunStats {
+        name: name.to_string(),
+        is_process_plugin,
+        startup_ms,
+        format_ms: 0,
+        files_count: 0,
+        peak_memory_bytes: None,
+      });
+    }
+  }
+
+  pub fn record_incremental_skip(&self) {
+    self.state.lock().incremental_skipped_files_count += 1;
+  }
+
+  pub fn record_file(&self, file_path: &Path, duration: Duration) {
+    self.state.lock().files.push((file_path.to_path_buf(), duration));
+  }
+
+  /// Aggregates the collected stats using the provided functions to get the plugins
+  /// of a file and the peak memory usage of a plugin.
+  pub fn finish(&self, get_plugin_names: impl Fn(&Path) -> Vec<String>, get_peak_memory_bytes: impl Fn(&str) -> Option<u64>) -> RunStats {
+    let state = self.state.lock();
+    let mut plugins = state.plugins.clone();
+    for plugin in plugins.iter_mut() {
+      plugin.peak_memory_bytes = get_peak_memory_bytes(&plugin.name);
+    }
+    for (file_path, duration) in state.files.iter() {
+      let plugin_names = get_plugin_names(file_path);
+      // split the time evenly when multiple plugins format the same file
+      let plugin_ms = duration.as_millis() as u64 / std::cmp::max(1, plugin_names.len()) as u64;
+      for plugin in plugins.iter_mut() {
+        if plugin_names.contains(&plugin.name) {
+          plugin.format_ms += plugin_ms;
+          plugin.files_count += 1;
+        }
+      }
+    }
+    plugins.sort_by(|a, b| b.format_ms.cmp(&a.format_ms).then_with(|| a.name.cmp(&b.name)));
+
+    let get_relative_path = |path: &Path| -> String {
+      let path = path.strip_prefix(&self.base_path).unwrap_or(path);
+      path.to_string_lossy().replace('\\', "/")
+    };
+
+    let mut files = state.files.iter().collect::<Vec<_>>();
+    files.sort_by(|a, b| b.1.cmp(&a.1));
+    let slowest_files = files
+      .iter()
+      .take(MAX_ITEMS)
+      .map(|(file_path, duration)| FileRunStats {
+        file_path: get_relative_path(file_path),
+        duration_ms: duration.as_millis() as u64,
+      })
+      .collect();
+
+    let mut dirs: HashMap<String, (Duration, usize)> = HashMap::new();
+    for (file_path, duration) in state.files.iter() {
+      let dir_path = match file_path.parent() {
+        Some(parent) => get_relative_path(parent),
+        None => continue,
+      };
+      let entry = dirs.entry(dir_path).or_default();
+      entry.0 += *duration;
+      entry.1 += 1;
+    }
+    let mut dirs = dirs.into_iter().collect::<Vec<_>>();
+    dirs.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then_with(|| a.0.cmp(&b.0)));
+    let slowest_dirs = dirs
+      .into_iter()
+      .take(MAX_ITEMS)
+      .map(|(dir_path, (duration, files_count))| DirRunStats {
+        dir_path: if dir_path.is_empty() { ".".to_string() } else { dir_path },
+        duration_ms: duration.as_millis() as u64,
+        files_count,
+      })
+      .collect();
+
+    RunStats {
+      schema_version: STATS_SCHEMA_VERSION,
+      command: self.command.clone(),
+      total_ms: self.start_instant.elapsed().as_millis() as u64,
+      is_incremental: self.is_incremental,
+      thread_count: state.thread_count,
+      formatted_files_count: state.files.len(),
+      incremental_skipped_files_count: state.incremental_skipped_files_count,
+      compiled_wasm_plugins: state.compiled_wasm_plugins.clone(),
+      plugins,
+      slowest_files,
+      slowest_dirs,
+    }
+  }
+}
+
+/// Gets the memory in megabytes for display (ex. "12.50MB").
+pub fn get_memory_text(bytes: u64) -> String {
+  format!("{:.2}MB", bytes as f64 / 1_000_000.0)
+}
+
+pub fn read_run_stats(file_path: &Path, environment: &impl Environment) -> Option<RunStats> {
+  let file_text = environment.read_file(file_path).ok()?;
+  match serde_json::from_str::<RunStats>(&file_text) {
+    Ok(stats) if stats.schema_version == STATS_SCHEMA_VERSION => Some(stats),
+    Ok(_) => None,
+    Err(err) => {
+      log_verbose!(environment, "Error deserializing stats file {}: {:#}", file_path.display(), err);
+      None
+    }
+  }
+}
+
+pub fn write_run_stats(file_path: &Path, stats: &RunStats, environment: &impl Environment) {
+  let result = serde_json::to_string(stats).map_err(anyhow::Error::from).and_then(|json_text| {
+    if let Some(parent) = file_path.parent() {
+      environment.mk_dir_all(parent)?;
+    }
+    environment.write_file(file_path, &json_text)
+  });
+  if let Err(err) = result {
+    log_verbose!(environment, "Error saving stats file {}: {:#}", file_path.display(), err);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::environment::TestEnvironment;
+
+  #[test]
+  fn should_collect_stats() {
+    let collector = RunStatsCollector::new("check", CanonicalizedPathBuf::new_for_testing("/project"), true);
+    collector.set_thread_count(4);
+    collector.record_plugin_startup("a", false, Some(10));
+    collector.record_plugin_startup("b", true, Some(20));
+    collector.record_file(Path::new("/project/src/a.ts"), Duration::from_millis(30));
+    collector.record_file(Path::new("/project/src/b.ts"), Duration::from_millis(40));
+    collector.record_file(Path::new("/project/c.md"), Duration::from_millis(5));
+    collector.record_incremental_skip();
+
+    let stats = collector.finish(
+      |file_path| match file_path.to_string_lossy().as_ref() {
+        "/project/src/a.ts" => vec!["a".to_string()],
+        "/project/src/b.ts" => vec!["a".to_string(), "b".to_string()],
+        _ => vec!["b".to_string()],
+      },
+      |plugin_name| if plugin_name == "b" { Some(12_500_000) } else { None },
+    );
+    assert_eq!(stats.formatted_files_count, 3);
+    assert_eq!(stats.incremental_skipped_files_count, 1);
+    assert_eq!(
+      stats.slowest_files.iter().map(|f| f.file_path.as_str()).collect::<Vec<_>>(),
+      vec!["src/b.ts", "src/a.ts", "c.md"]
+    );
+    assert_eq!(
+      stats.slowest_dirs,
+      vec![
+        DirRunStats {
+          dir_path: "src".to_string(),
+          duration_ms: 70,
+          files_count: 2,
+        },
+        DirRunStats {
+          dir_path: ".".to_string(),
+          duration_ms: 5,
+          files_count: 1,
+        },
+      ]
+    );
+    assert_eq!(
+      stats.plugins,
+      vec![
+        PluginRunStats {
+          name: "a".to_string(),
+          is_process_plugin: false,
+          startup_ms: Some(10),
+          format_ms: 50,
+          files_count: 2,
+          peak_memory_bytes: None,
+        },
+        PluginRunStats {
+          name: "b".to_string(),
+          is_process_plugin: true,
+          startup_ms: Some(20),
+          format_ms: 25,
+          files_count: 2,
+          peak_memory_bytes: Some(12_500_000),
+        },
+      ]
+    );
+
+    let environment = TestEnvironment::new();
+    write_run_stats(Path::new("/cache/stats/1"), &stats, &environment);
+    assert_eq!(read_run_stats(Path::new("/cache/stats/1"), &environment), Some(stats));
+    assert_eq!(get_memory_text(12_500_000), "12.50MB");
+  }
+}
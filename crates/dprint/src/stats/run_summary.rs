@@ -0,0 +1,158 @@
+use anyhow::bail;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::configuration::get_flattened_config_text;
+use crate::configuration::ResolvedConfig;
+use crate::environment::Environment;
+use crate::plugins::PluginsCollection;
+use crate::utils::get_sha256_checksum;
+
+const SUMMARY_SCHEMA_VERSION: u32 = 1;
+
+/// The inputs and outputs of a formatting run for build caching systems.
+///
+/// This doesn't contain any timing information or absolute paths so that
+/// running with the same inputs produces the same summary.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummary {
+  pub schema_version: u32,
+  pub command: String,
+  /// Checksum of the resolved configuration with any extended configuration inlined.
+  pub config_hash: String,
+  pub plugins: Vec<PluginRunSummary>,
+  pub files: Vec<FileRunSummary>,
+  /// Commands that format the files found not formatted when checking.
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub fix_commands: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginRunSummary {
+  pub name: String,
+  pub version: String,
+  /// Hash of the plugin and its configuration.
+  pub hash: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileRunSummary {
+  /// Path relative to the base directory.
+  pub file_path: String,
+  /// Checksum of the file's text after the run.
+  pub hash: String,
+}
+
+/// Gets the summary of a run using the text of the files on the file system.
+pub fn get_run_summary<TEnvironment: Environment>(
+  command: &str,
+  config: &ResolvedConfig,
+  plugins_collection: &PluginsCollection<TEnvironment>,
+  file_paths: &[PathBuf],
+  fix_commands: Vec<String>,
+  environment: &TEnvironment,
+) -> Result<RunSummary> {
+  let config_text = get_flattened_config_text(config, config.plugins.iter().map(|plugin| plugin.to_full_string()).collect())?;
+  let plugins = plugins_collection
+    .get_plugins()
+    .iter()
+    .map(|plugin| PluginRunSummary {
+      name: plugin.name().to_string(),
+      version: plugin.version().to_string(),
+      hash: format!("{:016x}", plugin.get_hash()),
+    })
+    .collect();
+  let mut files = Vec::with_capacity(file_paths.len());
+  for file_path in file_paths {
+    let relative_path = file_path.strip_prefix(&config.base_path).unwrap_or(file_path);
+    files.push(FileRunSummary {
+      file_path: relative_path.to_string_lossy().replace('\\', "/"),
+      hash: get_sha256_checksum(&environment.read_file_bytes(file_path)?),
+    });
+  }
+  files.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+  files.dedup();
+
+  Ok(RunSummary {
+    schema_version: SUMMARY_SCHEMA_VERSION,
+    command: command.to_string(),
+    config_hash: get_sha256_checksum(config_text.as_bytes()),
+    plugins,
+    files,
+    fix_commands,
+  })
+}
+
+/// A file whose formatted output differs from a previous run summary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunSummaryFileChange {
+  Changed(String),
+  Added(String),
+  Removed(String),
+}
+
+impl RunSummaryFileChange {
+  pub fn file_path(&self) -> &str {
+    match self {
+      RunSummaryFileChange::Changed(file_path) | RunSummaryFileChange::Added(file_path) | RunSummaryFileChange::Removed(file_path) => file_path,
+    }
+  }
+}
+
+/// Gets the files whose hash changed along with the added and removed files sorted by path.
+pub fn get_run_summary_file_changes(old_files: &[FileRunSummary], new_files: &[FileRunSummary]) -> Vec<RunSummaryFileChange> {
+  let old_hashes = old_files
+    .iter()
+    .map(|file| (file.file_path.as_str(), file.hash.as_str()))
+    .collect::<BTreeMap<_, _>>();
+  let new_hashes = new_files
+    .iter()
+    .map(|file| (file.file_path.as_str(), file.hash.as_str()))
+    .collect::<BTreeMap<_, _>>();
+  let mut changes = Vec::new();
+  for (file_path, hash) in new_hashes.iter() {
+    match old_hashes.get(file_path) {
+      Some(old_hash) if old_hash == hash => {}
+      Some(_) => changes.push(RunSummaryFileChange::Changed(file_path.to_string())),
+      None => changes.push(RunSummaryFileChange::Added(file_path.to_string())),
+    }
+  }
+  for file_path in old_hashes.keys() {
+    if !new_hashes.contains_key(file_path) {
+      changes.push(RunSummaryFileChange::Removed(file_path.to_string()));
+    }
+  }
+  changes.sort_by(|a, b| a.file_path().cmp(b.file_path()));
+  changes
+}
+
+pub fn read_run_summary(file_path: &Path, environment: &impl Environment) -> Result<RunSummary> {
+  let summary: RunSummary = match serde_json::from_str(&environment.read_file(file_path)?) {
+    Ok(summary) => summary,
+    Err(err) => bail!("Error deserializing run summary {}. {:#}", file_path.display(), err),
+  };
+  if summary.schema_version != SUMMARY_SCHEMA_VERSION {
+    bail!(
+      "Unsupported run summary schema version {} in {}. Expected version {}.",
+      summary.schema_version,
+      file_path.display(),
+      SUMMARY_SCHEMA_VERSION
+    );
+  }
+  Ok(summary)
+}
+
+pub fn write_run_summary(file_path: &Path, summary: &RunSummary, environment: &impl Environment) -> Result<()> {
+  let json_text = serde_json::to_string_pretty(summary)?;
+  if let Some(parent) = file_path.parent() {
+    environment.mk_dir_all(parent)?;
+  }
+  environment.write_file(file_path, &format!("{}\n", json_text))
+}
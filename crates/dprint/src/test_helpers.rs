@@ -7,6 +7,7 @@ use crossterm::style::Stylize;
 use once_cell::sync::Lazy;
 
 use crate::arg_parser::parse_args;
+use crate::environment::Environment;
 use crate::environment::TestEnvironment;
 use crate::plugins::CompilationResult;
 use crate::plugins::PluginCache;
@@ -34,6 +35,30 @@ pub static TEST_PROCESS_PLUGIN_PATH: Lazy<PathBuf> = Lazy::new(|| {
 pub static WASM_PLUGIN_BYTES: &'static [u8] = include_bytes!("../../test-plugin/test_plugin.wasm");
 // cache these so it only has to be done once across all tests
 static COMPILATION_RESULT: Lazy<CompilationResult> = Lazy::new(|| crate::plugins::compile_wasm(WASM_PLUGIN_BYTES).unwrap());
+/// A `fileSelector` module that excludes the paths that start with an underscore.
+pub const FILE_SELECTOR_WAT: &str = r#"(module
+  (memory (export "memory") 2)
+  (func (export "dprint_file_selector_alloc") (param $size i32) (result i32)
+    i32.const 1024)
+  (func (export "dprint_file_selector_select") (param $ptr i32) (param $size i32) (result i32)
+    (local $i i32)
+    (local $out i32)
+    (local $line_start i32)
+    (local.set $out (i32.const 65536))
+    (local.set $line_start (i32.const 1))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $size)))
+        (if (local.get $line_start)
+          (then
+            (i32.store8 (local.get $out)
+              (i32.ne (i32.load8_u (i32.add (local.get $ptr) (local.get $i))) (i32.const 95)))
+            (local.set $out (i32.add (local.get $out) (i32.const 1)))))
+        (local.set $line_start
+          (i32.eq (i32.load8_u (i32.add (local.get $ptr) (local.get $i))) (i32.const 10)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.const 65536)))"#;
 pub static PROCESS_PLUGIN_ZIP_BYTES: Lazy<Vec<u8>> = Lazy::new(|| {
   let buf: Vec<u8> = Vec::new();
   let w = std::io::Cursor::new(buf);
@@ -62,12 +87,15 @@ pub fn run_test_cli_with_stdin(args: Vec<&str>, environment: &TestEnvironment, s
   let mut args: Vec<String> = args.into_iter().map(String::from).collect();
   args.insert(0, String::from(""));
   environment.set_wasm_compile_result(COMPILATION_RESULT.clone());
-  let plugin_cache = Arc::new(PluginCache::new(environment.clone()));
-  let plugin_pools = Arc::new(PluginsCollection::new(environment.clone()));
-  let plugin_resolver = PluginResolver::new(environment.clone(), plugin_cache, plugin_pools.clone());
   let args = parse_args(args, stdin_reader)?;
+  let mut plugin_cache = PluginCache::new(environment.clone());
+  // the tests that aren't interactive don't test trusting the plugins
+  plugin_cache.set_assume_trusted(args.yes || !environment.is_interactive());
+  let plugin_pools = Arc::new(PluginsCollection::new(environment.clone()));
+  let plugin_resolver = PluginResolver::new(environment.clone(), Arc::new(plugin_cache), plugin_pools.clone());
   environment.set_stdout_machine_readable(args.is_stdout_machine_readable());
   environment.set_verbose(args.verbose);
+  environment.set_assert_no_writes(args.assert_no_writes);
 
   environment.run_in_runtime({
     let environment = environment.clone();
@@ -157,9 +185,17 @@ SUBCOMMANDS:
   fmt                     Formats the source files and writes the result to the file system.
   check                   Checks for any files that haven't been formatted.
   config                  Functionality related to the configuration file.
+  quarantine              Manages the files that are skipped because they previously caused a critical error in a plugin.
   output-file-paths       Prints the resolved file paths for the plugins based on the args and configuration.
   output-resolved-config  Prints the resolved configuration for the plugins based on the args and configuration.
   output-format-times     Prints the amount of time it takes to format each file. Use this for debugging.
+  why-slow                Analyzes the last fmt or check run and explains what made it slow.
+  stats                   Outputs the statistics of the last fmt or check run, which are only stored locally.
+  suggest-config          Samples the files to suggest configuration values that cause the fewest changes when adopting dprint.
+  coverage                Outputs how many files of each file type are formatted by a plugin and suggests plugins for the unformatted ones.
+  run-diff                Outputs the files whose formatted output changed since a run summary was written with --summary-file.
+  lsp                     Runs a language server over stdio for formatting in editors with a Language Server Protocol client.
+  cache                   Inspects the plugin cache.
   clear-cache             Deletes the plugin cache directory.
   upgrade                 Upgrades the dprint executable.
   license                 Outputs the software license.
@@ -167,9 +203,15 @@ SUBCOMMANDS:
 More details at `dprint help <SUBCOMMAND>`
 
 OPTIONS:
-  -c, --config <config>          Path or url to JSON configuration file. Defaults to dprint.json or .dprint.json in current or ancestor directory when not provided.
+  -c, --config <config>          Path or url to JSON configuration file. Defaults to dprint.json or .dprint.json in current or ancestor directory when not provided. Provide it multiple times to run fmt or check for each configuration.
+      --config-conflicts <mode>  What to do when more than one configuration file provided via --config includes the same file: error (default) or format it with the configuration file nearest to it.
       --plugins <urls/files>...  List of urls or file paths of plugins to use. This overrides what is specified in the config file.
       --verbose                  Prints additional diagnostic information.
+      --assert-no-writes         Errors if anything would be written to the file system, including the cache. Use this to run in a read-only build sandbox.
+      --cache-overlay[=<mode>]   Writes downloaded plugins and incremental data to a temporary overlay instead of the shared cache, then discards it (default) or merges it into the cache at the end.
+  -y, --yes                      Answers yes to prompts, such as trusting remote plugins that are run for the first time or upgrading process plugins without confirming their checksums.
+      --frozen                   Errors when a remote plugin is not pinned in the lockfile (dprint.lock) or the lockfile is out of date. Use this on CI.
+      --sort-output[=<paths>]    Outputs file lists, summaries, and plugin lists in a stable locale independent order with paths displayed using forward slashes (default) or the native separators, so logs diff cleanly across platforms.
 
 ENVIRONMENT VARIABLES:
   DPRINT_CACHE_DIR    Directory to store the dprint cache. Note that this
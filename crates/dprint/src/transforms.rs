@@ -0,0 +1,228 @@
+use anyhow::Result;
+use std::borrow::Cow;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::path::Path;
+use twox_hash::XxHash64;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::configuration::ResolvedConfig;
+use crate::configuration::TransformConfig;
+use crate::configuration::UnicodeNormalizationForm;
+use crate::patterns::get_patterns_as_glob_matcher;
+use crate::utils::GlobMatcher;
+use crate::utils::BOM_CHAR;
+
+/// Text of a UTF-8 byte order mark that was incorrectly decoded as Windows-1252.
+const MISDECODED_BOM: &str = "\u{EF}\u{BB}\u{BF}";
+
+/// Transforms the text of files on the host before it's provided to
+/// the plugins and after the plugins return the formatted text.
+pub struct FileTextTransforms {
+  transforms: Vec<(GlobMatcher, TransformConfig)>,
+}
+
+impl FileTextTransforms {
+  /// Gets the transforms of the configuration or `None` when there aren't any.
+  pub fn from_config(config: &ResolvedConfig) -> Result<Option<Self>> {
+    if config.transforms.is_empty() {
+      return Ok(None);
+    }
+    let mut transforms = Vec::with_capacity(config.transforms.len());
+    for (pattern, transform) in config.transforms.iter() {
+      let matcher = get_patterns_as_glob_matcher(&[pattern.to_string()], &config.base_path)?;
+      transforms.push((matcher, transform.clone()));
+    }
+    Ok(Some(FileTextTransforms { transforms }))
+  }
+
+  /// Transforms the text before it's formatted.
+  pub fn before_format<'a>(&self, file_path: &Path, text: &'a str) -> Cow<'a, str> {
+    let mut text = Cow::Borrowed(text);
+    for transform in self.get_transforms(file_path) {
+      if transform.normalize_bom {
+        text = map_cow(text, normalize_bom);
+      }
+      if let Some(form) = transform.unicode_normalization {
+        text = map_cow(text, |text| normalize_unicode(text, form));
+      }
+      for header in transform.license_header.iter().chain(transform.remove_headers.iter()) {
+        text = map_cow(text, |text| remove_header(text, header));
+      }
+    }
+    text
+  }
+
+  /// Transforms the formatted text.
+  pub fn after_format(&self, file_path: &Path, mut text: String) -> String {
+    for transform in self.get_transforms(file_path) {
+      if transform.trim_trailing_whitespace {
+        if let Some(new_text) = trim_trailing_whitespace(&text) {
+          text = new_text;
+        }
+      }
+      if let Some(tab_width) = transform.tabs_to_spaces {
+        if let Some(new_text) = expand_indentation_tabs(&text, tab_width as usize) {
+          text = new_text;
+        }
+      }
+      if let Some(license_header) = &transform.license_header {
+        if !text.starts_with(license_header.as_str()) {
+          let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+          text = format!("{}{}{}", license_header, newline, text);
+        }
+      }
+    }
+    text
+  }
+
+  /// Gets a hash of the transforms of the file, which changes the
+  /// formatted text in the same way the plugins do.
+  pub fn get_hash(&self, file_path: &Path) -> u64 {
+    let mut hasher = XxHash64::default();
+    for transform in self.get_transforms(file_path) {
+      transform.hash(&mut hasher);
+    }
+    hasher.finish()
+  }
+
+  fn get_transforms<'a>(&'a self, file_path: &'a Path) -> impl Iterator<Item = &'a TransformConfig> {
+    self
+      .transforms
+      .iter()
+      .filter(move |(matcher, _)| matcher.matches(file_path))
+      .map(|(_, transform)| transform)
+  }
+}
+
+fn map_cow(text: Cow<'_, str>, transform: impl Fn(&str) -> Option<String>) -> Cow<'_, str> {
+  match transform(&text) {
+    Some(new_text) => Cow::Owned(new_text),
+    None => text,
+  }
+}
+
+/// Removes any byte order marks left at the start of the text after the first one was removed.
+fn normalize_bom(text: &str) -> Option<String> {
+  let mut new_text = text;
+  loop {
+    if let Some(remaining) = new_text.strip_prefix(BOM_CHAR) {
+      new_text = remaining;
+    } else if let Some(remaining) = new_text.strip_prefix(MISDECODED_BOM) {
+      new_text = remaining;
+    } else {
+      break;
+    }
+  }
+  if new_text.len() == text.len() {
+    None
+  } else {
+    Some(new_text.to_string())
+  }
+}
+
+fn normalize_unicode(text: &str, form: UnicodeNormalizationForm) -> Option<String> {
+  match form {
+    UnicodeNormalizationForm::Nfc if !unicode_normalization::is_nfc(text) => Some(text.nfc().collect()),
+    UnicodeNormalizationForm::Nfd if !unicode_normalization::is_nfd(text) => Some(text.nfd().collect()),
+    _ => None,
+  }
+}
+
+/// Removes the spaces and tabs at the end of each line.
+fn trim_trailing_whitespace(text: &str) -> Option<String> {
+  let mut new_text = String::with_capacity(text.len());
+  for line in text.split_inclusive('\n') {
+    let (content, line_ending) = match line.strip_suffix("\r\n") {
+      Some(content) => (content, "\r\n"),
+      None => match line.strip_suffix('\n') {
+        Some(content) => (content, "\n"),
+        None => (line, ""),
+      },
+    };
+    new_text.push_str(content.trim_end_matches(|c: char| c == ' ' || c == '\t'));
+    new_text.push_str(line_ending);
+  }
+  if new_text.len() == text.len() {
+    None
+  } else {
+    Some(new_text)
+  }
+}
+
+/// Replaces the tabs in the indentation of each line with spaces up to the next tab stop.
+fn expand_indentation_tabs(text: &str, tab_width: usize) -> Option<String> {
+  if !text.contains('\t') {
+    return None;
+  }
+  let mut new_text = String::with_capacity(text.len());
+  for line in text.split_inclusive('\n') {
+    let content = line.trim_start_matches(|c: char| c == ' ' || c == '\t');
+    let mut column = 0;
+    for c in line[..line.len() - content.len()].chars() {
+      let count = if c == '\t' { tab_width - column % tab_width } else { 1 };
+      new_text.extend(std::iter::repeat(' ').take(count));
+      column += count;
+    }
+    new_text.push_str(content);
+  }
+  if new_text == text {
+    None
+  } else {
+    Some(new_text)
+  }
+}
+
+/// Removes the header and the line ending after it from the start of the text.
+fn remove_header(text: &str, header: &str) -> Option<String> {
+  let remaining = text.strip_prefix(header)?;
+  let remaining = remaining.strip_prefix("\r\n").or_else(|| remaining.strip_prefix('\n')).unwrap_or(remaining);
+  Some(remaining.to_string())
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn should_normalize_bom() {
+    assert_eq!(normalize_bom("\u{FEFF}\u{EF}\u{BB}\u{BF}text"), Some("text".to_string()));
+    assert_eq!(normalize_bom("text\u{FEFF}"), None);
+  }
+
+  #[test]
+  fn should_normalize_unicode() {
+    // e followed by a combining acute accent
+    assert_eq!(
+      normalize_unicode("caf\u{0065}\u{0301}", UnicodeNormalizationForm::Nfc),
+      Some("caf\u{00E9}".to_string())
+    );
+    assert_eq!(normalize_unicode("caf\u{00E9}", UnicodeNormalizationForm::Nfc), None);
+    assert_eq!(
+      normalize_unicode("caf\u{00E9}", UnicodeNormalizationForm::Nfd),
+      Some("caf\u{0065}\u{0301}".to_string())
+    );
+  }
+
+  #[test]
+  fn should_trim_trailing_whitespace() {
+    assert_eq!(trim_trailing_whitespace("a \t\r\nb  \n\nc "), Some("a\r\nb\n\nc".to_string()));
+    assert_eq!(trim_trailing_whitespace("a\n  b\n"), None);
+  }
+
+  #[test]
+  fn should_expand_indentation_tabs() {
+    assert_eq!(expand_indentation_tabs("\ta\tb\n  \tc\n", 4), Some("    a\tb\n    c\n".to_string()));
+    assert_eq!(expand_indentation_tabs("a\tb", 4), None);
+    assert_eq!(expand_indentation_tabs("a", 4), None);
+  }
+
+  #[test]
+  fn should_remove_header() {
+    assert_eq!(remove_header("// header\ntext", "// header"), Some("text".to_string()));
+    assert_eq!(remove_header("// header\r\n\r\ntext", "// header"), Some("\r\ntext".to_string()));
+    assert_eq!(remove_header("text\n// header", "// header"), None);
+  }
+}
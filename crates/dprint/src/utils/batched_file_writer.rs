@@ -0,0 +1,179 @@
+use anyhow::bail;
+use anyhow::Error;
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use crate::environment::Environment;
+
+/// The pending writes are flushed once either of these is reached.
+const MAX_PENDING_FILES_COUNT: usize = 64;
+const MAX_PENDING_BYTES: usize = 8 * 1024 * 1024;
+
+#[derive(Default)]
+struct PendingWrites {
+  files: Vec<(PathBuf, String)>,
+  bytes: usize,
+}
+
+/// Collects the files to write into batches that are flushed with
+/// the files of each directory written by one of a bounded number
+/// of threads, optionally syncing the files and their directories
+/// to the storage device (`--fsync`).
+///
+/// Errors are stored and surfaced when finishing.
+pub struct BatchedFileWriter<TEnvironment: Environment> {
+  environment: TEnvironment,
+  fsync: bool,
+  pending: Mutex<PendingWrites>,
+  errors: Mutex<Vec<Error>>,
+}
+
+impl<TEnvironment: Environment> BatchedFileWriter<TEnvironment> {
+  pub fn new(environment: TEnvironment, fsync: bool) -> Self {
+    BatchedFileWriter {
+      environment,
+      fsync,
+      pending: Default::default(),
+      errors: Default::default(),
+    }
+  }
+
+  pub fn write(&self, file_path: PathBuf, file_text: String) {
+    let batch = {
+      let mut pending = self.pending.lock();
+      pending.bytes += file_text.len();
+      pending.files.push((file_path, file_text));
+      if pending.files.len() >= MAX_PENDING_FILES_COUNT || pending.bytes >= MAX_PENDING_BYTES {
+        Some(std::mem::take(&mut *pending))
+      } else {
+        None
+      }
+    };
+    if let Some(batch) = batch {
+      self.write_batch(batch.files);
+    }
+  }
+
+  /// Writes the remaining files, then errors if any file failed to be written.
+  pub fn finish(&self) -> Result<()> {
+    let batch = std::mem::take(&mut *self.pending.lock());
+    self.write_batch(batch.files);
+
+    let errors = std::mem::take(&mut *self.errors.lock());
+    if errors.is_empty() {
+      return Ok(());
+    }
+    for err in errors.iter() {
+      self.environment.log_stderr(&format!("{:#}", err));
+    }
+    bail!("Had {0} error(s) writing the formatted files.", errors.len())
+  }
+
+  fn write_batch(&self, files: Vec<(PathBuf, String)>) {
+    if files.is_empty() {
+      return;
+    }
+    let mut files_by_dir = BTreeMap::<PathBuf, Vec<(PathBuf, String)>>::new();
+    for (file_path, file_text) in files {
+      let dir_path = file_path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+      files_by_dir.entry(dir_path).or_default().push((file_path, file_text));
+    }
+    let dirs = files_by_dir.into_iter().collect::<Vec<_>>();
+    let thread_count = std::cmp::max(1, std::cmp::min(self.environment.max_threads(), dirs.len()));
+    if thread_count == 1 {
+      for (dir_path, files) in dirs.iter() {
+        self.write_dir_files(dir_path, files);
+      }
+      return;
+    }
+
+    let next_index = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+      for _ in 0..thread_count {
+        scope.spawn(|| loop {
+          let index = next_index.fetch_add(1, Ordering::SeqCst);
+          match dirs.get(index) {
+            Some((dir_path, files)) => self.write_dir_files(dir_path, files),
+            None => break,
+          }
+        });
+      }
+    });
+  }
+
+  fn write_dir_files(&self, dir_path: &Path, files: &[(PathBuf, String)]) {
+    let mut wrote_file = false;
+    for (file_path, file_text) in files {
+      let result = self
+        .environment
+        .write_file(file_path, file_text)
+        .and_then(|_| if self.fsync { self.environment.fsync(file_path) } else { Ok(()) });
+      match result {
+        Ok(_) => wrote_file = true,
+        Err(err) => self.errors.lock().push(err),
+      }
+    }
+    // sync the directory once per batch so the directory entries are durable
+    if self.fsync && wrote_file && !dir_path.as_os_str().is_empty() {
+      if let Err(err) = self.environment.fsync(dir_path) {
+        self.errors.lock().push(err);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::environment::TestEnvironment;
+
+  #[test]
+  fn should_write_files_in_batches() {
+    let environment = TestEnvironment::new();
+    let writer = BatchedFileWriter::new(environment.clone(), false);
+    for i in 0..MAX_PENDING_FILES_COUNT {
+      writer.write(PathBuf::from(format!("/dir{}/file.txt", i % 3)), i.to_string());
+    }
+    // the first batch was flushed
+    assert_eq!(environment.read_file("/dir1/file.txt").unwrap(), (MAX_PENDING_FILES_COUNT - 3).to_string());
+    writer.write(PathBuf::from("/dir4/file.txt"), "text".to_string());
+    assert!(!environment.path_exists("/dir4/file.txt"));
+    writer.finish().unwrap();
+    assert_eq!(environment.read_file("/dir4/file.txt").unwrap(), "text");
+    assert_eq!(environment.take_synced_paths(), Vec::<PathBuf>::new());
+  }
+
+  #[test]
+  fn should_sync_files_and_directories() {
+    let environment = TestEnvironment::new();
+    let writer = BatchedFileWriter::new(environment.clone(), true);
+    writer.write(PathBuf::from("/dir/a.txt"), "a".to_string());
+    writer.write(PathBuf::from("/dir/b.txt"), "b".to_string());
+    writer.finish().unwrap();
+    assert_eq!(
+      environment.take_synced_paths(),
+      vec![PathBuf::from("/dir/a.txt"), PathBuf::from("/dir/b.txt"), PathBuf::from("/dir")]
+    );
+  }
+
+  #[test]
+  fn should_surface_write_errors_when_finishing() {
+    let environment = TestEnvironment::new();
+    environment.set_assert_no_writes(true);
+    let writer = BatchedFileWriter::new(environment.clone(), false);
+    writer.write(PathBuf::from("/file.txt"), "text".to_string());
+    let err = writer.finish().err().unwrap();
+    assert_eq!(err.to_string(), "Had 1 error(s) writing the formatted files.");
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec!["Cannot write to /file.txt when running with --assert-no-writes."]
+    );
+  }
+}
@@ -0,0 +1,58 @@
+use anyhow::bail;
+use anyhow::Result;
+
+const ENV_VAR_REFERENCE_START: &str = "${env:";
+
+/// Replaces the references to environment variables in the text (ex. `${env:JAVA_HOME}`)
+/// with their values, where a variable that isn't set is replaced with nothing.
+pub fn expand_env_var_references(text: &str, get_env_var: impl Fn(&str) -> Option<String>) -> Result<String> {
+  let mut result = String::with_capacity(text.len());
+  let mut remaining = text;
+  while let Some(start_index) = remaining.find(ENV_VAR_REFERENCE_START) {
+    result.push_str(&remaining[..start_index]);
+    let reference = &remaining[start_index + ENV_VAR_REFERENCE_START.len()..];
+    let end_index = match reference.find('}') {
+      Some(end_index) => end_index,
+      None => bail!("Expected a closing brace for the environment variable reference in '{}'.", text),
+    };
+    let name = &reference[..end_index];
+    if name.is_empty() {
+      bail!("Expected the name of an environment variable in '{}'.", text);
+    }
+    if let Some(value) = get_env_var(name) {
+      result.push_str(&value);
+    }
+    remaining = &reference[end_index + 1..];
+  }
+  result.push_str(remaining);
+  Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn should_expand_env_var_references() {
+    let get_env_var = |name: &str| match name {
+      "JAVA_HOME" => Some("/opt/java".to_string()),
+      _ => None,
+    };
+    assert_eq!(expand_env_var_references("-Xmx1g", get_env_var).unwrap(), "-Xmx1g");
+    assert_eq!(expand_env_var_references("${env:JAVA_HOME}", get_env_var).unwrap(), "/opt/java");
+    assert_eq!(
+      expand_env_var_references("${env:JAVA_HOME}/bin:${env:OTHER}:$HOME", get_env_var).unwrap(),
+      "/opt/java/bin::$HOME"
+    );
+    assert_eq!(
+      expand_env_var_references("${env:JAVA_HOME", get_env_var).err().unwrap().to_string(),
+      "Expected a closing brace for the environment variable reference in '${env:JAVA_HOME'."
+    );
+    assert_eq!(
+      expand_env_var_references("${env:}", get_env_var).err().unwrap().to_string(),
+      "Expected the name of an environment variable in '${env:}'."
+    );
+  }
+}
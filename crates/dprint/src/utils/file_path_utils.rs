@@ -1,4 +1,5 @@
 use std::path::Path;
+use std::path::PathBuf;
 
 pub fn get_lowercase_file_extension(file_path: &Path) -> Option<String> {
   file_path.extension().and_then(|e| e.to_str()).map(|ext| String::from(ext).to_lowercase())
@@ -7,3 +8,61 @@ pub fn get_lowercase_file_extension(file_path: &Path) -> Option<String> {
 pub fn get_lowercase_file_name(file_path: &Path) -> Option<String> {
   file_path.file_name().and_then(|s| s.to_str()).map(|s| s.to_lowercase())
 }
+
+/// Gets if the file system is case insensitive, which is the default on Windows and macOS.
+pub fn is_case_insensitive_file_system() -> bool {
+  cfg!(any(windows, target_os = "macos"))
+}
+
+/// Gets a key for comparing file paths that ignores the case on case-insensitive
+/// file systems so a file is still found after a case-only rename.
+pub fn get_file_path_key(file_path: &Path) -> PathBuf {
+  get_file_path_key_with_case(file_path, is_case_insensitive_file_system())
+}
+
+fn get_file_path_key_with_case(file_path: &Path, is_case_insensitive: bool) -> PathBuf {
+  if is_case_insensitive {
+    PathBuf::from(file_path.to_string_lossy().to_lowercase())
+  } else {
+    file_path.to_path_buf()
+  }
+}
+
+/// Gets the pairs of patterns that only differ by case, which match
+/// the same files on case-insensitive file systems.
+pub fn get_patterns_differing_by_case(patterns: &[String]) -> Vec<(&str, &str)> {
+  let mut unique_patterns: Vec<&str> = Vec::with_capacity(patterns.len());
+  for pattern in patterns {
+    if !unique_patterns.contains(&pattern.as_str()) {
+      unique_patterns.push(pattern);
+    }
+  }
+  let mut result = Vec::new();
+  for (index, pattern) in unique_patterns.iter().enumerate() {
+    for other_pattern in unique_patterns.iter().skip(index + 1) {
+      if pattern.to_lowercase() == other_pattern.to_lowercase() {
+        result.push((*pattern, *other_pattern));
+      }
+    }
+  }
+  result
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn should_get_file_path_key() {
+    assert_eq!(get_file_path_key_with_case(Path::new("/Dir/File.ts"), true), PathBuf::from("/dir/file.ts"));
+    assert_eq!(get_file_path_key_with_case(Path::new("/Dir/File.ts"), false), PathBuf::from("/Dir/File.ts"));
+  }
+
+  #[test]
+  fn should_get_patterns_differing_by_case() {
+    let patterns = vec!["**/*.md".to_string(), "**/*.ts".to_string(), "**/*.MD".to_string(), "**/*.md".to_string()];
+    assert_eq!(get_patterns_differing_by_case(&patterns), vec![("**/*.md", "**/*.MD")]);
+  }
+}
@@ -0,0 +1,105 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Keeps each command well below the command line length limit on Windows (8191).
+const MAX_FIX_COMMAND_LEN: usize = 4000;
+
+/// Gets the `dprint fmt` commands that format the provided files when run from
+/// the current directory, batched so each command doesn't get too long.
+pub fn get_fix_commands(file_paths: &[PathBuf], config: Option<&str>, cwd: &Path) -> Vec<String> {
+  let mut command_prefix = "dprint fmt".to_string();
+  if let Some(config) = config {
+    command_prefix.push_str(" --config ");
+    command_prefix.push_str(&quote_shell_arg(config));
+  }
+
+  let mut file_path_args = file_paths
+    .iter()
+    .map(|file_path| {
+      let file_path = file_path.strip_prefix(cwd).unwrap_or(file_path);
+      quote_shell_arg(&file_path.to_string_lossy().replace('\\', "/"))
+    })
+    .collect::<Vec<_>>();
+  file_path_args.sort();
+  file_path_args.dedup();
+
+  let mut commands = Vec::new();
+  let mut current_command = command_prefix.clone();
+  for file_path_arg in file_path_args {
+    if current_command.len() > command_prefix.len() && current_command.len() + 1 + file_path_arg.len() > MAX_FIX_COMMAND_LEN {
+      commands.push(std::mem::replace(&mut current_command, command_prefix.clone()));
+    }
+    current_command.push(' ');
+    current_command.push_str(&file_path_arg);
+  }
+  if current_command.len() > command_prefix.len() {
+    commands.push(current_command);
+  }
+  commands
+}
+
+/// Gets the text of a shell script that runs the provided commands.
+pub fn get_fix_script_text(fix_commands: &[String]) -> String {
+  let mut text = "#!/bin/sh\nset -e\n".to_string();
+  for command in fix_commands {
+    text.push_str(command);
+    text.push('\n');
+  }
+  text
+}
+
+fn quote_shell_arg(text: &str) -> String {
+  let is_safe = !text.is_empty()
+    && text
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | '@' | '+' | ',' | ':' | '='));
+  if is_safe {
+    text.to_string()
+  } else {
+    format!("'{}'", text.replace('\'', "'\\''"))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn should_get_fix_commands() {
+    let cwd = PathBuf::from("/project");
+    let file_paths = vec![
+      PathBuf::from("/project/src/b.ts"),
+      PathBuf::from("/project/src/a.ts"),
+      PathBuf::from("/project/with space.ts"),
+      PathBuf::from("/other/it's.ts"),
+    ];
+    assert_eq!(
+      get_fix_commands(&file_paths, None, &cwd),
+      vec!["dprint fmt '/other/it'\\''s.ts' 'with space.ts' src/a.ts src/b.ts"]
+    );
+    assert_eq!(
+      get_fix_commands(&file_paths[..1], Some("config/dprint.json"), &cwd),
+      vec!["dprint fmt --config config/dprint.json src/b.ts"]
+    );
+    assert_eq!(get_fix_commands(&[], None, &cwd), Vec::<String>::new());
+  }
+
+  #[test]
+  fn should_batch_fix_commands() {
+    let cwd = PathBuf::from("/");
+    let file_paths = (0..1000).map(|i| PathBuf::from(format!("/dir/file{:04}.ts", i))).collect::<Vec<_>>();
+    let commands = get_fix_commands(&file_paths, None, &cwd);
+    assert_eq!(commands.len(), 5);
+    assert!(commands
+      .iter()
+      .all(|command| command.len() <= MAX_FIX_COMMAND_LEN && command.starts_with("dprint fmt dir/")));
+    assert_eq!(commands.iter().map(|command| command.split(' ').count() - 2).sum::<usize>(), 1000);
+  }
+
+  #[test]
+  fn should_get_fix_script_text() {
+    assert_eq!(get_fix_script_text(&["dprint fmt a.ts".to_string()]), "#!/bin/sh\nset -e\ndprint fmt a.ts\n");
+  }
+}
@@ -0,0 +1,72 @@
+use parking_lot::Mutex;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::environment::Environment;
+
+/// Markers used when the configuration doesn't specify any.
+pub const DEFAULT_GENERATED_FILE_MARKERS: [&str; 2] = ["@generated", "DO NOT EDIT"];
+
+/// Markers are only searched for in the first lines of a file so that
+/// a file that mentions a marker in its code isn't considered generated.
+const GENERATED_FILE_MARKER_LINE_COUNT: usize = 10;
+
+/// Gets if the file text contains a marker near its start.
+pub fn is_generated_file(file_text: &str, markers: &[String]) -> bool {
+  file_text
+    .lines()
+    .take(GENERATED_FILE_MARKER_LINE_COUNT)
+    .any(|line| markers.iter().any(|marker| line.contains(marker.as_str())))
+}
+
+/// Skips the generated files in a run and stores them for the summary.
+pub struct GeneratedFileSkipper {
+  markers: Vec<String>,
+  skipped_file_paths: Mutex<Vec<PathBuf>>,
+}
+
+impl GeneratedFileSkipper {
+  pub fn new(markers: Vec<String>) -> Self {
+    GeneratedFileSkipper {
+      markers,
+      skipped_file_paths: Default::default(),
+    }
+  }
+
+  pub fn should_skip(&self, file_path: &Path, file_text: &str) -> bool {
+    let is_generated = is_generated_file(file_text, &self.markers);
+    if is_generated {
+      self.skipped_file_paths.lock().push(file_path.to_path_buf());
+    }
+    is_generated
+  }
+
+  pub fn log_summary(&self, environment: &impl Environment) {
+    let mut skipped_file_paths = self.skipped_file_paths.lock();
+    if skipped_file_paths.is_empty() {
+      return;
+    }
+    skipped_file_paths.sort();
+    for file_path in skipped_file_paths.iter() {
+      log_verbose!(environment, "Skipped generated file: {}", file_path.display());
+    }
+    let suffix = if skipped_file_paths.len() == 1 { "file" } else { "files" };
+    environment.log(&format!("Skipped {} generated {}.", skipped_file_paths.len(), suffix));
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn should_get_if_generated_file() {
+    let markers = DEFAULT_GENERATED_FILE_MARKERS.iter().map(|marker| marker.to_string()).collect::<Vec<_>>();
+    assert!(is_generated_file("// @generated\nconst a = 1;", &markers));
+    assert!(is_generated_file("// Code generated by protoc-gen-go. DO NOT EDIT.\n", &markers));
+    assert!(!is_generated_file("const a = 1;", &markers));
+    assert!(!is_generated_file(&format!("{}// @generated", "\n".repeat(10)), &markers));
+    assert!(is_generated_file("# autogenerated", &["autogenerated".to_string()]));
+    assert!(!is_generated_file("// @generated", &[]));
+  }
+}
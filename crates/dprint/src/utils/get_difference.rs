@@ -1,12 +1,114 @@
 use std::time::Duration;
 
+use anyhow::bail;
+use anyhow::Result;
+use crossterm::style::Color;
 use crossterm::style::Stylize;
+use serde::Serialize;
 use similar::ChangeTag;
+use similar::DiffTag;
 use similar::TextDiffConfig;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffOptions {
+  /// Number of unchanged lines to show around each change.
+  pub context_lines: usize,
+  /// Whether to highlight the words that changed within a changed line.
+  pub word_diff: bool,
+  pub colors: DiffColors,
+}
+
+impl Default for DiffOptions {
+  fn default() -> Self {
+    DiffOptions {
+      context_lines: 3,
+      word_diff: true,
+      colors: Default::default(),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffColors {
+  pub removed: Color,
+  pub added: Color,
+}
+
+impl Default for DiffColors {
+  fn default() -> Self {
+    DiffColors {
+      removed: Color::Red,
+      added: Color::Green,
+    }
+  }
+}
+
+impl DiffColors {
+  /// Parses a theme name (`default` or `colorblind`) or a list
+  /// of colors (ex. `removed=magenta,added=cyan`).
+  pub fn parse(text: &str) -> Result<DiffColors> {
+    match text {
+      "default" => return Ok(DiffColors::default()),
+      "colorblind" => {
+        return Ok(DiffColors {
+          removed: Color::DarkYellow,
+          added: Color::Blue,
+        })
+      }
+      _ => {}
+    }
+
+    let mut colors = DiffColors::default();
+    for part in text.split(',') {
+      let (name, color_text) = match part.split_once('=') {
+        Some((name, color_text)) => (name.trim(), color_text.trim()),
+        None => bail!(
+          "Invalid diff colors '{}'. Expected 'default', 'colorblind', or a list of colors like 'removed=magenta,added=cyan'.",
+          text
+        ),
+      };
+      let color = match parse_color(color_text) {
+        Some(color) => color,
+        None => bail!(
+          "Invalid diff color '{}'. Expected one of: black, red, green, yellow, blue, magenta, cyan, white, grey",
+          color_text
+        ),
+      };
+      match name {
+        "removed" => colors.removed = color,
+        "added" => colors.added = color,
+        _ => bail!("Unknown diff color name '{}'. Expected 'removed' or 'added'.", name),
+      }
+    }
+    Ok(colors)
+  }
+}
+
+fn parse_color(text: &str) -> Option<Color> {
+  Some(match text.to_lowercase().as_str() {
+    "black" => Color::Black,
+    "red" => Color::Red,
+    "green" => Color::Green,
+    "yellow" => Color::DarkYellow,
+    "blue" => Color::Blue,
+    "magenta" => Color::Magenta,
+    "cyan" => Color::Cyan,
+    "white" => Color::White,
+    "grey" | "gray" => Color::Grey,
+    _ => return None,
+  })
+}
+
 /// Gets a string showing the difference between two strings.
+#[cfg(test)]
 pub fn get_difference(old_text: &str, new_text: &str) -> String {
+  get_difference_with_options(old_text, new_text, &DiffOptions::default())
+}
+
+/// Gets a string showing the difference between two strings using the provided options.
+pub fn get_difference_with_options(old_text: &str, new_text: &str, options: &DiffOptions) -> String {
   debug_assert!(old_text != new_text);
+  let colors = &options.colors;
 
   // normalize newlines
   let old_text = old_text.replace("\r\n", "\n");
@@ -20,9 +122,11 @@ pub fn get_difference(old_text: &str, new_text: &str) -> String {
   config.timeout(Duration::from_millis(500));
 
   let diff = config.diff_lines(&old_text, &new_text);
+  let mut unified_diff = diff.unified_diff();
+  unified_diff.context_radius(options.context_lines);
 
   let mut output = String::new();
-  for hunk in diff.unified_diff().iter_hunks() {
+  for hunk in unified_diff.iter_hunks() {
     if !output.is_empty() {
       output.push_str("\n...");
     }
@@ -34,8 +138,8 @@ pub fn get_difference(old_text: &str, new_text: &str) -> String {
           output.push('\n');
         }
         let sign = match change.tag() {
-          ChangeTag::Delete => get_removal_text("-"),
-          ChangeTag::Insert => get_addition_text("+"),
+          ChangeTag::Delete => get_removal_text("-", colors),
+          ChangeTag::Insert => get_addition_text("+", colors),
           ChangeTag::Equal => " ".to_string(),
         };
         output.push_str(&format!(
@@ -56,21 +160,21 @@ pub fn get_difference(old_text: &str, new_text: &str) -> String {
           };
           let change_text = annotate_whitespace(change_text);
           if !change_text.is_empty() {
-            let change_text = if highlight {
+            let change_text = if highlight && options.word_diff {
               match change.tag() {
-                ChangeTag::Delete => get_removal_highlight_text(&change_text),
-                ChangeTag::Insert => get_addition_highlight_text(&change_text),
+                ChangeTag::Delete => get_removal_highlight_text(&change_text, colors),
+                ChangeTag::Insert => get_addition_highlight_text(&change_text, colors),
                 ChangeTag::Equal => change_text,
               }
             } else {
-              get_text_for_tag(change.tag(), change_text)
+              get_text_for_tag(change.tag(), change_text, colors)
             };
             output.push_str(&change_text);
           }
         }
         if change.missing_newline() {
           // show an ETX (end of text)
-          output.push_str(&get_text_for_tag(change.tag(), "\u{2403}".to_string()));
+          output.push_str(&get_text_for_tag(change.tag(), "\u{2403}".to_string(), colors));
         }
       }
     }
@@ -79,10 +183,113 @@ pub fn get_difference(old_text: &str, new_text: &str) -> String {
   output
 }
 
-fn get_text_for_tag(tag: ChangeTag, text: String) -> String {
+/// Gets a unified diff (patch) of the changes to a file, which may
+/// be applied with `git apply` or `patch -p1`.
+pub fn get_unified_diff(file_path: &str, old_text: &str, new_text: &str) -> String {
+  let old_header = format!("a/{}", file_path);
+  let new_header = format!("b/{}", file_path);
+  let mut config = TextDiffConfig::default();
+  config.timeout(Duration::from_millis(500));
+  let diff = config.diff_lines(old_text, new_text);
+  let mut unified_diff = diff.unified_diff();
+  unified_diff.header(&old_header, &new_header);
+  unified_diff.to_string()
+}
+
+/// Gets the number of added and removed lines between the texts.
+pub fn get_changed_line_counts(old_text: &str, new_text: &str) -> (usize, usize) {
+  let mut config = TextDiffConfig::default();
+  config.timeout(Duration::from_millis(500));
+  let diff = config.diff_lines(old_text, new_text);
+  let mut added_count = 0;
+  let mut removed_count = 0;
+  for change in diff.iter_all_changes() {
+    match change.tag() {
+      ChangeTag::Insert => added_count += 1,
+      ChangeTag::Delete => removed_count += 1,
+      ChangeTag::Equal => {}
+    }
+  }
+  (added_count, removed_count)
+}
+
+/// Lines of the old text that are replaced in the new text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineChange {
+  /// The one-based line of the old text where the change starts.
+  pub start_line: usize,
+  /// The number of replaced lines, which is zero when lines are inserted.
+  pub line_count: usize,
+  /// The text that replaces the lines.
+  pub expected_text: String,
+}
+
+/// Gets the lines that changed between the texts along with the text that replaces them.
+pub fn get_line_changes(old_text: &str, new_text: &str) -> Vec<LineChange> {
+  let mut config = TextDiffConfig::default();
+  config.timeout(Duration::from_millis(500));
+  let diff = config.diff_lines(old_text, new_text);
+  let mut changes = Vec::new();
+  for op in diff.ops() {
+    let (tag, old_range, new_range) = op.as_tag_tuple();
+    if tag != DiffTag::Equal {
+      changes.push(LineChange {
+        start_line: old_range.start + 1,
+        line_count: old_range.len(),
+        expected_text: diff.new_slices()[new_range].concat(),
+      });
+    }
+  }
+  changes
+}
+
+/// Applies only the changes of the formatted text that touch the lines of the
+/// range in the original text. This is used to format a range of the text with
+/// plugins that only format the entire file.
+pub fn splice_formatted_range(file_text: &str, formatted_text: &str, range: &std::ops::Range<usize>) -> String {
+  let mut line_starts = vec![0];
+  line_starts.extend(file_text.match_indices('\n').map(|(index, _)| index + 1));
+  let get_line_start = |line_index: usize| line_starts.get(line_index).copied().unwrap_or(file_text.len());
+
+  // split changes that replace lines one to one so only the lines in the range are replaced
+  let mut changes = Vec::new();
+  for change in get_line_changes(file_text, formatted_text) {
+    let expected_lines = change.expected_text.split_inclusive('\n').collect::<Vec<_>>();
+    if change.line_count > 1 && expected_lines.len() == change.line_count {
+      for (i, expected_line) in expected_lines.into_iter().enumerate() {
+        changes.push((change.start_line + i, 1, expected_line.to_string()));
+      }
+    } else {
+      changes.push((change.start_line, change.line_count, change.expected_text));
+    }
+  }
+
+  let mut result = String::with_capacity(file_text.len());
+  let mut last_end = 0;
+  for (start_line, line_count, expected_text) in changes {
+    let start = get_line_start(start_line - 1);
+    let end = get_line_start(start_line - 1 + line_count);
+    // inserted lines and empty ranges are positions, so they touch what's on either side
+    let touches_range = if start == end || range.start == range.end {
+      start <= range.end && range.start <= end
+    } else {
+      start < range.end && range.start < end
+    };
+    if touches_range {
+      result.push_str(&file_text[last_end..start]);
+      result.push_str(&expected_text);
+      last_end = end;
+    }
+  }
+  result.push_str(&file_text[last_end..]);
+  result
+}
+
+fn get_text_for_tag(tag: ChangeTag, text: String, colors: &DiffColors) -> String {
   match tag {
-    ChangeTag::Delete => get_removal_text(&text),
-    ChangeTag::Insert => get_addition_text(&text),
+    ChangeTag::Delete => get_removal_text(&text, colors),
+    ChangeTag::Insert => get_addition_text(&text, colors),
     ChangeTag::Equal => text,
   }
 }
@@ -91,23 +298,23 @@ fn get_number_char_count(numbers: impl Iterator<Item = usize>) -> usize {
   numbers.max().unwrap_or(1).to_string().chars().count()
 }
 
-fn get_addition_text(text: &str) -> String {
-  text.green().to_string()
+fn get_addition_text(text: &str, colors: &DiffColors) -> String {
+  text.with(colors.added).to_string()
 }
 
-fn get_addition_highlight_text(text: &str) -> String {
+fn get_addition_highlight_text(text: &str, colors: &DiffColors) -> String {
   let text = text.replace('\t', "\u{21E5}");
-  text.black().on_green().to_string()
+  text.black().on(colors.added).to_string()
 }
 
-fn get_removal_text(text: &str) -> String {
+fn get_removal_text(text: &str, colors: &DiffColors) -> String {
   let text = text.replace('\t', "\u{21E5}");
-  text.red().to_string()
+  text.with(colors.removed).to_string()
 }
 
-fn get_removal_highlight_text(text: &str) -> String {
+fn get_removal_highlight_text(text: &str, colors: &DiffColors) -> String {
   let text = text.replace('\t', "\u{21E5}");
-  text.white().on_red().to_string()
+  text.white().on(colors.removed).to_string()
 }
 
 fn annotate_whitespace(text: &str) -> String {
@@ -119,6 +326,157 @@ mod test {
   use super::*;
   use pretty_assertions::assert_eq;
 
+  #[test]
+  fn should_get_changed_line_counts() {
+    assert_eq!(get_changed_line_counts("a\nb\nc\n", "a\nb\nc\n"), (0, 0));
+    assert_eq!(get_changed_line_counts("a\nb\nc\n", "a\nB\nc\nd\n"), (2, 1));
+  }
+
+  #[test]
+  fn should_get_line_changes() {
+    assert_eq!(get_line_changes("a\nb\nc\n", "a\nb\nc\n"), vec![]);
+    assert_eq!(
+      get_line_changes("a\nb\nc\nd\n", "a\nB\nc\n\nd\ne\n"),
+      vec![
+        LineChange {
+          start_line: 2,
+          line_count: 1,
+          expected_text: "B\n".to_string(),
+        },
+        LineChange {
+          start_line: 4,
+          line_count: 0,
+          expected_text: "\n".to_string(),
+        },
+        LineChange {
+          start_line: 5,
+          line_count: 0,
+          expected_text: "e\n".to_string(),
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn should_splice_formatted_range() {
+    let file_text = "a \nb \nc \nd \n";
+    let formatted_text = "a\nb\nc\nd\n";
+    // only the second and third lines
+    assert_eq!(splice_formatted_range(file_text, formatted_text, &(4..7)), "a \nb\nc\nd \n");
+    // an empty range formats its line
+    assert_eq!(splice_formatted_range(file_text, formatted_text, &(1..1)), "a\nb \nc \nd \n");
+    // inserted lines at the range
+    assert_eq!(splice_formatted_range("a\nb\n", "a\n\nb\n\n", &(2..3)), "a\n\nb\n");
+    assert_eq!(splice_formatted_range(file_text, formatted_text, &(0..file_text.len())), formatted_text);
+  }
+
+  #[test]
+  fn should_get_unified_diff() {
+    assert_eq!(
+      get_unified_diff("src/a.txt", "a\nb\nc\n", "a\nB\nc\n"),
+      "--- a/src/a.txt\n+++ b/src/a.txt\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+    );
+  }
+
+  #[test]
+  fn should_limit_context_lines() {
+    let options = DiffOptions {
+      context_lines: 1,
+      ..Default::default()
+    };
+    assert_eq!(
+      get_difference_with_options("1\n2\n3\ntest1\n4\n5\n6\n", "1\n2\n3\ntest2\n4\n5\n6\n", &options),
+      format!(
+        "3 3| 3\n  4|{}{}\n4  |{}{}\n5 5| 4",
+        get_removal_text("-"),
+        get_removal_highlight_text("test1"),
+        get_addition_text("+"),
+        get_addition_highlight_text("test2"),
+      )
+    );
+  }
+
+  #[test]
+  fn should_not_highlight_words_when_word_diff_disabled() {
+    let options = DiffOptions {
+      word_diff: false,
+      ..Default::default()
+    };
+    assert_eq!(
+      get_difference_with_options("test1\n", "test2\n", &options),
+      format!(
+        "  1|{}{}\n1  |{}{}",
+        get_removal_text("-"),
+        get_removal_text("test1"),
+        get_addition_text("+"),
+        get_addition_text("test2"),
+      )
+    );
+  }
+
+  #[test]
+  fn should_use_diff_colors() {
+    let colors = DiffColors::parse("removed=magenta,added=cyan").unwrap();
+    let options = DiffOptions { colors, ..Default::default() };
+    assert_eq!(
+      get_difference_with_options("test1\n", "test2\n", &options),
+      format!(
+        "  1|{}{}\n1  |{}{}",
+        "-".magenta(),
+        "test1".white().on_magenta(),
+        "+".cyan(),
+        "test2".black().on_cyan(),
+      )
+    );
+  }
+
+  #[test]
+  fn should_parse_diff_colors() {
+    assert_eq!(DiffColors::parse("default").unwrap(), DiffColors::default());
+    assert_eq!(
+      DiffColors::parse("colorblind").unwrap(),
+      DiffColors {
+        removed: Color::DarkYellow,
+        added: Color::Blue,
+      }
+    );
+    assert_eq!(
+      DiffColors::parse("added=blue").unwrap(),
+      DiffColors {
+        removed: Color::Red,
+        added: Color::Blue,
+      }
+    );
+    assert_eq!(
+      DiffColors::parse("other").err().unwrap().to_string(),
+      "Invalid diff colors 'other'. Expected 'default', 'colorblind', or a list of colors like 'removed=magenta,added=cyan'."
+    );
+    assert_eq!(
+      DiffColors::parse("added=pink").err().unwrap().to_string(),
+      "Invalid diff color 'pink'. Expected one of: black, red, green, yellow, blue, magenta, cyan, white, grey"
+    );
+    assert_eq!(
+      DiffColors::parse("changed=red").err().unwrap().to_string(),
+      "Unknown diff color name 'changed'. Expected 'removed' or 'added'."
+    );
+  }
+
+  fn get_addition_text(text: &str) -> String {
+    super::get_addition_text(text, &DiffColors::default())
+  }
+
+  fn get_addition_highlight_text(text: &str) -> String {
+    super::get_addition_highlight_text(text, &DiffColors::default())
+  }
+
+  fn get_removal_text(text: &str) -> String {
+    super::get_removal_text(text, &DiffColors::default())
+  }
+
+  fn get_removal_highlight_text(text: &str) -> String {
+    super::get_removal_highlight_text(text, &DiffColors::default())
+  }
+
   #[test]
   fn should_get_when_differs_by_line_endings() {
     assert_eq!(get_difference("test\r\n", "test\n"), " | Text differed by line endings.");
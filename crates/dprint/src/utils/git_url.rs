@@ -0,0 +1,311 @@
+use anyhow::bail;
+use anyhow::Result;
+use url::Url;
+
+use super::get_bytes_hash;
+use super::ResolvedPath;
+use crate::environment::Environment;
+
+/// A file in a git repository pinned to a ref and verified by its commit hash.
+///
+/// Ex. `git+https://github.com/org/repo.git#v1.0.0:path/to/dprint.json@<commit-hash>`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GitUrl {
+  pub repo_url: String,
+  pub git_ref: String,
+  pub file_path: String,
+  pub commit: String,
+}
+
+impl GitUrl {
+  pub fn is_git_url(url: &Url) -> bool {
+    url.scheme().starts_with("git+")
+  }
+
+  pub fn parse(url: &Url) -> Result<GitUrl> {
+    let text = url.as_str();
+    let (repo_url, fragment) = match text.strip_prefix("git+").and_then(|text| text.split_once('#')) {
+      Some(value) => value,
+      None => bail!("{}", get_invalid_git_url_message(text)),
+    };
+    let (git_ref_and_path, commit) = match fragment.rsplit_once('@') {
+      Some((git_ref_and_path, commit)) => (git_ref_and_path, Some(commit)),
+      None => (fragment, None),
+    };
+    let (git_ref, file_path) = match git_ref_and_path.split_once(':') {
+      Some(value) => value,
+      None => bail!("{}", get_invalid_git_url_message(text)),
+    };
+    let commit = match commit {
+      Some(commit) => commit,
+      // the ref is already pinned when it's a commit hash
+      None if is_commit_hash(git_ref) => git_ref,
+      None => bail!(
+        "The git url {} must be pinned to a commit hash for security reasons (ex. {}@<commit-hash>).",
+        text,
+        text
+      ),
+    };
+    if !is_commit_hash(commit) {
+      bail!("Expected a full commit hash in git url {}, but found '{}'.", text, commit);
+    }
+    if git_ref.is_empty() || file_path.is_empty() {
+      bail!("{}", get_invalid_git_url_message(text));
+    }
+    // these are provided to git, so don't allow them to be mistaken for options
+    if repo_url.starts_with('-') || git_ref.starts_with('-') {
+      bail!("The repository url and ref of git url {} must not start with a hyphen.", text);
+    }
+    // the file is resolved within the checkout, so don't allow it to be outside of it
+    if file_path.contains('\\') || file_path.split('/').any(|segment| segment == "..") {
+      bail!(
+        "The file path of git url {} must not contain backslashes or '..' segments, so it stays within the repository.",
+        text
+      );
+    }
+
+    Ok(GitUrl {
+      repo_url: repo_url.to_string(),
+      git_ref: git_ref.to_string(),
+      file_path: file_path.trim_start_matches("./").trim_start_matches('/').to_string(),
+      commit: commit.to_lowercase(),
+    })
+  }
+
+  /// Creates a url to another file in the same repository and commit.
+  pub fn join(&self, relative_path: &str) -> Result<Url> {
+    let dir_path = match self.file_path.rsplit_once('/') {
+      Some((dir_path, _)) => format!("{}/", dir_path),
+      None => String::new(),
+    };
+    // use a url to resolve the relative path
+    let base = Url::parse("file:///")?.join(&dir_path)?;
+    let file_path = base.join(relative_path)?.path().trim_start_matches('/').to_string();
+    Ok(Url::parse(&format!("git+{}#{}:{}@{}", self.repo_url, self.git_ref, file_path, self.commit))?)
+  }
+}
+
+fn get_invalid_git_url_message(text: &str) -> String {
+  format!("Invalid git url {}. Expected the format git+<repo-url>#<ref>:<file-path>@<commit-hash>", text)
+}
+
+fn is_commit_hash(text: &str) -> bool {
+  // sha-1 or sha-256
+  (text.len() == 40 || text.len() == 64) && text.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Shallowly fetches the repository into the cache and resolves the file within it.
+pub fn resolve_git_url(url: &Url, environment: &impl Environment) -> Result<ResolvedPath> {
+  let git_url = GitUrl::parse(url)?;
+  let cache_dir = environment.get_cache_dir().join_panic_relative("git");
+  environment.mk_dir_all(&cache_dir)?;
+
+  let key = get_bytes_hash(format!("{}#{}@{}", git_url.repo_url, git_url.git_ref, git_url.commit).as_bytes()).to_string();
+  let repo_dir = cache_dir.join_panic_relative(&key);
+  let verified_file_path = cache_dir.join_panic_relative(format!("{}.verified", key));
+  let is_verified = environment
+    .read_file(&verified_file_path)
+    .map(|text| text.trim() == git_url.commit)
+    .unwrap_or(false);
+  let mut is_first_download = false;
+
+  if !is_verified {
+    is_first_download = true;
+    if environment.path_exists(&repo_dir) {
+      environment.remove_dir_all(&repo_dir)?;
+    }
+    let repo_dir_text = repo_dir.to_string_lossy().to_string();
+    environment.run_command("git", &["init", "--quiet", &repo_dir_text])?;
+    environment.run_command(
+      "git",
+      &[
+        "-C",
+        &repo_dir_text,
+        "fetch",
+        "--quiet",
+        "--depth",
+        "1",
+        "--",
+        &git_url.repo_url,
+        &git_url.git_ref,
+      ],
+    )?;
+    let fetched_commit = environment.run_command("git", &["-C", &repo_dir_text, "rev-parse", "FETCH_HEAD"])?;
+    let fetched_commit = fetched_commit.trim().to_lowercase();
+    if fetched_commit != git_url.commit {
+      bail!(
+        "The commit hash of {} at {} did not match the expected commit hash.\n\nExpected: {}\nActual: {}",
+        git_url.repo_url,
+        git_url.git_ref,
+        git_url.commit,
+        fetched_commit,
+      );
+    }
+    environment.run_command("git", &["-C", &repo_dir_text, "checkout", "--quiet", "FETCH_HEAD"])?;
+    environment.write_file(&verified_file_path, &git_url.commit)?;
+  }
+
+  let file_path = repo_dir.join_panic_relative(&git_url.file_path);
+  if !environment.path_exists(&file_path) {
+    bail!("Could not find {} in {} at {}.", git_url.file_path, git_url.repo_url, git_url.git_ref);
+  }
+
+  Ok(ResolvedPath::remote(file_path, url.clone(), is_first_download))
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use crate::environment::TestEnvironment;
+
+  use super::*;
+
+  const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+
+  #[test]
+  fn should_parse_git_url() {
+    let url = Url::parse(&format!("git+https://github.com/org/repo.git#v1.0.0:configs/dprint.json@{}", COMMIT)).unwrap();
+    assert!(GitUrl::is_git_url(&url));
+    assert_eq!(
+      GitUrl::parse(&url).unwrap(),
+      GitUrl {
+        repo_url: "https://github.com/org/repo.git".to_string(),
+        git_ref: "v1.0.0".to_string(),
+        file_path: "configs/dprint.json".to_string(),
+        commit: COMMIT.to_string(),
+      }
+    );
+    assert!(!GitUrl::is_git_url(&Url::parse("https://github.com/org/repo.git").unwrap()));
+  }
+
+  #[test]
+  fn should_parse_git_url_with_commit_ref() {
+    let url = Url::parse(&format!("git+https://github.com/org/repo.git#{}:dprint.json", COMMIT)).unwrap();
+    let git_url = GitUrl::parse(&url).unwrap();
+    assert_eq!(git_url.git_ref, COMMIT);
+    assert_eq!(git_url.commit, COMMIT);
+  }
+
+  #[test]
+  fn should_error_for_unpinned_git_url() {
+    let url = Url::parse("git+https://github.com/org/repo.git#main:dprint.json").unwrap();
+    assert_eq!(
+      GitUrl::parse(&url).err().unwrap().to_string(),
+      concat!(
+        "The git url git+https://github.com/org/repo.git#main:dprint.json must be pinned to a commit hash ",
+        "for security reasons (ex. git+https://github.com/org/repo.git#main:dprint.json@<commit-hash>)."
+      )
+    );
+    let url = Url::parse("git+https://github.com/org/repo.git#main:dprint.json@abc").unwrap();
+    assert_eq!(
+      GitUrl::parse(&url).err().unwrap().to_string(),
+      "Expected a full commit hash in git url git+https://github.com/org/repo.git#main:dprint.json@abc, but found 'abc'."
+    );
+    let url = Url::parse("git+https://github.com/org/repo.git").unwrap();
+    assert_eq!(
+      GitUrl::parse(&url).err().unwrap().to_string(),
+      "Invalid git url git+https://github.com/org/repo.git. Expected the format git+<repo-url>#<ref>:<file-path>@<commit-hash>"
+    );
+  }
+
+  #[test]
+  fn should_error_for_git_url_with_option_like_ref() {
+    let url = Url::parse(&format!(
+      "git+https://github.com/org/repo.git#--upload-pack=touch /tmp/a:dprint.json@{}",
+      COMMIT
+    ))
+    .unwrap();
+    assert_eq!(
+      GitUrl::parse(&url).err().unwrap().to_string(),
+      format!("The repository url and ref of git url {} must not start with a hyphen.", url.as_str())
+    );
+  }
+
+  #[test]
+  fn should_error_for_git_url_with_file_path_outside_repo() {
+    for file_path in ["../x", "configs/../../x", "..", "configs\\..\\x"] {
+      let url = Url::parse(&format!("git+https://github.com/org/repo.git#v1:{}@{}", file_path, COMMIT)).unwrap();
+      assert_eq!(
+        GitUrl::parse(&url).err().unwrap().to_string(),
+        format!(
+          "The file path of git url {} must not contain backslashes or '..' segments, so it stays within the repository.",
+          url.as_str()
+        )
+      );
+    }
+    // allowed in a file name
+    let url = Url::parse(&format!("git+https://github.com/org/repo.git#v1:configs/..dprint.json@{}", COMMIT)).unwrap();
+    assert_eq!(GitUrl::parse(&url).unwrap().file_path, "configs/..dprint.json");
+  }
+
+  #[test]
+  fn should_join_relative_path() {
+    let url = Url::parse(&format!("git+https://github.com/org/repo.git#v1:configs/dprint.json@{}", COMMIT)).unwrap();
+    let git_url = GitUrl::parse(&url).unwrap();
+    assert_eq!(
+      git_url.join("../base.json").unwrap().as_str(),
+      format!("git+https://github.com/org/repo.git#v1:base.json@{}", COMMIT)
+    );
+    assert_eq!(
+      git_url.join("./other.json").unwrap().as_str(),
+      format!("git+https://github.com/org/repo.git#v1:configs/other.json@{}", COMMIT)
+    );
+  }
+
+  #[test]
+  fn should_resolve_git_url() {
+    let environment = TestEnvironment::new();
+    let url = Url::parse(&format!("git+https://github.com/org/repo.git#v1:dprint.json@{}", COMMIT)).unwrap();
+    let key = get_bytes_hash(format!("https://github.com/org/repo.git#v1@{}", COMMIT).as_bytes());
+    let repo_dir = format!("/cache/git/{}", key);
+    environment.add_command_output(&format!("git init --quiet {}", repo_dir), "");
+    environment.add_command_output(
+      &format!("git -C {} fetch --quiet --depth 1 -- https://github.com/org/repo.git v1", repo_dir),
+      "",
+    );
+    environment.add_command_output(&format!("git -C {} rev-parse FETCH_HEAD", repo_dir), &format!("{}\n", COMMIT));
+    environment.add_command_output(&format!("git -C {} checkout --quiet FETCH_HEAD", repo_dir), "");
+    // simulate the checkout
+    environment.write_file(format!("{}/dprint.json", repo_dir), "{}").unwrap();
+
+    let result = resolve_git_url(&url, &environment).unwrap();
+    assert_eq!(result.file_path.to_string_lossy(), format!("{}/dprint.json", repo_dir));
+    assert_eq!(result.is_remote(), true);
+    assert_eq!(result.is_first_download, true);
+    assert_eq!(environment.take_run_commands().len(), 4);
+
+    // should use the cache the second time
+    let result = resolve_git_url(&url, &environment).unwrap();
+    assert_eq!(result.is_first_download, false);
+    assert_eq!(environment.take_run_commands().len(), 0);
+  }
+
+  #[test]
+  fn should_error_when_commit_does_not_match() {
+    let environment = TestEnvironment::new();
+    let url = Url::parse(&format!("git+https://github.com/org/repo.git#v1:dprint.json@{}", COMMIT)).unwrap();
+    let key = get_bytes_hash(format!("https://github.com/org/repo.git#v1@{}", COMMIT).as_bytes());
+    let repo_dir = format!("/cache/git/{}", key);
+    environment.add_command_output(&format!("git init --quiet {}", repo_dir), "");
+    environment.add_command_output(
+      &format!("git -C {} fetch --quiet --depth 1 -- https://github.com/org/repo.git v1", repo_dir),
+      "",
+    );
+    environment.add_command_output(
+      &format!("git -C {} rev-parse FETCH_HEAD", repo_dir),
+      "ffffffffffffffffffffffffffffffffffffffff\n",
+    );
+
+    let err = resolve_git_url(&url, &environment).err().unwrap();
+    assert_eq!(
+      err.to_string(),
+      format!(
+        "The commit hash of https://github.com/org/repo.git at v1 did not match the expected commit hash.\n\nExpected: {}\nActual: {}",
+        COMMIT, "ffffffffffffffffffffffffffffffffffffffff"
+      )
+    );
+    assert_eq!(environment.path_exists(format!("/cache/git/{}.verified", key)), false);
+    environment.take_run_commands();
+  }
+}
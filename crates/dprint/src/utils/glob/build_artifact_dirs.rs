@@ -0,0 +1,90 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use crate::environment::DirEntry;
+use crate::environment::DirEntryKind;
+
+use super::is_negated_glob;
+use super::GlobPattern;
+
+/// Directory names of build artifacts.
+const BUILD_ARTIFACT_DIR_NAMES: [&str; 1] = [".venv"];
+/// Files that mark their directory as containing build artifacts, which are
+/// [cache directory tags](https://bford.info/cachedir/) (ex. Cargo's _target_ directory)
+/// and the configuration of Python virtual environments.
+const BUILD_ARTIFACT_MARKER_FILE_NAMES: [&str; 2] = ["CACHEDIR.TAG", "pyvenv.cfg"];
+
+/// Finds the directories of build artifacts and dependencies, which
+/// are skipped when globbing unless the includes name them explicitly.
+pub struct BuildArtifactDirs {
+  explicitly_included_dir_names: HashSet<String>,
+}
+
+impl BuildArtifactDirs {
+  pub fn new(includes: &[GlobPattern]) -> Self {
+    // a directory is explicitly included when its name is used in an include pattern
+    let explicitly_included_dir_names = includes
+      .iter()
+      .filter(|pattern| !is_negated_glob(&pattern.relative_pattern))
+      .flat_map(|pattern| pattern.relative_pattern.split('/'))
+      .filter(|component| !component.is_empty() && !component.contains(|c| matches!(c, '*' | '?' | '[' | '{')))
+      .map(|component| component.to_string())
+      .collect();
+    BuildArtifactDirs { explicitly_included_dir_names }
+  }
+
+  /// Gets the marker that makes the directory one of build artifacts when it's not explicitly included.
+  pub fn get_marker(&self, dir_path: &Path, entries: &[DirEntry]) -> Option<&'static str> {
+    let dir_name = dir_path.file_name()?.to_string_lossy();
+    if self.explicitly_included_dir_names.contains(dir_name.as_ref()) {
+      return None;
+    }
+    if let Some(name) = BUILD_ARTIFACT_DIR_NAMES.iter().find(|name| **name == dir_name) {
+      return Some(*name);
+    }
+    entries
+      .iter()
+      .filter(|entry| matches!(entry.kind, DirEntryKind::File))
+      .filter_map(|entry| entry.path.file_name())
+      .find_map(|file_name| BUILD_ARTIFACT_MARKER_FILE_NAMES.iter().find(|name| file_name == OsStr::new(name)).copied())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::path::PathBuf;
+
+  use super::*;
+  use crate::environment::CanonicalizedPathBuf;
+
+  fn file_entry(path: &str) -> DirEntry {
+    DirEntry {
+      kind: DirEntryKind::File,
+      path: PathBuf::from(path),
+    }
+  }
+
+  #[test]
+  fn should_get_build_artifact_dir_marker() {
+    let base_dir = CanonicalizedPathBuf::new_for_testing("/");
+    let dirs = BuildArtifactDirs::new(&[
+      GlobPattern::new("**/*.json".to_string(), base_dir.clone()),
+      GlobPattern::new("generated/**/*.json".to_string(), base_dir.clone()),
+      GlobPattern::new("!vendor/**".to_string(), base_dir),
+    ]);
+    assert_eq!(dirs.get_marker(Path::new("/src"), &[file_entry("/src/a.json")]), None);
+    assert_eq!(dirs.get_marker(Path::new("/.venv"), &[]), Some(".venv"));
+    assert_eq!(dirs.get_marker(Path::new("/env"), &[file_entry("/env/pyvenv.cfg")]), Some("pyvenv.cfg"));
+    assert_eq!(
+      dirs.get_marker(Path::new("/target"), &[file_entry("/target/a.json"), file_entry("/target/CACHEDIR.TAG")]),
+      Some("CACHEDIR.TAG")
+    );
+    assert_eq!(
+      dirs.get_marker(Path::new("/vendor"), &[file_entry("/vendor/CACHEDIR.TAG")]),
+      Some("CACHEDIR.TAG")
+    );
+    // explicitly included
+    assert_eq!(dirs.get_marker(Path::new("/generated"), &[file_entry("/generated/CACHEDIR.TAG")]), None);
+  }
+}
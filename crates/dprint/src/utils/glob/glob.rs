@@ -10,12 +10,20 @@ use std::sync::Arc;
 use crate::environment::DirEntry;
 use crate::environment::DirEntryKind;
 use crate::environment::Environment;
+use crate::utils::is_case_insensitive_file_system;
 
+use super::BuildArtifactDirs;
 use super::GlobMatcher;
 use super::GlobMatcherOptions;
 use super::GlobPatterns;
 
-pub fn glob(environment: &impl Environment, base: impl AsRef<Path>, file_patterns: GlobPatterns) -> Result<Vec<PathBuf>> {
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GlobOptions {
+  /// Skips the directories of build artifacts and dependencies that aren't explicitly included.
+  pub skip_build_artifacts: bool,
+}
+
+pub fn glob(environment: &impl Environment, base: impl AsRef<Path>, file_patterns: GlobPatterns, options: &GlobOptions) -> Result<Vec<PathBuf>> {
   if file_patterns.includes.iter().all(|p| p.is_negated()) {
     // performance improvement (see issue #379)
     log_verbose!(environment, "Skipping negated globs: {:?}", file_patterns.includes);
@@ -25,10 +33,15 @@ pub fn glob(environment: &impl Environment, base: impl AsRef<Path>, file_pattern
   let start_instant = std::time::Instant::now();
   log_verbose!(environment, "Globbing: {:?}", file_patterns);
 
+  let build_artifact_dirs = if options.skip_build_artifacts {
+    Some(BuildArtifactDirs::new(&file_patterns.includes))
+  } else {
+    None
+  };
   let glob_matcher = GlobMatcher::new(
     file_patterns,
     &GlobMatcherOptions {
-      case_sensitive: !cfg!(windows),
+      case_sensitive: !is_case_insensitive_file_system(),
     },
   )?;
 
@@ -38,7 +51,7 @@ pub fn glob(environment: &impl Environment, base: impl AsRef<Path>, file_pattern
   // This is a performance improvement to attempt to reduce the time of globbing down
   // to the speed of `fs::read_dir` calls. Essentially, run all the `fs::read_dir` calls
   // on a new thread and do the glob matching on the other thread.
-  let read_dir_runner = ReadDirRunner::new(environment.clone(), shared_state.clone());
+  let read_dir_runner = ReadDirRunner::new(environment.clone(), shared_state.clone(), build_artifact_dirs);
   tokio::task::spawn_blocking(move || read_dir_runner.run());
 
   // run the glob matching on the current thread (the two threads will communicate with each other)
@@ -56,14 +69,20 @@ const PUSH_DIR_ENTRIES_BATCH_COUNT: usize = 500;
 struct ReadDirRunner<TEnvironment: Environment> {
   environment: TEnvironment,
   shared_state: Arc<SharedState>,
+  build_artifact_dirs: Option<BuildArtifactDirs>,
 }
 
 impl<TEnvironment: Environment> ReadDirRunner<TEnvironment> {
-  pub fn new(environment: TEnvironment, shared_state: Arc<SharedState>) -> Self {
-    Self { environment, shared_state }
+  pub fn new(environment: TEnvironment, shared_state: Arc<SharedState>, build_artifact_dirs: Option<BuildArtifactDirs>) -> Self {
+    Self {
+      environment,
+      shared_state,
+      build_artifact_dirs,
+    }
   }
 
   pub fn run(&self) {
+    let mut is_start_dir = true;
     while let Some(pending_dirs) = self.get_next_pending_dirs() {
       let mut all_entries = Vec::new();
       for current_dir in pending_dirs.into_iter().flatten() {
@@ -73,6 +92,10 @@ impl<TEnvironment: Environment> ReadDirRunner<TEnvironment> {
           .map_err(|err| anyhow!("Error reading dir '{}': {:#}", current_dir.display(), err));
         match info_result {
           Ok(entries) => {
+            // the directory being globbed was provided, so only skip its descendants
+            if !std::mem::take(&mut is_start_dir) && self.is_build_artifact_dir(&current_dir, &entries) {
+              continue;
+            }
             if !entries.is_empty() {
               all_entries.extend(entries);
               // it is much faster to batch these than to hit the lock every time
@@ -93,6 +116,25 @@ impl<TEnvironment: Environment> ReadDirRunner<TEnvironment> {
     }
   }
 
+  fn is_build_artifact_dir(&self, dir_path: &Path, entries: &[DirEntry]) -> bool {
+    let marker = match &self.build_artifact_dirs {
+      Some(build_artifact_dirs) => build_artifact_dirs.get_marker(dir_path, entries),
+      None => None,
+    };
+    match marker {
+      Some(marker) => {
+        log_verbose!(
+          self.environment,
+          "Skipping build artifacts directory {} ({}). Include it explicitly or set \"skipBuildArtifacts\": false to format its files.",
+          dir_path.display(),
+          marker
+        );
+        true
+      }
+      None => false,
+    }
+  }
+
   fn get_next_pending_dirs(&self) -> Option<Vec<Vec<PathBuf>>> {
     let (ref lock, ref cvar) = &self.shared_state.inner;
     let mut state = lock.lock();
@@ -287,6 +329,7 @@ mod test {
         includes: vec![GlobPattern::new("**/*.txt".to_string(), root_dir.clone())],
         excludes: vec![GlobPattern::new("**/ignore".to_string(), root_dir)],
       },
+      &Default::default(),
     )
     .unwrap();
     let mut result = result.into_iter().map(|r| r.to_string_lossy().to_string()).collect::<Vec<_>>();
@@ -295,6 +338,44 @@ mod test {
     assert_eq!(result, expected_matches);
   }
 
+  #[tokio::test]
+  async fn should_skip_build_artifact_dirs() {
+    let environment = TestEnvironmentBuilder::new()
+      .write_file("/src/a.txt", "")
+      .write_file("/target/CACHEDIR.TAG", "Signature: 8a477f597d28d172789f06886806bc55")
+      .write_file("/target/debug/b.txt", "")
+      .write_file("/.venv/lib/c.txt", "")
+      .write_file("/env/pyvenv.cfg", "")
+      .write_file("/env/lib/d.txt", "")
+      .write_file("/generated/CACHEDIR.TAG", "")
+      .write_file("/generated/e.txt", "")
+      .build();
+    let root_dir = environment.canonicalize("/").unwrap();
+    let get_result = |skip_build_artifacts: bool| {
+      let result = glob(
+        &environment,
+        "/",
+        GlobPatterns {
+          includes: vec![
+            GlobPattern::new("**/*.txt".to_string(), root_dir.clone()),
+            GlobPattern::new("generated/*.txt".to_string(), root_dir.clone()),
+          ],
+          excludes: Vec::new(),
+        },
+        &GlobOptions { skip_build_artifacts },
+      )
+      .unwrap();
+      let mut result = result.into_iter().map(|r| r.to_string_lossy().to_string()).collect::<Vec<_>>();
+      result.sort();
+      result
+    };
+    assert_eq!(get_result(true), vec!["/generated/e.txt", "/src/a.txt"]);
+    assert_eq!(
+      get_result(false),
+      vec!["/.venv/lib/c.txt", "/env/lib/d.txt", "/generated/e.txt", "/src/a.txt", "/target/debug/b.txt"]
+    );
+  }
+
   #[tokio::test]
   async fn should_handle_dir_info_erroring() {
     let environment = TestEnvironmentBuilder::new().build();
@@ -307,6 +388,7 @@ mod test {
         includes: vec![GlobPattern::new("**/*.txt".to_string(), root_dir)],
         excludes: Vec::new(),
       },
+      &Default::default(),
     )
     .err()
     .unwrap();
@@ -328,6 +410,7 @@ mod test {
         ],
         excludes: Vec::new(),
       },
+      &Default::default(),
     )
     .unwrap();
 
@@ -354,6 +437,7 @@ mod test {
         ],
         excludes: Vec::new(),
       },
+      &Default::default(),
     )
     .unwrap();
 
@@ -381,6 +465,7 @@ mod test {
         ],
         excludes: Vec::new(),
       },
+      &Default::default(),
     )
     .unwrap();
 
@@ -407,6 +492,7 @@ mod test {
         ],
         excludes: Vec::new(),
       },
+      &Default::default(),
     )
     .unwrap();
 
@@ -2,13 +2,13 @@ use crate::environment::CanonicalizedPathBuf;
 
 use super::is_negated_glob;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GlobPatterns {
   pub includes: Vec<GlobPattern>,
   pub excludes: Vec<GlobPattern>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GlobPattern {
   pub relative_pattern: String,
   pub base_dir: CanonicalizedPathBuf,
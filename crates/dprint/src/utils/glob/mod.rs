@@ -1,9 +1,11 @@
+mod build_artifact_dirs;
 #[allow(clippy::module_inception)]
 mod glob;
 mod glob_matcher;
 mod glob_pattern;
 mod glob_utils;
 
+pub use build_artifact_dirs::*;
 pub use glob::*;
 pub use glob_matcher::*;
 pub use glob_pattern::*;
@@ -1,39 +1,55 @@
+mod batched_file_writer;
 mod cached_downloader;
 mod checksums;
+mod env_var_references;
 mod error_count_logger;
 mod extract_zip;
 mod file_path_utils;
 mod file_text;
+mod fix_commands;
+mod generated_files;
 mod get_bytes_hash;
 mod get_difference;
+mod git_url;
 mod glob;
 mod logging;
 mod path_source;
 mod pretty_print_json_text;
 mod process;
 mod resolve_url_or_file_path;
+mod sort_output;
 mod stdin_reader;
 mod table_text;
 mod terminal;
+mod time_text;
 mod update_checker;
 mod url;
+mod version_range;
 
 pub use self::url::*;
+pub use batched_file_writer::*;
 pub use cached_downloader::*;
 pub use checksums::*;
+pub use env_var_references::*;
 pub use error_count_logger::*;
 pub use extract_zip::*;
 pub use file_path_utils::*;
 pub use file_text::*;
+pub use fix_commands::*;
+pub use generated_files::*;
 pub use get_bytes_hash::*;
 pub use get_difference::*;
+pub use git_url::*;
 pub use glob::*;
 pub use logging::*;
 pub use path_source::*;
 pub use pretty_print_json_text::*;
 pub use process::*;
 pub use resolve_url_or_file_path::*;
+pub use sort_output::*;
 pub use stdin_reader::*;
 pub use table_text::*;
 pub use terminal::*;
+pub use time_text::*;
 pub use update_checker::*;
+pub use version_range::*;
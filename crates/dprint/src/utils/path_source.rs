@@ -2,6 +2,7 @@ use std::fmt;
 
 use url::Url;
 
+use super::GitUrl;
 use crate::environment::CanonicalizedPathBuf;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -41,6 +42,8 @@ impl PathSource {
           PathSource::new_local(local.path.clone())
         }
       }
+      // the git url is necessary to resolve relative paths in the same repository
+      PathSource::Remote(remote) if GitUrl::is_git_url(&remote.url) => PathSource::new_remote(remote.url.clone()),
       PathSource::Remote(remote) => {
         let mut parent_url = remote.url.join("./").expect("Expected to be able to go back a directory in the url.");
         parent_url.set_query(None);
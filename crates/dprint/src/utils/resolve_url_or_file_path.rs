@@ -1,12 +1,34 @@
 use anyhow::bail;
 use anyhow::Result;
+use crossterm::style::Stylize;
+use std::path::Path;
+use std::time::SystemTime;
 use url::Url;
 
+use super::get_age_text;
 use super::get_bytes_hash;
+use super::resolve_git_url;
+use super::GitUrl;
 use super::PathSource;
 use crate::environment::CanonicalizedPathBuf;
 use crate::environment::Environment;
 
+/// Cached remote files older than this are downloaded again.
+const REMOTE_FILE_MAX_AGE_SECS: u64 = 7 * 86_400;
+/// How old a temporary download file without a process id must be before it's removed.
+const INTERRUPTED_DOWNLOAD_MIN_AGE_SECS: u64 = 60 * 60;
+
+/// What to do when a cached remote configuration file is outdated,
+/// but it can't be downloaded again (`remoteConfigFallback`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RemoteConfigFallback {
+  /// Fail the run.
+  Error,
+  /// Warn and use the cached copy.
+  #[default]
+  Warn,
+}
+
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct ResolvedPath {
   pub file_path: CanonicalizedPathBuf,
@@ -43,15 +65,25 @@ impl ResolvedPath {
 }
 
 pub fn resolve_url_or_file_path<TEnvironment: Environment>(url_or_file_path: &str, base: &PathSource, environment: &TEnvironment) -> Result<ResolvedPath> {
+  resolve_url_or_file_path_with_fallback(url_or_file_path, base, RemoteConfigFallback::default(), environment)
+}
+
+pub fn resolve_url_or_file_path_with_fallback<TEnvironment: Environment>(
+  url_or_file_path: &str,
+  base: &PathSource,
+  fallback: RemoteConfigFallback,
+  environment: &TEnvironment,
+) -> Result<ResolvedPath> {
   let path_source = resolve_url_or_file_path_to_path_source(url_or_file_path, base, environment)?;
 
   match path_source {
-    PathSource::Remote(path_source) => resolve_url(&path_source.url, environment),
+    PathSource::Remote(path_source) if GitUrl::is_git_url(&path_source.url) => resolve_git_url(&path_source.url, environment),
+    PathSource::Remote(path_source) => resolve_url(&path_source.url, fallback, environment),
     PathSource::Local(path_source) => Ok(ResolvedPath::local(path_source.path)),
   }
 }
 
-fn resolve_url<TEnvironment: Environment>(url: &Url, environment: &TEnvironment) -> Result<ResolvedPath> {
+fn resolve_url<TEnvironment: Environment>(url: &Url, fallback: RemoteConfigFallback, environment: &TEnvironment) -> Result<ResolvedPath> {
   let mut is_first_download = false;
 
   let cache_dir = environment.get_cache_dir().join_panic_relative("remote");
@@ -62,16 +94,94 @@ fn resolve_url<TEnvironment: Environment>(url: &Url, environment: &TEnvironment)
 
   if !environment.path_exists(&file_path) {
     is_first_download = true;
-    let file_bytes = environment.download_file_err_404(url.as_str())?;
-    let temp_path = file_path.as_ref().with_extension(".tmp");
-    // atomic save
-    environment.write_file_bytes(&temp_path, &file_bytes)?;
-    environment.rename(&temp_path, &file_path)?;
+    download_to_file(url, file_path.as_ref(), environment)?;
+  } else if let Some(age_secs) = get_outdated_age_secs(file_path.as_ref(), environment) {
+    // keep using the cached copy when the url is temporarily unreachable
+    if let Err(err) = download_to_file(url, file_path.as_ref(), environment) {
+      match fallback {
+        RemoteConfigFallback::Error => bail!(
+          "{:#}\n  The cached copy from {} ago was not used because 'remoteConfigFallback' is \"error\".",
+          err,
+          get_age_text(age_secs)
+        ),
+        RemoteConfigFallback::Warn => environment.log_stderr(&format!(
+          "{} Could not download {}, so its cached copy from {} ago is being used.\n  Error: {:#}",
+          "Warning:".bold().yellow(),
+          url,
+          get_age_text(age_secs),
+          err
+        )),
+      }
+    }
   }
 
   Ok(ResolvedPath::remote(file_path, url.clone(), is_first_download))
 }
 
+fn download_to_file(url: &Url, file_path: &Path, environment: &impl Environment) -> Result<()> {
+  let file_bytes = environment.download_file_err_404(url.as_str())?;
+  // include the process id so that it's known if the write was interrupted
+  let temp_path = file_path.with_extension(format!("{}.tmp", std::process::id()));
+  // atomic save
+  environment.write_file_bytes(&temp_path, &file_bytes)?;
+  environment.rename(&temp_path, file_path)?;
+  Ok(())
+}
+
+/// Gets the age of the cached file when it should be downloaded again.
+fn get_outdated_age_secs(file_path: &Path, environment: &impl Environment) -> Option<u64> {
+  let age_secs = get_file_age_secs(file_path, environment)?;
+  if age_secs >= REMOTE_FILE_MAX_AGE_SECS {
+    Some(age_secs)
+  } else {
+    None
+  }
+}
+
+fn get_file_age_secs(file_path: &Path, environment: &impl Environment) -> Option<u64> {
+  let modified_time = environment.file_modified_time(file_path).ok()?;
+  let modified_secs = modified_time.duration_since(SystemTime::UNIX_EPOCH).ok()?.as_secs();
+  Some(environment.get_time_secs().saturating_sub(modified_secs))
+}
+
+/// Removes the temporary files of remote file downloads that were interrupted.
+///
+/// Returns the number of removed files.
+pub fn remove_interrupted_download_files(environment: &impl Environment) -> usize {
+  let entries = match environment.dir_info(environment.get_cache_dir().join("remote")) {
+    Ok(entries) => entries,
+    Err(_) => return 0,
+  };
+  let mut removed_count = 0;
+  for entry in entries {
+    let file_name = match entry.path.file_name().and_then(|name| name.to_str()) {
+      Some(file_name) => file_name,
+      None => continue,
+    };
+    let file_stem = match file_name.strip_suffix(".tmp") {
+      Some(file_stem) => file_stem,
+      None => continue,
+    };
+    let writing_process_id = file_stem.rsplit('.').next().and_then(|text| text.parse::<u32>().ok());
+    let is_interrupted = match writing_process_id {
+      Some(process_id) => !environment.is_process_active(process_id),
+      // files without a process id are from an older version of the CLI,
+      // which may still be writing it when it was modified recently
+      None => get_file_age_secs(&entry.path, environment)
+        .map(|age_secs| age_secs >= INTERRUPTED_DOWNLOAD_MIN_AGE_SECS)
+        .unwrap_or(false),
+    };
+    if !is_interrupted {
+      continue;
+    }
+    log_verbose!(environment, "Removing interrupted download: {}", entry.path.display());
+    if environment.remove_file(&entry.path).is_ok() {
+      removed_count += 1;
+    }
+  }
+  removed_count
+}
+
 pub fn fetch_file_or_url_bytes(url_or_file_path: &PathSource, environment: &impl Environment) -> Result<Vec<u8>> {
   match url_or_file_path {
     PathSource::Remote(path_source) => environment.download_file_err_404(path_source.url.as_str()),
@@ -100,6 +210,10 @@ pub fn resolve_url_or_file_path_to_path_source(url_or_file_path: &str, base: &Pa
   }
 
   Ok(match base {
+    PathSource::Remote(remote_base) if GitUrl::is_git_url(&remote_base.url) => {
+      // keep relative paths within the same repository and commit
+      PathSource::new_remote(GitUrl::parse(&remote_base.url)?.join(url_or_file_path)?)
+    }
     PathSource::Remote(remote_base) => {
       let url = remote_base.url.join(url_or_file_path)?;
       PathSource::new_remote(url)
@@ -160,6 +274,80 @@ mod tests {
     assert_eq!(result.is_first_download, false);
   }
 
+  #[test]
+  fn should_download_outdated_cached_url_again() {
+    let environment = TestEnvironment::new();
+    environment.add_remote_file("https://dprint.dev/test.json", "t".as_bytes());
+    let base = PathSource::new_local(CanonicalizedPathBuf::new_for_testing("/"));
+    let url = "https://dprint.dev/test.json";
+    resolve_url_or_file_path(url, &base, &environment).unwrap();
+
+    environment.set_time_secs(123456 + REMOTE_FILE_MAX_AGE_SECS);
+    environment.add_remote_file("https://dprint.dev/test.json", "new".as_bytes());
+    let result = resolve_url_or_file_path(url, &base, &environment).unwrap();
+    assert_eq!(result.is_first_download, false);
+    assert_eq!(environment.read_file(&result.file_path).unwrap(), "new");
+    assert_eq!(environment.take_stderr_messages().len(), 0);
+  }
+
+  #[test]
+  fn should_use_outdated_cached_url_when_it_cannot_be_downloaded() {
+    let environment = TestEnvironment::new();
+    environment.add_remote_file("https://dprint.dev/test.json", "t".as_bytes());
+    let base = PathSource::new_local(CanonicalizedPathBuf::new_for_testing("/"));
+    let url = "https://dprint.dev/test.json";
+    resolve_url_or_file_path(url, &base, &environment).unwrap();
+
+    environment.set_time_secs(10 * 86_400);
+    environment.add_remote_file_error(url, "Connection refused.");
+    let result = resolve_url_or_file_path(url, &base, &environment).unwrap();
+    assert_eq!(environment.read_file(result.file_path).unwrap(), "t");
+    assert_eq!(
+      environment.take_stderr_messages(),
+      vec![format!(
+        "{} Could not download https://dprint.dev/test.json, so its cached copy from 9 days ago is being used.\n  Error: Connection refused.",
+        "Warning:".bold().yellow(),
+      )]
+    );
+
+    let err = resolve_url_or_file_path_with_fallback(url, &base, RemoteConfigFallback::Error, &environment)
+      .err()
+      .unwrap();
+    assert_eq!(
+      err.to_string(),
+      "Connection refused.\n  The cached copy from 9 days ago was not used because 'remoteConfigFallback' is \"error\"."
+    );
+  }
+
+  #[test]
+  fn should_remove_interrupted_download_files() {
+    let environment = TestEnvironment::new();
+    assert_eq!(remove_interrupted_download_files(&environment), 0);
+    environment.write_file("/cache/remote/1", "complete").unwrap();
+    environment.write_file("/cache/remote/2..tmp", "").unwrap();
+    environment.write_file("/cache/remote/3.50.tmp", "").unwrap();
+    environment.write_file("/cache/remote/4.51.tmp", "").unwrap();
+    environment.write_file(format!("/cache/remote/5.{}.tmp", std::process::id()), "").unwrap();
+    // being written by another process
+    environment.set_running_process(51, "/dprint");
+
+    assert_eq!(remove_interrupted_download_files(&environment), 2);
+    assert!(environment.path_exists("/cache/remote/1"));
+    assert!(!environment.path_exists("/cache/remote/2..tmp"));
+    assert!(!environment.path_exists("/cache/remote/3.50.tmp"));
+    assert!(environment.path_exists("/cache/remote/4.51.tmp"));
+    assert!(environment.path_exists(format!("/cache/remote/5.{}.tmp", std::process::id())));
+
+    // files without a process id are only removed once they're old
+    environment.write_file("/cache/remote/6..tmp", "").unwrap();
+    environment.set_time_secs(100);
+    assert_eq!(remove_interrupted_download_files(&environment), 0);
+    assert!(environment.path_exists("/cache/remote/6..tmp"));
+    environment.set_time_secs(100 + 60 * 60);
+    assert_eq!(remove_interrupted_download_files(&environment), 1);
+    assert!(!environment.path_exists("/cache/remote/6..tmp"));
+  }
+
   #[test]
   fn should_resolve_a_relative_path_to_base_url() {
     let environment = TestEnvironment::new();
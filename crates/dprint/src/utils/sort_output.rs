@@ -0,0 +1,53 @@
+use std::path::Path;
+
+/// Makes the human readable output stable so it diffs cleanly
+/// across runs and platforms (`--sort-output`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOutputMode {
+  /// Displays paths with forward slashes.
+  ForwardSlashes,
+  /// Displays paths with the platform's separators.
+  NativeSeparators,
+}
+
+/// Gets the text to display for a path.
+pub fn display_path(path: &Path, sort_output: Option<SortOutputMode>) -> String {
+  let text = path.display().to_string();
+  match sort_output {
+    Some(SortOutputMode::ForwardSlashes) => text.replace('\\', "/"),
+    Some(SortOutputMode::NativeSeparators) | None => text,
+  }
+}
+
+/// Sorts the lines of a listing when sorting the output.
+///
+/// This compares the text byte by byte so the order doesn't depend on the locale.
+pub fn sort_listing<T: AsRef<str>>(items: &mut [T], sort_output: Option<SortOutputMode>) {
+  if sort_output.is_some() {
+    items.sort_by(|a, b| a.as_ref().as_bytes().cmp(b.as_ref().as_bytes()));
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn should_display_path() {
+    let path = Path::new("dir\\sub/file.txt");
+    assert_eq!(display_path(path, None), "dir\\sub/file.txt");
+    assert_eq!(display_path(path, Some(SortOutputMode::NativeSeparators)), "dir\\sub/file.txt");
+    assert_eq!(display_path(path, Some(SortOutputMode::ForwardSlashes)), "dir/sub/file.txt");
+  }
+
+  #[test]
+  fn should_sort_listing() {
+    let mut items = vec!["b", "a", "B", "ä", "A"];
+    sort_listing(&mut items, None);
+    assert_eq!(items, vec!["b", "a", "B", "ä", "A"]);
+    sort_listing(&mut items, Some(SortOutputMode::ForwardSlashes));
+    assert_eq!(items, vec!["A", "B", "a", "b", "ä"]);
+  }
+}
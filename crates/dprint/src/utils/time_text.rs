@@ -0,0 +1,58 @@
+/// Gets the text of the time in UTC (ex. `2023-02-14 09:30:05 UTC`) from
+/// the seconds since the unix epoch.
+pub fn get_utc_time_text(unix_secs: u64) -> String {
+  let days = (unix_secs / 86_400) as i64;
+  let secs_of_day = unix_secs % 86_400;
+  // civil from days algorithm (http://howardhinnant.github.io/date_algorithms.html)
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let day_of_era = z.rem_euclid(146_097);
+  let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_index = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+  let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+  let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+  format!(
+    "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+    year,
+    month,
+    day,
+    secs_of_day / 3_600,
+    secs_of_day % 3_600 / 60,
+    secs_of_day % 60
+  )
+}
+
+/// Gets the text of a duration for display (ex. `3 days`).
+pub fn get_age_text(secs: u64) -> String {
+  let (count, unit) = if secs >= 86_400 {
+    (secs / 86_400, "day")
+  } else if secs >= 3_600 {
+    (secs / 3_600, "hour")
+  } else {
+    (secs / 60, "minute")
+  };
+  format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn should_get_utc_time_text() {
+    assert_eq!(get_utc_time_text(0), "1970-01-01 00:00:00 UTC");
+    assert_eq!(get_utc_time_text(951_782_400), "2000-02-29 00:00:00 UTC");
+    assert_eq!(get_utc_time_text(1_676_367_005), "2023-02-14 09:30:05 UTC");
+  }
+
+  #[test]
+  fn should_get_age_text() {
+    assert_eq!(get_age_text(30), "0 minutes");
+    assert_eq!(get_age_text(60), "1 minute");
+    assert_eq!(get_age_text(7_200), "2 hours");
+    assert_eq!(get_age_text(86_400), "1 day");
+    assert_eq!(get_age_text(864_000), "10 days");
+  }
+}
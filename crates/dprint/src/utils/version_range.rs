@@ -0,0 +1,214 @@
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Result;
+use std::fmt;
+
+/// A release version. Pre-release versions are not supported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+  pub major: u64,
+  pub minor: u64,
+  pub patch: u64,
+}
+
+impl Version {
+  pub fn new(major: u64, minor: u64, patch: u64) -> Version {
+    Version { major, minor, patch }
+  }
+
+  /// Parses a version like `1.2.3`. Returns `None` for pre-releases.
+  pub fn parse(text: &str) -> Option<Version> {
+    let text = text.trim().trim_start_matches('v');
+    let text = text.split('+').next().unwrap(); // ignore build metadata
+    let mut parts = text.split('.');
+    let version = Version {
+      major: parts.next()?.parse().ok()?,
+      minor: parts.next()?.parse().ok()?,
+      patch: parts.next()?.parse().ok()?,
+    };
+    if parts.next().is_some() {
+      return None;
+    }
+    Some(version)
+  }
+}
+
+impl fmt::Display for Version {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+  Gte,
+  Lt,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Comparator {
+  op: Op,
+  version: Version,
+}
+
+impl Comparator {
+  fn matches(&self, version: &Version) -> bool {
+    match self.op {
+      Op::Gte => version >= &self.version,
+      Op::Lt => version < &self.version,
+    }
+  }
+}
+
+/// A subset of npm style semver ranges (ex. `^0.91`, `~1.2.3`, `>=1.2 <2`, `1.x`, `*`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionRange {
+  comparators: Vec<Comparator>,
+}
+
+impl VersionRange {
+  pub fn parse(text: &str) -> Result<VersionRange> {
+    let mut comparators = Vec::new();
+    let parts = text.split_whitespace().collect::<Vec<_>>();
+    if parts.is_empty() {
+      bail!("Expected a version range.");
+    }
+    for part in parts {
+      parse_comparators(part, &mut comparators)?;
+    }
+    Ok(VersionRange { comparators })
+  }
+
+  pub fn matches(&self, version: &Version) -> bool {
+    self.comparators.iter().all(|c| c.matches(version))
+  }
+}
+
+fn parse_comparators(text: &str, comparators: &mut Vec<Comparator>) -> Result<()> {
+  let (op_text, version_text) = match text.find(|c: char| c.is_ascii_digit() || c == '*' || c == 'x' || c == 'X') {
+    Some(index) => text.split_at(index),
+    None => bail!("Invalid version range '{}'.", text),
+  };
+  let (major, minor, patch) = parse_partial_version(version_text).ok_or_else(|| anyhow!("Invalid version range '{}'.", text))?;
+  let major = match major {
+    Some(major) => major,
+    None => {
+      if op_text.is_empty() || op_text == "=" {
+        return Ok(()); // matches everything
+      }
+      bail!("Invalid version range '{}'.", text);
+    }
+  };
+  let lower = Version::new(major, minor.unwrap_or(0), patch.unwrap_or(0));
+  // the exclusive upper bound for the partial version (ex. `1.2` is `<1.3.0`)
+  let partial_upper = match (minor, patch) {
+    (None, _) => Version::new(major + 1, 0, 0),
+    (Some(minor), None) => Version::new(major, minor + 1, 0),
+    (Some(minor), Some(patch)) => Version::new(major, minor, patch + 1),
+  };
+  let mut push = |op: Op, version: Version| comparators.push(Comparator { op, version });
+
+  match op_text {
+    "^" => {
+      let upper = match (minor, patch) {
+        (Some(0), Some(patch)) if major == 0 => Version::new(0, 0, patch + 1),
+        (Some(minor), _) if major == 0 => Version::new(0, minor + 1, 0),
+        _ => Version::new(major + 1, 0, 0),
+      };
+      push(Op::Gte, lower);
+      push(Op::Lt, upper);
+    }
+    "~" => {
+      let upper = match minor {
+        Some(minor) => Version::new(major, minor + 1, 0),
+        None => Version::new(major + 1, 0, 0),
+      };
+      push(Op::Gte, lower);
+      push(Op::Lt, upper);
+    }
+    "" | "=" => {
+      push(Op::Gte, lower);
+      push(Op::Lt, partial_upper);
+    }
+    ">=" => push(Op::Gte, lower),
+    ">" => push(Op::Gte, partial_upper),
+    "<" => push(Op::Lt, lower),
+    "<=" => push(Op::Lt, partial_upper),
+    _ => bail!("Invalid version range '{}'.", text),
+  }
+
+  Ok(())
+}
+
+fn parse_partial_version(text: &str) -> Option<(Option<u64>, Option<u64>, Option<u64>)> {
+  let mut parts = text.split('.');
+  let mut next_part = || -> Option<Option<u64>> {
+    match parts.next() {
+      None | Some("*") | Some("x") | Some("X") => Some(None),
+      Some(part) => part.parse::<u64>().ok().map(Some),
+    }
+  };
+  let major = next_part()?;
+  let minor = next_part()?;
+  let patch = next_part()?;
+  // nothing is allowed after a wildcard
+  if (major.is_none() && minor.is_some()) || (minor.is_none() && patch.is_some()) {
+    return None;
+  }
+  if parts.next().is_some() {
+    return None;
+  }
+  Some((major, minor, patch))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn should_parse_version() {
+    assert_eq!(Version::parse("1.2.3"), Some(Version::new(1, 2, 3)));
+    assert_eq!(Version::parse("v0.91.0"), Some(Version::new(0, 91, 0)));
+    assert_eq!(Version::parse("1.2.3+build"), Some(Version::new(1, 2, 3)));
+    assert_eq!(Version::parse("1.2.3-beta.1"), None);
+    assert_eq!(Version::parse("1.2"), None);
+  }
+
+  #[test]
+  fn should_match_ranges() {
+    assert_matches("^0.91", &["0.91.0", "0.91.5"], &["0.90.9", "0.92.0", "1.0.0"]);
+    assert_matches("^1.2.3", &["1.2.3", "1.9.0"], &["1.2.2", "2.0.0"]);
+    assert_matches("^0.0.3", &["0.0.3"], &["0.0.4", "0.1.0"]);
+    assert_matches("~1.2.3", &["1.2.3", "1.2.9"], &["1.3.0", "1.2.2"]);
+    assert_matches("~1", &["1.0.0", "1.9.9"], &["2.0.0"]);
+    assert_matches("1.2.3", &["1.2.3"], &["1.2.4", "1.2.2"]);
+    assert_matches("1.x", &["1.0.0", "1.5.2"], &["2.0.0", "0.9.0"]);
+    assert_matches("*", &["0.0.1", "10.0.0"], &[]);
+    assert_matches(">=1.2 <2", &["1.2.0", "1.99.0"], &["1.1.9", "2.0.0"]);
+    assert_matches(">1.2", &["1.3.0"], &["1.2.9"]);
+    assert_matches("<=1.2", &["1.2.9", "0.1.0"], &["1.3.0"]);
+  }
+
+  #[test]
+  fn should_error_for_invalid_ranges() {
+    assert_eq!(VersionRange::parse("latest").err().unwrap().to_string(), "Invalid version range 'latest'.");
+    assert_eq!(VersionRange::parse("!1.2").err().unwrap().to_string(), "Invalid version range '!1.2'.");
+    assert_eq!(VersionRange::parse("1.x.3").err().unwrap().to_string(), "Invalid version range '1.x.3'.");
+    assert_eq!(VersionRange::parse("").err().unwrap().to_string(), "Expected a version range.");
+  }
+
+  fn assert_matches(range: &str, matches: &[&str], not_matches: &[&str]) {
+    let range_value = VersionRange::parse(range).unwrap();
+    for version in matches {
+      assert!(range_value.matches(&Version::parse(version).unwrap()), "{} should match {}", range, version);
+    }
+    for version in not_matches {
+      assert!(
+        !range_value.matches(&Version::parse(version).unwrap()),
+        "{} should not match {}",
+        range,
+        version
+      );
+    }
+  }
+}
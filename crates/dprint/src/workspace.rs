@@ -0,0 +1,486 @@
+use anyhow::bail;
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::environment::CanonicalizedPathBuf;
+use crate::environment::DirEntryKind;
+use crate::environment::Environment;
+
+/// A package found in a package.json or Cargo workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspacePackage {
+  pub name: String,
+  pub dir_path: PathBuf,
+  /// Names of the packages this package depends on.
+  pub dependencies: Vec<String>,
+}
+
+/// The packages of the package.json and/or Cargo workspaces found
+/// in a directory along with the dependencies between them.
+pub struct WorkspaceGraph {
+  packages: Vec<WorkspacePackage>,
+}
+
+impl WorkspaceGraph {
+  pub fn discover(root_dir: &CanonicalizedPathBuf, environment: &impl Environment) -> Result<Self> {
+    let mut packages = Vec::new();
+
+    let package_json_path = root_dir.join("package.json");
+    if environment.path_exists(&package_json_path) {
+      let root_package = read_npm_package(&package_json_path, environment)?;
+      for dir_path in expand_member_patterns(root_dir.as_ref(), &root_package.workspaces, &[], environment)? {
+        let package_json_path = dir_path.join("package.json");
+        if environment.path_exists(&package_json_path) {
+          let package = read_npm_package(&package_json_path, environment)?;
+          if let Some(name) = package.name {
+            packages.push(WorkspacePackage {
+              name,
+              dir_path,
+              dependencies: package.dependencies,
+            });
+          }
+        }
+      }
+    }
+
+    let cargo_toml_path = root_dir.join("Cargo.toml");
+    if environment.path_exists(&cargo_toml_path) {
+      let root_manifest = read_cargo_manifest(&cargo_toml_path, environment)?;
+      for dir_path in expand_member_patterns(
+        root_dir.as_ref(),
+        &root_manifest.workspace_members,
+        &root_manifest.workspace_exclude,
+        environment,
+      )? {
+        let cargo_toml_path = dir_path.join("Cargo.toml");
+        if environment.path_exists(&cargo_toml_path) {
+          let manifest = read_cargo_manifest(&cargo_toml_path, environment)?;
+          if let Some(name) = manifest.package_name {
+            packages.push(WorkspacePackage {
+              name,
+              dir_path,
+              dependencies: manifest.dependencies,
+            });
+          }
+        }
+      }
+      // a virtual manifest won't have a package
+      if let Some(name) = root_manifest.package_name {
+        if !packages.iter().any(|p| p.dir_path == root_dir.clone().into_path_buf()) {
+          packages.push(WorkspacePackage {
+            name,
+            dir_path: root_dir.clone().into_path_buf(),
+            dependencies: root_manifest.dependencies,
+          });
+        }
+      }
+    }
+
+    Ok(WorkspaceGraph { packages })
+  }
+
+  pub fn packages(&self) -> &[WorkspacePackage] {
+    &self.packages
+  }
+
+  /// Gets the package with the deepest directory containing the file path.
+  pub fn get_owning_package(&self, file_path: &Path) -> Option<&WorkspacePackage> {
+    self
+      .packages
+      .iter()
+      .filter(|p| file_path.starts_with(&p.dir_path))
+      .max_by_key(|p| p.dir_path.components().count())
+  }
+
+  /// Gets the packages that contain the changed files along with all
+  /// the packages that directly or transitively depend on them.
+  pub fn get_affected_packages(&self, changed_files: &[PathBuf]) -> Vec<&WorkspacePackage> {
+    let mut affected_names = HashSet::new();
+    let mut pending = Vec::new();
+    for file_path in changed_files {
+      if let Some(package) = self.get_owning_package(file_path) {
+        if affected_names.insert(package.name.as_str()) {
+          pending.push(package.name.as_str());
+        }
+      }
+    }
+
+    while let Some(name) = pending.pop() {
+      for package in self.packages.iter() {
+        if package.dependencies.iter().any(|d| d == name) && affected_names.insert(package.name.as_str()) {
+          pending.push(package.name.as_str());
+        }
+      }
+    }
+
+    self.packages.iter().filter(|p| affected_names.contains(p.name.as_str())).collect()
+  }
+}
+
+struct NpmPackage {
+  name: Option<String>,
+  workspaces: Vec<String>,
+  dependencies: Vec<String>,
+}
+
+fn read_npm_package(file_path: &Path, environment: &impl Environment) -> Result<NpmPackage> {
+  let text = environment.read_file(file_path)?;
+  match parse_npm_package(&text) {
+    Ok(package) => Ok(package),
+    Err(err) => bail!("Error reading {}. {:#}", file_path.display(), err),
+  }
+}
+
+fn parse_npm_package(text: &str) -> Result<NpmPackage> {
+  let value: Value = serde_json::from_str(text)?;
+  let name = value.get("name").and_then(|v| v.as_str()).map(ToString::to_string);
+  let workspaces = match value.get("workspaces") {
+    Some(Value::Array(items)) => items.iter().filter_map(|v| v.as_str()).map(ToString::to_string).collect(),
+    Some(Value::Object(obj)) => match obj.get("packages") {
+      Some(Value::Array(items)) => items.iter().filter_map(|v| v.as_str()).map(ToString::to_string).collect(),
+      _ => Vec::new(),
+    },
+    _ => Vec::new(),
+  };
+  let mut dependencies = Vec::new();
+  for key in ["dependencies", "devDependencies", "peerDependencies", "optionalDependencies"] {
+    if let Some(Value::Object(obj)) = value.get(key) {
+      dependencies.extend(obj.keys().cloned());
+    }
+  }
+  Ok(NpmPackage {
+    name,
+    workspaces,
+    dependencies,
+  })
+}
+
+struct CargoManifest {
+  package_name: Option<String>,
+  workspace_members: Vec<String>,
+  workspace_exclude: Vec<String>,
+  dependencies: Vec<String>,
+}
+
+fn read_cargo_manifest(file_path: &Path, environment: &impl Environment) -> Result<CargoManifest> {
+  let text = environment.read_file(file_path)?;
+  Ok(parse_cargo_manifest(&text))
+}
+
+/// A lightweight line based parse of the parts of a Cargo.toml file needed
+/// to build the graph. This doesn't attempt to understand all of toml.
+fn parse_cargo_manifest(text: &str) -> CargoManifest {
+  let mut manifest = CargoManifest {
+    package_name: None,
+    workspace_members: Vec::new(),
+    workspace_exclude: Vec::new(),
+    dependencies: Vec::new(),
+  };
+  let mut table = String::new();
+  let mut open_array: Option<(String, String)> = None;
+
+  for line in text.lines() {
+    let line = strip_toml_comment(line).trim();
+    if let Some((key, mut array_text)) = open_array.take() {
+      array_text.push_str(line);
+      if line.contains(']') {
+        add_workspace_array(&mut manifest, &key, &array_text);
+      } else {
+        open_array = Some((key, array_text));
+      }
+      continue;
+    }
+    if line.starts_with('[') {
+      table = line.trim_start_matches('[').trim_end_matches(']').trim().to_string();
+      if let Some(name) = table
+        .strip_prefix("dependencies.")
+        .or_else(|| table.strip_prefix("dev-dependencies."))
+        .or_else(|| table.strip_prefix("build-dependencies."))
+      {
+        manifest.dependencies.push(unquote(name).to_string());
+      }
+      continue;
+    }
+    let (key, value) = match line.split_once('=') {
+      Some((key, value)) => (key.trim(), value.trim()),
+      None => continue,
+    };
+    if table == "package" && key == "name" {
+      manifest.package_name = Some(unquote(value).to_string());
+    } else if table == "workspace" && (key == "members" || key == "exclude") {
+      if value.contains(']') {
+        add_workspace_array(&mut manifest, key, value);
+      } else {
+        open_array = Some((key.to_string(), value.to_string()));
+      }
+    } else if is_dependencies_table(&table) && table != "workspace.dependencies" {
+      // ex. `name = "1"`, `name = { path = "../name" }`, or `name.workspace = true`
+      let name = key.split('.').next().unwrap();
+      manifest.dependencies.push(unquote(name).to_string());
+    }
+  }
+
+  manifest
+}
+
+fn add_workspace_array(manifest: &mut CargoManifest, key: &str, array_text: &str) {
+  let items = array_text
+    .trim_start_matches('[')
+    .split(|c| c == ',' || c == ']')
+    .map(|item| unquote(item.trim()))
+    .filter(|item| !item.is_empty())
+    .map(ToString::to_string);
+  if key == "members" {
+    manifest.workspace_members.extend(items);
+  } else {
+    manifest.workspace_exclude.extend(items);
+  }
+}
+
+fn is_dependencies_table(table: &str) -> bool {
+  table == "dependencies"
+    || table == "dev-dependencies"
+    || table == "build-dependencies"
+    || table.ends_with(".dependencies")
+    || table.ends_with(".dev-dependencies")
+    || table.ends_with(".build-dependencies")
+}
+
+fn strip_toml_comment(line: &str) -> &str {
+  // good enough since package names and member paths won't contain a #
+  match line.find('#') {
+    Some(index) => &line[..index],
+    None => line,
+  }
+}
+
+fn unquote(text: &str) -> &str {
+  text.trim_matches(|c| c == '"' || c == '\'')
+}
+
+/// Expands workspace member patterns like `packages/*` or `packages/**` to directories.
+/// Only `*` wildcards within a path segment and `**` segments are supported.
+fn expand_member_patterns(root_dir: &Path, includes: &[String], excludes: &[String], environment: &impl Environment) -> Result<Vec<PathBuf>> {
+  let mut excluded_dirs = HashSet::new();
+  let mut negated_patterns = Vec::new();
+  for pattern in excludes {
+    negated_patterns.push(pattern.as_str());
+  }
+  for pattern in includes.iter().filter_map(|p| p.strip_prefix('!')) {
+    negated_patterns.push(pattern);
+  }
+  for pattern in negated_patterns {
+    excluded_dirs.extend(expand_member_pattern(root_dir, pattern, environment)?);
+  }
+
+  let mut result = Vec::new();
+  for pattern in includes.iter().filter(|p| !p.starts_with('!')) {
+    for dir_path in expand_member_pattern(root_dir, pattern, environment)? {
+      if !excluded_dirs.contains(&dir_path) && !result.contains(&dir_path) {
+        result.push(dir_path);
+      }
+    }
+  }
+  Ok(result)
+}
+
+fn expand_member_pattern(root_dir: &Path, pattern: &str, environment: &impl Environment) -> Result<Vec<PathBuf>> {
+  let mut dirs = vec![root_dir.to_path_buf()];
+  for segment in pattern.split('/') {
+    if segment.is_empty() || segment == "." {
+      continue;
+    }
+    let mut next_dirs = Vec::new();
+    if segment == "**" {
+      for dir in dirs {
+        add_descendant_dirs(&dir, environment, &mut next_dirs);
+        next_dirs.push(dir);
+      }
+      dirs = next_dirs;
+      continue;
+    }
+    for dir in dirs {
+      // use the directory entries rather than checking if the path exists
+      // in order to only match directories
+      for entry in environment.dir_info(&dir).unwrap_or_default() {
+        if matches!(entry.kind, DirEntryKind::Directory) {
+          let is_match = entry
+            .path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| matches_wildcard(segment, n))
+            .unwrap_or(false);
+          if is_match {
+            next_dirs.push(entry.path);
+          }
+        }
+      }
+    }
+    dirs = next_dirs;
+  }
+  dirs.sort();
+  dirs.dedup();
+  Ok(dirs)
+}
+
+/// Adds the directories below a directory for a `**` segment, skipping
+/// dependency and hidden directories since they never contain members.
+fn add_descendant_dirs(dir: &Path, environment: &impl Environment, dirs: &mut Vec<PathBuf>) {
+  for entry in environment.dir_info(dir).unwrap_or_default() {
+    if matches!(entry.kind, DirEntryKind::Directory) {
+      let is_skipped = entry
+        .path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n == "node_modules" || n == "target" || n.starts_with('.'))
+        .unwrap_or(false);
+      if !is_skipped {
+        add_descendant_dirs(&entry.path, environment, dirs);
+        dirs.push(entry.path);
+      }
+    }
+  }
+}
+
+fn matches_wildcard(pattern: &str, text: &str) -> bool {
+  match pattern.split_once('*') {
+    Some((prefix, rest)) => {
+      if !text.starts_with(prefix) {
+        return false;
+      }
+      let text = &text[prefix.len()..];
+      if rest.is_empty() {
+        return true;
+      }
+      (0..=text.len())
+        .filter(|i| text.is_char_boundary(*i))
+        .any(|i| matches_wildcard(rest, &text[i..]))
+    }
+    None => pattern == text,
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use pretty_assertions::assert_eq;
+  use std::path::PathBuf;
+
+  use crate::environment::Environment;
+  use crate::environment::TestEnvironment;
+
+  use super::*;
+
+  #[test]
+  fn should_discover_npm_workspace() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file("/package.json", r#"{ "workspaces": ["packages/*", "!packages/ignored"] }"#)
+      .unwrap();
+    environment
+      .write_file("/packages/a/package.json", r#"{ "name": "a", "dependencies": { "b": "1.0.0" } }"#)
+      .unwrap();
+    environment.write_file("/packages/b/package.json", r#"{ "name": "b" }"#).unwrap();
+    environment.write_file("/packages/c/package.json", r#"{ "name": "c" }"#).unwrap();
+    environment.write_file("/packages/ignored/package.json", r#"{ "name": "ignored" }"#).unwrap();
+
+    let graph = WorkspaceGraph::discover(&CanonicalizedPathBuf::new_for_testing("/"), &environment).unwrap();
+    let names = graph.packages().iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["a", "b", "c"]);
+
+    let affected = graph.get_affected_packages(&[PathBuf::from("/packages/b/src/mod.ts")]);
+    let names = affected.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["a", "b"]);
+  }
+
+  #[test]
+  fn should_discover_cargo_workspace() {
+    let environment = TestEnvironment::new();
+    environment
+      .write_file(
+        "/Cargo.toml",
+        r#"[workspace]
+members = [
+  "crates/*", # comment
+]
+exclude = ["crates/excluded"]
+
+[workspace.dependencies]
+core = { path = "crates/core" }
+"#,
+      )
+      .unwrap();
+    environment
+      .write_file(
+        "/crates/cli/Cargo.toml",
+        r#"[package]
+name = "cli"
+
+[dependencies]
+core.workspace = true
+anyhow = "1"
+"#,
+      )
+      .unwrap();
+    environment
+      .write_file(
+        "/crates/core/Cargo.toml",
+        r#"[package]
+name = "core"
+
+[dev-dependencies.helpers]
+path = "../helpers"
+"#,
+      )
+      .unwrap();
+    environment.write_file("/crates/helpers/Cargo.toml", "[package]\nname = \"helpers\"\n").unwrap();
+    environment
+      .write_file("/crates/excluded/Cargo.toml", "[package]\nname = \"excluded\"\n")
+      .unwrap();
+
+    let graph = WorkspaceGraph::discover(&CanonicalizedPathBuf::new_for_testing("/"), &environment).unwrap();
+    let names = graph.packages().iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["cli", "core", "helpers"]);
+
+    let affected = graph.get_affected_packages(&[PathBuf::from("/crates/helpers/src/lib.rs")]);
+    let names = affected.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["cli", "core", "helpers"]);
+
+    let affected = graph.get_affected_packages(&[PathBuf::from("/crates/cli/src/main.rs"), PathBuf::from("/README.md")]);
+    let names = affected.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["cli"]);
+  }
+
+  #[test]
+  fn should_expand_globstar_member_patterns() {
+    let environment = TestEnvironment::new();
+    environment.write_file("/package.json", r#"{ "workspaces": ["packages/**"] }"#).unwrap();
+    environment.write_file("/packages/a/package.json", r#"{ "name": "a" }"#).unwrap();
+    environment
+      .write_file("/packages/group/b/package.json", r#"{ "name": "b", "dependencies": { "a": "1.0.0" } }"#)
+      .unwrap();
+    environment
+      .write_file("/packages/a/node_modules/dep/package.json", r#"{ "name": "dep" }"#)
+      .unwrap();
+
+    let graph = WorkspaceGraph::discover(&CanonicalizedPathBuf::new_for_testing("/"), &environment).unwrap();
+    let names = graph.packages().iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["a", "b"]);
+
+    let affected = graph.get_affected_packages(&[PathBuf::from("/packages/a/index.ts")]);
+    let names = affected.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["a", "b"]);
+  }
+
+  #[test]
+  fn should_match_wildcards() {
+    assert!(matches_wildcard("*", "a"));
+    assert!(matches_wildcard("pkg-*", "pkg-a"));
+    assert!(!matches_wildcard("pkg-*", "other"));
+    assert!(matches_wildcard("*-plugin", "ts-plugin"));
+    assert!(matches_wildcard("a*b*c", "aXbYc"));
+    assert!(!matches_wildcard("a*b*c", "aXbY"));
+  }
+}
@@ -58,6 +58,7 @@ impl AsyncPluginHandler for TestProcessPluginHandler {
       help_url: "https://dprint.dev/plugins/test-process".to_string(),
       config_schema_url: "".to_string(),
       update_url: None,
+      experiments: vec![],
     }
   }
 